@@ -35,6 +35,7 @@ function init(adminSigners: PublicKey[], minSignatures: number): Promise<void> {
     allowPnlWithdrawal: true,
     allowCollateralWithdrawal: true,
     allowSizeChange: true,
+    allowCpi: false,
   };
 
   return client.init(adminSigners, perpetualsConfig);
@@ -83,6 +84,13 @@ async function addCustody(
   const oracleConfig: OracleParams = {
     maxPriceError: new BN(10_000),
     maxPriceAgeSec: 60,
+    emaHalfLifeSec: 0,
+    maxPriceAgeCloseSec: 0,
+    maxPriceAgeLiquidateSec: 0,
+    maxPriceAgeLiquiditySec: 0,
+    heartbeatMult: 0,
+    closeGraceMult: 0,
+    closeGraceSpread: new BN(0),
     oracleType: { [oracleType]: {} },
     oracleAccount: tokenOracle,
     oracleAuthority: PublicKey.default, // By default, permissionless oracle price update is not allowed.
@@ -101,6 +109,14 @@ async function addCustody(
     maxUtilization: new BN(10_000),
     maxPositionLockedUsd: new BN(1_000_000_000),
     maxTotalLockedUsd: new BN(1_000_000_000),
+    minHoldingPeriod: new BN(0),
+    rejectEarlyClose: false,
+    liquidationPriceMode: { aggregate: {} },
+    lpFeeDecayPeriod: new BN(0),
+    riskWarningHealth: new BN(15_000),
+    riskDangerHealth: new BN(11_000),
+    maxPower: 0, // all powers up to 5
+    convexityVol: new BN(0),
   };
   const permissions: Permissions = {
     allowSwap: true,
@@ -128,6 +144,13 @@ async function addCustody(
     protocolShare: new BN(10),
     feeMax: new BN(250),
     feeOptimal: new BN(10),
+    earlyClose: new BN(0),
+    rollPosition: new BN(100),
+    earlyRemoveLiquidity: new BN(0),
+    swapProtocolShare: new BN(0),
+    liquidityProtocolShare: new BN(0),
+    positionProtocolShare: new BN(0),
+    liquidationProtocolShare: new BN(0),
   };
   const borrowRate: BorrowRateParams = {
     baseRate: new BN(0),
@@ -14,7 +14,11 @@ import {
     SYSVAR_RENT_PUBKEY,
     AccountMeta,
   } from "@solana/web3.js";
-  import { getAssociatedTokenAddress, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+  import {
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    getAssociatedTokenAddress,
+    TOKEN_PROGRAM_ID,
+  } from "@solana/spl-token";
   import { sha256 } from "js-sha256";
   import bs58 from "bs58";
   import { readFileSync } from "fs";
@@ -143,6 +147,20 @@ import {
         .publicKey;
     };
   
+    getLpLedgerKey = (poolName: string, owner: PublicKey): PublicKey => {
+      return this.findProgramAddress("lp_ledger", [
+        this.getPoolKey(poolName),
+        owner,
+      ]).publicKey;
+    };
+  
+    getOwnerPositionsKey = (poolName: string, owner: PublicKey): PublicKey => {
+      return this.findProgramAddress("owner_positions", [
+        this.getPoolKey(poolName),
+        owner,
+      ]).publicKey;
+    };
+  
     getCustodyKey = (poolName: string, tokenMint: PublicKey): PublicKey => {
       return this.findProgramAddress("custody", [
         this.getPoolKey(poolName),
@@ -520,6 +538,7 @@ import {
             poolName,
             tokenMint
           ),
+          rentReceiver: this.admin.publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
         } as any)
@@ -583,12 +602,21 @@ import {
       poolName: string,
       tokenMint: PublicKey,
       amountIn: BN,
-      minLpAmountOut: BN
+      minLpAmountOut: BN,
+      maxLpPriceUsd: BN = new BN(0),
+      minUsdAmountOut: BN = new BN(0),
+      maxFeeBps: BN = new BN(0)
     ): Promise<void> => {
       const lpTokenMint = this.getPoolLpTokenKey(poolName);
   
       await this.program.methods
-        .addLiquidity({ amountIn, minLpAmountOut } as any)
+        .addLiquidity({
+          amountIn,
+          minLpAmountOut,
+          maxLpPriceUsd,
+          minUsdAmountOut,
+          maxFeeBps,
+        } as any)
         .accounts({
           owner: this.provider.wallet.publicKey,
           fundingAccount: await getAssociatedTokenAddress(
@@ -612,7 +640,12 @@ import {
             tokenMint
           ),
           lpTokenMint,
+          systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
+          lpLedger: this.getLpLedgerKey(
+            poolName,
+            this.provider.wallet.publicKey
+          ),
         } as any)
         .remainingAccounts(await this.getCustodyMetas(poolName))
         .rpc()
@@ -631,6 +664,10 @@ import {
       receivingAccount: PublicKey,
       rewardsReceivingAccount: PublicKey
     ): Promise<void> => {
+      // the position counter is optional, only pass it if the owner has one
+      const ownerPositions = this.getOwnerPositionsKey(poolName, wallet);
+      const ownerPositionsInfo =
+        await this.provider.connection.getAccountInfo(ownerPositions);
       await this.program.methods
         .liquidate({})
         .accounts({
@@ -656,6 +693,12 @@ import {
             collateralMint
           ),
           tokenProgram: TOKEN_PROGRAM_ID,
+          ownerPositions: ownerPositionsInfo ? ownerPositions : null,
+          // creates the owner receiving ATA if it was closed
+          owner: wallet,
+          collateralMint,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         } as any)
         .rpc()
         .catch((err) => {
@@ -672,7 +715,8 @@ import {
       price: BN,
       collateral: BN,
       size: BN,
-      power: number = 1
+      power: number = 1,
+      sizeInUsd: boolean = false
     ): Promise<void> => {
       await this.program.methods
         .openPosition({
@@ -681,6 +725,7 @@ import {
           size,
           side: side === "long" ? { long: {} } : { short: {} },
           power,
+          sizeMode: sizeInUsd ? { usd: {} } : { tokens: {} },
         } as any)
         .accounts({
           owner: this.provider.wallet.publicKey,
@@ -713,6 +758,10 @@ import {
           ),
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
+          ownerPositions: this.getOwnerPositionsKey(
+            poolName,
+            this.provider.wallet.publicKey
+          ),
         } as any)
         .rpc()
         .catch((err) => {
@@ -806,13 +855,17 @@ import {
       collateralMint: PublicKey,
       collateral: BN,
       size: BN,
-      side: PositionSide
+      side: PositionSide,
+      sizeInUsd: boolean = false,
+      power: number = 1
     ): Promise<NewPositionPricesAndFee> => {
       return this.program.methods
         .getEntryPriceAndFee({
           collateral,
           size,
           side: side === "long" ? { long: {} } : { short: {} },
+          sizeMode: sizeInUsd ? { usd: {} } : { tokens: {} },
+          power,
         } as any)
         .accounts({
           perpetuals: this.perpetuals.publicKey,
@@ -0,0 +1,292 @@
+//! Unit conversion routines shared by the program and client SDKs.
+//!
+//! Every token <-> USD conversion, price rescale and BPS application done by the
+//! instructions goes through these functions. They are pure and public, so off-chain
+//! clients can link the crate with the `no-entrypoint` feature and quote exactly the
+//! amounts the program will compute, including rounding direction.
+
+use {
+    crate::{math, state::perpetuals::Perpetuals},
+    anchor_lang::prelude::*,
+};
+
+/// Converts a token amount to USD (Perpetuals::USD_DECIMALS decimals), rounding down
+///
+/// # Arguments
+/// * `token_amount` - Amount in native token units
+/// * `token_decimals` - Number of decimals of the token
+/// * `price` - Price mantissa
+/// * `price_exponent` - Price exponent
+///
+/// # Returns
+/// USD value of the token amount
+pub fn token_to_usd(
+    token_amount: u64,
+    token_decimals: u8,
+    price: u64,
+    price_exponent: i32,
+) -> Result<u64> {
+    if token_amount == 0 || price == 0 {
+        return Ok(0);
+    }
+    math::checked_decimal_mul(
+        token_amount,
+        -(token_decimals as i32),
+        price,
+        price_exponent,
+        -(Perpetuals::USD_DECIMALS as i32),
+    )
+}
+
+/// Converts a USD amount (Perpetuals::USD_DECIMALS decimals) to token units, rounding down
+///
+/// # Arguments
+/// * `amount_usd` - USD amount
+/// * `token_decimals` - Number of decimals of the token
+/// * `price` - Price mantissa
+/// * `price_exponent` - Price exponent
+///
+/// # Returns
+/// Amount in native token units, 0 if the price is 0
+pub fn usd_to_token(
+    amount_usd: u64,
+    token_decimals: u8,
+    price: u64,
+    price_exponent: i32,
+) -> Result<u64> {
+    if amount_usd == 0 || price == 0 {
+        return Ok(0);
+    }
+    math::checked_decimal_div(
+        amount_usd,
+        -(Perpetuals::USD_DECIMALS as i32),
+        price,
+        price_exponent,
+        -(token_decimals as i32),
+    )
+}
+
+/// Converts a token amount to USD (Perpetuals::USD_DECIMALS decimals), rounding up
+///
+/// Used for USD debited from a position for the tokens paid out.
+pub fn token_to_usd_ceil(
+    token_amount: u64,
+    token_decimals: u8,
+    price: u64,
+    price_exponent: i32,
+) -> Result<u64> {
+    if token_amount == 0 || price == 0 {
+        return Ok(0);
+    }
+    math::checked_decimal_ceil_mul(
+        token_amount,
+        -(token_decimals as i32),
+        price,
+        price_exponent,
+        -(Perpetuals::USD_DECIMALS as i32),
+    )
+}
+
+/// Converts a USD amount (Perpetuals::USD_DECIMALS decimals) to token units, rounding up
+///
+/// Returns the smallest token amount worth at least `amount_usd`, used for tokens
+/// collected from users for a USD amount.
+pub fn usd_to_token_ceil(
+    amount_usd: u64,
+    token_decimals: u8,
+    price: u64,
+    price_exponent: i32,
+) -> Result<u64> {
+    let token_amount = usd_to_token(amount_usd, token_decimals, price, price_exponent)?;
+    if price == 0
+        || token_to_usd(token_amount, token_decimals, price, price_exponent)? >= amount_usd
+    {
+        Ok(token_amount)
+    } else {
+        math::checked_add(token_amount, 1)
+    }
+}
+
+/// Rescales a price mantissa from one exponent to another, truncating extra digits
+///
+/// # Arguments
+/// * `price` - Price mantissa
+/// * `exponent` - Current exponent
+/// * `target_exponent` - Desired exponent
+///
+/// # Returns
+/// Price mantissa expressed with `target_exponent`
+pub fn scale_price(price: u64, exponent: i32, target_exponent: i32) -> Result<u64> {
+    math::scale_to_exponent(price, exponent, target_exponent)
+}
+
+/// Applies a BPS rate to an amount, rounding down (amount * bps / BPS_POWER)
+///
+/// Used for shares paid out by the program (e.g. LP share of fees).
+pub fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    if amount == 0 || bps == 0 {
+        return Ok(0);
+    }
+    math::checked_as_u64(math::checked_div(
+        math::checked_mul(amount as u128, bps as u128)?,
+        Perpetuals::BPS_POWER,
+    )?)
+}
+
+/// Applies a BPS rate to an amount, rounding up (ceil(amount * bps / BPS_POWER))
+///
+/// Used for fees charged by the program, so rounding always favors the pool.
+pub fn apply_bps_ceil(amount: u64, bps: u64) -> Result<u64> {
+    if amount == 0 || bps == 0 {
+        return Ok(0);
+    }
+    math::checked_as_u64(math::checked_ceil_div(
+        math::checked_mul(amount as u128, bps as u128)?,
+        Perpetuals::BPS_POWER,
+    )?)
+}
+
+/// Returns the part of an amount left after removing a BPS share, rounding the
+/// removed share down (amount * (BPS_POWER - bps) / BPS_POWER)
+pub fn remaining_after_bps(amount: u64, bps: u64) -> Result<u64> {
+    apply_bps(
+        amount,
+        math::checked_sub(Perpetuals::BPS_POWER as u64, bps)?,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::error::PerpetualsError};
+
+    #[test]
+    fn test_token_to_usd() {
+        // 1 SOL (9 decimals) at $150.00 (exponent -2)
+        assert_eq!(
+            token_to_usd(1_000_000_000, 9, 15_000, -2).unwrap(),
+            150_000_000
+        );
+        // 2.5 USDC (6 decimals) at $1 with 8 decimal price
+        assert_eq!(
+            token_to_usd(2_500_000, 6, 100_000_000, -8).unwrap(),
+            2_500_000
+        );
+        // positive price exponent
+        assert_eq!(token_to_usd(1_000_000, 6, 3, 2).unwrap(), 300_000_000);
+        // rounds down below 1 micro-USD
+        assert_eq!(token_to_usd(1, 9, 15_000, -2).unwrap(), 0);
+        assert_eq!(token_to_usd(0, 9, 15_000, -2).unwrap(), 0);
+        assert_eq!(token_to_usd(1_000_000_000, 9, 0, -2).unwrap(), 0);
+        assert_eq!(
+            token_to_usd(u64::MAX, 0, u64::MAX, 0).unwrap_err(),
+            PerpetualsError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_usd_to_token() {
+        assert_eq!(
+            usd_to_token(150_000_000, 9, 15_000, -2).unwrap(),
+            1_000_000_000
+        );
+        assert_eq!(
+            usd_to_token(2_500_000, 6, 100_000_000, -8).unwrap(),
+            2_500_000
+        );
+        assert_eq!(usd_to_token(300_000_000, 6, 3, 2).unwrap(), 1_000_000);
+        // $1 at $3 rounds down
+        assert_eq!(usd_to_token(1_000_000, 6, 300, -2).unwrap(), 333_333);
+        assert_eq!(usd_to_token(0, 9, 15_000, -2).unwrap(), 0);
+        assert_eq!(usd_to_token(150_000_000, 9, 0, -2).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_token_usd_round_trip() {
+        for decimals in [0u8, 6, 8, 9] {
+            for (price, exponent) in [(1u64, 0i32), (15_000, -2), (123_456_789, -8), (7, 3)] {
+                let amount = 10u64.pow(decimals as u32) * 42;
+                let usd = token_to_usd(amount, decimals, price, exponent).unwrap();
+                let back = usd_to_token(usd, decimals, price, exponent).unwrap();
+                assert!(back <= amount);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ceil_conversions() {
+        // 1 lamport at $150.00 is worth 0.15 micro-USD
+        assert_eq!(token_to_usd_ceil(1, 9, 15_000, -2).unwrap(), 1);
+        assert_eq!(
+            token_to_usd_ceil(1_000_000_000, 9, 15_000, -2).unwrap(),
+            150_000_000
+        );
+        // $1 at $3 rounds up
+        assert_eq!(usd_to_token_ceil(1_000_000, 6, 300, -2).unwrap(), 333_334);
+        assert_eq!(usd_to_token_ceil(300_000_000, 6, 3, 2).unwrap(), 1_000_000);
+        assert_eq!(usd_to_token_ceil(0, 6, 300, -2).unwrap(), 0);
+        assert_eq!(usd_to_token_ceil(1_000_000, 6, 0, -2).unwrap(), 0);
+
+        for (price, exponent) in [(15_000u64, -2i32), (123_456_789, -8), (7, 3)] {
+            for usd in [1u64, 999_999, 42_000_001] {
+                let tokens = usd_to_token_ceil(usd, 9, price, exponent).unwrap();
+                assert!(token_to_usd(tokens, 9, price, exponent).unwrap() >= usd);
+                assert!(token_to_usd_ceil(tokens, 9, price, exponent).unwrap() >= usd);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scale_price() {
+        assert_eq!(scale_price(15_000, -2, -2).unwrap(), 15_000);
+        assert_eq!(scale_price(15_000, -2, -6).unwrap(), 150_000_000);
+        assert_eq!(scale_price(150_000_000, -6, -2).unwrap(), 15_000);
+        // truncates digits that don't fit the target exponent
+        assert_eq!(scale_price(15_999, -2, 0).unwrap(), 159);
+        assert_eq!(scale_price(3, 2, 0).unwrap(), 300);
+        assert_eq!(
+            scale_price(u64::MAX, 0, -1).unwrap_err(),
+            PerpetualsError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_apply_bps() {
+        assert_eq!(apply_bps(1_000_000, 10_000).unwrap(), 1_000_000);
+        assert_eq!(apply_bps(1_000_000, 2_500).unwrap(), 250_000);
+        assert_eq!(apply_bps(999, 1).unwrap(), 0);
+        assert_eq!(apply_bps(0, 100).unwrap(), 0);
+        assert_eq!(apply_bps(100, 0).unwrap(), 0);
+        assert_eq!(apply_bps(u64::MAX, 10_000).unwrap(), u64::MAX);
+        assert_eq!(
+            apply_bps(u64::MAX, 20_000).unwrap_err(),
+            PerpetualsError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_apply_bps_ceil() {
+        assert_eq!(apply_bps_ceil(1_000_000, 2_500).unwrap(), 250_000);
+        assert_eq!(apply_bps_ceil(999, 1).unwrap(), 1);
+        assert_eq!(apply_bps_ceil(10_001, 1).unwrap(), 2);
+        assert_eq!(apply_bps_ceil(0, 100).unwrap(), 0);
+        assert_eq!(apply_bps_ceil(100, 0).unwrap(), 0);
+        for amount in [1u64, 7, 9_999, 10_000, 123_456_789] {
+            for bps in [1u64, 30, 5_000, 10_000] {
+                let floor = apply_bps(amount, bps).unwrap();
+                let ceil = apply_bps_ceil(amount, bps).unwrap();
+                assert!(ceil == floor || ceil == floor + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_remaining_after_bps() {
+        assert_eq!(remaining_after_bps(1_000_000, 2_500).unwrap(), 750_000);
+        assert_eq!(remaining_after_bps(1_000_000, 0).unwrap(), 1_000_000);
+        assert_eq!(remaining_after_bps(1_000_000, 10_000).unwrap(), 0);
+        assert_eq!(
+            remaining_after_bps(1_000_000, 10_001).unwrap_err(),
+            PerpetualsError::MathOverflow.into()
+        );
+    }
+}
@@ -46,7 +46,7 @@ pub enum PerpetualsError {
     InsufficientAmountReturned,
     #[msg("Price slippage limit exceeded")]
     MaxPriceSlippage,
-    #[msg("Position leverage limit exceeded")]
+    #[msg("Position leverage exceeds max leverage")]
     MaxLeverage,
     #[msg("Custody amount limit exceeded")]
     CustodyAmountLimit,
@@ -68,4 +68,110 @@ pub enum PerpetualsError {
     PermissionlessOracleSignerMismatch,
     #[msg("Signed message does not match instruction params")]
     PermissionlessOracleMessageMismatch,
-}
\ No newline at end of file
+    #[msg("Custody market has expired")]
+    CustodyExpired,
+    #[msg("Custody market has not expired or settlement price is not set")]
+    CustodyNotSettled,
+    #[msg("Invalid market maker config")]
+    InvalidMarketMakerConfig,
+    #[msg("Position leverage is below min initial leverage")]
+    MinInitialLeverage,
+    #[msg("Position leverage exceeds max initial leverage")]
+    MaxInitialLeverage,
+    #[msg("Owner is not on the pool liquidity provider allowlist")]
+    LpNotAllowlisted,
+    #[msg("Liquidity provider allowlist is full")]
+    LpAllowlistFull,
+    #[msg("Position is within the minimum holding period")]
+    MinHoldingPeriod,
+    #[msg("Instruction cannot be invoked through CPI")]
+    CpiNotAllowed,
+    #[msg("Liquidity provider deposit ledger is required")]
+    LpLedgerRequired,
+    #[msg("Invalid custody exchange rate account or value")]
+    InvalidExchangeRate,
+    #[msg("Custody exchange rate is stale")]
+    StaleExchangeRate,
+    #[msg("Owner has reached the pool limit of open positions")]
+    MaxPositionsPerOwner,
+    #[msg("Owner position counter is required")]
+    OwnerPositionsRequired,
+    #[msg("Fee exceeds the maximum accepted by the caller")]
+    MaxFeeExceeded,
+    #[msg("Pending claim is not at the head of the queue")]
+    ClaimNotAtQueueHead,
+    #[msg("Custody is in oracle safe mode")]
+    OracleSafeMode,
+    #[msg("Order doesn't match the commitment")]
+    CommitmentMismatch,
+    #[msg("Order can't be revealed outside of the reveal window")]
+    RevealWindowClosed,
+    #[msg("Token account mint doesn't match the collateral custody mint")]
+    InvalidCollateralMint,
+    #[msg("Performance fee epoch hasn't elapsed")]
+    PerformanceEpochNotElapsed,
+    #[msg("Operation is not allowed in the market lifecycle stage")]
+    MarketLifecycleRestricted,
+    #[msg("Invalid market lifecycle transition")]
+    InvalidLifecycleTransition,
+    #[msg("Owner has reached the pool limit of trades per slot")]
+    MaxTradesPerSlot,
+    #[msg("Trader activity counter is required")]
+    TraderActivityRequired,
+    #[msg("Invalid LP index config")]
+    InvalidLpIndexConfig,
+    #[msg("Invalid LP index state")]
+    InvalidLpIndexState,
+    #[msg("Deposit doesn't match the LP index weights")]
+    LpIndexWeightMismatch,
+    #[msg("Invalid position hook")]
+    InvalidPositionHook,
+    #[msg("Position hook is required")]
+    PositionHookRequired,
+    #[msg("Invalid risk oracle config")]
+    InvalidRiskOracleConfig,
+    #[msg("Invalid risk oracle account")]
+    InvalidRiskOracleAccount,
+    #[msg("Risk oracle reports an incident, risk-increasing operations are halted")]
+    RiskOracleIncident,
+    #[msg("Invalid backstop config")]
+    InvalidBackstopConfig,
+    #[msg("Invalid backstop state")]
+    InvalidBackstopState,
+    #[msg("Invalid listing config")]
+    InvalidListingConfig,
+    #[msg("Listing parameters are outside the template bounds")]
+    InvalidListingParams,
+    #[msg("Listing timelock hasn't passed yet")]
+    ListingTimelockActive,
+    #[msg("Invalid listing state")]
+    InvalidListingState,
+    #[msg("Invalid staged params")]
+    InvalidStagedParams,
+    #[msg("Owner is on the custody compliance freeze list")]
+    OwnerFrozen,
+    #[msg("Compliance freeze list is full")]
+    ComplianceFreezeFull,
+    #[msg("Compliance freeze list account is required")]
+    ComplianceFreezeRequired,
+    #[msg("Mint would exceed the pool LP supply cap")]
+    LpSupplyCapExceeded,
+    #[msg("Receiving account isn't the payout account of the position")]
+    InvalidPayoutAccount,
+    #[msg("Collateral withdrawal exceeds the limit of the window")]
+    CollateralWithdrawalLimitExceeded,
+    #[msg("Accounting invariant violated")]
+    InvariantViolation,
+    #[msg("Custody params are outside the program safety bounds")]
+    ParamOutOfBounds,
+    #[msg("Receiving account is missing or isn't a valid token account")]
+    InvalidReceivingAccount,
+    #[msg("Position power exceeds the market max power")]
+    MaxPowerExceeded,
+    #[msg("Invalid custody volatility account or value")]
+    InvalidVolatility,
+    #[msg("Custody volatility source is stale")]
+    StaleVolatility,
+    #[msg("Oracle price wasn't published within the settlement window")]
+    SettlementPriceOutsideWindow,
+}
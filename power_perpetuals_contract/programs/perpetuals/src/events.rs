@@ -0,0 +1,388 @@
+//! Program events
+//!
+//! Every event carries the pool it belongs to and a per-pool sequence number, so
+//! indexers can order events across slots and detect missed ones.
+
+use {
+    crate::state::{
+        custody::{LiquidationPriceMode, MarketLifecycle},
+        position::{RiskTier, Side},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Emitted on every liquidation attempt, for monitoring false-positive rates
+///
+/// All leverages are in BPS.
+#[event]
+pub struct LiquidationChecked {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Position being liquidated
+    pub position: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Leverage using the aggregate spot/EMA price selection
+    pub leverage: u64,
+    /// Leverage using spot prices only
+    pub spot_leverage: u64,
+    /// Leverage using EMA prices only
+    pub ema_leverage: u64,
+    /// Maintenance leverage adjusted for position power (liquidation threshold)
+    pub max_leverage: u64,
+    /// Liquidation price mode of the custody
+    pub liquidation_price_mode: LiquidationPriceMode,
+    /// Whether the position was liquidatable
+    pub liquidatable: bool,
+    /// Max leverage over the leverage deciding liquidation, in BPS
+    pub health_factor: u64,
+    /// Risk tier of the position
+    pub risk_tier: RiskTier,
+    /// Interest accrued since the position's cumulative interest snapshot in USD
+    pub interest_usd: u64,
+    /// Time of the check
+    pub time: i64,
+}
+
+/// Emitted when tokens are donated to a pool
+///
+/// Amounts are in custody token decimals.
+#[event]
+pub struct Donated {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody receiving the donation
+    pub custody: Pubkey,
+    /// Donor
+    pub donor: Pubkey,
+    /// Amount donated
+    pub amount: u64,
+    /// Part of the donation that covered bad debt (the rest is credited to owned assets)
+    pub bad_debt_covered: u64,
+}
+
+/// Emitted when custody bookkeeping is reconciled with its token account balance
+///
+/// All amounts are in custody token decimals.
+#[event]
+pub struct CustodyReconciled {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody being reconciled
+    pub custody: Pubkey,
+    /// Token account balance
+    pub balance: u64,
+    /// Sum of owned, collateral and protocol fee assets before reconciliation
+    pub book_amount: u64,
+    /// Balance in excess of bookkeeping, swept into owned or protocol fee assets
+    pub surplus: u64,
+    /// Bookkeeping in excess of balance, recorded as bad debt
+    pub deficit: u64,
+    /// Whether the surplus was credited to owned assets (otherwise protocol fees)
+    pub surplus_to_owned: bool,
+}
+
+/// Emitted when a withdrawal is queued for lack of free liquidity
+///
+/// Amounts are in custody token decimals.
+#[event]
+pub struct ClaimQueued {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody the tokens are owed from
+    pub custody: Pubkey,
+    /// Pending claim account
+    pub pending_claim: Pubkey,
+    /// Owner of the claim
+    pub owner: Pubkey,
+    /// Position in the custody claim queue
+    pub claim_id: u64,
+    /// Tokens owed to the owner
+    pub amount: u64,
+    /// Tokens of all queued claims of the custody, including this one
+    pub pending_amount: u64,
+    /// Time the claim was queued
+    pub time: i64,
+}
+
+/// Emitted when a queued claim is paid out
+///
+/// Amounts are in custody token decimals.
+#[event]
+pub struct ClaimExecuted {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody the tokens were owed from
+    pub custody: Pubkey,
+    /// Pending claim account (closed)
+    pub pending_claim: Pubkey,
+    /// Owner of the claim
+    pub owner: Pubkey,
+    /// Position in the custody claim queue
+    pub claim_id: u64,
+    /// Tokens paid to the owner
+    pub amount: u64,
+    /// Time the claim was executed
+    pub time: i64,
+}
+
+/// Emitted when a position is closed by its owner
+#[event]
+pub struct PositionClosed {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Closed position account
+    pub position: Pubkey,
+    /// Owner of the position
+    pub owner: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Exit price, scaled to PRICE_DECIMALS
+    pub exit_price: u64,
+    /// Position size in USD
+    pub size_usd: u64,
+    /// Net profit in USD
+    pub profit_usd: u64,
+    /// Net loss in USD
+    pub loss_usd: u64,
+    /// Fees charged in USD
+    pub fee_usd: u64,
+    /// Interest accrued since the position's cumulative interest snapshot in USD
+    pub interest_usd: u64,
+    /// Collateral tokens paid out (or queued) to the owner
+    pub amount_out: u64,
+    /// Whether the payout was queued as a pending claim
+    pub queued: bool,
+    /// Whether the close was priced with a stale price within the close grace
+    pub close_grace: bool,
+    /// Whether the profit was capped at the funds locked for the position
+    pub profit_capped: bool,
+    /// Time of the close
+    pub time: i64,
+}
+
+/// Emitted when a custody enters or leaves oracle safe mode
+#[event]
+pub struct OracleSafeModeUpdated {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody the flag applies to
+    pub custody: Pubkey,
+    /// New safe mode flag
+    pub safe_mode: bool,
+    /// Publish time of the last oracle update
+    pub last_update: i64,
+    /// Oracle update age that triggers safe mode
+    pub heartbeat_limit_sec: u64,
+    /// Time the flag was updated
+    pub time: i64,
+}
+
+/// Emitted when an emergency settlement of a position is scheduled
+#[event]
+pub struct ForceSettlementScheduled {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Position to settle
+    pub position: Pubkey,
+    /// Owner of the position
+    pub owner: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Admin supplied settlement price, scaled to PRICE_DECIMALS
+    pub price: u64,
+    /// Earliest time the settlement can be executed
+    pub execute_time: i64,
+    /// Time the settlement was scheduled
+    pub time: i64,
+}
+
+/// Emitted when a position is settled at an admin supplied price
+#[event]
+pub struct PositionForceSettled {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Settled position account
+    pub position: Pubkey,
+    /// Owner of the position
+    pub owner: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Admin supplied settlement price, scaled to PRICE_DECIMALS
+    pub price: u64,
+    /// First reference price, scaled to PRICE_DECIMALS
+    pub reference_price_a: u64,
+    /// Second reference price, scaled to PRICE_DECIMALS
+    pub reference_price_b: u64,
+    /// Position size in USD
+    pub size_usd: u64,
+    /// Net profit in USD
+    pub profit_usd: u64,
+    /// Net loss in USD
+    pub loss_usd: u64,
+    /// Fees charged in USD
+    pub fee_usd: u64,
+    /// Collateral tokens paid out to the owner
+    pub amount_out: u64,
+    /// Time of the settlement
+    pub time: i64,
+}
+
+/// Emitted when a performance fee epoch is rolled over
+#[event]
+pub struct PerformanceEpochRolled {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// LP token price before the fee, scaled to USD_DECIMALS
+    pub lp_price: u64,
+    /// High-water mark after the rollover, scaled to USD_DECIMALS
+    pub lp_price_hwm: u64,
+    /// Performance fee in USD
+    pub fee_usd: u64,
+    /// LP tokens minted to the performance fee account
+    pub fee_lp_amount: u64,
+    /// Time of the rollover
+    pub time: i64,
+}
+
+/// Emitted when the liquidation reward auction of a position starts
+#[event]
+pub struct LiquidationAuctionStarted {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Liquidatable position account
+    pub position: Pubkey,
+    /// Owner of the position
+    pub owner: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Slot the auction started at
+    pub slot: u64,
+    /// Slot the full liquidation reward is reached at
+    pub end_slot: u64,
+    /// Time the auction started
+    pub time: i64,
+}
+
+/// Emitted when admins set a custom oracle price through the multisig path
+#[event]
+pub struct AdminOraclePriceSet {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody the price is set for
+    pub custody: Pubkey,
+    /// Custom oracle account
+    pub oracle_account: Pubkey,
+    /// Price before the update
+    pub previous_price: u64,
+    /// EMA price before the update
+    pub previous_ema: u64,
+    /// New price
+    pub price: u64,
+    /// New EMA price
+    pub ema: u64,
+    /// Price exponent
+    pub expo: i32,
+    /// Deviation of the new price from the previous price in BPS
+    pub deviation: u64,
+    /// Publish time of the new price
+    pub publish_time: i64,
+    /// Time of the update
+    pub time: i64,
+}
+
+/// Emitted when admins move a custody market to another lifecycle stage
+#[event]
+pub struct MarketLifecycleChanged {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody of the market
+    pub custody: Pubkey,
+    /// Lifecycle stage before the change
+    pub previous_lifecycle: MarketLifecycle,
+    /// New lifecycle stage
+    pub lifecycle: MarketLifecycle,
+    /// Time of the change
+    pub time: i64,
+}
+
+/// Emitted when pool liquidity is locked for or released from a position payoff
+#[event]
+pub struct LockChanged {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Custody holding the locked funds
+    pub custody: Pubkey,
+    /// Position the funds are locked for
+    pub position: Pubkey,
+    /// True if funds were locked, false if released
+    pub is_lock: bool,
+    /// Amount locked or released in custody tokens
+    pub amount: u64,
+    /// Locked funds of the custody after the change
+    pub locked: u64,
+    /// Owned funds of the custody after the change
+    pub owned: u64,
+    /// Time of the change
+    pub time: i64,
+}
+
+/// Emitted when a position is opened or closed by its owner, for slippage and spread
+/// capture analytics
+///
+/// Prices are scaled to PRICE_DECIMALS.
+#[event]
+pub struct TradeExecuted {
+    /// Pool the event belongs to
+    pub pool: Pubkey,
+    /// Pool event sequence number, increases by one with every pool event
+    pub seq: u64,
+    /// Position opened or closed
+    pub position: Pubkey,
+    /// Custody of the position token
+    pub custody: Pubkey,
+    /// Position side
+    pub side: Side,
+    /// True for an open, false for a close
+    pub is_open: bool,
+    /// Worst price accepted by the owner (slippage bound)
+    pub bound_price: u64,
+    /// Oracle spot price at execution
+    pub spot_price: u64,
+    /// Oracle EMA price at execution
+    pub ema_price: u64,
+    /// Spread applied to the oracle price in BPS
+    pub spread_bps: u64,
+    /// Execution price
+    pub execution_price: u64,
+    /// Time of the trade
+    pub time: i64,
+}
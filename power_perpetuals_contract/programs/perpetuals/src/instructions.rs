@@ -1,14 +1,54 @@
 // admin instructions
 pub mod add_custody;
+pub mod add_custody_from_staged;
+pub mod add_lp_index;
 pub mod add_pool;
+pub mod export_pool_state;
+pub mod finalize_pool_migration;
+pub mod force_settle_position;
+pub mod import_pool_positions;
+pub mod import_pool_state;
 pub mod init;
+pub mod migrate_custody_mint;
+pub mod reconcile_custody;
 pub mod remove_custody;
+pub mod remove_market_maker;
 pub mod remove_pool;
+pub mod schedule_custody_migration;
+pub mod schedule_force_settlement;
 pub mod set_admin_signers;
+pub mod set_backstop_tranche;
+pub mod set_collateral_withdrawal_limit;
+pub mod set_compliance_freeze;
 pub mod set_custody_config;
+pub mod set_custody_exchange_rate;
+pub mod set_custody_expiry;
+pub mod set_custody_lifecycle;
+pub mod set_custody_volatility;
+pub mod set_crank_config;
 pub mod set_custom_oracle_price;
+pub mod set_fee_custody;
+pub mod set_imbalance_fee;
+pub mod set_liquidation_auction;
+pub mod set_listing_config;
+pub mod set_lp_allowlist;
+pub mod set_lp_index_component;
+pub mod set_lp_supply_cap;
+pub mod set_market_maker;
+pub mod set_performance_fee;
 pub mod set_permissions;
+pub mod set_pool_cpi;
+pub mod set_position_hook;
+pub mod set_position_limit;
+pub mod set_risk_oracle;
+pub mod set_trade_rate_limit;
+pub mod stage_params;
+pub mod start_stats_epoch;
+pub mod sweep_sol;
 pub mod upgrade_custody;
+pub mod upgrade_perpetuals;
+pub mod upgrade_pool;
+pub mod veto_listing;
 pub mod withdraw_fees;
 pub mod withdraw_sol_fees;
 
@@ -16,37 +56,88 @@ pub mod withdraw_sol_fees;
 pub mod set_test_time;
 
 // public instructions
+pub mod activate_listing;
+pub mod add_backstop_liquidity;
 pub mod add_collateral;
 pub mod add_liquidity;
+pub mod assert_oracles_fresh;
+pub mod cancel_commit_open;
+pub mod change_power;
 pub mod close_position;
+pub mod commit_open;
+pub mod cross_pool_swap;
+pub mod donate;
+pub mod execute_pending_claim;
 pub mod get_add_liquidity_amount_and_fee;
 pub mod get_assets_under_management;
+pub mod get_close_position_quote;
+pub mod get_custody_stats;
 pub mod get_entry_price_and_fee;
 pub mod get_exit_price_and_fee;
+pub mod get_liquidation_preview;
 pub mod get_liquidation_price;
 pub mod get_liquidation_state;
+pub mod get_locked_breakdown;
+pub mod get_lp_supply_capacity;
 pub mod get_lp_token_price;
+pub mod get_max_payoff;
+pub mod get_oracle_health;
 pub mod get_oracle_price;
 pub mod get_pnl;
+pub mod get_pool_apr;
+pub mod get_pool_custodies;
+pub mod get_protocol_config;
+pub mod get_position_interest;
+pub mod get_position_risk;
 pub mod get_remove_liquidity_amount_and_fee;
+pub mod get_required_collateral;
 pub mod get_swap_amount_and_fees;
+pub mod get_token_ratio_impact;
 pub mod liquidate;
+pub mod migrate_lp_tokens;
+pub mod mint_lp_index;
 pub mod open_position;
+pub mod propose_listing;
+pub mod redeem_lp_index;
+pub mod remove_backstop_liquidity;
 pub mod remove_collateral;
 pub mod remove_liquidity;
+pub mod remove_liquidity_and_swap;
+pub mod run_crank;
+pub mod reveal_open;
+pub mod roll_performance_epoch;
+pub mod roll_position;
 pub mod set_custom_oracle_price_permissionless;
+pub mod set_payout_account;
+pub mod set_custom_oracle_prices_permissionless_batch;
+pub mod set_settlement_price;
+pub mod settle_expired_position;
+pub mod start_liquidation_auction;
 pub mod swap;
+pub mod update_compliance_freeze;
+pub mod update_exchange_rate;
+pub mod update_lp_allowlist;
+pub mod update_oracle_safe_mode;
 pub mod update_pool_aum;
+pub mod update_volatility;
+pub mod upgrade_position;
+pub mod validate_custody_config;
 
 // bring everything in scope
 pub use {
-    add_collateral::*, add_custody::*, add_liquidity::*, add_pool::*, close_position::*,
-    get_add_liquidity_amount_and_fee::*, get_assets_under_management::*,
-    get_entry_price_and_fee::*, get_exit_price_and_fee::*, get_liquidation_price::*,
-    get_liquidation_state::*, get_lp_token_price::*, get_oracle_price::*, get_pnl::*,
-    get_remove_liquidity_amount_and_fee::*, get_swap_amount_and_fees::*, init::*, liquidate::*,
-    open_position::*, remove_collateral::*, remove_custody::*, remove_liquidity::*, remove_pool::*,
-    set_admin_signers::*, set_custody_config::*, set_custom_oracle_price::*,
-    set_custom_oracle_price_permissionless::*, set_permissions::*, set_test_time::*, swap::*,
-    update_pool_aum::*, upgrade_custody::*, withdraw_fees::*, withdraw_sol_fees::*,
+    activate_listing::*, add_backstop_liquidity::*, add_collateral::*, add_custody::*, add_custody_from_staged::*, add_lp_index::*, add_liquidity::*, add_pool::*, assert_oracles_fresh::*, cancel_commit_open::*, change_power::*,
+    close_position::*, commit_open::*, cross_pool_swap::*, donate::*, execute_pending_claim::*, export_pool_state::*, finalize_pool_migration::*, force_settle_position::*,
+    get_add_liquidity_amount_and_fee::*, get_assets_under_management::*, get_close_position_quote::*, get_custody_stats::*,
+    get_entry_price_and_fee::*, get_exit_price_and_fee::*, get_liquidation_preview::*, get_liquidation_price::*,
+    get_liquidation_state::*, get_locked_breakdown::*, get_lp_supply_capacity::*, get_lp_token_price::*, get_max_payoff::*, get_oracle_health::*, get_oracle_price::*, get_pnl::*, get_pool_apr::*, get_pool_custodies::*, get_protocol_config::*, get_position_interest::*, get_position_risk::*,
+    get_remove_liquidity_amount_and_fee::*, get_required_collateral::*, get_swap_amount_and_fees::*, get_token_ratio_impact::*, import_pool_positions::*, import_pool_state::*, init::*, liquidate::*,
+    migrate_custody_mint::*, migrate_lp_tokens::*, mint_lp_index::*,
+    open_position::*, propose_listing::*, reconcile_custody::*, redeem_lp_index::*, remove_backstop_liquidity::*, remove_collateral::*, remove_custody::*, remove_liquidity::*, remove_liquidity_and_swap::*, remove_market_maker::*, remove_pool::*,
+    reveal_open::*, roll_performance_epoch::*, roll_position::*, run_crank::*,
+    schedule_custody_migration::*, schedule_force_settlement::*,
+    set_admin_signers::*, set_backstop_tranche::*, set_collateral_withdrawal_limit::*, set_compliance_freeze::*, set_crank_config::*, set_custody_config::*, set_custody_exchange_rate::*, set_custody_expiry::*, set_custody_lifecycle::*, set_custody_volatility::*, set_custom_oracle_price::*, set_fee_custody::*, set_imbalance_fee::*,
+    set_liquidation_auction::*, set_listing_config::*, set_lp_allowlist::*, set_lp_index_component::*, set_lp_supply_cap::*, set_market_maker::*, set_performance_fee::*,
+    set_custom_oracle_price_permissionless::*, set_custom_oracle_prices_permissionless_batch::*, set_payout_account::*, set_permissions::*, set_pool_cpi::*, set_position_hook::*, set_position_limit::*, set_risk_oracle::*, set_settlement_price::*, set_trade_rate_limit::*,
+    set_test_time::*, settle_expired_position::*, stage_params::*, start_liquidation_auction::*, start_stats_epoch::*, swap::*, sweep_sol::*,
+    update_compliance_freeze::*, update_exchange_rate::*, update_lp_allowlist::*, update_oracle_safe_mode::*, update_pool_aum::*, update_volatility::*, upgrade_custody::*, upgrade_perpetuals::*, upgrade_pool::*, upgrade_position::*, validate_custody_config::*, veto_listing::*, withdraw_fees::*, withdraw_sol_fees::*,
 };
\ No newline at end of file
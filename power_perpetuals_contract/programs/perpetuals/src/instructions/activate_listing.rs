@@ -0,0 +1,214 @@
+//! ActivateListing instruction handler
+//!
+//! This instruction lets anyone activate a market listing once its veto timelock has
+//! passed. The listed token is added to the pool as a virtual custody configured from
+//! the pool listing template, and the bond is returned to the lister.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            listing::{ListingConfig, ListingProposal, ListingStatus},
+            perpetuals::Perpetuals,
+            pool::{Pool, TokenRatios},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for activating a listing
+#[derive(Accounts)]
+pub struct ActivateListing<'info> {
+    /// Caller (signer, pays rent of the new custody)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Lister's bond token account (receives the bond back)
+    #[account(
+        mut,
+        constraint = receiving_account.mint == bond_vault.mint,
+        constraint = receiving_account.owner == listing_proposal.lister
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, will be reallocated to accommodate new custody)
+    #[account(
+        mut,
+        realloc = Pool::LEN + (pool.custodies.len() + 1) * std::mem::size_of::<Pubkey>() +
+                              (pool.ratios.len() + 1) * std::mem::size_of::<TokenRatios>(),
+        realloc::payer = payer,
+        realloc::zero = false,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Listing config of the pool
+    #[account(
+        seeds = [b"listing_config",
+                 pool.key().as_ref()],
+        bump = listing_config.bump,
+        constraint = listing_config.version == ListingConfig::VERSION
+            @ PerpetualsError::InvalidListingConfig
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// Listing proposal being activated
+    #[account(
+        mut,
+        seeds = [b"listing_proposal",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump = listing_proposal.bump
+    )]
+    pub listing_proposal: Box<Account<'info, ListingProposal>>,
+
+    /// Token account escrowing the listing bond
+    #[account(
+        mut,
+        seeds = [b"listing_bond",
+                 listing_proposal.key().as_ref()],
+        bump = listing_proposal.bond_vault_bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+
+    /// New custody account (PDA derived from pool and token mint)
+    #[account(
+        init,
+        payer = payer,
+        space = Custody::LEN,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Token account of the custody, owned by transfer_authority PDA
+    #[account(
+        init,
+        payer = payer,
+        token::mint = custody_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Mint of the listed token
+    #[account()]
+    pub custody_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for activating a listing
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ActivateListingParams {}
+
+/// Activate a market listing after its veto timelock
+///
+/// The process:
+/// 1. Checks that the listing is pending and its timelock has passed
+/// 2. Re-validates the listing parameters against the current template
+/// 3. Returns the bond to the lister
+/// 4. Adds the custody to the pool with a zero target ratio
+/// 5. Initializes the custody as a virtual custody from the template
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Empty parameters struct
+///
+/// # Returns
+/// `Result<()>` - Success if the custody was added to the pool
+pub fn activate_listing(
+    ctx: Context<ActivateListing>,
+    _params: &ActivateListingParams,
+) -> Result<()> {
+    // Validate inputs
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    require!(
+        ctx.accounts.listing_proposal.status == ListingStatus::Pending,
+        PerpetualsError::InvalidListingState
+    );
+    require!(
+        ctx.accounts.listing_proposal.can_activate(curtime),
+        PerpetualsError::ListingTimelockActive
+    );
+    let listing_config = ctx.accounts.listing_config.as_ref();
+    let listing_params = ctx.accounts.listing_proposal.params;
+    require!(
+        listing_config.validate_params(&listing_params),
+        PerpetualsError::InvalidListingParams
+    );
+
+    // Return bond
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.bond_vault.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.listing_proposal.bond_amount,
+    )?;
+    ctx.accounts.listing_proposal.status = ListingStatus::Activated;
+
+    // Update pool data to include new custody
+    let pool = ctx.accounts.pool.as_mut();
+    pool.custodies.push(ctx.accounts.custody.key());
+    pool.ratios.push(TokenRatios::default());
+    if !pool.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    // Initialize custody from the template
+    let template = &listing_config.template;
+    let custody = ctx.accounts.custody.as_mut();
+    custody.pool = pool.key();
+    custody.mint = ctx.accounts.custody_token_mint.key();
+    custody.token_account = ctx.accounts.custody_token_account.key();
+    custody.decimals = ctx.accounts.custody_token_mint.decimals;
+    custody.is_stable = false;
+    custody.is_virtual = true;
+    custody.oracle = listing_config.get_oracle(&listing_params);
+    custody.pricing = listing_config.get_pricing(&listing_params);
+    custody.permissions = template.permissions;
+    custody.allow_cpi = template.allow_cpi;
+    custody.fees = template.fees;
+    custody.borrow_rate = template.borrow_rate;
+    custody.borrow_rate_state.current_rate = template.borrow_rate.base_rate;
+    custody.borrow_rate_state.last_update = curtime;
+    custody.stats_epoch.start_time = curtime;
+    custody.bump = ctx.bumps.custody;
+    custody.token_account_bump = ctx.bumps.custody_token_account;
+
+    require!(custody.validate_bounds(), PerpetualsError::ParamOutOfBounds);
+    if !custody.validate() {
+        return err!(PerpetualsError::InvalidCustodyConfig);
+    }
+    msg!("Listing activated: {}", custody.mint);
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+//! AddBackstopLiquidity instruction handler
+//!
+//! This instruction allows users to deposit tokens into the backstop tranche of a pool
+//! and receive backstop tokens in return. Backstop tokens are priced from the tranche
+//! value, which absorbs realized trader profits first and earns a boosted share of
+//! realized fees and trader losses.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            lp_allowlist::LpAllowlist,
+            oracle::OracleOperation,
+            perpetuals::Perpetuals,
+            pool::{Pool, RatioFlow},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for adding backstop liquidity to a pool
+#[derive(Accounts)]
+pub struct AddBackstopLiquidity<'info> {
+    /// Owner of the backstop tokens (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's token account from which tokens will be deposited
+    #[account(
+        mut,
+        constraint = funding_account.mint == custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// User's backstop token account where backstop tokens will be minted
+    #[account(
+        mut,
+        constraint = backstop_token_account.mint == backstop_token_mint.key(),
+        has_one = owner
+    )]
+    pub backstop_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token transfers
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, backstop tranche will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the token being deposited (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being deposited
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account where deposited tokens will be stored
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Backstop token mint of the pool (mutable, will mint new backstop tokens)
+    #[account(
+        mut,
+        seeds = [b"backstop_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.backstop.token_bump
+    )]
+    pub backstop_token_mint: Box<Account<'info, Mint>>,
+
+    token_program: Program<'info, Token>,
+
+    /// Allowlist of the pool, required if the pool is permissioned
+    #[account(
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Option<Box<Account<'info, LpAllowlist>>>,
+}
+
+/// Parameters for adding backstop liquidity to a pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddBackstopLiquidityParams {
+    /// Amount of tokens to deposit (in token's native decimals)
+    pub amount_in: u64,
+    /// Minimum backstop tokens expected (slippage protection, in LP token decimals)
+    pub min_backstop_amount_out: u64,
+}
+
+/// Add liquidity to the backstop tranche of a pool
+///
+/// The process:
+/// 1. Validates permissions and inputs
+/// 2. Validates token ratios remain within acceptable range
+/// 3. Transfers tokens from user to pool
+/// 4. Mints backstop tokens for the deposit value at the tranche token price
+/// 5. Updates custody, backstop and pool value
+///
+/// Backstop deposits pay no liquidity fee, the tranche value is a claim in USD.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Deposit amount and minimum backstop tokens expected
+///
+/// # Returns
+/// `Result<()>` - Success if liquidity was added successfully
+pub fn add_backstop_liquidity(
+    ctx: Context<AddBackstopLiquidity>,
+    params: &AddBackstopLiquidityParams,
+) -> Result<()> {
+    // Check permissions
+    msg!("Check permissions");
+    // Permissioned pools only accept allowlisted owners
+    if ctx.accounts.pool.lp_allowlist_enabled {
+        require!(
+            ctx.accounts
+                .lp_allowlist
+                .as_ref()
+                .is_some_and(|lp_allowlist| lp_allowlist.is_allowed(ctx.accounts.owner.key)),
+            PerpetualsError::LpNotAllowlisted
+        );
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_add_liquidity
+            && custody.permissions.allow_add_liquidity
+            && !custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(!custody.oracle_safe_mode, PerpetualsError::OracleSafeMode);
+
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.amount_in == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    let pool = ctx.accounts.pool.as_mut();
+    let token_id = pool.get_token_id(&custody.key())?;
+    let curtime = perpetuals.get_time()?;
+
+    // Get token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidity,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidity,
+    )?;
+
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
+    // Use minimum price (spot or EMA) for conservative deposit valuation
+    let min_price = if token_price < token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    // Check pool constraints
+    msg!("Check pool constraints");
+    require!(
+        pool.check_token_ratio(
+            RatioFlow::AddLiquidity,
+            token_id,
+            params.amount_in,
+            0,
+            custody,
+            &token_ema_price
+        )?,
+        PerpetualsError::TokenRatioOutOfRange
+    );
+
+    // Transfer tokens from user's funding account to pool's custody account
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount_in,
+    )?;
+
+    // Calculate amount of backstop tokens to mint
+    let deposit_usd = min_price.get_asset_amount_usd(
+        params.amount_in,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let backstop_amount = pool
+        .backstop
+        .get_mint_amount(deposit_usd, ctx.accounts.backstop_token_mint.supply)?;
+    msg!("Backstop tokens to mint: {}", backstop_amount);
+    require!(
+        backstop_amount > 0 && backstop_amount >= params.min_backstop_amount_out,
+        PerpetualsError::MaxPriceSlippage
+    );
+
+    perpetuals.mint_tokens(
+        ctx.accounts.backstop_token_mint.to_account_info(),
+        ctx.accounts.backstop_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        backstop_amount,
+    )?;
+
+    // Update custody statistics
+    msg!("Update custody stats");
+    custody.volume_stats.add_liquidity_usd = math::checked_add(
+        custody.volume_stats.add_liquidity_usd,
+        token_ema_price.get_asset_amount_usd(
+            params.amount_in,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
+    custody.assets.owned = math::checked_add(custody.assets.owned, params.amount_in)?;
+    custody.update_borrow_rate(curtime)?;
+
+    // Update backstop and pool value
+    pool.backstop.value_usd = math::checked_add(pool.backstop.value_usd, deposit_usd)?;
+    pool.aum_usd = math::checked_add(pool.aum_usd, deposit_usd as u128)?;
+
+    Ok(())
+}
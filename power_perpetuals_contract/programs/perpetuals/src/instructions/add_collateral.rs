@@ -8,13 +8,15 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        invariants,
+        math::{self, RoundingDirection},
         state::{
             custody::Custody,
-            oracle::OraclePrice,
+            oracle::OracleOperation,
             perpetuals::Perpetuals,
             pool::Pool,
-            position::{Position, Side},
+            position::{CollateralAmount, Position, Side},
+            position_summary::PositionSummary,
         },
     },
     anchor_lang::prelude::*,
@@ -30,10 +32,11 @@ pub struct AddCollateral<'info> {
     pub owner: Signer<'info>,
 
     /// User's token account from which collateral will be transferred
-    /// Must be owned by the owner and have the same mint as the position custody
+    /// Must be owned by the owner and have the same mint as the collateral custody
+    /// (the stablecoin custody for shorts)
     #[account(
         mut,
-        constraint = funding_account.mint == custody.mint,
+        constraint = funding_account.mint == collateral_custody.mint @ PerpetualsError::InvalidCollateralMint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
@@ -87,7 +90,7 @@ pub struct AddCollateral<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -102,7 +105,7 @@ pub struct AddCollateral<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -118,13 +121,26 @@ pub struct AddCollateral<'info> {
 
     /// Token program for token transfers
     pub token_program: Program<'info, Token>,
+
+    /// Optional summary of the position, mirrors the updated position
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
 }
 
 /// Parameters for adding collateral to a position
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddCollateralParams {
-    /// Amount of collateral tokens to add (in collateral token's native decimals)
-    collateral: u64,
+    /// Collateral to add, in collateral tokens or in USD
+    ///
+    /// A USD amount is converted to tokens at the min collateral price rounding up, so
+    /// the deposit is worth at least the requested USD. Either way the position is
+    /// credited the deposited tokens valued at the min price, rounding down.
+    pub collateral: CollateralAmount,
 }
 
 /// Add collateral to an existing position
@@ -141,14 +157,14 @@ pub struct AddCollateralParams {
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
-/// * `params` - Parameters including the collateral amount to add
+/// * `params` - Parameters including the collateral amount to add in tokens or USD
 /// 
 /// # Returns
 /// `Result<()>` - Success if collateral was added successfully
 pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams) -> Result<()> {
     // Validate inputs
     msg!("Validate inputs");
-    if params.collateral == 0 {
+    if params.collateral.is_zero() {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
     
@@ -162,62 +178,103 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
     // Get current time for price calculations
     let curtime = perpetuals.get_time()?;
 
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Positions can be deleveraged until the market is settled
+    require!(
+        custody.lifecycle.allows_close(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Use minimum collateral price for conservative valuation
     // For stablecoins, caps price at 1 USD
     let min_collateral_price = collateral_token_price
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
-    // Calculate collateral amount in USD for position updates
+    // Calculate the collateral token amount and its USD value for position updates
+    let collateral = match params.collateral {
+        CollateralAmount::Tokens(amount) => amount,
+        CollateralAmount::Usd(amount_usd) => min_collateral_price
+            .get_token_amount(amount_usd, collateral_custody.decimals, RoundingDirection::Up)?,
+    };
     let collateral_usd = min_collateral_price
-        .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
-    msg!("Amount in: {}", params.collateral);
+        .get_asset_amount_usd(collateral, collateral_custody.decimals, RoundingDirection::Down)?;
+    msg!("Amount in: {}", collateral);
     msg!("Collateral added in USD: {}", collateral_usd);
 
     // Update position with new collateral
     msg!("Update existing position");
     position.update_time = perpetuals.get_time()?;
     position.collateral_usd = math::checked_add(position.collateral_usd, collateral_usd)?;
-    position.collateral_amount = math::checked_add(position.collateral_amount, params.collateral)?;
+    position.collateral_amount = math::checked_add(position.collateral_amount, collateral)?;
 
     // Validate position leverage after adding collateral
     // This ensures the position remains within acceptable risk limits
     msg!("Check position risks");
-    require!(
-        pool.check_leverage(
+    if let Some(error) = pool.get_leverage_error(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )? {
+        return Err(error.into());
+    }
+
+    // The position passed the leverage check, a started liquidation auction is over
+    position.liquidation_auction_slot = 0;
+
+    // Label the position with its risk tier under the custody thresholds
+    position.risk_tier = pool
+        .get_liquidation_check(
             position,
             &token_price,
             &token_ema_price,
@@ -226,10 +283,8 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
             &collateral_token_ema_price,
             collateral_custody,
             curtime,
-            true
-        )?,
-        PerpetualsError::MaxLeverage
-    );
+        )?
+        .risk_tier;
 
     // Transfer collateral tokens from user's funding account to pool's custody account
     msg!("Transfer tokens");
@@ -240,13 +295,13 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
             .to_account_info(),
         ctx.accounts.owner.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
-        params.collateral,
+        collateral,
     )?;
 
     // Update custody statistics to reflect new collateral
     msg!("Update custody stats");
     collateral_custody.assets.collateral =
-        math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
+        math::checked_add(collateral_custody.assets.collateral, collateral)?;
 
     // If custody and collateral_custody accounts are the same (e.g., for long positions),
     // ensure that data is synchronized between the two references
@@ -254,5 +309,16 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         *custody = collateral_custody.clone();
     }
 
+    // Mirror the position into its summary
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.update(position, pool.get_token_id(&custody.key())?, curtime)?;
+    }
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.collateral_custody,
+        &mut ctx.accounts.collateral_custody_token_account,
+    )?;
+
     Ok(())
 }
\ No newline at end of file
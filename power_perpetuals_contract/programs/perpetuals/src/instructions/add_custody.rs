@@ -9,7 +9,7 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::{BorrowRateParams, Custody, Fees, PricingParams},
+            custody::{BorrowRateParams, Custody, EntryFeeTier, Fees, PricingParams},
             multisig::{AdminInstruction, Multisig},
             oracle::OracleParams,
             perpetuals::{Permissions, Perpetuals},
@@ -113,8 +113,12 @@ pub struct AddCustodyParams {
     pub pricing: PricingParams,
     /// Permission flags controlling allowed operations
     pub permissions: Permissions,
+    /// Allow user-facing trading instructions to be invoked through CPI
+    pub allow_cpi: bool,
     /// Fee structure (open/close position fees, swap fees, etc.)
     pub fees: Fees,
+    /// Open position fee tiers by position size (unused tiers have min_size_usd 0)
+    pub entry_fee_tiers: [EntryFeeTier; Custody::MAX_ENTRY_FEE_TIERS],
     /// Borrow rate parameters for interest calculations
     pub borrow_rate: BorrowRateParams,
     /// Token ratios for pool rebalancing (must include ratio for new custody)
@@ -168,16 +172,38 @@ pub fn add_custody<'info>(
         return Ok(signatures_left);
     }
 
+    // Release the multisig borrow before handing the accounts over
+    drop(multisig);
+    init_custody(ctx.accounts, &ctx.bumps, params)?;
+    Ok(0)
+}
+
+/// Add the custody to the pool and initialize it, once the multisig has approved
+///
+/// Shared with add_custody_from_staged.
+///
+/// # Arguments
+/// * `accounts` - AddCustody accounts
+/// * `bumps` - PDA bumps of the AddCustody accounts
+/// * `params` - Parameters including custody configuration
+///
+/// # Returns
+/// `Result<()>` - Success if the custody was added, or error
+pub fn init_custody(
+    accounts: &mut AddCustody,
+    bumps: &AddCustodyBumps,
+    params: &AddCustodyParams,
+) -> Result<()> {
     // Check if custody already exists in the pool
-    let pool = ctx.accounts.pool.as_mut();
-    if pool.get_token_id(&ctx.accounts.custody.key()).is_ok() {
+    let pool = accounts.pool.as_mut();
+    if pool.get_token_id(&accounts.custody.key()).is_ok() {
         // Return error if custody is already initialized
         return Err(anchor_lang::error::ErrorCode::ConstraintMut.into());
     }
 
     // Update pool data to include new custody
     // Add custody pubkey to pool's custody list
-    pool.custodies.push(ctx.accounts.custody.key());
+    pool.custodies.push(accounts.custody.key());
     // Update token ratios (must include ratio for new custody)
     pool.ratios = params.ratios.clone();
     // Validate pool configuration after adding custody
@@ -186,30 +212,35 @@ pub fn add_custody<'info>(
     }
 
     // Initialize custody account with all configuration parameters
-    let custody = ctx.accounts.custody.as_mut();
+    let custody = accounts.custody.as_mut();
     custody.pool = pool.key();
-    custody.mint = ctx.accounts.custody_token_mint.key();
-    custody.token_account = ctx.accounts.custody_token_account.key();
-    custody.decimals = ctx.accounts.custody_token_mint.decimals;
+    custody.mint = accounts.custody_token_mint.key();
+    custody.token_account = accounts.custody_token_account.key();
+    custody.decimals = accounts.custody_token_mint.decimals;
     custody.is_stable = params.is_stable;
     custody.is_virtual = params.is_virtual;
     custody.oracle = params.oracle;
     custody.pricing = params.pricing;
     custody.permissions = params.permissions;
+    custody.allow_cpi = params.allow_cpi;
     custody.fees = params.fees;
+    custody.entry_fee_tiers = params.entry_fee_tiers;
     custody.borrow_rate = params.borrow_rate;
     // Initialize borrow rate state with base rate
     custody.borrow_rate_state.current_rate = params.borrow_rate.base_rate;
-    custody.borrow_rate_state.last_update = ctx.accounts.perpetuals.get_time()?;
+    custody.borrow_rate_state.last_update = accounts.perpetuals.get_time()?;
+    custody.stats_epoch.start_time = custody.borrow_rate_state.last_update;
     // Store PDA bumps for future account derivation
-    custody.bump = ctx.bumps.custody;
-    custody.token_account_bump = ctx.bumps.custody_token_account;
+    custody.bump = bumps.custody;
+    custody.token_account_bump = bumps.custody_token_account;
+
+    // Params must stay within the program-wide safety bounds
+    require!(custody.validate_bounds(), PerpetualsError::ParamOutOfBounds);
 
     // Validate custody configuration
-    // Return error if validation fails, otherwise return success (0 signatures left)
     if !custody.validate() {
         err!(PerpetualsError::InvalidCustodyConfig)
     } else {
-        Ok(0)
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+//! AddCustodyFromStaged instruction handler
+//!
+//! This instruction adds a custody like add_custody, but reads AddCustodyParams from
+//! the staged params account of the pool instead of the instruction data, so pools
+//! with many custodies and complex configs aren't limited by the transaction size.
+//! This requires multisig approval over the staged params.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        // glob import, nested Accounts need the generated client modules in scope
+        instructions::add_custody::*,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            staged_params::StagedParams,
+        },
+    },
+    anchor_lang::{prelude::*, AccountsClose},
+};
+
+/// Accounts required for adding a custody from staged params
+#[derive(Accounts)]
+pub struct AddCustodyFromStaged<'info> {
+    /// Same accounts as add_custody
+    pub add_custody: AddCustody<'info>,
+
+    /// Staged params account holding the serialized AddCustodyParams (closed to the
+    /// admin once the custody is added)
+    #[account(
+        mut,
+        seeds = [b"staged_params",
+                 add_custody.pool.key().as_ref()],
+        bump = staged_params.load()?.bump
+    )]
+    pub staged_params: AccountLoader<'info, StagedParams>,
+}
+
+/// Parameters for adding a custody from staged params
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddCustodyFromStagedParams {}
+
+/// Add a new custody to a pool with params staged by stage_params
+///
+/// The process:
+/// 1. Deserializes AddCustodyParams from the staged params account
+/// 2. Validates multisig signatures over the staged params
+/// 3. Adds and initializes the custody as add_custody does
+/// 4. Closes the staged params account
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Empty parameters struct
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn add_custody_from_staged<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddCustodyFromStaged<'info>>,
+    _params: &AddCustodyFromStagedParams,
+) -> Result<u8> {
+    // Validate inputs
+    let params: AddCustodyParams = ctx.accounts.staged_params.load()?.get_params()?;
+    if params.ratios.len() != ctx.accounts.add_custody.pool.ratios.len() + 1 {
+        return err!(PerpetualsError::InvalidStagedParams);
+    }
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.add_custody.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.add_custody.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::AddCustodyFromStaged, &params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Add custody
+    drop(multisig);
+    init_custody(
+        &mut ctx.accounts.add_custody,
+        &ctx.bumps.add_custody,
+        &params,
+    )?;
+
+    // Close staged params
+    ctx.accounts
+        .staged_params
+        .close(ctx.accounts.add_custody.admin.to_account_info())?;
+
+    Ok(0)
+}
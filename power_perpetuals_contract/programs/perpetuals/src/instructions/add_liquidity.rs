@@ -7,13 +7,17 @@
 
 use {
     crate::{
+        conversions,
         error::PerpetualsError,
-        math,
+        invariants,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody,
-            oracle::OraclePrice,
+            custody::{Custody, FeeType},
+            lp_allowlist::LpAllowlist,
+            lp_ledger::LpLedger,
+            oracle::OracleOperation,
             perpetuals::Perpetuals,
-            pool::{AumCalcMode, Pool},
+            pool::{AumCalcMode, Pool, RatioFlow},
         },
     },
     anchor_lang::prelude::*,
@@ -85,7 +89,7 @@ pub struct AddLiquidity<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -108,7 +112,28 @@ pub struct AddLiquidity<'info> {
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
 
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
+
+    /// Allowlist of the pool, required if the pool is permissioned
+    #[account(
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Option<Box<Account<'info, LpAllowlist>>>,
+
+    /// Deposit ledger of the owner, required if the custody charges a decaying remove fee
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LpLedger::LEN,
+        seeds = [b"lp_ledger",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub lp_ledger: Option<Box<Account<'info, LpLedger>>>,
     // remaining accounts:
     //   pool.tokens.len() custody accounts (read-only, unsigned)
     //   pool.tokens.len() custody oracles (read-only, unsigned)
@@ -121,6 +146,12 @@ pub struct AddLiquidityParams {
     pub amount_in: u64,
     /// Minimum LP tokens expected (slippage protection, in LP token decimals)
     pub min_lp_amount_out: u64,
+    /// Maximum LP token price accepted (slippage protection, USD_DECIMALS), 0 to disable
+    pub max_lp_price_usd: u64,
+    /// Minimum USD value credited after fees (slippage protection, USD_DECIMALS), 0 to disable
+    pub min_usd_amount_out: u64,
+    /// Maximum liquidity fee accepted (in BPS of amount_in), 0 to disable
+    pub max_fee_bps: u64,
 }
 
 /// Add liquidity to a pool and receive LP tokens
@@ -137,6 +168,10 @@ pub struct AddLiquidityParams {
 /// 
 /// LP tokens are calculated proportionally: lp_amount = (token_amount_usd * lp_supply) / pool_aum_usd
 /// 
+/// Slippage can be bounded in LP tokens (min_lp_amount_out) or in USD (max_lp_price_usd,
+/// min_usd_amount_out) for integrators quoting in USD. The fee itself can be bounded
+/// with max_fee_bps.
+/// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
 /// * `params` - Parameters including deposit amount and minimum LP tokens expected
@@ -147,6 +182,16 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
     // Check permissions
     // Both perpetuals and custody must allow adding liquidity, and custody must not be virtual
     msg!("Check permissions");
+    // Permissioned pools only accept allowlisted owners
+    if ctx.accounts.pool.lp_allowlist_enabled {
+        require!(
+            ctx.accounts
+                .lp_allowlist
+                .as_ref()
+                .is_some_and(|lp_allowlist| lp_allowlist.is_allowed(ctx.accounts.owner.key)),
+            PerpetualsError::LpNotAllowlisted
+        );
+    }
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     let custody = ctx.accounts.custody.as_mut();
     require!(
@@ -155,12 +200,23 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
     );
+    // Markets being wound down take no new liquidity
+    require!(
+        custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(!custody.oracle_safe_mode, PerpetualsError::OracleSafeMode);
 
     // Validate inputs
     msg!("Validate inputs");
     if params.amount_in == 0 {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
+    // Deposits must be tracked while the custody charges a decaying remove fee
+    require!(
+        !custody.is_lp_fee_decay_enabled() || ctx.accounts.lp_ledger.is_some(),
+        PerpetualsError::LpLedgerRequired
+    );
     let pool = ctx.accounts.pool.as_mut();
     let token_id = pool.get_token_id(&custody.key())?;
 
@@ -173,20 +229,24 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
         pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
 
     // Get token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidity,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidity,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
     // Use minimum price (spot or EMA) for conservative LP token calculation
     let min_price = if token_price < token_ema_price {
         token_price
@@ -198,14 +258,20 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
     let fee_amount =
         pool.get_add_liquidity_fee(token_id, params.amount_in, custody, &token_ema_price)?;
     msg!("Collected fee: {}", fee_amount);
+    if params.max_fee_bps > 0 {
+        require!(
+            fee_amount <= conversions::apply_bps_ceil(params.amount_in, params.max_fee_bps)?,
+            PerpetualsError::MaxFeeExceeded
+        );
+    }
 
     // Check pool constraints
     // Ensure token ratios remain within acceptable range after deposit
     msg!("Check pool constraints");
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    let protocol_fee = Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Liquidity), fee_amount)?;
     let deposit_amount = math::checked_sub(params.amount_in, protocol_fee)?;
     require!(
-        pool.check_token_ratio(token_id, deposit_amount, 0, custody, &token_ema_price)?,
+        pool.check_token_ratio(RatioFlow::AddLiquidity, token_id, deposit_amount, 0, custody, &token_ema_price)?,
         PerpetualsError::TokenRatioOutOfRange
     );
 
@@ -222,12 +288,15 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
     // Compute total assets under management using Max mode
     // This gives the maximum pool value for LP token calculation
     msg!("Compute assets under management");
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?;
+    // LP tokens are priced from the AUM left after the backstop tranche value
+    let pool_amount_usd = pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?,
+    );
 
     // Calculate amount of LP tokens to mint
     // Formula: lp_amount = (token_amount_usd * lp_supply) / pool_aum_usd
-    // If pool is empty (first deposit), LP amount equals token amount in USD
+    // If the LP supply is empty (first deposit or fully burned), LP amount equals token
+    // amount in USD and any residual AUM is credited to the depositor
     let no_fee_amount = math::checked_sub(params.amount_in, fee_amount)?;
     require_gte!(
         no_fee_amount,
@@ -236,23 +305,20 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
     );
 
     // Convert token amount (after fees) to USD using minimum price
-    let token_amount_usd = min_price.get_asset_amount_usd(no_fee_amount, custody.decimals)?;
+    let token_amount_usd = min_price.get_asset_amount_usd(
+        no_fee_amount,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
 
     // Calculate LP tokens proportionally based on pool value
-    let lp_amount = if pool_amount_usd == 0 {
-        // First deposit: LP tokens equal token value in USD
-        token_amount_usd
-    } else {
-        // Subsequent deposits: proportional to existing LP supply
-        math::checked_as_u64(math::checked_div(
-            math::checked_mul(
-                token_amount_usd as u128,
-                ctx.accounts.lp_token_mint.supply as u128,
-            )?,
-            pool_amount_usd,
-        )?)?
-    };
+    let lp_amount = Pool::get_lp_amount(
+        token_amount_usd,
+        pool_amount_usd,
+        ctx.accounts.lp_token_mint.supply,
+    )?;
     msg!("LP tokens to mint: {}", lp_amount);
+    pool.check_lp_supply_cap(ctx.accounts.lp_token_mint.supply, lp_amount)?;
 
     // Validate slippage protection
     // Ensure user receives at least the minimum expected LP tokens
@@ -261,6 +327,29 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
         PerpetualsError::MaxPriceSlippage
     );
 
+    // USD-denominated slippage protection, stable between quote and execution
+    // even if AUM moves the LP token price
+    require!(
+        token_amount_usd >= params.min_usd_amount_out,
+        PerpetualsError::MaxPriceSlippage
+    );
+    if params.max_lp_price_usd > 0 {
+        require_gt!(lp_amount, 0, PerpetualsError::MaxPriceSlippage);
+        let lp_price_usd = math::checked_decimal_div(
+            token_amount_usd,
+            -(Perpetuals::USD_DECIMALS as i32),
+            lp_amount,
+            -(Perpetuals::LP_DECIMALS as i32),
+            -(Perpetuals::USD_DECIMALS as i32),
+        )?;
+        msg!("LP token price: {}", lp_price_usd);
+        require_gte!(
+            params.max_lp_price_usd,
+            lp_price_usd,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
     // Mint LP tokens to user's LP token account
     perpetuals.mint_tokens(
         ctx.accounts.lp_token_mint.to_account_info(),
@@ -270,19 +359,37 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
         lp_amount,
     )?;
 
+    // Record the deposit in the owner's ledger
+    if let Some(lp_ledger) = ctx.accounts.lp_ledger.as_mut() {
+        if lp_ledger.owner == Pubkey::default() {
+            lp_ledger.pool = pool.key();
+            lp_ledger.owner = ctx.accounts.owner.key();
+            lp_ledger.bump = ctx.bumps.lp_ledger.unwrap_or_default();
+        }
+        lp_ledger.add_tranche(lp_amount, curtime)?;
+    }
+
     // Update custody statistics
     msg!("Update custody stats");
     // Track collected fees in USD
-    custody.collected_fees.add_liquidity_usd = custody
-        .collected_fees
-        .add_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?);
+    custody.collected_fees.add_liquidity_usd = math::checked_add(
+        custody.collected_fees.add_liquidity_usd,
+        token_ema_price.get_asset_amount_usd(
+            fee_amount,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
 
     // Track volume statistics in USD
-    custody.volume_stats.add_liquidity_usd = custody
-        .volume_stats
-        .add_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(params.amount_in, custody.decimals)?);
+    custody.volume_stats.add_liquidity_usd = math::checked_add(
+        custody.volume_stats.add_liquidity_usd,
+        token_ema_price.get_asset_amount_usd(
+            params.amount_in,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
 
     // Update protocol fees (portion of liquidity fee that goes to protocol)
     custody.assets.protocol_fees = math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
@@ -295,11 +402,20 @@ pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'inf
 
     // Update pool statistics
     msg!("Update pool stats");
-    // Exit custody account (release borrow from Anchor's account context)
-    custody.exit(&crate::ID)?;
     // Refresh pool AUM using EMA mode for accurate tracking
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    // The updated custody is passed in memory, its account data is only written on exit
+    pool.aum_usd = pool.get_assets_under_management_usd_with_custody(
+        AumCalcMode::EMA,
+        ctx.remaining_accounts,
+        &[(custody.key(), custody)],
+        curtime,
+    )?;
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.custody,
+        &mut ctx.accounts.custody_token_account,
+    )?;
 
     Ok(())
 }
\ No newline at end of file
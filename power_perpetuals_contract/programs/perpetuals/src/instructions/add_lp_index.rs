@@ -0,0 +1,150 @@
+//! AddLpIndex instruction handler
+//!
+//! This instruction allows admins to create an LP index, a meta-LP token backed by a
+//! basket of LP tokens of several pools. The index starts without components, they are
+//! added with set_lp_index_component. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            lp_index::LpIndex,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token},
+};
+
+/// Accounts required for creating an LP index
+#[derive(Accounts)]
+#[instruction(params: AddLpIndexParams)]
+pub struct AddLpIndex<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// New LP index account (PDA derived from the index name)
+    ///
+    /// Note: Uses init_if_needed because the instruction is called once per multisig
+    /// signature, uniqueness is enforced in the instruction handler.
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = LpIndex::LEN,
+        seeds = [b"lp_index",
+                 params.name.as_bytes()],
+        bump
+    )]
+    pub lp_index: Box<Account<'info, LpIndex>>,
+
+    /// Index token mint (owned by transfer_authority PDA)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        mint::authority = transfer_authority,
+        mint::freeze_authority = transfer_authority,
+        mint::decimals = Perpetuals::LP_DECIMALS,
+        seeds = [b"lp_index_token_mint",
+                 lp_index.key().as_ref()],
+        bump
+    )]
+    pub index_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for creating an LP index
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddLpIndexParams {
+    /// Index name (max 64 characters, must be unique)
+    pub name: String,
+    /// Maximum deviation of a deposit component share from its weight in BPS
+    pub max_weight_deviation: u64,
+}
+
+/// Create an LP index
+///
+/// The process:
+/// 1. Validates the index name
+/// 2. Validates multisig signatures (requires enough admin signatures)
+/// 3. Checks that the index doesn't already exist
+/// 4. Initializes the index account and validates its configuration
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Index name and weight tolerance
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn add_lp_index<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddLpIndex<'info>>,
+    params: &AddLpIndexParams,
+) -> Result<u8> {
+    // Validate inputs
+    if params.name.is_empty() || params.name.len() > 64 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::AddLpIndex, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Initialize index
+    let lp_index = ctx.accounts.lp_index.as_mut();
+    if lp_index.inception_time != 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintMut.into());
+    }
+
+    msg!("Record LP index: {}", params.name);
+    lp_index.name = params.name.clone();
+    lp_index.max_weight_deviation = params.max_weight_deviation;
+    lp_index.inception_time = ctx.accounts.perpetuals.get_time()?;
+    lp_index.bump = ctx.bumps.lp_index;
+    lp_index.index_token_bump = ctx.bumps.index_token_mint;
+
+    if !lp_index.validate() {
+        return err!(PerpetualsError::InvalidLpIndexConfig);
+    }
+
+    Ok(0)
+}
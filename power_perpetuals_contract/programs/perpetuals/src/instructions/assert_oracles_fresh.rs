@@ -0,0 +1,100 @@
+//! AssertOraclesFresh instruction handler
+//!
+//! This instruction checks the oracles of several custodies at once and fails if any
+//! price is stale or out of bounds. Bundlers place it first in a transaction bundle so
+//! the bundle aborts early, before heavier instructions run on a stale price.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for asserting oracle freshness
+///
+/// This instruction is read-only and doesn't modify any state.
+/// Remaining accounts are (custody, oracle) pairs: [custody0, oracle0, custody1, oracle1, ...]
+#[derive(Accounts)]
+pub struct AssertOraclesFresh<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the custodies belong to (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for asserting oracle freshness
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AssertOraclesFreshParams {
+    /// Operation the prices will be used for, selects the applicable max price age
+    pub operation: OracleOperation,
+}
+
+/// Fail fast if any of the given custody oracles is stale or out of bounds
+///
+/// The process:
+/// 1. Validates that remaining accounts come in (custody, oracle) pairs
+/// 2. For each pair, validates the custody belongs to the pool and the oracle account
+/// 3. Reads the spot price with the same staleness and confidence checks as trades
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Oracle operation the bundle will perform
+///
+/// # Returns
+/// `Result<()>` - Success if all oracles are fresh, error on the first failing pair
+pub fn assert_oracles_fresh<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AssertOraclesFresh<'info>>,
+    params: &AssertOraclesFreshParams,
+) -> Result<()> {
+    let accounts = ctx.remaining_accounts;
+    if accounts.is_empty() || !accounts.len().is_multiple_of(2) {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    let pool = &ctx.accounts.pool;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    for pair in accounts.chunks(2) {
+        let (custody_info, oracle_info) = (&pair[0], &pair[1]);
+        require!(
+            pool.custodies.contains(&custody_info.key()),
+            PerpetualsError::InvalidCustodyState
+        );
+        let custody = Account::<Custody>::try_from(custody_info)?;
+        require_keys_eq!(
+            oracle_info.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+
+        if let Err(err) = OraclePrice::new_from_oracle(
+            oracle_info,
+            &custody.oracle,
+            curtime,
+            false,
+            params.operation,
+        ) {
+            msg!("Oracle check failed for custody {}", custody_info.key());
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! CancelCommitOpen instruction handler
+//!
+//! This instruction closes an order commitment that won't be revealed, e.g. after the
+//! reveal window passed. The rent is refunded to the owner, while the deposit is
+//! forfeited to the SOL fee vault.
+
+use {
+    crate::state::{order_commitment::OrderCommitment, perpetuals::Perpetuals, pool::Pool},
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for cancelling an order commitment
+#[derive(Accounts)]
+pub struct CancelCommitOpen<'info> {
+    /// Owner of the order (signer, receives the rent)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the order was committed for
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Order commitment account (closed)
+    #[account(
+        mut,
+        has_one = owner,
+        close = owner,
+        seeds = [b"order_commitment",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = order_commitment.bump
+    )]
+    pub order_commitment: Box<Account<'info, OrderCommitment>>,
+
+    /// SOL fee vault PDA, receives the forfeited deposit
+    ///
+    /// CHECK: Empty PDA, holds protocol SOL
+    #[account(
+        mut,
+        seeds = [b"sol_fee_vault"],
+        bump
+    )]
+    pub sol_fee_vault: AccountInfo<'info>,
+}
+
+/// Cancel an order commitment, forfeiting the deposit
+///
+/// The process:
+/// 1. Moves the deposit to the SOL fee vault
+/// 2. Closes the commitment, refunding the rent to the owner
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<()>` - Success if the commitment was cancelled
+pub fn cancel_commit_open(ctx: Context<CancelCommitOpen>) -> Result<()> {
+    msg!("Forfeit deposit: {}", ctx.accounts.order_commitment.deposit);
+    Perpetuals::transfer_sol_from_owned(
+        ctx.accounts.order_commitment.to_account_info(),
+        ctx.accounts.sol_fee_vault.to_account_info(),
+        ctx.accounts.order_commitment.deposit,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,634 @@
+//! ChangePower instruction handler
+//!
+//! This instruction allows users to change the power (payoff exponent) of an existing
+//! position. The current PnL is settled into the position's collateral, the position is
+//! re-entered at the current entry price under the new exponent, leverage is re-validated
+//! and a reconfiguration fee (charged at the custody's close position rate) is collected.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::LockChanged,
+        math::{self, RoundingDirection},
+        state::{
+            compliance_freeze::ComplianceFreeze,
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::Perpetuals,
+            pool::{Pool, PositionSettlement},
+            position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+            trader_activity::TraderActivity,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for changing the power of a position
+#[derive(Accounts)]
+#[instruction(params: ChangePowerParams)]
+pub struct ChangePower<'info> {
+    /// Owner of the position (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to reconfigure (mutable, owned by owner)
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token (mutable, stats will be updated)
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+
+    /// Optional per-slot trade counter of the owner, required while the pool caps
+    /// trades per slot
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TraderActivity::LEN,
+        seeds = [b"trader_activity",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub trader_activity: Option<Box<Account<'info, TraderActivity>>>,
+
+    /// Optional summary of the position, mirrors the updated position
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional circuit-breaker feed, required if the protocol follows a risk oracle
+    ///
+    /// CHECK: Validated against the perpetuals risk oracle
+    pub risk_oracle_account: Option<AccountInfo<'info>>,
+
+    /// Optional compliance freeze list of the custody, required while the pool enforces
+    /// freeze lists
+    #[account(
+        seeds = [b"compliance_freeze",
+                 custody.key().as_ref()],
+        bump = compliance_freeze.bump
+    )]
+    pub compliance_freeze: Option<Box<Account<'info, ComplianceFreeze>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+}
+
+/// Parameters for changing the power of a position
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ChangePowerParams {
+    /// Maximum acceptable new entry price (slippage protection, scaled to PRICE_DECIMALS)
+    /// For longs: must be >= actual entry price
+    /// For shorts: must be <= actual entry price
+    pub price: u64,
+    /// New power multiplier for the position (1-5)
+    pub power: u8,
+}
+
+/// Change the power (payoff exponent) of an existing position
+///
+/// This function re-leverages a position by switching it to a different exponent.
+/// The process:
+/// 1. Validates permissions and inputs
+/// 2. Settles current PnL (including accrued interest and reconfiguration fee) into collateral
+/// 3. Calculates new entry price (with spread applied) and validates slippage protection
+/// 4. Recomputes position notional at the new entry price
+/// 5. Re-locks funds for potential profit payouts
+/// 6. Validates leverage under the new exponent
+/// 7. Updates custody statistics
+///
+/// No tokens are transferred: profit is moved from pool-owned assets into the position's
+/// collateral, while losses and the fee are moved from collateral into pool-owned assets.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters including slippage price and the new power
+///
+/// # Returns
+/// `Result<()>` - Success if the position was reconfigured successfully
+pub fn change_power(ctx: Context<ChangePower>, params: &ChangePowerParams) -> Result<()> {
+    // Check permissions
+    // Changing power is equivalent to closing and reopening the position
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_size_change
+            && perpetuals.permissions.allow_open_position
+            && perpetuals.permissions.allow_close_position
+            && custody.permissions.allow_size_change
+            && custody.permissions.allow_open_position
+            && custody.permissions.allow_close_position,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Markets being wound down take no new exposure
+    require!(
+        custody.lifecycle.allows_open() && collateral_custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        !custody.oracle_safe_mode && !collateral_custody.oracle_safe_mode,
+        PerpetualsError::OracleSafeMode
+    );
+    require!(
+        perpetuals.check_cpi_allowed(&ctx.accounts.pool, custody),
+        PerpetualsError::CpiNotAllowed
+    );
+    // Re-leveraging a position adds risk, refused while the risk oracle flags an incident
+    perpetuals.risk_oracle.check_risk_increase(
+        ctx.accounts.risk_oracle_account.as_ref(),
+        perpetuals.get_time()?,
+    )?;
+    // Frozen owners can't take on new exposure
+    ComplianceFreeze::validate_owner(
+        ctx.accounts.compliance_freeze.as_deref().map(AsRef::as_ref),
+        ctx.accounts.pool.compliance_freeze_enabled,
+        ctx.accounts.owner.key,
+    )?;
+
+    // Check the per-slot trade cap of the owner
+    let max_trades_per_slot = ctx.accounts.pool.max_trades_per_slot;
+    require!(
+        max_trades_per_slot == 0 || ctx.accounts.trader_activity.is_some(),
+        PerpetualsError::TraderActivityRequired
+    );
+    if let Some(trader_activity) = ctx.accounts.trader_activity.as_mut() {
+        if trader_activity.owner == Pubkey::default() {
+            trader_activity.pool = ctx.accounts.pool.key();
+            trader_activity.owner = ctx.accounts.owner.key();
+            trader_activity.bump = ctx.bumps.trader_activity.unwrap_or_default();
+        }
+        trader_activity.record_trade(Clock::get()?.slot, max_trades_per_slot)?;
+    }
+
+    // Validate inputs
+    msg!("Validate inputs");
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    if params.price == 0 || params.power == position.power {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    custody.pricing.check_power(params.power)?;
+    let use_collateral_custody = position.side == Side::Short || custody.is_virtual;
+
+    // Get current time for calculations
+    let curtime = perpetuals.get_time()?;
+
+    // Custodies can reject reconfigurations within the minimum holding period
+    require!(
+        !custody.pricing.reject_early_close
+            || !custody.is_within_holding_period(position.open_time, curtime),
+        PerpetualsError::MinHoldingPeriod
+    );
+
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Get position token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Get collateral token prices from oracle (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Settle current PnL under the old exponent
+    // The close fee returned here is charged as the reconfiguration fee
+    msg!("Settle position");
+    let (mut settled_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    // Convert fee to collateral token if needed
+    let mut fee_amount_usd = token_ema_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
+    if use_collateral_custody {
+        fee_amount = collateral_token_ema_price
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    // Charge anti-scalp fee on positions reconfigured within the minimum holding period
+    if custody.is_within_holding_period(position.open_time, curtime) {
+        let size = token_ema_price.get_token_amount(
+            position.size_usd,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        let early_close_fee_usd = token_ema_price.get_asset_amount_usd(
+            pool.get_early_close_fee(size, custody)?,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        let early_close_fee = std::cmp::min(
+            collateral_token_ema_price.get_token_amount(
+                early_close_fee_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            settled_amount,
+        );
+        msg!("Early close fee: {}", early_close_fee);
+        settled_amount = math::checked_sub(settled_amount, early_close_fee)?;
+        fee_amount = math::checked_add(fee_amount, early_close_fee)?;
+        fee_amount_usd = math::checked_add(fee_amount_usd, early_close_fee_usd)?;
+    }
+    require!(settled_amount > 0, PerpetualsError::InsufficientAmountReturned);
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Collected fee: {}", fee_amount);
+
+    // Calculate new entry price (applies spread based on position side)
+    let position_price =
+        pool.get_entry_price(&token_price, &token_ema_price, position.side, custody, curtime)?;
+    msg!("Entry price: {}", position_price);
+
+    // Validate slippage protection
+    if position.side == Side::Long {
+        require_gte!(
+            params.price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            params.price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    // Recompute position notional at the new entry price
+    // Token size is preserved, USD notional follows the new entry price
+    let old_position_price = OraclePrice {
+        price: position.price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+    let position_oracle_price = OraclePrice {
+        price: position_price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+    let size = old_position_price.get_token_amount(
+        position.size_usd,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let size_usd = position_oracle_price.get_asset_amount_usd(
+        size,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
+
+    let min_collateral_price = collateral_token_price
+        .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
+    let collateral_usd = min_collateral_price.get_asset_amount_usd(
+        settled_amount,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+
+    // Calculate new locked amount
+    let locked_amount = if use_collateral_custody {
+        custody.get_locked_amount(
+            min_collateral_price.get_token_amount(
+                size_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            position.side,
+            params.power,
+            curtime,
+        )?
+    } else {
+        custody.get_locked_amount(size, position.side, params.power, curtime)?
+    };
+
+    // Calculate new borrow size USD
+    let borrow_size_usd = if custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER {
+        if use_collateral_custody {
+            let max_collateral_price = if collateral_token_price < collateral_token_ema_price {
+                collateral_token_ema_price
+            } else {
+                collateral_token_price
+            };
+            max_collateral_price.get_asset_amount_usd(
+                locked_amount,
+                collateral_custody.decimals,
+                RoundingDirection::Down,
+            )?
+        } else {
+            position_oracle_price.get_asset_amount_usd(
+                locked_amount,
+                custody.decimals,
+                RoundingDirection::Down,
+            )?
+        }
+    } else {
+        size_usd
+    };
+
+    // Unlock funds that were locked for the old position
+    msg!("Update custody stats");
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Settle the position under the old exponent in custody accounts, the settled amount
+    // stays in the pool as its collateral. The reconfiguration fee is tracked as a close
+    // fee.
+    let exit_price = pool.get_exit_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        custody,
+        curtime,
+    )?;
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+    let interest_amount = collateral_token_ema_price.get_token_amount(
+        interest_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let hook_payload = PositionHookPayload {
+        event: PositionHookEvent::Close,
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        side: position.side,
+        size_usd: position.size_usd,
+        price: exit_price,
+        time: curtime,
+    };
+    pool.settle_position(
+        position,
+        custody,
+        collateral_custody,
+        &PositionSettlement {
+            amount: settled_amount,
+            fee_amount,
+            fee_usd: fee_amount_usd,
+            close_fee_usd: fee_amount_usd,
+            profit_usd,
+            loss_usd,
+            interest_amount,
+            reentered: true,
+            ..PositionSettlement::default()
+        },
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &hook_payload,
+    )?;
+
+    // Re-enter the position under the new exponent
+    msg!("Update existing position");
+    position.update_time = curtime;
+    position.power = params.power;
+    position.price = position_price;
+    position.size_usd = size_usd;
+    position.borrow_size_usd = borrow_size_usd;
+    position.collateral_usd = collateral_usd;
+    position.unrealized_profit_usd = 0;
+    position.unrealized_loss_usd = 0;
+    collateral_custody.snapshot_interest(position, curtime)?;
+    position.locked_amount = locked_amount;
+    position.collateral_amount = settled_amount;
+
+    // Validate position leverage under the new exponent
+    msg!("Check position risks");
+    require!(
+        position.locked_amount > 0,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    if let Some(error) = pool.get_leverage_error(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )? {
+        return Err(error.into());
+    }
+
+    // The position passed the leverage check, a started liquidation auction is over
+    position.liquidation_auction_slot = 0;
+
+    // Label the position with its risk tier under the custody thresholds
+    position.risk_tier = pool
+        .get_liquidation_check(
+            position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?
+        .risk_tier;
+
+    // Lock funds for potential profit payouts under the new exponent
+    collateral_custody.lock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: true,
+        amount: position.locked_amount,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Update trade statistics and add the re-entered position to tracking
+    let collateral_tracked = position.side == Side::Long && !custody.is_virtual;
+    let stats_custody = if collateral_tracked {
+        &mut *collateral_custody
+    } else {
+        &mut *custody
+    };
+    stats_custody.volume_stats.open_position_usd = math::checked_add(
+        stats_custody.volume_stats.open_position_usd,
+        size_usd as u128,
+    )?;
+    if position.side == Side::Long {
+        stats_custody.trade_stats.oi_long_usd =
+            math::checked_add(stats_custody.trade_stats.oi_long_usd, size_usd)?;
+    } else {
+        stats_custody.trade_stats.oi_short_usd =
+            math::checked_add(stats_custody.trade_stats.oi_short_usd, size_usd)?;
+    }
+    if collateral_tracked {
+        collateral_custody.add_position(position, &token_ema_price, curtime, None)?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        *custody = collateral_custody.clone();
+    } else {
+        custody.add_position(
+            position,
+            &token_ema_price,
+            curtime,
+            Some(collateral_custody),
+        )?;
+        collateral_custody.update_borrow_rate(curtime)?;
+    }
+
+    // Mirror the position into its summary
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.update(position, pool.get_token_id(&custody.key())?, curtime)?;
+    }
+
+    // Notify the pool position hook of the re-entered position, the close under the old
+    // exponent was reported when it was settled
+    pool.position_hook.invoke(
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &PositionHookPayload {
+            event: PositionHookEvent::Open,
+            pool: pool.key(),
+            position: position.key(),
+            owner: position.owner,
+            custody: custody.key(),
+            side: position.side,
+            size_usd: position.size_usd,
+            price: position_price,
+            time: curtime,
+        },
+    )?;
+
+    Ok(())
+}
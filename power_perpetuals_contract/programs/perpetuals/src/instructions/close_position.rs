@@ -7,17 +7,28 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        events::{ClaimQueued, LockChanged, PositionClosed, TradeExecuted},
+        invariants,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody,
-            oracle::OraclePrice,
+            custody::{Custody, FeeType},
+            market_maker::MarketMaker,
+            oracle::OracleOperation,
+            pending_claim::PendingClaim,
             perpetuals::Perpetuals,
-            pool::Pool,
+            owner_positions::OwnerPositions,
+            pool::{Pool, PositionSettlement},
             position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+            trader_activity::TraderActivity,
         },
     },
     anchor_lang::prelude::*,
-    anchor_spl::token::{Token, TokenAccount},
+    anchor_spl::{
+        associated_token::AssociatedToken,
+        token::{Mint, Token, TokenAccount},
+    },
 };
 
 /// Accounts required for closing a position
@@ -32,13 +43,12 @@ pub struct ClosePosition<'info> {
 
     /// User's token account to receive remaining collateral
     /// 
-    /// Must match the collateral custody mint and be owned by the owner.
-    #[account(
-        mut,
-        constraint = receiving_account.mint == collateral_custody.mint,
-        has_one = owner
-    )]
-    pub receiving_account: Box<Account<'info, TokenAccount>>,
+    /// Must match the collateral custody mint and be owned by the owner. The owner ATA
+    /// is created if it doesn't exist and the optional creation accounts are passed.
+    ///
+    /// CHECK: Token account, validated in the handler after the optional creation
+    #[account(mut)]
+    pub receiving_account: AccountInfo<'info>,
 
     /// Transfer authority PDA (authority for token accounts)
     /// 
@@ -93,7 +103,7 @@ pub struct ClosePosition<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -108,7 +118,7 @@ pub struct ClosePosition<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -124,6 +134,91 @@ pub struct ClosePosition<'info> {
 
     /// Token program for token transfers
     token_program: Program<'info, Token>,
+
+    system_program: Program<'info, System>,
+
+    /// Optional market maker account of the owner (discounted spreads)
+    #[account(
+        mut,
+        seeds = [b"market_maker",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump = market_maker.bump
+    )]
+    pub market_maker: Option<Box<Account<'info, MarketMaker>>>,
+
+    /// Optional open position counter of the owner
+    #[account(
+        mut,
+        seeds = [b"owner_positions",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump = owner_positions.bump
+    )]
+    pub owner_positions: Option<Box<Account<'info, OwnerPositions>>>,
+
+    /// Optional per-slot trade counter of the owner, required while the pool caps
+    /// trades per slot
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TraderActivity::LEN,
+        seeds = [b"trader_activity",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub trader_activity: Option<Box<Account<'info, TraderActivity>>>,
+
+    /// Optional summary of the position, marked closed
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional claim, the payout is queued in it if the collateral custody lacks free
+    /// liquidity (closed right away if the payout can be made)
+    #[account(
+        init,
+        payer = owner,
+        space = PendingClaim::LEN,
+        seeds = [b"pending_claim",
+                 collateral_custody.key().as_ref(),
+                 &collateral_custody.claim_queue.next_claim_id.to_le_bytes()],
+        bump
+    )]
+    pub pending_claim: Option<Box<Account<'info, PendingClaim>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+
+    /// Optional collateral mint, to create a missing receiving account
+    #[account(
+        constraint = collateral_mint.key() == collateral_custody.mint
+    )]
+    pub collateral_mint: Option<Box<Account<'info, Mint>>>,
+
+    /// Optional associated token program, to create a missing receiving account
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    // Optional remaining accounts (to pay the exit fee in the pool fee token):
+    //   - fee custody (mutable)
+    //   - fee custody oracle account
+    //   - fee custody token account (mutable)
+    //   - user's fee token account (mutable)
 }
 
 /// Parameters for closing a position
@@ -143,10 +238,19 @@ pub struct ClosePositionParams {
 /// 2. Calculates exit price and validates slippage protection
 /// 3. Calculates profit/loss and fees
 /// 4. Unlocks pool funds
-/// 5. Transfers remaining collateral to user
+/// 5. Transfers remaining collateral to user, or queues the payout if a pending claim
+///    account is provided and free liquidity is short
 /// 6. Updates custody statistics (volume, open interest, PnL)
 /// 7. Removes position from custody tracking
 /// 8. Closes the position account (returns rent to owner)
+///
+/// If the oracle price is past the close max age but within the custody close grace,
+/// the close is priced with the last stored price plus the close grace spread, and
+/// flagged in the PositionClosed event. Other instructions never use the grace.
+///
+/// If the pool has a fee custody and its accounts are passed as remaining accounts,
+/// the position is settled without exit fees and the fees are charged from the
+/// user's fee token account instead.
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
@@ -154,7 +258,32 @@ pub struct ClosePositionParams {
 /// 
 /// # Returns
 /// Error if validation fails, otherwise Ok(())
-pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams) -> Result<()> {
+pub fn close_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClosePosition<'info>>,
+    params: &ClosePositionParams,
+) -> Result<()> {
+    // Create the owner ATA if the receiving account doesn't exist yet
+    let receiving_account = Perpetuals::load_or_create_receiving_account(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        Some(ctx.accounts.owner.to_account_info()),
+        ctx.accounts
+            .collateral_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
+        Some(ctx.accounts.system_program.to_account_info()),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts
+            .associated_token_program
+            .as_ref()
+            .map(|program| program.to_account_info()),
+    )?;
+    require!(
+        receiving_account.mint == ctx.accounts.collateral_custody.mint
+            && receiving_account.owner == ctx.accounts.owner.key(),
+        PerpetualsError::InvalidReceivingAccount
+    );
+
     // Check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
@@ -164,54 +293,154 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
+    // Positions can be closed until the market is settled
+    require!(
+        custody.lifecycle.allows_close(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        perpetuals.check_cpi_allowed(&ctx.accounts.pool, custody),
+        PerpetualsError::CpiNotAllowed
+    );
 
     // Validate inputs
     msg!("Validate inputs");
     if params.price == 0 {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
+
+    // Check the per-slot trade cap of the owner
+    let max_trades_per_slot = ctx.accounts.pool.max_trades_per_slot;
+    require!(
+        max_trades_per_slot == 0 || ctx.accounts.trader_activity.is_some(),
+        PerpetualsError::TraderActivityRequired
+    );
+    if let Some(trader_activity) = ctx.accounts.trader_activity.as_mut() {
+        if trader_activity.owner == Pubkey::default() {
+            trader_activity.pool = ctx.accounts.pool.key();
+            trader_activity.owner = ctx.accounts.owner.key();
+            trader_activity.bump = ctx.bumps.trader_activity.unwrap_or_default();
+        }
+        trader_activity.record_trade(Clock::get()?.slot, max_trades_per_slot)?;
+    }
+
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
 
     // Get current time for calculations
     let curtime = perpetuals.get_time()?;
 
+    // Custodies can reject closes within the minimum holding period instead of charging
+    // the anti-scalp fee
+    require!(
+        !custody.pricing.reject_early_close
+            || !custody.is_within_holding_period(position.open_time, curtime),
+        PerpetualsError::MinHoldingPeriod
+    );
+
+    // Expired power futures markets can only be settled, positions are closed with
+    // settle_expired_position at the settlement price
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Prices that just went stale can still be used to close, within the close grace
+    let token_operation = custody.get_close_operation(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+    )?;
+    let collateral_operation = collateral_custody.get_close_operation(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+    )?;
+    let close_grace = token_operation == OracleOperation::CloseGrace
+        || collateral_operation == OracleOperation::CloseGrace;
+    if close_grace {
+        msg!("Closing with stale price grace");
+    }
+
     // Get position token prices (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        token_operation,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        token_operation,
     )?;
 
     // Get collateral token prices (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        collateral_operation,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        collateral_operation,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Market makers with remaining volume capacity trade at discounted spreads
+    let market_maker = match ctx.accounts.market_maker.as_mut() {
+        Some(market_maker) if market_maker.has_capacity(position.size_usd)? => {
+            market_maker.add_volume(position.size_usd)?;
+            Some(market_maker)
+        }
+        _ => None,
+    };
+    let mm_custody = match &market_maker {
+        Some(market_maker) => Some(market_maker.get_custody(custody)?),
+        None => None,
+    };
+    let pricing_custody = mm_custody.as_ref().unwrap_or(custody);
+
+    // Closes priced within the stale price grace pay an extra spread
+    let grace_custody = if close_grace {
+        let spread = std::cmp::max(
+            custody.oracle.close_grace_spread,
+            collateral_custody.oracle.close_grace_spread,
+        );
+        let mut grace_custody = Box::new(pricing_custody.clone());
+        grace_custody.pricing.trade_spread_long =
+            math::checked_add(grace_custody.pricing.trade_spread_long, spread)?;
+        grace_custody.pricing.trade_spread_short =
+            math::checked_add(grace_custody.pricing.trade_spread_short, spread)?;
+        Some(grace_custody)
+    } else {
+        None
+    };
+    let pricing_custody = grace_custody.as_deref().unwrap_or(pricing_custody);
+
     // Calculate exit price (applies spread based on position side)
-    let exit_price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let exit_price = pool.get_exit_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        pricing_custody,
+        curtime,
+    )?;
     msg!("Exit price: {}", exit_price);
 
     // Validate slippage protection
@@ -222,27 +451,124 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     } else {
         require_gte!(params.price, exit_price, PerpetualsError::MaxPriceSlippage);
     }
+    emit!(TradeExecuted {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        custody: custody.key(),
+        side: position.side,
+        is_open: false,
+        bound_price: params.price,
+        spot_price: token_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price,
+        ema_price: token_ema_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price,
+        spread_bps: pricing_custody.get_exit_spread(position.side, curtime)?,
+        execution_price: exit_price,
+        time: curtime,
+    });
 
     // Calculate final settlement amounts (collateral to return, fees, PnL)
     msg!("Settle position");
-    let (transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+    let (mut transfer_amount, mut fee_amount, mut profit_usd, mut loss_usd) = pool.get_close_amount(
         position,
         &token_price,
         &token_ema_price,
-        custody,
+        pricing_custody,
         &collateral_token_price,
         &collateral_token_ema_price,
         collateral_custody,
         curtime,
         false, // Not a liquidation
     )?;
+    let (mut uncapped_profit_usd, _, _) = pool.get_uncapped_pnl_usd(
+        position,
+        &token_price,
+        &token_ema_price,
+        pricing_custody,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
 
     // Convert fee to collateral token if needed
     // For shorts or virtual custodies, fee is in position token, convert to collateral
-    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    let mut fee_amount_usd = token_ema_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
     if position.side == Side::Short || custody.is_virtual {
         fee_amount = collateral_token_ema_price
-            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    // Charge anti-scalp fee on positions closed within the minimum holding period
+    if custody.is_within_holding_period(position.open_time, curtime) {
+        let size = token_ema_price.get_token_amount(
+            position.size_usd,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        let early_close_fee_usd = token_ema_price.get_asset_amount_usd(
+            pool.get_early_close_fee(size, custody)?,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        let early_close_fee = std::cmp::min(
+            collateral_token_ema_price.get_token_amount(
+                early_close_fee_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            transfer_amount,
+        );
+        msg!("Early close fee: {}", early_close_fee);
+        transfer_amount = math::checked_sub(transfer_amount, early_close_fee)?;
+        fee_amount = math::checked_add(fee_amount, early_close_fee)?;
+        fee_amount_usd = math::checked_add(fee_amount_usd, early_close_fee_usd)?;
+    }
+
+    // Settle without exit fees if they are paid in the pool fee token
+    let mut fee_token_accounts = pool.load_fee_token_accounts(
+        ctx.remaining_accounts,
+        &ctx.accounts.owner.key(),
+        &custody.key(),
+        &collateral_custody.key(),
+    )?;
+    if fee_token_accounts.is_some() {
+        let mut fee_free_custody = Box::new(pricing_custody.clone());
+        fee_free_custody.fees.close_position = 0;
+        (transfer_amount, _, profit_usd, loss_usd) = pool.get_close_amount(
+            position,
+            &token_price,
+            &token_ema_price,
+            &fee_free_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+        (uncapped_profit_usd, _, _) = pool.get_uncapped_pnl_usd(
+            position,
+            &token_price,
+            &token_ema_price,
+            &fee_free_custody,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+        fee_amount = 0;
+    }
+
+    // Profit is capped at the funds locked for the position
+    let profit_capped = uncapped_profit_usd > profit_usd;
+    if profit_capped {
+        msg!("Profit capped, uncapped profit: {}", uncapped_profit_usd);
     }
 
     msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
@@ -250,132 +576,166 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     msg!("Amount out: {}", transfer_amount);
 
     // Unlock funds that were locked for this position
-    collateral_custody.unlock_funds(position.locked_amount)?;
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
 
     // Check pool has sufficient funds available
+    // Queued claims are served first
     msg!("Check pool constraints");
-    require!(
-        pool.check_available_amount(transfer_amount, collateral_custody)?,
-        PerpetualsError::CustodyAmountLimit
-    );
-
-    // Transfer remaining collateral to user
-    msg!("Transfer tokens");
-    perpetuals.transfer_tokens(
-        ctx.accounts
-            .collateral_custody_token_account
-            .to_account_info(),
-        ctx.accounts.receiving_account.to_account_info(),
-        ctx.accounts.transfer_authority.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        transfer_amount,
-    )?;
-
-    // Update custody statistics
-    msg!("Update custody stats");
-    // Track collected fees
-    collateral_custody.collected_fees.close_position_usd = collateral_custody
-        .collected_fees
-        .close_position_usd
-        .wrapping_add(fee_amount_usd);
-
-    // Adjust owned assets based on PnL
-    // If transfer_amount > collateral_amount: pool lost money (user profited)
-    // If transfer_amount < collateral_amount: pool gained money (user lost)
-    if transfer_amount > position.collateral_amount {
-        let amount_lost = transfer_amount.saturating_sub(position.collateral_amount);
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
+    let queued = !pool.check_available_amount(transfer_amount, collateral_custody)?;
+    if queued {
+        require!(
+            ctx.accounts.pending_claim.is_some(),
+            PerpetualsError::CustodyAmountLimit
+        );
     } else {
-        let amount_gained = position.collateral_amount.saturating_sub(transfer_amount);
-        collateral_custody.assets.owned =
-            math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+        // Transfer remaining collateral to user
+        msg!("Transfer tokens");
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.receiving_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            transfer_amount,
+        )?;
+
+        // The claim account is not needed, return its rent
+        if let Some(pending_claim) = ctx.accounts.pending_claim.as_ref() {
+            pending_claim.close(ctx.accounts.owner.to_account_info())?;
+        }
     }
-    
-    // Remove collateral from locked collateral tracking
-    collateral_custody.assets.collateral = math::checked_sub(
-        collateral_custody.assets.collateral,
-        position.collateral_amount,
-    )?;
-
-    // Calculate and deduct protocol fee if pool has sufficient funds
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
-
-    // Pay protocol_fee from custody if possible, otherwise no protocol_fee
-    if pool.check_available_amount(protocol_fee, collateral_custody)? {
-        collateral_custody.assets.protocol_fees =
-            math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
 
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
+    if let Some(fee_token_accounts) = fee_token_accounts.as_mut() {
+        fee_token_accounts.charge_fee(
+            perpetuals,
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            fee_amount_usd,
+            custody.fees.get_protocol_share(FeeType::Position),
+            OracleOperation::Close,
+            curtime,
+        )?;
     }
 
-    // Update trade statistics and remove position from tracking
-    // Handle differently if custody and collateral_custody are the same (long positions)
-    if position.side == Side::Long && !custody.is_virtual {
-        // For long positions where custody == collateral_custody, update collateral_custody stats
-        collateral_custody.volume_stats.close_position_usd = collateral_custody
-            .volume_stats
-            .close_position_usd
-            .wrapping_add(position.size_usd);
-
-        // Update open interest (reduce by position size)
-        if position.side == Side::Long {
-            collateral_custody.trade_stats.oi_long_usd = collateral_custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            collateral_custody.trade_stats.oi_short_usd = collateral_custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
+    // Queue the payout behind the claims already waiting
+    if let (true, Some(pending_claim)) = (queued, ctx.accounts.pending_claim.as_mut()) {
+        msg!("Queue claim");
+        pending_claim.set_inner(PendingClaim {
+            custody: collateral_custody.key(),
+            owner: ctx.accounts.owner.key(),
+            receiving_account: ctx.accounts.receiving_account.key(),
+            claim_id: 0,
+            amount: transfer_amount,
+            protocol_fee: 0,
+            request_time: curtime,
+            bump: ctx.bumps.pending_claim.unwrap_or_default(),
+        });
+        collateral_custody.queue_claim(pending_claim)?;
+
+        emit!(ClaimQueued {
+            pool: pool.key(),
+            seq: pool.next_event_seq()?,
+            custody: collateral_custody.key(),
+            pending_claim: pending_claim.key(),
+            owner: pending_claim.owner,
+            claim_id: pending_claim.claim_id,
+            amount: transfer_amount,
+            pending_amount: collateral_custody.claim_queue.pending_amount,
+            time: curtime,
+        });
+    }
 
-        // Track aggregate profit/loss
-        collateral_custody.trade_stats.profit_usd = collateral_custody
-            .trade_stats
-            .profit_usd
-            .wrapping_add(profit_usd);
-        collateral_custody.trade_stats.loss_usd = collateral_custody
-            .trade_stats
-            .loss_usd
-            .wrapping_add(loss_usd);
-
-        // Remove position from custody tracking (no separate collateral_custody to update)
-        collateral_custody.remove_position(position, curtime, None)?;
-        collateral_custody.update_borrow_rate(curtime)?;
-        // Sync custody account data
-        *custody = collateral_custody.clone();
-    } else {
-        // For positions where custody != collateral_custody, update custody stats
-        custody.volume_stats.close_position_usd = custody
-            .volume_stats
-            .close_position_usd
-            .wrapping_add(position.size_usd);
-
-        // Update open interest
-        if position.side == Side::Long {
-            custody.trade_stats.oi_long_usd = custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            custody.trade_stats.oi_short_usd = custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
+    // Settle the position in custody accounts and notify the pool position hook
+    // Borrow interest paid by the position feeds the pnl reserve
+    msg!("Update custody stats");
+    let interest_amount = collateral_token_ema_price.get_token_amount(
+        interest_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let hook_payload = PositionHookPayload {
+        event: PositionHookEvent::Close,
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        side: position.side,
+        size_usd: position.size_usd,
+        price: exit_price,
+        time: curtime,
+    };
+    pool.settle_position(
+        position,
+        custody,
+        collateral_custody,
+        &PositionSettlement {
+            amount: transfer_amount,
+            fee_amount,
+            fee_usd: fee_amount_usd,
+            // Fees paid in the pool fee token are tracked when charged
+            close_fee_usd: if fee_token_accounts.is_none() {
+                fee_amount_usd
+            } else {
+                0
+            },
+            profit_usd,
+            loss_usd,
+            interest_amount,
+            queued,
+            ..PositionSettlement::default()
+        },
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &hook_payload,
+    )?;
 
-        // Track aggregate profit/loss
-        custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
-        custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
+    // Stop counting the closed position
+    if let Some(owner_positions) = ctx.accounts.owner_positions.as_mut() {
+        owner_positions.remove_position();
+    }
 
-        // Remove position from custody tracking (also update collateral_custody)
-        custody.remove_position(position, curtime, Some(collateral_custody))?;
-        // Update borrow rate for collateral custody
-        collateral_custody.update_borrow_rate(curtime)?;
+    // Mark the position summary closed
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.clear(curtime);
     }
 
+    emit!(PositionClosed {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        exit_price,
+        size_usd: position.size_usd,
+        profit_usd,
+        loss_usd,
+        fee_usd: fee_amount_usd,
+        interest_usd,
+        amount_out: transfer_amount,
+        queued,
+        close_grace,
+        profit_capped,
+        time: curtime,
+    });
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.collateral_custody,
+        &mut ctx.accounts.collateral_custody_token_account,
+    )?;
+
     Ok(())
-}
\ No newline at end of file
+}
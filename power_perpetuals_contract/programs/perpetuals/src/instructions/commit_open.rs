@@ -0,0 +1,103 @@
+//! CommitOpen instruction handler
+//!
+//! This instruction starts the commit-reveal flow for opening a large position. The
+//! owner stores a hash of the order and a SOL deposit, and opens the position with
+//! reveal_open in a later slot at the then-current price.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{order_commitment::OrderCommitment, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for committing to an order
+#[derive(Accounts)]
+pub struct CommitOpen<'info> {
+    /// Owner of the order (signer, pays rent and deposit)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the order will be opened in
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Order commitment account (PDA derived from owner and pool)
+    #[account(
+        init,
+        payer = owner,
+        space = OrderCommitment::LEN,
+        seeds = [b"order_commitment",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub order_commitment: Box<Account<'info, OrderCommitment>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for committing to an order
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CommitOpenParams {
+    /// Hash of the order, see OrderCommitment::get_hash
+    pub hash: [u8; 32],
+    /// SOL deposit in lamports (at least OrderCommitment::MIN_DEPOSIT)
+    pub deposit: u64,
+}
+
+/// Commit to an order to be opened with reveal_open
+///
+/// The process:
+/// 1. Validates the perpetuals permissions and the deposit
+/// 2. Transfers the deposit to the commitment account
+/// 3. Records the order hash and the commit slot
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Order hash and deposit
+///
+/// # Returns
+/// `Result<()>` - Success if the commitment was recorded
+pub fn commit_open(ctx: Context<CommitOpen>, params: &CommitOpenParams) -> Result<()> {
+    // Validate inputs
+    require!(
+        ctx.accounts.perpetuals.permissions.allow_open_position,
+        PerpetualsError::InstructionNotAllowed
+    );
+    if params.deposit < OrderCommitment::MIN_DEPOSIT {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // Transfer deposit
+    Perpetuals::transfer_sol(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.order_commitment.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        params.deposit,
+    )?;
+
+    // Record commitment
+    let order_commitment = ctx.accounts.order_commitment.as_mut();
+    order_commitment.owner = ctx.accounts.owner.key();
+    order_commitment.pool = ctx.accounts.pool.key();
+    order_commitment.hash = params.hash;
+    order_commitment.deposit = params.deposit;
+    order_commitment.commit_slot = Clock::get()?.slot;
+    order_commitment.bump = ctx.bumps.order_commitment;
+    msg!("Order committed at slot: {}", order_commitment.commit_slot);
+
+    Ok(())
+}
@@ -0,0 +1,342 @@
+//! CrossPoolSwap instruction handler
+//!
+//! This instruction swaps a token of one pool for a token of another pool through a
+//! stable token both pools list (e.g. USDC). The input is swapped into the stable token
+//! in the first pool, the stable tokens are moved from the first pool custody to the
+//! second one, and swapped into the output token in the second pool. Both legs are
+//! priced and checked like swap, fees of both legs are charged, and a single
+//! min_amount_out protects the final amount. Market maker discounts don't apply.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        instructions::swap::swap_leg,
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::{CrossPoolSwapAmountAndFees, Perpetuals},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+/// Accounts required for swapping tokens across two pools
+#[derive(Accounts)]
+#[instruction(params: CrossPoolSwapParams)]
+pub struct CrossPoolSwap<'info> {
+    /// Owner of the swap transaction (signer)
+    #[account()]
+    pub owner: Signer<'info>,
+
+    /// User's token account from which tokens will be deposited
+    /// Must be owned by owner and have the same mint as receiving_custody
+    #[account(
+        mut,
+        constraint = funding_account.mint == receiving_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// User's token account where tokens will be received
+    /// Must be owned by owner and have the same mint as dispensing_custody
+    #[account(
+        mut,
+        constraint = receiving_account.mint == dispensing_custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token transfers
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the input token is swapped in
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 receiving_pool.name.as_bytes()],
+        bump = receiving_pool.bump
+    )]
+    pub receiving_pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the token being deposited (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 receiving_pool.key().as_ref(),
+                 receiving_custody.mint.as_ref()],
+        bump = receiving_custody.bump
+    )]
+    pub receiving_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being deposited
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub receiving_custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account where deposited tokens are stored (mutable, tokens will be added)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 receiving_pool.key().as_ref(),
+                 receiving_custody.mint.as_ref()],
+        bump = receiving_custody.token_account_bump
+    )]
+    pub receiving_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Stable token custody of the first pool (mutable, dispenses the stable tokens)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 receiving_pool.key().as_ref(),
+                 receiving_stable_custody.mint.as_ref()],
+        bump = receiving_stable_custody.bump
+    )]
+    pub receiving_stable_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the stable token in the first pool
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = receiving_stable_custody_oracle_account.key() == receiving_stable_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub receiving_stable_custody_oracle_account: AccountInfo<'info>,
+
+    /// Stable token account of the first pool (mutable, tokens will be transferred out)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 receiving_pool.key().as_ref(),
+                 receiving_stable_custody.mint.as_ref()],
+        bump = receiving_stable_custody.token_account_bump
+    )]
+    pub receiving_stable_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Pool the output token is swapped in
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 dispensing_pool.name.as_bytes()],
+        bump = dispensing_pool.bump
+    )]
+    pub dispensing_pool: Box<Account<'info, Pool>>,
+
+    /// Stable token custody of the second pool (mutable, receives the stable tokens)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 dispensing_pool.key().as_ref(),
+                 receiving_stable_custody.mint.as_ref()],
+        bump = dispensing_stable_custody.bump
+    )]
+    pub dispensing_stable_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the stable token in the second pool
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = dispensing_stable_custody_oracle_account.key() == dispensing_stable_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub dispensing_stable_custody_oracle_account: AccountInfo<'info>,
+
+    /// Stable token account of the second pool (mutable, tokens will be added)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 dispensing_pool.key().as_ref(),
+                 receiving_stable_custody.mint.as_ref()],
+        bump = dispensing_stable_custody.token_account_bump
+    )]
+    pub dispensing_stable_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Custody account for the token being dispensed (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 dispensing_pool.key().as_ref(),
+                 dispensing_custody.mint.as_ref()],
+        bump = dispensing_custody.bump
+    )]
+    pub dispensing_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being dispensed
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub dispensing_custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account where dispensed tokens are stored (mutable, tokens will be transferred out)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 dispensing_pool.key().as_ref(),
+                 dispensing_custody.mint.as_ref()],
+        bump = dispensing_custody.token_account_bump
+    )]
+    pub dispensing_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+
+    /// Optional circuit-breaker feed, required if the protocol follows a risk oracle
+    ///
+    /// CHECK: Validated against the perpetuals risk oracle
+    pub risk_oracle_account: Option<AccountInfo<'info>>,
+}
+
+/// Parameters for swapping tokens across two pools
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct CrossPoolSwapParams {
+    /// Amount of tokens to deposit (in token decimals)
+    pub amount_in: u64,
+    /// Minimum output tokens expected (slippage protection, in token decimals)
+    pub min_amount_out: u64,
+}
+
+/// Swap a token of one pool for a token of another pool through a shared stable token
+///
+/// The process:
+/// 1. Validates permissions, inputs and that both pools share the stable token
+/// 2. Swaps the input into the stable token in the first pool (swap_leg)
+/// 3. Swaps the stable tokens into the output token in the second pool (swap_leg)
+/// 4. Validates slippage protection on the final amount
+/// 5. Transfers tokens (deposit from user, stable tokens between the pools, withdrawal
+///    to user)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters including input amount and minimum output amount
+///
+/// # Returns
+/// `CrossPoolSwapAmountAndFees` with the output amount and the fees of both legs
+pub fn cross_pool_swap(
+    ctx: Context<CrossPoolSwap>,
+    params: &CrossPoolSwapParams,
+) -> Result<CrossPoolSwapAmountAndFees> {
+    // Check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    require!(
+        perpetuals.permissions.allow_swap,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Swaps let traders dump an incident asset on the pool, refused during incidents
+    perpetuals.risk_oracle.check_risk_increase(
+        ctx.accounts.risk_oracle_account.as_ref(),
+        perpetuals.get_time()?,
+    )?;
+
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.amount_in == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    require_keys_neq!(
+        ctx.accounts.receiving_pool.key(),
+        ctx.accounts.dispensing_pool.key()
+    );
+    // Both pools must route through the same stable token
+    require!(
+        ctx.accounts.receiving_stable_custody.is_stable
+            && ctx.accounts.dispensing_stable_custody.is_stable,
+        PerpetualsError::InvalidCustodyState
+    );
+
+    // Swap the input into the stable token in the first pool
+    let curtime = perpetuals.get_time()?;
+    msg!("Swap in receiving pool");
+    let receiving_leg = swap_leg(
+        ctx.accounts.receiving_pool.as_ref(),
+        &mut ctx.accounts.receiving_custody,
+        &ctx.accounts.receiving_custody_oracle_account,
+        &mut ctx.accounts.receiving_stable_custody,
+        &ctx.accounts.receiving_stable_custody_oracle_account,
+        None,
+        params.amount_in,
+        curtime,
+    )?;
+
+    // Swap the stable tokens into the output token in the second pool
+    msg!("Swap in dispensing pool");
+    let dispensing_leg = swap_leg(
+        ctx.accounts.dispensing_pool.as_ref(),
+        &mut ctx.accounts.dispensing_stable_custody,
+        &ctx.accounts.dispensing_stable_custody_oracle_account,
+        &mut ctx.accounts.dispensing_custody,
+        &ctx.accounts.dispensing_custody_oracle_account,
+        None,
+        receiving_leg.amount_out,
+        curtime,
+    )?;
+
+    // Validate slippage protection on the final amount
+    require_gte!(
+        dispensing_leg.amount_out,
+        params.min_amount_out,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // Transfer tokens
+    msg!("Transfer tokens");
+    // Transfer tokens from user to the first pool (deposit)
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .receiving_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount_in,
+    )?;
+
+    // Move the stable tokens from the first pool to the second one
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .receiving_stable_custody_token_account
+            .to_account_info(),
+        ctx.accounts
+            .dispensing_stable_custody_token_account
+            .to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        receiving_leg.amount_out,
+    )?;
+
+    // Transfer tokens from the second pool to user (withdrawal, after fees)
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .dispensing_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        dispensing_leg.amount_out,
+    )?;
+
+    Ok(CrossPoolSwapAmountAndFees {
+        amount_out: dispensing_leg.amount_out,
+        stable_amount: receiving_leg.amount_out,
+        fee_in: receiving_leg.fee_in,
+        stable_fee: math::checked_add(receiving_leg.fee_out, dispensing_leg.fee_in)?,
+        fee_out: dispensing_leg.fee_out,
+        fee_usd: math::checked_add(receiving_leg.fee_usd, dispensing_leg.fee_usd)?,
+    })
+}
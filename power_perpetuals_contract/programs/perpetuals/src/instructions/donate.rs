@@ -0,0 +1,138 @@
+//! Donate instruction handler
+//!
+//! This instruction allows anyone to subsidize a pool by transferring tokens into a
+//! custody without receiving LP tokens. Donations first cover recorded bad debt and
+//! the rest is credited to the pool owned assets, so the bookkeeping sees the funds
+//! unlike raw transfers into the custody token account.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::Donated,
+        math,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+/// Accounts required for donating to a pool
+#[derive(Accounts)]
+pub struct Donate<'info> {
+    /// Donor (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Donor's token account from which tokens will be transferred
+    /// Must be owned by owner and have the same mint as the custody
+    #[account(
+        mut,
+        constraint = funding_account.mint == custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account receiving the donation (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the donated token (mutable, assets will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Pool's token account where tokens will be deposited
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+/// Parameters for donating to a pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DonateParams {
+    /// Amount of tokens to donate (in token decimals)
+    pub amount: u64,
+}
+
+/// Donate tokens to a pool
+///
+/// The process:
+/// 1. Validates inputs
+/// 2. Transfers tokens from the donor to the custody token account
+/// 3. Covers recorded bad debt, then credits the rest to owned assets
+/// 4. Emits a Donated event
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Amount to donate
+///
+/// # Returns
+/// `Result<()>` - Success if the donation was credited
+pub fn donate(ctx: Context<Donate>, params: &DonateParams) -> Result<()> {
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.amount == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    let custody = ctx.accounts.custody.as_mut();
+    require!(!custody.is_virtual, PerpetualsError::InstructionNotAllowed);
+
+    // Transfer tokens from donor to pool
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    // Cover bad debt first, the rest goes to liquidity providers
+    msg!("Update custody stats");
+    let bad_debt_covered = std::cmp::min(params.amount, custody.assets.bad_debt);
+    custody.assets.bad_debt = math::checked_sub(custody.assets.bad_debt, bad_debt_covered)?;
+    let owned_credited = math::checked_sub(params.amount, bad_debt_covered)?;
+    custody.assets.owned = math::checked_add(custody.assets.owned, owned_credited)?;
+    msg!(
+        "Bad debt covered: {}, owned credited: {}",
+        bad_debt_covered,
+        owned_credited
+    );
+
+    let pool = ctx.accounts.pool.as_mut();
+
+    emit!(Donated {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: custody.key(),
+        donor: ctx.accounts.owner.key(),
+        amount: params.amount,
+        bad_debt_covered,
+    });
+
+    Ok(())
+}
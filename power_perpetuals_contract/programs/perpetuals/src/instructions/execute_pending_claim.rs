@@ -0,0 +1,198 @@
+//! ExecutePendingClaim instruction handler
+//!
+//! This instruction allows anyone to pay out the claim at the head of a custody claim
+//! queue once the custody has enough free liquidity. Claims are queued by
+//! remove_liquidity and close_position when the custody can't pay right away. If the
+//! receiving account of the claim can't be paid (closed or frozen), the claim is paid
+//! to the owner ATA, created if needed, so the queue head can't be blocked.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::ClaimExecuted,
+        state::{custody::Custody, pending_claim::PendingClaim, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::{
+        associated_token::AssociatedToken,
+        token::{Mint, Token, TokenAccount},
+    },
+};
+
+/// Accounts required for executing a pending claim
+#[derive(Accounts)]
+pub struct ExecutePendingClaim<'info> {
+    /// Keeper account (signer, pays for transaction fees and a created owner ATA)
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// Owner of the claim, receives the claim account rent
+    ///
+    /// CHECK: Rent receiver, validated by constraint
+    #[account(
+        mut,
+        constraint = owner.key() == pending_claim.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    /// Token account the claimed tokens are paid to, the receiving account of the claim
+    /// or the owner ATA. The owner ATA is created if it doesn't exist and the optional
+    /// creation accounts are passed.
+    ///
+    /// CHECK: Token account, validated in the handler after the optional creation
+    #[account(
+        mut,
+        constraint = pending_claim.is_receiving_account(&receiving_account.key(), &custody.mint) @ PerpetualsError::InvalidReceivingAccount
+    )]
+    pub receiving_account: AccountInfo<'info>,
+
+    /// Transfer authority PDA for token transfers
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account the tokens are owed from (mutable, queue will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Pool's token account where tokens are stored (mutable, tokens will be transferred out)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Claim to execute (closed, rent is returned to the owner)
+    #[account(
+        mut,
+        seeds = [b"pending_claim",
+                 custody.key().as_ref(),
+                 &pending_claim.claim_id.to_le_bytes()],
+        bump = pending_claim.bump,
+        close = owner
+    )]
+    pub pending_claim: Box<Account<'info, PendingClaim>>,
+
+    token_program: Program<'info, Token>,
+
+    /// Optional custody mint, to create a missing owner ATA
+    #[account(
+        constraint = custody_mint.key() == custody.mint
+    )]
+    pub custody_mint: Option<Box<Account<'info, Mint>>>,
+
+    /// Optional system program, to create a missing owner ATA
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Optional associated token program, to create a missing owner ATA
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+/// Execute the claim at the head of a custody claim queue
+///
+/// The process:
+/// 1. Validates the claim is next in the queue (FIFO)
+/// 2. Validates the custody has enough free liquidity (owned - locked)
+/// 3. Transfers the claimed tokens to the receiving account, or to the owner ATA if the
+///    receiving account can't be paid
+/// 4. Moves the claim protocol fee to protocol fees and removes the claim from owned assets
+/// 5. Closes the claim account
+///
+/// Queued tokens were already excluded from pool AUM, so AUM doesn't change.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<()>` - Success if the claim was paid
+pub fn execute_pending_claim(ctx: Context<ExecutePendingClaim>) -> Result<()> {
+    // Create the owner ATA if the receiving account doesn't exist
+    let receiving_account = Perpetuals::load_or_create_receiving_account(
+        ctx.accounts.signer.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        Some(ctx.accounts.owner.to_account_info()),
+        ctx.accounts
+            .custody_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
+        ctx.accounts
+            .system_program
+            .as_ref()
+            .map(|program| program.to_account_info()),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts
+            .associated_token_program
+            .as_ref()
+            .map(|program| program.to_account_info()),
+    )?;
+    require!(
+        receiving_account.mint == ctx.accounts.custody.mint,
+        PerpetualsError::InvalidReceivingAccount
+    );
+
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let custody = ctx.accounts.custody.as_mut();
+    let pending_claim = ctx.accounts.pending_claim.as_ref();
+    let curtime = perpetuals.get_time()?;
+
+    // Claims are paid in queue order, the head of the queue can use all free liquidity.
+    // The claim protocol fee moves to protocol fees
+    msg!("Execute claim");
+    custody.execute_claim(pending_claim)?;
+
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        pending_claim.amount,
+    )?;
+
+    msg!("Update custody stats");
+    custody.update_borrow_rate(curtime)?;
+
+    let pool = ctx.accounts.pool.as_mut();
+
+    emit!(ClaimExecuted {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: custody.key(),
+        pending_claim: pending_claim.key(),
+        owner: pending_claim.owner,
+        claim_id: pending_claim.claim_id,
+        amount: pending_claim.amount,
+        time: curtime,
+    });
+
+    Ok(())
+}
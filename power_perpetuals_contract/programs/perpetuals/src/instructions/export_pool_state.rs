@@ -0,0 +1,205 @@
+//! ExportPoolState instruction handler
+//!
+//! This instruction allows admins to start migrating a pool to a new pool PDA (e.g. to
+//! change the pool name). The source pool is frozen and its custodies, open position
+//! count and LP supply are recorded in a migration account, which import_pool_state then
+//! uses as a cursor to re-create the state under the target pool over several
+//! transactions. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::{Permissions, Perpetuals},
+            pool::Pool,
+            pool_migration::{CustodyMapping, PoolMigration},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+/// Accounts required for exporting a pool state
+#[derive(Accounts)]
+pub struct ExportPoolState<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool being migrated
+    #[account(
+        seeds = [b"pool",
+                 source_pool.name.as_bytes()],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint of the source pool
+    #[account(
+        seeds = [b"lp_token_mint",
+                 source_pool.key().as_ref()],
+        bump = source_pool.lp_token_bump
+    )]
+    pub source_lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// Pool the state will be imported into (created with add_pool)
+    #[account(
+        seeds = [b"pool",
+                 target_pool.name.as_bytes()],
+        bump = target_pool.bump
+    )]
+    pub target_pool: Box<Account<'info, Pool>>,
+
+    /// Pool migration account (PDA derived from the source pool)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = PoolMigration::LEN,
+        seeds = [b"pool_migration",
+                 source_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_migration: Box<Account<'info, PoolMigration>>,
+
+    system_program: Program<'info, System>,
+    // Remaining accounts:
+    //   - source_pool.custodies.len() custody accounts (mutable, will be frozen)
+}
+
+/// Parameters for exporting a pool state
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ExportPoolStateParams {}
+
+/// Start migrating a pool to a new pool PDA
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the target pool and the source custodies
+/// 3. Freezes the source custodies (all permissions off), keeping their permissions
+/// 4. Records custodies, open position count and LP supply in the migration account
+///
+/// Custodies with queued claims can't be migrated. Pool-scoped auxiliary accounts
+/// (owner position counters, LP ledgers, market makers, LP allowlist, crank state)
+/// are not migrated and have to be re-created for the target pool.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn export_pool_state<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExportPoolState<'info>>,
+    params: &ExportPoolStateParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ExportPoolState, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate pools
+    let source_pool = ctx.accounts.source_pool.as_ref();
+    let target_pool = ctx.accounts.target_pool.as_ref();
+    require_keys_neq!(source_pool.key(), target_pool.key());
+    require!(
+        target_pool.custodies.is_empty(),
+        PerpetualsError::InvalidPoolState
+    );
+    require!(
+        ctx.accounts.pool_migration.source_pool == Pubkey::default(),
+        PerpetualsError::InvalidPoolState
+    );
+    require!(
+        source_pool.custodies.len() <= PoolMigration::MAX_CUSTODIES,
+        PerpetualsError::InvalidPoolConfig
+    );
+    if ctx.remaining_accounts.len() < source_pool.custodies.len() {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    // Freeze source custodies
+    msg!("Freeze source custodies");
+    let mut custodies = Vec::with_capacity(source_pool.custodies.len());
+    let mut positions_total: u64 = 0;
+    for (custody_info, &custody_key) in ctx
+        .remaining_accounts
+        .iter()
+        .zip(source_pool.custodies.iter())
+    {
+        require_keys_eq!(custody_info.key(), custody_key);
+        require!(
+            custody_info.is_writable,
+            PerpetualsError::InvalidCustodyState
+        );
+        let mut custody = Account::<Custody>::try_from(custody_info)?;
+        require!(
+            custody.claim_queue.pending_amount == 0,
+            PerpetualsError::InvalidCustodyState
+        );
+
+        positions_total = math::checked_add(
+            positions_total,
+            math::checked_add(
+                custody.long_positions.open_positions,
+                custody.short_positions.open_positions,
+            )?,
+        )?;
+        custodies.push(CustodyMapping {
+            source: custody_key,
+            target: Pubkey::default(),
+            permissions: custody.permissions,
+            allow_cpi: custody.allow_cpi,
+        });
+
+        custody.permissions = Permissions::default();
+        custody.allow_cpi = false;
+        custody.exit(&crate::ID)?;
+    }
+
+    // Record migration
+    let pool_migration = ctx.accounts.pool_migration.as_mut();
+    pool_migration.source_pool = source_pool.key();
+    pool_migration.target_pool = target_pool.key();
+    pool_migration.custodies = custodies;
+    pool_migration.positions_total = positions_total;
+    pool_migration.positions_migrated = 0;
+    pool_migration.lp_supply = ctx.accounts.source_lp_token_mint.supply;
+    pool_migration.finalized = false;
+    pool_migration.bump = ctx.bumps.pool_migration;
+    msg!(
+        "Positions to migrate: {}, LP supply: {}",
+        positions_total,
+        pool_migration.lp_supply
+    );
+
+    Ok(0)
+}
@@ -0,0 +1,198 @@
+//! FinalizePoolMigration instruction handler
+//!
+//! This instruction completes a pool migration once all custodies and positions were
+//! imported. The exported LP token supply is minted from the target pool LP mint to a
+//! migration vault, from which LPs swap their tokens 1:1 with migrate_lp_tokens, and the
+//! target custodies get their original permissions back. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            pool_migration::PoolMigration,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for finalizing a pool migration
+#[derive(Accounts)]
+pub struct FinalizePoolMigration<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool being migrated
+    #[account(
+        seeds = [b"pool",
+                 source_pool.name.as_bytes()],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Box<Account<'info, Pool>>,
+
+    /// Pool the state was imported into
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 target_pool.name.as_bytes()],
+        bump = target_pool.bump
+    )]
+    pub target_pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint of the target pool
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 target_pool.key().as_ref()],
+        bump = target_pool.lp_token_bump
+    )]
+    pub target_lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// Pool migration account
+    #[account(
+        mut,
+        has_one = source_pool,
+        has_one = target_pool,
+        seeds = [b"pool_migration",
+                 source_pool.key().as_ref()],
+        bump = pool_migration.bump
+    )]
+    pub pool_migration: Box<Account<'info, PoolMigration>>,
+
+    /// Vault holding the target LP tokens to swap source LP tokens for
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = target_lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"pool_migration_lp",
+                 pool_migration.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+    // Remaining accounts:
+    //   - target_pool.custodies.len() custody accounts (mutable, permissions restored)
+}
+
+/// Parameters for finalizing a pool migration
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FinalizePoolMigrationParams {}
+
+/// Finalize a pool migration
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates all custodies and positions were imported
+/// 3. Mints the exported LP supply to the migration LP vault
+/// 4. Restores the original permissions of the target custodies
+/// 5. Carries over pool settings (fee custody, LP allowlist, position limit)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn finalize_pool_migration<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FinalizePoolMigration<'info>>,
+    params: &FinalizePoolMigrationParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::FinalizePoolMigration, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate migration state
+    let pool_migration = ctx.accounts.pool_migration.as_ref();
+    require!(
+        pool_migration.is_imported() && !pool_migration.finalized,
+        PerpetualsError::InvalidPoolState
+    );
+    if ctx.remaining_accounts.len() < pool_migration.custodies.len() {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    // Mint LP supply to the vault
+    msg!("Mint LP tokens: {}", pool_migration.lp_supply);
+    ctx.accounts.perpetuals.mint_tokens(
+        ctx.accounts.target_lp_token_mint.to_account_info(),
+        ctx.accounts.lp_vault.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        pool_migration.lp_supply,
+    )?;
+
+    // Restore custody permissions
+    msg!("Restore custody permissions");
+    for (custody_info, mapping) in ctx
+        .remaining_accounts
+        .iter()
+        .zip(pool_migration.custodies.iter())
+    {
+        require_keys_eq!(custody_info.key(), mapping.target);
+        let mut custody = Account::<Custody>::try_from(custody_info)?;
+        custody.permissions = mapping.permissions;
+        custody.allow_cpi = mapping.allow_cpi;
+        custody.exit(&crate::ID)?;
+    }
+
+    // Carry over pool settings
+    let source_pool = ctx.accounts.source_pool.as_ref();
+    let target_pool = ctx.accounts.target_pool.as_mut();
+    if source_pool.fee_custody != Pubkey::default() {
+        target_pool.fee_custody = pool_migration.get_target_custody(&source_pool.fee_custody)?;
+    }
+    target_pool.lp_allowlist_enabled = source_pool.lp_allowlist_enabled;
+    target_pool.max_positions_per_owner = source_pool.max_positions_per_owner;
+    target_pool.max_lp_supply = source_pool.max_lp_supply;
+    target_pool.collateral_withdrawal_limit = source_pool.collateral_withdrawal_limit;
+    target_pool.aum_usd = source_pool.aum_usd;
+
+    ctx.accounts.pool_migration.finalized = true;
+
+    Ok(0)
+}
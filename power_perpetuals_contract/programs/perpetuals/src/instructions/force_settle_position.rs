@@ -0,0 +1,491 @@
+//! ForceSettlePosition instruction handler
+//!
+//! This instruction settles a single position at the price scheduled with
+//! schedule_force_settlement, for when the custody oracle is permanently broken and the
+//! position could otherwise never be closed. The timelock must have passed and the price
+//! must be within the allowed deviation of two independent reference prices, read from
+//! custodies of the same mint in other pools with non-custom oracles of different types
+//! or providers. No trade spread is applied, and the
+//! position account is closed (deleted) after execution. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::{LockChanged, PositionForceSettled},
+        math::RoundingDirection,
+        state::{
+            custody::Custody,
+            force_settlement::ForceSettlement,
+            multisig::{AdminInstruction, Multisig},
+            oracle::{OracleOperation, OraclePrice},
+            owner_positions::OwnerPositions,
+            perpetuals::Perpetuals,
+            pool::{Pool, PositionSettlement},
+            position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+/// Accounts required for force settling a position
+#[derive(Accounts)]
+pub struct ForceSettlePosition<'info> {
+    /// Admin account that must sign (must be part of multisig), receives the rent of the
+    /// force settlement account
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Owner of the position, receives the position rent
+    ///
+    /// CHECK: Must be the position owner, validated by constraint
+    #[account(
+        mut,
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    /// Owner's token account to receive remaining collateral (payout account chosen by
+    /// the owner)
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.owner == position.owner,
+        constraint = position.is_payout_account(&receiving_account.key(), &collateral_custody.mint) @ PerpetualsError::InvalidPayoutAccount
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA (authority for token accounts)
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the position belongs to
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to settle (closed after execution, rent returned to owner)
+    #[account(
+        mut,
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump,
+        close = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (the asset being traded)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Custody account for the collateral token (the asset used as margin)
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token, unused if the collateral
+    /// custody is the position custody
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account for collateral (source of collateral transfer)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Force settlement account (closed, rent returned to admin)
+    #[account(
+        mut,
+        has_one = position,
+        close = admin,
+        seeds = [b"force_settlement",
+                 position.key().as_ref()],
+        bump = force_settlement.bump
+    )]
+    pub force_settlement: Box<Account<'info, ForceSettlement>>,
+
+    /// First reference custody, of the position token mint in another pool
+    #[account(
+        constraint = reference_custody_a.mint == custody.mint,
+        constraint = reference_custody_a.key() != custody.key()
+    )]
+    pub reference_custody_a: Box<Account<'info, Custody>>,
+
+    /// Oracle account of the first reference custody
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = reference_oracle_account_a.key() == reference_custody_a.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount,
+        constraint = reference_oracle_account_a.key() != custody.oracle.oracle_account
+    )]
+    pub reference_oracle_account_a: AccountInfo<'info>,
+
+    /// Second reference custody, of the position token mint in another pool
+    #[account(
+        constraint = reference_custody_b.mint == custody.mint,
+        constraint = reference_custody_b.key() != custody.key(),
+        constraint = reference_custody_b.key() != reference_custody_a.key()
+    )]
+    pub reference_custody_b: Box<Account<'info, Custody>>,
+
+    /// Oracle account of the second reference custody
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = reference_oracle_account_b.key() == reference_custody_b.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount,
+        constraint = reference_oracle_account_b.key() != custody.oracle.oracle_account,
+        constraint = reference_oracle_account_b.key() != reference_oracle_account_a.key()
+    )]
+    pub reference_oracle_account_b: AccountInfo<'info>,
+
+    /// Token program for token transfers
+    token_program: Program<'info, Token>,
+
+    /// Optional open position counter of the owner
+    #[account(
+        mut,
+        seeds = [b"owner_positions",
+                 pool.key().as_ref(),
+                 position.owner.as_ref()],
+        bump = owner_positions.bump
+    )]
+    pub owner_positions: Option<Box<Account<'info, OwnerPositions>>>,
+
+    /// Optional summary of the position, marked closed
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+}
+
+/// Parameters for force settling a position
+///
+/// Currently empty, the settlement price is fixed by schedule_force_settlement.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ForceSettlePositionParams {}
+
+/// Force settle a position at the scheduled price
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the timelock has passed
+/// 3. Validates the reference oracles are independent and the settlement price is within
+///    the allowed deviation of both reference prices
+/// 4. Calculates profit/loss and fees at the settlement price and unlocks pool funds
+/// 5. Transfers remaining collateral to the owner
+/// 6. Updates custody statistics and removes the position from custody tracking
+/// 7. Emits a PositionForceSettled event and closes the position
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn force_settle_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, ForceSettlePosition<'info>>,
+    params: &ForceSettlePositionParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ForceSettlePosition, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate timelock
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let curtime = perpetuals.get_time()?;
+    let force_settlement = ctx.accounts.force_settlement.as_ref();
+    require!(
+        curtime >= force_settlement.execute_time,
+        PerpetualsError::InstructionNotAllowed
+    );
+
+    // Validate settlement price against the reference prices
+    msg!("Check reference prices");
+    require!(
+        ForceSettlement::are_independent_references(
+            &ctx.accounts.reference_custody_a.oracle,
+            ctx.accounts.reference_oracle_account_a.owner,
+            &ctx.accounts.reference_custody_b.oracle,
+            ctx.accounts.reference_oracle_account_b.owner,
+        ),
+        PerpetualsError::InvalidOracleAccount
+    );
+    let reference_price_a = ctx.accounts.reference_custody_a.get_oracle_price(
+        &ctx.accounts.reference_oracle_account_a.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?
+    .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+    .price;
+    let reference_price_b = ctx.accounts.reference_custody_b.get_oracle_price(
+        &ctx.accounts.reference_oracle_account_b.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?
+    .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+    .price;
+    msg!(
+        "Settlement price: {}, reference prices: {} {}",
+        force_settlement.price,
+        reference_price_a,
+        reference_price_b
+    );
+    require!(
+        force_settlement.is_within_reference(reference_price_a)?
+            && force_settlement.is_within_reference(reference_price_b)?,
+        PerpetualsError::InvalidOraclePrice
+    );
+
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // Settlement price is used as both spot and EMA price of the position token
+    let settlement_price = OraclePrice {
+        price: force_settlement.price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+
+    // Collateral of the position custody is valued at the settlement price too, as
+    // its oracle is broken
+    let (collateral_token_price, collateral_token_ema_price) =
+        if position.collateral_custody == position.custody {
+            (settlement_price, settlement_price)
+        } else {
+            let collateral_token_price = collateral_custody.get_oracle_price(
+                &ctx.accounts
+                    .collateral_custody_oracle_account
+                    .to_account_info(),
+                curtime,
+                false,
+                OracleOperation::Close,
+            )?;
+            let collateral_token_ema_price = collateral_custody.get_oracle_price(
+                &ctx.accounts
+                    .collateral_custody_oracle_account
+                    .to_account_info(),
+                curtime,
+                collateral_custody.pricing.use_ema,
+                OracleOperation::Close,
+            )?;
+            (
+                collateral_custody.get_fair_price(&collateral_token_price, curtime)?,
+                collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?,
+            )
+        };
+
+    // Settlement pays out at the fixed price, without trade spread
+    let mut settlement_custody = custody.clone();
+    settlement_custody.pricing.trade_spread_long = 0;
+    settlement_custody.pricing.trade_spread_short = 0;
+
+    // Calculate final settlement amounts (collateral to return, fees, PnL)
+    msg!("Settle position");
+    let (transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+        position,
+        &settlement_price,
+        &settlement_price,
+        &settlement_custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    // Convert fee to collateral token if needed
+    let fee_amount_usd = settlement_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
+    if position.side == Side::Short || custody.is_virtual {
+        fee_amount = collateral_token_ema_price
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Collected fee: {}", fee_amount);
+    msg!("Amount out: {}", transfer_amount);
+
+    // Unlock funds that were locked for this position
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Check pool has sufficient funds available
+    // Token ratio bounds don't apply to forced settlements (RatioFlow::ForceSettlement)
+    msg!("Check pool constraints");
+    require!(
+        pool.check_available_amount(transfer_amount, collateral_custody)?,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // Transfer remaining collateral to the owner
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // Settle the position in custody accounts and notify the pool position hook
+    // Borrow interest paid by the position feeds the pnl reserve
+    msg!("Update custody stats");
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+    let interest_amount = collateral_token_ema_price.get_token_amount(
+        interest_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let hook_payload = PositionHookPayload {
+        event: PositionHookEvent::Close,
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        side: position.side,
+        size_usd: position.size_usd,
+        price: force_settlement.price,
+        time: curtime,
+    };
+    pool.settle_position(
+        position,
+        custody,
+        collateral_custody,
+        &PositionSettlement {
+            amount: transfer_amount,
+            fee_amount,
+            fee_usd: fee_amount_usd,
+            close_fee_usd: fee_amount_usd,
+            profit_usd,
+            loss_usd,
+            interest_amount,
+            ..PositionSettlement::default()
+        },
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &hook_payload,
+    )?;
+
+    // Stop counting the closed position
+    if let Some(owner_positions) = ctx.accounts.owner_positions.as_mut() {
+        owner_positions.remove_position();
+    }
+
+    // Mark the position summary closed
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.clear(curtime);
+    }
+
+    emit!(PositionForceSettled {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        price: force_settlement.price,
+        reference_price_a,
+        reference_price_b,
+        size_usd: position.size_usd,
+        profit_usd,
+        loss_usd,
+        fee_usd: fee_amount_usd,
+        amount_out: transfer_amount,
+        time: curtime,
+    });
+
+    Ok(0)
+}
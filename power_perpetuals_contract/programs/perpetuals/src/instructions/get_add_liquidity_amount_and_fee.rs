@@ -6,10 +6,11 @@
 
 use {
     crate::{
-        math,
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
         state::{
             custody::Custody,
-            oracle::OraclePrice,
+            oracle::OracleOperation,
             perpetuals::{AmountAndFee, Perpetuals},
             pool::{AumCalcMode, Pool},
         },
@@ -52,7 +53,7 @@ pub struct GetAddLiquidityAmountAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -112,20 +113,24 @@ pub fn get_add_liquidity_amount_and_fee<'info>(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidity,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidity,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
     // Calculate fee that would be charged
     let fee_amount =
         pool.get_add_liquidity_fee(token_id, params.amount_in, custody, &token_price)?;
@@ -134,8 +139,10 @@ pub fn get_add_liquidity_amount_and_fee<'info>(
     let no_fee_amount = math::checked_sub(params.amount_in, fee_amount)?;
 
     // Calculate pool AUM using Max mode (ensures fair LP token calculation)
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?;
+    // LP tokens are priced from the AUM left after the backstop tranche value
+    let pool_amount_usd = pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?,
+    );
 
     // Use minimum price for conservative LP token calculation
     let min_price = if token_price < token_ema_price {
@@ -145,23 +152,22 @@ pub fn get_add_liquidity_amount_and_fee<'info>(
     };
     
     // Convert token amount (after fee) to USD value
-    let token_amount_usd = min_price.get_asset_amount_usd(no_fee_amount, custody.decimals)?;
+    let token_amount_usd = min_price.get_asset_amount_usd(
+        no_fee_amount,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
 
     // Calculate LP tokens to mint
     // Formula: LP_tokens = (token_amount_usd * lp_supply) / pool_aum_usd
-    let lp_amount = if pool_amount_usd == 0 {
-        // First liquidity provider: LP tokens = token value in USD
-        token_amount_usd
-    } else {
-        // Subsequent deposits: proportional to existing LP supply
-        math::checked_as_u64(math::checked_div(
-            math::checked_mul(
-                token_amount_usd as u128,
-                ctx.accounts.lp_token_mint.supply as u128,
-            )?,
-            pool_amount_usd,
-        )?)?
-    };
+    // (first deposit or fully burned supply: LP tokens = token value in USD)
+    let lp_amount = Pool::get_lp_amount(
+        token_amount_usd,
+        pool_amount_usd,
+        ctx.accounts.lp_token_mint.supply,
+    )?;
+    // Deposits over the LP supply cap are rejected like in add_liquidity
+    pool.check_lp_supply_cap(ctx.accounts.lp_token_mint.supply, lp_amount)?;
 
     // Return calculated amounts
     Ok(AmountAndFee {
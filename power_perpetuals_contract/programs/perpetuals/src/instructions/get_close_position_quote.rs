@@ -0,0 +1,197 @@
+//! GetClosePositionQuote instruction handler
+//!
+//! This is a view/query instruction that quotes closing several positions of a pool
+//! in one simulation, so "close all" interfaces don't need one RPC round trip per
+//! position. The math mirrors close_position.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{ClosePositionQuote, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying close position quotes
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetClosePositionQuote<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the positions belong to (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+    // Remaining accounts (read-only, unsigned):
+    //   - pool.custodies.len() custody accounts
+    //   - pool.custodies.len() custody oracle accounts
+    //   - up to MAX_POSITIONS position accounts to quote
+}
+
+/// Parameters for querying close position quotes
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetClosePositionQuoteParams {}
+
+/// Maximum number of positions quoted at once (bounded by the return data size)
+const MAX_POSITIONS: usize = 12;
+
+/// Quote closing multiple positions at current prices (view function)
+///
+/// For each position this calculates the exit price, the exit fee (including the
+/// anti-scalp fee within the minimum holding period), the collateral that would be
+/// returned and the net PnL, as close_position would without a market maker account.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `Vec<ClosePositionQuote>` with one quote per position, in remaining accounts order
+pub fn get_close_position_quote<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetClosePositionQuote<'info>>,
+    _params: &GetClosePositionQuoteParams,
+) -> Result<Vec<ClosePositionQuote>> {
+    let pool = &ctx.accounts.pool;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // Validate remaining accounts
+    let custodies_len = pool.custodies.len();
+    if ctx.remaining_accounts.len() < custodies_len * 2 {
+        return Err(PerpetualsError::UnsupportedOracle.into());
+    }
+    let position_accounts = &ctx.remaining_accounts[custodies_len * 2..];
+    if position_accounts.is_empty() || position_accounts.len() > MAX_POSITIONS {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+
+    // Load custodies and their spot and EMA prices once
+    let mut custodies = Vec::with_capacity(custodies_len);
+    let mut prices = Vec::with_capacity(custodies_len);
+    for (idx, &custody_key) in pool.custodies.iter().enumerate() {
+        let custody_info = &ctx.remaining_accounts[idx];
+        let oracle_info = &ctx.remaining_accounts[idx + custodies_len];
+        require_keys_eq!(custody_info.key(), custody_key);
+        let custody = Account::<Custody>::try_from(custody_info)?;
+        require_keys_eq!(
+            oracle_info.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+
+        let token_price = custody.get_oracle_price(
+            oracle_info,
+            curtime,
+            false,
+            OracleOperation::Close,
+        )?;
+        let token_ema_price = custody.get_oracle_price(
+            oracle_info,
+            curtime,
+            custody.pricing.use_ema,
+            OracleOperation::Close,
+        )?;
+        custodies.push(custody);
+        prices.push((token_price, token_ema_price));
+    }
+
+    let mut quotes = Vec::with_capacity(position_accounts.len());
+    for position_info in position_accounts {
+        let position = Account::<Position>::try_from(position_info)?;
+        require_keys_eq!(position.pool, pool.key());
+
+        let token_id = pool.get_token_id(&position.custody)?;
+        let collateral_token_id = pool.get_token_id(&position.collateral_custody)?;
+        let custody = &custodies[token_id];
+        let collateral_custody = &custodies[collateral_token_id];
+        let (token_price, token_ema_price) = &prices[token_id];
+        let (collateral_token_price, collateral_token_ema_price) = &prices[collateral_token_id];
+
+        // Value liquid staking collateral at its fair value in underlying terms
+        let collateral_token_price =
+            &collateral_custody.get_fair_price(collateral_token_price, curtime)?;
+        let collateral_token_ema_price =
+            &collateral_custody.get_fair_price(collateral_token_ema_price, curtime)?;
+
+        // Calculate exit price (applies spread based on position side)
+        let exit_price =
+            pool.get_exit_price(token_price, token_ema_price, position.side, custody, curtime)?;
+
+        // Calculate final settlement amounts (collateral to return, fees, PnL)
+        let (mut amount_out, mut fee, profit_usd, loss_usd) = pool.get_close_amount(
+            &position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+
+        // Convert fee to collateral token if needed
+        if position.side == Side::Short || custody.is_virtual {
+            let fee_usd = token_ema_price.get_asset_amount_usd(
+                fee,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?;
+            fee = collateral_token_ema_price.get_token_amount(
+                fee_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?;
+        }
+
+        // Add anti-scalp fee if the position is within the minimum holding period
+        if custody.is_within_holding_period(position.open_time, curtime) {
+            let size = token_ema_price.get_token_amount(
+                position.size_usd,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?;
+            let early_close_fee_usd = token_ema_price.get_asset_amount_usd(
+                pool.get_early_close_fee(size, custody)?,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?;
+            let early_close_fee = std::cmp::min(
+                collateral_token_ema_price.get_token_amount(
+                    early_close_fee_usd,
+                    collateral_custody.decimals,
+                    RoundingDirection::Up,
+                )?,
+                amount_out,
+            );
+            amount_out = math::checked_sub(amount_out, early_close_fee)?;
+            fee = math::checked_add(fee, early_close_fee)?;
+        }
+
+        quotes.push(ClosePositionQuote {
+            position: position_info.key(),
+            exit_price,
+            fee,
+            amount_out,
+            profit_usd,
+            loss_usd,
+        });
+    }
+
+    Ok(quotes)
+}
@@ -0,0 +1,72 @@
+//! GetCustodyStats instruction handler
+//!
+//! This is a view/query instruction that returns the volume and fee counters of a
+//! custody, both lifetime and since the current stats epoch started, along with the
+//! highest position power the market allows.
+
+use {
+    crate::state::{
+        custody::Custody,
+        perpetuals::{CustodyStats, Perpetuals},
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying custody stats
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetCustodyStats<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to query (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for querying custody stats
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetCustodyStatsParams {}
+
+/// Get lifetime and since-epoch volume and fee counters of a custody (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `CustodyStats` struct containing lifetime and since-epoch counters and the max power
+pub fn get_custody_stats(
+    ctx: Context<GetCustodyStats>,
+    _params: &GetCustodyStatsParams,
+) -> Result<CustodyStats> {
+    let custody = &ctx.accounts.custody;
+
+    Ok(CustodyStats {
+        collected_fees: custody.collected_fees,
+        volume_stats: custody.volume_stats,
+        epoch_start_time: custody.stats_epoch.start_time,
+        epoch_collected_fees: custody.get_epoch_collected_fees()?,
+        epoch_volume_stats: custody.get_epoch_volume_stats()?,
+        max_power: custody.pricing.get_max_power(),
+    })
+}
@@ -5,12 +5,17 @@
 //! before executing it, helping them understand the costs and risks.
 
 use {
-    crate::state::{
-        custody::Custody,
-        oracle::OraclePrice,
-        perpetuals::{NewPositionPricesAndFee, Perpetuals},
-        pool::Pool,
-        position::{Position, Side},
+    crate::{
+        error::PerpetualsError,
+        instructions::open_position::SizeMode,
+        math::RoundingDirection,
+        state::{
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::{NewPositionPricesAndFee, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -49,7 +54,7 @@ pub struct GetEntryPriceAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -66,7 +71,7 @@ pub struct GetEntryPriceAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -77,6 +82,8 @@ pub struct GetEntryPriceAndFeeParams {
     collateral: u64,
     size: u64,
     side: Side,
+    size_mode: SizeMode,
+    power: u8,
 }
 
 /// Calculate entry price, liquidation price, and fee for opening a position (view function)
@@ -86,16 +93,20 @@ pub struct GetEntryPriceAndFeeParams {
 /// 1. Entry price (with spread applied)
 /// 2. Liquidation price (price at which position would be liquidated)
 /// 3. Entry fee (with utilization-based adjustments)
+/// 4. Borrow size (locked amount value if max_payoff_mult is set)
+///
+/// Liquidation price and borrow size follow the requested power, as open_position does.
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts (read-only)
-/// * `params` - Parameters including collateral, size, and side
+/// * `params` - Parameters including collateral, size, side and power
 /// 
 /// # Returns
 /// `NewPositionPricesAndFee` struct containing:
 /// - `entry_price`: Price at which position would be opened (scaled to PRICE_DECIMALS)
 /// - `liquidation_price`: Price threshold for liquidation (scaled to PRICE_DECIMALS)
 /// - `fee`: Fee amount that would be charged (in position token decimals, or collateral if short/virtual)
+/// - `borrow_size_usd`: Borrow size used for leverage checks
 pub fn get_entry_price_and_fee(
     ctx: Context<GetEntryPriceAndFee>,
     params: &GetEntryPriceAndFeeParams,
@@ -104,6 +115,7 @@ pub fn get_entry_price_and_fee(
     if params.collateral == 0 || params.size == 0 || params.side == Side::None {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
+    ctx.accounts.custody.pricing.check_power(params.power)?;
     let pool = &ctx.accounts.pool;
     let custody = &ctx.accounts.custody;
     let collateral_custody = &ctx.accounts.collateral_custody;
@@ -112,46 +124,53 @@ pub fn get_entry_price_and_fee(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Use minimum collateral price for conservative valuation
     // For stablecoins, caps price at 1 USD
     let min_collateral_price = collateral_token_price
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
     // Calculate entry price (applies spread based on position side)
-    let entry_price = pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+    let entry_price =
+        pool.get_entry_price(&token_price, &token_ema_price, params.side, custody, curtime)?;
 
     // Convert entry price to OraclePrice format for calculations
     let position_oracle_price = OraclePrice {
@@ -159,29 +178,83 @@ pub fn get_entry_price_and_fee(
         exponent: -(Perpetuals::PRICE_DECIMALS as i32),
     };
     
-    // Calculate position size and collateral in USD
-    let size_usd = position_oracle_price.get_asset_amount_usd(params.size, custody.decimals)?;
-    let collateral_usd = min_collateral_price
-        .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
+    // Calculate position size in tokens and USD (same conversion as open_position)
+    let (size, size_usd) = match params.size_mode {
+        SizeMode::Tokens => (
+            params.size,
+            position_oracle_price.get_asset_amount_usd(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+        ),
+        SizeMode::Usd => (
+            position_oracle_price.get_token_amount(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            params.size,
+        ),
+    };
+    // Calculate collateral in USD
+    let collateral_usd = min_collateral_price.get_asset_amount_usd(
+        params.collateral,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
 
     // Calculate locked amount (tokens that would be locked for this position)
     // For shorts or virtual custodies, convert size_usd to collateral tokens first
     let locked_amount = if params.side == Side::Short || custody.is_virtual {
         custody.get_locked_amount(
-            min_collateral_price.get_token_amount(size_usd, collateral_custody.decimals)?,
+            min_collateral_price.get_token_amount(
+                size_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
             params.side,
+            params.power,
+            curtime,
         )?
     } else {
-        custody.get_locked_amount(params.size, params.side)?
+        custody.get_locked_amount(size, params.side, params.power, curtime)?
+    };
+
+    // Calculate borrow size USD (same as open_position)
+    let borrow_size_usd = if custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER {
+        if params.side == Side::Short || custody.is_virtual {
+            let max_collateral_price = if collateral_token_price < collateral_token_ema_price {
+                collateral_token_ema_price
+            } else {
+                collateral_token_price
+            };
+            max_collateral_price.get_asset_amount_usd(
+                locked_amount,
+                collateral_custody.decimals,
+                RoundingDirection::Down,
+            )?
+        } else {
+            position_oracle_price.get_asset_amount_usd(
+                locked_amount,
+                custody.decimals,
+                RoundingDirection::Down,
+            )?
+        }
+    } else {
+        size_usd
     };
 
     // Create temporary position struct for liquidation price calculation
     let position = Position {
         side: params.side,
+        power: params.power,
         price: entry_price,
         size_usd,
+        borrow_size_usd,
         collateral_usd,
         cumulative_interest_snapshot: collateral_custody.get_cumulative_interest(curtime)?,
+        interest_epoch: collateral_custody.interest_epoch.id,
         ..Position::default()
     };
 
@@ -197,17 +270,24 @@ pub fn get_entry_price_and_fee(
     // Calculate entry fee (includes utilization-based adjustments)
     let mut fee = pool.get_entry_fee(
         custody.fees.open_position,
-        params.size,
+        &custody.entry_fee_tiers,
+        size,
+        size_usd,
         locked_amount,
         collateral_custody,
     )?;
+    fee = pool.apply_imbalance_fee(fee, size, size_usd, params.side, custody)?;
 
     // Convert fee to collateral token if needed
     // For shorts or virtual custodies, fee is calculated in position token, convert to collateral
     if params.side == Side::Short || custody.is_virtual {
-        let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee, custody.decimals)?;
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(
+            fee,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
         fee = collateral_token_ema_price
-            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
     }
 
     // Return calculated prices and fee
@@ -215,5 +295,6 @@ pub fn get_entry_price_and_fee(
         entry_price,
         liquidation_price,
         fee,
+        borrow_size_usd,
     })
 }
\ No newline at end of file
@@ -5,12 +5,16 @@
 //! before executing it, helping them understand the costs and expected returns.
 
 use {
-    crate::state::{
-        custody::Custody,
-        oracle::OraclePrice,
-        perpetuals::{Perpetuals, PriceAndFee},
-        pool::Pool,
-        position::{Position, Side},
+    crate::{
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, PriceAndFee},
+            pool::Pool,
+            position::{Position, Side},
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -60,7 +64,7 @@ pub struct GetExitPriceAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -77,7 +81,7 @@ pub struct GetExitPriceAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -117,47 +121,65 @@ pub fn get_exit_price_and_fee(
     let collateral_custody = &ctx.accounts.collateral_custody;
 
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Close,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Close,
     )?;
 
     // Get collateral token EMA price (needed for fee conversion)
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Close,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Calculate exit price (applies spread based on position side)
     // For longs: uses short spread (minimum price)
     // For shorts: uses long spread (maximum price)
-    let price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let price =
+        pool.get_exit_price(&token_price, &token_ema_price, position.side, custody, curtime)?;
 
     // Calculate position size in tokens for fee calculation
-    let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
+    let size = token_ema_price.get_token_amount(
+        position.size_usd,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
 
     // Calculate exit fee (initially in position token decimals)
     let mut fee = pool.get_exit_fee(size, custody)?;
 
+    // Add anti-scalp fee if the position is within the minimum holding period
+    if custody.is_within_holding_period(position.open_time, curtime) {
+        fee = math::checked_add(fee, pool.get_early_close_fee(size, custody)?)?;
+    }
+
     // Convert fee to collateral token if needed
     // For shorts or virtual custodies, fee is calculated in position token, convert to collateral
     if position.side == Side::Short || custody.is_virtual {
-        let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee, custody.decimals)?;
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(
+            fee,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
         fee = collateral_token_ema_price
-            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
     }
     
     // Return calculated exit price and fee
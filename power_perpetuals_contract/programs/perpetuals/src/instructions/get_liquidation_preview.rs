@@ -0,0 +1,215 @@
+//! GetLiquidationPreview instruction handler
+//!
+//! This is a view/query instruction that dry-runs a liquidation at current prices.
+//! It uses the same settlement path as the liquidate instruction, so keeper bots can
+//! rank positions by reward without reimplementing the program math.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math::RoundingDirection,
+        state::{
+            custody::{Custody, FeeType},
+            oracle::OracleOperation,
+            perpetuals::{LiquidationPreview, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for previewing a liquidation
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetLiquidationPreview<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to query (read-only)
+    #[account(
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token (read-only)
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for previewing a liquidation
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetLiquidationPreviewParams {}
+
+/// Preview liquidation of a position (view function)
+///
+/// The process:
+/// 1. Fetches spot and EMA prices for the position and collateral tokens
+/// 2. Runs the liquidation check under the custody liquidation price mode
+/// 3. Computes settlement amounts with get_close_amount (liquidation = true)
+/// 4. Splits the amount out into keeper reward and user amount
+/// 5. Derives the protocol fee and the resulting pool PnL
+///
+/// Amounts are returned even if the position is not liquidatable yet, so keepers
+/// can track positions approaching the threshold.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `LiquidationPreview` struct containing projected liquidation amounts
+pub fn get_liquidation_preview(
+    ctx: Context<GetLiquidationPreview>,
+    _params: &GetLiquidationPreviewParams,
+) -> Result<LiquidationPreview> {
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    let position = &ctx.accounts.position;
+    let pool = &ctx.accounts.pool;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Get position token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+
+    // Get collateral token prices from oracle (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    let liquidation_check = pool.get_liquidation_check(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+
+    // Settlement amounts, exactly as computed by liquidate
+    let (total_amount_out, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )?;
+
+    // Convert fee to collateral token if needed
+    if position.side == Side::Short || custody.is_virtual {
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(
+            fee_amount,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        fee_amount = collateral_token_ema_price
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    let reward_rate = pool.get_liquidation_reward_rate(position, custody, Clock::get()?.slot)?;
+    let reward = Pool::get_fee_amount(reward_rate, total_amount_out)?;
+    let user_amount = total_amount_out.saturating_sub(reward);
+    let protocol_fee = Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Liquidation), fee_amount)?;
+
+    Ok(LiquidationPreview {
+        position: position.key(),
+        liquidatable: liquidation_check.liquidatable,
+        user_amount,
+        reward,
+        fee: fee_amount,
+        protocol_fee,
+        profit_usd,
+        loss_usd,
+        pool_profit: position.collateral_amount.saturating_sub(total_amount_out),
+        pool_loss: total_amount_out.saturating_sub(position.collateral_amount),
+    })
+}
@@ -7,9 +7,10 @@
 
 use {
     crate::{
-        math,
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool,
+            custody::Custody, oracle::OracleOperation, perpetuals::Perpetuals, pool::Pool,
             position::Position,
         },
     },
@@ -61,7 +62,7 @@ pub struct GetLiquidationPrice<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -75,7 +76,7 @@ pub struct GetLiquidationPrice<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -115,32 +116,38 @@ pub fn get_liquidation_price(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get position token EMA price (used for liquidation calculations)
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidate,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Use minimum collateral price for conservative valuation
     // For stablecoins, caps price at 1 USD
     let min_collateral_price = collateral_token_price
@@ -152,8 +159,11 @@ pub fn get_liquidation_price(
 
     // Apply hypothetical collateral addition if specified
     if params.add_collateral > 0 {
-        let collateral_usd = min_collateral_price
-            .get_asset_amount_usd(params.add_collateral, collateral_custody.decimals)?;
+        let collateral_usd = min_collateral_price.get_asset_amount_usd(
+            params.add_collateral,
+            collateral_custody.decimals,
+            RoundingDirection::Down,
+        )?;
         position.collateral_usd = math::checked_add(position.collateral_usd, collateral_usd)?;
         position.collateral_amount =
             math::checked_add(position.collateral_amount, params.add_collateral)?;
@@ -161,8 +171,11 @@ pub fn get_liquidation_price(
     
     // Apply hypothetical collateral removal if specified
     if params.remove_collateral > 0 {
-        let collateral_usd = min_collateral_price
-            .get_asset_amount_usd(params.remove_collateral, collateral_custody.decimals)?;
+        let collateral_usd = min_collateral_price.get_asset_amount_usd(
+            params.remove_collateral,
+            collateral_custody.decimals,
+            RoundingDirection::Up,
+        )?;
         // Validate that removal doesn't exceed available collateral
         if collateral_usd >= position.collateral_usd
             || params.remove_collateral >= position.collateral_amount
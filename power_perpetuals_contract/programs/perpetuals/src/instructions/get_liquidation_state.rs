@@ -5,9 +5,12 @@
 //! maximum leverage requirements. Returns 0 if position is safe, 1 if at risk.
 
 use {
-    crate::state::{
-        custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool,
-        position::Position,
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody, oracle::OracleOperation, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -57,7 +60,7 @@ pub struct GetLiquidationState<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -71,7 +74,7 @@ pub struct GetLiquidationState<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -104,56 +107,65 @@ pub fn get_liquidation_state(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidate,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidate,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
-    // Check if position leverage is within acceptable limits
-    // Returns true if position is safe, false if it exceeds maximum leverage
-    if ctx.accounts.pool.check_leverage(
-        &ctx.accounts.position,
-        &token_price,
-        &token_ema_price,
-        custody,
-        &collateral_token_price,
-        &collateral_token_ema_price,
-        collateral_custody,
-        curtime,
-        false,
-    )? {
-        // Position is safe (leverage within limits)
-        Ok(0)
-    } else {
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Check if position can be liquidated under the custody liquidation price mode
+    if ctx
+        .accounts
+        .pool
+        .get_liquidation_check(
+            &ctx.accounts.position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?
+        .liquidatable
+    {
         // Position is at risk (leverage exceeds maximum allowed)
         Ok(1)
+    } else {
+        // Position is safe (leverage within limits)
+        Ok(0)
     }
 }
\ No newline at end of file
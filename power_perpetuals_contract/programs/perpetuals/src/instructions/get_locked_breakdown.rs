@@ -0,0 +1,63 @@
+//! GetLockedBreakdown instruction handler
+//!
+//! This is a view/query instruction that returns how the custody balance splits between
+//! pool liquidity, funds locked for trader payoffs, trader collateral and protocol fees,
+//! so risk monitors can track how much liquidity is committed to open positions.
+
+use {
+    crate::state::{
+        custody::Custody,
+        perpetuals::{LockedBreakdown, Perpetuals},
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying the locked funds breakdown
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetLockedBreakdown<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to query (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for querying the locked funds breakdown
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetLockedBreakdownParams {}
+
+/// Get the locked vs owned vs collateral vs protocol fees split of a custody (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `LockedBreakdown` struct containing the custody balances and utilization
+pub fn get_locked_breakdown(
+    ctx: Context<GetLockedBreakdown>,
+    _params: &GetLockedBreakdownParams,
+) -> Result<LockedBreakdown> {
+    ctx.accounts.custody.get_locked_breakdown()
+}
@@ -0,0 +1,69 @@
+//! GetLpSupplyCapacity instruction handler
+//!
+//! This is a view/query instruction that returns the LP supply cap of a pool and how
+//! many LP tokens can still be minted under it, so frontends can size deposits to the
+//! remaining capacity of capped pools.
+
+use {
+    crate::state::{
+        perpetuals::{LpSupplyCapacity, Perpetuals},
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+/// Accounts required for querying the LP supply capacity
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetLpSupplyCapacity<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint for this pool (read-only, to get supply)
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+}
+
+/// Parameters for querying the LP supply capacity
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetLpSupplyCapacityParams {}
+
+/// Get the LP supply cap of a pool and its remaining capacity (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Empty parameters struct
+///
+/// # Returns
+/// `LpSupplyCapacity` struct containing the cap, the supply and the remaining capacity
+pub fn get_lp_supply_capacity(
+    ctx: Context<GetLpSupplyCapacity>,
+    _params: &GetLpSupplyCapacityParams,
+) -> Result<LpSupplyCapacity> {
+    let pool = &ctx.accounts.pool;
+    let lp_supply = ctx.accounts.lp_token_mint.supply;
+
+    Ok(LpSupplyCapacity {
+        max_lp_supply: pool.max_lp_supply,
+        lp_supply,
+        remaining_lp_supply: pool.get_lp_supply_capacity(lp_supply),
+    })
+}
@@ -75,11 +75,15 @@ pub fn get_lp_token_price<'info>(
 ) -> Result<u64> {
     // Calculate total Assets Under Management using EMA mode
     // This gives a smoothed value based on exponential moving average prices
-    let aum_usd = math::checked_as_u64(ctx.accounts.pool.get_assets_under_management_usd(
-        AumCalcMode::EMA,
-        ctx.remaining_accounts,
-        ctx.accounts.perpetuals.get_time()?,
-    )?)?;
+    // The backstop tranche value is not part of the LP token value
+    let pool = &ctx.accounts.pool;
+    let aum_usd = math::checked_as_u64(pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(
+            AumCalcMode::EMA,
+            ctx.remaining_accounts,
+            ctx.accounts.perpetuals.get_time()?,
+        )?,
+    ))?;
 
     msg!("aum_usd: {}", aum_usd);
 
@@ -0,0 +1,167 @@
+//! GetMaxPayoff instruction handler
+//!
+//! This is a view/query instruction that returns the maximum profit a position can
+//! realize. Profits are paid out of the funds locked for the position at open, sized by
+//! the custody max_payoff_mult, so a position's profit stops growing once it reaches the
+//! locked value.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{MaxPayoff, Perpetuals},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying the max payoff of a position
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetMaxPayoff<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to query (read-only)
+    #[account(
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token (read-only)
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for querying the max payoff of a position
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetMaxPayoffParams {}
+
+/// Get the max payoff of a position and whether it caps the current profit (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `MaxPayoff` struct containing the locked funds, the profit cap and the uncapped profit
+pub fn get_max_payoff(ctx: Context<GetMaxPayoff>, _params: &GetMaxPayoffParams) -> Result<MaxPayoff> {
+    let position = &ctx.accounts.position;
+    let pool = &ctx.accounts.pool;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+
+    // Get position token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Close,
+    )?;
+
+    // Get collateral token prices from oracle (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Close,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    let max_profit_usd = Pool::get_max_profit_usd(
+        position,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    let (profit_usd, _, _) = pool.get_uncapped_pnl_usd(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    Ok(MaxPayoff {
+        locked_amount: position.locked_amount,
+        max_profit_usd,
+        profit_usd,
+        capped: profit_usd > max_profit_usd,
+    })
+}
@@ -0,0 +1,90 @@
+//! GetOracleHealth instruction handler
+//!
+//! This is a view/query instruction that reports how fresh the custody oracle is
+//! relative to its staleness and heartbeat limits, so keepers know when to call
+//! update_oracle_safe_mode and frontends can warn about stale prices.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            perpetuals::{OracleHealth, Perpetuals},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying oracle health
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetOracleHealth<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to query (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the custody token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for querying oracle health
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetOracleHealthParams {}
+
+/// Get oracle freshness and heartbeat status of a custody (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `OracleHealth` struct containing the oracle update age and limits
+pub fn get_oracle_health(
+    ctx: Context<GetOracleHealth>,
+    _params: &GetOracleHealthParams,
+) -> Result<OracleHealth> {
+    let custody = &ctx.accounts.custody;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    let last_update = custody.get_oracle_publish_time(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+    )?;
+    let age_sec = curtime.saturating_sub(last_update).max(0) as u64;
+
+    Ok(OracleHealth {
+        last_update,
+        age_sec,
+        max_price_age_sec: custody.oracle.max_price_age_sec,
+        heartbeat_limit_sec: custody.oracle.get_heartbeat_limit(),
+        stale: age_sec > custody.oracle.max_price_age_sec as u64,
+        heartbeat_missed: custody.oracle.is_heartbeat_missed(last_update, curtime),
+        safe_mode: custody.oracle_safe_mode,
+    })
+}
@@ -5,7 +5,10 @@
 //! (Exponential Moving Average) price based on the parameters.
 
 use {
-    crate::state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+    crate::{
+        error::PerpetualsError,
+        state::{custody::Custody, oracle::OracleOperation, perpetuals::Perpetuals, pool::Pool},
+    },
     anchor_lang::prelude::*,
 };
 
@@ -43,7 +46,7 @@ pub struct GetOraclePrice<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 }
@@ -74,11 +77,11 @@ pub fn get_oracle_price(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get price from oracle (spot or EMA based on params.ema)
-    let price = OraclePrice::new_from_oracle(
+    let price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         params.ema,
+        OracleOperation::Open,
     )?;
 
     // Scale price to PRICE_DECIMALS and return
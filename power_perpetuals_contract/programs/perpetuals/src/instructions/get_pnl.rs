@@ -5,12 +5,15 @@
 //! compared to the position's entry price, without actually closing the position.
 
 use {
-    crate::state::{
-        custody::Custody,
-        oracle::OraclePrice,
-        perpetuals::{Perpetuals, ProfitAndLoss},
-        pool::Pool,
-        position::Position,
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, ProfitAndLoss},
+            pool::Pool,
+            position::Position,
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -60,7 +63,7 @@ pub struct GetPnl<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -74,7 +77,7 @@ pub struct GetPnl<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -93,7 +96,7 @@ pub struct GetPnlParams {}
 /// 
 /// PnL is calculated as:
 /// - Profit: Positive difference between current exit price and entry price (for longs)
-///            or between entry price and current exit price (for shorts)
+///   or between entry price and current exit price (for shorts)
 /// - Loss: Negative difference, including fees and interest
 /// 
 /// # Arguments
@@ -111,39 +114,45 @@ pub fn get_pnl(ctx: Context<GetPnl>, _params: &GetPnlParams) -> Result<ProfitAnd
     let collateral_custody = &ctx.accounts.collateral_custody;
 
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Close,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Close,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Close,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Close,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Compute profit and loss in USD
     // Returns (profit_usd, loss_usd, fee_amount)
     // We ignore fee_amount here as we only need profit/loss
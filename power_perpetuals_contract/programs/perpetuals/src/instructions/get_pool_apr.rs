@@ -0,0 +1,137 @@
+//! GetPoolApr instruction handler
+//!
+//! This is a view/query instruction that estimates the fee APR earned by liquidity
+//! providers, per custody and for the whole pool. It is computed on-chain so every
+//! frontend shows consistent numbers.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, PoolApr},
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying pool fee APR
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetPoolApr<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+    // Remaining accounts (read-only, unsigned):
+    //   - pool.custodies.len() custody accounts (for fee counters and balances)
+    //   - pool.custodies.len() custody oracle accounts (for price feeds)
+}
+
+/// Parameters for querying pool fee APR
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetPoolAprParams {}
+
+/// Seconds in a 365 day year
+const SECONDS_PER_YEAR: u128 = 31_536_000;
+
+/// Estimate trailing fee APR for liquidity providers (view function)
+///
+/// Fees are taken from the cumulative custody fee counters, net of the protocol
+/// share, and annualized over the time since pool inception:
+/// apr = lp_fees_usd * BPS_POWER * SECONDS_PER_YEAR / (elapsed_time * aum_usd)
+///
+/// Custody APRs use the custody's own token balance, the pool APR uses pool AUM
+/// (EMA mode). APRs are 0 when the value base or elapsed time is 0.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `PoolApr` struct containing per-custody and pool fee APR (in BPS)
+pub fn get_pool_apr<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetPoolApr<'info>>,
+    _params: &GetPoolAprParams,
+) -> Result<PoolApr> {
+    let pool = &ctx.accounts.pool;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let elapsed_time = curtime.saturating_sub(pool.inception_time).max(0) as u128;
+
+    let custodies_len = pool.custodies.len();
+    if ctx.remaining_accounts.len() < custodies_len * 2 {
+        return Err(PerpetualsError::UnsupportedOracle.into());
+    }
+
+    let mut custody_aprs = Vec::with_capacity(custodies_len);
+    let mut pool_lp_fees_usd: u128 = 0;
+    for (idx, &custody_key) in pool.custodies.iter().enumerate() {
+        let custody_info = &ctx.remaining_accounts[idx];
+        let oracle_info = &ctx.remaining_accounts[idx + custodies_len];
+        require_keys_eq!(custody_info.key(), custody_key);
+        let custody = Account::<Custody>::try_from(custody_info)?;
+        require_keys_eq!(
+            oracle_info.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+
+        // Fees earned by liquidity providers (net of protocol share)
+        let lp_fees_usd = custody.fees.get_lp_fees_usd(&custody.collected_fees)?;
+        pool_lp_fees_usd = math::checked_add(pool_lp_fees_usd, lp_fees_usd)?;
+
+        let token_ema_price = custody.get_oracle_price(
+            oracle_info,
+            curtime,
+            custody.pricing.use_ema,
+            OracleOperation::Liquidity,
+        )?;
+
+        // Value liquid staking tokens at their fair value in underlying terms
+        let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+        let custody_value_usd = token_ema_price.get_asset_amount_usd(
+            custody.assets.owned,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128;
+
+        custody_aprs.push(get_apr(lp_fees_usd, custody_value_usd, elapsed_time)?);
+    }
+
+    let aum_usd =
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    let pool_apr = get_apr(pool_lp_fees_usd, aum_usd, elapsed_time)?;
+    msg!("Pool APR: {}", pool_apr);
+
+    Ok(PoolApr {
+        custody_aprs,
+        pool_apr,
+    })
+}
+
+fn get_apr(fees_usd: u128, value_usd: u128, elapsed_time: u128) -> Result<u64> {
+    if value_usd == 0 || elapsed_time == 0 {
+        return Ok(0);
+    }
+    math::checked_as_u64(math::checked_div(
+        math::checked_mul(
+            math::checked_mul(fees_usd, Perpetuals::BPS_POWER)?,
+            SECONDS_PER_YEAR,
+        )?,
+        math::checked_mul(elapsed_time, value_usd)?,
+    )?)
+}
@@ -0,0 +1,124 @@
+//! GetPoolCustodies instruction handler
+//!
+//! This is a view/query instruction that returns the key parameters of a page of pool
+//! custodies (mint, token flags, oracle type, ratio against target, utilization and
+//! open interest), so frontends can render the pool overview from a single simulated
+//! instruction. Results are paged to stay within the return data limit.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{CustodyOverview, Perpetuals, PoolCustodies},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying pool custodies
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetPoolCustodies<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+    // Remaining accounts (read-only, unsigned):
+    //   - a (custody, custody oracle account) pair for every custody of the page
+}
+
+/// Parameters for querying pool custodies
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetPoolCustodiesParams {
+    /// Index of the first custody of the page in the pool custodies
+    pub start_index: u8,
+    /// Maximum number of custodies to return (capped at MAX_PAGE_SIZE)
+    pub max_count: u8,
+}
+
+/// Maximum number of custodies returned by a single call
+pub const MAX_PAGE_SIZE: usize = 8;
+
+/// Get key parameters of a page of pool custodies (view function)
+///
+/// Current ratios are computed against the last recorded pool AUM.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `params` - Page start and size
+///
+/// # Returns
+/// `PoolCustodies` struct containing the custody count and the page of overviews
+pub fn get_pool_custodies<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetPoolCustodies<'info>>,
+    params: &GetPoolCustodiesParams,
+) -> Result<PoolCustodies> {
+    let pool = &ctx.accounts.pool;
+    let start = std::cmp::min(params.start_index as usize, pool.custodies.len());
+    let end = std::cmp::min(
+        start + std::cmp::min(params.max_count as usize, MAX_PAGE_SIZE),
+        pool.custodies.len(),
+    );
+    if ctx.remaining_accounts.len() != (end - start) * 2 {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let mut custodies = Vec::with_capacity(end - start);
+    for (token_id, pair) in (start..end).zip(ctx.remaining_accounts.chunks(2)) {
+        let (custody_info, oracle_info) = (&pair[0], &pair[1]);
+        require_keys_eq!(
+            custody_info.key(),
+            pool.custodies[token_id],
+            PerpetualsError::InvalidCustodyState
+        );
+        let custody = Account::<Custody>::try_from(custody_info)?;
+        require_keys_eq!(
+            oracle_info.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+
+        let token_ema_price = custody.get_oracle_price(
+            oracle_info,
+            curtime,
+            custody.pricing.use_ema,
+            OracleOperation::Liquidity,
+        )?;
+        let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
+        custodies.push(CustodyOverview {
+            custody: custody_info.key(),
+            mint: custody.mint,
+            decimals: custody.decimals,
+            is_stable: custody.is_stable,
+            is_virtual: custody.is_virtual,
+            oracle_type: custody.oracle.oracle_type,
+            current_ratio: pool.get_current_ratio(&custody, &token_ema_price)?,
+            target_ratio: pool.ratios[token_id].target,
+            utilization: custody.get_locked_breakdown()?.utilization,
+            oi_long_usd: custody.trade_stats.oi_long_usd,
+            oi_short_usd: custody.trade_stats.oi_short_usd,
+        });
+    }
+
+    Ok(PoolCustodies {
+        total: math::checked_as_u8(pool.custodies.len())?,
+        custodies,
+    })
+}
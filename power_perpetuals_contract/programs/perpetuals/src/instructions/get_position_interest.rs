@@ -0,0 +1,90 @@
+//! GetPositionInterest instruction handler
+//!
+//! This is a view/query instruction that returns the borrow interest a position has
+//! accrued since its cumulative interest snapshot, i.e. the interest that would be
+//! charged if the position was closed now.
+
+use {
+    crate::state::{
+        custody::Custody,
+        perpetuals::{Perpetuals, PositionInterest},
+        pool::Pool,
+        position::Position,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying position interest
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetPositionInterest<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to query (read-only)
+    #[account(
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Custody account for the collateral token, accrues the interest (read-only)
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for querying position interest
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetPositionInterestParams {}
+
+/// Get the interest accrued by a position since its snapshot (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `PositionInterest` struct containing the accrued interest and the custody rate state
+pub fn get_position_interest(
+    ctx: Context<GetPositionInterest>,
+    _params: &GetPositionInterestParams,
+) -> Result<PositionInterest> {
+    let position = &ctx.accounts.position;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    Ok(PositionInterest {
+        interest_usd: collateral_custody.get_interest_amount_usd(position, curtime)?,
+        cumulative_interest_snapshot: position.cumulative_interest_snapshot,
+        cumulative_interest: collateral_custody.get_cumulative_interest(curtime)?,
+        borrow_rate: collateral_custody.borrow_rate_state.current_rate,
+    })
+}
@@ -0,0 +1,166 @@
+//! GetPositionRisk instruction handler
+//!
+//! This is a view/query instruction that returns the health factor and risk tier
+//! of a position at current prices, using the same definition as the tier stored
+//! on the position and emitted on liquidation attempts.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, PositionRisk},
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying position risk
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetPositionRisk<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to query (read-only)
+    #[account(
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token (read-only)
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for querying position risk
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetPositionRiskParams {}
+
+/// Get health factor and risk tier of a position (view function)
+///
+/// The health factor is max leverage over current leverage in BPS, it drops below
+/// BPS_POWER when the position becomes liquidatable. Tiers use the custody
+/// `risk_warning_health` and `risk_danger_health` thresholds.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `PositionRisk` struct containing leverage, max leverage, health factor and tier
+pub fn get_position_risk(
+    ctx: Context<GetPositionRisk>,
+    _params: &GetPositionRiskParams,
+) -> Result<PositionRisk> {
+    // Get account references
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // Get position token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+
+    // Get collateral token prices from oracle (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    let liquidation_check = ctx.accounts.pool.get_liquidation_check(
+        &ctx.accounts.position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+
+    Ok(PositionRisk {
+        leverage: liquidation_check.leverage,
+        max_leverage: liquidation_check.max_leverage,
+        health_factor: liquidation_check.health_factor,
+        risk_tier: liquidation_check.risk_tier,
+    })
+}
@@ -0,0 +1,111 @@
+//! GetProtocolConfig instruction handler
+//!
+//! This is a view/query instruction that returns the program-level configuration:
+//! protocol permissions, the risk oracle, the multisig threshold and signers, and the
+//! fee and access configuration of a page of registered pools. Explorers and SDKs can
+//! introspect a deployment from a single simulated instruction instead of decoding
+//! account layouts. Pools are paged to stay within the return data limit.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            multisig::Multisig,
+            perpetuals::{Perpetuals, PoolConfigOverview, ProtocolConfig},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying the protocol configuration
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetProtocolConfig<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Multisig account (read-only)
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+    // Remaining accounts (read-only, unsigned):
+    //   - a pool account for every pool of the page
+}
+
+/// Parameters for querying the protocol configuration
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetProtocolConfigParams {
+    /// Index of the first pool of the page in the registered pools
+    pub start_index: u16,
+    /// Maximum number of pools to return (capped at MAX_POOLS_PAGE_SIZE)
+    pub max_count: u8,
+}
+
+/// Maximum number of pools returned by a single call
+pub const MAX_POOLS_PAGE_SIZE: usize = 4;
+
+/// Get the program-level configuration and a page of registered pools (view function)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `params` - Pool page start and size
+///
+/// # Returns
+/// `ProtocolConfig` struct containing the protocol settings and the page of pools
+pub fn get_protocol_config<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetProtocolConfig<'info>>,
+    params: &GetProtocolConfigParams,
+) -> Result<ProtocolConfig> {
+    let perpetuals = &ctx.accounts.perpetuals;
+    let start = std::cmp::min(params.start_index as usize, perpetuals.pools.len());
+    let end = std::cmp::min(
+        start + std::cmp::min(params.max_count as usize, MAX_POOLS_PAGE_SIZE),
+        perpetuals.pools.len(),
+    );
+    if ctx.remaining_accounts.len() != end - start {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    let mut pools = Vec::with_capacity(end - start);
+    for (pool_id, pool_info) in (start..end).zip(ctx.remaining_accounts.iter()) {
+        require_keys_eq!(
+            pool_info.key(),
+            perpetuals.pools[pool_id],
+            PerpetualsError::InvalidPoolState
+        );
+        let pool = Account::<Pool>::try_from(pool_info)?;
+        pools.push(PoolConfigOverview {
+            pool: pool_info.key(),
+            name: pool.name.clone(),
+            custodies: math::checked_as_u8(pool.custodies.len())?,
+            fee_custody: pool.fee_custody,
+            performance_fee_bps: pool.performance_fee_bps,
+            performance_fee_account: pool.performance_fee_account,
+            backstop_fee_boost_bps: pool.backstop.fee_boost_bps,
+            lp_allowlist_enabled: pool.lp_allowlist_enabled,
+            compliance_freeze_enabled: pool.compliance_freeze_enabled,
+            allow_cpi: pool.allow_cpi,
+            max_lp_supply: pool.max_lp_supply,
+        });
+    }
+
+    let multisig = ctx.accounts.multisig.load()?;
+    Ok(ProtocolConfig {
+        permissions: perpetuals.permissions,
+        allow_cpi: perpetuals.allow_cpi,
+        risk_oracle: perpetuals.risk_oracle,
+        min_signatures: multisig.min_signatures,
+        signers: multisig.signers[..multisig.num_signers as usize].to_vec(),
+        total_pools: math::checked_as_u16(perpetuals.pools.len())?,
+        pools,
+    })
+}
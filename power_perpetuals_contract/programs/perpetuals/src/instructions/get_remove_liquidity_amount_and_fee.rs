@@ -7,10 +7,12 @@
 
 use {
     crate::{
-        math,
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
         state::{
             custody::Custody,
-            oracle::OraclePrice,
+            lp_ledger::LpLedger,
+            oracle::OracleOperation,
             perpetuals::{AmountAndFee, Perpetuals},
             pool::{AumCalcMode, Pool},
         },
@@ -53,7 +55,7 @@ pub struct GetRemoveLiquidityAmountAndFee<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -64,6 +66,15 @@ pub struct GetRemoveLiquidityAmountAndFee<'info> {
         bump = pool.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// Deposit ledger of the LP owner, to include the decaying fee of young deposits
+    #[account(
+        seeds = [b"lp_ledger",
+                 pool.key().as_ref(),
+                 lp_ledger.owner.as_ref()],
+        bump = lp_ledger.bump
+    )]
+    pub lp_ledger: Option<Box<Account<'info, LpLedger>>>,
 }
 
 /// Parameters for querying remove liquidity amount and fee
@@ -106,23 +117,29 @@ pub fn get_remove_liquidity_amount_and_fee<'info>(
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     // Get token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidity,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidity,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
     // Calculate pool AUM using Min mode (conservative estimate)
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?;
+    // LP tokens are priced from the AUM left after the backstop tranche value
+    let pool_amount_usd = pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?,
+    );
 
     // Calculate USD value of LP tokens being redeemed
     // Formula: remove_amount_usd = (pool_aum_usd * lp_amount_in) / lp_supply
@@ -139,12 +156,33 @@ pub fn get_remove_liquidity_amount_and_fee<'info>(
         token_ema_price
     };
     // Convert USD amount to token amount using maximum price
-    let remove_amount = max_price.get_token_amount(remove_amount_usd, custody.decimals)?;
+    let remove_amount = max_price.get_token_amount(
+        remove_amount_usd,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
 
     // Calculate remove liquidity fee
-    let fee_amount =
+    let mut fee_amount =
         pool.get_remove_liquidity_fee(token_id, remove_amount, custody, &token_price)?;
 
+    // Add the decaying fee of young deposits, LP tokens without a ledger pay the full fee
+    if custody.is_lp_fee_decay_enabled() {
+        let early_fee = match ctx.accounts.lp_ledger.as_ref() {
+            Some(lp_ledger) => lp_ledger.get_early_remove_fee(
+                params.lp_amount_in,
+                custody.fees.early_remove_liquidity,
+                custody.pricing.lp_fee_decay_period,
+                curtime,
+            )?,
+            None => custody.fees.early_remove_liquidity,
+        };
+        fee_amount = std::cmp::min(
+            math::checked_add(fee_amount, Pool::get_fee_amount(early_fee, remove_amount)?)?,
+            remove_amount,
+        );
+    }
+
     // Calculate amount to transfer after deducting fee
     let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
 
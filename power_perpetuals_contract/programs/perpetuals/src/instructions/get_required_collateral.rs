@@ -0,0 +1,352 @@
+//! GetRequiredCollateral instruction handler
+//!
+//! This is a view/query instruction that returns the minimum collateral needed to open
+//! a position of a given size at a target leverage. It prices the position the same way
+//! open_position does (entry spread, exit fee and interest in the margin, min collateral
+//! price), so order forms can fill in the collateral instead of retrying against
+//! leverage errors.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        instructions::open_position::SizeMode,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::{Perpetuals, RequiredCollateral},
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying the required collateral
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetRequiredCollateral<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the position token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for querying the required collateral
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetRequiredCollateralParams {
+    /// Position size, in tokens or USD depending on size_mode
+    pub size: u64,
+    /// Position side
+    pub side: Side,
+    /// Unit of the position size
+    pub size_mode: SizeMode,
+    /// Position power (1-5)
+    pub power: u8,
+    /// Target leverage in BPS (0 = max initial leverage for the power)
+    pub leverage: u64,
+}
+
+/// Calculate the minimum collateral to open a position at a target leverage (view function)
+///
+/// The process:
+/// 1. Computes entry price, size, locked amount and borrow size as open_position does
+/// 2. Computes the PnL of the position right after opening (spread, exit fee)
+/// 3. Derives the collateral USD value giving the target leverage and converts it to
+///    collateral tokens at the min collateral price
+/// 4. Checks the resulting position against the initial leverage limits
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `params` - Position size, side, power and target leverage
+///
+/// # Returns
+/// `RequiredCollateral` struct containing the collateral amount, its USD value, the entry
+/// fee charged on top of it and the resulting leverage
+pub fn get_required_collateral(
+    ctx: Context<GetRequiredCollateral>,
+    params: &GetRequiredCollateralParams,
+) -> Result<RequiredCollateral> {
+    // Validate inputs
+    if params.size == 0 || params.side == Side::None {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    ctx.accounts.custody.pricing.check_power(params.power)?;
+    let pool = &ctx.accounts.pool;
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    let (max_initial_leverage, _) = Pool::get_power_leverage_limits(params.power, custody);
+    let target_leverage = if params.leverage == 0 {
+        max_initial_leverage
+    } else {
+        params.leverage
+    };
+
+    // Get current time for calculations
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // Get position token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Get collateral token prices from oracle (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Collateral is valued at the min price on open
+    let min_collateral_price = collateral_token_price
+        .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
+
+    // Calculate entry price (applies spread based on position side)
+    let entry_price =
+        pool.get_entry_price(&token_price, &token_ema_price, params.side, custody, curtime)?;
+    let position_oracle_price = OraclePrice {
+        price: entry_price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+
+    // Calculate position size in tokens and USD (same conversion as open_position)
+    let (size, size_usd) = match params.size_mode {
+        SizeMode::Tokens => (
+            params.size,
+            position_oracle_price.get_asset_amount_usd(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+        ),
+        SizeMode::Usd => (
+            position_oracle_price.get_token_amount(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            params.size,
+        ),
+    };
+
+    // Calculate locked amount and borrow size (same as open_position)
+    let use_collateral_custody = params.side == Side::Short || custody.is_virtual;
+    let locked_amount = if use_collateral_custody {
+        custody.get_locked_amount(
+            min_collateral_price.get_token_amount(
+                size_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            params.side,
+            params.power,
+            curtime,
+        )?
+    } else {
+        custody.get_locked_amount(size, params.side, params.power, curtime)?
+    };
+
+    let borrow_size_usd = if custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER {
+        if use_collateral_custody {
+            let max_collateral_price = if collateral_token_price < collateral_token_ema_price {
+                collateral_token_ema_price
+            } else {
+                collateral_token_price
+            };
+            max_collateral_price.get_asset_amount_usd(
+                locked_amount,
+                collateral_custody.decimals,
+                RoundingDirection::Down,
+            )?
+        } else {
+            position_oracle_price.get_asset_amount_usd(
+                locked_amount,
+                custody.decimals,
+                RoundingDirection::Down,
+            )?
+        }
+    } else {
+        size_usd
+    };
+
+    let mut position = Position {
+        side: params.side,
+        power: params.power,
+        price: entry_price,
+        size_usd,
+        borrow_size_usd,
+        collateral_usd: size_usd,
+        locked_amount,
+        cumulative_interest_snapshot: collateral_custody.get_cumulative_interest(curtime)?,
+        interest_epoch: collateral_custody.interest_epoch.id,
+        ..Position::default()
+    };
+
+    // PnL right after opening, the margin is collateral + profit - loss
+    let (profit_usd, loss_usd, _) = pool.get_pnl_usd(
+        &position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    // margin_usd = size_usd / leverage, rounded up to stay within the target
+    require_gt!(target_leverage, 0, PerpetualsError::MaxInitialLeverage);
+    let margin_usd = math::checked_as_u64(math::checked_ceil_div(
+        math::checked_mul(size_usd as u128, Perpetuals::BPS_POWER)?,
+        target_leverage as u128,
+    )?)?;
+    let collateral_usd = std::cmp::max(
+        math::checked_add(margin_usd, loss_usd)?.saturating_sub(profit_usd),
+        1,
+    );
+    let mut collateral = min_collateral_price.get_token_amount(
+        collateral_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Up,
+    )?;
+    position.collateral_usd = min_collateral_price.get_asset_amount_usd(
+        collateral,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    // one more token if the conversion back rounded below the required value
+    if position.collateral_usd < collateral_usd {
+        collateral = math::checked_add(collateral, 1)?;
+        position.collateral_usd = min_collateral_price.get_asset_amount_usd(
+            collateral,
+            collateral_custody.decimals,
+            RoundingDirection::Down,
+        )?;
+    }
+
+    // The target must be within the initial leverage limits of the power
+    if let Some(error) = pool.get_leverage_error(
+        &position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )? {
+        return Err(error.into());
+    }
+    let leverage = pool.get_leverage(
+        &position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+
+    // Entry fee, charged on top of the collateral (same as open_position)
+    let mut fee = pool.get_entry_fee(
+        custody.fees.open_position,
+        &custody.entry_fee_tiers,
+        size,
+        size_usd,
+        locked_amount,
+        collateral_custody,
+    )?;
+    fee = pool.apply_imbalance_fee(fee, size, size_usd, params.side, custody)?;
+    if use_collateral_custody {
+        let fee_amount_usd =
+            token_ema_price.get_asset_amount_usd(fee, custody.decimals, RoundingDirection::Up)?;
+        fee = collateral_token_ema_price.get_token_amount(
+            fee_amount_usd,
+            collateral_custody.decimals,
+            RoundingDirection::Up,
+        )?;
+    }
+
+    Ok(RequiredCollateral {
+        collateral,
+        collateral_usd: position.collateral_usd,
+        fee,
+        leverage,
+    })
+}
@@ -5,14 +5,16 @@
 //! before executing it, helping them understand the costs and expected returns.
 
 use {
-    crate::state::{
-        custody::Custody,
-        oracle::OraclePrice,
-        perpetuals::{Perpetuals, SwapAmountAndFees},
-        pool::Pool,
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, SwapAmountAndFees},
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
-    anchor_lang::error::ErrorCode::ConstraintRaw,
 };
 
 /// Accounts required for querying swap amount and fees
@@ -49,7 +51,7 @@ pub struct GetSwapAmountAndFees<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account
+        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub receiving_custody_oracle_account: AccountInfo<'info>,
 
@@ -66,7 +68,7 @@ pub struct GetSwapAmountAndFees<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub dispensing_custody_oracle_account: AccountInfo<'info>,
 }
@@ -120,43 +122,53 @@ pub fn get_swap_amount_and_fees(
     let dispensing_custody = &ctx.accounts.dispensing_custody;
 
     // Get input token prices from oracle (spot and EMA)
-    let received_token_price = OraclePrice::new_from_oracle(
+    let received_token_price = receiving_custody.get_oracle_price(
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let received_token_ema_price = OraclePrice::new_from_oracle(
+    let received_token_ema_price = receiving_custody.get_oracle_price(
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
         curtime,
         receiving_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let received_token_price = receiving_custody.get_fair_price(&received_token_price, curtime)?;
+    let received_token_ema_price =
+        receiving_custody.get_fair_price(&received_token_ema_price, curtime)?;
+
     // Get output token prices from oracle (spot and EMA)
-    let dispensed_token_price = OraclePrice::new_from_oracle(
+    let dispensed_token_price = dispensing_custody.get_oracle_price(
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let dispensed_token_ema_price = OraclePrice::new_from_oracle(
+    let dispensed_token_ema_price = dispensing_custody.get_oracle_price(
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
         curtime,
         dispensing_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let dispensed_token_price = dispensing_custody.get_fair_price(&dispensed_token_price, curtime)?;
+    let dispensed_token_ema_price =
+        dispensing_custody.get_fair_price(&dispensed_token_ema_price, curtime)?;
+
     // Calculate output token amount based on oracle prices and swap algorithm
     let amount_out = pool.get_swap_amount(
         &received_token_price,
@@ -166,6 +178,7 @@ pub fn get_swap_amount_and_fees(
         receiving_custody,
         dispensing_custody,
         params.amount_in,
+        curtime,
     )?;
 
     // Calculate swap fees
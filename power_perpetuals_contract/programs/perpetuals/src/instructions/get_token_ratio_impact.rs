@@ -0,0 +1,140 @@
+//! GetTokenRatioImpact instruction handler
+//!
+//! This is a view/query instruction that previews how a prospective deposit or
+//! withdrawal of a custody token would move its share of the pool, and whether
+//! the operation would pass the token ratio check. Frontends can use it to disable
+//! inputs that would otherwise revert with TokenRatioOutOfRange.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            oracle::OracleOperation,
+            perpetuals::{Perpetuals, TokenRatioImpact},
+            pool::{AumCalcMode, Pool, RatioFlow},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for querying token ratio impact
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct GetTokenRatioImpact<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account to query (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the token being added or removed (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the custody token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+    // Remaining accounts (read-only, unsigned):
+    //   - pool.custodies.len() custody accounts (for AUM calculation)
+    //   - pool.custodies.len() custody oracle accounts (for price feeds)
+}
+
+/// Parameters for querying token ratio impact
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetTokenRatioImpactParams {
+    /// Amount of tokens added to the pool (0 if removing)
+    pub amount_add: u64,
+    /// Amount of tokens removed from the pool (0 if adding)
+    pub amount_remove: u64,
+}
+
+/// Calculate token ratio impact of a deposit or withdrawal (view function)
+///
+/// For add_liquidity pass the deposited amount net of protocol fee, for
+/// remove_liquidity the withdrawn amount including protocol fee. A swap is
+/// checked as a deposit into the input custody and a withdrawal from the output
+/// custody, so it is previewed with one call per leg.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `params` - Amount added or removed
+///
+/// # Returns
+/// `TokenRatioImpact` struct containing current and projected ratios, the
+/// configured bounds, and whether check_token_ratio would pass
+pub fn get_token_ratio_impact<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetTokenRatioImpact<'info>>,
+    params: &GetTokenRatioImpactParams,
+) -> Result<TokenRatioImpact> {
+    // Validate inputs
+    if params.amount_add > 0 && params.amount_remove > 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    let custody = &ctx.accounts.custody;
+    let token_id = ctx.accounts.pool.get_token_id(&custody.key())?;
+
+    // Get current time for price calculations
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // Refresh pool AUM the same way liquidity instructions do before the ratio check
+    let mut pool = Pool::clone(&ctx.accounts.pool);
+    pool.aum_usd =
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+
+    // Get token EMA price from oracle
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidity,
+    )?;
+
+    // Value liquid staking tokens at their fair value in underlying terms
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
+    let ratios = pool.ratios[token_id];
+    Ok(TokenRatioImpact {
+        current_ratio: pool.get_current_ratio(custody, &token_ema_price)?,
+        new_ratio: pool.get_new_ratio(
+            params.amount_add,
+            params.amount_remove,
+            custody,
+            &token_ema_price,
+        )?,
+        target: ratios.target,
+        min: ratios.min,
+        max: ratios.max,
+        allowed: pool.check_token_ratio(
+            if params.amount_remove > 0 {
+                RatioFlow::RemoveLiquidity
+            } else {
+                RatioFlow::AddLiquidity
+            },
+            token_id,
+            params.amount_add,
+            params.amount_remove,
+            custody,
+            &token_ema_price,
+        )?,
+    })
+}
@@ -0,0 +1,195 @@
+//! ImportPoolPositions instruction handler
+//!
+//! This instruction re-creates a chunk of open positions of a pool exported with
+//! export_pool_state under the target pool. Position accounts are derived from the pool
+//! and custody keys, so each position is copied to its new address with the pool and
+//! custodies remapped, and the source position is closed. Custodies have to be imported
+//! with import_pool_state first. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            pool_migration::PoolMigration,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for importing positions into the target pool
+#[derive(Accounts)]
+pub struct ImportPoolPositions<'info> {
+    /// Admin account that must sign (must be part of multisig), pays for the new
+    /// positions and receives the rent of the closed ones
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool being migrated
+    #[account(
+        seeds = [b"pool",
+                 source_pool.name.as_bytes()],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Box<Account<'info, Pool>>,
+
+    /// Pool the positions are imported into
+    #[account(
+        seeds = [b"pool",
+                 target_pool.name.as_bytes()],
+        bump = target_pool.bump
+    )]
+    pub target_pool: Box<Account<'info, Pool>>,
+
+    /// Pool migration account
+    #[account(
+        mut,
+        has_one = source_pool,
+        has_one = target_pool,
+        seeds = [b"pool_migration",
+                 source_pool.key().as_ref()],
+        bump = pool_migration.bump
+    )]
+    pub pool_migration: Box<Account<'info, PoolMigration>>,
+
+    system_program: Program<'info, System>,
+    // Remaining accounts (pairs):
+    //   - source position account (mutable, closed)
+    //   - target position account (mutable, uninitialized PDA derived from the target pool)
+}
+
+/// Parameters for importing positions into the target pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ImportPoolPositionsParams {}
+
+/// Import a chunk of positions of an exported pool into the target pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. For each source and target position pair:
+///    - Validates the source position belongs to the source pool
+///    - Validates the target address derives from the target pool and mapped custodies
+///    - Creates the target position with the pool and custodies remapped
+///    - Closes the source position
+/// 3. Advances the migrated positions counter
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn import_pool_positions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ImportPoolPositions<'info>>,
+    params: &ImportPoolPositionsParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ImportPoolPositions, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    if ctx.remaining_accounts.is_empty() || !ctx.remaining_accounts.len().is_multiple_of(2) {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    let source_pool_key = ctx.accounts.source_pool.key();
+    let target_pool_key = ctx.accounts.target_pool.key();
+    let mut imported: u64 = 0;
+
+    for accounts in ctx.remaining_accounts.chunks(2) {
+        let source_position = Account::<Position>::try_from(&accounts[0])?;
+        let target_info = &accounts[1];
+        require_keys_eq!(source_position.pool, source_pool_key);
+
+        // Remap pool and custodies
+        let pool_migration = ctx.accounts.pool_migration.as_ref();
+        let mut position = Position::clone(&source_position);
+        position.pool = target_pool_key;
+        position.custody = pool_migration.get_target_custody(&source_position.custody)?;
+        position.collateral_custody =
+            pool_migration.get_target_custody(&source_position.collateral_custody)?;
+
+        let (target_key, bump) = Pubkey::find_program_address(
+            &[
+                b"position",
+                position.owner.as_ref(),
+                target_pool_key.as_ref(),
+                position.custody.as_ref(),
+                &[position.side as u8],
+            ],
+            &crate::ID,
+        );
+        require_keys_eq!(target_info.key(), target_key);
+        require!(
+            Perpetuals::is_empty_account(target_info)?,
+            PerpetualsError::InvalidPositionState
+        );
+        position.bump = bump;
+
+        // Create the target position
+        Perpetuals::create_account(
+            ctx.accounts.admin.to_account_info(),
+            target_info.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            Position::LEN,
+            &[&[
+                b"position",
+                position.owner.as_ref(),
+                target_pool_key.as_ref(),
+                position.custody.as_ref(),
+                &[position.side as u8],
+                &[bump],
+            ]],
+        )?;
+        position.try_serialize(&mut &mut target_info.try_borrow_mut_data()?[..])?;
+
+        source_position.close(ctx.accounts.admin.to_account_info())?;
+        imported = math::checked_add(imported, 1)?;
+    }
+
+    // Advance migration cursor
+    let pool_migration = ctx.accounts.pool_migration.as_mut();
+    pool_migration.positions_migrated =
+        math::checked_add(pool_migration.positions_migrated, imported)?;
+    require!(
+        pool_migration.positions_migrated <= pool_migration.positions_total,
+        PerpetualsError::InvalidPoolState
+    );
+    msg!(
+        "Positions migrated: {}/{}",
+        pool_migration.positions_migrated,
+        pool_migration.positions_total
+    );
+
+    Ok(0)
+}
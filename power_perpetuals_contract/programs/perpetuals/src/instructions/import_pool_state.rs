@@ -0,0 +1,243 @@
+//! ImportPoolState instruction handler
+//!
+//! This instruction imports one custody of a pool exported with export_pool_state into
+//! the target pool. The custody is re-created under the target pool with its
+//! configuration and stats carried over, and the custody token balance is moved to the
+//! new custody token account. Custodies are imported one per transaction in source pool
+//! order, so token ratios stay aligned. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::{Permissions, Perpetuals},
+            pool::{Pool, TokenRatios},
+            pool_migration::PoolMigration,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for importing a custody into the target pool
+#[derive(Accounts)]
+pub struct ImportPoolState<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool being migrated
+    #[account(
+        seeds = [b"pool",
+                 source_pool.name.as_bytes()],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Box<Account<'info, Pool>>,
+
+    /// Custody of the source pool being imported
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 source_pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump = source_custody.bump
+    )]
+    pub source_custody: Box<Account<'info, Custody>>,
+
+    /// Token account of the source custody (emptied by the import)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 source_pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump = source_custody.token_account_bump
+    )]
+    pub source_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Pool the state is imported into (reallocated to fit the new custody)
+    #[account(
+        mut,
+        realloc = Pool::LEN + (target_pool.custodies.len() + 1) * std::mem::size_of::<Pubkey>() +
+                              (target_pool.ratios.len() + 1) * std::mem::size_of::<TokenRatios>(),
+        realloc::payer = admin,
+        realloc::zero = false,
+        seeds = [b"pool",
+                 target_pool.name.as_bytes()],
+        bump = target_pool.bump
+    )]
+    pub target_pool: Box<Account<'info, Pool>>,
+
+    /// Pool migration account
+    #[account(
+        mut,
+        has_one = source_pool,
+        has_one = target_pool,
+        seeds = [b"pool_migration",
+                 source_pool.key().as_ref()],
+        bump = pool_migration.bump
+    )]
+    pub pool_migration: Box<Account<'info, PoolMigration>>,
+
+    /// Custody account of the target pool (PDA derived from target pool and token mint)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Custody::LEN,
+        seeds = [b"custody",
+                 target_pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub target_custody: Box<Account<'info, Custody>>,
+
+    /// Token account of the target custody (receives the source custody balance)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = custody_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"custody_token_account",
+                 target_pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub target_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Mint of the imported custody
+    #[account()]
+    pub custody_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for importing a custody into the target pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ImportPoolStateParams {}
+
+/// Import one custody of an exported pool into the target pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the custody is the next one to import in source pool order
+/// 3. Moves the custody token balance and checks it covers the custody assets
+/// 4. Re-creates the custody under the target pool (kept frozen until finalization)
+/// 5. Appends the custody and its token ratio to the target pool
+/// 6. Clears the source custody assets and records the mapping
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn import_pool_state<'info>(
+    ctx: Context<'_, '_, '_, 'info, ImportPoolState<'info>>,
+    params: &ImportPoolStateParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ImportPoolState, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate import order
+    let source_custody_key = ctx.accounts.source_custody.key();
+    let token_id = ctx.accounts.source_pool.get_token_id(&source_custody_key)?;
+    require!(
+        ctx.accounts.target_pool.custodies.len() == token_id
+            && ctx.accounts.pool_migration.custodies[token_id].target == Pubkey::default(),
+        PerpetualsError::InvalidPoolState
+    );
+
+    // Move custody balance
+    msg!("Transfer tokens");
+    let source_custody = ctx.accounts.source_custody.as_ref();
+    let balance = ctx.accounts.source_custody_token_account.amount;
+    require!(
+        balance
+            >= math::checked_add(
+                math::checked_add(
+                    source_custody.assets.owned,
+                    source_custody.assets.collateral
+                )?,
+                source_custody.assets.protocol_fees
+            )?,
+        PerpetualsError::InvalidCustodyState
+    );
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.source_custody_token_account.to_account_info(),
+        ctx.accounts.target_custody_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        balance,
+    )?;
+
+    // Re-create custody under the target pool
+    msg!("Import custody");
+    let mut target_custody_data = Custody::clone(source_custody);
+    target_custody_data.pool = ctx.accounts.target_pool.key();
+    target_custody_data.token_account = ctx.accounts.target_custody_token_account.key();
+    target_custody_data.permissions = Permissions::default();
+    target_custody_data.allow_cpi = false;
+    target_custody_data.bump = ctx.bumps.target_custody;
+    target_custody_data.token_account_bump = ctx.bumps.target_custody_token_account;
+    ctx.accounts.target_custody.set_inner(target_custody_data);
+
+    let target_pool = ctx.accounts.target_pool.as_mut();
+    target_pool
+        .custodies
+        .push(ctx.accounts.target_custody.key());
+    target_pool
+        .ratios
+        .push(ctx.accounts.source_pool.ratios[token_id]);
+
+    // Clear source custody assets, the tokens now belong to the target custody
+    let source_custody = ctx.accounts.source_custody.as_mut();
+    source_custody.assets.owned = 0;
+    source_custody.assets.collateral = 0;
+    source_custody.assets.protocol_fees = 0;
+    source_custody.assets.locked = 0;
+
+    ctx.accounts.pool_migration.custodies[token_id].target = ctx.accounts.target_custody.key();
+
+    Ok(0)
+}
@@ -92,6 +92,8 @@ pub struct InitParams {
     pub allow_collateral_withdrawal: bool,
     /// Allow changing position size
     pub allow_size_change: bool,
+    /// Allow open/close position and collateral withdrawal through CPI
+    pub allow_cpi: bool,
 }
 
 /// Initialize the perpetuals program
@@ -148,6 +150,7 @@ pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
     perpetuals.permissions.allow_pnl_withdrawal = params.allow_pnl_withdrawal;
     perpetuals.permissions.allow_collateral_withdrawal = params.allow_collateral_withdrawal;
     perpetuals.permissions.allow_size_change = params.allow_size_change;
+    perpetuals.allow_cpi = params.allow_cpi;
     
     // Record transfer_authority PDA bump
     // This is needed for token account authority derivations
@@ -8,17 +8,25 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        events::{LiquidationChecked, LockChanged},
+        invariants,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody,
-            oracle::OraclePrice,
+            custody::{Custody, FeeType},
+            oracle::OracleOperation,
             perpetuals::Perpetuals,
+            owner_positions::OwnerPositions,
             pool::Pool,
             position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
         },
     },
     anchor_lang::prelude::*,
-    anchor_spl::token::{Token, TokenAccount},
+    anchor_spl::{
+        associated_token::AssociatedToken,
+        token::{Mint, Token, TokenAccount},
+    },
 };
 
 /// Accounts required for liquidating a position
@@ -29,13 +37,16 @@ pub struct Liquidate<'info> {
     pub signer: Signer<'info>,
 
     /// Position owner's token account to receive remaining collateral after liquidation
-    /// Must be owned by position owner and have the same mint as collateral custody
+    /// Must be owned by position owner and have the same mint as collateral custody, and
+    /// be the payout account chosen by the owner. The owner ATA is created if it doesn't
+    /// exist and the optional creation accounts are passed.
+    ///
+    /// CHECK: Token account, validated in the handler after the optional creation
     #[account(
         mut,
-        constraint = receiving_account.mint == collateral_custody.mint,
-        constraint = receiving_account.owner == position.owner
+        constraint = position.is_payout_account(&receiving_account.key(), &collateral_custody.mint) @ PerpetualsError::InvalidPayoutAccount
     )]
-    pub receiving_account: Box<Account<'info, TokenAccount>>,
+    pub receiving_account: AccountInfo<'info>,
 
     /// Liquidator's token account to receive liquidation reward
     /// Must be owned by liquidator and have the same mint as collateral custody
@@ -96,7 +107,7 @@ pub struct Liquidate<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -111,7 +122,7 @@ pub struct Liquidate<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -127,6 +138,59 @@ pub struct Liquidate<'info> {
 
     /// Token program for token transfers
     pub token_program: Program<'info, Token>,
+
+    /// Optional open position counter of the owner
+    #[account(
+        mut,
+        seeds = [b"owner_positions",
+                 pool.key().as_ref(),
+                 position.owner.as_ref()],
+        bump = owner_positions.bump
+    )]
+    pub owner_positions: Option<Box<Account<'info, OwnerPositions>>>,
+
+    /// Optional summary of the position, marked closed
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+
+    /// Optional position owner, to create a missing receiving account
+    ///
+    /// CHECK: Only used as the ATA wallet, validated by constraint
+    #[account(
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: Option<AccountInfo<'info>>,
+
+    /// Optional collateral mint, to create a missing receiving account
+    #[account(
+        constraint = collateral_mint.key() == collateral_custody.mint
+    )]
+    pub collateral_mint: Option<Box<Account<'info, Mint>>>,
+
+    /// Optional system program, to create a missing receiving account
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Optional associated token program, to create a missing receiving account
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
 }
 
 /// Parameters for liquidating a position
@@ -148,7 +212,9 @@ pub struct LiquidateParams {}
 /// 7. Updates custody and pool statistics
 /// 8. Removes position from custody tracking
 /// 
-/// Liquidation reward is calculated as a percentage of total amount out.
+/// Liquidation reward is calculated as a percentage of total amount out. If the pool runs
+/// liquidation auctions, the percentage ramps up from the auction start, see
+/// start_liquidation_auction.
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
@@ -157,6 +223,31 @@ pub struct LiquidateParams {}
 /// # Returns
 /// `Result<()>` - Success if position was liquidated successfully
 pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<()> {
+    // Create the owner ATA if the receiving account doesn't exist yet
+    let receiving_account = Perpetuals::load_or_create_receiving_account(
+        ctx.accounts.signer.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.owner.as_ref().map(|owner| owner.to_account_info()),
+        ctx.accounts
+            .collateral_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
+        ctx.accounts
+            .system_program
+            .as_ref()
+            .map(|program| program.to_account_info()),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts
+            .associated_token_program
+            .as_ref()
+            .map(|program| program.to_account_info()),
+    )?;
+    require!(
+        receiving_account.mint == ctx.accounts.collateral_custody.mint
+            && receiving_account.owner == ctx.accounts.position.owner,
+        PerpetualsError::InvalidReceivingAccount
+    );
+
     // Check permissions
     // Both perpetuals and custody must allow closing positions
     msg!("Check permissions");
@@ -167,6 +258,11 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
+    // Positions can be closed until the market is settled
+    require!(
+        custody.lifecycle.allows_close(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
 
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
@@ -176,55 +272,83 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     msg!("Check position state");
     let curtime = perpetuals.get_time()?;
 
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidate,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidate,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Validate that position exceeds maximum leverage (can be liquidated)
-    // check_leverage returns true if position is safe, false if it exceeds limits
-    // We require it to be false (unsafe) for liquidation
+    // Depending on the custody liquidation price mode, spot and EMA prices may both
+    // have to breach the threshold
+    let liquidation_check = pool.get_liquidation_check(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    emit!(LiquidationChecked {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        custody: custody.key(),
+        leverage: liquidation_check.leverage,
+        spot_leverage: liquidation_check.spot_leverage,
+        ema_leverage: liquidation_check.ema_leverage,
+        max_leverage: liquidation_check.max_leverage,
+        liquidation_price_mode: custody.pricing.liquidation_price_mode,
+        liquidatable: liquidation_check.liquidatable,
+        health_factor: liquidation_check.health_factor,
+        risk_tier: liquidation_check.risk_tier,
+        interest_usd: collateral_custody.get_interest_amount_usd(position, curtime)?,
+        time: curtime,
+    });
     require!(
-        !pool.check_leverage(
-            position,
-            &token_price,
-            &token_ema_price,
-            custody,
-            &collateral_token_price,
-            &collateral_token_ema_price,
-            collateral_custody,
-            curtime,
-            false
-        )?,
+        liquidation_check.liquidatable,
         PerpetualsError::InvalidPositionState
     );
 
@@ -245,17 +369,23 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
 
     // Convert fee to collateral token if needed
     // For shorts or virtual custodies, fee is calculated in position token, convert to collateral
-    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
     if position.side == Side::Short || custody.is_virtual {
         fee_amount = collateral_token_ema_price
-            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
     }
 
     msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
     msg!("Collected fee: {}", fee_amount);
 
-    // Calculate liquidation reward (percentage of total amount out)
-    let reward = Pool::get_fee_amount(custody.fees.liquidation, total_amount_out)?;
+    // Calculate liquidation reward (percentage of total amount out, ramped up over the
+    // liquidation auction)
+    let reward_rate = pool.get_liquidation_reward_rate(position, custody, Clock::get()?.slot)?;
+    let reward = Pool::get_fee_amount(reward_rate, total_amount_out)?;
     // Calculate amount to return to position owner (after deducting reward)
     let user_amount = math::checked_sub(total_amount_out, reward)?;
 
@@ -263,10 +393,22 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     msg!("Reward: {}", reward);
 
     // Unlock pool funds that were locked for this position
-    collateral_custody.unlock_funds(position.locked_amount)?;
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
 
     // Check pool constraints
-    // Ensure pool has enough funds to cover the liquidation
+    // Ensure pool has enough funds to cover the liquidation, token ratio bounds don't
+    // apply to liquidations (RatioFlow::Liquidation)
     msg!("Check pool constraints");
     require!(
         pool.check_available_amount(total_amount_out, collateral_custody)?,
@@ -300,22 +442,24 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     // Update custody statistics
     msg!("Update custody stats");
     // Track collected liquidation fees
-    collateral_custody.collected_fees.liquidation_usd = collateral_custody
-        .collected_fees
-        .liquidation_usd
-        .wrapping_add(fee_amount_usd);
+    collateral_custody.collected_fees.liquidation_usd = math::checked_add(
+        collateral_custody.collected_fees.liquidation_usd,
+        fee_amount_usd as u128,
+    )?;
 
     // Update owned assets based on PnL
     // If total_amount_out > collateral_amount, pool lost funds (subtract difference)
     // If total_amount_out < collateral_amount, pool gained funds (add difference)
+    // Profits are paid out of the pnl reserve first, liquidation gains feed it
     if total_amount_out > position.collateral_amount {
         let amount_lost = total_amount_out.saturating_sub(position.collateral_amount);
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
+        let principal_amount = collateral_custody.pay_profit(amount_lost)?;
+        msg!("Profit paid out of LP principal: {}", principal_amount);
     } else {
         let amount_gained = position.collateral_amount.saturating_sub(total_amount_out);
         collateral_custody.assets.owned =
             math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+        collateral_custody.add_pnl_reserve(amount_gained)?;
     }
     // Remove collateral amount from custody tracking
     collateral_custody.assets.collateral = math::checked_sub(
@@ -324,7 +468,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     )?;
 
     // Calculate and pay protocol fee if pool has sufficient funds
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    let protocol_fee = Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Liquidation), fee_amount)?;
 
     // Pay protocol_fee from custody if possible, otherwise no protocol_fee
     if pool.check_available_amount(protocol_fee, collateral_custody)? {
@@ -335,6 +479,12 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
     }
 
+    // Run the realized trader PnL through the backstop waterfall, trader losses
+    // include the fees
+    let protocol_fee_usd =
+        Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Liquidation), fee_amount_usd)?;
+    pool.record_backstop_pnl(loss_usd.saturating_sub(protocol_fee_usd), profit_usd)?;
+
     // Update trade statistics and remove position from tracking
     // If custody and collateral_custody accounts are the same (e.g., for long positions),
     // update collateral_custody stats and sync to custody
@@ -342,7 +492,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         // Track liquidation volume
         collateral_custody.volume_stats.liquidation_usd = math::checked_add(
             collateral_custody.volume_stats.liquidation_usd,
-            position.size_usd,
+            position.size_usd as u128,
         )?;
 
         // Update open interest (reduce by position size)
@@ -375,8 +525,10 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         *custody = collateral_custody.clone();
     } else {
         // Update custody stats (position token custody)
-        custody.volume_stats.liquidation_usd =
-            math::checked_add(custody.volume_stats.liquidation_usd, position.size_usd)?;
+        custody.volume_stats.liquidation_usd = math::checked_add(
+            custody.volume_stats.liquidation_usd,
+            position.size_usd as u128,
+        )?;
 
         // Update open interest
         if position.side == Side::Long {
@@ -401,5 +553,45 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    // A market winding down is settled once its last position is closed
+    custody.update_lifecycle();
+    collateral_custody.update_lifecycle();
+
+    // Stop counting the closed position
+    if let Some(owner_positions) = ctx.accounts.owner_positions.as_mut() {
+        owner_positions.remove_position();
+    }
+
+    // Mark the position summary closed
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.clear(curtime);
+    }
+
+    // Notify the pool position hook at the liquidation price
+    let exit_price =
+        pool.get_exit_price(&token_price, &token_ema_price, position.side, custody, curtime)?;
+    pool.position_hook.invoke(
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &PositionHookPayload {
+            event: PositionHookEvent::Liquidate,
+            pool: pool.key(),
+            position: position.key(),
+            owner: position.owner,
+            custody: custody.key(),
+            side: position.side,
+            size_usd: position.size_usd,
+            price: exit_price,
+            time: curtime,
+        },
+    )?;
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.collateral_custody,
+        &mut ctx.accounts.collateral_custody_token_account,
+    )?;
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,278 @@
+//! MigrateCustodyMint instruction handler
+//!
+//! This instruction executes a custody migration scheduled with schedule_custody_migration.
+//! Custody accounts are derived from their mint, so the migrated custody is re-created at
+//! the address of the new mint with its configuration and stats carried over, token
+//! balances rescaled to the new mint, a new oracle config for the new token, and the pool
+//! updated to point to it. The new custody
+//! token account must be pre-funded with the converted balances, while the old balances
+//! are released to the admin for conversion. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            custody_migration::CustodyMigration,
+            multisig::{AdminInstruction, Multisig},
+            oracle::OracleParams,
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for executing a custody migration
+#[derive(Accounts)]
+pub struct MigrateCustodyMint<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts (receives rent of closed accounts)
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        mut,
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, custody address will be replaced)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account being migrated (closed after migration)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Token account of the custody being migrated (closed after migration)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Custody migration account (closed after migration)
+    #[account(
+        mut,
+        has_one = custody,
+        has_one = new_mint,
+        seeds = [b"custody_migration",
+                 custody.key().as_ref()],
+        bump = custody_migration.bump
+    )]
+    pub custody_migration: Box<Account<'info, CustodyMigration>>,
+
+    /// Token account receiving old mint balances for conversion
+    #[account(
+        mut,
+        constraint = old_tokens_receiving_account.mint == custody.mint
+    )]
+    pub old_tokens_receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Mint the custody is migrated to
+    #[account()]
+    pub new_mint: Box<Account<'info, Mint>>,
+
+    /// Migrated custody account (PDA derived from pool and new mint), must not exist yet
+    ///
+    /// CHECK: Created once all signatures are collected, as an init constraint would fail
+    /// every signature after the first one
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 new_mint.key().as_ref()],
+        bump
+    )]
+    pub new_custody: AccountInfo<'info>,
+
+    /// Token account for the migrated custody, must be pre-funded with converted balances
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = new_mint,
+        token::authority = transfer_authority,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 new_mint.key().as_ref()],
+        bump
+    )]
+    pub new_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for executing a custody migration
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MigrateCustodyMintParams {
+    /// Oracle config of the migrated custody, the old one prices the old token
+    pub oracle: OracleParams,
+}
+
+/// Execute a scheduled custody migration
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the timelock has passed and the custody has no open positions or collateral
+/// 3. Builds the migrated custody with rescaled balances and the new oracle config, the
+///    custody for the new mint must not exist yet
+/// 4. Validates the new custody token account holds the converted balances
+/// 5. Releases old token balances to the admin and closes the old token account
+/// 6. Creates the custody for the new mint
+/// 7. Replaces the custody in the pool and restores custody permissions
+/// 8. Closes the old custody and migration accounts
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Oracle config of the migrated custody
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn migrate_custody_mint<'info>(
+    ctx: Context<'_, '_, '_, 'info, MigrateCustodyMint<'info>>,
+    params: &MigrateCustodyMintParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::MigrateCustodyMint, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate custody state
+    msg!("Validate custody state");
+    let custody = ctx.accounts.custody.as_ref();
+    let custody_migration = ctx.accounts.custody_migration.as_ref();
+    require!(
+        ctx.accounts.perpetuals.get_time()? >= custody_migration.execute_time,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.long_positions.open_positions == 0
+            && custody.short_positions.open_positions == 0
+            && custody.assets.collateral == 0
+            && custody.assets.locked == 0,
+        PerpetualsError::InvalidCustodyState
+    );
+
+    // Build the migrated custody
+    let mut new_custody_data = custody_migration.get_migrated_custody(
+        custody,
+        &ctx.accounts.new_custody,
+        ctx.accounts.new_custody_token_account.key(),
+        ctx.accounts.new_mint.decimals,
+        params.oracle,
+    )?;
+    new_custody_data.bump = ctx.bumps.new_custody;
+    new_custody_data.token_account_bump = ctx.bumps.new_custody_token_account;
+
+    // Validate pre-funded balances
+    let owned = new_custody_data.assets.owned;
+    let protocol_fees = new_custody_data.assets.protocol_fees;
+    msg!("Converted owned: {}, protocol fees: {}", owned, protocol_fees);
+    require!(
+        ctx.accounts.new_custody_token_account.amount
+            >= math::checked_add(owned, protocol_fees)?,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // Release old balances for conversion and close old token account
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.old_tokens_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.custody_token_account.amount,
+    )?;
+    Perpetuals::close_token_account(
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        &[&[
+            b"transfer_authority",
+            &[ctx.accounts.perpetuals.transfer_authority_bump],
+        ]],
+    )?;
+
+    // Create custody for the new mint
+    msg!("Migrate custody");
+    let new_custody = &ctx.accounts.new_custody;
+    Perpetuals::create_account(
+        ctx.accounts.admin.to_account_info(),
+        new_custody.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        Custody::LEN,
+        &[&[
+            b"custody",
+            ctx.accounts.pool.key().as_ref(),
+            new_custody_data.mint.as_ref(),
+            &[new_custody_data.bump],
+        ]],
+    )?;
+    new_custody_data.try_serialize(&mut &mut new_custody.try_borrow_mut_data()?[..])?;
+
+    // Replace custody in the pool
+    let pool = ctx.accounts.pool.as_mut();
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
+    pool.custodies[token_id] = ctx.accounts.new_custody.key();
+
+    // Close old custody and migration accounts
+    ctx.accounts
+        .custody
+        .close(ctx.accounts.transfer_authority.to_account_info())?;
+    ctx.accounts
+        .custody_migration
+        .close(ctx.accounts.transfer_authority.to_account_info())?;
+
+    Ok(0)
+}
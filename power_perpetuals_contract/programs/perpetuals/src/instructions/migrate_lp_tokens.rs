@@ -0,0 +1,144 @@
+//! MigrateLpTokens instruction handler
+//!
+//! This instruction allows LPs of a migrated pool to swap their source pool LP tokens
+//! 1:1 for target pool LP tokens once the migration was finalized. Source LP tokens are
+//! burned and target LP tokens are paid out of the migration LP vault.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{perpetuals::Perpetuals, pool::Pool, pool_migration::PoolMigration},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for migrating LP tokens
+#[derive(Accounts)]
+pub struct MigrateLpTokens<'info> {
+    /// Owner of the LP tokens (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's source pool LP token account (tokens will be burned)
+    #[account(
+        mut,
+        constraint = source_lp_token_account.mint == source_lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub source_lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// User's target pool LP token account (receives migrated tokens)
+    #[account(
+        mut,
+        constraint = target_lp_token_account.mint == lp_vault.mint
+    )]
+    pub target_lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool being migrated
+    #[account(
+        seeds = [b"pool",
+                 source_pool.name.as_bytes()],
+        bump = source_pool.bump
+    )]
+    pub source_pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint of the source pool (mutable, tokens will be burned)
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 source_pool.key().as_ref()],
+        bump = source_pool.lp_token_bump
+    )]
+    pub source_lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// Pool migration account
+    #[account(
+        has_one = source_pool,
+        seeds = [b"pool_migration",
+                 source_pool.key().as_ref()],
+        bump = pool_migration.bump
+    )]
+    pub pool_migration: Box<Account<'info, PoolMigration>>,
+
+    /// Vault holding the target LP tokens
+    #[account(
+        mut,
+        seeds = [b"pool_migration_lp",
+                 pool_migration.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+/// Parameters for migrating LP tokens
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MigrateLpTokensParams {
+    /// Amount of source LP tokens to migrate
+    pub amount: u64,
+}
+
+/// Swap source pool LP tokens 1:1 for target pool LP tokens
+///
+/// The process:
+/// 1. Validates the migration was finalized and the amount is non-zero
+/// 2. Burns the source LP tokens
+/// 3. Transfers the same amount of target LP tokens from the migration vault
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Amount of LP tokens to migrate
+///
+/// # Returns
+/// `Result<()>` - Success if LP tokens were migrated
+pub fn migrate_lp_tokens(
+    ctx: Context<MigrateLpTokens>,
+    params: &MigrateLpTokensParams,
+) -> Result<()> {
+    // Validate inputs
+    require!(
+        ctx.accounts.pool_migration.finalized,
+        PerpetualsError::InstructionNotAllowed
+    );
+    if params.amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // Swap LP tokens
+    msg!("Migrate LP tokens: {}", params.amount);
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    perpetuals.burn_tokens(
+        ctx.accounts.source_lp_token_mint.to_account_info(),
+        ctx.accounts.source_lp_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+    perpetuals.transfer_tokens(
+        ctx.accounts.lp_vault.to_account_info(),
+        ctx.accounts.target_lp_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    Ok(())
+}
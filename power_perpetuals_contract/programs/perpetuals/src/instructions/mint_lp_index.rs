@@ -0,0 +1,233 @@
+//! MintLpIndex instruction handler
+//!
+//! This instruction allows users to deposit pool LP tokens into an LP index in exchange
+//! for index tokens. The deposit must follow the index weights, and index tokens are
+//! minted pro rata to the USD value of the LP tokens already held, priced from the pool
+//! AUMs computed from the pool custodies and oracles. Permissioned component pools only
+//! accept allowlisted owners.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            lp_allowlist::LpAllowlist,
+            lp_index::LpIndex,
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for minting index tokens
+///
+/// Remaining accounts: for every index component in order, the pool, its LP token mint,
+/// the user's LP token account, the component vault, the pool custodies and their oracles,
+/// followed by the allowlist of every component pool with the allowlist enabled, in
+/// component order.
+#[derive(Accounts)]
+pub struct MintLpIndex<'info> {
+    /// Owner of the deposited LP tokens (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's index token account (receives minted index tokens)
+    #[account(
+        mut,
+        constraint = receiving_account.mint == index_token_mint.key()
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// LP index account
+    #[account(
+        seeds = [b"lp_index",
+                 lp_index.name.as_bytes()],
+        bump = lp_index.bump
+    )]
+    pub lp_index: Box<Account<'info, LpIndex>>,
+
+    /// Index token mint (mutable, tokens will be minted)
+    #[account(
+        mut,
+        seeds = [b"lp_index_token_mint",
+                 lp_index.key().as_ref()],
+        bump = lp_index.index_token_bump
+    )]
+    pub index_token_mint: Box<Account<'info, Mint>>,
+
+    token_program: Program<'info, Token>,
+}
+
+/// Parameters for minting index tokens
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MintLpIndexParams {
+    /// Amount of pool LP tokens to deposit, in component order
+    pub lp_amounts: Vec<u64>,
+    /// Minimum amount of index tokens to receive (slippage protection)
+    pub min_index_amount_out: u64,
+}
+
+/// Deposit pool LP tokens into an LP index and mint index tokens
+///
+/// The process:
+/// 1. Validates the index weights add up to 100% and the remaining accounts
+/// 2. Values the deposit and the LP tokens already held at the pool LP token prices,
+///    computing the pool AUMs from their custodies and oracles
+/// 3. Checks the owner is allowlisted by the permissioned component pools
+/// 4. Checks the deposit follows the index weights
+/// 5. Transfers the LP tokens to the component vaults
+/// 6. Mints index tokens pro rata to the index value
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Deposited LP token amounts and slippage bound
+///
+/// # Returns
+/// `Result<()>` - Success if index tokens were minted
+pub fn mint_lp_index<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MintLpIndex<'info>>,
+    params: &MintLpIndexParams,
+) -> Result<()> {
+    // Validate inputs
+    let lp_index = ctx.accounts.lp_index.as_ref();
+    require!(lp_index.is_balanced(), PerpetualsError::InvalidLpIndexState);
+    require_eq!(
+        params.lp_amounts.len(),
+        lp_index.components.len(),
+        PerpetualsError::InvalidLpIndexState
+    );
+
+    // Value deposit and index holdings
+    // The AUM cached in a pool may be stale, it is computed from the pool custodies and
+    // oracles instead. Deposits are valued at the lower AUM and index holdings at the
+    // higher one, so the spread between spot and EMA prices can't be extracted.
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let mut component_accounts = Vec::with_capacity(lp_index.components.len());
+    let mut permissioned_pools = Vec::new();
+    let mut deposit_values_usd = Vec::with_capacity(lp_index.components.len());
+    let mut index_value_usd: u64 = 0;
+    let mut offset = 0;
+    for (component, lp_amount) in lp_index.components.iter().zip(params.lp_amounts.iter()) {
+        let accounts = ctx
+            .remaining_accounts
+            .get(offset..offset + 4)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let (pool_info, mint_info, vault_info) = (&accounts[0], &accounts[1], &accounts[3]);
+        require_keys_eq!(
+            pool_info.key(),
+            component.pool,
+            PerpetualsError::InvalidLpIndexState
+        );
+        require_keys_eq!(
+            vault_info.key(),
+            component.vault,
+            PerpetualsError::InvalidLpIndexState
+        );
+        let pool = Account::<Pool>::try_from(pool_info)?;
+        let lp_token_mint = Account::<Mint>::try_from(mint_info)?;
+        let vault = Account::<TokenAccount>::try_from(vault_info)?;
+        require_keys_eq!(
+            vault.mint,
+            lp_token_mint.key(),
+            PerpetualsError::InvalidLpIndexState
+        );
+
+        if pool.lp_allowlist_enabled {
+            permissioned_pools.push(pool.key());
+        }
+
+        let aum_accounts = ctx
+            .remaining_accounts
+            .get(offset + 4..offset + 4 + pool.custodies.len() * 2)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        offset += 4 + aum_accounts.len();
+        component_accounts.push(accounts);
+
+        let deposit_aum_usd =
+            LpIndex::get_component_aum_usd(&pool, aum_accounts, AumCalcMode::Min, curtime)?;
+        let index_aum_usd =
+            LpIndex::get_component_aum_usd(&pool, aum_accounts, AumCalcMode::Max, curtime)?;
+        deposit_values_usd.push(LpIndex::get_lp_value_usd(
+            *lp_amount,
+            deposit_aum_usd,
+            lp_token_mint.supply,
+        )?);
+        index_value_usd = math::checked_add(
+            index_value_usd,
+            LpIndex::get_lp_value_usd(vault.amount, index_aum_usd, lp_token_mint.supply)?,
+        )?;
+    }
+    let allowlist_accounts = &ctx.remaining_accounts[offset..];
+
+    // Permissioned pools only accept allowlisted owners
+    if allowlist_accounts.len() != permissioned_pools.len() {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+    for (pool, allowlist_info) in permissioned_pools.iter().zip(allowlist_accounts) {
+        LpAllowlist::check_allowlist_account(allowlist_info, pool, ctx.accounts.owner.key)?;
+    }
+
+    lp_index.check_deposit_weights(&deposit_values_usd)?;
+
+    let deposit_usd = deposit_values_usd
+        .iter()
+        .try_fold(0u64, |acc, v| math::checked_add(acc, *v))?;
+    let index_amount = LpIndex::get_mint_amount(
+        deposit_usd,
+        index_value_usd,
+        ctx.accounts.index_token_mint.supply,
+    )?;
+    msg!(
+        "Deposit USD: {}, index amount: {}",
+        deposit_usd,
+        index_amount
+    );
+    require!(
+        index_amount > 0 && index_amount >= params.min_index_amount_out,
+        PerpetualsError::MaxPriceSlippage
+    );
+
+    // Transfer LP tokens
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    for (lp_amount, accounts) in params.lp_amounts.iter().zip(component_accounts) {
+        if *lp_amount == 0 {
+            continue;
+        }
+        perpetuals.transfer_tokens_from_user(
+            accounts[2].clone(),
+            accounts[3].clone(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            *lp_amount,
+        )?;
+    }
+
+    // Mint index tokens
+    perpetuals.mint_tokens(
+        ctx.accounts.index_token_mint.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        index_amount,
+    )?;
+
+    Ok(())
+}
@@ -8,18 +8,25 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        events::{LockChanged, TradeExecuted},
+        invariants,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody,
-            oracle::OraclePrice,
+            compliance_freeze::ComplianceFreeze,
+            custody::{Custody, FeeType},
+            market_maker::MarketMaker,
+            oracle::{OracleOperation, OraclePrice},
             perpetuals::Perpetuals,
+            owner_positions::OwnerPositions,
             pool::Pool,
             position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+            trader_activity::TraderActivity,
         },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
-    anchor_lang::error::ErrorCode::ConstraintRaw,
 };
 
 /// Accounts required for opening a new position
@@ -92,7 +99,7 @@ pub struct OpenPosition<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -110,7 +117,7 @@ pub struct OpenPosition<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -126,6 +133,96 @@ pub struct OpenPosition<'info> {
 
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
+
+    /// Optional market maker account of the owner (discounted spreads)
+    #[account(
+        mut,
+        seeds = [b"market_maker",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump = market_maker.bump
+    )]
+    pub market_maker: Option<Box<Account<'info, MarketMaker>>>,
+
+    /// Optional open position counter of the owner (required while the pool caps
+    /// open positions per owner)
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = OwnerPositions::LEN,
+        seeds = [b"owner_positions",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub owner_positions: Option<Box<Account<'info, OwnerPositions>>>,
+
+    /// Optional per-slot trade counter of the owner, required while the pool caps
+    /// trades per slot
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TraderActivity::LEN,
+        seeds = [b"trader_activity",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub trader_activity: Option<Box<Account<'info, TraderActivity>>>,
+
+    /// Optional summary of the position, mirrors the opened position
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PositionSummary::LEN,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+
+    /// Optional circuit-breaker feed, required if the protocol follows a risk oracle
+    ///
+    /// CHECK: Validated against the perpetuals risk oracle
+    pub risk_oracle_account: Option<AccountInfo<'info>>,
+
+    /// Optional compliance freeze list of the custody, required while the pool enforces
+    /// freeze lists
+    #[account(
+        seeds = [b"compliance_freeze",
+                 custody.key().as_ref()],
+        bump = compliance_freeze.bump
+    )]
+    pub compliance_freeze: Option<Box<Account<'info, ComplianceFreeze>>>,
+    // Optional remaining accounts (to pay the entry fee in the pool fee token):
+    //   - fee custody (mutable)
+    //   - fee custody oracle account
+    //   - fee custody token account (mutable)
+    //   - user's fee token account (mutable)
+}
+
+/// Unit of the requested position size
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum SizeMode {
+    /// Size in position tokens (in position token's native decimals)
+    #[default]
+    Tokens,
+    /// Size in USD notional (scaled to USD_DECIMALS), token size derived from entry price
+    Usd,
 }
 
 /// Parameters for opening a new position
@@ -137,13 +234,15 @@ pub struct OpenPositionParams {
     pub price: u64,
     /// Amount of collateral tokens to deposit (in collateral token's native decimals)
     pub collateral: u64,
-    /// Position size in tokens (in position token's native decimals)
+    /// Position size, in tokens or USD depending on size_mode
     pub size: u64,
     /// Position side (Long or Short)
     pub side: Side,
     /// Power multiplier for power perpetuals (1-5)
     /// 1 = linear perps, 2 = squared perps, 3 = cubed, etc.
     pub power: u8,
+    /// Unit of the size parameter
+    pub size_mode: SizeMode,
 }
 
 /// Open a new trading position
@@ -160,6 +259,9 @@ pub struct OpenPositionParams {
 /// 8. Locks funds for potential profit payouts
 /// 9. Transfers collateral and fees from user to pool
 /// 10. Updates custody and pool statistics
+///
+/// If the pool has a fee custody and its accounts are passed as remaining accounts,
+/// the entry fee is charged from the user's fee token account instead of in kind.
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
@@ -167,7 +269,10 @@ pub struct OpenPositionParams {
 /// 
 /// # Returns
 /// `Result<()>` - Success if position was opened successfully
-pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) -> Result<()> {
+pub fn open_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, OpenPosition<'info>>,
+    params: &OpenPositionParams,
+) -> Result<()> {
     // Check permissions
     // Both perpetuals and custody must allow opening positions
     // Position token cannot be a stablecoin
@@ -181,6 +286,24 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
             && !custody.is_stable,
         PerpetualsError::InstructionNotAllowed
     );
+    // Markets being wound down take no new positions
+    require!(
+        custody.lifecycle.allows_open() && collateral_custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        !custody.oracle_safe_mode && !collateral_custody.oracle_safe_mode,
+        PerpetualsError::OracleSafeMode
+    );
+    require!(
+        perpetuals.check_cpi_allowed(&ctx.accounts.pool, custody),
+        PerpetualsError::CpiNotAllowed
+    );
+    // Opening a position adds risk, refused while the risk oracle flags an incident
+    perpetuals.risk_oracle.check_risk_increase(
+        ctx.accounts.risk_oracle_account.as_ref(),
+        perpetuals.get_time()?,
+    )?;
 
     // Validate inputs
     msg!("Validate inputs");
@@ -189,12 +312,9 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
 
-    // Validate power parameter (must be 1-5)
+    // Validate power parameter (must be 1-5, and within the market max power)
     // power=1: linear perps, power=2: squared, ..., power=5: max power
-    require!(
-        params.power >= 1 && params.power <= 5,
-        PerpetualsError::InvalidPositionState
-    );
+    custody.pricing.check_power(params.power)?;
 
     // Determine if collateral custody is different from position custody
     // For shorts or virtual custodies, must use a different stablecoin as collateral
@@ -210,54 +330,128 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         // For longs: collateral custody must be the same as position custody
         require_keys_eq!(custody.key(), collateral_custody.key());
     };
+
+    // Frozen owners can't take on new exposure
+    ComplianceFreeze::validate_owner(
+        ctx.accounts.compliance_freeze.as_deref().map(AsRef::as_ref),
+        ctx.accounts.pool.compliance_freeze_enabled,
+        ctx.accounts.owner.key,
+    )?;
+
+    // Enforce the pool limit of open positions per owner
+    let max_positions_per_owner = ctx.accounts.pool.max_positions_per_owner;
+    require!(
+        max_positions_per_owner == 0 || ctx.accounts.owner_positions.is_some(),
+        PerpetualsError::OwnerPositionsRequired
+    );
+    if let Some(owner_positions) = ctx.accounts.owner_positions.as_mut() {
+        if owner_positions.owner == Pubkey::default() {
+            owner_positions.pool = ctx.accounts.pool.key();
+            owner_positions.owner = ctx.accounts.owner.key();
+            owner_positions.bump = ctx.bumps.owner_positions.unwrap_or_default();
+        }
+        owner_positions.add_position(max_positions_per_owner)?;
+    }
+
+    // Check the per-slot trade cap of the owner
+    let max_trades_per_slot = ctx.accounts.pool.max_trades_per_slot;
+    require!(
+        max_trades_per_slot == 0 || ctx.accounts.trader_activity.is_some(),
+        PerpetualsError::TraderActivityRequired
+    );
+    if let Some(trader_activity) = ctx.accounts.trader_activity.as_mut() {
+        if trader_activity.owner == Pubkey::default() {
+            trader_activity.pool = ctx.accounts.pool.key();
+            trader_activity.owner = ctx.accounts.owner.key();
+            trader_activity.bump = ctx.bumps.trader_activity.unwrap_or_default();
+        }
+        trader_activity.record_trade(Clock::get()?.slot, max_trades_per_slot)?;
+    }
+
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
 
     // Get current time for calculations
     let curtime = perpetuals.get_time()?;
 
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Use minimum collateral price for conservative valuation
     // For stablecoins, caps price at 1 USD
     let min_collateral_price = collateral_token_price
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
+    // Market makers with remaining volume capacity trade at discounted spreads, the
+    // volume is valued before spreads so the capacity check and the recorded volume match
+    let trade_volume_usd = match params.size_mode {
+        SizeMode::Tokens => token_ema_price.get_asset_amount_usd(
+            params.size,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?,
+        SizeMode::Usd => params.size,
+    };
+    let mut market_maker = match ctx.accounts.market_maker.as_mut() {
+        Some(market_maker) if market_maker.has_capacity(trade_volume_usd)? => Some(market_maker),
+        _ => None,
+    };
+    let mm_custody = match &market_maker {
+        Some(market_maker) => Some(market_maker.get_custody(custody)?),
+        None => None,
+    };
+
     // Calculate entry price (applies spread based on position side)
-    let position_price =
-        pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+    let position_price = pool.get_entry_price(
+        &token_price,
+        &token_ema_price,
+        params.side,
+        mm_custody.as_ref().unwrap_or(custody),
+        curtime,
+    )?;
     msg!("Entry price: {}", position_price);
 
     // Validate slippage protection
@@ -276,6 +470,24 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
             PerpetualsError::MaxPriceSlippage
         );
     }
+    emit!(TradeExecuted {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        custody: custody.key(),
+        side: params.side,
+        is_open: true,
+        bound_price: params.price,
+        spot_price: token_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price,
+        ema_price: token_ema_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price,
+        spread_bps: mm_custody.as_ref().unwrap_or(custody).get_entry_spread(params.side, curtime)?,
+        execution_price: position_price,
+        time: curtime,
+    });
 
     // Calculate position parameters
     // Convert entry price to OraclePrice format for calculations
@@ -283,20 +495,54 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         price: position_price,
         exponent: -(Perpetuals::PRICE_DECIMALS as i32),
     };
-    // Calculate position size and collateral in USD
-    let size_usd = position_oracle_price.get_asset_amount_usd(params.size, custody.decimals)?;
-    let collateral_usd = min_collateral_price
-        .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
+    // Calculate position size in tokens and USD
+    // USD sizes are converted at the entry price so the requested notional is kept
+    // regardless of price movement between quote and execution
+    let (size, size_usd) = match params.size_mode {
+        SizeMode::Tokens => (
+            params.size,
+            position_oracle_price.get_asset_amount_usd(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+        ),
+        SizeMode::Usd => (
+            position_oracle_price.get_token_amount(
+                params.size,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?,
+            params.size,
+        ),
+    };
+    msg!("Position size: {}, size USD: {}", size, size_usd);
+    require_gt!(size, 0, PerpetualsError::InvalidPositionState);
+    // Calculate collateral in USD
+    let collateral_usd = min_collateral_price.get_asset_amount_usd(
+        params.collateral,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    if let Some(market_maker) = market_maker.as_mut() {
+        market_maker.add_volume(trade_volume_usd)?;
+    }
 
     // Calculate locked amount (tokens that will be locked for potential profit payouts)
     // For shorts or virtual custodies, convert size_usd to collateral tokens first
     let locked_amount = if use_collateral_custody {
         custody.get_locked_amount(
-            min_collateral_price.get_token_amount(size_usd, collateral_custody.decimals)?,
+            min_collateral_price.get_token_amount(
+                size_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
             params.side,
+            params.power,
+            curtime,
         )?
     } else {
-        custody.get_locked_amount(params.size, params.side)?
+        custody.get_locked_amount(size, params.side, params.power, curtime)?
     };
 
     // Calculate borrow size USD (used for leverage calculations)
@@ -309,9 +555,17 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
             } else {
                 collateral_token_price
             };
-            max_collateral_price.get_asset_amount_usd(locked_amount, collateral_custody.decimals)?
+            max_collateral_price.get_asset_amount_usd(
+                locked_amount,
+                collateral_custody.decimals,
+                RoundingDirection::Down,
+            )?
         } else {
-            position_oracle_price.get_asset_amount_usd(locked_amount, custody.decimals)?
+            position_oracle_price.get_asset_amount_usd(
+                locked_amount,
+                custody.decimals,
+                RoundingDirection::Down,
+            )?
         }
     } else {
         size_usd
@@ -320,15 +574,34 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     // Calculate entry fee (includes utilization-based adjustments)
     let mut fee_amount = pool.get_entry_fee(
         custody.fees.open_position,
-        params.size,
+        &custody.entry_fee_tiers,
+        size,
+        size_usd,
         locked_amount,
         collateral_custody,
     )?;
-    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    // Surcharge opens towards the heavier side of the book, discount the lighter side
+    fee_amount = pool.apply_imbalance_fee(fee_amount, size, size_usd, params.side, custody)?;
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
     // Convert fee to collateral token if needed
     if use_collateral_custody {
         fee_amount = collateral_token_ema_price
-            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    // Charge the fee in the pool fee token if its accounts are provided
+    let mut fee_token_accounts = pool.load_fee_token_accounts(
+        ctx.remaining_accounts,
+        &ctx.accounts.owner.key(),
+        &custody.key(),
+        &collateral_custody.key(),
+    )?;
+    if fee_token_accounts.is_some() {
+        fee_amount = 0;
     }
     msg!("Collected fee: {}", fee_amount);
 
@@ -352,7 +625,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     position.collateral_usd = collateral_usd;
     position.unrealized_profit_usd = 0;
     position.unrealized_loss_usd = 0;
-    position.cumulative_interest_snapshot = collateral_custody.get_cumulative_interest(curtime)?;
+    collateral_custody.snapshot_interest(position, curtime)?;
     position.locked_amount = locked_amount;
     position.collateral_amount = params.collateral;
     position.bump = ctx.bumps.position;
@@ -364,8 +637,23 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         PerpetualsError::InsufficientAmountReturned
     );
     // Ensure position leverage is within acceptable limits
-    require!(
-        pool.check_leverage(
+    if let Some(error) = pool.get_leverage_error(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true, // new_position = true
+    )? {
+        return Err(error.into());
+    }
+
+    // Label the position with its risk tier under the custody thresholds
+    position.risk_tier = pool
+        .get_liquidation_check(
             position,
             &token_price,
             &token_ema_price,
@@ -374,14 +662,23 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
             &collateral_token_ema_price,
             collateral_custody,
             curtime,
-            true // new_position = true
-        )?,
-        PerpetualsError::MaxLeverage
-    );
+        )?
+        .risk_tier;
 
     // Lock funds for potential profit payouts
     // This ensures the pool has enough liquidity to pay profits if position becomes profitable
     collateral_custody.lock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: true,
+        amount: position.locked_amount,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
 
     // Transfer collateral and fee from user's funding account to pool's custody account
     msg!("Transfer tokens");
@@ -394,33 +691,51 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         ctx.accounts.token_program.to_account_info(),
         transfer_amount,
     )?;
+    if let Some(fee_token_accounts) = fee_token_accounts.as_mut() {
+        fee_token_accounts.charge_fee(
+            perpetuals,
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            fee_amount_usd,
+            custody.fees.get_protocol_share(FeeType::Position),
+            OracleOperation::Open,
+            curtime,
+        )?;
+    }
 
     // Update custody statistics
     msg!("Update custody stats");
-    // Track collected fees
-    collateral_custody.collected_fees.open_position_usd = collateral_custody
-        .collected_fees
-        .open_position_usd
-        .wrapping_add(fee_amount_usd);
+    // Track collected fees (charged in kind)
+    if fee_token_accounts.is_none() {
+        collateral_custody.collected_fees.open_position_usd = math::checked_add(
+            collateral_custody.collected_fees.open_position_usd,
+            fee_amount_usd as u128,
+        )?;
+    }
 
     // Update collateral tracking
     collateral_custody.assets.collateral =
         math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
 
     // Calculate and track protocol fee (portion of entry fee that goes to protocol)
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    let protocol_fee = Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Position), fee_amount)?;
     collateral_custody.assets.protocol_fees =
         math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
 
+    // Credit the backstop tranche with its share of the entry fee
+    let protocol_fee_usd =
+        Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Position), fee_amount_usd)?;
+    pool.record_backstop_pnl(math::checked_sub(fee_amount_usd, protocol_fee_usd)?, 0)?;
+
     // Update trade statistics and add position to tracking
     // If custody and collateral_custody accounts are the same (e.g., for long positions),
     // update collateral_custody stats and sync to custody
     if position.side == Side::Long && !custody.is_virtual {
         // Track opening volume
-        collateral_custody.volume_stats.open_position_usd = collateral_custody
-            .volume_stats
-            .open_position_usd
-            .wrapping_add(size_usd);
+        collateral_custody.volume_stats.open_position_usd = math::checked_add(
+            collateral_custody.volume_stats.open_position_usd,
+            size_usd as u128,
+        )?;
 
         // Update open interest (increase by position size)
         if params.side == Side::Long {
@@ -438,10 +753,10 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         *custody = collateral_custody.clone();
     } else {
         // Update custody stats (position token custody)
-        custody.volume_stats.open_position_usd = custody
-            .volume_stats
-            .open_position_usd
-            .wrapping_add(size_usd);
+        custody.volume_stats.open_position_usd = math::checked_add(
+            custody.volume_stats.open_position_usd,
+            size_usd as u128,
+        )?;
 
         // Update open interest
         if params.side == Side::Long {
@@ -463,5 +778,35 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    // Mirror the position into its summary
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.bump = ctx.bumps.position_summary.unwrap_or_default();
+        position_summary.update(position, pool.get_token_id(&custody.key())?, curtime)?;
+    }
+
+    // Notify the pool position hook
+    pool.position_hook.invoke(
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &PositionHookPayload {
+            event: PositionHookEvent::Open,
+            pool: pool.key(),
+            position: position.key(),
+            owner: position.owner,
+            custody: custody.key(),
+            side: position.side,
+            size_usd: position.size_usd,
+            price: position_price,
+            time: curtime,
+        },
+    )?;
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.collateral_custody,
+        &mut ctx.accounts.collateral_custody_token_account,
+    )?;
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,188 @@
+//! ProposeListing instruction handler
+//!
+//! This instruction lets anyone propose a new market for a pool that accepts listings.
+//! The lister escrows the listing bond and picks the oracle and risk limits of the
+//! custody within the template bounds. The custody can be activated with
+//! activate_listing once the veto timelock has passed.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            listing::{ListingConfig, ListingParams, ListingProposal, ListingStatus},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for proposing a listing
+#[derive(Accounts)]
+pub struct ProposeListing<'info> {
+    /// Lister (signer, pays rent and posts the bond)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Lister's bond token account (source of the bond)
+    #[account(
+        mut,
+        constraint = funding_account.mint == listing_config.bond_mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the market is listed in
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Listing config of the pool
+    #[account(
+        seeds = [b"listing_config",
+                 pool.key().as_ref()],
+        bump = listing_config.bump,
+        constraint = listing_config.version == ListingConfig::VERSION
+            @ PerpetualsError::InvalidListingConfig
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// Listing proposal account (PDA derived from pool and listed token mint)
+    #[account(
+        init,
+        payer = owner,
+        space = ListingProposal::LEN,
+        seeds = [b"listing_proposal",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub listing_proposal: Box<Account<'info, ListingProposal>>,
+
+    /// Token account escrowing the bond until the listing is activated or vetoed
+    #[account(
+        init,
+        payer = owner,
+        token::mint = bond_mint,
+        token::authority = transfer_authority,
+        seeds = [b"listing_bond",
+                 listing_proposal.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Mint of the listing bond
+    #[account(
+        constraint = bond_mint.key() == listing_config.bond_mint
+    )]
+    pub bond_mint: Box<Account<'info, Mint>>,
+
+    /// Custody account the listing activates
+    ///
+    /// CHECK: Custody PDA of the listed token, must not be in the pool yet
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody_token_mint.key().as_ref()],
+        bump
+    )]
+    pub custody: AccountInfo<'info>,
+
+    /// Mint of the listed token
+    pub custody_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for proposing a listing
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProposeListingParams {
+    /// Custody parameters chosen by the lister
+    pub params: ListingParams,
+}
+
+/// Propose a new market for a pool
+///
+/// The process:
+/// 1. Validates the listing parameters against the pool template
+/// 2. Checks that the market isn't listed in the pool yet
+/// 3. Escrows the listing bond
+/// 4. Records the proposal and its activation time
+///
+/// A proposal can't be resubmitted for the same mint, so a vetoed market stays
+/// delisted unless admins add it.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Custody parameters chosen by the lister
+///
+/// # Returns
+/// `Result<()>` - Success if the listing was proposed
+pub fn propose_listing(ctx: Context<ProposeListing>, params: &ProposeListingParams) -> Result<()> {
+    // Validate inputs
+    let listing_config = ctx.accounts.listing_config.as_ref();
+    require!(
+        listing_config.validate_params(&params.params),
+        PerpetualsError::InvalidListingParams
+    );
+    require!(
+        ctx.accounts
+            .pool
+            .get_token_id(&ctx.accounts.custody.key())
+            .is_err(),
+        PerpetualsError::InvalidListingParams
+    );
+
+    // Escrow bond
+    ctx.accounts.perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.bond_vault.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        listing_config.bond_amount,
+    )?;
+
+    // Record proposal
+    let proposal_time = ctx.accounts.perpetuals.get_time()?;
+    let listing_proposal = ctx.accounts.listing_proposal.as_mut();
+    listing_proposal.pool = ctx.accounts.pool.key();
+    listing_proposal.mint = ctx.accounts.custody_token_mint.key();
+    listing_proposal.lister = ctx.accounts.owner.key();
+    listing_proposal.params = params.params;
+    listing_proposal.bond_amount = listing_config.bond_amount;
+    listing_proposal.proposal_time = proposal_time;
+    listing_proposal.activation_time =
+        math::checked_add(proposal_time, listing_config.timelock_sec)?;
+    listing_proposal.status = ListingStatus::Pending;
+    listing_proposal.bump = ctx.bumps.listing_proposal;
+    listing_proposal.bond_vault_bump = ctx.bumps.bond_vault;
+    msg!(
+        "Listing proposed, activation time: {}",
+        listing_proposal.activation_time
+    );
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! ReconcileCustody instruction handler
+//!
+//! This instruction allows admins to reconcile custody bookkeeping with the actual
+//! balance of the custody token account. Balances can drift after bugs or direct
+//! transfers into the token account. Surpluses are swept into protocol fees or owned
+//! assets and deficits are recorded as bad debt. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::CustodyReconciled,
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
+};
+
+/// Accounts required for reconciling a custody
+#[derive(Accounts)]
+pub struct ReconcileCustody<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account (mutable, assets will be reconciled)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Pool's token account holding the custody tokens (read-only)
+    #[account(
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+}
+
+/// Parameters for reconciling a custody
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ReconcileCustodyParams {
+    /// Credit surplus to owned assets (liquidity providers) instead of protocol fees
+    pub surplus_to_owned: bool,
+}
+
+/// Reconcile custody bookkeeping with its token account balance
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Compares owned + collateral + protocol_fees to the token account balance
+/// 3. Credits any surplus to protocol fees or owned assets and clears bad debt
+/// 4. Records any deficit as bad debt
+/// 5. Emits a CustodyReconciled event
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Where to credit a surplus
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn reconcile_custody<'info>(
+    ctx: Context<'_, '_, '_, 'info, ReconcileCustody<'info>>,
+    params: &ReconcileCustodyParams,
+) -> Result<u8> {
+    // Virtual custodies don't hold tokens
+    require!(
+        !ctx.accounts.custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ReconcileCustody, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Compare bookkeeping to the token account balance
+    let custody = ctx.accounts.custody.as_mut();
+    let balance = ctx.accounts.custody_token_account.amount;
+    let book_amount = math::checked_add(
+        math::checked_add(custody.assets.owned, custody.assets.collateral)?,
+        custody.assets.protocol_fees,
+    )?;
+    let surplus = balance.saturating_sub(book_amount);
+    let deficit = book_amount.saturating_sub(balance);
+    msg!(
+        "Balance: {}, book amount: {}, surplus: {}, deficit: {}",
+        balance,
+        book_amount,
+        surplus,
+        deficit
+    );
+
+    // Sweep surplus and record deficit
+    if surplus > 0 {
+        if params.surplus_to_owned {
+            custody.assets.owned = math::checked_add(custody.assets.owned, surplus)?;
+        } else {
+            custody.assets.protocol_fees =
+                math::checked_add(custody.assets.protocol_fees, surplus)?;
+        }
+    }
+    custody.assets.bad_debt = deficit;
+
+    let pool = ctx.accounts.pool.as_mut();
+
+    emit!(CustodyReconciled {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: custody.key(),
+        balance,
+        book_amount,
+        surplus,
+        deficit,
+        surplus_to_owned: params.surplus_to_owned,
+    });
+
+    Ok(0)
+}
@@ -0,0 +1,140 @@
+//! RedeemLpIndex instruction handler
+//!
+//! This instruction allows index token holders to burn index tokens for their pro rata
+//! share of the pool LP tokens held by the LP index.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{lp_index::LpIndex, perpetuals::Perpetuals},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for redeeming index tokens
+///
+/// Remaining accounts: for every index component in order, the component vault and the
+/// user's LP token account receiving the payout.
+#[derive(Accounts)]
+pub struct RedeemLpIndex<'info> {
+    /// Owner of the index tokens (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's index token account (tokens will be burned)
+    #[account(
+        mut,
+        constraint = index_token_account.mint == index_token_mint.key(),
+        has_one = owner
+    )]
+    pub index_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// LP index account
+    #[account(
+        seeds = [b"lp_index",
+                 lp_index.name.as_bytes()],
+        bump = lp_index.bump
+    )]
+    pub lp_index: Box<Account<'info, LpIndex>>,
+
+    /// Index token mint (mutable, tokens will be burned)
+    #[account(
+        mut,
+        seeds = [b"lp_index_token_mint",
+                 lp_index.key().as_ref()],
+        bump = lp_index.index_token_bump
+    )]
+    pub index_token_mint: Box<Account<'info, Mint>>,
+
+    token_program: Program<'info, Token>,
+}
+
+/// Parameters for redeeming index tokens
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RedeemLpIndexParams {
+    /// Amount of index tokens to burn
+    pub index_amount: u64,
+}
+
+/// Burn index tokens for a pro rata share of every component vault
+///
+/// The process:
+/// 1. Validates the amount and the remaining accounts
+/// 2. Pays out each vault's share of the LP tokens, rounded down
+/// 3. Burns the index tokens
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Amount of index tokens to redeem
+///
+/// # Returns
+/// `Result<()>` - Success if index tokens were redeemed
+pub fn redeem_lp_index<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RedeemLpIndex<'info>>,
+    params: &RedeemLpIndexParams,
+) -> Result<()> {
+    // Validate inputs
+    if params.index_amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let lp_index = ctx.accounts.lp_index.as_ref();
+    if ctx.remaining_accounts.len() != lp_index.components.len() * 2 {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    // Pay out LP tokens
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let index_supply = ctx.accounts.index_token_mint.supply;
+    for (component, accounts) in lp_index
+        .components
+        .iter()
+        .zip(ctx.remaining_accounts.chunks(2))
+    {
+        require_keys_eq!(
+            accounts[0].key(),
+            component.vault,
+            PerpetualsError::InvalidLpIndexState
+        );
+        let vault = Account::<TokenAccount>::try_from(&accounts[0])?;
+        let amount = LpIndex::get_redeem_amount(vault.amount, params.index_amount, index_supply)?;
+        msg!("Redeem {} LP tokens of pool {}", amount, component.pool);
+        if amount == 0 {
+            continue;
+        }
+        perpetuals.transfer_tokens(
+            accounts[0].clone(),
+            accounts[1].clone(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            amount,
+        )?;
+    }
+
+    // Burn index tokens
+    perpetuals.burn_tokens(
+        ctx.accounts.index_token_mint.to_account_info(),
+        ctx.accounts.index_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.index_amount,
+    )?;
+
+    Ok(())
+}
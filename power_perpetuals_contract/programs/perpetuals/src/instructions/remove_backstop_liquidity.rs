@@ -0,0 +1,274 @@
+//! RemoveBackstopLiquidity instruction handler
+//!
+//! This instruction allows backstop token holders to redeem their backstop tokens for
+//! their share of the backstop tranche value, paid in a pool token. Trader profits
+//! already absorbed by the tranche are not recovered.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::Custody,
+            lp_allowlist::LpAllowlist,
+            oracle::OracleOperation,
+            perpetuals::Perpetuals,
+            pool::{Pool, RatioFlow},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for removing backstop liquidity from a pool
+#[derive(Accounts)]
+pub struct RemoveBackstopLiquidity<'info> {
+    /// Owner of the backstop tokens (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's token account where withdrawn tokens will be sent
+    #[account(
+        mut,
+        constraint = receiving_account.mint == custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// User's backstop token account from which backstop tokens will be burned
+    #[account(
+        mut,
+        constraint = backstop_token_account.mint == backstop_token_mint.key(),
+        has_one = owner
+    )]
+    pub backstop_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token transfers
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, backstop tranche will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the token being withdrawn (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being withdrawn
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account from which tokens will be withdrawn
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Backstop token mint of the pool (mutable, will burn backstop tokens)
+    #[account(
+        mut,
+        seeds = [b"backstop_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.backstop.token_bump
+    )]
+    pub backstop_token_mint: Box<Account<'info, Mint>>,
+
+    token_program: Program<'info, Token>,
+
+    /// Allowlist of the pool, required if the pool is permissioned
+    #[account(
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Option<Box<Account<'info, LpAllowlist>>>,
+}
+
+/// Parameters for removing backstop liquidity from a pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveBackstopLiquidityParams {
+    /// Amount of backstop tokens to redeem (in LP token decimals)
+    pub backstop_amount_in: u64,
+    /// Minimum tokens expected (slippage protection, in token's native decimals)
+    pub min_amount_out: u64,
+}
+
+/// Remove liquidity from the backstop tranche of a pool
+///
+/// The process:
+/// 1. Validates permissions and inputs
+/// 2. Values the redeemed backstop tokens at their share of the tranche value
+/// 3. Validates pool funds and token ratios
+/// 4. Transfers tokens to the user and burns the backstop tokens
+/// 5. Updates custody, backstop and pool value
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Backstop tokens to redeem and minimum tokens expected
+///
+/// # Returns
+/// `Result<()>` - Success if liquidity was removed successfully
+pub fn remove_backstop_liquidity(
+    ctx: Context<RemoveBackstopLiquidity>,
+    params: &RemoveBackstopLiquidityParams,
+) -> Result<()> {
+    // Check permissions
+    msg!("Check permissions");
+    // Permissioned pools only accept allowlisted owners
+    if ctx.accounts.pool.lp_allowlist_enabled {
+        require!(
+            ctx.accounts
+                .lp_allowlist
+                .as_ref()
+                .is_some_and(|lp_allowlist| lp_allowlist.is_allowed(ctx.accounts.owner.key)),
+            PerpetualsError::LpNotAllowlisted
+        );
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_remove_liquidity
+            && custody.permissions.allow_remove_liquidity
+            && !custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.lifecycle.allows_lp_exit(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.backstop_amount_in == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    let pool = ctx.accounts.pool.as_mut();
+    let token_id = pool.get_token_id(&custody.key())?;
+    let curtime = perpetuals.get_time()?;
+
+    // Get token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidity,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidity,
+    )?;
+
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
+    // Use maximum price (spot or EMA) for conservative withdrawal amount
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    // Calculate the share of the tranche value being redeemed
+    let remove_amount_usd = pool.backstop.get_redeem_amount_usd(
+        params.backstop_amount_in,
+        ctx.accounts.backstop_token_mint.supply,
+    )?;
+    let remove_amount = max_price.get_token_amount(
+        remove_amount_usd,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    msg!("Amount out: {}", remove_amount);
+    require!(
+        remove_amount >= params.min_amount_out,
+        PerpetualsError::MaxPriceSlippage
+    );
+
+    // Check pool constraints
+    msg!("Check pool constraints");
+    require!(
+        pool.check_token_ratio(
+            RatioFlow::RemoveLiquidity,
+            token_id,
+            0,
+            remove_amount,
+            custody,
+            &token_ema_price
+        )?,
+        PerpetualsError::TokenRatioOutOfRange
+    );
+    require!(
+        pool.check_available_amount(remove_amount, custody)?,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // Transfer tokens from pool's custody account to user's receiving account
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        remove_amount,
+    )?;
+
+    // Burn backstop tokens from user's backstop token account
+    msg!("Burn backstop tokens");
+    perpetuals.burn_tokens(
+        ctx.accounts.backstop_token_mint.to_account_info(),
+        ctx.accounts.backstop_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.backstop_amount_in,
+    )?;
+
+    // Update custody statistics
+    msg!("Update custody stats");
+    custody.volume_stats.remove_liquidity_usd = math::checked_add(
+        custody.volume_stats.remove_liquidity_usd,
+        remove_amount_usd as u128,
+    )?;
+    custody.assets.owned = math::checked_sub(custody.assets.owned, remove_amount)?;
+    custody.update_borrow_rate(curtime)?;
+
+    // Update backstop and pool value
+    pool.backstop.value_usd = math::checked_sub(pool.backstop.value_usd, remove_amount_usd)?;
+    pool.aum_usd = pool.aum_usd.saturating_sub(remove_amount_usd as u128);
+
+    Ok(())
+}
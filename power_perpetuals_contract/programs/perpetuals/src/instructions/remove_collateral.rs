@@ -7,13 +7,15 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        invariants,
+        math::{self, RoundingDirection},
         state::{
             custody::Custody,
-            oracle::OraclePrice,
+            oracle::OracleOperation,
             perpetuals::Perpetuals,
             pool::Pool,
-            position::{Position, Side},
+            position::{CollateralAmount, Position, Side},
+            position_summary::PositionSummary,
         },
     },
     anchor_lang::prelude::*,
@@ -29,10 +31,11 @@ pub struct RemoveCollateral<'info> {
     pub owner: Signer<'info>,
 
     /// User's token account where collateral will be returned
-    /// Must be owned by owner and have the same mint as custody
+    /// Must be owned by owner and have the same mint as the collateral custody
+    /// (the stablecoin custody for shorts)
     #[account(
         mut,
-        constraint = receiving_account.mint == custody.mint,
+        constraint = receiving_account.mint == collateral_custody.mint @ PerpetualsError::InvalidCollateralMint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
@@ -86,7 +89,7 @@ pub struct RemoveCollateral<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -101,7 +104,7 @@ pub struct RemoveCollateral<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -117,12 +120,25 @@ pub struct RemoveCollateral<'info> {
 
     /// Token program for token transfers
     pub token_program: Program<'info, Token>,
+
+    /// Optional summary of the position, mirrors the updated position
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
 }
 
 /// Parameters for removing collateral from a position
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct RemoveCollateralParams {
-    collateral_usd: u64,
+    /// Collateral to remove, in collateral tokens or in USD
+    ///
+    /// Conversions use the max collateral price. A USD amount pays out tokens rounding
+    /// down, a token amount debits USD from the position rounding up.
+    pub collateral: CollateralAmount,
 }
 
 /// Remove collateral from an existing position
@@ -142,7 +158,7 @@ pub struct RemoveCollateralParams {
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
-/// * `params` - Parameters including collateral amount to remove in USD
+/// * `params` - Parameters including collateral amount to remove in tokens or USD
 /// 
 /// # Returns
 /// `Result<()>` - Success if collateral was removed successfully
@@ -161,12 +177,21 @@ pub fn remove_collateral(
             && custody.permissions.allow_collateral_withdrawal,
         PerpetualsError::InstructionNotAllowed
     );
+    // Positions can be reduced until the market is settled
+    require!(
+        custody.lifecycle.allows_close(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        perpetuals.check_cpi_allowed(&ctx.accounts.pool, custody),
+        PerpetualsError::CpiNotAllowed
+    );
 
     // Validate inputs
     // Collateral amount must be greater than 0 and less than position's current collateral
     msg!("Validate inputs");
     let position = ctx.accounts.position.as_mut();
-    if params.collateral_usd == 0 || params.collateral_usd >= position.collateral_usd {
+    if params.collateral.is_zero() {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
     let pool = ctx.accounts.pool.as_mut();
@@ -174,40 +199,52 @@ pub fn remove_collateral(
     // Get current time for calculations
     let curtime = perpetuals.get_time()?;
 
+    // Expired power futures markets can only be settled
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
     // Get position token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
     // Get collateral token prices from oracle (spot and EMA)
-    let collateral_token_price = OraclePrice::new_from_oracle(
+    let collateral_token_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
         curtime,
         collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
     // Use maximum collateral price for conservative token amount calculation
     // This ensures users get a conservative estimate of tokens they'll receive
     let max_collateral_price = if collateral_token_price > collateral_token_ema_price {
@@ -216,12 +253,28 @@ pub fn remove_collateral(
         collateral_token_ema_price
     };
 
-    // Calculate amount of collateral tokens to transfer
-    // Convert USD amount to token amount using maximum price
-    let collateral = max_collateral_price
-        .get_token_amount(params.collateral_usd, collateral_custody.decimals)?;
-    // Validate that calculated amount doesn't exceed available collateral
-    if collateral > position.collateral_amount {
+    // Calculate amount of collateral tokens to transfer and USD to debit from the position
+    // using maximum price
+    let (collateral, collateral_usd) = match params.collateral {
+        CollateralAmount::Tokens(amount) => (
+            amount,
+            max_collateral_price.get_asset_amount_usd(
+                amount,
+                collateral_custody.decimals,
+                RoundingDirection::Up,
+            )?,
+        ),
+        CollateralAmount::Usd(amount_usd) => (
+            max_collateral_price.get_token_amount(
+                amount_usd,
+                collateral_custody.decimals,
+                RoundingDirection::Down,
+            )?,
+            amount_usd,
+        ),
+    };
+    // Validate that the removal leaves collateral in the position
+    if collateral_usd >= position.collateral_usd || collateral > position.collateral_amount {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
     msg!("Amount out: {}", collateral);
@@ -229,14 +282,38 @@ pub fn remove_collateral(
     // Update position with reduced collateral
     msg!("Update existing position");
     position.update_time = perpetuals.get_time()?;
-    position.collateral_usd = math::checked_sub(position.collateral_usd, params.collateral_usd)?;
+    // Large positions can only remove part of their collateral per window
+    position.record_collateral_withdrawal(
+        &pool.collateral_withdrawal_limit,
+        collateral_usd,
+        curtime,
+    )?;
+    position.collateral_usd = math::checked_sub(position.collateral_usd, collateral_usd)?;
     position.collateral_amount = math::checked_sub(position.collateral_amount, collateral)?;
 
     // Validate position leverage after removing collateral
     // This ensures the position remains within acceptable risk limits
     msg!("Check position risks");
-    require!(
-        pool.check_leverage(
+    if let Some(error) = pool.get_leverage_error(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )? {
+        return Err(error.into());
+    }
+
+    // The position passed the leverage check, a started liquidation auction is over
+    position.liquidation_auction_slot = 0;
+
+    // Label the position with its risk tier under the custody thresholds
+    position.risk_tier = pool
+        .get_liquidation_check(
             position,
             &token_price,
             &token_ema_price,
@@ -245,10 +322,8 @@ pub fn remove_collateral(
             &collateral_token_ema_price,
             collateral_custody,
             curtime,
-            true
-        )?,
-        PerpetualsError::MaxLeverage
-    );
+        )?
+        .risk_tier;
 
     // Transfer collateral tokens from pool's custody account to user's receiving account
     msg!("Transfer tokens");
@@ -273,5 +348,16 @@ pub fn remove_collateral(
         *custody = collateral_custody.clone();
     }
 
+    // Mirror the position into its summary
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.update(position, pool.get_token_id(&custody.key())?, curtime)?;
+    }
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.collateral_custody,
+        &mut ctx.accounts.collateral_custody_token_account,
+    )?;
+
     Ok(())
 }
\ No newline at end of file
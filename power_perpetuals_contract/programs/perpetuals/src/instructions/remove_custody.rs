@@ -8,7 +8,7 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::Custody,
+            custody::{Custody, MarketLifecycle},
             multisig::{AdminInstruction, Multisig},
             perpetuals::Perpetuals,
             pool::{Pool, TokenRatios},
@@ -50,14 +50,10 @@ pub struct RemoveCustody<'info> {
     )]
     pub perpetuals: Box<Account<'info, Perpetuals>>,
 
-    /// Pool account (mutable, will be reallocated to remove custody)
-    /// Reallocation decreases size to remove custody pubkey and token ratios
+    /// Pool account (mutable, will be shrunk to remove custody)
+    /// Reallocation decreases size to the exact size of the remaining pool data
     #[account(
         mut,
-        realloc = Pool::LEN + (pool.custodies.len() - 1) * std::mem::size_of::<Pubkey>() +
-                              (pool.ratios.len() - 1) * std::mem::size_of::<TokenRatios>(),
-        realloc::payer = admin,
-        realloc::zero = false,
         seeds = [b"pool",
                  pool.name.as_bytes()],
         bump = pool.bump
@@ -87,6 +83,12 @@ pub struct RemoveCustody<'info> {
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    /// Account receiving the rent freed by shrinking the pool
+    ///
+    /// CHECK: Rent receiver, any account
+    #[account(mut)]
+    pub rent_receiver: AccountInfo<'info>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
@@ -107,7 +109,8 @@ pub struct RemoveCustodyParams {
 /// 4. Removes custody from pool's custody list
 /// 5. Updates token ratios
 /// 6. Validates pool configuration
-/// 7. Closes custody token account
+/// 7. Shrinks the pool account and refunds freed rent to the receiver
+/// 8. Closes custody token account
 /// 
 /// Returns the number of signatures still required (0 if fully signed and executed).
 /// 
@@ -156,10 +159,23 @@ pub fn remove_custody<'info>(
         PerpetualsError::InvalidCustodyState
     );
 
+    // A market being wound down can only be removed once retired
+    require!(
+        matches!(
+            ctx.accounts.custody.lifecycle,
+            MarketLifecycle::Active | MarketLifecycle::Retired
+        ),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+
     // Remove custody from pool's custody list
     let pool = ctx.accounts.pool.as_mut();
     let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
     pool.custodies.remove(token_id);
+    // Fees can no longer be paid in the removed custody token
+    if pool.fee_custody == ctx.accounts.custody.key() {
+        pool.fee_custody = Pubkey::default();
+    }
     // Update token ratios (must exclude ratio for removed custody)
     pool.ratios = params.ratios.clone();
     // Validate pool configuration after removing custody
@@ -167,6 +183,16 @@ pub fn remove_custody<'info>(
         return err!(PerpetualsError::InvalidPoolConfig);
     }
 
+    // Shrink pool account to the exact size of the remaining data
+    // Freed rent is refunded to rent_receiver
+    let pool_size = pool.get_size()?;
+    msg!("Pool size: {}", pool_size);
+    Perpetuals::shrink(
+        ctx.accounts.pool.to_account_info(),
+        ctx.accounts.rent_receiver.to_account_info(),
+        pool_size,
+    )?;
+
     // Close custody token account
     // Returns rent to transfer_authority PDA
     Perpetuals::close_token_account(
@@ -7,13 +7,19 @@
 
 use {
     crate::{
+        conversions,
         error::PerpetualsError,
-        math,
+        events::ClaimQueued,
+        invariants,
+        math::{self, RoundingDirection},
         state::{
-            custody::Custody,
-            oracle::OraclePrice,
+            custody::{Custody, FeeType},
+            lp_allowlist::LpAllowlist,
+            lp_ledger::LpLedger,
+            oracle::OracleOperation,
+            pending_claim::PendingClaim,
             perpetuals::Perpetuals,
-            pool::{AumCalcMode, Pool},
+            pool::{AumCalcMode, Pool, RatioFlow},
         },
     },
     anchor_lang::prelude::*,
@@ -85,7 +91,7 @@ pub struct RemoveLiquidity<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -108,7 +114,41 @@ pub struct RemoveLiquidity<'info> {
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
 
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
+
+    /// Allowlist of the pool, required if the pool is permissioned
+    #[account(
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Option<Box<Account<'info, LpAllowlist>>>,
+
+    /// Deposit ledger of the owner, required if the custody charges a decaying remove fee
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LpLedger::LEN,
+        seeds = [b"lp_ledger",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub lp_ledger: Option<Box<Account<'info, LpLedger>>>,
+
+    /// Optional claim, the withdrawal is queued in it if the custody lacks free liquidity
+    /// (closed right away if the withdrawal can be paid)
+    #[account(
+        init,
+        payer = owner,
+        space = PendingClaim::LEN,
+        seeds = [b"pending_claim",
+                 custody.key().as_ref(),
+                 &custody.claim_queue.next_claim_id.to_le_bytes()],
+        bump
+    )]
+    pub pending_claim: Option<Box<Account<'info, PendingClaim>>>,
     // remaining accounts:
     //   pool.tokens.len() custody accounts (read-only, unsigned)
     //   pool.tokens.len() custody oracles (read-only, unsigned)
@@ -121,6 +161,9 @@ pub struct RemoveLiquidityParams {
     pub lp_amount_in: u64,
     /// Minimum tokens expected (slippage protection, in token decimals)
     pub min_amount_out: u64,
+    /// Maximum total fee accepted, including the early remove fee
+    /// (in BPS of the withdrawn amount), 0 to disable
+    pub max_fee_bps: u64,
 }
 
 /// Remove liquidity from a pool and burn LP tokens
@@ -130,10 +173,11 @@ pub struct RemoveLiquidityParams {
 /// 1. Validates permissions and inputs
 /// 2. Calculates AUM and token amount to return (proportional to LP tokens)
 /// 3. Calculates remove liquidity fee
-/// 4. Validates slippage protection
+/// 4. Validates slippage protection and the optional fee cap
 /// 5. Validates token ratios remain within acceptable range
 /// 6. Validates pool has sufficient available funds
-/// 7. Transfers tokens from pool to user
+/// 7. Transfers tokens from pool to user, or queues the withdrawal if a pending claim
+///    account is provided and free liquidity is short
 /// 8. Burns LP tokens
 /// 9. Updates custody and pool statistics
 /// 
@@ -152,6 +196,16 @@ pub fn remove_liquidity<'info>(
     // Check permissions
     // Both perpetuals and custody must allow removing liquidity, and custody must not be virtual
     msg!("Check permissions");
+    // Permissioned pools only accept allowlisted owners
+    if ctx.accounts.pool.lp_allowlist_enabled {
+        require!(
+            ctx.accounts
+                .lp_allowlist
+                .as_ref()
+                .is_some_and(|lp_allowlist| lp_allowlist.is_allowed(ctx.accounts.owner.key)),
+            PerpetualsError::LpNotAllowlisted
+        );
+    }
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     let custody = ctx.accounts.custody.as_mut();
     require!(
@@ -160,12 +214,22 @@ pub fn remove_liquidity<'info>(
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
     );
+    // Liquidity providers can exit until the market is retired
+    require!(
+        custody.lifecycle.allows_lp_exit(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
 
     // Validate inputs
     msg!("Validate inputs");
     if params.lp_amount_in == 0 {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
+    // Deposits must be tracked while the custody charges a decaying remove fee
+    require!(
+        !custody.is_lp_fee_decay_enabled() || ctx.accounts.lp_ledger.is_some(),
+        PerpetualsError::LpLedgerRequired
+    );
     let pool = ctx.accounts.pool.as_mut();
     let token_id = pool.get_token_id(&custody.key())?;
 
@@ -180,20 +244,24 @@ pub fn remove_liquidity<'info>(
         pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
 
     // Get token prices from oracle (spot and EMA)
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
+        OracleOperation::Liquidity,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
+        OracleOperation::Liquidity,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
     // Use maximum price (spot or EMA) for conservative token amount calculation
     // This ensures users get a conservative estimate of tokens they'll receive
     let max_price = if token_price > token_ema_price {
@@ -203,8 +271,10 @@ pub fn remove_liquidity<'info>(
     };
 
     // Calculate pool AUM using Min mode (conservative estimate)
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?;
+    // LP tokens are priced from the AUM left after the backstop tranche value
+    let pool_amount_usd = pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?,
+    );
 
     // Calculate USD value of LP tokens being redeemed
     // Formula: remove_amount_usd = (pool_aum_usd * lp_amount_in) / lp_supply
@@ -214,12 +284,46 @@ pub fn remove_liquidity<'info>(
     )?)?;
 
     // Convert USD amount to token amount using maximum price
-    let remove_amount = max_price.get_token_amount(remove_amount_usd, custody.decimals)?;
+    let remove_amount = max_price.get_token_amount(
+        remove_amount_usd,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
 
     // Calculate remove liquidity fee
-    let fee_amount =
+    let mut fee_amount =
         pool.get_remove_liquidity_fee(token_id, remove_amount, custody, &token_ema_price)?;
+
+    // Add the decaying fee of young deposits and remove redeemed LP tokens from the ledger
+    if let Some(lp_ledger) = ctx.accounts.lp_ledger.as_mut() {
+        if lp_ledger.owner == Pubkey::default() {
+            lp_ledger.pool = pool.key();
+            lp_ledger.owner = ctx.accounts.owner.key();
+            lp_ledger.bump = ctx.bumps.lp_ledger.unwrap_or_default();
+        }
+        if custody.is_lp_fee_decay_enabled() {
+            let early_fee = lp_ledger.get_early_remove_fee(
+                params.lp_amount_in,
+                custody.fees.early_remove_liquidity,
+                custody.pricing.lp_fee_decay_period,
+                curtime,
+            )?;
+            let early_fee_amount = Pool::get_fee_amount(early_fee, remove_amount)?;
+            msg!("Early remove liquidity fee: {}", early_fee_amount);
+            fee_amount = std::cmp::min(
+                math::checked_add(fee_amount, early_fee_amount)?,
+                remove_amount,
+            );
+        }
+        lp_ledger.remove(params.lp_amount_in);
+    }
     msg!("Collected fee: {}", fee_amount);
+    if params.max_fee_bps > 0 {
+        require!(
+            fee_amount <= conversions::apply_bps_ceil(remove_amount, params.max_fee_bps)?,
+            PerpetualsError::MaxFeeExceeded
+        );
+    }
 
     // Calculate amount to transfer after deducting fee
     let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
@@ -235,30 +339,65 @@ pub fn remove_liquidity<'info>(
     // Check pool constraints
     msg!("Check pool constraints");
     // Calculate protocol fee (portion of liquidity fee that goes to protocol)
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    let protocol_fee = Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Liquidity), fee_amount)?;
     // Total withdrawal amount includes both user amount and protocol fee
     let withdrawal_amount = math::checked_add(transfer_amount, protocol_fee)?;
     // Ensure token ratios remain within acceptable range after withdrawal
     require!(
-        pool.check_token_ratio(token_id, 0, withdrawal_amount, custody, &token_ema_price)?,
+        pool.check_token_ratio(RatioFlow::RemoveLiquidity, token_id, 0, withdrawal_amount, custody, &token_ema_price)?,
         PerpetualsError::TokenRatioOutOfRange
     );
 
-    // Ensure pool has sufficient available funds (owned - locked >= withdrawal_amount)
-    require!(
-        math::checked_sub(custody.assets.owned, custody.assets.locked)? >= withdrawal_amount,
-        PerpetualsError::CustodyAmountLimit
-    );
-
-    // Transfer tokens from pool's custody account to user's receiving account
-    msg!("Transfer tokens");
-    perpetuals.transfer_tokens(
-        ctx.accounts.custody_token_account.to_account_info(),
-        ctx.accounts.receiving_account.to_account_info(),
-        ctx.accounts.transfer_authority.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        transfer_amount,
-    )?;
+    // Check pool available funds (owned - locked >= withdrawal_amount)
+    // Queued claims are served first
+    let available_amount = custody.get_available_amount()?;
+    let queued = available_amount < withdrawal_amount;
+
+    if queued {
+        // Queue the withdrawal until liquidity frees up
+        let Some(pending_claim) = ctx.accounts.pending_claim.as_mut() else {
+            return err!(PerpetualsError::CustodyAmountLimit);
+        };
+        msg!("Queue claim");
+        pending_claim.set_inner(PendingClaim {
+            custody: custody.key(),
+            owner: ctx.accounts.owner.key(),
+            receiving_account: ctx.accounts.receiving_account.key(),
+            claim_id: 0,
+            amount: transfer_amount,
+            protocol_fee,
+            request_time: curtime,
+            bump: ctx.bumps.pending_claim.unwrap_or_default(),
+        });
+        custody.queue_claim(pending_claim)?;
+
+        emit!(ClaimQueued {
+            pool: pool.key(),
+            seq: pool.next_event_seq()?,
+            custody: custody.key(),
+            pending_claim: pending_claim.key(),
+            owner: pending_claim.owner,
+            claim_id: pending_claim.claim_id,
+            amount: transfer_amount,
+            pending_amount: custody.claim_queue.pending_amount,
+            time: curtime,
+        });
+    } else {
+        // Transfer tokens from pool's custody account to user's receiving account
+        msg!("Transfer tokens");
+        perpetuals.transfer_tokens(
+            ctx.accounts.custody_token_account.to_account_info(),
+            ctx.accounts.receiving_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            transfer_amount,
+        )?;
+
+        // The claim account is not needed, return its rent
+        if let Some(pending_claim) = ctx.accounts.pending_claim.as_ref() {
+            pending_claim.close(ctx.accounts.owner.to_account_info())?;
+        }
+    }
 
     // Burn LP tokens from user's LP token account
     msg!("Burn LP tokens");
@@ -273,33 +412,50 @@ pub fn remove_liquidity<'info>(
     // Update custody statistics
     msg!("Update custody stats");
     // Track collected fees in USD
-    custody.collected_fees.remove_liquidity_usd = custody
-        .collected_fees
-        .remove_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?);
+    custody.collected_fees.remove_liquidity_usd = math::checked_add(
+        custody.collected_fees.remove_liquidity_usd,
+        token_ema_price.get_asset_amount_usd(
+            fee_amount,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
 
     // Track volume statistics in USD
-    custody.volume_stats.remove_liquidity_usd = custody
-        .volume_stats
-        .remove_liquidity_usd
-        .wrapping_add(remove_amount_usd);
+    custody.volume_stats.remove_liquidity_usd = math::checked_add(
+        custody.volume_stats.remove_liquidity_usd,
+        remove_amount_usd as u128,
+    )?;
 
-    // Update protocol fees (portion of liquidity fee that goes to protocol)
-    custody.assets.protocol_fees = math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
+    // Queued withdrawals stay in owned assets until the claim is executed
+    if !queued {
+        // Update protocol fees (portion of liquidity fee that goes to protocol)
+        custody.assets.protocol_fees =
+            math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
 
-    // Update owned assets (tokens owned by the pool after withdrawal)
-    custody.assets.owned = math::checked_sub(custody.assets.owned, withdrawal_amount)?;
+        // Update owned assets (tokens owned by the pool after withdrawal)
+        custody.assets.owned = math::checked_sub(custody.assets.owned, withdrawal_amount)?;
+    }
 
     // Update borrow rate based on new utilization
     custody.update_borrow_rate(curtime)?;
 
     // Update pool statistics
     msg!("Update pool stats");
-    // Exit custody account (release borrow from Anchor's account context)
-    custody.exit(&crate::ID)?;
     // Refresh pool AUM using EMA mode for accurate tracking
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    // The updated custody is passed in memory, its account data is only written on exit
+    pool.aum_usd = pool.get_assets_under_management_usd_with_custody(
+        AumCalcMode::EMA,
+        ctx.remaining_accounts,
+        &[(custody.key(), custody)],
+        curtime,
+    )?;
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.custody,
+        &mut ctx.accounts.custody_token_account,
+    )?;
 
     Ok(())
 }
\ No newline at end of file
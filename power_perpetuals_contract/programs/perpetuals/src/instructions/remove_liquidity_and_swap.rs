@@ -0,0 +1,529 @@
+//! RemoveLiquidityAndSwap instruction handler
+//!
+//! This instruction redeems LP tokens for one custody token and swaps the withdrawn
+//! amount into another custody token in a single step, so liquidity providers can
+//! always exit into e.g. USDC. The withdrawn tokens never leave the pool, they are
+//! deposited back into their custody as the swap input, and only the swap output is
+//! transferred to the user. Remove liquidity and swap fees are both charged, and a
+//! single min_amount_out protects the final amount.
+
+use {
+    crate::{
+        conversions,
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::{
+            custody::{Custody, FeeType},
+            lp_allowlist::LpAllowlist,
+            lp_ledger::LpLedger,
+            oracle::OracleOperation,
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool, RatioFlow},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for removing liquidity and swapping the withdrawn tokens
+#[derive(Accounts)]
+#[instruction(params: RemoveLiquidityAndSwapParams)]
+pub struct RemoveLiquidityAndSwap<'info> {
+    /// Owner of the liquidity position (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's token account where the swapped tokens will be returned
+    /// Must be owned by owner and have the same mint as the dispensing custody
+    #[account(
+        mut,
+        constraint = receiving_account.mint == dispensing_custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// User's LP token account from which LP tokens will be burned
+    /// Must be owned by owner and have the LP token mint
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA for token transfers
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account for the token being withdrawn and swapped (mutable, stats will be
+    /// updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being withdrawn
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the token being dispensed (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 dispensing_custody.mint.as_ref()],
+        bump = dispensing_custody.bump
+    )]
+    pub dispensing_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the token being dispensed
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub dispensing_custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account where dispensed tokens are stored (mutable, tokens will be
+    /// transferred out)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 dispensing_custody.mint.as_ref()],
+        bump = dispensing_custody.token_account_bump
+    )]
+    pub dispensing_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// LP token mint for this pool (mutable, will burn LP tokens)
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+
+    /// Allowlist of the pool, required if the pool is permissioned
+    #[account(
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Option<Box<Account<'info, LpAllowlist>>>,
+
+    /// Deposit ledger of the owner, required if the custody charges a decaying remove fee
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LpLedger::LEN,
+        seeds = [b"lp_ledger",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub lp_ledger: Option<Box<Account<'info, LpLedger>>>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (read-only, unsigned)
+    //   pool.tokens.len() custody oracles (read-only, unsigned)
+}
+
+/// Parameters for removing liquidity and swapping the withdrawn tokens
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveLiquidityAndSwapParams {
+    /// Amount of LP tokens to redeem (in LP token decimals)
+    pub lp_amount_in: u64,
+    /// Minimum dispensed tokens expected (slippage protection, in token decimals)
+    pub min_amount_out: u64,
+    /// Maximum remove liquidity fee accepted, including the early remove fee
+    /// (in BPS of the withdrawn amount), 0 to disable
+    pub max_fee_bps: u64,
+}
+
+/// Remove liquidity from a pool and swap the withdrawn tokens into another custody token
+///
+/// The process:
+/// 1. Validates remove liquidity and swap permissions and inputs
+/// 2. Calculates the withdrawn token amount and remove liquidity fee, as remove_liquidity
+/// 3. Calculates the swap of the withdrawn amount into the dispensing custody, as swap
+/// 4. Validates slippage protection on the dispensed amount
+/// 5. Validates token ratios and dispensing custody available funds
+/// 6. Transfers dispensed tokens to the user and burns LP tokens
+/// 7. Updates custody statistics and borrow rates
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters including LP token amount and minimum tokens expected
+///
+/// # Returns
+/// `Result<()>` - Success if liquidity was removed and swapped successfully
+pub fn remove_liquidity_and_swap<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RemoveLiquidityAndSwap<'info>>,
+    params: &RemoveLiquidityAndSwapParams,
+) -> Result<()> {
+    // Check permissions
+    msg!("Check permissions");
+    // Permissioned pools only accept allowlisted owners
+    if ctx.accounts.pool.lp_allowlist_enabled {
+        require!(
+            ctx.accounts
+                .lp_allowlist
+                .as_ref()
+                .is_some_and(|lp_allowlist| lp_allowlist.is_allowed(ctx.accounts.owner.key)),
+            PerpetualsError::LpNotAllowlisted
+        );
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let dispensing_custody = ctx.accounts.dispensing_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_remove_liquidity
+            && perpetuals.permissions.allow_swap
+            && custody.permissions.allow_remove_liquidity
+            && custody.permissions.allow_swap
+            && dispensing_custody.permissions.allow_swap
+            && !custody.is_virtual
+            && !dispensing_custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Liquidity providers can exit until the market is retired, swaps need active markets
+    require!(
+        custody.lifecycle.allows_lp_exit() && dispensing_custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        !custody.oracle_safe_mode && !dispensing_custody.oracle_safe_mode,
+        PerpetualsError::OracleSafeMode
+    );
+
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.lp_amount_in == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    require_keys_neq!(custody.key(), dispensing_custody.key());
+    // Deposits must be tracked while the custody charges a decaying remove fee
+    require!(
+        !custody.is_lp_fee_decay_enabled() || ctx.accounts.lp_ledger.is_some(),
+        PerpetualsError::LpLedgerRequired
+    );
+    let pool = ctx.accounts.pool.as_mut();
+    let token_id = pool.get_token_id(&custody.key())?;
+    let token_id_out = pool.get_token_id(&dispensing_custody.key())?;
+    let curtime = perpetuals.get_time()?;
+
+    // Refresh pool AUM using EMA mode, as remove_liquidity
+    msg!("Compute assets under management");
+    pool.aum_usd =
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+
+    // Get withdrawn token prices, for liquidity removal and for the swap
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidity,
+    )?;
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidity,
+    )?;
+    let swap_token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+    let swap_token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+    let token_price = custody.get_fair_price(&token_price, curtime)?;
+    let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+    let swap_token_price = custody.get_fair_price(&swap_token_price, curtime)?;
+    let swap_token_ema_price = custody.get_fair_price(&swap_token_ema_price, curtime)?;
+
+    // Get dispensed token prices
+    let dispensed_token_price = dispensing_custody.get_oracle_price(
+        &ctx.accounts
+            .dispensing_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+    let dispensed_token_ema_price = dispensing_custody.get_oracle_price(
+        &ctx.accounts
+            .dispensing_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        dispensing_custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+    let dispensed_token_price = dispensing_custody.get_fair_price(&dispensed_token_price, curtime)?;
+    let dispensed_token_ema_price =
+        dispensing_custody.get_fair_price(&dispensed_token_ema_price, curtime)?;
+
+    // Calculate withdrawn amount, as remove_liquidity
+    msg!("Compute remove amount");
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+    // LP tokens are priced from the AUM left after the backstop tranche value
+    let pool_amount_usd = pool.backstop.get_senior_aum_usd(
+        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?,
+    );
+    let remove_amount_usd = math::checked_as_u64(math::checked_div(
+        math::checked_mul(pool_amount_usd, params.lp_amount_in as u128)?,
+        ctx.accounts.lp_token_mint.supply as u128,
+    )?)?;
+    let remove_amount = max_price.get_token_amount(
+        remove_amount_usd,
+        custody.decimals,
+        RoundingDirection::Down,
+    )?;
+
+    // Calculate remove liquidity fee, including the decaying fee of young deposits
+    let mut fee_amount =
+        pool.get_remove_liquidity_fee(token_id, remove_amount, custody, &token_ema_price)?;
+    if let Some(lp_ledger) = ctx.accounts.lp_ledger.as_mut() {
+        if lp_ledger.owner == Pubkey::default() {
+            lp_ledger.pool = pool.key();
+            lp_ledger.owner = ctx.accounts.owner.key();
+            lp_ledger.bump = ctx.bumps.lp_ledger.unwrap_or_default();
+        }
+        if custody.is_lp_fee_decay_enabled() {
+            let early_fee = lp_ledger.get_early_remove_fee(
+                params.lp_amount_in,
+                custody.fees.early_remove_liquidity,
+                custody.pricing.lp_fee_decay_period,
+                curtime,
+            )?;
+            let early_fee_amount = Pool::get_fee_amount(early_fee, remove_amount)?;
+            msg!("Early remove liquidity fee: {}", early_fee_amount);
+            fee_amount = std::cmp::min(
+                math::checked_add(fee_amount, early_fee_amount)?,
+                remove_amount,
+            );
+        }
+        lp_ledger.remove(params.lp_amount_in);
+    }
+    msg!("Collected remove liquidity fee: {}", fee_amount);
+    if params.max_fee_bps > 0 {
+        require!(
+            fee_amount <= conversions::apply_bps_ceil(remove_amount, params.max_fee_bps)?,
+            PerpetualsError::MaxFeeExceeded
+        );
+    }
+    let amount_in = math::checked_sub(remove_amount, fee_amount)?;
+    msg!("Amount withdrawn: {}", amount_in);
+
+    // Calculate swap of the withdrawn amount, as swap
+    msg!("Compute swap amount");
+    let amount_out = pool.get_swap_amount(
+        &swap_token_price,
+        &swap_token_ema_price,
+        &dispensed_token_price,
+        &dispensed_token_ema_price,
+        custody,
+        dispensing_custody,
+        amount_in,
+        curtime,
+    )?;
+    let fees = pool.get_swap_fees(
+        token_id,
+        token_id_out,
+        amount_in,
+        amount_out,
+        custody,
+        &swap_token_price,
+        dispensing_custody,
+        &dispensed_token_price,
+    )?;
+    msg!("Collected swap fees: {} {}", fees.0, fees.1);
+
+    let no_fee_amount = math::checked_sub(amount_out, fees.1)?;
+    msg!("Amount out: {}", no_fee_amount);
+
+    // Validate slippage protection on the final amount
+    require_gte!(
+        no_fee_amount,
+        params.min_amount_out,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // Check pool constraints
+    msg!("Check pool constraints");
+    // The withdrawn tokens are deposited back as the swap input, so the custody only
+    // loses the protocol share of both fees
+    let protocol_fee_liquidity = Pool::get_fee_amount(
+        custody.fees.get_protocol_share(FeeType::Liquidity),
+        fee_amount,
+    )?;
+    let protocol_fee_in =
+        Pool::get_fee_amount(custody.fees.get_protocol_share(FeeType::Swap), fees.0)?;
+    let protocol_fee_out = Pool::get_fee_amount(
+        dispensing_custody.fees.get_protocol_share(FeeType::Swap),
+        fees.1,
+    )?;
+    let custody_protocol_fee = math::checked_add(protocol_fee_liquidity, protocol_fee_in)?;
+    let withdrawal_amount = math::checked_add(no_fee_amount, protocol_fee_out)?;
+    require!(
+        pool.check_token_ratio(
+            RatioFlow::RemoveLiquidity,
+            token_id,
+            0,
+            custody_protocol_fee,
+            custody,
+            &token_ema_price
+        )? && pool.check_token_ratio(
+            RatioFlow::Swap,
+            token_id_out,
+            0,
+            withdrawal_amount,
+            dispensing_custody,
+            &dispensed_token_price
+        )?,
+        PerpetualsError::TokenRatioOutOfRange
+    );
+    require!(
+        dispensing_custody.get_available_amount()? >= withdrawal_amount,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // Transfer dispensed tokens from pool to user
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .dispensing_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        no_fee_amount,
+    )?;
+
+    // Burn LP tokens from user's LP token account
+    msg!("Burn LP tokens");
+    perpetuals.burn_tokens(
+        ctx.accounts.lp_token_mint.to_account_info(),
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.lp_amount_in,
+    )?;
+
+    // Update custody statistics
+    msg!("Update custody stats");
+    custody.collected_fees.remove_liquidity_usd = math::checked_add(
+        custody.collected_fees.remove_liquidity_usd,
+        token_ema_price.get_asset_amount_usd(
+            fee_amount,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
+    custody.volume_stats.remove_liquidity_usd = math::checked_add(
+        custody.volume_stats.remove_liquidity_usd,
+        remove_amount_usd as u128,
+    )?;
+    custody.collected_fees.swap_usd = math::checked_add(
+        custody.collected_fees.swap_usd,
+        swap_token_price.get_asset_amount_usd(
+            fees.0,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
+    custody.volume_stats.swap_usd = math::checked_add(
+        custody.volume_stats.swap_usd,
+        swap_token_price.get_asset_amount_usd(
+            amount_in,
+            custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
+    custody.assets.protocol_fees =
+        math::checked_add(custody.assets.protocol_fees, custody_protocol_fee)?;
+    custody.assets.owned = math::checked_sub(custody.assets.owned, custody_protocol_fee)?;
+
+    dispensing_custody.collected_fees.swap_usd = math::checked_add(
+        dispensing_custody.collected_fees.swap_usd,
+        dispensed_token_price.get_asset_amount_usd(
+            fees.1,
+            dispensing_custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
+    dispensing_custody.volume_stats.swap_usd = math::checked_add(
+        dispensing_custody.volume_stats.swap_usd,
+        dispensed_token_price.get_asset_amount_usd(
+            amount_out,
+            dispensing_custody.decimals,
+            RoundingDirection::Down,
+        )?
+            as u128,
+    )?;
+    dispensing_custody.assets.protocol_fees =
+        math::checked_add(dispensing_custody.assets.protocol_fees, protocol_fee_out)?;
+    dispensing_custody.assets.owned =
+        math::checked_sub(dispensing_custody.assets.owned, withdrawal_amount)?;
+
+    // Update borrow rates for both custodies based on new utilization
+    custody.update_borrow_rate(curtime)?;
+    dispensing_custody.update_borrow_rate(curtime)?;
+
+    // Update pool statistics
+    msg!("Update pool stats");
+    // Refresh pool AUM using EMA mode for accurate tracking
+    // The updated custodies are passed in memory, their account data is only written on exit
+    pool.aum_usd = pool.get_assets_under_management_usd_with_custody(
+        AumCalcMode::EMA,
+        ctx.remaining_accounts,
+        &[
+            (custody.key(), custody),
+            (dispensing_custody.key(), dispensing_custody),
+        ],
+        curtime,
+    )?;
+
+    Ok(())
+}
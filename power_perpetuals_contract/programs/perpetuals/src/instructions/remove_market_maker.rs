@@ -0,0 +1,105 @@
+//! RemoveMarketMaker instruction handler
+//!
+//! This instruction allows admins to deregister a market maker. The market maker account
+//! is closed and subsequent trades are priced at retail spreads and fees. This requires
+//! multisig approval.
+
+use {
+    crate::state::{
+        market_maker::MarketMaker,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for removing a market maker
+#[derive(Accounts)]
+pub struct RemoveMarketMaker<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA (receives the market maker account rent)
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        mut,
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the market maker is registered with
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Market maker account to be removed (closed, rent returned to transfer_authority)
+    #[account(
+        mut,
+        seeds = [b"market_maker",
+                 pool.key().as_ref(),
+                 market_maker.owner.as_ref()],
+        bump = market_maker.bump,
+        close = transfer_authority
+    )]
+    pub market_maker: Box<Account<'info, MarketMaker>>,
+}
+
+/// Parameters for removing a market maker
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveMarketMakerParams {}
+
+/// Remove a market maker
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+/// The market maker account is closed by the `close` constraint once fully signed.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn remove_market_maker<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveMarketMaker<'info>>,
+    params: &RemoveMarketMakerParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::RemoveMarketMaker, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    Ok(0)
+}
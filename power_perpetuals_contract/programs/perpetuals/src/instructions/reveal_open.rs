@@ -0,0 +1,102 @@
+//! RevealOpen instruction handler
+//!
+//! This instruction completes the commit-reveal flow started with commit_open. The
+//! revealed order is checked against the committed hash and opened with open_position
+//! at the current price, and the commitment is closed with the deposit refunded.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        instructions::open_position::{self, *},
+        state::order_commitment::OrderCommitment,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for revealing a committed order
+#[derive(Accounts)]
+pub struct RevealOpen<'info> {
+    /// Accounts of the position being opened, as for open_position
+    pub open: OpenPosition<'info>,
+
+    /// Order commitment account (closed, rent and deposit refunded to the owner)
+    #[account(
+        mut,
+        close = owner,
+        constraint = order_commitment.owner == open.owner.key(),
+        seeds = [b"order_commitment",
+                 open.owner.key().as_ref(),
+                 open.pool.key().as_ref()],
+        bump = order_commitment.bump
+    )]
+    pub order_commitment: Box<Account<'info, OrderCommitment>>,
+
+    /// Owner of the order, receives the closed commitment
+    ///
+    /// CHECK: Must be the position owner, validated by constraint
+    #[account(
+        mut,
+        constraint = owner.key() == open.owner.key()
+    )]
+    pub owner: AccountInfo<'info>,
+}
+
+/// Parameters for revealing a committed order
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RevealOpenParams {
+    /// Committed order (must be the first field, open position accounts are validated
+    /// against it)
+    pub order: OpenPositionParams,
+    /// Salt the order was committed with
+    pub salt: [u8; 32],
+}
+
+/// Reveal a committed order and open the position
+///
+/// The process:
+/// 1. Validates the reveal happens within the reveal window
+/// 2. Validates the order, custodies and salt match the committed hash
+/// 3. Opens the position with open_position at the current price
+/// 4. Closes the commitment, refunding the deposit
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Revealed order and salt
+///
+/// # Returns
+/// `Result<()>` - Success if the position was opened
+pub fn reveal_open<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RevealOpen<'info>>,
+    params: &RevealOpenParams,
+) -> Result<()> {
+    // Validate reveal window
+    let order_commitment = ctx.accounts.order_commitment.as_ref();
+    require!(
+        order_commitment.can_reveal(Clock::get()?.slot)?,
+        PerpetualsError::RevealWindowClosed
+    );
+
+    // Validate order against the commitment
+    let hash = OrderCommitment::get_hash(
+        &borsh::to_vec(&params.order)?,
+        &ctx.accounts.open.custody.key(),
+        &ctx.accounts.open.collateral_custody.key(),
+        &params.salt,
+    );
+    require!(
+        hash == order_commitment.hash,
+        PerpetualsError::CommitmentMismatch
+    );
+
+    // Open position at the current price
+    msg!("Reveal committed order");
+    open_position::open_position(
+        Context::new(
+            ctx.program_id,
+            &mut ctx.accounts.open,
+            ctx.remaining_accounts,
+            ctx.bumps.open,
+        ),
+        &params.order,
+    )
+}
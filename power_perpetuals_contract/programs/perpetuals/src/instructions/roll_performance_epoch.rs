@@ -0,0 +1,130 @@
+//! RollPerformanceEpoch instruction handler
+//!
+//! This instruction allows anyone to roll over the performance fee epoch of a pool once
+//! it has elapsed. If the LP token price is above the high-water mark, the protocol
+//! share of the gain is minted in LP tokens to the pool performance fee account and the
+//! high-water mark moves up to the price net of the fee.
+
+use {
+    crate::{
+        events::PerformanceEpochRolled,
+        math,
+        state::{
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for rolling over a performance fee epoch
+#[derive(Accounts)]
+pub struct RollPerformanceEpoch<'info> {
+    /// Payer account (signer, pays for transaction fees)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Transfer authority PDA for token minting
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, high-water mark and epoch will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint (mutable, performance fee is minted)
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// LP token account receiving the performance fee
+    #[account(
+        mut,
+        address = pool.performance_fee_account,
+        constraint = performance_fee_account.mint == lp_token_mint.key()
+    )]
+    pub performance_fee_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (read-only, unsigned)
+    //   pool.tokens.len() custody oracles (read-only, unsigned)
+}
+
+/// Roll over the performance fee epoch of a pool
+///
+/// The process:
+/// 1. Recalculates the pool AUM using EMA prices
+/// 2. Validates the performance fee is enabled and the epoch has elapsed
+/// 3. Moves the high-water mark to the price net of the fee and starts a new epoch
+/// 4. Mints the performance fee on the gain above the high-water mark
+/// 5. Emits a PerformanceEpochRolled event
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<u64>` - LP tokens minted as performance fee, or error
+pub fn roll_performance_epoch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RollPerformanceEpoch<'info>>,
+) -> Result<u64> {
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // Recalculate AUM
+    let curtime = perpetuals.get_time()?;
+    pool.aum_usd =
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    let aum_usd = math::checked_as_u64(pool.backstop.get_senior_aum_usd(pool.aum_usd))?;
+
+    // Roll over the epoch, updating the high-water mark
+    let (lp_price, fee_usd, fee_lp_amount) =
+        pool.roll_performance_epoch(aum_usd, ctx.accounts.lp_token_mint.supply, curtime)?;
+    msg!("LP token price: {}, high-water mark: {}", lp_price, pool.lp_price_hwm);
+
+    // Mint the performance fee
+    if fee_lp_amount > 0 {
+        msg!("Performance fee: {} USD, {} LP tokens", fee_usd, fee_lp_amount);
+        perpetuals.mint_tokens(
+            ctx.accounts.lp_token_mint.to_account_info(),
+            ctx.accounts.performance_fee_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            fee_lp_amount,
+        )?;
+    }
+
+    emit!(PerformanceEpochRolled {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        lp_price,
+        lp_price_hwm: pool.lp_price_hwm,
+        fee_usd,
+        fee_lp_amount,
+        time: curtime,
+    });
+
+    Ok(fee_lp_amount)
+}
@@ -0,0 +1,658 @@
+//! RollPosition instruction handler
+//!
+//! This instruction allows users to atomically roll an existing position into a new
+//! position on a different market of the same pool, optionally with a different power.
+//! The old position is settled and its collateral is carried over to the new position
+//! inside the pool, so no tokens are transferred and the trader is never flat between
+//! the two legs. A single roll fee is charged instead of the close and open fees.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::LockChanged,
+        math::{self, RoundingDirection},
+        state::{
+            compliance_freeze::ComplianceFreeze,
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::Perpetuals,
+            pool::{Pool, PositionSettlement},
+            position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+            trader_activity::TraderActivity,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for rolling a position
+///
+/// Both positions must share the same (stablecoin) collateral custody.
+#[derive(Accounts)]
+#[instruction(params: RollPositionParams)]
+pub struct RollPosition<'info> {
+    /// Owner of the positions (signer)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to roll from
+    ///
+    /// The `close = owner` constraint ensures the position account is closed
+    /// and rent is returned to the owner after execution.
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump,
+        close = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the token of the old position (mutable, stats will be updated)
+    #[account(
+        mut,
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the old position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// New position account to be initialized (PDA derived from owner, pool, new custody, side)
+    #[account(
+        init,
+        payer = owner,
+        space = Position::LEN,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 new_custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump
+    )]
+    pub new_position: Box<Account<'info, Position>>,
+
+    /// Custody account for the token of the new position (mutable, stats will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 new_custody.mint.as_ref()],
+        bump = new_custody.bump
+    )]
+    pub new_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the new position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = new_custody_oracle_account.key() == new_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub new_custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token shared by both positions
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+
+    /// Optional per-slot trade counter of the owner, required while the pool caps
+    /// trades per slot
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TraderActivity::LEN,
+        seeds = [b"trader_activity",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub trader_activity: Option<Box<Account<'info, TraderActivity>>>,
+
+    /// Optional summary of the old position, marked closed
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional summary of the new position, mirrors the opened position
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PositionSummary::LEN,
+        seeds = [b"position_summary",
+                 new_position.key().as_ref()],
+        bump
+    )]
+    pub new_position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional circuit-breaker feed, required if the protocol follows a risk oracle
+    ///
+    /// CHECK: Validated against the perpetuals risk oracle
+    pub risk_oracle_account: Option<AccountInfo<'info>>,
+
+    /// Optional compliance freeze list of the new custody, required while the pool
+    /// enforces freeze lists
+    #[account(
+        seeds = [b"compliance_freeze",
+                 new_custody.key().as_ref()],
+        bump = compliance_freeze.bump
+    )]
+    pub compliance_freeze: Option<Box<Account<'info, ComplianceFreeze>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+}
+
+/// Parameters for rolling a position
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct RollPositionParams {
+    /// Minimum acceptable exit price of the old position (slippage protection, scaled to PRICE_DECIMALS)
+    /// For longs: must be <= actual exit price
+    /// For shorts: must be >= actual exit price
+    pub exit_price: u64,
+    /// Maximum acceptable entry price of the new position (slippage protection, scaled to PRICE_DECIMALS)
+    /// For longs: must be >= actual entry price
+    /// For shorts: must be <= actual entry price
+    pub entry_price: u64,
+    /// New position size (in new position token's native decimals)
+    pub size: u64,
+    /// Power multiplier for the new position (1-5)
+    pub power: u8,
+}
+
+/// Roll an existing position into a new position on a different market
+///
+/// The process:
+/// 1. Validates permissions and inputs
+/// 2. Settles the old position (PnL, interest and roll fee) into collateral
+/// 3. Calculates the new entry price and validates slippage protection for both legs
+/// 4. Removes the old position from custody tracking and unlocks its funds
+/// 5. Initializes the new position with the settled collateral
+/// 6. Validates leverage and locks funds for the new position
+/// 7. Updates custody statistics
+///
+/// The roll fee (`fees.roll_position` of the old custody) replaces both the close and
+/// the open position fees. To change the power on the same market use change_power.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters including slippage prices, new size and power
+///
+/// # Returns
+/// `Result<()>` - Success if the position was rolled successfully
+pub fn roll_position(ctx: Context<RollPosition>, params: &RollPositionParams) -> Result<()> {
+    // Check permissions
+    // Rolling closes the old position and opens a new one
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let new_custody = ctx.accounts.new_custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_close_position
+            && perpetuals.permissions.allow_open_position
+            && custody.permissions.allow_close_position
+            && new_custody.permissions.allow_open_position
+            && !new_custody.is_stable,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Positions can't be rolled into or with collateral of markets being wound down
+    require!(
+        custody.lifecycle.allows_close()
+            && new_custody.lifecycle.allows_open()
+            && collateral_custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        !new_custody.oracle_safe_mode && !collateral_custody.oracle_safe_mode,
+        PerpetualsError::OracleSafeMode
+    );
+    // Rolling opens a new position, refused while the risk oracle flags an incident
+    perpetuals.risk_oracle.check_risk_increase(
+        ctx.accounts.risk_oracle_account.as_ref(),
+        perpetuals.get_time()?,
+    )?;
+    require!(
+        perpetuals.check_cpi_allowed(&ctx.accounts.pool, custody)
+            && perpetuals.check_cpi_allowed(&ctx.accounts.pool, new_custody),
+        PerpetualsError::CpiNotAllowed
+    );
+    // Frozen owners can't take on new exposure
+    ComplianceFreeze::validate_owner(
+        ctx.accounts.compliance_freeze.as_deref().map(AsRef::as_ref),
+        ctx.accounts.pool.compliance_freeze_enabled,
+        ctx.accounts.owner.key,
+    )?;
+
+    // Validate inputs
+    msg!("Validate inputs");
+    if params.exit_price == 0 || params.entry_price == 0 || params.size == 0 {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    new_custody.pricing.check_power(params.power)?;
+
+    // Check the per-slot trade cap of the owner
+    let max_trades_per_slot = ctx.accounts.pool.max_trades_per_slot;
+    require!(
+        max_trades_per_slot == 0 || ctx.accounts.trader_activity.is_some(),
+        PerpetualsError::TraderActivityRequired
+    );
+    if let Some(trader_activity) = ctx.accounts.trader_activity.as_mut() {
+        if trader_activity.owner == Pubkey::default() {
+            trader_activity.pool = ctx.accounts.pool.key();
+            trader_activity.owner = ctx.accounts.owner.key();
+            trader_activity.bump = ctx.bumps.trader_activity.unwrap_or_default();
+        }
+        trader_activity.record_trade(Clock::get()?.slot, max_trades_per_slot)?;
+    }
+    let position = ctx.accounts.position.as_mut();
+    let new_position = ctx.accounts.new_position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+    let side = position.side;
+
+    // Collateral stays in the pool, so both positions must be margined with the same
+    // stablecoin collateral custody
+    require_keys_neq!(custody.key(), new_custody.key());
+    require_keys_neq!(new_custody.key(), collateral_custody.key());
+    require!(
+        collateral_custody.is_stable
+            && !collateral_custody.is_virtual
+            && (side == Side::Short || new_custody.is_virtual),
+        PerpetualsError::InvalidCollateralCustody
+    );
+
+    // Get current time for calculations
+    let curtime = perpetuals.get_time()?;
+
+    // Custodies can reject closes within the minimum holding period
+    require!(
+        !custody.pricing.reject_early_close
+            || !custody.is_within_holding_period(position.open_time, curtime),
+        PerpetualsError::MinHoldingPeriod
+    );
+
+    // Expired markets can't be entered, expired positions are rejected when settled
+    require!(
+        !new_custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Get old position token prices (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Close,
+    )?;
+
+    // Get new position token prices (spot and EMA)
+    let new_token_price = new_custody.get_oracle_price(
+        &ctx.accounts.new_custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let new_token_ema_price = new_custody.get_oracle_price(
+        &ctx.accounts.new_custody_oracle_account.to_account_info(),
+        curtime,
+        new_custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Get collateral token prices (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Open,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Open,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Calculate exit price of the old position and validate slippage protection
+    let exit_price = pool.get_exit_price(&token_price, &token_ema_price, side, custody, curtime)?;
+    msg!("Exit price: {}", exit_price);
+    if side == Side::Long {
+        require_gte!(exit_price, params.exit_price, PerpetualsError::MaxPriceSlippage);
+    } else {
+        require_gte!(params.exit_price, exit_price, PerpetualsError::MaxPriceSlippage);
+    }
+
+    // Settle the old position, charging the roll fee in place of the close fee
+    msg!("Settle position");
+    let (settled_amount, fee_amount, roll_fee_usd, early_close_fee_usd, profit_usd, loss_usd) =
+        pool.get_roll_amount(
+            position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?;
+    let fee_amount_usd = math::checked_add(roll_fee_usd, early_close_fee_usd)?;
+
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Collected fee: {}", fee_amount);
+    msg!("Amount rolled: {}", settled_amount);
+
+    // Calculate entry price of the new position and validate slippage protection
+    let position_price =
+        pool.get_entry_price(&new_token_price, &new_token_ema_price, side, new_custody, curtime)?;
+    msg!("Entry price: {}", position_price);
+    if side == Side::Long {
+        require_gte!(
+            params.entry_price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            params.entry_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    // Unlock funds that were locked for the old position
+    msg!("Update custody stats");
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Settle the old position in custody accounts, the settled amount stays in the pool
+    // as collateral of the new position. The roll fee is charged in place of the entry
+    // fee of the new position and the early close fee on the old one.
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+    let interest_amount = collateral_token_ema_price.get_token_amount(
+        interest_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let hook_payload = PositionHookPayload {
+        event: PositionHookEvent::Close,
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        side,
+        size_usd: position.size_usd,
+        price: exit_price,
+        time: curtime,
+    };
+    pool.settle_position(
+        position,
+        custody,
+        collateral_custody,
+        &PositionSettlement {
+            amount: settled_amount,
+            fee_amount,
+            fee_usd: fee_amount_usd,
+            close_fee_usd: early_close_fee_usd,
+            open_fee_usd: roll_fee_usd,
+            profit_usd,
+            loss_usd,
+            interest_amount,
+            reentered: true,
+            ..PositionSettlement::default()
+        },
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &hook_payload,
+    )?;
+
+    // Calculate new position parameters
+    let position_oracle_price = OraclePrice {
+        price: position_price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+    let size_usd = position_oracle_price.get_asset_amount_usd(
+        params.size,
+        new_custody.decimals,
+        RoundingDirection::Up,
+    )?;
+    let min_collateral_price = collateral_token_price
+        .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
+    let collateral_usd = min_collateral_price.get_asset_amount_usd(
+        settled_amount,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+
+    let locked_amount = new_custody.get_locked_amount(
+        min_collateral_price.get_token_amount(
+            size_usd,
+            collateral_custody.decimals,
+            RoundingDirection::Up,
+        )?,
+        side,
+        params.power,
+        curtime,
+    )?;
+
+    let borrow_size_usd = if new_custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER
+    {
+        let max_collateral_price = if collateral_token_price < collateral_token_ema_price {
+            collateral_token_ema_price
+        } else {
+            collateral_token_price
+        };
+        max_collateral_price.get_asset_amount_usd(
+            locked_amount,
+            collateral_custody.decimals,
+            RoundingDirection::Down,
+        )?
+    } else {
+        size_usd
+    };
+
+    // Initialize new position account with the rolled collateral
+    msg!("Initialize new position");
+    new_position.owner = ctx.accounts.owner.key();
+    new_position.pool = pool.key();
+    new_position.custody = new_custody.key();
+    new_position.collateral_custody = collateral_custody.key();
+    new_position.open_time = curtime;
+    new_position.update_time = 0;
+    new_position.side = side;
+    new_position.power = params.power;
+    new_position.price = position_price;
+    new_position.size_usd = size_usd;
+    new_position.borrow_size_usd = borrow_size_usd;
+    new_position.collateral_usd = collateral_usd;
+    new_position.unrealized_profit_usd = 0;
+    new_position.unrealized_loss_usd = 0;
+    collateral_custody.snapshot_interest(new_position, curtime)?;
+    new_position.locked_amount = locked_amount;
+    new_position.collateral_amount = settled_amount;
+    new_position.payout_account = position.payout_account;
+    new_position.bump = ctx.bumps.new_position;
+
+    // Validate new position leverage and locked amount
+    msg!("Check position risks");
+    require!(
+        new_position.locked_amount > 0,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    if let Some(error) = pool.get_leverage_error(
+        new_position,
+        &new_token_price,
+        &new_token_ema_price,
+        new_custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        true,
+    )? {
+        return Err(error.into());
+    }
+
+    // Label the position with its risk tier under the custody thresholds
+    new_position.risk_tier = pool
+        .get_liquidation_check(
+            new_position,
+            &new_token_price,
+            &new_token_ema_price,
+            new_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?
+        .risk_tier;
+
+    // Lock funds for potential profit payouts
+    collateral_custody.lock_funds(new_position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: new_position.key(),
+        is_lock: true,
+        amount: new_position.locked_amount,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Add the new position to custody tracking
+    new_custody.volume_stats.open_position_usd = math::checked_add(
+        new_custody.volume_stats.open_position_usd,
+        size_usd as u128,
+    )?;
+    if side == Side::Long {
+        new_custody.trade_stats.oi_long_usd =
+            math::checked_add(new_custody.trade_stats.oi_long_usd, size_usd)?;
+    } else {
+        new_custody.trade_stats.oi_short_usd =
+            math::checked_add(new_custody.trade_stats.oi_short_usd, size_usd)?;
+    }
+    new_custody.add_position(
+        new_position,
+        &new_token_ema_price,
+        curtime,
+        Some(collateral_custody),
+    )?;
+    collateral_custody.update_borrow_rate(curtime)?;
+
+    // Mirror the roll into the position summaries
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.clear(curtime);
+    }
+    if let Some(new_position_summary) = ctx.accounts.new_position_summary.as_mut() {
+        new_position_summary.bump = ctx.bumps.new_position_summary.unwrap_or_default();
+        new_position_summary.update(
+            new_position,
+            pool.get_token_id(&new_custody.key())?,
+            curtime,
+        )?;
+    }
+
+    // Notify the pool position hook of the new position, the close of the old one was
+    // reported when it was settled
+    pool.position_hook.invoke(
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &PositionHookPayload {
+            event: PositionHookEvent::Open,
+            pool: pool.key(),
+            position: new_position.key(),
+            owner: new_position.owner,
+            custody: new_custody.key(),
+            side: new_position.side,
+            size_usd: new_position.size_usd,
+            price: position_price,
+            time: curtime,
+        },
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! RunCrank instruction handler
+//!
+//! This instruction allows anyone to run a pool maintenance task. Keepers that run a
+//! task which was due according to the pool crank schedule are paid a SOL bounty from
+//! the crank budget, so independent keepers are incentivized to maintain the protocol.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::PerformanceEpochRolled,
+        math,
+        state::{
+            crank_state::{CrankState, CrankTask},
+            custody::{Custody, VolatilityType},
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token},
+};
+
+/// Accounts required for running a crank task
+#[derive(Accounts)]
+pub struct RunCrank<'info> {
+    /// Keeper account (signer, receives the bounty)
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, AUM will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Crank state account of the pool (mutable, holds the bounty budget)
+    #[account(
+        mut,
+        seeds = [b"crank_state",
+                 pool.key().as_ref()],
+        bump = crank_state.bump
+    )]
+    pub crank_state: Box<Account<'info, CrankState>>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (unsigned, writable for UpdateBorrowRates and
+    //     UpdateFunding)
+    //   UpdatePoolAum and RollPerformanceEpoch:
+    //     pool.tokens.len() custody oracles (read-only, unsigned)
+    //   UpdateFunding:
+    //     volatility accounts of the custodies with a volatility feed, in custody order
+    //     (read-only, unsigned)
+    //   RollPerformanceEpoch:
+    //     transfer authority PDA (read-only, unsigned)
+    //     LP token mint (writable, unsigned)
+    //     pool performance fee account (writable, unsigned)
+    //     token program
+}
+
+/// Parameters for running a crank task
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RunCrankParams {
+    /// Task to run
+    pub task: CrankTask,
+}
+
+/// Run a pool maintenance task
+///
+/// The process:
+/// 1. Runs the task (always, whether due or not)
+/// 2. If the task was due, records the run slot and pays the bounty to the keeper
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Task to run
+///
+/// # Returns
+/// `Result<u64>` - Bounty paid to the keeper (in lamports), or error
+pub fn run_crank<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RunCrank<'info>>,
+    params: &RunCrankParams,
+) -> Result<u64> {
+    let pool = ctx.accounts.pool.as_mut();
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let slot = Clock::get()?.slot;
+
+    // Run the task
+    match params.task {
+        CrankTask::UpdatePoolAum => {
+            msg!("Update pool asset under management");
+            pool.aum_usd = pool.get_assets_under_management_usd(
+                AumCalcMode::EMA,
+                ctx.remaining_accounts,
+                curtime,
+            )?;
+            msg!("Updated value: {}", pool.aum_usd);
+        }
+        CrankTask::UpdateBorrowRates => {
+            msg!("Update custody borrow rates");
+            for custody_info in get_custody_infos(pool, ctx.remaining_accounts)? {
+                let mut custody = Account::<Custody>::try_from(custody_info)?;
+                custody.update_borrow_rate(curtime)?;
+                custody.exit(&crate::ID)?;
+            }
+        }
+        CrankTask::UpdateFunding => {
+            msg!("Update custody funding");
+            let custody_infos = get_custody_infos(pool, ctx.remaining_accounts)?;
+            let mut vol_accounts = ctx.remaining_accounts[custody_infos.len()..].iter();
+            for custody_info in custody_infos {
+                let mut custody = Account::<Custody>::try_from(custody_info)?;
+                if custody.volatility.vol_type != VolatilityType::None {
+                    let vol_account = vol_accounts
+                        .next()
+                        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+                    custody.volatility_state.vol =
+                        custody.volatility.get_vol(vol_account, curtime)?;
+                    custody.volatility_state.last_update = curtime;
+                }
+                custody.update_borrow_rate(curtime)?;
+                custody.exit(&crate::ID)?;
+            }
+        }
+        CrankTask::RollPerformanceEpoch => {
+            msg!("Roll performance epoch");
+            let perpetuals = ctx.accounts.perpetuals.as_ref();
+            let accounts_len = pool.custodies.len() * 2;
+            if ctx.remaining_accounts.len() < accounts_len + 4 {
+                return Err(ProgramError::NotEnoughAccountKeys.into());
+            }
+            let (aum_accounts, accounts) = ctx.remaining_accounts.split_at(accounts_len);
+            let (transfer_authority, lp_token_mint_info, performance_fee_account, token_program) =
+                (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+            require_keys_eq!(
+                transfer_authority.key(),
+                Pubkey::create_program_address(
+                    &[b"transfer_authority", &[perpetuals.transfer_authority_bump]],
+                    &crate::ID
+                )
+                .map_err(|_| ProgramError::InvalidSeeds)?
+            );
+            require_keys_eq!(
+                lp_token_mint_info.key(),
+                Pubkey::create_program_address(
+                    &[b"lp_token_mint", pool.key().as_ref(), &[pool.lp_token_bump]],
+                    &crate::ID
+                )
+                .map_err(|_| ProgramError::InvalidSeeds)?
+            );
+            require_keys_eq!(performance_fee_account.key(), pool.performance_fee_account);
+            require_keys_eq!(token_program.key(), Token::id());
+            let lp_token_mint = Account::<Mint>::try_from(lp_token_mint_info)?;
+
+            pool.aum_usd =
+                pool.get_assets_under_management_usd(AumCalcMode::EMA, aum_accounts, curtime)?;
+            let aum_usd = math::checked_as_u64(pool.backstop.get_senior_aum_usd(pool.aum_usd))?;
+            let (lp_price, fee_usd, fee_lp_amount) =
+                pool.roll_performance_epoch(aum_usd, lp_token_mint.supply, curtime)?;
+            msg!("LP token price: {}, high-water mark: {}", lp_price, pool.lp_price_hwm);
+
+            if fee_lp_amount > 0 {
+                msg!("Performance fee: {} USD, {} LP tokens", fee_usd, fee_lp_amount);
+                perpetuals.mint_tokens(
+                    lp_token_mint_info.clone(),
+                    performance_fee_account.clone(),
+                    transfer_authority.clone(),
+                    token_program.clone(),
+                    fee_lp_amount,
+                )?;
+            }
+
+            emit!(PerformanceEpochRolled {
+                pool: pool.key(),
+                seq: pool.next_event_seq()?,
+                lp_price,
+                lp_price_hwm: pool.lp_price_hwm,
+                fee_usd,
+                fee_lp_amount,
+                time: curtime,
+            });
+        }
+    }
+
+    // Pay the keeper if the task was due
+    let crank_state_info = ctx.accounts.crank_state.to_account_info();
+    let crank_state = ctx.accounts.crank_state.as_mut();
+    if !crank_state.is_due(params.task, slot) {
+        msg!("Task was not due");
+        return Ok(0);
+    }
+    let bounty = crank_state.pay_bounty(
+        params.task,
+        slot,
+        crank_state_info,
+        ctx.accounts.keeper.to_account_info(),
+    )?;
+    msg!("Bounty paid: {}", bounty);
+
+    Ok(bounty)
+}
+
+/// Get the writable custody accounts of the pool from the remaining accounts
+fn get_custody_infos<'a, 'info>(
+    pool: &Pool,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<&'a [AccountInfo<'info>]> {
+    if remaining_accounts.len() < pool.custodies.len() {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+    let custody_infos = &remaining_accounts[..pool.custodies.len()];
+    for (custody_info, &custody_key) in custody_infos.iter().zip(pool.custodies.iter()) {
+        require_keys_eq!(custody_info.key(), custody_key);
+        require!(
+            custody_info.is_writable,
+            PerpetualsError::InvalidCustodyState
+        );
+    }
+    Ok(custody_infos)
+}
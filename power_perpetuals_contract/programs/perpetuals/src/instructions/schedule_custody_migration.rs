@@ -0,0 +1,162 @@
+//! ScheduleCustodyMigration instruction handler
+//!
+//! This instruction allows admins to schedule the migration of a custody to a new token
+//! mint. The custody is paused (no new positions, swaps or deposits) until the migration
+//! is executed with migrate_custody_mint after the timelock. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            custody_migration::CustodyMigration,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::Mint,
+};
+
+/// Accounts required for scheduling a custody migration
+#[derive(Accounts)]
+pub struct ScheduleCustodyMigration<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to migrate (mutable, will be paused)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Custody migration account (PDA derived from custody)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = CustodyMigration::LEN,
+        seeds = [b"custody_migration",
+                 custody.key().as_ref()],
+        bump
+    )]
+    pub custody_migration: Box<Account<'info, CustodyMigration>>,
+
+    /// Mint the custody will be migrated to
+    #[account()]
+    pub new_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for scheduling a custody migration
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ScheduleCustodyMigrationParams {
+    /// New token amount per old token amount, after decimals adjustment (RATE_DECIMALS)
+    pub conversion_rate: u64,
+    /// Delay before the migration can be executed (seconds, at least MIN_DELAY_SEC)
+    pub delay_sec: i64,
+}
+
+/// Schedule migration of a custody to a new mint
+///
+/// The process:
+/// 1. Validates inputs
+/// 2. Validates multisig signatures (requires enough admin signatures)
+/// 3. Records the migration and its execution time
+/// 4. Pauses the custody, keeping only exits (close, collateral and liquidity withdrawal)
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Conversion rate and timelock delay
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn schedule_custody_migration<'info>(
+    ctx: Context<'_, '_, '_, 'info, ScheduleCustodyMigration<'info>>,
+    params: &ScheduleCustodyMigrationParams,
+) -> Result<u8> {
+    // Validate inputs
+    if params.conversion_rate == 0
+        || params.delay_sec < CustodyMigration::MIN_DELAY_SEC
+        || ctx.accounts.new_mint.key() == ctx.accounts.custody.mint
+    {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ScheduleCustodyMigration, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // A migration can only be scheduled once
+    let custody_migration = ctx.accounts.custody_migration.as_mut();
+    require!(
+        custody_migration.custody == Pubkey::default(),
+        PerpetualsError::InvalidCustodyState
+    );
+
+    // Record migration
+    let custody = ctx.accounts.custody.as_mut();
+    custody_migration.custody = custody.key();
+    custody_migration.new_mint = ctx.accounts.new_mint.key();
+    custody_migration.conversion_rate = params.conversion_rate;
+    custody_migration.execute_time = ctx
+        .accounts
+        .perpetuals
+        .get_time()?
+        .saturating_add(params.delay_sec);
+    custody_migration.permissions = custody.permissions;
+    custody_migration.allow_cpi = custody.allow_cpi;
+    custody_migration.bump = ctx.bumps.custody_migration;
+    msg!("Migration execute time: {}", custody_migration.execute_time);
+
+    // Pause custody
+    custody.permissions.allow_swap = false;
+    custody.permissions.allow_add_liquidity = false;
+    custody.permissions.allow_open_position = false;
+    custody.permissions.allow_size_change = false;
+
+    Ok(0)
+}
@@ -0,0 +1,163 @@
+//! ScheduleForceSettlement instruction handler
+//!
+//! This instruction allows admins to schedule the emergency settlement of a position
+//! whose custody oracle is permanently broken, at an admin supplied price. The position
+//! can be settled with force_settle_position after the timelock. This requires multisig
+//! approval.
+
+use {
+    crate::{
+        events::ForceSettlementScheduled,
+        state::{
+            custody::Custody,
+            force_settlement::ForceSettlement,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for scheduling a force settlement
+#[derive(Accounts)]
+pub struct ScheduleForceSettlement<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the position belongs to (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position to settle
+    #[account(
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Force settlement account (PDA derived from position)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = ForceSettlement::LEN,
+        seeds = [b"force_settlement",
+                 position.key().as_ref()],
+        bump
+    )]
+    pub force_settlement: Box<Account<'info, ForceSettlement>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for scheduling a force settlement
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ScheduleForceSettlementParams {
+    /// Settlement price of the position token, scaled to PRICE_DECIMALS
+    pub price: u64,
+    /// Delay before the settlement can be executed (seconds, at least MIN_DELAY_SEC)
+    pub delay_sec: i64,
+}
+
+/// Schedule the emergency settlement of a position
+///
+/// The process:
+/// 1. Validates inputs
+/// 2. Validates multisig signatures (requires enough admin signatures)
+/// 3. Records the settlement price and execution time, replacing any earlier schedule
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Settlement price and timelock delay
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn schedule_force_settlement<'info>(
+    ctx: Context<'_, '_, '_, 'info, ScheduleForceSettlement<'info>>,
+    params: &ScheduleForceSettlementParams,
+) -> Result<u8> {
+    // Validate inputs
+    if params.price == 0 || params.delay_sec < ForceSettlement::MIN_DELAY_SEC {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::ScheduleForceSettlement, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Record settlement, a new schedule restarts the timelock
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let force_settlement = ctx.accounts.force_settlement.as_mut();
+    force_settlement.position = ctx.accounts.position.key();
+    force_settlement.price = params.price;
+    force_settlement.execute_time = curtime.saturating_add(params.delay_sec);
+    force_settlement.bump = ctx.bumps.force_settlement;
+    msg!(
+        "Force settlement price: {}, execute time: {}",
+        force_settlement.price,
+        force_settlement.execute_time
+    );
+
+    let pool = ctx.accounts.pool.as_mut();
+    emit!(ForceSettlementScheduled {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: force_settlement.position,
+        owner: ctx.accounts.position.owner,
+        custody: ctx.accounts.custody.key(),
+        price: force_settlement.price,
+        execute_time: force_settlement.execute_time,
+        time: curtime,
+    });
+
+    Ok(0)
+}
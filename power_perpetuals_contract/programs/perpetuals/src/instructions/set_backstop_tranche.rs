@@ -0,0 +1,127 @@
+//! SetBackstopTranche instruction handler
+//!
+//! This instruction allows admins to create the backstop token mint of a pool and set
+//! the fee boost of its backstop tranche. The backstop absorbs realized trader profits
+//! before pool LPs and earns a boosted share of pool gains in exchange. This requires
+//! multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token},
+};
+
+/// Accounts required for setting the pool backstop tranche
+#[derive(Accounts)]
+pub struct SetBackstopTranche<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, backstop tranche will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Backstop token mint of the pool (owned by transfer_authority PDA)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        mint::authority = transfer_authority,
+        mint::freeze_authority = transfer_authority,
+        mint::decimals = Perpetuals::LP_DECIMALS,
+        seeds = [b"backstop_token_mint",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub backstop_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for setting the pool backstop tranche
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetBackstopTrancheParams {
+    /// Share of pool gains paid to the backstop on top of its pro-rata share (BPS)
+    pub fee_boost_bps: u64,
+}
+
+/// Set up the backstop tranche of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Records the backstop token mint and validates the new fee boost
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New fee boost
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_backstop_tranche<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetBackstopTranche<'info>>,
+    params: &SetBackstopTrancheParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetBackstopTranche, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    let backstop = &mut ctx.accounts.pool.backstop;
+    backstop.fee_boost_bps = params.fee_boost_bps;
+    backstop.token_bump = ctx.bumps.backstop_token_mint;
+    msg!("Backstop fee boost: {}", params.fee_boost_bps);
+
+    require!(backstop.validate(), PerpetualsError::InvalidBackstopConfig);
+
+    Ok(0)
+}
@@ -0,0 +1,115 @@
+//! SetCollateralWithdrawalLimit instruction handler
+//!
+//! This instruction allows admins to rate-limit collateral removal from large positions:
+//! positions at or above a size threshold can only remove a share of their collateral
+//! per time window, enforced in remove_collateral. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::{CollateralWithdrawalLimit, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool collateral withdrawal limit
+#[derive(Accounts)]
+pub struct SetCollateralWithdrawalLimit<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, withdrawal limit will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool collateral withdrawal limit
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCollateralWithdrawalLimitParams {
+    /// Position size from which collateral removal is limited (USD, scaled to USD_DECIMALS)
+    pub min_size_usd: u64,
+    /// Share of the position collateral removable per window (BPS, 0 = disabled)
+    pub max_withdrawal_bps: u64,
+    /// Window duration in seconds
+    pub window_sec: i64,
+}
+
+/// Set the collateral withdrawal limit of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the limit configuration
+/// 3. Updates the pool collateral withdrawal limit
+///
+/// Windows already started on positions keep their start time, the new share applies
+/// to their next removal.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New withdrawal limit
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_collateral_withdrawal_limit<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCollateralWithdrawalLimit<'info>>,
+    params: &SetCollateralWithdrawalLimitParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCollateralWithdrawalLimit, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate parameters
+    let limit = CollateralWithdrawalLimit {
+        min_size_usd: params.min_size_usd,
+        max_withdrawal_bps: params.max_withdrawal_bps,
+        window_sec: params.window_sec,
+    };
+    require!(limit.validate(), PerpetualsError::InvalidPoolConfig);
+
+    // Update pool
+    ctx.accounts.pool.collateral_withdrawal_limit = limit;
+    msg!(
+        "Collateral withdrawal limit: {} bps per {} sec",
+        params.max_withdrawal_bps,
+        params.window_sec
+    );
+
+    Ok(0)
+}
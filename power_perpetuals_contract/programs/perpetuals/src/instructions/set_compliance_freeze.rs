@@ -0,0 +1,139 @@
+//! SetComplianceFreeze instruction handler
+//!
+//! This instruction allows admins to configure the compliance freeze list of a custody.
+//! While enforcement is enabled on the pool, frozen owners can close positions and
+//! withdraw collateral but can't open or increase positions. Admins set the compliance
+//! authority that can maintain the list and can freeze or unfreeze owners directly.
+//! This requires multisig approval.
+
+use {
+    crate::state::{
+        compliance_freeze::ComplianceFreeze,
+        custody::Custody,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for configuring a custody freeze list
+#[derive(Accounts)]
+pub struct SetComplianceFreeze<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, freeze enforcement flag will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody the freeze list applies to
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Freeze list account (PDA derived from custody)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = ComplianceFreeze::LEN,
+        seeds = [b"compliance_freeze",
+                 custody.key().as_ref()],
+        bump
+    )]
+    pub compliance_freeze: Box<Account<'info, ComplianceFreeze>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for configuring a custody freeze list
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetComplianceFreezeParams {
+    /// Whether the pool checks opens and increases against custody freeze lists
+    /// (every tradable custody of the pool needs a freeze list while enabled)
+    pub enabled: bool,
+    /// Authority allowed to maintain the freeze list
+    pub compliance_authority: Pubkey,
+    /// Owners to freeze
+    pub add_owners: Vec<Pubkey>,
+    /// Owners to unfreeze
+    pub remove_owners: Vec<Pubkey>,
+}
+
+/// Configure a custody compliance freeze list
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Initializes or updates the freeze list account
+/// 3. Applies owner additions, then removals
+/// 4. Enables or disables freeze list enforcement on the pool
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Freeze list settings and owner changes
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_compliance_freeze<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetComplianceFreeze<'info>>,
+    params: &SetComplianceFreezeParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetComplianceFreeze, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update freeze list data
+    let compliance_freeze = ctx.accounts.compliance_freeze.as_mut();
+    compliance_freeze.custody = ctx.accounts.custody.key();
+    compliance_freeze.compliance_authority = params.compliance_authority;
+    compliance_freeze.bump = ctx.bumps.compliance_freeze;
+    for owner in params.add_owners.iter() {
+        compliance_freeze.add_owner(*owner)?;
+    }
+    for owner in params.remove_owners.iter() {
+        compliance_freeze.remove_owner(owner);
+    }
+    msg!("Frozen owners: {}", compliance_freeze.owners.len());
+
+    // Update pool
+    ctx.accounts.pool.compliance_freeze_enabled = params.enabled;
+
+    Ok(0)
+}
@@ -0,0 +1,112 @@
+//! SetCrankConfig instruction handler
+//!
+//! This instruction allows admins to configure the crank scheduler of a pool: how often
+//! each maintenance task is due and the SOL bounty paid to keepers that run a due task.
+//! The crank state account is created on first use. This requires multisig approval.
+
+use {
+    crate::state::{
+        crank_state::CrankState,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for configuring the crank scheduler of a pool
+#[derive(Accounts)]
+pub struct SetCrankConfig<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the cranks maintain
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Crank state account (PDA derived from pool)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = CrankState::LEN,
+        seeds = [b"crank_state",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub crank_state: Box<Account<'info, CrankState>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for configuring the crank scheduler of a pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCrankConfigParams {
+    /// Minimum number of slots between two paid runs, indexed by CrankTask (0 = not paid)
+    pub task_intervals: [u64; CrankState::NUM_TASKS],
+    /// Bounty paid per due run (in lamports)
+    pub bounty_lamports: u64,
+}
+
+/// Configure the crank scheduler of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Initializes or updates the crank state account
+///
+/// The bounty budget is funded separately by transferring lamports to the crank state.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Task intervals and bounty
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_crank_config<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCrankConfig<'info>>,
+    params: &SetCrankConfigParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCrankConfig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update crank state data
+    let crank_state = ctx.accounts.crank_state.as_mut();
+    crank_state.pool = ctx.accounts.pool.key();
+    crank_state.task_intervals = params.task_intervals;
+    crank_state.bounty_lamports = params.bounty_lamports;
+    crank_state.bump = ctx.bumps.crank_state;
+
+    Ok(0)
+}
@@ -9,7 +9,7 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::{BorrowRateParams, Custody, Fees, PricingParams},
+            custody::{BorrowRateParams, Custody, EntryFeeTier, Fees, PricingParams},
             multisig::{AdminInstruction, Multisig},
             oracle::OracleParams,
             perpetuals::Permissions,
@@ -67,8 +67,12 @@ pub struct SetCustodyConfigParams {
     pub pricing: PricingParams,
     /// Permission flags for various operations
     pub permissions: Permissions,
+    /// Allow user-facing trading instructions to be invoked through CPI
+    pub allow_cpi: bool,
     /// Fee structure for this custody
     pub fees: Fees,
+    /// Open position fee tiers by position size (unused tiers have min_size_usd 0)
+    pub entry_fee_tiers: [EntryFeeTier; Custody::MAX_ENTRY_FEE_TIERS],
     /// Borrow rate parameters
     pub borrow_rate: BorrowRateParams,
     /// Token ratios for this custody (must match pool's ratio count)
@@ -138,9 +142,14 @@ pub fn set_custody_config<'info>(
     custody.oracle = params.oracle;
     custody.pricing = params.pricing;
     custody.permissions = params.permissions;
+    custody.allow_cpi = params.allow_cpi;
     custody.fees = params.fees;
+    custody.entry_fee_tiers = params.entry_fee_tiers;
     custody.borrow_rate = params.borrow_rate;
 
+    // Params must stay within the program-wide safety bounds
+    require!(custody.validate_bounds(), PerpetualsError::ParamOutOfBounds);
+
     // Validate custody configuration after updates
     // Ensure all parameters are within acceptable ranges
     if !custody.validate() {
@@ -0,0 +1,116 @@
+//! SetCustodyExchangeRate instruction handler
+//!
+//! This instruction allows admins to configure the exchange rate source of a custody
+//! holding a liquid staking token. The custody oracle prices the underlying token and
+//! the exchange rate converts it to the fair value of the custody token for AUM,
+//! liquidity and collateral valuation. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::{Custody, ExchangeRateParams, ExchangeRateState},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting custody exchange rate source
+#[derive(Accounts)]
+pub struct SetCustodyExchangeRate<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to update (mutable, exchange rate config will be changed)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for setting custody exchange rate source
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCustodyExchangeRateParams {
+    /// Exchange rate source, `ExchangeRateType::None` values the token at the oracle price
+    pub exchange_rate: ExchangeRateParams,
+}
+
+/// Set exchange rate source for a custody
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates custody exchange rate config and clears the cached rate
+///
+/// With a rate source configured, valuations fail until update_exchange_rate has
+/// cached a fresh rate.
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New exchange rate config
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_custody_exchange_rate<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCustodyExchangeRate<'info>>,
+    params: &SetCustodyExchangeRateParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCustodyExchangeRate, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update custody data
+    let custody = ctx.accounts.custody.as_mut();
+    custody.exchange_rate = params.exchange_rate;
+    custody.exchange_rate_state = ExchangeRateState::default();
+
+    if !custody.validate() {
+        err!(PerpetualsError::InvalidCustodyConfig)
+    } else {
+        Ok(0)
+    }
+}
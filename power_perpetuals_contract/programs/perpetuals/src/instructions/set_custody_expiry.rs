@@ -0,0 +1,123 @@
+//! SetCustodyExpiry instruction handler
+//!
+//! This instruction allows admins to turn a custody market into a power futures market
+//! by setting an expiry timestamp. After expiry only closing and settlement are allowed,
+//! and borrow interest stops accruing. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting custody expiry
+#[derive(Accounts)]
+pub struct SetCustodyExpiry<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to update (mutable, expiry will be changed)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for setting custody expiry
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCustodyExpiryParams {
+    /// Expiry timestamp (unix seconds), 0 to make the market perpetual
+    pub expiry_time: i64,
+}
+
+/// Set expiry time for a custody market
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates that the market has not already expired and the new expiry is in the future
+/// 3. Updates custody expiry time
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New expiry time
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_custody_expiry<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCustodyExpiry<'info>>,
+    params: &SetCustodyExpiryParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCustodyExpiry, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate inputs
+    // An expired market can't be reopened or rescheduled
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+    if params.expiry_time != 0 && params.expiry_time <= curtime {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+
+    // Update custody data
+    custody.expiry_time = params.expiry_time;
+
+    if !custody.validate() {
+        err!(PerpetualsError::InvalidCustodyConfig)
+    } else {
+        Ok(0)
+    }
+}
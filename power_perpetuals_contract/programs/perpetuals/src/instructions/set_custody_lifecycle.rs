@@ -0,0 +1,135 @@
+//! SetCustodyLifecycle instruction handler
+//!
+//! This instruction allows admins to move a custody market through its wind-down
+//! lifecycle when it is delisted: Active -> CloseOnly -> Settled -> Retired. A close-only
+//! market only lets positions be reduced and closed, and is settled automatically once
+//! its last position is gone. A settled market only lets liquidity providers exit, and a
+//! retired market can be removed with remove_custody. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::MarketLifecycleChanged,
+        state::{
+            custody::{Custody, MarketLifecycle},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for changing the lifecycle of a custody market
+#[derive(Accounts)]
+pub struct SetCustodyLifecycle<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to update (mutable, lifecycle will be changed)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for changing the lifecycle of a custody market
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCustodyLifecycleParams {
+    /// Next lifecycle stage
+    pub lifecycle: MarketLifecycle,
+}
+
+/// Change the lifecycle of a custody market
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the transition, a market can only be settled without open positions
+/// 3. Updates the custody lifecycle
+/// 4. Emits a MarketLifecycleChanged event
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Next lifecycle stage
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_custody_lifecycle<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCustodyLifecycle<'info>>,
+    params: &SetCustodyLifecycleParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCustodyLifecycle, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate the transition
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        custody.lifecycle.can_transition_to(params.lifecycle),
+        PerpetualsError::InvalidLifecycleTransition
+    );
+    if params.lifecycle == MarketLifecycle::Settled {
+        require!(
+            custody.has_no_positions(),
+            PerpetualsError::InvalidLifecycleTransition
+        );
+    }
+
+    // Update custody
+    let previous_lifecycle = custody.lifecycle;
+    custody.lifecycle = params.lifecycle;
+    msg!("Lifecycle: {:?} -> {:?}", previous_lifecycle, params.lifecycle);
+
+    let pool = ctx.accounts.pool.as_mut();
+    emit!(MarketLifecycleChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: custody.key(),
+        previous_lifecycle,
+        lifecycle: params.lifecycle,
+        time: ctx.accounts.perpetuals.get_time()?,
+    });
+
+    Ok(0)
+}
@@ -0,0 +1,116 @@
+//! SetCustodyVolatility instruction handler
+//!
+//! This instruction allows admins to configure the volatility feed of a custody. While
+//! the feed is fresh, the trade and swap spreads, the borrow rate slopes and the
+//! convexity reserve scale with the feed volatility relative to a reference volatility.
+//! This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::{Custody, VolatilityParams, VolatilityState},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting custody volatility feed
+#[derive(Accounts)]
+pub struct SetCustodyVolatility<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account to update (mutable, volatility config will be changed)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for setting custody volatility feed
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetCustodyVolatilityParams {
+    /// Volatility feed, `VolatilityType::None` keeps the static pricing params
+    pub volatility: VolatilityParams,
+}
+
+/// Set volatility feed for a custody
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates custody volatility config and clears the cached volatility
+/// 3. Validates the config, including the ParamBounds volatility multiplier cap
+///
+/// The static pricing params apply until update_volatility has cached a fresh
+/// volatility.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New volatility config
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_custody_volatility<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCustodyVolatility<'info>>,
+    params: &SetCustodyVolatilityParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCustodyVolatility, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update custody data
+    let custody = ctx.accounts.custody.as_mut();
+    custody.volatility = params.volatility;
+    custody.volatility_state = VolatilityState::default();
+
+    if !custody.validate() {
+        return err!(PerpetualsError::InvalidCustodyConfig);
+    }
+    require!(custody.validate_bounds(), PerpetualsError::ParamOutOfBounds);
+
+    Ok(0)
+}
@@ -2,15 +2,21 @@
 //! 
 //! This instruction allows admins to set or update custom oracle prices for a custody.
 //! The oracle account is created if it doesn't exist (init_if_needed). This requires
-//! multisig approval and is used for admin-controlled price feeds.
+//! multisig approval and is used for admin-controlled price feeds, and as the fallback
+//! that keeps custody prices alive while the oracle authority of the permissionless
+//! path is unreachable. Every admin price is logged with its deviation from the
+//! previous price.
 
 use {
-    crate::state::{
-        custody::Custody,
-        multisig::{AdminInstruction, Multisig},
-        oracle::CustomOracle,
-        perpetuals::Perpetuals,
-        pool::Pool,
+    crate::{
+        events::AdminOraclePriceSet,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            oracle::CustomOracle,
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -37,8 +43,9 @@ pub struct SetCustomOraclePrice<'info> {
     )]
     pub perpetuals: Box<Account<'info, Perpetuals>>,
 
-    /// Pool account
+    /// Pool account (mutable, event sequence will be updated)
     #[account(
+        mut,
         seeds = [b"pool",
                  pool.name.as_bytes()],
         bump = pool.bump
@@ -60,7 +67,7 @@ pub struct SetCustomOraclePrice<'info> {
         init_if_needed,
         payer = admin,
         space = CustomOracle::LEN,
-        //constraint = oracle_account.key() == custody.oracle.oracle_account,
+        //constraint = oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount,
         seeds = [b"oracle_account",
                  pool.key().as_ref(),
                  custody.mint.as_ref()],
@@ -80,7 +87,8 @@ pub struct SetCustomOraclePriceParams {
     pub expo: i32,
     /// Price confidence interval
     pub conf: u64,
-    /// Exponential moving average price
+    /// Exponential moving average price, 0 to compute it on-chain. A non-zero value
+    /// replaces the on-chain EMA, e.g. to re-anchor it after an oracle outage
     pub ema: u64,
     /// Timestamp when price was published
     pub publish_time: i64,
@@ -90,7 +98,9 @@ pub struct SetCustomOraclePriceParams {
 /// 
 /// This function allows admins to set custom oracle prices. The process:
 /// 1. Validates multisig signatures (requires enough admin signatures)
-/// 2. Updates oracle account with new price data
+/// 2. Logs the deviation of the new price from the previous price
+/// 3. Updates oracle account with new price data, overriding the EMA if provided
+/// 4. Emits an AdminOraclePriceSet event
 /// 
 /// The oracle account is created if it doesn't exist (init_if_needed).
 /// 
@@ -126,14 +136,46 @@ pub fn set_custom_oracle_price<'info>(
         return Ok(signatures_left);
     }
 
+    // Log the deviation from the previous price
+    let oracle_account = ctx.accounts.oracle_account.as_mut();
+    let previous_price = oracle_account.price;
+    let previous_ema = oracle_account.ema;
+    let deviation = oracle_account.get_deviation(params.price, params.expo)?;
+    msg!(
+        "Admin price: {}, previous: {}, deviation: {} bps",
+        params.price,
+        previous_price,
+        deviation
+    );
+
     // Update oracle data
     // Set all price-related fields in the custom oracle account
-    ctx.accounts.oracle_account.set(
+    oracle_account.set(
         params.price,
         params.expo,
         params.conf,
-        params.ema,
         params.publish_time,
-    );
+        ctx.accounts.custody.oracle.ema_half_life_sec,
+    )?;
+    if params.ema > 0 {
+        oracle_account.ema = params.ema;
+    }
+
+    let pool = ctx.accounts.pool.as_mut();
+    emit!(AdminOraclePriceSet {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: ctx.accounts.custody.key(),
+        oracle_account: oracle_account.key(),
+        previous_price,
+        previous_ema,
+        price: oracle_account.price,
+        ema: oracle_account.ema,
+        expo: oracle_account.expo,
+        deviation,
+        publish_time: oracle_account.publish_time,
+        time: ctx.accounts.perpetuals.get_time()?,
+    });
+
     Ok(0)
 }
\ No newline at end of file
@@ -3,13 +3,11 @@
 //! This instruction allows anyone to update custom oracle prices without admin approval,
 //! as long as they provide a valid Ed25519 signature from the oracle authority. The oracle
 //! account must first be initialized by an admin. This enables permissionless price updates
-//! while maintaining security through cryptographic signatures.
+//! while maintaining security through cryptographic signatures. Updates can be throttled
+//! per custody with a minimum publish time interval and a per slot cap.
 
 use {
-    crate::{
-        error::PerpetualsError,
-        state::{custody::Custody, oracle::CustomOracle, perpetuals::Perpetuals, pool::Pool},
-    },
+    crate::state::{custody::Custody, oracle::CustomOracle, perpetuals::Perpetuals, pool::Pool},
     anchor_lang::prelude::*,
 };
 
@@ -32,9 +30,11 @@ pub struct SetCustomOraclePricePermissionless<'info> {
     )]
     pub pool: Box<Account<'info, Pool>>,
 
-    /// Custody account for which oracle price is being updated
+    /// Custody account for which oracle price is being updated (mutable, update throttle
+    /// will be updated)
     /// Must match the custody_account in params
     #[account(
+        mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
                  custody.mint.as_ref()],
@@ -73,7 +73,7 @@ pub struct SetCustomOraclePricePermissionlessParams {
     pub expo: i32,
     /// Price confidence interval
     pub conf: u64,
-    /// Exponential moving average price
+    /// Exponential moving average price (ignored, the EMA is computed on-chain)
     pub ema: u64,
     /// Timestamp when price was published (must be newer than current publish_time)
     pub publish_time: i64,
@@ -86,7 +86,8 @@ pub struct SetCustomOraclePricePermissionlessParams {
 /// 1. Validates publish_time is newer than current (prevents stale updates)
 /// 2. Loads Ed25519 signature verification instruction from transaction
 /// 3. Validates signature matches oracle authority and message matches params
-/// 4. Updates oracle account with new price data
+/// 4. Skips the update if it is within the minimum interval or over the slot cap
+/// 5. Updates oracle account with new price data
 /// 
 /// This enables permissionless price updates while maintaining security through
 /// cryptographic signatures. The oracle account must first be initialized by an admin.
@@ -115,87 +116,34 @@ pub fn set_custom_oracle_price_permissionless(
 
     // Validate Ed25519 signature
     // Ensures signature is from oracle authority and message matches params
-    validate_ed25519_signature_instruction(
+    let mut message = vec![];
+    params.serialize(&mut message)?;
+    CustomOracle::validate_ed25519_signature_instruction(
         &signature_ix,
+        0,
         &ctx.accounts.custody.oracle.oracle_authority,
-        params,
+        &message,
     )?;
 
+    // Throttle updates, like stale ones they are skipped so bundled transactions still land
+    if !ctx.accounts.custody.register_oracle_update(
+        ctx.accounts.oracle_account.publish_time,
+        params.publish_time,
+        Clock::get()?.slot,
+    ) {
+        msg!("Custom oracle price did not update because updates are throttled.");
+        return Ok(());
+    }
+
     // Update oracle account with new price data
     // Only reached if signature validation passes
     ctx.accounts.oracle_account.set(
         params.price,
         params.expo,
         params.conf,
-        params.ema,
         params.publish_time,
-    );
+        ctx.accounts.custody.oracle.ema_half_life_sec,
+    )?;
     Ok(())
 }
 
-/// Validate Ed25519 signature instruction format and content
-/// 
-/// This function validates that:
-/// 1. Instruction is from Ed25519Program
-/// 2. Instruction format matches expected structure (no accounts, single signature, correct data length)
-/// 3. Signer pubkey matches expected oracle authority
-/// 4. Signed message matches the provided parameters
-/// 
-/// The Ed25519 instruction format follows Solana's specification:
-/// - data[0] = number of signatures (must be 0x01 for single signature)
-/// - data[16..48] = signer pubkey (32 bytes)
-/// - data[112..] = signed message (instruction params)
-/// 
-/// # Arguments
-/// * `signature_ix` - Ed25519 signature verification instruction from transaction
-/// * `expected_pubkey` - Expected oracle authority pubkey
-/// * `expected_params` - Expected instruction parameters (must match signed message)
-/// 
-/// # Returns
-/// `Result<()>` - Success if signature is valid, or error
-fn validate_ed25519_signature_instruction(
-    signature_ix: &anchor_lang::solana_program::instruction::Instruction,
-    expected_pubkey: &Pubkey,
-    expected_params: &SetCustomOraclePricePermissionlessParams,
-) -> Result<()> {
-    // Validate instruction is from Ed25519Program
-    let ed25519_program_id = anchor_lang::solana_program::system_program::ID;
-    require_eq!(
-        signature_ix.program_id,
-        ed25519_program_id,
-        PerpetualsError::PermissionlessOracleMissingSignature
-    );
-    
-    // Validate instruction format matches expected structure
-    // Must have no accounts, single signature (0x01), and exact data length (180 bytes)
-    require!(
-        signature_ix.accounts.is_empty() /* no accounts touched */
-            && signature_ix.data[0] == 0x01 /* only one ed25519 signature */
-            && signature_ix.data.len() == 180, /* data len matches exactly the expected */
-        PerpetualsError::PermissionlessOracleMalformedEd25519Data
-    );
-
-    // Extract signer pubkey and message from instruction data
-    // Manually access offsets for signer pubkey and message data according to:
-    // https://docs.solana.com/developing/runtime-facilities/programs#ed25519-program
-    let signer_pubkey = &signature_ix.data[16..16 + 32];
-    let mut verified_message = &signature_ix.data[112..];
-
-    // Deserialize signed message to get instruction parameters
-    let deserialized_instruction_params =
-        SetCustomOraclePricePermissionlessParams::deserialize(&mut verified_message)?;
-
-    // Validate signer pubkey matches expected oracle authority
-    require!(
-        signer_pubkey == expected_pubkey.to_bytes(),
-        PerpetualsError::PermissionlessOracleSignerMismatch
-    );
-    
-    // Validate signed message matches provided parameters
-    // This ensures the signature was created for exactly these parameters
-    require!(
-        deserialized_instruction_params == *expected_params,
-        PerpetualsError::PermissionlessOracleMessageMismatch
-    );
-    Ok(())
-}
\ No newline at end of file
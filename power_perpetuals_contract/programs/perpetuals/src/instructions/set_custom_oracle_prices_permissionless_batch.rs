@@ -0,0 +1,158 @@
+//! SetCustomOraclePricesPermissionlessBatch instruction handler
+//!
+//! This instruction updates the custom oracle prices of several custodies of a pool in
+//! one go. The oracle authority signs the whole batch with a single Ed25519 signature,
+//! so a publisher maintaining many markets needs one transaction instead of one per
+//! custody. Each price goes through the same staleness and throttle checks as
+//! set_custom_oracle_price_permissionless.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        instructions::set_custom_oracle_price_permissionless::SetCustomOraclePricePermissionlessParams,
+        state::{custody::Custody, oracle::CustomOracle, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for a batch permissionless custom oracle price update
+///
+/// Remaining accounts are (custody, oracle) pairs in the order of the batch entries:
+/// [custody0, oracle0, custody1, oracle1, ...]. Custodies must be writable (update
+/// throttle will be updated), oracles must be writable (price will be updated).
+#[derive(Accounts)]
+pub struct SetCustomOraclePricesPermissionlessBatch<'info> {
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the custodies belong to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Instructions sysvar account for Ed25519 signature verification
+    ///
+    /// CHECK: Needed for ed25519 signature verification, to inspect all instructions in this transaction.
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+/// Parameters for a batch permissionless custom oracle price update
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct SetCustomOraclePricesPermissionlessBatchParams {
+    /// Price updates, one per (custody, oracle) pair of remaining accounts
+    pub prices: Vec<SetCustomOraclePricePermissionlessParams>,
+}
+
+/// Update several custom oracle prices permissionlessly with one Ed25519 signature
+///
+/// The process:
+/// 1. Validates that remaining accounts come in (custody, oracle) pairs, one per entry
+/// 2. Loads Ed25519 signature verification instruction from transaction
+/// 3. Validates signature is from the oracle authority of the first custody and the
+///    message matches the serialized batch
+/// 4. For each entry, validates the custody and oracle accounts and that the custody
+///    shares the signing oracle authority
+/// 5. Skips entries that are stale, within the minimum interval or over the slot cap
+/// 6. Updates the oracle accounts of the remaining entries
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Oracle price updates (must match signed message)
+///
+/// # Returns
+/// `Result<()>` - Success if all entries were applied or skipped, or error
+pub fn set_custom_oracle_prices_permissionless_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SetCustomOraclePricesPermissionlessBatch<'info>>,
+    params: &SetCustomOraclePricesPermissionlessBatchParams,
+) -> Result<()> {
+    // Validate inputs
+    let accounts = ctx.remaining_accounts;
+    if params.prices.is_empty() || accounts.len() != params.prices.len() * 2 {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    // Validate Ed25519 signature over the whole batch
+    let signature_ix: anchor_lang::solana_program::instruction::Instruction =
+        anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked(
+            0,
+            &ctx.accounts.ix_sysvar,
+        )?;
+    let oracle_authority = Account::<Custody>::try_from(&accounts[0])?
+        .oracle
+        .oracle_authority;
+    let mut message = vec![];
+    params.serialize(&mut message)?;
+    CustomOracle::validate_ed25519_signature_instruction(
+        &signature_ix,
+        0,
+        &oracle_authority,
+        &message,
+    )?;
+
+    let pool = &ctx.accounts.pool;
+    let slot = Clock::get()?.slot;
+    let mut updates = 0;
+
+    for (price, pair) in params.prices.iter().zip(accounts.chunks(2)) {
+        let (custody_info, oracle_info) = (&pair[0], &pair[1]);
+        require_keys_eq!(
+            custody_info.key(),
+            price.custody_account,
+            PerpetualsError::PermissionlessOracleMessageMismatch
+        );
+        require!(
+            pool.custodies.contains(&custody_info.key()),
+            PerpetualsError::InvalidCustodyState
+        );
+        let mut custody = Account::<Custody>::try_from(custody_info)?;
+        require_keys_eq!(
+            custody.oracle.oracle_authority,
+            oracle_authority,
+            PerpetualsError::PermissionlessOracleSignerMismatch
+        );
+        require_keys_eq!(
+            oracle_info.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+        let mut oracle_account = Account::<CustomOracle>::try_from(oracle_info)?;
+
+        // Stale and throttled entries are skipped so the rest of the batch still lands
+        if price.publish_time <= oracle_account.publish_time {
+            msg!(
+                "Custom oracle price of custody {} is stale, skipped",
+                custody_info.key()
+            );
+            continue;
+        }
+        if !custody.register_oracle_update(oracle_account.publish_time, price.publish_time, slot) {
+            msg!(
+                "Custom oracle price of custody {} is throttled, skipped",
+                custody_info.key()
+            );
+            continue;
+        }
+
+        oracle_account.set(
+            price.price,
+            price.expo,
+            price.conf,
+            price.publish_time,
+            custody.oracle.ema_half_life_sec,
+        )?;
+        custody.exit(&crate::ID)?;
+        oracle_account.exit(&crate::ID)?;
+        updates += 1;
+    }
+    msg!("Custom oracle prices updated: {}", updates);
+
+    Ok(())
+}
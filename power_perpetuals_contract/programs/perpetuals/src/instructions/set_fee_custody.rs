@@ -0,0 +1,103 @@
+//! SetFeeCustody instruction handler
+//!
+//! This instruction allows admins to choose a pool custody (e.g. USDC) that traders
+//! can pay entry and exit fees in, regardless of the position collateral token.
+//! This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool fee custody
+#[derive(Accounts)]
+pub struct SetFeeCustody<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, fee custody will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool fee custody
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetFeeCustodyParams {
+    /// Custody fees can be paid in (default = fees are always charged in kind)
+    pub fee_custody: Pubkey,
+}
+
+/// Set the custody traders can pay trade fees in
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the custody belongs to the pool
+/// 3. Updates the pool fee custody
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New fee custody
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_fee_custody<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetFeeCustody<'info>>,
+    params: &SetFeeCustodyParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetFeeCustody, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate and update pool
+    let pool = ctx.accounts.pool.as_mut();
+    if params.fee_custody != Pubkey::default() {
+        require!(
+            pool.custodies.contains(&params.fee_custody),
+            PerpetualsError::UnsupportedToken
+        );
+    }
+    pool.fee_custody = params.fee_custody;
+    msg!("Fee custody: {}", params.fee_custody);
+
+    Ok(0)
+}
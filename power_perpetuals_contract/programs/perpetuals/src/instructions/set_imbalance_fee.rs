@@ -0,0 +1,103 @@
+//! SetImbalanceFee instruction handler
+//!
+//! This instruction allows admins to set the open position fee surcharge charged on
+//! opens towards the heavier side of a market and refunded on opens against it, which
+//! pays traders to keep long and short open interest balanced. This requires multisig
+//! approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool imbalance fee
+#[derive(Accounts)]
+pub struct SetImbalanceFee<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, trade rate limit will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool imbalance fee
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetImbalanceFeeParams {
+    /// Fee surcharge at a fully one-sided book (BPS, 0 = disabled)
+    pub imbalance_fee_bps: u64,
+}
+
+/// Set the open interest imbalance fee of a pool
+///
+/// The process:
+/// 1. Validates the fee doesn't exceed the maximum
+/// 2. Validates multisig signatures (requires enough admin signatures)
+/// 3. Updates the pool imbalance fee
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New imbalance fee
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_imbalance_fee<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetImbalanceFee<'info>>,
+    params: &SetImbalanceFeeParams,
+) -> Result<u8> {
+    // Validate inputs
+    require!(
+        params.imbalance_fee_bps <= Pool::MAX_IMBALANCE_FEE_BPS,
+        PerpetualsError::InvalidPoolConfig
+    );
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetImbalanceFee, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.imbalance_fee_bps = params.imbalance_fee_bps;
+    msg!("Imbalance fee: {}", params.imbalance_fee_bps);
+
+    Ok(0)
+}
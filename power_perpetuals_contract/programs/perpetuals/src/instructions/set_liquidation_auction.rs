@@ -0,0 +1,108 @@
+//! SetLiquidationAuction instruction handler
+//!
+//! This instruction allows admins to configure the liquidation reward auction of a pool:
+//! the share of the full reward claimable when the auction starts and the number of slots
+//! it takes to ramp up to the full reward. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for configuring the liquidation auction of a pool
+#[derive(Accounts)]
+pub struct SetLiquidationAuction<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, liquidation auction will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for configuring the liquidation auction of a pool
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetLiquidationAuctionParams {
+    /// Number of slots the liquidation reward ramps up over (0 = fixed reward)
+    pub auction_slots: u64,
+    /// Share of the full liquidation reward claimable when the auction starts (BPS)
+    pub start_share: u64,
+}
+
+/// Configure the liquidation auction of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the start share
+/// 3. Updates the pool
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Auction duration and start share
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_liquidation_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetLiquidationAuction<'info>>,
+    params: &SetLiquidationAuctionParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetLiquidationAuction, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate and update pool
+    require!(
+        params.start_share as u128 <= Perpetuals::BPS_POWER,
+        PerpetualsError::InvalidPoolConfig
+    );
+    let pool = ctx.accounts.pool.as_mut();
+    pool.liquidation_auction_slots = params.auction_slots;
+    pool.liquidation_auction_start_share = params.start_share;
+    msg!(
+        "Liquidation auction: {} slots, start share {}",
+        params.auction_slots,
+        params.start_share
+    );
+
+    Ok(0)
+}
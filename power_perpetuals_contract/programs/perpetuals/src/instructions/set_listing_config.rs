@@ -0,0 +1,179 @@
+//! SetListingConfig instruction handler
+//!
+//! This instruction allows admins to open a pool to permissionless market listings.
+//! It sets the bond a lister must post, the account slashed bonds are paid to, the
+//! veto timelock and the custody template listed markets are created from. Configs
+//! stored in a legacy layout are resized and rewritten in the current one.
+//! This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            listing::{ListingConfig, ListingTemplate},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, TokenAccount},
+};
+
+/// Accounts required for configuring pool listings
+#[derive(Accounts)]
+pub struct SetListingConfig<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool listings are added to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Listing config account (PDA derived from pool)
+    ///
+    /// CHECK: Created on first use, legacy layouts are resized, validated in function
+    #[account(
+        mut,
+        seeds = [b"listing_config",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub listing_config: AccountInfo<'info>,
+
+    /// Mint of the listing bond
+    pub bond_mint: Box<Account<'info, Mint>>,
+
+    /// Token account slashed bonds are paid to
+    #[account(
+        constraint = slash_account.mint == bond_mint.key()
+    )]
+    pub slash_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for configuring pool listings
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetListingConfigParams {
+    /// Bond posted per listing (in bond token decimals)
+    pub bond_amount: u64,
+    /// Time a listing waits for a veto before it can be activated
+    pub timelock_sec: i64,
+    /// Custody configuration of listed markets
+    pub template: ListingTemplate,
+}
+
+/// Configure permissionless market listings of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Creates the listing config account, or resizes a config stored in a legacy layout
+/// 3. Validates the bond, timelock and custody template
+/// 4. Writes the listing config in the current layout
+///
+/// Pending listings are re-checked against the config when they are activated.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Bond, timelock and custody template
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_listing_config<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetListingConfig<'info>>,
+    params: &SetListingConfigParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetListingConfig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Create listing config, or resize a config stored in a legacy layout
+    let listing_config_account = &ctx.accounts.listing_config;
+    if Perpetuals::is_empty_account(listing_config_account)? {
+        msg!("Create listing config");
+        Perpetuals::create_account(
+            ctx.accounts.admin.to_account_info(),
+            listing_config_account.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ListingConfig::LEN,
+            &[&[
+                b"listing_config",
+                ctx.accounts.pool.key().as_ref(),
+                &[ctx.bumps.listing_config],
+            ]],
+        )?;
+    } else {
+        if listing_config_account.owner != &crate::ID {
+            return Err(anchor_lang::error::ErrorCode::ConstraintOwner.into());
+        }
+        {
+            let data = listing_config_account.try_borrow_data()?;
+            if data.len() < 8 || data[..8] != *ListingConfig::DISCRIMINATOR {
+                return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+            }
+        }
+        if listing_config_account.data_len() < ListingConfig::LEN {
+            msg!("Resize listing config");
+            Perpetuals::realloc(
+                ctx.accounts.admin.to_account_info(),
+                listing_config_account.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                ListingConfig::LEN,
+                true,
+            )?;
+        }
+    }
+
+    // Update listing config
+    let listing_config = ListingConfig {
+        pool: ctx.accounts.pool.key(),
+        bond_mint: ctx.accounts.bond_mint.key(),
+        bond_amount: params.bond_amount,
+        slash_account: ctx.accounts.slash_account.key(),
+        timelock_sec: params.timelock_sec,
+        template: params.template,
+        version: ListingConfig::VERSION,
+        bump: ctx.bumps.listing_config,
+    };
+
+    if !listing_config.validate() {
+        return err!(PerpetualsError::InvalidListingConfig);
+    }
+
+    listing_config.try_serialize(&mut &mut listing_config_account.try_borrow_mut_data()?[..])?;
+
+    Ok(0)
+}
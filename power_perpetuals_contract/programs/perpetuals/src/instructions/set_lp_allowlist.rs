@@ -0,0 +1,127 @@
+//! SetLpAllowlist instruction handler
+//!
+//! This instruction allows admins to configure the liquidity provider allowlist of a pool.
+//! When enabled, only allowlisted owners can add or remove liquidity. Admins set the
+//! compliance authority that can maintain the list and can add or remove owners directly.
+//! This requires multisig approval.
+
+use {
+    crate::state::{
+        lp_allowlist::LpAllowlist,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for configuring a pool allowlist
+#[derive(Accounts)]
+pub struct SetLpAllowlist<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, allowlist flag will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Allowlist account (PDA derived from pool)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = LpAllowlist::LEN,
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub lp_allowlist: Box<Account<'info, LpAllowlist>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for configuring a pool allowlist
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetLpAllowlistParams {
+    /// Whether liquidity operations are restricted to allowlisted owners
+    pub enabled: bool,
+    /// Authority allowed to maintain the allowlist
+    pub compliance_authority: Pubkey,
+    /// Owners to add to the allowlist
+    pub add_owners: Vec<Pubkey>,
+    /// Owners to remove from the allowlist
+    pub remove_owners: Vec<Pubkey>,
+}
+
+/// Configure a pool liquidity provider allowlist
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Initializes or updates the allowlist account
+/// 3. Applies owner additions, then removals
+/// 4. Enables or disables allowlist enforcement on the pool
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Allowlist settings and owner changes
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_lp_allowlist<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetLpAllowlist<'info>>,
+    params: &SetLpAllowlistParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetLpAllowlist, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update allowlist data
+    let lp_allowlist = ctx.accounts.lp_allowlist.as_mut();
+    lp_allowlist.pool = ctx.accounts.pool.key();
+    lp_allowlist.compliance_authority = params.compliance_authority;
+    lp_allowlist.bump = ctx.bumps.lp_allowlist;
+    for owner in params.add_owners.iter() {
+        lp_allowlist.add_owner(*owner)?;
+    }
+    for owner in params.remove_owners.iter() {
+        lp_allowlist.remove_owner(owner);
+    }
+    msg!("Allowlisted owners: {}", lp_allowlist.owners.len());
+
+    // Update pool
+    ctx.accounts.pool.lp_allowlist_enabled = params.enabled;
+
+    Ok(0)
+}
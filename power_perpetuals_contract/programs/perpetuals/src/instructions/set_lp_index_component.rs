@@ -0,0 +1,162 @@
+//! SetLpIndexComponent instruction handler
+//!
+//! This instruction allows admins to add a pool to an LP index or change its weight.
+//! Adding a pool creates the vault holding its LP tokens. Index tokens can only be
+//! minted once the component weights add up to 100%. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            lp_index::{LpIndex, LpIndexComponent},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+/// Accounts required for setting an LP index component
+#[derive(Accounts)]
+pub struct SetLpIndexComponent<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// LP index account (mutable, component will be updated)
+    #[account(
+        mut,
+        seeds = [b"lp_index",
+                 lp_index.name.as_bytes()],
+        bump = lp_index.bump
+    )]
+    pub lp_index: Box<Account<'info, LpIndex>>,
+
+    /// Pool whose LP tokens are held by the component
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// LP token mint of the pool
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    /// Vault holding the pool LP tokens (initialized if needed)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"lp_index_vault",
+                 lp_index.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+/// Parameters for setting an LP index component
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetLpIndexComponentParams {
+    /// Target share of the index value in BPS (0 stops deposits of the pool LP token)
+    pub weight: u64,
+}
+
+/// Add a pool to an LP index or change its weight
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Adds the component if the pool isn't in the index yet, updates its weight
+/// 3. Validates the index configuration
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Component weight
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_lp_index_component<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetLpIndexComponent<'info>>,
+    params: &SetLpIndexComponentParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetLpIndexComponent, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update component
+    let pool_key = ctx.accounts.pool.key();
+    let lp_index = ctx.accounts.lp_index.as_mut();
+    let index = match lp_index.get_component_index(&pool_key) {
+        Some(index) => index,
+        None => {
+            require!(
+                lp_index.components.len() < LpIndex::MAX_COMPONENTS,
+                PerpetualsError::InvalidLpIndexConfig
+            );
+            lp_index.components.push(LpIndexComponent {
+                pool: pool_key,
+                vault: ctx.accounts.vault.key(),
+                weight: 0,
+                vault_bump: ctx.bumps.vault,
+            });
+            lp_index.components.len() - 1
+        }
+    };
+    lp_index.components[index].weight = params.weight;
+    msg!("LP index component {}: {} BPS", pool_key, params.weight);
+
+    if !lp_index.validate() {
+        return err!(PerpetualsError::InvalidLpIndexConfig);
+    }
+
+    Ok(0)
+}
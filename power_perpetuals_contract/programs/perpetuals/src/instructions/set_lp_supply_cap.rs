@@ -0,0 +1,95 @@
+//! SetLpSupplyCap instruction handler
+//!
+//! This instruction allows admins to cap the total LP token supply of a pool, for
+//! capped vault-style products and controlled launches. add_liquidity rejects deposits
+//! that would mint over the cap. This requires multisig approval.
+
+use {
+    crate::state::{
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool LP supply cap
+#[derive(Accounts)]
+pub struct SetLpSupplyCap<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, LP supply cap will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool LP supply cap
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetLpSupplyCapParams {
+    /// Maximum LP token supply (0 = uncapped)
+    pub max_lp_supply: u64,
+}
+
+/// Set the maximum LP token supply of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates the pool LP supply cap
+///
+/// Lowering the cap below the current supply doesn't burn LP tokens, it only blocks
+/// new deposits until enough liquidity is removed.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New LP supply cap
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_lp_supply_cap<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetLpSupplyCap<'info>>,
+    params: &SetLpSupplyCapParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetLpSupplyCap, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.max_lp_supply = params.max_lp_supply;
+    msg!("Max LP supply: {}", params.max_lp_supply);
+
+    Ok(0)
+}
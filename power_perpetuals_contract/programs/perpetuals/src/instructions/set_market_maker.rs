@@ -0,0 +1,128 @@
+//! SetMarketMaker instruction handler
+//!
+//! This instruction allows admins to register a market maker for a pool, or update the
+//! terms of an existing one. Market makers trade at reduced spreads and receive a rebate
+//! on swap fees up to a volume cap. This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            market_maker::MarketMaker,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for registering a market maker
+#[derive(Accounts)]
+#[instruction(params: SetMarketMakerParams)]
+pub struct SetMarketMaker<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the market maker is registered with
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Market maker account (PDA derived from pool and market maker owner)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = MarketMaker::LEN,
+        seeds = [b"market_maker",
+                 pool.key().as_ref(),
+                 params.owner.as_ref()],
+        bump
+    )]
+    pub market_maker: Box<Account<'info, MarketMaker>>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for registering a market maker
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetMarketMakerParams {
+    /// Wallet address of the market maker
+    pub owner: Pubkey,
+    /// Share of trade and swap spreads waived (in BPS, 10,000 = zero spread)
+    pub spread_discount: u64,
+    /// Share of swap fees rebated to the market maker (in BPS)
+    pub swap_rebate: u64,
+    /// Volume that can be traded at discounted pricing (USD_DECIMALS), 0 for unlimited
+    pub max_volume_usd: u64,
+}
+
+/// Register or update a market maker
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Initializes or updates the market maker account
+/// 3. Validates market maker parameters
+///
+/// Traded volume is preserved when an existing market maker is updated.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Market maker terms
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_market_maker<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetMarketMaker<'info>>,
+    params: &SetMarketMakerParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetMarketMaker, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update market maker data
+    let market_maker = ctx.accounts.market_maker.as_mut();
+    market_maker.owner = params.owner;
+    market_maker.pool = ctx.accounts.pool.key();
+    market_maker.spread_discount = params.spread_discount;
+    market_maker.swap_rebate = params.swap_rebate;
+    market_maker.max_volume_usd = params.max_volume_usd;
+    market_maker.bump = ctx.bumps.market_maker;
+
+    if !market_maker.validate() {
+        err!(PerpetualsError::InvalidMarketMakerConfig)
+    } else {
+        Ok(0)
+    }
+}
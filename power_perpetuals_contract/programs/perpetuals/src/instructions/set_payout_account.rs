@@ -0,0 +1,70 @@
+//! SetPayoutAccount instruction handler
+//!
+//! This instruction lets a position owner choose the token account the remainder of the
+//! position is paid to when a third party closes it (liquidation or forced settlement).
+//! Liquidators must pay out to this account, so the remainder can't be sent to an account
+//! of the owner they don't monitor. Without a registered account the remainder goes to
+//! the associated token account of the owner.
+
+use {
+    crate::state::{custody::Custody, position::Position},
+    anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
+};
+
+/// Accounts required for setting the payout account of a position
+#[derive(Accounts)]
+pub struct SetPayoutAccount<'info> {
+    /// Owner of the position (signer)
+    pub owner: Signer<'info>,
+
+    /// Position account (mutable, owned by owner)
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 position.pool.as_ref(),
+                 position.custody.as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the collateral token of the position
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Token account to pay the remainder to
+    /// Must be owned by the owner and have the same mint as the collateral custody
+    #[account(
+        constraint = payout_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub payout_account: Box<Account<'info, TokenAccount>>,
+}
+
+/// Parameters for setting the payout account of a position
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPayoutAccountParams {}
+
+/// Register the token account the position remainder is paid to
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Empty parameters struct
+///
+/// # Returns
+/// `Result<()>` - Success if the payout account was registered
+pub fn set_payout_account(
+    ctx: Context<SetPayoutAccount>,
+    _params: &SetPayoutAccountParams,
+) -> Result<()> {
+    let position = ctx.accounts.position.as_mut();
+    position.payout_account = ctx.accounts.payout_account.key();
+    msg!("Payout account: {}", position.payout_account);
+
+    Ok(())
+}
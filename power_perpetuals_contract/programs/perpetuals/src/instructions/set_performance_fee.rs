@@ -0,0 +1,123 @@
+//! SetPerformanceFee instruction handler
+//!
+//! This instruction allows admins to configure the pool performance fee: the protocol
+//! share of LP token price gains above the high-water mark, minted in LP tokens to a
+//! treasury account when a performance epoch is rolled over. This requires multisig
+//! approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for configuring the pool performance fee
+#[derive(Accounts)]
+pub struct SetPerformanceFee<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, performance fee will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for configuring the pool performance fee
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPerformanceFeeParams {
+    /// Protocol share of LP token price gains above the high-water mark (BPS, 0 = disabled)
+    pub performance_fee_bps: u64,
+    /// LP token account performance fees are minted to
+    pub performance_fee_account: Pubkey,
+    /// Minimum duration of a performance fee epoch in seconds
+    pub performance_epoch_sec: i64,
+}
+
+/// Configure the pool performance fee
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the fee parameters
+/// 3. Updates the pool, starting the first epoch if none was started yet
+///
+/// The high-water mark is recorded at the first rollover, gains before it are not charged.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Fee rate, treasury account and epoch duration
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_performance_fee<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPerformanceFee<'info>>,
+    params: &SetPerformanceFeeParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPerformanceFee, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Validate parameters
+    require!(
+        params.performance_fee_bps <= Pool::MAX_PERFORMANCE_FEE_BPS
+            && params.performance_epoch_sec >= 0,
+        PerpetualsError::InvalidPoolConfig
+    );
+    if params.performance_fee_bps > 0 {
+        require!(
+            params.performance_fee_account != Pubkey::default()
+                && params.performance_epoch_sec > 0,
+            PerpetualsError::InvalidPoolConfig
+        );
+    }
+
+    // Update pool
+    let pool = ctx.accounts.pool.as_mut();
+    pool.performance_fee_bps = params.performance_fee_bps;
+    pool.performance_fee_account = params.performance_fee_account;
+    pool.performance_epoch_sec = params.performance_epoch_sec;
+    if pool.performance_epoch_start == 0 {
+        pool.performance_epoch_start = ctx.accounts.perpetuals.get_time()?;
+    }
+    msg!("Performance fee: {} bps", params.performance_fee_bps);
+
+    Ok(0)
+}
@@ -58,6 +58,8 @@ pub struct SetPermissionsParams {
     pub allow_collateral_withdrawal: bool,
     /// Allow changing position size
     pub allow_size_change: bool,
+    /// Allow open/close position and collateral withdrawal through CPI
+    pub allow_cpi: bool,
 }
 
 /// Update global permissions for the perpetuals program
@@ -111,6 +113,7 @@ pub fn set_permissions<'info>(
     perpetuals.permissions.allow_pnl_withdrawal = params.allow_pnl_withdrawal;
     perpetuals.permissions.allow_collateral_withdrawal = params.allow_collateral_withdrawal;
     perpetuals.permissions.allow_size_change = params.allow_size_change;
+    perpetuals.allow_cpi = params.allow_cpi;
 
     // Validate perpetuals configuration after updates
     // Ensure all parameters are within acceptable ranges
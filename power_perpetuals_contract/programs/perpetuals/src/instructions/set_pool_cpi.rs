@@ -0,0 +1,93 @@
+//! SetPoolCpi instruction handler
+//!
+//! This instruction allows admins to allow or disallow CPI invocation of the trading
+//! instructions of a single pool. CPI invocations also require the global and custody
+//! permissions, so a pool can be closed to wrapper programs without touching the rest
+//! of the protocol. This requires multisig approval.
+
+use {
+    crate::state::{
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool CPI permission
+#[derive(Accounts)]
+pub struct SetPoolCpi<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, CPI permission will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool CPI permission
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPoolCpiParams {
+    /// Allow trading instructions of the pool to be invoked through CPI
+    pub allow_cpi: bool,
+}
+
+/// Allow or disallow CPI invocation of the trading instructions of a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates the pool CPI permission
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New CPI permission
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_pool_cpi<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPoolCpi<'info>>,
+    params: &SetPoolCpiParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPoolCpi, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.allow_cpi = params.allow_cpi;
+    msg!("Allow CPI: {}", params.allow_cpi);
+
+    Ok(0)
+}
@@ -0,0 +1,107 @@
+//! SetPositionHook instruction handler
+//!
+//! This instruction allows admins to allow-list an external program that is notified
+//! after positions of a pool are opened, closed or liquidated, and to choose the events,
+//! whether the hook is required and the compute it needs. This requires multisig
+//! approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position_hook::PositionHook,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool position hook
+#[derive(Accounts)]
+pub struct SetPositionHook<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, position hook will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool position hook
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPositionHookParams {
+    /// New position hook (default program = no hook)
+    pub position_hook: PositionHook,
+}
+
+/// Set the external program notified of position lifecycle events in a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates and updates the pool position hook
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New position hook
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_position_hook<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPositionHook<'info>>,
+    params: &SetPositionHookParams,
+) -> Result<u8> {
+    // Validate inputs
+    require!(
+        params.position_hook.validate(),
+        PerpetualsError::InvalidPositionHook
+    );
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPositionHook, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.position_hook = params.position_hook;
+    msg!(
+        "Position hook: {}, events: {}",
+        params.position_hook.program,
+        params.position_hook.events
+    );
+
+    Ok(0)
+}
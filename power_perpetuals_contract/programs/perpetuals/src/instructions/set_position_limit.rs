@@ -0,0 +1,94 @@
+//! SetPositionLimit instruction handler
+//!
+//! This instruction allows admins to cap the number of positions a single owner can
+//! keep open in a pool. Open positions are tracked in a per-owner counter account
+//! that open_position requires while the cap is set. This requires multisig approval.
+
+use {
+    crate::state::{
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool position limit
+#[derive(Accounts)]
+pub struct SetPositionLimit<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, position limit will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool position limit
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPositionLimitParams {
+    /// Maximum number of open positions per owner (0 = unlimited)
+    pub max_positions_per_owner: u32,
+}
+
+/// Set the maximum number of open positions per owner in a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates the pool position limit
+///
+/// Lowering the limit doesn't close positions, owners above it can't open new ones.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New position limit
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_position_limit<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPositionLimit<'info>>,
+    params: &SetPositionLimitParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPositionLimit, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.max_positions_per_owner = params.max_positions_per_owner;
+    msg!("Max positions per owner: {}", params.max_positions_per_owner);
+
+    Ok(0)
+}
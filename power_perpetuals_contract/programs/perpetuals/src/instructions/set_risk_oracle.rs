@@ -0,0 +1,93 @@
+//! SetRiskOracle instruction handler
+//!
+//! This instruction allows admins to set the external circuit-breaker feed that halts
+//! risk-increasing operations across the protocol while it flags an incident. This
+//! requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            risk_oracle::RiskOracle,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the risk oracle
+#[derive(Accounts)]
+pub struct SetRiskOracle<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account (mutable, risk oracle will be updated)
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+}
+
+/// Parameters for setting the risk oracle
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetRiskOracleParams {
+    /// New risk oracle (default feed = no risk oracle)
+    pub risk_oracle: RiskOracle,
+}
+
+/// Set the circuit-breaker feed checked before risk-increasing operations
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates and updates the protocol risk oracle
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New risk oracle
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_risk_oracle<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetRiskOracle<'info>>,
+    params: &SetRiskOracleParams,
+) -> Result<u8> {
+    // Validate inputs
+    require!(
+        params.risk_oracle.validate(),
+        PerpetualsError::InvalidRiskOracleConfig
+    );
+
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetRiskOracle, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update perpetuals
+    ctx.accounts.perpetuals.risk_oracle = params.risk_oracle;
+    msg!("Risk oracle feed: {}", params.risk_oracle.feed);
+
+    Ok(0)
+}
@@ -0,0 +1,94 @@
+//! SetSettlementPrice instruction handler
+//!
+//! This permissionless crank snapshots the settlement price of an expired power
+//! futures market. Anyone (usually a keeper) can call it once the market has expired.
+//! The price is read from the custody oracle once and must have been published at or
+//! after expiry_time, within the settlement window. It is then used by
+//! settle_expired_position for all remaining positions.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for snapshotting the settlement price
+#[derive(Accounts)]
+pub struct SetSettlementPrice<'info> {
+    /// Keeper account (signer, pays for transaction fees)
+    pub signer: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account of the expired market (mutable, settlement price will be set)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the custody token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for snapshotting the settlement price
+///
+/// Currently empty, but kept for consistency with other instructions.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetSettlementPriceParams {}
+
+/// Snapshot the settlement price of an expired market
+///
+/// The process:
+/// 1. Validates that the market has expired and hasn't been settled yet
+/// 2. Reads the spot price from the oracle, it must be published at or after
+///    expiry_time and no later than Custody::SETTLEMENT_PRICE_WINDOW_SEC after it
+/// 3. Stores the price, scaled to PRICE_DECIMALS, as the settlement price
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `Result<u64>` - Settlement price scaled to PRICE_DECIMALS
+pub fn set_settlement_price(
+    ctx: Context<SetSettlementPrice>,
+    _params: &SetSettlementPriceParams,
+) -> Result<u64> {
+    // Snapshot the spot price at expiry
+    msg!("Read settlement price");
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody = ctx.accounts.custody.as_mut();
+    let settlement_price = custody.get_settlement_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+    )?;
+
+    custody.settlement_price = settlement_price;
+    msg!("Settlement price: {}", settlement_price);
+
+    Ok(settlement_price)
+}
@@ -0,0 +1,93 @@
+//! SetTradeRateLimit instruction handler
+//!
+//! This instruction allows admins to cap the number of position opens and closes a
+//! single owner can make per slot in a pool. Trades are tracked in a per-owner counter
+//! account that open_position and close_position require while the cap is set. This
+//! requires multisig approval.
+
+use {
+    crate::state::{
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for setting the pool trade rate limit
+#[derive(Accounts)]
+pub struct SetTradeRateLimit<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, trade rate limit will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+/// Parameters for setting the pool trade rate limit
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetTradeRateLimitParams {
+    /// Maximum number of position opens and closes per owner and slot (0 = unlimited)
+    pub max_trades_per_slot: u16,
+}
+
+/// Set the maximum number of position opens and closes per owner and slot in a pool
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Updates the pool trade rate limit
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - New trade rate limit
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn set_trade_rate_limit<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetTradeRateLimit<'info>>,
+    params: &SetTradeRateLimitParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetTradeRateLimit, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Update pool
+    ctx.accounts.pool.max_trades_per_slot = params.max_trades_per_slot;
+    msg!("Max trades per slot: {}", params.max_trades_per_slot);
+
+    Ok(0)
+}
@@ -0,0 +1,358 @@
+//! SettleExpiredPosition instruction handler
+//!
+//! This instruction closes a position in an expired power futures market at the fixed
+//! settlement price snapshotted by set_settlement_price. No trade spread is
+//! applied and borrow interest stops accruing at expiry. The position account is closed
+//! (deleted) after execution.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::LockChanged,
+        math::RoundingDirection,
+        state::{
+            custody::Custody,
+            oracle::{OracleOperation, OraclePrice},
+            perpetuals::Perpetuals,
+            owner_positions::OwnerPositions,
+            pool::{Pool, PositionSettlement},
+            position::{Position, Side},
+            position_hook::{PositionHookEvent, PositionHookPayload},
+            position_summary::PositionSummary,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+/// Accounts required for settling an expired position
+#[derive(Accounts)]
+pub struct SettleExpiredPosition<'info> {
+    /// Position owner (must sign the transaction)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// User's token account to receive remaining collateral
+    ///
+    /// Must match the collateral custody mint and be owned by the owner.
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// Transfer authority PDA (authority for token accounts)
+    ///
+    /// CHECK: This is a PDA, no data validation needed
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the position belongs to
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account to settle (closed after execution, rent returned to owner)
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"position",
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump,
+        close = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token (the asset being traded)
+    #[account(
+        mut,
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Custody account for the collateral token (the asset used as margin)
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    /// Pool's token account for collateral (source of collateral transfer)
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Token program for token transfers
+    token_program: Program<'info, Token>,
+
+    /// Optional open position counter of the owner
+    #[account(
+        mut,
+        seeds = [b"owner_positions",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump = owner_positions.bump
+    )]
+    pub owner_positions: Option<Box<Account<'info, OwnerPositions>>>,
+
+    /// Optional summary of the position, marked closed
+    #[account(
+        mut,
+        seeds = [b"position_summary",
+                 position.key().as_ref()],
+        bump = position_summary.bump
+    )]
+    pub position_summary: Option<Box<Account<'info, PositionSummary>>>,
+
+    /// Optional position hook program, required if the pool position hook is required
+    ///
+    /// CHECK: Validated against the pool position hook
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    /// Optional hook authority PDA, signs the position hook call
+    ///
+    /// CHECK: Empty PDA, only used as a signer
+    #[account(
+        seeds = [b"hook_authority"],
+        bump
+    )]
+    pub hook_authority: Option<AccountInfo<'info>>,
+}
+
+
+/// Parameters for settling an expired position
+///
+/// Currently empty, the settlement price is fixed by the crank.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SettleExpiredPositionParams {}
+
+/// Settle a position in an expired power futures market
+///
+/// This function:
+/// 1. Validates permissions and that the market has been settled
+/// 2. Calculates profit/loss and fees at the fixed settlement price
+/// 3. Unlocks pool funds
+/// 4. Transfers remaining collateral to user
+/// 5. Updates custody statistics (volume, open interest, PnL)
+/// 6. Removes position from custody tracking
+/// 7. Closes the position account (returns rent to owner)
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// Error if validation fails, otherwise Ok(())
+pub fn settle_expired_position(
+    ctx: Context<SettleExpiredPosition>,
+    _params: &SettleExpiredPositionParams,
+) -> Result<()> {
+    // Check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Positions can be closed until the market is settled
+    require!(
+        custody.lifecycle.allows_close(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+
+    // Get current time for calculations
+    let curtime = perpetuals.get_time()?;
+
+    // Market must be expired and settled by the crank
+    require!(
+        custody.is_expired(curtime) && custody.settlement_price > 0,
+        PerpetualsError::CustodyNotSettled
+    );
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // Fixed settlement price is used as both spot and EMA price of the position token
+    let settlement_price = OraclePrice {
+        price: custody.settlement_price,
+        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+    };
+
+    // Get collateral token prices (spot and EMA)
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Close,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Close,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Settlement pays out at the fixed price, without trade spread
+    let mut settlement_custody = custody.clone();
+    settlement_custody.pricing.trade_spread_long = 0;
+    settlement_custody.pricing.trade_spread_short = 0;
+
+    // Calculate final settlement amounts (collateral to return, fees, PnL)
+    msg!("Settle position");
+    let (transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+        position,
+        &settlement_price,
+        &settlement_price,
+        &settlement_custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    // Convert fee to collateral token if needed
+    let fee_amount_usd = settlement_price.get_asset_amount_usd(
+        fee_amount,
+        custody.decimals,
+        RoundingDirection::Up,
+    )?;
+    if position.side == Side::Short || custody.is_virtual {
+        fee_amount = collateral_token_ema_price
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals, RoundingDirection::Up)?;
+    }
+
+    msg!("Settlement price: {}", custody.settlement_price);
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Collected fee: {}", fee_amount);
+    msg!("Amount out: {}", transfer_amount);
+
+    // Unlock funds that were locked for this position
+    let unlocked = collateral_custody.unlock_funds(position.locked_amount)?;
+    emit!(LockChanged {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        custody: collateral_custody.key(),
+        position: position.key(),
+        is_lock: false,
+        amount: unlocked,
+        locked: collateral_custody.assets.locked,
+        owned: collateral_custody.assets.owned,
+        time: curtime,
+    });
+
+    // Check pool has sufficient funds available
+    // Token ratio bounds don't apply to expiry settlements (RatioFlow::ExpirySettlement)
+    msg!("Check pool constraints");
+    require!(
+        pool.check_available_amount(transfer_amount, collateral_custody)?,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // Transfer remaining collateral to user
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // Settle the position in custody accounts and notify the pool position hook
+    // Borrow interest paid by the position feeds the pnl reserve
+    msg!("Update custody stats");
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+    let interest_amount = collateral_token_ema_price.get_token_amount(
+        interest_usd,
+        collateral_custody.decimals,
+        RoundingDirection::Down,
+    )?;
+    let hook_payload = PositionHookPayload {
+        event: PositionHookEvent::Close,
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        side: position.side,
+        size_usd: position.size_usd,
+        price: custody.settlement_price,
+        time: curtime,
+    };
+    pool.settle_position(
+        position,
+        custody,
+        collateral_custody,
+        &PositionSettlement {
+            amount: transfer_amount,
+            fee_amount,
+            fee_usd: fee_amount_usd,
+            close_fee_usd: fee_amount_usd,
+            profit_usd,
+            loss_usd,
+            interest_amount,
+            ..PositionSettlement::default()
+        },
+        ctx.accounts.hook_program.as_ref(),
+        ctx.accounts.hook_authority.as_ref(),
+        ctx.bumps.hook_authority,
+        &hook_payload,
+    )?;
+
+    // Stop counting the closed position
+    if let Some(owner_positions) = ctx.accounts.owner_positions.as_mut() {
+        owner_positions.remove_position();
+    }
+
+    // Mark the position summary closed
+    if let Some(position_summary) = ctx.accounts.position_summary.as_mut() {
+        position_summary.clear(curtime);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! StageParams instruction handler
+//!
+//! This instruction lets an admin write serialized instruction params into the staged
+//! params account of a pool, one chunk per transaction. Admin instructions with
+//! payloads too large for a single transaction (add_custody_from_staged) read their
+//! params from this account. Staging itself changes nothing, the executing instruction
+//! still requires multisig approval over the staged params.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{multisig::Multisig, pool::Pool, staged_params::StagedParams},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for staging params
+#[derive(Accounts)]
+pub struct StageParams<'info> {
+    /// Admin account that must sign (must be one of the multisig signers, pays rent)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account (read-only, used to check the admin)
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Pool the params apply to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Staged params account (PDA derived from pool)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = StagedParams::LEN,
+        seeds = [b"staged_params",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub staged_params: AccountLoader<'info, StagedParams>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for staging params
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StageParamsParams {
+    /// Offset of the chunk in the serialized params (0 restarts staging)
+    pub offset: u32,
+    /// Chunk of the serialized params
+    pub data: Vec<u8>,
+}
+
+/// Write a chunk of serialized params into the staged params account
+///
+/// The process:
+/// 1. Validates that the admin is one of the multisig signers
+/// 2. Initializes the staged params account on first use
+/// 3. Appends the chunk, restarting staging at offset 0
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Chunk offset and bytes
+///
+/// # Returns
+/// `Result<()>` - Success if the chunk was staged
+pub fn stage_params(ctx: Context<StageParams>, params: &StageParamsParams) -> Result<()> {
+    // Validate inputs
+    require!(
+        ctx.accounts
+            .multisig
+            .load()?
+            .is_signer(&ctx.accounts.admin.key())?,
+        PerpetualsError::MultisigAccountNotAuthorized
+    );
+
+    // Write chunk
+    let staged_params_account = &ctx.accounts.staged_params;
+    let mut staged_params = if let Ok(staged_params) = staged_params_account.load_mut() {
+        staged_params
+    } else {
+        staged_params_account.load_init()?
+    };
+    staged_params.pool = ctx.accounts.pool.key();
+    staged_params.bump = ctx.bumps.staged_params;
+    staged_params.write(params.offset, &params.data)?;
+    let data_len = staged_params.data_len;
+    msg!("Staged params length: {}", data_len);
+
+    Ok(())
+}
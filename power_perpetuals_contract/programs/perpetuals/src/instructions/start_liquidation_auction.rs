@@ -0,0 +1,206 @@
+//! StartLiquidationAuction instruction handler
+//!
+//! This instruction allows anyone to start the liquidation reward auction of a position
+//! that can be liquidated. The liquidation reward starts at a share of the full reward
+//! and ramps up slot by slot, so keepers compete on the reward they accept instead of
+//! on transaction priority. The auction ends when the position is liquidated, passes a
+//! leverage check again or expires. Called on a position that can't be liquidated, it
+//! ends the running auction.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::LiquidationAuctionStarted,
+        state::{
+            custody::Custody, oracle::OracleOperation, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for starting a liquidation auction
+#[derive(Accounts)]
+pub struct StartLiquidationAuction<'info> {
+    /// Keeper account (signer, pays for transaction fees)
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Position account (mutable, auction start will be recorded)
+    #[account(
+        mut,
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    /// Custody account for the position token
+    #[account(
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the position token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    /// Custody account for the collateral token
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the collateral token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Parameters for starting a liquidation auction
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StartLiquidationAuctionParams {}
+
+/// Start the liquidation reward auction of a position
+///
+/// The process:
+/// 1. Validates the pool runs liquidation auctions
+/// 2. Checks whether the position can be liquidated at current prices, a position that
+///    can't be liquidated has its auction ended (error if none was started)
+/// 3. Records the current slot as the auction start, unless already running
+/// 4. Emits a LiquidationAuctionStarted event
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Parameters (currently unused)
+///
+/// # Returns
+/// `Result<()>` - Success if the auction is running
+pub fn start_liquidation_auction(
+    ctx: Context<StartLiquidationAuction>,
+    _params: &StartLiquidationAuctionParams,
+) -> Result<()> {
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let pool = ctx.accounts.pool.as_mut();
+    let position = ctx.accounts.position.as_mut();
+    let custody = ctx.accounts.custody.as_ref();
+    let collateral_custody = ctx.accounts.collateral_custody.as_ref();
+    require!(
+        pool.liquidation_auction_slots > 0,
+        PerpetualsError::InstructionNotAllowed
+    );
+
+    // Expired power futures markets can only be settled
+    let curtime = perpetuals.get_time()?;
+    require!(
+        !custody.is_expired(curtime),
+        PerpetualsError::CustodyExpired
+    );
+
+    // Get position and collateral token prices from oracle (spot and EMA)
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+        OracleOperation::Liquidate,
+    )?;
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+        OracleOperation::Liquidate,
+    )?;
+
+    // Value liquid staking collateral at its fair value in underlying terms
+    let collateral_token_price =
+        collateral_custody.get_fair_price(&collateral_token_price, curtime)?;
+    let collateral_token_ema_price =
+        collateral_custody.get_fair_price(&collateral_token_ema_price, curtime)?;
+
+    // Validate the position can be liquidated
+    let liquidation_check = pool.get_liquidation_check(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    if !liquidation_check.liquidatable {
+        // The position recovered, a started auction is over
+        require!(
+            position.liquidation_auction_slot != 0,
+            PerpetualsError::InvalidPositionState
+        );
+        msg!("Auction ended, the position can't be liquidated");
+        position.liquidation_auction_slot = 0;
+        return Ok(());
+    }
+
+    let slot = Clock::get()?.slot;
+    if pool.is_liquidation_auction_running(position, slot) {
+        msg!("Auction already started at slot {}", position.liquidation_auction_slot);
+        return Ok(());
+    }
+
+    // Record the auction start
+    position.liquidation_auction_slot = slot;
+    msg!("Auction started at slot {}", slot);
+
+    emit!(LiquidationAuctionStarted {
+        pool: pool.key(),
+        seq: pool.next_event_seq()?,
+        position: position.key(),
+        owner: position.owner,
+        custody: custody.key(),
+        slot,
+        end_slot: slot.saturating_add(pool.liquidation_auction_slots),
+        time: curtime,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! StartStatsEpoch instruction handler
+//!
+//! This instruction allows admins to start a new stats epoch for a custody. Lifetime
+//! volume and fee counters keep accumulating, while counters since the epoch start
+//! (reported by get_custody_stats) are reset to zero. This requires multisig approval.
+
+use {
+    crate::state::{
+        custody::Custody,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for starting a custody stats epoch
+#[derive(Accounts)]
+pub struct StartStatsEpoch<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account (mutable, stats epoch will be reset)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Parameters for starting a custody stats epoch
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StartStatsEpochParams {}
+
+/// Start a new stats epoch for a custody
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Snapshots the lifetime volume and fee counters as the epoch start
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently empty)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn start_stats_epoch<'info>(
+    ctx: Context<'_, '_, '_, 'info, StartStatsEpoch<'info>>,
+    params: &StartStatsEpochParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::StartStatsEpoch, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Start new epoch
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    ctx.accounts.custody.start_stats_epoch(curtime);
+    msg!("Stats epoch started at: {}", curtime);
+
+    Ok(0)
+}
@@ -8,8 +8,12 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
-        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+        invariants,
+        math::{self, RoundingDirection},
+        state::{
+            custody::{Custody, FeeType}, market_maker::MarketMaker, oracle::OracleOperation,
+            perpetuals::Perpetuals, pool::{Pool, RatioFlow},
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
@@ -80,7 +84,7 @@ pub struct Swap<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account
+        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub receiving_custody_oracle_account: AccountInfo<'info>,
 
@@ -108,7 +112,7 @@ pub struct Swap<'info> {
     /// 
     /// CHECK: Oracle account, validated by constraint
     #[account(
-        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
     )]
     pub dispensing_custody_oracle_account: AccountInfo<'info>,
 
@@ -123,6 +127,21 @@ pub struct Swap<'info> {
     pub dispensing_custody_token_account: Box<Account<'info, TokenAccount>>,
 
     token_program: Program<'info, Token>,
+
+    /// Optional market maker account of the owner (discounted spreads)
+    #[account(
+        mut,
+        seeds = [b"market_maker",
+                 pool.key().as_ref(),
+                 owner.key().as_ref()],
+        bump = market_maker.bump
+    )]
+    pub market_maker: Option<Box<Account<'info, MarketMaker>>>,
+
+    /// Optional circuit-breaker feed, required if the protocol follows a risk oracle
+    ///
+    /// CHECK: Validated against the perpetuals risk oracle
+    pub risk_oracle_account: Option<AccountInfo<'info>>,
 }
 
 /// Parameters for swapping tokens
@@ -134,19 +153,26 @@ pub struct SwapParams {
     pub min_amount_out: u64,
 }
 
+/// Outcome of a swap leg
+pub struct SwapLeg {
+    /// Amount dispensed after the output fee (in dispensing token decimals)
+    pub amount_out: u64,
+    /// Fee on the input token
+    pub fee_in: u64,
+    /// Fee on the output token
+    pub fee_out: u64,
+    /// Value of both fees in USD
+    pub fee_usd: u64,
+}
+
 /// Swap tokens within a pool
 /// 
 /// This function allows users to swap tokens of one type for tokens of another type within
 /// the same pool. The process:
 /// 1. Validates permissions and inputs
-/// 2. Fetches oracle prices for both tokens (spot and EMA)
-/// 3. Calculates swap amount based on prices and pool state
-/// 4. Calculates swap fees
-/// 5. Validates slippage protection
-/// 6. Validates token ratios remain within acceptable range
-/// 7. Validates pool has sufficient available funds
-/// 8. Transfers tokens (deposit from user, withdrawal to user)
-/// 9. Updates custody statistics and borrow rates
+/// 2. Prices the swap, checks pool constraints and updates custody stats (swap_leg)
+/// 3. Validates slippage protection
+/// 4. Transfers tokens (deposit from user, withdrawal to user)
 /// 
 /// # Arguments
 /// * `ctx` - Context containing all required accounts
@@ -156,75 +182,198 @@ pub struct SwapParams {
 /// `Result<()>` - Success if swap was executed successfully
 pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     // Check permissions
-    // All three (perpetuals, receiving_custody, dispensing_custody) must allow swaps
-    // Both custodies must not be virtual
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let receiving_custody = ctx.accounts.receiving_custody.as_mut();
-    let dispensing_custody = ctx.accounts.dispensing_custody.as_mut();
     require!(
-        perpetuals.permissions.allow_swap
-            && receiving_custody.permissions.allow_swap
-            && dispensing_custody.permissions.allow_swap
-            && !receiving_custody.is_virtual
-            && !dispensing_custody.is_virtual,
+        perpetuals.permissions.allow_swap,
         PerpetualsError::InstructionNotAllowed
     );
+    // Swaps let traders dump an incident asset on the pool, refused during incidents
+    perpetuals.risk_oracle.check_risk_increase(
+        ctx.accounts.risk_oracle_account.as_ref(),
+        perpetuals.get_time()?,
+    )?;
 
     // Validate inputs
     msg!("Validate inputs");
     if params.amount_in == 0 {
         return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
     }
+
+    // Price the swap and update custodies
+    let curtime = perpetuals.get_time()?;
+    let swap_leg = swap_leg(
+        ctx.accounts.pool.as_ref(),
+        &mut ctx.accounts.receiving_custody,
+        &ctx.accounts.receiving_custody_oracle_account,
+        &mut ctx.accounts.dispensing_custody,
+        &ctx.accounts.dispensing_custody_oracle_account,
+        ctx.accounts.market_maker.as_deref_mut(),
+        params.amount_in,
+        curtime,
+    )?;
+
+    // Validate slippage protection
+    // Ensure user receives at least the minimum expected tokens
+    require_gte!(
+        swap_leg.amount_out,
+        params.min_amount_out,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // Transfer tokens
+    msg!("Transfer tokens");
+    // Transfer tokens from user to pool (deposit)
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .receiving_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount_in,
+    )?;
+
+    // Transfer tokens from pool to user (withdrawal, after fees)
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .dispensing_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        swap_leg.amount_out,
+    )?;
+
+    // Check custody accounting identities (strict-invariants builds only)
+    invariants::check_custody(
+        &ctx.accounts.receiving_custody,
+        &mut ctx.accounts.receiving_custody_token_account,
+    )?;
+    invariants::check_custody(
+        &ctx.accounts.dispensing_custody,
+        &mut ctx.accounts.dispensing_custody_token_account,
+    )?;
+
+    Ok(())
+}
+
+/// Execute a swap between two custodies of a pool, without moving tokens
+///
+/// Shared by swap and cross_pool_swap. Token transfers and slippage checks are left
+/// to the caller. The process:
+/// 1. Validates custody permissions, lifecycle and oracle safe mode
+/// 2. Fetches oracle prices for both tokens (spot and EMA)
+/// 3. Calculates swap amount based on prices and pool state
+/// 4. Calculates swap fees (discounted for market makers with remaining capacity)
+/// 5. Validates token ratios remain within acceptable range
+/// 6. Validates pool has sufficient available funds
+/// 7. Updates custody statistics and borrow rates
+///
+/// # Arguments
+/// * `pool` - Pool both custodies belong to
+/// * `receiving_custody` - Custody of the token being deposited
+/// * `receiving_custody_oracle_account` - Oracle account of the deposited token
+/// * `dispensing_custody` - Custody of the token being dispensed
+/// * `dispensing_custody_oracle_account` - Oracle account of the dispensed token
+/// * `market_maker` - Optional market maker account of the owner
+/// * `amount_in` - Amount of tokens deposited
+/// * `curtime` - Current timestamp
+///
+/// # Returns
+/// `SwapLeg` with the amount to dispense and the fees
+#[allow(clippy::too_many_arguments)]
+pub fn swap_leg<'info>(
+    pool: &Pool,
+    receiving_custody: &mut Account<'info, Custody>,
+    receiving_custody_oracle_account: &AccountInfo<'info>,
+    dispensing_custody: &mut Account<'info, Custody>,
+    dispensing_custody_oracle_account: &AccountInfo<'info>,
+    market_maker: Option<&mut Account<'info, MarketMaker>>,
+    amount_in: u64,
+    curtime: i64,
+) -> Result<SwapLeg> {
+    // Both custodies must allow swaps and must not be virtual
+    require!(
+        receiving_custody.permissions.allow_swap
+            && dispensing_custody.permissions.allow_swap
+            && !receiving_custody.is_virtual
+            && !dispensing_custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    // Swaps need active markets
+    require!(
+        receiving_custody.lifecycle.allows_open() && dispensing_custody.lifecycle.allows_open(),
+        PerpetualsError::MarketLifecycleRestricted
+    );
+    require!(
+        !receiving_custody.oracle_safe_mode && !dispensing_custody.oracle_safe_mode,
+        PerpetualsError::OracleSafeMode
+    );
     // Ensure receiving and dispensing custodies are different
     require_keys_neq!(receiving_custody.key(), dispensing_custody.key());
 
-    // Get current time and token IDs for calculations
-    let pool = ctx.accounts.pool.as_mut();
-    let curtime = perpetuals.get_time()?;
+    // Get token IDs for calculations
     let token_id_in = pool.get_token_id(&receiving_custody.key())?;
     let token_id_out = pool.get_token_id(&dispensing_custody.key())?;
 
     // Fetch oracle prices for the token being deposited (receiving custody)
     // Get both spot price and EMA price
-    let received_token_price = OraclePrice::new_from_oracle(
-        &ctx.accounts
-            .receiving_custody_oracle_account
-            .to_account_info(),
-        &receiving_custody.oracle,
+    let received_token_price = receiving_custody.get_oracle_price(
+        receiving_custody_oracle_account,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let received_token_ema_price = OraclePrice::new_from_oracle(
-        &ctx.accounts
-            .receiving_custody_oracle_account
-            .to_account_info(),
-        &receiving_custody.oracle,
+    let received_token_ema_price = receiving_custody.get_oracle_price(
+        receiving_custody_oracle_account,
         curtime,
         receiving_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let received_token_price = receiving_custody.get_fair_price(&received_token_price, curtime)?;
+    let received_token_ema_price =
+        receiving_custody.get_fair_price(&received_token_ema_price, curtime)?;
+
     // Fetch oracle prices for the token being dispensed (dispensing custody)
     // Get both spot price and EMA price
-    let dispensed_token_price = OraclePrice::new_from_oracle(
-        &ctx.accounts
-            .dispensing_custody_oracle_account
-            .to_account_info(),
-        &dispensing_custody.oracle,
+    let dispensed_token_price = dispensing_custody.get_oracle_price(
+        dispensing_custody_oracle_account,
         curtime,
         false,
+        OracleOperation::Open,
     )?;
 
-    let dispensed_token_ema_price = OraclePrice::new_from_oracle(
-        &ctx.accounts
-            .dispensing_custody_oracle_account
-            .to_account_info(),
-        &dispensing_custody.oracle,
+    let dispensed_token_ema_price = dispensing_custody.get_oracle_price(
+        dispensing_custody_oracle_account,
         curtime,
         dispensing_custody.pricing.use_ema,
+        OracleOperation::Open,
     )?;
 
+    // Value liquid staking tokens at their fair value in underlying terms
+    let dispensed_token_price = dispensing_custody.get_fair_price(&dispensed_token_price, curtime)?;
+    let dispensed_token_ema_price =
+        dispensing_custody.get_fair_price(&dispensed_token_ema_price, curtime)?;
+
+    // Market makers with remaining volume capacity swap at discounted spread and fees
+    let swap_volume_usd = received_token_price
+        .get_asset_amount_usd(amount_in, receiving_custody.decimals, RoundingDirection::Up)?;
+    let market_maker = match market_maker {
+        Some(market_maker) if market_maker.has_capacity(swap_volume_usd)? => {
+            market_maker.add_volume(swap_volume_usd)?;
+            Some(market_maker)
+        }
+        _ => None,
+    };
+    let mm_custody = match &market_maker {
+        Some(market_maker) => Some(market_maker.get_custody(receiving_custody)?),
+        None => None,
+    };
+
     // Calculate swap amount based on prices and pool state
     msg!("Compute swap amount");
     let amount_out = pool.get_swap_amount(
@@ -232,56 +381,57 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         &received_token_ema_price,
         &dispensed_token_price,
         &dispensed_token_ema_price,
-        receiving_custody,
+        mm_custody.as_ref().unwrap_or(receiving_custody),
         dispensing_custody,
-        params.amount_in,
+        amount_in,
+        curtime,
     )?;
 
     // Calculate swap fees
     // Fees are calculated for both input and output tokens
-    let fees = pool.get_swap_fees(
+    let mut fees = pool.get_swap_fees(
         token_id_in,
         token_id_out,
-        params.amount_in,
+        amount_in,
         amount_out,
         receiving_custody,
         &received_token_price,
         dispensing_custody,
         &dispensed_token_price,
     )?;
+    if let Some(market_maker) = &market_maker {
+        fees = (
+            market_maker.get_rebated_fee(fees.0)?,
+            market_maker.get_rebated_fee(fees.1)?,
+        );
+    }
     msg!("Collected fees: {} {}", fees.0, fees.1);
 
     // Calculate amount user will receive after deducting output fee
     let no_fee_amount = math::checked_sub(amount_out, fees.1)?;
     msg!("Amount out: {}", no_fee_amount);
-    
-    // Validate slippage protection
-    // Ensure user receives at least the minimum expected tokens
-    require_gte!(
-        no_fee_amount,
-        params.min_amount_out,
-        PerpetualsError::InsufficientAmountReturned
-    );
 
     // Check pool constraints
     msg!("Check pool constraints");
     // Calculate protocol fees (portion of swap fees that go to protocol)
-    let protocol_fee_in = Pool::get_fee_amount(receiving_custody.fees.protocol_share, fees.0)?;
-    let protocol_fee_out = Pool::get_fee_amount(dispensing_custody.fees.protocol_share, fees.1)?;
+    let protocol_fee_in = Pool::get_fee_amount(receiving_custody.fees.get_protocol_share(FeeType::Swap), fees.0)?;
+    let protocol_fee_out = Pool::get_fee_amount(dispensing_custody.fees.get_protocol_share(FeeType::Swap), fees.1)?;
     // Calculate net deposit and withdrawal amounts (after protocol fees)
-    let deposit_amount = math::checked_sub(params.amount_in, protocol_fee_in)?;
+    let deposit_amount = math::checked_sub(amount_in, protocol_fee_in)?;
     let withdrawal_amount = math::checked_add(no_fee_amount, protocol_fee_out)?;
 
     // Ensure token ratios remain within acceptable range after swap
     // Check both input token ratio (after deposit) and output token ratio (after withdrawal)
     require!(
         pool.check_token_ratio(
+            RatioFlow::Swap,
             token_id_in,
             deposit_amount,
             0,
             receiving_custody,
             &received_token_price
         )? && pool.check_token_ratio(
+            RatioFlow::Swap,
             token_id_out,
             0,
             withdrawal_amount,
@@ -292,52 +442,33 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     );
     
     // Ensure pool has sufficient available funds for withdrawal
-    // (owned - locked >= withdrawal_amount)
+    // (owned - locked - pending claims >= withdrawal_amount)
     require!(
-        math::checked_sub(
-            dispensing_custody.assets.owned,
-            dispensing_custody.assets.locked
-        )? >= withdrawal_amount,
+        dispensing_custody.get_available_amount()? >= withdrawal_amount,
         PerpetualsError::CustodyAmountLimit
     );
 
-    // Transfer tokens
-    msg!("Transfer tokens");
-    // Transfer tokens from user to pool (deposit)
-    perpetuals.transfer_tokens_from_user(
-        ctx.accounts.funding_account.to_account_info(),
-        ctx.accounts
-            .receiving_custody_token_account
-            .to_account_info(),
-        ctx.accounts.owner.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        params.amount_in,
-    )?;
-
-    // Transfer tokens from pool to user (withdrawal, after fees)
-    perpetuals.transfer_tokens(
-        ctx.accounts
-            .dispensing_custody_token_account
-            .to_account_info(),
-        ctx.accounts.receiving_account.to_account_info(),
-        ctx.accounts.transfer_authority.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        no_fee_amount,
-    )?;
-
     // Update custody statistics
     msg!("Update custody stats");
     // Update receiving custody stats (token being deposited)
     // Track volume in USD
-    receiving_custody.volume_stats.swap_usd = receiving_custody.volume_stats.swap_usd.wrapping_add(
-        received_token_price.get_asset_amount_usd(params.amount_in, receiving_custody.decimals)?,
-    );
+    receiving_custody.volume_stats.swap_usd = math::checked_add(
+        receiving_custody.volume_stats.swap_usd,
+        received_token_price.get_asset_amount_usd(
+            amount_in,
+            receiving_custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
 
     // Track collected fees in USD
+    let fee_in_usd = received_token_price.get_asset_amount_usd(
+        fees.0,
+        receiving_custody.decimals,
+        RoundingDirection::Down,
+    )?;
     receiving_custody.collected_fees.swap_usd =
-        receiving_custody.collected_fees.swap_usd.wrapping_add(
-            received_token_price.get_asset_amount_usd(fees.0, receiving_custody.decimals)?,
-        );
+        math::checked_add(receiving_custody.collected_fees.swap_usd, fee_in_usd as u128)?;
 
     // Update owned assets (tokens owned by the pool after deposit)
     receiving_custody.assets.owned =
@@ -349,16 +480,23 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
 
     // Update dispensing custody stats (token being withdrawn)
     // Track collected fees in USD
+    let fee_out_usd = dispensed_token_price.get_asset_amount_usd(
+        fees.1,
+        dispensing_custody.decimals,
+        RoundingDirection::Down,
+    )?;
     dispensing_custody.collected_fees.swap_usd =
-        dispensing_custody.collected_fees.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(fees.1, dispensing_custody.decimals)?,
-        );
+        math::checked_add(dispensing_custody.collected_fees.swap_usd, fee_out_usd as u128)?;
 
     // Track volume in USD
-    dispensing_custody.volume_stats.swap_usd =
-        dispensing_custody.volume_stats.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(amount_out, dispensing_custody.decimals)?,
-        );
+    dispensing_custody.volume_stats.swap_usd = math::checked_add(
+        dispensing_custody.volume_stats.swap_usd,
+        dispensed_token_price.get_asset_amount_usd(
+            amount_out,
+            dispensing_custody.decimals,
+            RoundingDirection::Down,
+        )? as u128,
+    )?;
 
     // Update protocol fees (portion of swap fee that goes to protocol)
     dispensing_custody.assets.protocol_fees =
@@ -372,5 +510,10 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     receiving_custody.update_borrow_rate(curtime)?;
     dispensing_custody.update_borrow_rate(curtime)?;
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(SwapLeg {
+        amount_out: no_fee_amount,
+        fee_in: fees.0,
+        fee_out: fees.1,
+        fee_usd: math::checked_add(fee_in_usd, fee_out_usd)?,
+    })
+}
@@ -0,0 +1,152 @@
+//! SweepSol instruction handler
+//!
+//! This instruction allows admins to recover operational SOL. Lamports above the
+//! rent-exempt minimum accumulate on program-owned PDAs (realloc funding, direct
+//! transfers). The excess of every program-owned account passed in is swept into the
+//! SOL fee vault PDA, and an optional amount is then paid out of the vault. Every
+//! account keeps its rent-exempt minimum. Crank state accounts and order commitments
+//! can't be swept, their excess lamports are the keeper bounty budget and the
+//! commit-reveal deposits. This requires multisig approval.
+
+use {
+    crate::{
+        math,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for sweeping SOL into the fee vault
+#[derive(Accounts)]
+pub struct SweepSol<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// SOL fee vault PDA collecting swept lamports (created on first use)
+    ///
+    /// CHECK: Empty PDA owned by the program, only holds lamports
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 0,
+        seeds = [b"sol_fee_vault"],
+        bump
+    )]
+    pub sol_fee_vault: AccountInfo<'info>,
+
+    /// Receiving account for the SOL paid out of the vault (mutable)
+    /// Must be an empty account (no data)
+    ///
+    /// CHECK: SOL receiving account, validated by constraint
+    #[account(
+        mut,
+        constraint = receiving_account.data_is_empty()
+    )]
+    pub receiving_account: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+    // Remaining accounts (writable, unsigned):
+    //   - program-owned accounts to sweep excess lamports from
+}
+
+/// Parameters for sweeping SOL
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SweepSolParams {
+    /// Amount of SOL to pay out of the vault after sweeping (in lamports, 0 = sweep only)
+    pub amount: u64,
+}
+
+/// Sweep excess SOL from program-owned accounts into the fee vault
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Moves lamports above the rent-exempt minimum of each remaining account to the vault
+/// 3. Validates the vault holds enough excess SOL for the payout
+/// 4. Transfers the requested amount from the vault to the receiving account
+///
+/// Remaining accounts are part of the signed instruction data, so all signers approve
+/// the same set of sweep sources.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Amount to pay out of the vault
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn sweep_sol<'info>(
+    ctx: Context<'_, '_, '_, 'info, SweepSol<'info>>,
+    params: &SweepSolParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SweepSol, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    let rent = Rent::get()?;
+    let sol_fee_vault = ctx.accounts.sol_fee_vault.to_account_info();
+
+    // Sweep excess lamports into the vault
+    msg!("Sweep excess SOL");
+    let mut swept: u64 = 0;
+    let excluded = [sol_fee_vault.key(), ctx.accounts.multisig.key()];
+    for account in ctx.remaining_accounts {
+        let excess = Perpetuals::get_sweepable_lamports(account, &excluded, &rent)?;
+        if excess > 0 {
+            Perpetuals::transfer_sol_from_owned(account.clone(), sol_fee_vault.clone(), excess)?;
+            swept = math::checked_add(swept, excess)?;
+        }
+    }
+
+    // Pay out of the vault, keeping its rent-exempt minimum
+    let available_balance = sol_fee_vault
+        .try_lamports()?
+        .saturating_sub(rent.minimum_balance(0));
+    msg!(
+        "Swept SOL: {}, withdraw: {} / {}",
+        swept,
+        params.amount,
+        available_balance
+    );
+    if available_balance < params.amount {
+        return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+    }
+    if params.amount > 0 {
+        Perpetuals::transfer_sol_from_owned(
+            sol_fee_vault,
+            ctx.accounts.receiving_account.to_account_info(),
+            params.amount,
+        )?;
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,69 @@
+//! UpdateComplianceFreeze instruction handler
+//!
+//! This instruction allows the compliance authority of a custody to freeze or unfreeze
+//! an owner without multisig approval. Freezing only blocks new exposure, the authority
+//! can't move or lock the funds of frozen owners.
+
+use {
+    crate::state::{compliance_freeze::ComplianceFreeze, custody::Custody},
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for updating a custody freeze list
+#[derive(Accounts)]
+pub struct UpdateComplianceFreeze<'info> {
+    /// Compliance authority of the freeze list (signer)
+    pub authority: Signer<'info>,
+
+    /// Custody the freeze list applies to
+    #[account(
+        seeds = [b"custody",
+                 custody.pool.as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Freeze list account (mutable, owners will be updated)
+    #[account(
+        mut,
+        has_one = custody,
+        constraint = compliance_freeze.compliance_authority == authority.key(),
+        seeds = [b"compliance_freeze",
+                 custody.key().as_ref()],
+        bump = compliance_freeze.bump
+    )]
+    pub compliance_freeze: Box<Account<'info, ComplianceFreeze>>,
+}
+
+/// Parameters for updating a custody freeze list
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateComplianceFreezeParams {
+    /// Trader wallet address
+    pub owner: Pubkey,
+    /// true to freeze the owner, false to unfreeze it
+    pub frozen: bool,
+}
+
+/// Freeze or unfreeze an owner on a custody
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Owner and whether it is frozen
+///
+/// # Returns
+/// `Result<()>` - Success if the freeze list was updated
+pub fn update_compliance_freeze(
+    ctx: Context<UpdateComplianceFreeze>,
+    params: &UpdateComplianceFreezeParams,
+) -> Result<()> {
+    let compliance_freeze = ctx.accounts.compliance_freeze.as_mut();
+    if params.frozen {
+        compliance_freeze.add_owner(params.owner)?;
+    } else {
+        compliance_freeze.remove_owner(&params.owner);
+    }
+    msg!("Frozen owners: {}", compliance_freeze.owners.len());
+
+    Ok(())
+}
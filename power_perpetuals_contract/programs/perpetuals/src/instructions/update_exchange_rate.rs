@@ -0,0 +1,80 @@
+//! UpdateExchangeRate instruction handler
+//!
+//! This instruction allows anyone to refresh the cached exchange rate of a custody
+//! holding a liquid staking token. The rate is read from the configured custom oracle
+//! or SPL stake pool account and used to value the custody token at its fair value.
+
+use {
+    crate::state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for updating a custody exchange rate
+#[derive(Accounts)]
+pub struct UpdateExchangeRate<'info> {
+    /// Payer account (signer, pays for transaction fees)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account (mutable, exchange rate will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Exchange rate source account (custom oracle or stake pool)
+    ///
+    /// CHECK: Rate account, validated by constraint and parsed by custody exchange rate params
+    #[account(
+        constraint = rate_account.key() == custody.exchange_rate.rate_account
+    )]
+    pub rate_account: AccountInfo<'info>,
+}
+
+/// Update the cached exchange rate of a custody
+///
+/// The process:
+/// 1. Reads the custody token to underlying rate from the rate account
+/// 2. Stores the rate and update time in the custody
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<u64>` - Updated exchange rate (scaled to RATE_DECIMALS), or error
+pub fn update_exchange_rate(ctx: Context<UpdateExchangeRate>) -> Result<u64> {
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody = ctx.accounts.custody.as_mut();
+
+    msg!("Update custody exchange rate");
+    msg!("Previous value: {}", custody.exchange_rate_state.rate);
+
+    let rate = custody
+        .exchange_rate
+        .get_rate(&ctx.accounts.rate_account.to_account_info(), curtime)?;
+    custody.exchange_rate_state.rate = rate;
+    custody.exchange_rate_state.last_update = curtime;
+
+    msg!("Updated value: {}", rate);
+
+    Ok(rate)
+}
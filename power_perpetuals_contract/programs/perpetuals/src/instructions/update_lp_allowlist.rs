@@ -0,0 +1,67 @@
+//! UpdateLpAllowlist instruction handler
+//!
+//! This instruction allows the compliance authority of a pool to add or remove a
+//! liquidity provider from the pool allowlist without multisig approval.
+
+use {
+    crate::state::{lp_allowlist::LpAllowlist, pool::Pool},
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for updating a pool allowlist
+#[derive(Accounts)]
+pub struct UpdateLpAllowlist<'info> {
+    /// Compliance authority of the allowlist (signer)
+    pub authority: Signer<'info>,
+
+    /// Pool the allowlist applies to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Allowlist account (mutable, owners will be updated)
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = lp_allowlist.compliance_authority == authority.key(),
+        seeds = [b"lp_allowlist",
+                 pool.key().as_ref()],
+        bump = lp_allowlist.bump
+    )]
+    pub lp_allowlist: Box<Account<'info, LpAllowlist>>,
+}
+
+/// Parameters for updating a pool allowlist
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateLpAllowlistParams {
+    /// Liquidity provider wallet address
+    pub owner: Pubkey,
+    /// true to allowlist the owner, false to remove it
+    pub allowed: bool,
+}
+
+/// Add or remove a liquidity provider from a pool allowlist
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Owner and whether it is allowed
+///
+/// # Returns
+/// `Result<()>` - Success if the allowlist was updated
+pub fn update_lp_allowlist(
+    ctx: Context<UpdateLpAllowlist>,
+    params: &UpdateLpAllowlistParams,
+) -> Result<()> {
+    let lp_allowlist = ctx.accounts.lp_allowlist.as_mut();
+    if params.allowed {
+        lp_allowlist.add_owner(params.owner)?;
+    } else {
+        lp_allowlist.remove_owner(&params.owner);
+    }
+    msg!("Allowlisted owners: {}", lp_allowlist.owners.len());
+
+    Ok(())
+}
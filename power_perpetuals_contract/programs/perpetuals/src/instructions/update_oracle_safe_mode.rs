@@ -0,0 +1,100 @@
+//! UpdateOracleSafeMode instruction handler
+//!
+//! This instruction allows anyone to check the oracle heartbeat of a custody. If the
+//! last oracle update is older than the configured heartbeat limit, the custody enters
+//! safe mode and stops accepting risk-increasing operations (open position, swap, add
+//! liquidity) until a fresh update is observed.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::OracleSafeModeUpdated,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for updating custody oracle safe mode
+#[derive(Accounts)]
+pub struct UpdateOracleSafeMode<'info> {
+    /// Payer account (signer, pays for transaction fees)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to (mutable, event sequence will be updated)
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account (mutable, safe mode flag will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Oracle account for price feed of the custody token
+    ///
+    /// CHECK: Oracle account, validated by constraint
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.oracle_account @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+}
+
+/// Enter or leave oracle safe mode based on the oracle heartbeat
+///
+/// The process:
+/// 1. Reads the publish time of the last oracle update
+/// 2. Compares its age with max_price_age_sec * heartbeat_mult
+/// 3. Sets safe mode if the heartbeat was missed, clears it otherwise
+/// 4. Emits OracleSafeModeUpdated if the flag changed
+///
+/// Safe mode is always cleared if the heartbeat is disabled (heartbeat_mult = 0).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<bool>` - Updated safe mode flag, or error
+pub fn update_oracle_safe_mode(ctx: Context<UpdateOracleSafeMode>) -> Result<bool> {
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody_key = ctx.accounts.custody.key();
+    let custody = ctx.accounts.custody.as_mut();
+
+    let publish_time = custody.get_oracle_publish_time(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+    )?;
+    let safe_mode = custody.oracle.is_heartbeat_missed(publish_time, curtime);
+
+    if safe_mode != custody.oracle_safe_mode {
+        msg!("Oracle safe mode: {}", safe_mode);
+        custody.oracle_safe_mode = safe_mode;
+        let pool = ctx.accounts.pool.as_mut();
+        emit!(OracleSafeModeUpdated {
+            pool: pool.key(),
+            seq: pool.next_event_seq()?,
+            custody: custody_key,
+            safe_mode,
+            last_update: publish_time,
+            heartbeat_limit_sec: custody.oracle.get_heartbeat_limit(),
+            time: curtime,
+        });
+    }
+
+    Ok(safe_mode)
+}
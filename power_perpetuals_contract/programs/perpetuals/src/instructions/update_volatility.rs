@@ -0,0 +1,86 @@
+//! UpdateVolatility instruction handler
+//!
+//! This instruction allows anyone to refresh the cached volatility of a custody with a
+//! volatility feed. The volatility is read from the configured custom oracle, either
+//! published directly or derived from the oracle confidence interval, and scales the
+//! custody spreads, borrow rate slopes and convexity reserve.
+
+use {
+    crate::state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for updating a custody volatility
+#[derive(Accounts)]
+pub struct UpdateVolatility<'info> {
+    /// Payer account (signer, pays for transaction fees)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account (mutable, volatility will be updated)
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// Volatility source account (custom oracle)
+    ///
+    /// CHECK: Volatility account, validated by constraint and parsed by custody volatility params
+    #[account(
+        constraint = vol_account.key() == custody.volatility.vol_account
+    )]
+    pub vol_account: AccountInfo<'info>,
+}
+
+/// Update the cached volatility of a custody
+///
+/// The process:
+/// 1. Reads the volatility from the volatility account
+/// 2. Stores the volatility and update time in the custody
+/// 3. Updates the borrow rate, its slopes scale with the new volatility
+///
+/// The cached volatility is clamped to the configured bounds when used. Once it is
+/// older than max_vol_age_sec the custody falls back to its static pricing params.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+///
+/// # Returns
+/// `Result<u64>` - Updated volatility (scaled to BPS_DECIMALS), or error
+pub fn update_volatility(ctx: Context<UpdateVolatility>) -> Result<u64> {
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let custody = ctx.accounts.custody.as_mut();
+
+    msg!("Update custody volatility");
+    msg!("Previous value: {}", custody.volatility_state.vol);
+
+    let vol = custody
+        .volatility
+        .get_vol(&ctx.accounts.vol_account.to_account_info(), curtime)?;
+    custody.volatility_state.vol = vol;
+    custody.volatility_state.last_update = curtime;
+    custody.update_borrow_rate(curtime)?;
+
+    msg!("Updated value: {}", vol);
+
+    Ok(vol)
+}
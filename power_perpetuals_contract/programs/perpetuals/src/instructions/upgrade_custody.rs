@@ -3,13 +3,14 @@
 //! This instruction allows admins to upgrade a deprecated custody account to the current
 //! custody format. This is used for migrating custody accounts after protocol upgrades.
 //! The deprecated custody data is loaded, converted to the new format, and the account
-//! is resized and reinitialized with the new structure.
+//! is resized and reinitialized with the new structure. The deprecated and original
+//! custody layouts can be upgraded.
 
 use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::{Custody, DeprecatedCustody},
+            custody::{Custody, CustodyV0},
             multisig::{AdminInstruction, Multisig},
             perpetuals::Perpetuals,
             pool::Pool,
@@ -127,8 +128,10 @@ pub struct UpgradeCustodyParams {}
 /// The process:
 /// 1. Validates multisig signatures (requires enough admin signatures)
 /// 2. Validates the deprecated custody account (owner and data length)
-/// 3. Loads deprecated custody data
-/// 4. Converts deprecated custody data to new format (sets is_virtual to false)
+/// 3. Loads deprecated custody data, the layout is selected by discriminator and data
+///    length
+/// 4. Converts deprecated custody data to new format (sets is_virtual to false for the
+///    deprecated layout, widens volume and fee counters)
 /// 5. Validates new custody configuration
 /// 6. Resizes account to new custody length
 /// 7. Serializes new custody data to account memory
@@ -174,41 +177,14 @@ pub fn upgrade_custody<'info>(
         return Err(anchor_lang::error::ErrorCode::ConstraintOwner.into());
     }
     
-    // Validate account data length matches deprecated custody size
-    if custody_account.try_data_len()? != DeprecatedCustody::LEN {
-        return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into());
-    }
-    
-    // Deserialize deprecated custody data
-    // Use ptr::read to avoid lifetime issues with try_from_unchecked
-    let deprecated_custody_data = {
+    // Convert deprecated custody data to new custody format, the deprecated and the
+    // original layouts are told apart by the discriminator and the data length
+    let custody_data = {
         let data = custody_account.try_borrow_data()?;
-        DeprecatedCustody::try_deserialize(&mut &data[..])?
-    };
-
-    // Convert deprecated custody data to new custody format
-    // Most fields are copied directly, but is_virtual is set to false
-    let custody_data = Custody {
-        pool: deprecated_custody_data.pool,
-        mint: deprecated_custody_data.mint,
-        token_account: deprecated_custody_data.token_account,
-        decimals: deprecated_custody_data.decimals,
-        is_stable: deprecated_custody_data.is_stable,
-        is_virtual: false, // Always set to false for upgraded custodies
-        oracle: deprecated_custody_data.oracle,
-        pricing: deprecated_custody_data.pricing,
-        permissions: deprecated_custody_data.permissions,
-        fees: deprecated_custody_data.fees,
-        borrow_rate: deprecated_custody_data.borrow_rate,
-        assets: deprecated_custody_data.assets,
-        collected_fees: deprecated_custody_data.collected_fees,
-        volume_stats: deprecated_custody_data.volume_stats,
-        trade_stats: deprecated_custody_data.trade_stats,
-        long_positions: deprecated_custody_data.long_positions,
-        short_positions: deprecated_custody_data.short_positions,
-        borrow_rate_state: deprecated_custody_data.borrow_rate_state,
-        bump: deprecated_custody_data.bump,
-        token_account_bump: deprecated_custody_data.token_account_bump,
+        match CustodyV0::load(&data) {
+            Some(custody) => Custody::from(custody),
+            None => return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into()),
+        }
     };
 
     // Validate new custody configuration
@@ -0,0 +1,124 @@
+//! UpgradePerpetuals instruction handler
+//!
+//! This instruction allows admins to upgrade the perpetuals account from the original
+//! layout to the current one. The legacy data is loaded, converted to the new format,
+//! and the account is resized and reinitialized with the new structure.
+
+use {
+    crate::{
+        instructions::upgrade_custody::BpfWriter,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::{Perpetuals, PerpetualsV0},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for upgrading the perpetuals account
+#[derive(Accounts)]
+pub struct UpgradePerpetuals<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Legacy perpetuals account to upgrade (mutable, will be resized and reinitialized)
+    ///
+    /// CHECK: Legacy perpetuals account, validated in function
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump
+    )]
+    pub perpetuals: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for upgrading the perpetuals account
+///
+/// Currently empty, but kept for consistency with other instructions.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePerpetualsParams {}
+
+/// Upgrade the perpetuals account to the current layout
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the perpetuals account owner and discriminator
+/// 3. Loads the legacy data, the original layout is sized for exactly its pools
+/// 4. Converts the legacy data to the current format
+/// 5. Resizes the account to the current length for its pools
+/// 6. Serializes the new data to account memory
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently unused)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn upgrade_perpetuals<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradePerpetuals<'info>>,
+    params: &UpgradePerpetualsParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradePerpetuals, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Load legacy perpetuals data
+    msg!("Load legacy perpetuals");
+    let perpetuals_account = &ctx.accounts.perpetuals;
+    if perpetuals_account.owner != &crate::ID {
+        return Err(anchor_lang::error::ErrorCode::ConstraintOwner.into());
+    }
+
+    let perpetuals_data = {
+        let data = perpetuals_account.try_borrow_data()?;
+        match PerpetualsV0::load(&data) {
+            Some(perpetuals) => Perpetuals::from(perpetuals),
+            None => return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into()),
+        }
+    };
+
+    // Resize perpetuals account to the current length for its pools
+    msg!("Resize perpetuals account");
+    let new_len = Perpetuals::LEN + perpetuals_data.pools.len() * std::mem::size_of::<Pubkey>();
+    Perpetuals::realloc(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.perpetuals.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        new_len,
+        true,
+    )?;
+
+    // Re-initialize the perpetuals account with new data
+    msg!("Re-initialize the perpetuals");
+    let mut data = perpetuals_account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = BpfWriter::new(dst);
+    perpetuals_data.try_serialize(&mut writer)?;
+
+    Ok(0)
+}
@@ -0,0 +1,134 @@
+//! UpgradePool instruction handler
+//!
+//! This instruction allows admins to upgrade a pool account from the original layout to
+//! the current one. The legacy data is loaded, converted to the new format, and the
+//! account is resized and reinitialized with the new structure. The perpetuals account
+//! must be upgraded first.
+
+use {
+    crate::{
+        instructions::upgrade_custody::BpfWriter,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::{Pool, PoolV0, TokenRatios},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for upgrading a pool account
+#[derive(Accounts)]
+pub struct UpgradePool<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Main perpetuals program account, lists the pool
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Legacy pool account to upgrade (mutable, will be resized and reinitialized)
+    ///
+    /// CHECK: Legacy pool account, validated in function
+    #[account(mut)]
+    pub pool: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for upgrading a pool account
+///
+/// Currently empty, but kept for consistency with other instructions.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePoolParams {}
+
+/// Upgrade a pool account to the current layout
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Validates the pool account owner and that the pool is registered
+/// 3. Loads the legacy data, the original layout is sized for exactly its custodies
+/// 4. Converts the legacy data to the current format
+/// 5. Resizes the account to the current length for its custodies
+/// 6. Serializes the new data to account memory
+///
+/// Returns the number of signatures still required (0 if fully signed and executed).
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Parameters (currently unused)
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn upgrade_pool<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+    params: &UpgradePoolParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradePool, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // Load legacy pool data
+    msg!("Load legacy pool");
+    let pool_account = &ctx.accounts.pool;
+    if pool_account.owner != &crate::ID {
+        return Err(anchor_lang::error::ErrorCode::ConstraintOwner.into());
+    }
+    if !ctx.accounts.perpetuals.pools.contains(pool_account.key) {
+        return Err(ProgramError::InvalidSeeds.into());
+    }
+
+    let pool_data = {
+        let data = pool_account.try_borrow_data()?;
+        match PoolV0::load(&data) {
+            Some(pool) => Pool::from(pool),
+            None => return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into()),
+        }
+    };
+
+    // Resize pool account to the current length for its custodies
+    msg!("Resize pool account");
+    let new_len = Pool::LEN
+        + pool_data.custodies.len() * std::mem::size_of::<Pubkey>()
+        + pool_data.ratios.len() * std::mem::size_of::<TokenRatios>();
+    Perpetuals::realloc(
+        ctx.accounts.admin.to_account_info(),
+        pool_account.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        new_len,
+        true,
+    )?;
+
+    // Re-initialize the pool with new data
+    msg!("Re-initialize the pool");
+    let mut data = pool_account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = BpfWriter::new(dst);
+    pool_data.try_serialize(&mut writer)?;
+
+    Ok(0)
+}
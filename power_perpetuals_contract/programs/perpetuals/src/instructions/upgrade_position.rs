@@ -0,0 +1,91 @@
+//! UpgradePosition instruction handler
+//!
+//! This instruction upgrades a position stored in the original layout to the current
+//! layout. The conversion only fills the fields added since with their defaults, so it
+//! is permissionless: anyone (usually the owner or a keeper) can pay the rent of the
+//! resized account. Positions can't be traded until they are upgraded.
+
+use {
+    crate::{
+        instructions::upgrade_custody::BpfWriter,
+        state::{
+            perpetuals::Perpetuals,
+            position::{Position, PositionV0},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for upgrading a position
+#[derive(Accounts)]
+pub struct UpgradePosition<'info> {
+    /// Account paying the rent of the resized position (signer)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Legacy position account to upgrade (mutable, will be resized and reinitialized)
+    ///
+    /// CHECK: Legacy position account, validated in function
+    #[account(mut)]
+    pub position: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+/// Parameters for upgrading a position
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePositionParams {}
+
+/// Upgrade a position to the current layout
+///
+/// The process:
+/// 1. Validates the position account owner
+/// 2. Loads the legacy data, the original layout is selected by data length
+/// 3. Converts the legacy data to the current format
+/// 4. Resizes the account to the current length
+/// 5. Serializes the new data to account memory
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `_params` - Empty parameters struct
+///
+/// # Returns
+/// `Result<()>` - Success if the position was upgraded
+pub fn upgrade_position(
+    ctx: Context<UpgradePosition>,
+    _params: &UpgradePositionParams,
+) -> Result<()> {
+    // Load legacy position data
+    msg!("Load legacy position");
+    let position_account = &ctx.accounts.position;
+    if position_account.owner != &crate::ID {
+        return Err(anchor_lang::error::ErrorCode::ConstraintOwner.into());
+    }
+
+    let position_data = {
+        let data = position_account.try_borrow_data()?;
+        match PositionV0::load(&data) {
+            Some(position) => Position::from(position),
+            None => return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into()),
+        }
+    };
+
+    // Resize position account to the current length
+    msg!("Resize position account");
+    Perpetuals::realloc(
+        ctx.accounts.payer.to_account_info(),
+        position_account.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        Position::LEN,
+        true,
+    )?;
+
+    // Re-initialize the position with new data
+    msg!("Re-initialize the position");
+    let mut data = position_account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = BpfWriter::new(dst);
+    position_data.try_serialize(&mut writer)?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! ValidateCustodyConfig instruction handler
+//!
+//! This is a view/query instruction that dry-runs a SetCustodyConfig proposal. It applies
+//! the proposed params to copies of the custody and the pool and returns every failed
+//! config check, including extended sanity checks set_custody_config doesn't enforce, so
+//! admins can fix a proposal before collecting multisig signatures.
+
+use {
+    crate::{
+        instructions::SetCustodyConfigParams,
+        state::{
+            custody::{ConfigViolation, Custody},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Accounts required for validating a custody config proposal
+///
+/// This instruction is read-only and doesn't modify any state.
+#[derive(Accounts)]
+pub struct ValidateCustodyConfig<'info> {
+    /// Main perpetuals program account (read-only)
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool account the custody belongs to (read-only)
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Custody account the proposal applies to (read-only)
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+/// Validate a custody config proposal (view function)
+///
+/// The process:
+/// 1. Applies the proposed ratios to a copy of the pool and checks the pool config
+/// 2. Applies the proposed params to a copy of the custody and runs the custody checks
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts (read-only)
+/// * `params` - Proposed SetCustodyConfig params
+///
+/// # Returns
+/// `Vec<ConfigViolation>` - Failed checks, empty if set_custody_config would accept the
+/// params and all extended checks pass
+pub fn validate_custody_config(
+    ctx: Context<ValidateCustodyConfig>,
+    params: &SetCustodyConfigParams,
+) -> Result<Vec<ConfigViolation>> {
+    let mut violations = Vec::new();
+
+    // Pool checks
+    if params.ratios.len() != ctx.accounts.pool.ratios.len() {
+        violations.push(ConfigViolation::RatioCount);
+    } else {
+        let mut pool = ctx.accounts.pool.as_ref().clone();
+        pool.ratios = params.ratios.clone();
+        if !pool.validate() {
+            violations.push(ConfigViolation::PoolRatios);
+        }
+    }
+
+    // Custody checks
+    let mut custody = ctx.accounts.custody.as_ref().clone();
+    custody.is_stable = params.is_stable;
+    custody.is_virtual = params.is_virtual;
+    custody.oracle = params.oracle;
+    custody.pricing = params.pricing;
+    custody.permissions = params.permissions;
+    custody.allow_cpi = params.allow_cpi;
+    custody.fees = params.fees;
+    custody.entry_fee_tiers = params.entry_fee_tiers;
+    custody.borrow_rate = params.borrow_rate;
+    violations.extend(custody.get_config_violations());
+
+    for violation in &violations {
+        msg!("Config violation: {:?}", violation);
+    }
+
+    Ok(violations)
+}
@@ -0,0 +1,157 @@
+//! VetoListing instruction handler
+//!
+//! This instruction allows admins to reject a pending market listing before its
+//! timelock ends. The listing bond is slashed to the pool slash account.
+//! This requires multisig approval.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            listing::{ListingConfig, ListingProposal, ListingStatus},
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+/// Accounts required for vetoing a listing
+#[derive(Accounts)]
+pub struct VetoListing<'info> {
+    /// Admin account that must sign (must be part of multisig)
+    #[account()]
+    pub admin: Signer<'info>,
+
+    /// Multisig account for admin instruction approval
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// Transfer authority PDA for token accounts
+    ///
+    /// CHECK: Empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// Main perpetuals program account
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    /// Pool the market is listed in
+    #[account(
+        seeds = [b"pool",
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Listing config of the pool
+    #[account(
+        seeds = [b"listing_config",
+                 pool.key().as_ref()],
+        bump = listing_config.bump,
+        constraint = listing_config.version == ListingConfig::VERSION
+            @ PerpetualsError::InvalidListingConfig
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// Listing proposal being vetoed
+    #[account(
+        mut,
+        seeds = [b"listing_proposal",
+                 pool.key().as_ref(),
+                 listing_proposal.mint.as_ref()],
+        bump = listing_proposal.bump
+    )]
+    pub listing_proposal: Box<Account<'info, ListingProposal>>,
+
+    /// Token account escrowing the listing bond
+    #[account(
+        mut,
+        seeds = [b"listing_bond",
+                 listing_proposal.key().as_ref()],
+        bump = listing_proposal.bond_vault_bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Token account the slashed bond is paid to
+    #[account(
+        mut,
+        constraint = slash_account.key() == listing_config.slash_account
+    )]
+    pub slash_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+/// Parameters for vetoing a listing
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct VetoListingParams {}
+
+/// Veto a pending market listing and slash its bond
+///
+/// The process:
+/// 1. Validates multisig signatures (requires enough admin signatures)
+/// 2. Checks that the listing is still pending
+/// 3. Transfers the bond to the slash account
+/// 4. Marks the listing as vetoed
+///
+/// A listing can be vetoed after its timelock as long as it hasn't been activated.
+///
+/// # Arguments
+/// * `ctx` - Context containing all required accounts
+/// * `params` - Empty parameters struct
+///
+/// # Returns
+/// `Result<u8>` - Number of signatures still required (0 if complete), or error
+pub fn veto_listing<'info>(
+    ctx: Context<'_, '_, '_, 'info, VetoListing<'info>>,
+    params: &VetoListingParams,
+) -> Result<u8> {
+    // Validate multisig signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::VetoListing, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    require!(
+        ctx.accounts.listing_proposal.status == ListingStatus::Pending,
+        PerpetualsError::InvalidListingState
+    );
+
+    // Slash bond
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.bond_vault.to_account_info(),
+        ctx.accounts.slash_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.listing_proposal.bond_amount,
+    )?;
+
+    ctx.accounts.listing_proposal.status = ListingStatus::Vetoed;
+    msg!("Listing vetoed: {}", ctx.accounts.listing_proposal.mint);
+
+    Ok(0)
+}
@@ -0,0 +1,142 @@
+//! Accounting invariants
+//!
+//! Custody bookkeeping identities that must hold after every instruction. Instructions
+//! moving custody tokens check them on exit when the program is built with the
+//! strict-invariants feature, so development builds and the fuzz harness catch
+//! accounting drift at the instruction that caused it. Release builds skip the checks
+//! and pay no compute for them.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{custody::Custody, position::Position},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
+};
+
+/// Check that owned assets and collateral cover the locked funds
+pub fn check_custody_assets(custody: &Custody) -> Result<()> {
+    let available = math::checked_add(custody.assets.owned, custody.assets.collateral)?;
+    if available < custody.assets.locked {
+        msg!(
+            "Invariant violated: owned {} + collateral {} < locked {}",
+            custody.assets.owned,
+            custody.assets.collateral,
+            custody.assets.locked
+        );
+        return err!(PerpetualsError::InvariantViolation);
+    }
+    Ok(())
+}
+
+/// Check that protocol fees are backed by the custody token account balance
+///
+/// # Arguments
+/// * `custody` - Custody account
+/// * `token_balance` - Balance of the custody token account
+pub fn check_custody_balance(custody: &Custody, token_balance: u64) -> Result<()> {
+    if custody.assets.protocol_fees > token_balance {
+        msg!(
+            "Invariant violated: protocol fees {} > token balance {}",
+            custody.assets.protocol_fees,
+            token_balance
+        );
+        return err!(PerpetualsError::InvariantViolation);
+    }
+    Ok(())
+}
+
+/// Check that the custody locked funds are the sum of its positions locked amounts
+///
+/// Needs every position using the custody as collateral custody, so it is run over
+/// full account snapshots (tests, fuzz harness) rather than inside an instruction.
+///
+/// # Arguments
+/// * `custody_key` - Custody account address
+/// * `custody` - Custody account
+/// * `positions` - All open positions of the pool
+pub fn check_positions_locked<'a>(
+    custody_key: &Pubkey,
+    custody: &Custody,
+    positions: impl IntoIterator<Item = &'a Position>,
+) -> Result<()> {
+    let mut locked: u64 = 0;
+    for position in positions {
+        if position.collateral_custody == *custody_key {
+            locked = math::checked_add(locked, position.locked_amount)?;
+        }
+    }
+    if locked != custody.assets.locked {
+        msg!(
+            "Invariant violated: positions locked {} != custody locked {}",
+            locked,
+            custody.assets.locked
+        );
+        return err!(PerpetualsError::InvariantViolation);
+    }
+    Ok(())
+}
+
+/// Check the custody accounting identities at the end of an instruction
+///
+/// No-op unless built with the strict-invariants feature. The token account is
+/// reloaded since transfers made by the instruction aren't reflected in its data.
+///
+/// # Arguments
+/// * `custody` - Custody account as updated by the instruction
+/// * `custody_token_account` - Token account of the custody
+pub fn check_custody<'info>(
+    custody: &Custody,
+    custody_token_account: &mut Account<'info, TokenAccount>,
+) -> Result<()> {
+    if !cfg!(feature = "strict-invariants") {
+        return Ok(());
+    }
+    custody_token_account.reload()?;
+    check_custody_assets(custody)?;
+    check_custody_balance(custody, custody_token_account.amount)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invariants() {
+        let custody_key = Pubkey::new_unique();
+        let mut custody = Custody::default();
+        custody.assets.owned = 1_000;
+        custody.assets.collateral = 200;
+        custody.assets.protocol_fees = 50;
+        custody.assets.locked = 1_200;
+        assert!(check_custody_assets(&custody).is_ok());
+        assert!(check_custody_balance(&custody, 1_250).is_ok());
+
+        custody.assets.locked = 1_201;
+        assert!(check_custody_assets(&custody).is_err());
+        assert!(check_custody_balance(&custody, 49).is_err());
+
+        let positions = [
+            Position {
+                collateral_custody: custody_key,
+                locked_amount: 1_000,
+                ..Position::default()
+            },
+            Position {
+                collateral_custody: custody_key,
+                locked_amount: 201,
+                ..Position::default()
+            },
+            Position {
+                collateral_custody: Pubkey::new_unique(),
+                locked_amount: 500,
+                ..Position::default()
+            },
+        ];
+        assert!(check_positions_locked(&custody_key, &custody, &positions).is_ok());
+        custody.assets.locked = 1_200;
+        assert!(check_positions_locked(&custody_key, &custody, &positions).is_err());
+    }
+}
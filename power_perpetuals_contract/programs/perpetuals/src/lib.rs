@@ -2,16 +2,24 @@
 
 #![allow(clippy::result_large_err)]
 
+pub mod conversions;
 pub mod error;
+pub mod events;
 pub mod instructions;
+pub mod invariants;
 pub mod math;
+pub mod pda;
 pub mod state;
 
 use {
     anchor_lang::prelude::*,
     instructions::*,
-    state::perpetuals::{
-        AmountAndFee, NewPositionPricesAndFee, PriceAndFee, ProfitAndLoss, SwapAmountAndFees,
+    state::{
+        custody::ConfigViolation,
+        perpetuals::{
+            AmountAndFee, ClosePositionQuote, CrossPoolSwapAmountAndFees, CustodyStats, LiquidationPreview, LockedBreakdown, LpSupplyCapacity, MaxPayoff, NewPositionPricesAndFee, OracleHealth, PoolApr, PoolCustodies, PositionInterest, PositionRisk, PriceAndFee,
+            ProfitAndLoss, ProtocolConfig, RequiredCollateral, SwapAmountAndFees, TokenRatioImpact,
+        },
     },
 };
 
@@ -105,6 +113,20 @@ pub mod perpetuals {
         instructions::upgrade_custody(ctx, &params)
     }
 
+    pub fn upgrade_perpetuals<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradePerpetuals<'info>>,
+        params: UpgradePerpetualsParams,
+    ) -> Result<u8> {
+        instructions::upgrade_perpetuals(ctx, &params)
+    }
+
+    pub fn upgrade_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+        params: UpgradePoolParams,
+    ) -> Result<u8> {
+        instructions::upgrade_pool(ctx, &params)
+    }
+
     pub fn set_custom_oracle_price<'info>(
         ctx: Context<'_, '_, '_, 'info, SetCustomOraclePrice<'info>>,
         params: SetCustomOraclePriceParams,
@@ -112,6 +134,269 @@ pub mod perpetuals {
         instructions::set_custom_oracle_price(ctx, &params)
     }
 
+    pub fn set_custody_expiry<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCustodyExpiry<'info>>,
+        params: SetCustodyExpiryParams,
+    ) -> Result<u8> {
+        instructions::set_custody_expiry(ctx, &params)
+    }
+
+    pub fn set_market_maker<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetMarketMaker<'info>>,
+        params: SetMarketMakerParams,
+    ) -> Result<u8> {
+        instructions::set_market_maker(ctx, &params)
+    }
+
+    pub fn remove_market_maker<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveMarketMaker<'info>>,
+        params: RemoveMarketMakerParams,
+    ) -> Result<u8> {
+        instructions::remove_market_maker(ctx, &params)
+    }
+
+    pub fn schedule_custody_migration<'info>(
+        ctx: Context<'_, '_, '_, 'info, ScheduleCustodyMigration<'info>>,
+        params: ScheduleCustodyMigrationParams,
+    ) -> Result<u8> {
+        instructions::schedule_custody_migration(ctx, &params)
+    }
+
+    pub fn migrate_custody_mint<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateCustodyMint<'info>>,
+        params: MigrateCustodyMintParams,
+    ) -> Result<u8> {
+        instructions::migrate_custody_mint(ctx, &params)
+    }
+
+    pub fn set_lp_allowlist<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetLpAllowlist<'info>>,
+        params: SetLpAllowlistParams,
+    ) -> Result<u8> {
+        instructions::set_lp_allowlist(ctx, &params)
+    }
+
+    pub fn set_crank_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCrankConfig<'info>>,
+        params: SetCrankConfigParams,
+    ) -> Result<u8> {
+        instructions::set_crank_config(ctx, &params)
+    }
+
+    pub fn reconcile_custody<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReconcileCustody<'info>>,
+        params: ReconcileCustodyParams,
+    ) -> Result<u8> {
+        instructions::reconcile_custody(ctx, &params)
+    }
+
+    pub fn sweep_sol<'info>(
+        ctx: Context<'_, '_, '_, 'info, SweepSol<'info>>,
+        params: SweepSolParams,
+    ) -> Result<u8> {
+        instructions::sweep_sol(ctx, &params)
+    }
+
+    pub fn set_custody_exchange_rate<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCustodyExchangeRate<'info>>,
+        params: SetCustodyExchangeRateParams,
+    ) -> Result<u8> {
+        instructions::set_custody_exchange_rate(ctx, &params)
+    }
+
+    pub fn set_custody_volatility<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCustodyVolatility<'info>>,
+        params: SetCustodyVolatilityParams,
+    ) -> Result<u8> {
+        instructions::set_custody_volatility(ctx, &params)
+    }
+
+    pub fn set_position_limit<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPositionLimit<'info>>,
+        params: SetPositionLimitParams,
+    ) -> Result<u8> {
+        instructions::set_position_limit(ctx, &params)
+    }
+
+    pub fn set_fee_custody<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetFeeCustody<'info>>,
+        params: SetFeeCustodyParams,
+    ) -> Result<u8> {
+        instructions::set_fee_custody(ctx, &params)
+    }
+
+    pub fn export_pool_state<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExportPoolState<'info>>,
+        params: ExportPoolStateParams,
+    ) -> Result<u8> {
+        instructions::export_pool_state(ctx, &params)
+    }
+
+    pub fn import_pool_state<'info>(
+        ctx: Context<'_, '_, '_, 'info, ImportPoolState<'info>>,
+        params: ImportPoolStateParams,
+    ) -> Result<u8> {
+        instructions::import_pool_state(ctx, &params)
+    }
+
+    pub fn import_pool_positions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ImportPoolPositions<'info>>,
+        params: ImportPoolPositionsParams,
+    ) -> Result<u8> {
+        instructions::import_pool_positions(ctx, &params)
+    }
+
+    pub fn finalize_pool_migration<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizePoolMigration<'info>>,
+        params: FinalizePoolMigrationParams,
+    ) -> Result<u8> {
+        instructions::finalize_pool_migration(ctx, &params)
+    }
+
+    pub fn start_stats_epoch<'info>(
+        ctx: Context<'_, '_, '_, 'info, StartStatsEpoch<'info>>,
+        params: StartStatsEpochParams,
+    ) -> Result<u8> {
+        instructions::start_stats_epoch(ctx, &params)
+    }
+
+    pub fn schedule_force_settlement<'info>(
+        ctx: Context<'_, '_, '_, 'info, ScheduleForceSettlement<'info>>,
+        params: ScheduleForceSettlementParams,
+    ) -> Result<u8> {
+        instructions::schedule_force_settlement(ctx, &params)
+    }
+
+    pub fn force_settle_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForceSettlePosition<'info>>,
+        params: ForceSettlePositionParams,
+    ) -> Result<u8> {
+        instructions::force_settle_position(ctx, &params)
+    }
+
+    pub fn set_performance_fee<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPerformanceFee<'info>>,
+        params: SetPerformanceFeeParams,
+    ) -> Result<u8> {
+        instructions::set_performance_fee(ctx, &params)
+    }
+
+    pub fn set_liquidation_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetLiquidationAuction<'info>>,
+        params: SetLiquidationAuctionParams,
+    ) -> Result<u8> {
+        instructions::set_liquidation_auction(ctx, &params)
+    }
+
+    pub fn set_custody_lifecycle<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCustodyLifecycle<'info>>,
+        params: SetCustodyLifecycleParams,
+    ) -> Result<u8> {
+        instructions::set_custody_lifecycle(ctx, &params)
+    }
+
+    pub fn set_trade_rate_limit<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetTradeRateLimit<'info>>,
+        params: SetTradeRateLimitParams,
+    ) -> Result<u8> {
+        instructions::set_trade_rate_limit(ctx, &params)
+    }
+
+    pub fn set_pool_cpi<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPoolCpi<'info>>,
+        params: SetPoolCpiParams,
+    ) -> Result<u8> {
+        instructions::set_pool_cpi(ctx, &params)
+    }
+
+    pub fn add_lp_index<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLpIndex<'info>>,
+        params: AddLpIndexParams,
+    ) -> Result<u8> {
+        instructions::add_lp_index(ctx, &params)
+    }
+
+    pub fn set_lp_index_component<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetLpIndexComponent<'info>>,
+        params: SetLpIndexComponentParams,
+    ) -> Result<u8> {
+        instructions::set_lp_index_component(ctx, &params)
+    }
+
+    pub fn set_position_hook<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPositionHook<'info>>,
+        params: SetPositionHookParams,
+    ) -> Result<u8> {
+        instructions::set_position_hook(ctx, &params)
+    }
+
+    pub fn set_risk_oracle<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetRiskOracle<'info>>,
+        params: SetRiskOracleParams,
+    ) -> Result<u8> {
+        instructions::set_risk_oracle(ctx, &params)
+    }
+
+    pub fn set_imbalance_fee<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetImbalanceFee<'info>>,
+        params: SetImbalanceFeeParams,
+    ) -> Result<u8> {
+        instructions::set_imbalance_fee(ctx, &params)
+    }
+
+    pub fn set_backstop_tranche<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetBackstopTranche<'info>>,
+        params: SetBackstopTrancheParams,
+    ) -> Result<u8> {
+        instructions::set_backstop_tranche(ctx, &params)
+    }
+
+    pub fn set_listing_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetListingConfig<'info>>,
+        params: SetListingConfigParams,
+    ) -> Result<u8> {
+        instructions::set_listing_config(ctx, &params)
+    }
+
+    pub fn veto_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, VetoListing<'info>>,
+        params: VetoListingParams,
+    ) -> Result<u8> {
+        instructions::veto_listing(ctx, &params)
+    }
+
+    pub fn stage_params(ctx: Context<StageParams>, params: StageParamsParams) -> Result<()> {
+        instructions::stage_params(ctx, &params)
+    }
+
+    pub fn add_custody_from_staged<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddCustodyFromStaged<'info>>,
+        params: AddCustodyFromStagedParams,
+    ) -> Result<u8> {
+        instructions::add_custody_from_staged(ctx, &params)
+    }
+
+    pub fn set_compliance_freeze<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetComplianceFreeze<'info>>,
+        params: SetComplianceFreezeParams,
+    ) -> Result<u8> {
+        instructions::set_compliance_freeze(ctx, &params)
+    }
+
+    pub fn set_lp_supply_cap<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetLpSupplyCap<'info>>,
+        params: SetLpSupplyCapParams,
+    ) -> Result<u8> {
+        instructions::set_lp_supply_cap(ctx, &params)
+    }
+
+    pub fn set_collateral_withdrawal_limit<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCollateralWithdrawalLimit<'info>>,
+        params: SetCollateralWithdrawalLimitParams,
+    ) -> Result<u8> {
+        instructions::set_collateral_withdrawal_limit(ctx, &params)
+    }
+
     // test instructions
 
     pub fn set_test_time<'info>(
@@ -127,6 +412,13 @@ pub mod perpetuals {
         instructions::swap(ctx, &params)
     }
 
+    pub fn cross_pool_swap(
+        ctx: Context<CrossPoolSwap>,
+        params: CrossPoolSwapParams,
+    ) -> Result<CrossPoolSwapAmountAndFees> {
+        instructions::cross_pool_swap(ctx, &params)
+    }
+
     pub fn add_liquidity<'info>(ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'info>>, params: AddLiquidityParams) -> Result<()> {
         instructions::add_liquidity(ctx, &params)
     }
@@ -138,10 +430,35 @@ pub mod perpetuals {
         instructions::remove_liquidity(ctx, &params)
     }
 
-    pub fn open_position(ctx: Context<OpenPosition>, params: OpenPositionParams) -> Result<()> {
+    pub fn remove_liquidity_and_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveLiquidityAndSwap<'info>>,
+        params: RemoveLiquidityAndSwapParams,
+    ) -> Result<()> {
+        instructions::remove_liquidity_and_swap(ctx, &params)
+    }
+
+    pub fn open_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenPosition<'info>>,
+        params: OpenPositionParams,
+    ) -> Result<()> {
         instructions::open_position(ctx, &params)
     }
 
+    pub fn commit_open(ctx: Context<CommitOpen>, params: CommitOpenParams) -> Result<()> {
+        instructions::commit_open(ctx, &params)
+    }
+
+    pub fn reveal_open<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevealOpen<'info>>,
+        params: RevealOpenParams,
+    ) -> Result<()> {
+        instructions::reveal_open(ctx, &params)
+    }
+
+    pub fn cancel_commit_open(ctx: Context<CancelCommitOpen>) -> Result<()> {
+        instructions::cancel_commit_open(ctx)
+    }
+
     pub fn add_collateral(ctx: Context<AddCollateral>, params: AddCollateralParams) -> Result<()> {
         instructions::add_collateral(ctx, &params)
     }
@@ -153,18 +470,146 @@ pub mod perpetuals {
         instructions::remove_collateral(ctx, &params)
     }
 
-    pub fn close_position(ctx: Context<ClosePosition>, params: ClosePositionParams) -> Result<()> {
+    pub fn change_power(ctx: Context<ChangePower>, params: ChangePowerParams) -> Result<()> {
+        instructions::change_power(ctx, &params)
+    }
+
+    pub fn roll_position(ctx: Context<RollPosition>, params: RollPositionParams) -> Result<()> {
+        instructions::roll_position(ctx, &params)
+    }
+
+    pub fn close_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClosePosition<'info>>,
+        params: ClosePositionParams,
+    ) -> Result<()> {
         instructions::close_position(ctx, &params)
     }
 
+    pub fn donate(ctx: Context<Donate>, params: DonateParams) -> Result<()> {
+        instructions::donate(ctx, &params)
+    }
+
+    pub fn start_liquidation_auction(
+        ctx: Context<StartLiquidationAuction>,
+        params: StartLiquidationAuctionParams,
+    ) -> Result<()> {
+        instructions::start_liquidation_auction(ctx, &params)
+    }
+
     pub fn liquidate(ctx: Context<Liquidate>, params: LiquidateParams) -> Result<()> {
         instructions::liquidate(ctx, &params)
     }
 
+    pub fn settle_expired_position(
+        ctx: Context<SettleExpiredPosition>,
+        params: SettleExpiredPositionParams,
+    ) -> Result<()> {
+        instructions::settle_expired_position(ctx, &params)
+    }
+
+    pub fn update_lp_allowlist(
+        ctx: Context<UpdateLpAllowlist>,
+        params: UpdateLpAllowlistParams,
+    ) -> Result<()> {
+        instructions::update_lp_allowlist(ctx, &params)
+    }
+
+    pub fn update_compliance_freeze(
+        ctx: Context<UpdateComplianceFreeze>,
+        params: UpdateComplianceFreezeParams,
+    ) -> Result<()> {
+        instructions::update_compliance_freeze(ctx, &params)
+    }
+
     pub fn update_pool_aum(ctx: Context<UpdatePoolAum>) -> Result<u128> {
         instructions::update_pool_aum(ctx)
     }
 
+    pub fn update_exchange_rate(ctx: Context<UpdateExchangeRate>) -> Result<u64> {
+        instructions::update_exchange_rate(ctx)
+    }
+
+    pub fn update_volatility(ctx: Context<UpdateVolatility>) -> Result<u64> {
+        instructions::update_volatility(ctx)
+    }
+
+    pub fn execute_pending_claim(ctx: Context<ExecutePendingClaim>) -> Result<()> {
+        instructions::execute_pending_claim(ctx)
+    }
+
+    pub fn update_oracle_safe_mode(ctx: Context<UpdateOracleSafeMode>) -> Result<bool> {
+        instructions::update_oracle_safe_mode(ctx)
+    }
+
+    pub fn migrate_lp_tokens(
+        ctx: Context<MigrateLpTokens>,
+        params: MigrateLpTokensParams,
+    ) -> Result<()> {
+        instructions::migrate_lp_tokens(ctx, &params)
+    }
+
+    pub fn run_crank<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RunCrank<'info>>,
+        params: RunCrankParams,
+    ) -> Result<u64> {
+        instructions::run_crank(ctx, &params)
+    }
+
+    pub fn roll_performance_epoch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RollPerformanceEpoch<'info>>,
+    ) -> Result<u64> {
+        instructions::roll_performance_epoch(ctx)
+    }
+
+    pub fn assert_oracles_fresh<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssertOraclesFresh<'info>>,
+        params: AssertOraclesFreshParams,
+    ) -> Result<()> {
+        instructions::assert_oracles_fresh(ctx, &params)
+    }
+
+    pub fn mint_lp_index<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintLpIndex<'info>>,
+        params: MintLpIndexParams,
+    ) -> Result<()> {
+        instructions::mint_lp_index(ctx, &params)
+    }
+
+    pub fn redeem_lp_index<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RedeemLpIndex<'info>>,
+        params: RedeemLpIndexParams,
+    ) -> Result<()> {
+        instructions::redeem_lp_index(ctx, &params)
+    }
+
+    pub fn add_backstop_liquidity(
+        ctx: Context<AddBackstopLiquidity>,
+        params: AddBackstopLiquidityParams,
+    ) -> Result<()> {
+        instructions::add_backstop_liquidity(ctx, &params)
+    }
+
+    pub fn remove_backstop_liquidity(
+        ctx: Context<RemoveBackstopLiquidity>,
+        params: RemoveBackstopLiquidityParams,
+    ) -> Result<()> {
+        instructions::remove_backstop_liquidity(ctx, &params)
+    }
+
+    pub fn propose_listing(
+        ctx: Context<ProposeListing>,
+        params: ProposeListingParams,
+    ) -> Result<()> {
+        instructions::propose_listing(ctx, &params)
+    }
+
+    pub fn activate_listing(
+        ctx: Context<ActivateListing>,
+        params: ActivateListingParams,
+    ) -> Result<()> {
+        instructions::activate_listing(ctx, &params)
+    }
+
     pub fn get_add_liquidity_amount_and_fee<'info>(
         ctx: Context<'_, '_, 'info, 'info, GetAddLiquidityAmountAndFee<'info>>,
         params: GetAddLiquidityAmountAndFeeParams,
@@ -186,6 +631,13 @@ pub mod perpetuals {
         instructions::get_entry_price_and_fee(ctx, &params)
     }
 
+    pub fn get_required_collateral(
+        ctx: Context<GetRequiredCollateral>,
+        params: GetRequiredCollateralParams,
+    ) -> Result<RequiredCollateral> {
+        instructions::get_required_collateral(ctx, &params)
+    }
+
     pub fn get_exit_price_and_fee(
         ctx: Context<GetExitPriceAndFee>,
         params: GetExitPriceAndFeeParams,
@@ -193,10 +645,21 @@ pub mod perpetuals {
         instructions::get_exit_price_and_fee(ctx, &params)
     }
 
+    pub fn get_close_position_quote<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetClosePositionQuote<'info>>,
+        params: GetClosePositionQuoteParams,
+    ) -> Result<Vec<ClosePositionQuote>> {
+        instructions::get_close_position_quote(ctx, &params)
+    }
+
     pub fn get_pnl(ctx: Context<GetPnl>, params: GetPnlParams) -> Result<ProfitAndLoss> {
         instructions::get_pnl(ctx, &params)
     }
 
+    pub fn get_max_payoff(ctx: Context<GetMaxPayoff>, params: GetMaxPayoffParams) -> Result<MaxPayoff> {
+        instructions::get_max_payoff(ctx, &params)
+    }
+
     pub fn get_liquidation_price(
         ctx: Context<GetLiquidationPrice>,
         params: GetLiquidationPriceParams,
@@ -211,6 +674,62 @@ pub mod perpetuals {
         instructions::get_liquidation_state(ctx, &params)
     }
 
+    pub fn get_liquidation_preview(
+        ctx: Context<GetLiquidationPreview>,
+        params: GetLiquidationPreviewParams,
+    ) -> Result<LiquidationPreview> {
+        instructions::get_liquidation_preview(ctx, &params)
+    }
+
+    pub fn get_position_interest(
+        ctx: Context<GetPositionInterest>,
+        params: GetPositionInterestParams,
+    ) -> Result<PositionInterest> {
+        instructions::get_position_interest(ctx, &params)
+    }
+
+    pub fn get_position_risk(
+        ctx: Context<GetPositionRisk>,
+        params: GetPositionRiskParams,
+    ) -> Result<PositionRisk> {
+        instructions::get_position_risk(ctx, &params)
+    }
+
+    pub fn get_oracle_health(
+        ctx: Context<GetOracleHealth>,
+        params: GetOracleHealthParams,
+    ) -> Result<OracleHealth> {
+        instructions::get_oracle_health(ctx, &params)
+    }
+
+    pub fn get_custody_stats(
+        ctx: Context<GetCustodyStats>,
+        params: GetCustodyStatsParams,
+    ) -> Result<CustodyStats> {
+        instructions::get_custody_stats(ctx, &params)
+    }
+
+    pub fn get_locked_breakdown(
+        ctx: Context<GetLockedBreakdown>,
+        params: GetLockedBreakdownParams,
+    ) -> Result<LockedBreakdown> {
+        instructions::get_locked_breakdown(ctx, &params)
+    }
+
+    pub fn get_pool_custodies<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetPoolCustodies<'info>>,
+        params: GetPoolCustodiesParams,
+    ) -> Result<PoolCustodies> {
+        instructions::get_pool_custodies(ctx, &params)
+    }
+
+    pub fn get_protocol_config<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetProtocolConfig<'info>>,
+        params: GetProtocolConfigParams,
+    ) -> Result<ProtocolConfig> {
+        instructions::get_protocol_config(ctx, &params)
+    }
+
     pub fn get_oracle_price(
         ctx: Context<GetOraclePrice>,
         params: GetOraclePriceParams,
@@ -225,6 +744,13 @@ pub mod perpetuals {
         instructions::get_swap_amount_and_fees(ctx, &params)
     }
 
+    pub fn get_token_ratio_impact<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetTokenRatioImpact<'info>>,
+        params: GetTokenRatioImpactParams,
+    ) -> Result<TokenRatioImpact> {
+        instructions::get_token_ratio_impact(ctx, &params)
+    }
+
     pub fn get_assets_under_management<'info>(
         ctx: Context<'_, '_, 'info, 'info, GetAssetsUnderManagement<'info>>,
         params: GetAssetsUnderManagementParams,
@@ -232,6 +758,13 @@ pub mod perpetuals {
         instructions::get_assets_under_management(ctx, &params)
     }
 
+    pub fn get_lp_supply_capacity(
+        ctx: Context<GetLpSupplyCapacity>,
+        params: GetLpSupplyCapacityParams,
+    ) -> Result<LpSupplyCapacity> {
+        instructions::get_lp_supply_capacity(ctx, &params)
+    }
+
     pub fn get_lp_token_price<'info>(
         ctx: Context<'_, '_, 'info, 'info, GetLpTokenPrice<'info>>,
         params: GetLpTokenPriceParams,
@@ -239,6 +772,20 @@ pub mod perpetuals {
         instructions::get_lp_token_price(ctx, &params)
     }
 
+    pub fn get_pool_apr<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetPoolApr<'info>>,
+        params: GetPoolAprParams,
+    ) -> Result<PoolApr> {
+        instructions::get_pool_apr(ctx, &params)
+    }
+
+    pub fn validate_custody_config(
+        ctx: Context<ValidateCustodyConfig>,
+        params: SetCustodyConfigParams,
+    ) -> Result<Vec<ConfigViolation>> {
+        instructions::validate_custody_config(ctx, &params)
+    }
+
     // This instruction must be part of a larger transaction where the **first** instruction
     // is an ed25519 verification of the serialized oracle price update params.
     pub fn set_custom_oracle_price_permissionless(
@@ -247,4 +794,34 @@ pub mod perpetuals {
     ) -> Result<()> {
         instructions::set_custom_oracle_price_permissionless(ctx, &params)
     }
+
+    // Same as set_custom_oracle_price_permissionless, the **first** instruction must be an
+    // ed25519 verification of the serialized batch params.
+    pub fn set_custom_oracle_prices_permissionless_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetCustomOraclePricesPermissionlessBatch<'info>>,
+        params: SetCustomOraclePricesPermissionlessBatchParams,
+    ) -> Result<()> {
+        instructions::set_custom_oracle_prices_permissionless_batch(ctx, &params)
+    }
+
+    pub fn set_payout_account(
+        ctx: Context<SetPayoutAccount>,
+        params: SetPayoutAccountParams,
+    ) -> Result<()> {
+        instructions::set_payout_account(ctx, &params)
+    }
+
+    pub fn upgrade_position(
+        ctx: Context<UpgradePosition>,
+        params: UpgradePositionParams,
+    ) -> Result<()> {
+        instructions::upgrade_position(ctx, &params)
+    }
+
+    pub fn set_settlement_price(
+        ctx: Context<SetSettlementPrice>,
+        params: SetSettlementPriceParams,
+    ) -> Result<u64> {
+        instructions::set_settlement_price(ctx, &params)
+    }
 }
\ No newline at end of file
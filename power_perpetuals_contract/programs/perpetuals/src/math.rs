@@ -4,6 +4,16 @@
 
 use {crate::error::PerpetualsError, anchor_lang::prelude::*, std::fmt::Display};
 
+/// Rounding direction of a conversion
+///
+/// Call sites pick the direction that favors the pool: amounts paid out or credited to
+/// users round down, amounts charged or debited from users round up.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RoundingDirection {
+    Down,
+    Up,
+}
+
 pub fn checked_add<T>(arg1: T, arg2: T) -> Result<T>
 where
     T: num_traits::PrimInt + Display,
@@ -327,6 +337,32 @@ pub fn checked_powi(arg: f64, exp: i32) -> Result<f64> {
     }
 }
 
+pub fn checked_as_u8<T>(arg: T) -> Result<u8>
+where
+    T: Display + num_traits::ToPrimitive + Clone,
+{
+    let option: Option<u8> = num_traits::NumCast::from(arg.clone());
+    if let Some(res) = option {
+        Ok(res)
+    } else {
+        msg!("Error: Overflow in {} as u8", arg);
+        err!(PerpetualsError::MathOverflow)
+    }
+}
+
+pub fn checked_as_u16<T>(arg: T) -> Result<u16>
+where
+    T: Display + num_traits::ToPrimitive + Clone,
+{
+    let option: Option<u16> = num_traits::NumCast::from(arg.clone());
+    if let Some(res) = option {
+        Ok(res)
+    } else {
+        msg!("Error: Overflow in {} as u16", arg);
+        err!(PerpetualsError::MathOverflow)
+    }
+}
+
 pub fn checked_as_u64<T>(arg: T) -> Result<u64>
 where
     T: Display + num_traits::ToPrimitive + Clone,
@@ -413,7 +449,7 @@ pub fn to_token_amount(ui_amount: f64, decimals: u8) -> Result<u64> {
 /// - power=1: linear perps, return = (S_exit/S_entry - 1)
 /// - power=2: squared perps, return = (S_exit/S_entry)^2 - 1
 /// - power=3: cubed perps, return = (S_exit/S_entry)^3 - 1
-/// etc.
+/// - etc.
 pub fn calc_power_perps_pnl(
     exit_price: u64,
     entry_price: u64,
@@ -468,4 +504,40 @@ pub fn calc_power_perps_pnl(
         )?)?;
         Ok((0, loss_usd))
     }
+}
+
+/// Raise a fixed-point value to an integer power
+///
+/// The result is truncated after every multiplication.
+///
+/// # Returns
+/// value^power scaled by `scale`, or None on overflow
+pub fn fixed_pow(value: u128, power: u8, scale: u128) -> Option<u128> {
+    let mut result = scale;
+    for _ in 0..power {
+        result = result.checked_mul(value)?.checked_div(scale)?;
+    }
+    Some(result)
+}
+
+/// Integer root of a fixed-point value
+///
+/// # Returns
+/// Largest r with fixed_pow(r, power, scale) <= value, scaled by `scale`
+pub fn checked_fixed_root(value: u128, power: u8, scale: u128) -> Result<u128> {
+    if power == 0 || scale == 0 {
+        msg!("Error: Invalid root {} of {}", power, value);
+        return err!(PerpetualsError::MathOverflow);
+    }
+    // the root is below the value if it is above one, below one otherwise
+    let mut low = 0u128;
+    let mut high = std::cmp::max(value, scale);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        match fixed_pow(mid, power, scale) {
+            Some(powered) if powered <= value => low = mid,
+            _ => high = mid - 1,
+        }
+    }
+    Ok(low)
 }
\ No newline at end of file
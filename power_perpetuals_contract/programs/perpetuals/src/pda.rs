@@ -0,0 +1,183 @@
+//! Program derived addresses.
+//!
+//! Mirrors the seeds used by the instruction account constraints, so off-chain
+//! services (keepers, quoting services, SDKs) can derive the same addresses when
+//! depending on the crate with the `client` feature.
+
+use {crate::state::position::Side, anchor_lang::prelude::*};
+
+pub fn find_perpetuals_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"perpetuals"], &crate::ID)
+}
+
+pub fn find_multisig_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"multisig"], &crate::ID)
+}
+
+pub fn find_transfer_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"transfer_authority"], &crate::ID)
+}
+
+pub fn find_sol_fee_vault_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sol_fee_vault"], &crate::ID)
+}
+
+pub fn find_pool_address(pool_name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool", pool_name.as_bytes()], &crate::ID)
+}
+
+pub fn find_lp_token_mint_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp_token_mint", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_backstop_token_mint_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"backstop_token_mint", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_custody_address(pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"custody", pool.as_ref(), mint.as_ref()], &crate::ID)
+}
+
+pub fn find_custody_token_account_address(pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"custody_token_account", pool.as_ref(), mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_oracle_account_address(pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"oracle_account", pool.as_ref(), mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_position_address(
+    owner: &Pubkey,
+    pool: &Pubkey,
+    custody: &Pubkey,
+    side: Side,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"position",
+            owner.as_ref(),
+            pool.as_ref(),
+            custody.as_ref(),
+            &[side as u8],
+        ],
+        &crate::ID,
+    )
+}
+
+pub fn find_owner_positions_address(pool: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"owner_positions", pool.as_ref(), owner.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_lp_ledger_address(pool: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp_ledger", pool.as_ref(), owner.as_ref()], &crate::ID)
+}
+
+pub fn find_market_maker_address(pool: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"market_maker", pool.as_ref(), owner.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_lp_allowlist_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp_allowlist", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_compliance_freeze_address(custody: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"compliance_freeze", custody.as_ref()], &crate::ID)
+}
+
+pub fn find_crank_state_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"crank_state", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_custody_migration_address(custody: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"custody_migration", custody.as_ref()], &crate::ID)
+}
+
+pub fn find_position_summary_address(position: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"position_summary", position.as_ref()], &crate::ID)
+}
+
+pub fn find_force_settlement_address(position: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"force_settlement", position.as_ref()], &crate::ID)
+}
+
+pub fn find_pool_migration_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool_migration", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_pool_migration_lp_vault_address(pool_migration: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"pool_migration_lp", pool_migration.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_order_commitment_address(owner: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"order_commitment", owner.as_ref(), pool.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_pending_claim_address(custody: &Pubkey, claim_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"pending_claim", custody.as_ref(), &claim_id.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+pub fn find_hook_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"hook_authority"], &crate::ID)
+}
+
+pub fn find_lp_index_address(name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp_index", name.as_bytes()], &crate::ID)
+}
+
+pub fn find_lp_index_token_mint_address(lp_index: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp_index_token_mint", lp_index.as_ref()], &crate::ID)
+}
+
+pub fn find_lp_index_vault_address(lp_index: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"lp_index_vault", lp_index.as_ref(), pool.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_listing_config_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing_config", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_listing_proposal_address(pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"listing_proposal", pool.as_ref(), mint.as_ref()],
+        &crate::ID,
+    )
+}
+
+pub fn find_listing_bond_address(listing_proposal: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing_bond", listing_proposal.as_ref()], &crate::ID)
+}
+
+pub fn find_staged_params_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"staged_params", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_trader_activity_address(pool: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"trader_activity", pool.as_ref(), owner.as_ref()],
+        &crate::ID,
+    )
+}
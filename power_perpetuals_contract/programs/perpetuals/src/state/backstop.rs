@@ -0,0 +1,175 @@
+//! Backstop tranche state
+//!
+//! A pool can split its liquidity into a senior tranche (the pool LP token) and a junior
+//! backstop tranche with its own token. Realized trader profits are paid out of the
+//! backstop value first, and in exchange the backstop earns a boosted share of realized
+//! fees and trader losses. The pool LP token is priced from the AUM left after the
+//! backstop value, so senior LPs only take trader losses once the backstop is wiped out.
+//! The backstop value is tracked in USD, price moves of pool assets only change the
+//! senior value.
+
+use {
+    crate::{error::PerpetualsError, math, state::perpetuals::Perpetuals},
+    anchor_lang::prelude::*,
+};
+
+/// Junior liquidity tranche of a pool
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct BackstopTranche {
+    /// Value of the tranche in USD (scaled to USD_DECIMALS)
+    pub value_usd: u64,
+    /// Share of pool gains paid to the tranche on top of its pro-rata share (BPS)
+    pub fee_boost_bps: u64,
+    /// Lifetime trader profits absorbed by the tranche in USD
+    pub absorbed_loss_usd: u64,
+    /// Bump seed for the backstop token mint PDA
+    pub token_bump: u8,
+}
+
+impl BackstopTranche {
+    /// Maximum fee boost (BPS), the tranche earns at most 3x its pro-rata share
+    pub const MAX_FEE_BOOST_BPS: u64 = 20_000;
+
+    pub fn validate(&self) -> bool {
+        self.fee_boost_bps <= Self::MAX_FEE_BOOST_BPS
+    }
+
+    /// Returns the pool AUM attributable to the pool LP token
+    ///
+    /// # Arguments
+    /// * `aum_usd` - Pool assets under management in USD
+    pub fn get_senior_aum_usd(&self, aum_usd: u128) -> u128 {
+        aum_usd.saturating_sub(self.value_usd as u128)
+    }
+
+    /// Returns the share of pool gains credited to the tranche in BPS
+    ///
+    /// # Arguments
+    /// * `aum_usd` - Pool assets under management in USD
+    pub fn get_gain_share(&self, aum_usd: u128) -> Result<u128> {
+        if self.value_usd == 0 {
+            return Ok(0);
+        }
+        if aum_usd == 0 {
+            return Ok(Perpetuals::BPS_POWER);
+        }
+        let boosted_value = math::checked_div(
+            math::checked_mul(
+                self.value_usd as u128,
+                math::checked_add(Perpetuals::BPS_POWER, self.fee_boost_bps as u128)?,
+            )?,
+            Perpetuals::BPS_POWER,
+        )?;
+        Ok(std::cmp::min(
+            math::checked_div(
+                math::checked_mul(boosted_value, Perpetuals::BPS_POWER)?,
+                aum_usd,
+            )?,
+            Perpetuals::BPS_POWER,
+        ))
+    }
+
+    /// Runs realized pool gains and losses through the waterfall
+    ///
+    /// Losses are absorbed by the tranche first, gains are credited by the boosted
+    /// share the tranche had before the trade.
+    ///
+    /// # Arguments
+    /// * `gain_usd` - Pool share of realized fees and trader losses in USD
+    /// * `loss_usd` - Realized trader profits paid by the pool in USD
+    /// * `aum_usd` - Pool assets under management in USD
+    pub fn record_pnl(&mut self, gain_usd: u64, loss_usd: u64, aum_usd: u128) -> Result<()> {
+        let gain_share = self.get_gain_share(aum_usd)?;
+
+        let absorbed_usd = std::cmp::min(self.value_usd, loss_usd);
+        self.value_usd = math::checked_sub(self.value_usd, absorbed_usd)?;
+        self.absorbed_loss_usd = self.absorbed_loss_usd.wrapping_add(absorbed_usd);
+
+        let credited_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(gain_usd as u128, gain_share)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        self.value_usd = math::checked_add(self.value_usd, credited_usd)?;
+        Ok(())
+    }
+
+    /// Returns the amount of backstop tokens minted for a deposit, rounded down
+    ///
+    /// The first deposit mints one backstop token per USD.
+    ///
+    /// # Arguments
+    /// * `deposit_usd` - Deposit value in USD
+    /// * `supply` - Backstop token supply before the deposit
+    pub fn get_mint_amount(&self, deposit_usd: u64, supply: u64) -> Result<u64> {
+        if supply == 0 {
+            return Ok(deposit_usd);
+        }
+        require!(self.value_usd > 0, PerpetualsError::InvalidBackstopState);
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(deposit_usd as u128, supply as u128)?,
+            self.value_usd as u128,
+        )?)
+    }
+
+    /// Returns the USD value paid out for redeemed backstop tokens, rounded down
+    ///
+    /// # Arguments
+    /// * `amount` - Backstop tokens being redeemed
+    /// * `supply` - Backstop token supply before the redemption
+    pub fn get_redeem_amount_usd(&self, amount: u64, supply: u64) -> Result<u64> {
+        require!(
+            amount <= supply && supply > 0,
+            PerpetualsError::InvalidBackstopState
+        );
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(self.value_usd as u128, amount as u128)?,
+            supply as u128,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backstop_waterfall() {
+        let mut tranche = BackstopTranche {
+            value_usd: 100,
+            fee_boost_bps: 10_000,
+            ..Default::default()
+        };
+        assert!(tranche.validate());
+        assert_eq!(tranche.get_senior_aum_usd(1_000), 900);
+
+        // a 10% tranche earns 20% of gains
+        assert_eq!(tranche.get_gain_share(1_000).unwrap(), 2_000);
+        tranche.record_pnl(50, 0, 1_000).unwrap();
+        assert_eq!(tranche.value_usd, 110);
+
+        // losses hit the tranche first
+        tranche.record_pnl(0, 60, 1_050).unwrap();
+        assert_eq!(tranche.value_usd, 50);
+        assert_eq!(tranche.absorbed_loss_usd, 60);
+        tranche.record_pnl(0, 80, 990).unwrap();
+        assert_eq!(tranche.value_usd, 0);
+        assert_eq!(tranche.absorbed_loss_usd, 110);
+        assert_eq!(tranche.get_senior_aum_usd(910), 910);
+
+        // a wiped out tranche earns nothing and takes no deposits
+        assert_eq!(tranche.get_gain_share(910).unwrap(), 0);
+        assert!(tranche.get_mint_amount(100, 100).is_err());
+        assert_eq!(tranche.get_mint_amount(100, 0).unwrap(), 100);
+
+        tranche.value_usd = 300;
+        assert_eq!(tranche.get_mint_amount(100, 150).unwrap(), 50);
+        assert_eq!(tranche.get_redeem_amount_usd(50, 150).unwrap(), 100);
+        assert!(tranche.get_redeem_amount_usd(151, 150).is_err());
+
+        // the boosted share is capped at all gains
+        assert_eq!(tranche.get_gain_share(400).unwrap(), 10_000);
+
+        tranche.fee_boost_bps = BackstopTranche::MAX_FEE_BOOST_BPS + 1;
+        assert!(!tranche.validate());
+    }
+}
@@ -0,0 +1,114 @@
+//! Compliance freeze list state
+//!
+//! Institutional deployments must stop sanctioned addresses from taking on new exposure.
+//! Owners on the freeze list of a custody can't open or increase positions on it, but
+//! can still close positions and withdraw collateral. The list is managed by the multisig
+//! or by a designated compliance authority, which can only freeze and unfreeze owners and
+//! has no power over their funds.
+
+use {crate::error::PerpetualsError, anchor_lang::prelude::*};
+
+/// Compliance freeze list account
+///
+/// PDA derived from the custody. Enforced only while `pool.compliance_freeze_enabled`
+/// is set.
+#[account]
+#[derive(Default, Debug)]
+pub struct ComplianceFreeze {
+    /// Custody the freeze list applies to
+    pub custody: Pubkey,
+    /// Authority allowed to update the freeze list besides the multisig
+    pub compliance_authority: Pubkey,
+    /// Owners that can't open or increase positions
+    pub owners: Vec<Pubkey>,
+
+    /// Bump seed for the freeze list PDA
+    pub bump: u8,
+}
+
+impl ComplianceFreeze {
+    /// Maximum number of frozen owners
+    pub const MAX_OWNERS: usize = 128;
+    /// Account size in bytes (8 byte discriminator + data + owners)
+    pub const LEN: usize = 8
+        + std::mem::size_of::<ComplianceFreeze>()
+        + ComplianceFreeze::MAX_OWNERS * std::mem::size_of::<Pubkey>();
+
+    /// Check whether an owner is frozen
+    pub fn is_frozen(&self, owner: &Pubkey) -> bool {
+        self.owners.contains(owner)
+    }
+
+    /// Add an owner to the freeze list (no-op if already present)
+    ///
+    /// # Returns
+    /// Error if the freeze list is full
+    pub fn add_owner(&mut self, owner: Pubkey) -> Result<()> {
+        if self.is_frozen(&owner) {
+            return Ok(());
+        }
+        if self.owners.len() >= ComplianceFreeze::MAX_OWNERS {
+            return err!(PerpetualsError::ComplianceFreezeFull);
+        }
+        self.owners.push(owner);
+        Ok(())
+    }
+
+    /// Remove an owner from the freeze list (no-op if not present)
+    pub fn remove_owner(&mut self, owner: &Pubkey) {
+        self.owners.retain(|x| x != owner);
+    }
+
+    /// Check that an owner may open or increase positions on the custody
+    ///
+    /// # Arguments
+    /// * `compliance_freeze` - Freeze list of the custody, required while enforced
+    /// * `enabled` - Whether the pool enforces freeze lists
+    /// * `owner` - Position owner
+    pub fn validate_owner(
+        compliance_freeze: Option<&ComplianceFreeze>,
+        enabled: bool,
+        owner: &Pubkey,
+    ) -> Result<()> {
+        if !enabled {
+            return Ok(());
+        }
+        match compliance_freeze {
+            Some(compliance_freeze) if compliance_freeze.is_frozen(owner) => {
+                err!(PerpetualsError::OwnerFrozen)
+            }
+            Some(_) => Ok(()),
+            None => err!(PerpetualsError::ComplianceFreezeRequired),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compliance_freeze() {
+        let owner = Pubkey::new_unique();
+        let mut compliance_freeze = ComplianceFreeze::default();
+
+        // not enforced, the list isn't needed
+        assert!(ComplianceFreeze::validate_owner(None, false, &owner).is_ok());
+        assert!(ComplianceFreeze::validate_owner(None, true, &owner).is_err());
+        assert!(ComplianceFreeze::validate_owner(Some(&compliance_freeze), true, &owner).is_ok());
+
+        compliance_freeze.add_owner(owner).unwrap();
+        compliance_freeze.add_owner(owner).unwrap();
+        assert_eq!(compliance_freeze.owners.len(), 1);
+        assert!(ComplianceFreeze::validate_owner(Some(&compliance_freeze), true, &owner).is_err());
+        assert!(ComplianceFreeze::validate_owner(Some(&compliance_freeze), false, &owner).is_ok());
+
+        compliance_freeze.remove_owner(&owner);
+        assert!(ComplianceFreeze::validate_owner(Some(&compliance_freeze), true, &owner).is_ok());
+
+        for _ in 0..ComplianceFreeze::MAX_OWNERS {
+            compliance_freeze.add_owner(Pubkey::new_unique()).unwrap();
+        }
+        assert!(compliance_freeze.add_owner(owner).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Crank scheduler state
+//!
+//! Tracks when each maintenance task of a pool last ran and pays keepers a SOL bounty
+//! from a budget held by the account when they run a task that was due. Anyone can top
+//! up the budget by transferring lamports to the account.
+
+use {crate::state::perpetuals::Perpetuals, anchor_lang::prelude::*};
+
+/// Maintenance tasks run through run_crank
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum CrankTask {
+    /// Refresh pool assets under management
+    UpdatePoolAum,
+    /// Accrue interest and refresh borrow rates of all pool custodies
+    UpdateBorrowRates,
+    /// Refresh the volatility of pool custodies with a volatility feed, which scales the
+    /// funding paid by positions, and accrue their funding
+    UpdateFunding,
+    /// Roll over the pool performance fee epoch once it has elapsed
+    RollPerformanceEpoch,
+}
+
+/// Crank scheduler account
+///
+/// PDA derived from the pool. Slot bookkeeping is indexed by `CrankTask`.
+#[account]
+#[derive(Default, Debug)]
+pub struct CrankState {
+    /// Pool the cranks maintain
+    pub pool: Pubkey,
+    /// Minimum number of slots between two paid runs of each task (0 = task not paid)
+    pub task_intervals: [u64; CrankState::NUM_TASKS],
+    /// Slot of the last paid run of each task
+    pub last_run_slots: [u64; CrankState::NUM_TASKS],
+    /// Bounty paid per due run (in lamports)
+    pub bounty_lamports: u64,
+    /// Total bounties paid (in lamports)
+    pub total_bounties_paid: u64,
+
+    /// Bump seed for the crank state PDA
+    pub bump: u8,
+}
+
+impl CrankState {
+    /// Number of crank tasks
+    pub const NUM_TASKS: usize = 4;
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<CrankState>();
+
+    /// Check whether a task is due for a paid run
+    pub fn is_due(&self, task: CrankTask, slot: u64) -> bool {
+        let interval = self.task_intervals[task as usize];
+        interval > 0 && slot >= self.last_run_slots[task as usize].saturating_add(interval)
+    }
+
+    /// Record a paid run of a task and pay the bounty to the keeper
+    ///
+    /// The bounty is capped to the budget held above the rent-exempt minimum, so an
+    /// empty budget still records the run but pays nothing.
+    ///
+    /// # Returns
+    /// Bounty paid (in lamports)
+    pub fn pay_bounty<'a>(
+        &mut self,
+        task: CrankTask,
+        slot: u64,
+        crank_state: AccountInfo<'a>,
+        keeper: AccountInfo<'a>,
+    ) -> Result<u64> {
+        self.last_run_slots[task as usize] = slot;
+
+        let budget = crank_state
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(crank_state.data_len()));
+        let bounty = std::cmp::min(self.bounty_lamports, budget);
+        if bounty > 0 {
+            Perpetuals::transfer_sol_from_owned(crank_state, keeper, bounty)?;
+            self.total_bounties_paid = self.total_bounties_paid.wrapping_add(bounty);
+        }
+        Ok(bounty)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_due() {
+        let mut crank_state = CrankState {
+            task_intervals: [10, 0, 20, 30],
+            ..Default::default()
+        };
+        crank_state.last_run_slots[CrankTask::UpdateFunding as usize] = 100;
+        crank_state.last_run_slots[CrankTask::RollPerformanceEpoch as usize] = 100;
+
+        // tasks without an interval are never paid
+        assert!(crank_state.is_due(CrankTask::UpdatePoolAum, 10));
+        assert!(!crank_state.is_due(CrankTask::UpdateBorrowRates, u64::MAX));
+
+        // each task is scheduled from its own last run
+        assert!(!crank_state.is_due(CrankTask::UpdateFunding, 119));
+        assert!(crank_state.is_due(CrankTask::UpdateFunding, 120));
+        assert!(!crank_state.is_due(CrankTask::RollPerformanceEpoch, 129));
+        assert!(crank_state.is_due(CrankTask::RollPerformanceEpoch, 130));
+
+        // a run of one task doesn't reschedule the others
+        crank_state.last_run_slots[CrankTask::UpdateFunding as usize] = 130;
+        assert!(!crank_state.is_due(CrankTask::UpdateFunding, 130));
+        assert!(crank_state.is_due(CrankTask::RollPerformanceEpoch, 130));
+        assert!(crank_state.is_due(CrankTask::UpdatePoolAum, 130));
+    }
+}
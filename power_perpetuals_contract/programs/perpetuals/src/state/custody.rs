@@ -1,23 +1,174 @@
 use {
     crate::{
         error::PerpetualsError,
-        math,
+        math::{self, RoundingDirection},
         state::{
-            oracle::{OracleParams, OraclePrice, OracleType},
-            perpetuals::{Permissions, Perpetuals},
-            position::{Position, Side},
+            oracle::{
+                CustomOracle, OracleOperation, OracleParams, OracleParamsV0, OraclePrice,
+                OracleType,
+            },
+            pending_claim::PendingClaim,
+            perpetuals::{LockedBreakdown, Permissions, Perpetuals},
+            position::{Position, RiskTier, Side},
         },
     },
     anchor_lang::prelude::*,
 };
 
-#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub enum FeesMode {
     Fixed,
+    #[default]
     Linear,
     Optimal,
 }
 
+// prices used to decide whether a position can be liquidated
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum LiquidationPriceMode {
+    // same spot/EMA price selection as the rest of the pricing
+    #[default]
+    Aggregate,
+    // both spot and EMA prices must breach max leverage
+    SpotAndEma,
+}
+
+// stage of a market being sunset, moves Active -> CloseOnly -> Settled -> Retired
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum MarketLifecycle {
+    // all operations allowed
+    #[default]
+    Active,
+    // positions can only be reduced and closed, no new exposure or liquidity
+    CloseOnly,
+    // no position left, liquidity providers can only exit
+    Settled,
+    // wound down, ready for remove_custody
+    Retired,
+}
+
+// source of the custody token to underlying exchange rate (e.g. mSOL/SOL)
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum ExchangeRateType {
+    // custody token is valued at the oracle price
+    #[default]
+    None,
+    // custom oracle account publishing underlying tokens per custody token
+    Custom,
+    // SPL stake pool account, rate = total_lamports / pool_token_supply
+    SplStakePool,
+}
+
+// source of the volatility scaling the dynamic pricing params
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum VolatilityType {
+    // static pricing params
+    #[default]
+    None,
+    // custom oracle confidence interval relative to its EMA price, times conf_mult
+    OracleConf,
+    // custom oracle account publishing the volatility
+    Custom,
+}
+
+/// Custody or pool config check failed by proposed custody params
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum ConfigViolation {
+    // checks enforced by set_custody_config
+    VirtualStable,
+    OracleParams,
+    PricingParams,
+    Fees,
+    EntryFeeTiers,
+    BorrowRate,
+    ExpiryTime,
+    ExchangeRate,
+    Volatility,
+    ParamBounds,
+    RatioCount,
+    PoolRatios,
+    // extended checks, not enforced on-chain
+    // stable swap fees above the regular swap fees
+    StableSwapFees,
+    // liquidation fee not below the maintenance margin
+    LiquidationFee,
+    // zero max price error rejects any price with a confidence interval
+    OraclePriceError,
+    // zero max price age rejects any price not published in the current second
+    OraclePriceAge,
+}
+
+/// Globally safe envelope of custody params
+///
+/// Enforced on top of the custody config checks when a custody is added, reconfigured
+/// or listed, so even a fully signed multisig can't set params outside of it. The
+/// bounds only change with a program upgrade.
+pub struct ParamBounds;
+
+impl ParamBounds {
+    // max trade and swap spreads, BPS
+    pub const MAX_SPREAD: u64 = 1_000;
+    // max leverage, including the maintenance leverage, BPS
+    pub const MAX_LEVERAGE: u64 = 2_000_000;
+    // max swap, liquidity, position and liquidation fees, BPS
+    pub const MAX_FEE: u64 = 1_000;
+    // max ratio and utilization fee multipliers, BPS
+    pub const MAX_FEE_MULT: u64 = 100_000;
+    // max volatility multiplier of the dynamic pricing params, BPS
+    pub const MAX_VOL_MULT: u64 = 50_000;
+}
+
+/// Fee kind, selects the applicable protocol share
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FeeType {
+    Swap,
+    // add and remove liquidity
+    Liquidity,
+    // open and close position
+    Position,
+    Liquidation,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ExchangeRateParams {
+    pub rate_type: ExchangeRateType,
+    pub rate_account: Pubkey,
+    // max age (seconds) of the rate source and of the cached rate, 0 to disable
+    pub max_rate_age_sec: u32,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ExchangeRateState {
+    // underlying tokens per custody token, has implied RATE_DECIMALS decimals
+    pub rate: u64,
+    pub last_update: i64,
+}
+
+// spreads, borrow rate slopes and the convexity reserve are scaled by vol / ref_vol
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct VolatilityParams {
+    pub vol_type: VolatilityType,
+    pub vol_account: Pubkey,
+    // max age (seconds) of the vol source and of the cached vol, past it the static
+    // params apply
+    pub max_vol_age_sec: u32,
+    // volatility params have implied BPS_DECIMALS decimals
+    // confidence interval to volatility multiplier, OracleConf only
+    pub conf_mult: u64,
+    // volatility the static params are calibrated for
+    pub ref_vol: u64,
+    // the feed volatility is clamped to [min_vol, max_vol]
+    pub min_vol: u64,
+    pub max_vol: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct VolatilityState {
+    // unclamped feed volatility, has implied BPS_DECIMALS decimals
+    pub vol: u64,
+    pub last_update: i64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Fees {
     pub mode: FeesMode,
@@ -37,26 +188,55 @@ pub struct Fees {
     // configs for optimal fee mode
     pub fee_max: u64,
     pub fee_optimal: u64,
+    // anti-scalp fee charged on closes within pricing.min_holding_period
+    pub early_close: u64,
+    // single fee charged by roll_position in place of the close and open position fees
+    pub roll_position: u64,
+    // extra remove liquidity fee for fresh deposits, decays to 0 over pricing.lp_fee_decay_period
+    pub early_remove_liquidity: u64,
+    // protocol share overrides per fee type, 0 to use protocol_share
+    pub swap_protocol_share: u64,
+    pub liquidity_protocol_share: u64,
+    pub position_protocol_share: u64,
+    pub liquidation_protocol_share: u64,
+}
+
+// extra open position fee charged on the part of the position size above min_size_usd,
+// a tier with min_size_usd 0 is unused
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct EntryFeeTier {
+    pub min_size_usd: u64,
+    // fee has implied BPS_DECIMALS decimals
+    pub fee: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct FeesStats {
-    pub swap_usd: u64,
-    pub add_liquidity_usd: u64,
-    pub remove_liquidity_usd: u64,
-    pub open_position_usd: u64,
-    pub close_position_usd: u64,
-    pub liquidation_usd: u64,
+    pub swap_usd: u128,
+    pub add_liquidity_usd: u128,
+    pub remove_liquidity_usd: u128,
+    pub open_position_usd: u128,
+    pub close_position_usd: u128,
+    pub liquidation_usd: u128,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct VolumeStats {
-    pub swap_usd: u64,
-    pub add_liquidity_usd: u64,
-    pub remove_liquidity_usd: u64,
-    pub open_position_usd: u64,
-    pub close_position_usd: u64,
-    pub liquidation_usd: u64,
+    pub swap_usd: u128,
+    pub add_liquidity_usd: u128,
+    pub remove_liquidity_usd: u128,
+    pub open_position_usd: u128,
+    pub close_position_usd: u128,
+    pub liquidation_usd: u128,
+}
+
+// lifetime counters when the current stats epoch started, counters since the epoch
+// started are the lifetime counters minus the snapshot
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct StatsEpoch {
+    pub start_time: i64,
+    pub collected_fees: FeesStats,
+    pub volume_stats: VolumeStats,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -78,6 +258,8 @@ pub struct Assets {
     pub owned: u64,
     // locked funds for pnl payoff
     pub locked: u64,
+    // shortfall of the custody token account vs bookkeeping found by reconcile_custody
+    pub bad_debt: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -92,12 +274,31 @@ pub struct PricingParams {
     pub min_initial_leverage: u64,
     pub max_initial_leverage: u64,
     pub max_leverage: u64,
+    // leverage above which positions can be liquidated, 0 to use max_leverage. Kept
+    // above max_leverage it leaves a buffer between entry and liquidation
+    pub maintenance_leverage: u64,
     // max_user_profit = position_size * max_payoff_mult
     pub max_payoff_mult: u64,
     pub max_utilization: u64,
     // USD denominated values always have implied USD_DECIMALS decimals
     pub max_position_locked_usd: u64,
     pub max_total_locked_usd: u64,
+    // minimum time a position must be held before it can be closed (seconds), 0 to disable
+    pub min_holding_period: i64,
+    // reject closes within min_holding_period instead of charging fees.early_close
+    pub reject_early_close: bool,
+    pub liquidation_price_mode: LiquidationPriceMode,
+    // age (seconds) after which LP deposits pay the base remove liquidity fee, 0 to disable
+    pub lp_fee_decay_period: i64,
+    // health factor (maintenance leverage / leverage in BPS) at or below which positions are
+    // labeled RiskTier::Warning / RiskTier::Danger, 0 to disable the tier
+    pub risk_warning_health: u64,
+    pub risk_danger_health: u64,
+    // highest position power allowed in the market, 0 for PricingParams::MAX_POWER
+    pub max_power: u8,
+    // volatility sizing the convexity reserve locked on top of power > 1 positions, 0 to
+    // disable
+    pub convexity_vol: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -117,6 +318,41 @@ pub struct BorrowRateState {
     pub last_update: i64,
 }
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct RateSnapshot {
+    pub time: i64,
+    // borrow rates have implied RATE_DECIMALS decimals
+    pub borrow_rate: u64,
+    pub cumulative_interest: u128,
+}
+
+// the cumulative interest index is rebased to zero once it reaches
+// Custody::INTEREST_REBASE_THRESHOLD, positions snapshot the index together with the epoch id
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct InterestEpoch {
+    pub id: u32,
+    // index value the previous epoch ended at, before it was rebased
+    pub prev_end_interest: u128,
+}
+
+// ring buffer of hourly borrow rate snapshots, a snapshot with time 0 is unused
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct RateHistory {
+    pub snapshots: [RateSnapshot; RateHistory::NUM_SNAPSHOTS],
+    pub next_index: u8,
+}
+
+// FIFO queue of withdrawals waiting for free liquidity
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ClaimQueue {
+    // id of the next queued claim
+    pub next_claim_id: u64,
+    // id of the next claim to execute
+    pub next_execute_id: u64,
+    // tokens of queued claims, still part of owned but not of pool AUM
+    pub pending_amount: u64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct PositionStats {
     pub open_positions: u64,
@@ -145,6 +381,10 @@ pub struct Custody {
     pub permissions: Permissions,
     pub fees: Fees,
     pub borrow_rate: BorrowRateParams,
+    // power futures expiry timestamp, 0 for perpetual markets
+    pub expiry_time: i64,
+    // liquid staking token valuation, the oracle prices the underlying token
+    pub exchange_rate: ExchangeRateParams,
 
     // dynamic variables
     pub assets: Assets,
@@ -154,12 +394,177 @@ pub struct Custody {
     pub long_positions: PositionStats,
     pub short_positions: PositionStats,
     pub borrow_rate_state: BorrowRateState,
+    // price snapshotted after expiry, has implied PRICE_DECIMALS decimals
+    pub settlement_price: u64,
+    // exchange rate cached by update_exchange_rate
+    pub exchange_rate_state: ExchangeRateState,
+    // hourly borrow rate snapshots recorded by update_borrow_rate
+    pub rate_history: RateHistory,
+    // withdrawals queued until liquidity frees up
+    pub claim_queue: ClaimQueue,
+    // set while the oracle misses its heartbeat, blocks risk-increasing operations
+    pub oracle_safe_mode: bool,
+    // volume and fee counters snapshot at the start of the stats epoch
+    pub stats_epoch: StatsEpoch,
+    // portion of owned assets set aside to pay trader profits, fed by borrow interest
+    // and liquidation gains
+    pub pnl_reserve: u64,
+    // slot of the last permissionless oracle update and the updates accepted in it
+    pub oracle_update_slot: u64,
+    pub oracle_slot_updates: u8,
+    // open position fee tiers by position size, sorted by min_size_usd
+    pub entry_fee_tiers: [EntryFeeTier; Custody::MAX_ENTRY_FEE_TIERS],
+    // wind-down stage of the market
+    pub lifecycle: MarketLifecycle,
+    // epoch of the cumulative interest index
+    pub interest_epoch: InterestEpoch,
+    // volatility feed scaling the dynamic pricing params
+    pub volatility: VolatilityParams,
+    // volatility cached by update_volatility
+    pub volatility_state: VolatilityState,
+    // allow user-facing trading instructions to be invoked through CPI
+    pub allow_cpi: bool,
+
+    // bumps for address validation
+    pub bump: u8,
+    pub token_account_bump: u8,
+}
+
+impl MarketLifecycle {
+    // opening and increasing positions, adding liquidity, swaps
+    pub fn allows_open(&self) -> bool {
+        *self == MarketLifecycle::Active
+    }
+
+    // reducing, closing, settling and liquidating positions
+    pub fn allows_close(&self) -> bool {
+        matches!(self, MarketLifecycle::Active | MarketLifecycle::CloseOnly)
+    }
+
+    // removing liquidity
+    pub fn allows_lp_exit(&self) -> bool {
+        *self != MarketLifecycle::Retired
+    }
+
+    // admin transitions, a close-only market can be reopened
+    pub fn can_transition_to(&self, next: MarketLifecycle) -> bool {
+        matches!(
+            (self, next),
+            (MarketLifecycle::Active, MarketLifecycle::CloseOnly)
+                | (MarketLifecycle::CloseOnly, MarketLifecycle::Active)
+                | (MarketLifecycle::CloseOnly, MarketLifecycle::Settled)
+                | (MarketLifecycle::Settled, MarketLifecycle::Retired)
+        )
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DeprecatedFeesStats {
+    pub swap_usd: u64,
+    pub add_liquidity_usd: u64,
+    pub remove_liquidity_usd: u64,
+    pub open_position_usd: u64,
+    pub close_position_usd: u64,
+    pub liquidation_usd: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DeprecatedVolumeStats {
+    pub swap_usd: u64,
+    pub add_liquidity_usd: u64,
+    pub remove_liquidity_usd: u64,
+    pub open_position_usd: u64,
+    pub close_position_usd: u64,
+    pub liquidation_usd: u64,
+}
+
+// original custody layout, upgraded by upgrade_custody. Shares the Custody account
+// discriminator.
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyV0 {
+    // static parameters
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub decimals: u8,
+    pub is_stable: bool,
+    pub is_virtual: bool,
+    pub oracle: OracleParamsV0,
+    pub pricing: PricingParamsV0,
+    pub permissions: Permissions,
+    pub fees: FeesV0,
+    pub borrow_rate: BorrowRateParams,
+
+    // dynamic variables
+    pub assets: AssetsV0,
+    pub collected_fees: DeprecatedFeesStats,
+    pub volume_stats: DeprecatedVolumeStats,
+    pub trade_stats: TradeStats,
+    pub long_positions: PositionStats,
+    pub short_positions: PositionStats,
+    pub borrow_rate_state: BorrowRateState,
 
     // bumps for address validation
     pub bump: u8,
     pub token_account_bump: u8,
 }
 
+// pricing params of the original custody layout
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PricingParamsV0 {
+    pub use_ema: bool,
+    // whether to account for unrealized pnl in assets under management calculations
+    pub use_unrealized_pnl_in_aum: bool,
+    // pricing params have implied BPS_DECIMALS decimals (except ended with _usd)
+    pub trade_spread_long: u64,
+    pub trade_spread_short: u64,
+    pub swap_spread: u64,
+    pub min_initial_leverage: u64,
+    pub max_initial_leverage: u64,
+    pub max_leverage: u64,
+    // max_user_profit = position_size * max_payoff_mult
+    pub max_payoff_mult: u64,
+    pub max_utilization: u64,
+    // USD denominated values always have implied USD_DECIMALS decimals
+    pub max_position_locked_usd: u64,
+    pub max_total_locked_usd: u64,
+}
+
+// fees of the original custody layout
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct FeesV0 {
+    pub mode: FeesMode,
+    // fees have implied BPS_DECIMALS decimals
+    pub ratio_mult: u64,
+    pub utilization_mult: u64,
+    pub swap_in: u64,
+    pub swap_out: u64,
+    pub stable_swap_in: u64,
+    pub stable_swap_out: u64,
+    pub add_liquidity: u64,
+    pub remove_liquidity: u64,
+    pub open_position: u64,
+    pub close_position: u64,
+    pub liquidation: u64,
+    pub protocol_share: u64,
+    // configs for optimal fee mode
+    pub fee_max: u64,
+    pub fee_optimal: u64,
+}
+
+// assets of the original custody layout
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct AssetsV0 {
+    // collateral debt
+    pub collateral: u64,
+    // protocol_fees are part of the collected fees that is reserved for the protocol
+    pub protocol_fees: u64,
+    // owned = total_assets - collateral + collected_fees - protocol_fees
+    pub owned: u64,
+    // locked funds for pnl payoff
+    pub locked: u64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct DeprecatedPricingParams {
     pub use_ema: bool,
@@ -184,16 +589,16 @@ pub struct DeprecatedCustody {
     pub token_account: Pubkey,
     pub decimals: u8,
     pub is_stable: bool,
-    pub oracle: OracleParams,
-    pub pricing: PricingParams,
+    pub oracle: OracleParamsV0,
+    pub pricing: PricingParamsV0,
     pub permissions: Permissions,
-    pub fees: Fees,
+    pub fees: FeesV0,
     pub borrow_rate: BorrowRateParams,
 
     // dynamic variables
-    pub assets: Assets,
-    pub collected_fees: FeesStats,
-    pub volume_stats: VolumeStats,
+    pub assets: AssetsV0,
+    pub collected_fees: DeprecatedFeesStats,
+    pub volume_stats: DeprecatedVolumeStats,
     pub trade_stats: TradeStats,
     pub long_positions: PositionStats,
     pub short_positions: PositionStats,
@@ -204,9 +609,217 @@ pub struct DeprecatedCustody {
     pub token_account_bump: u8,
 }
 
-impl Default for FeesMode {
-    fn default() -> Self {
-        Self::Linear
+impl FeesStats {
+    pub fn get_total_usd(&self) -> Result<u128> {
+        let mut total_usd = math::checked_add(self.swap_usd, self.add_liquidity_usd)?;
+        total_usd = math::checked_add(total_usd, self.remove_liquidity_usd)?;
+        total_usd = math::checked_add(total_usd, self.open_position_usd)?;
+        total_usd = math::checked_add(total_usd, self.close_position_usd)?;
+        math::checked_add(total_usd, self.liquidation_usd)
+    }
+
+    /// Returns the counters accumulated since the `since` snapshot
+    pub fn checked_sub(&self, since: &FeesStats) -> Result<FeesStats> {
+        Ok(FeesStats {
+            swap_usd: math::checked_sub(self.swap_usd, since.swap_usd)?,
+            add_liquidity_usd: math::checked_sub(self.add_liquidity_usd, since.add_liquidity_usd)?,
+            remove_liquidity_usd: math::checked_sub(
+                self.remove_liquidity_usd,
+                since.remove_liquidity_usd,
+            )?,
+            open_position_usd: math::checked_sub(self.open_position_usd, since.open_position_usd)?,
+            close_position_usd: math::checked_sub(
+                self.close_position_usd,
+                since.close_position_usd,
+            )?,
+            liquidation_usd: math::checked_sub(self.liquidation_usd, since.liquidation_usd)?,
+        })
+    }
+}
+
+impl From<DeprecatedFeesStats> for FeesStats {
+    fn from(stats: DeprecatedFeesStats) -> Self {
+        FeesStats {
+            swap_usd: stats.swap_usd as u128,
+            add_liquidity_usd: stats.add_liquidity_usd as u128,
+            remove_liquidity_usd: stats.remove_liquidity_usd as u128,
+            open_position_usd: stats.open_position_usd as u128,
+            close_position_usd: stats.close_position_usd as u128,
+            liquidation_usd: stats.liquidation_usd as u128,
+        }
+    }
+}
+
+impl VolumeStats {
+    /// Returns the counters accumulated since the `since` snapshot
+    pub fn checked_sub(&self, since: &VolumeStats) -> Result<VolumeStats> {
+        Ok(VolumeStats {
+            swap_usd: math::checked_sub(self.swap_usd, since.swap_usd)?,
+            add_liquidity_usd: math::checked_sub(self.add_liquidity_usd, since.add_liquidity_usd)?,
+            remove_liquidity_usd: math::checked_sub(
+                self.remove_liquidity_usd,
+                since.remove_liquidity_usd,
+            )?,
+            open_position_usd: math::checked_sub(self.open_position_usd, since.open_position_usd)?,
+            close_position_usd: math::checked_sub(
+                self.close_position_usd,
+                since.close_position_usd,
+            )?,
+            liquidation_usd: math::checked_sub(self.liquidation_usd, since.liquidation_usd)?,
+        })
+    }
+}
+
+impl From<PricingParamsV0> for PricingParams {
+    fn from(pricing: PricingParamsV0) -> Self {
+        PricingParams {
+            use_ema: pricing.use_ema,
+            use_unrealized_pnl_in_aum: pricing.use_unrealized_pnl_in_aum,
+            trade_spread_long: pricing.trade_spread_long,
+            trade_spread_short: pricing.trade_spread_short,
+            swap_spread: pricing.swap_spread,
+            min_initial_leverage: pricing.min_initial_leverage,
+            max_initial_leverage: pricing.max_initial_leverage,
+            max_leverage: pricing.max_leverage,
+            maintenance_leverage: 0,
+            max_payoff_mult: pricing.max_payoff_mult,
+            max_utilization: pricing.max_utilization,
+            max_position_locked_usd: pricing.max_position_locked_usd,
+            max_total_locked_usd: pricing.max_total_locked_usd,
+            min_holding_period: 0,
+            reject_early_close: false,
+            liquidation_price_mode: LiquidationPriceMode::Aggregate,
+            lp_fee_decay_period: 0,
+            risk_warning_health: 0,
+            risk_danger_health: 0,
+            max_power: 0,
+            convexity_vol: 0,
+        }
+    }
+}
+
+impl From<FeesV0> for Fees {
+    fn from(fees: FeesV0) -> Self {
+        Fees {
+            mode: fees.mode,
+            ratio_mult: fees.ratio_mult,
+            utilization_mult: fees.utilization_mult,
+            swap_in: fees.swap_in,
+            swap_out: fees.swap_out,
+            stable_swap_in: fees.stable_swap_in,
+            stable_swap_out: fees.stable_swap_out,
+            add_liquidity: fees.add_liquidity,
+            remove_liquidity: fees.remove_liquidity,
+            open_position: fees.open_position,
+            close_position: fees.close_position,
+            liquidation: fees.liquidation,
+            protocol_share: fees.protocol_share,
+            fee_max: fees.fee_max,
+            fee_optimal: fees.fee_optimal,
+            early_close: 0,
+            roll_position: fees.open_position.saturating_add(fees.close_position),
+            early_remove_liquidity: 0,
+            swap_protocol_share: 0,
+            liquidity_protocol_share: 0,
+            position_protocol_share: 0,
+            liquidation_protocol_share: 0,
+        }
+    }
+}
+
+impl From<AssetsV0> for Assets {
+    fn from(assets: AssetsV0) -> Self {
+        Assets {
+            collateral: assets.collateral,
+            protocol_fees: assets.protocol_fees,
+            owned: assets.owned,
+            locked: assets.locked,
+            bad_debt: 0,
+        }
+    }
+}
+
+impl From<DeprecatedCustody> for CustodyV0 {
+    fn from(custody: DeprecatedCustody) -> Self {
+        CustodyV0 {
+            pool: custody.pool,
+            mint: custody.mint,
+            token_account: custody.token_account,
+            decimals: custody.decimals,
+            is_stable: custody.is_stable,
+            is_virtual: false,
+            oracle: custody.oracle,
+            pricing: custody.pricing,
+            permissions: custody.permissions,
+            fees: custody.fees,
+            borrow_rate: custody.borrow_rate,
+            assets: custody.assets,
+            collected_fees: custody.collected_fees,
+            volume_stats: custody.volume_stats,
+            trade_stats: custody.trade_stats,
+            long_positions: custody.long_positions,
+            short_positions: custody.short_positions,
+            borrow_rate_state: custody.borrow_rate_state,
+            bump: custody.bump,
+            token_account_bump: custody.token_account_bump,
+        }
+    }
+}
+
+impl From<CustodyV0> for Custody {
+    fn from(custody: CustodyV0) -> Self {
+        Custody {
+            pool: custody.pool,
+            mint: custody.mint,
+            token_account: custody.token_account,
+            decimals: custody.decimals,
+            is_stable: custody.is_stable,
+            is_virtual: custody.is_virtual,
+            oracle: custody.oracle.into(),
+            pricing: custody.pricing.into(),
+            permissions: custody.permissions,
+            fees: custody.fees.into(),
+            borrow_rate: custody.borrow_rate,
+            expiry_time: 0,
+            exchange_rate: ExchangeRateParams::default(),
+            assets: custody.assets.into(),
+            collected_fees: custody.collected_fees.into(),
+            volume_stats: custody.volume_stats.into(),
+            trade_stats: custody.trade_stats,
+            long_positions: custody.long_positions,
+            short_positions: custody.short_positions,
+            borrow_rate_state: custody.borrow_rate_state,
+            settlement_price: 0,
+            exchange_rate_state: ExchangeRateState::default(),
+            rate_history: RateHistory::default(),
+            claim_queue: ClaimQueue::default(),
+            oracle_safe_mode: false,
+            stats_epoch: StatsEpoch::default(),
+            pnl_reserve: 0,
+            oracle_update_slot: 0,
+            oracle_slot_updates: 0,
+            entry_fee_tiers: Default::default(),
+            lifecycle: MarketLifecycle::Active,
+            interest_epoch: InterestEpoch::default(),
+            volatility: VolatilityParams::default(),
+            volatility_state: VolatilityState::default(),
+            allow_cpi: false,
+            bump: custody.bump,
+            token_account_bump: custody.token_account_bump,
+        }
+    }
+}
+
+impl From<DeprecatedVolumeStats> for VolumeStats {
+    fn from(stats: DeprecatedVolumeStats) -> Self {
+        VolumeStats {
+            swap_usd: stats.swap_usd as u128,
+            add_liquidity_usd: stats.add_liquidity_usd as u128,
+            remove_liquidity_usd: stats.remove_liquidity_usd as u128,
+            open_position_usd: stats.open_position_usd as u128,
+            close_position_usd: stats.close_position_usd as u128,
+            liquidation_usd: stats.liquidation_usd as u128,
+        }
     }
 }
 
@@ -224,6 +837,67 @@ impl Fees {
             && self.protocol_share as u128 <= Perpetuals::BPS_POWER
             && self.fee_max as u128 <= Perpetuals::BPS_POWER
             && self.fee_optimal as u128 <= Perpetuals::BPS_POWER
+            && self.fee_optimal <= self.fee_max
+            && self.early_close as u128 <= Perpetuals::BPS_POWER
+            && self.roll_position as u128 <= Perpetuals::BPS_POWER
+            && self.early_remove_liquidity as u128 <= Perpetuals::BPS_POWER
+            && self.swap_protocol_share as u128 <= Perpetuals::BPS_POWER
+            && self.liquidity_protocol_share as u128 <= Perpetuals::BPS_POWER
+            && self.position_protocol_share as u128 <= Perpetuals::BPS_POWER
+            && self.liquidation_protocol_share as u128 <= Perpetuals::BPS_POWER
+    }
+
+    /// Returns the protocol share (BPS) of a fee type
+    pub fn get_protocol_share(&self, fee_type: FeeType) -> u64 {
+        let share_override = match fee_type {
+            FeeType::Swap => self.swap_protocol_share,
+            FeeType::Liquidity => self.liquidity_protocol_share,
+            FeeType::Position => self.position_protocol_share,
+            FeeType::Liquidation => self.liquidation_protocol_share,
+        };
+        if share_override == 0 {
+            self.protocol_share
+        } else {
+            share_override
+        }
+    }
+
+    /// Returns collected fees net of the protocol share of each fee type
+    pub fn get_lp_fees_usd(&self, collected_fees: &FeesStats) -> Result<u128> {
+        let remaining_after_share = |amount: u128, fee_type: FeeType| -> Result<u128> {
+            math::checked_div(
+                math::checked_mul(
+                    amount,
+                    math::checked_sub(
+                        Perpetuals::BPS_POWER,
+                        self.get_protocol_share(fee_type) as u128,
+                    )?,
+                )?,
+                Perpetuals::BPS_POWER,
+            )
+        };
+        let swap_usd = remaining_after_share(collected_fees.swap_usd, FeeType::Swap)?;
+        let liquidity_usd = remaining_after_share(
+            math::checked_add(
+                collected_fees.add_liquidity_usd,
+                collected_fees.remove_liquidity_usd,
+            )?,
+            FeeType::Liquidity,
+        )?;
+        let position_usd = remaining_after_share(
+            math::checked_add(
+                collected_fees.open_position_usd,
+                collected_fees.close_position_usd,
+            )?,
+            FeeType::Position,
+        )?;
+        let liquidation_usd =
+            remaining_after_share(collected_fees.liquidation_usd, FeeType::Liquidation)?;
+
+        math::checked_add(
+            math::checked_add(swap_usd, liquidity_usd)?,
+            math::checked_add(position_usd, liquidation_usd)?,
+        )
     }
 }
 
@@ -231,18 +905,201 @@ impl OracleParams {
     pub fn validate(&self) -> bool {
         self.oracle_type == OracleType::None || self.oracle_account != Pubkey::default()
     }
+
+    /// Returns the oracle update age in seconds that triggers safe mode (0 = disabled)
+    pub fn get_heartbeat_limit(&self) -> u64 {
+        (self.max_price_age_sec as u64).saturating_mul(self.heartbeat_mult as u64)
+    }
+
+    /// Checks whether the oracle missed its heartbeat
+    pub fn is_heartbeat_missed(&self, publish_time: i64, curtime: i64) -> bool {
+        let heartbeat_limit = self.get_heartbeat_limit();
+        heartbeat_limit > 0 && curtime.saturating_sub(publish_time) > heartbeat_limit as i64
+    }
+
+    /// Returns the oracle operation to close positions with: Close, or CloseGrace if the
+    /// price is too old for Close and the custody allows a close grace
+    pub fn get_close_operation(
+        &self,
+        oracle_account: &AccountInfo,
+        curtime: i64,
+    ) -> Result<OracleOperation> {
+        if self.close_grace_mult > 1 {
+            let publish_time = OraclePrice::get_publish_time(oracle_account, self)?;
+            if curtime.saturating_sub(publish_time)
+                > self.get_max_price_age(OracleOperation::Close) as i64
+            {
+                return Ok(OracleOperation::CloseGrace);
+            }
+        }
+        Ok(OracleOperation::Close)
+    }
+
+    /// Returns the max price age in seconds that applies to the given operation
+    pub fn get_max_price_age(&self, operation: OracleOperation) -> u32 {
+        let max_price_age_sec = match operation {
+            OracleOperation::Open => self.max_price_age_sec,
+            OracleOperation::Close => self.max_price_age_close_sec,
+            OracleOperation::Liquidate => self.max_price_age_liquidate_sec,
+            OracleOperation::Liquidity => self.max_price_age_liquidity_sec,
+            OracleOperation::CloseGrace => {
+                return self
+                    .get_max_price_age(OracleOperation::Close)
+                    .saturating_mul(self.close_grace_mult.max(1));
+            }
+        };
+        if max_price_age_sec == 0 {
+            self.max_price_age_sec
+        } else {
+            max_price_age_sec
+        }
+    }
 }
 
 impl PricingParams {
+    // highest position power supported by the program
+    pub const MAX_POWER: u8 = 5;
+
     pub fn validate(&self) -> bool {
         (self.min_initial_leverage as u128) >= Perpetuals::BPS_POWER
             && self.min_initial_leverage <= self.max_initial_leverage
             && self.max_initial_leverage <= self.max_leverage
+            && (self.maintenance_leverage == 0 || self.maintenance_leverage >= self.max_leverage)
             && (self.trade_spread_long as u128) < Perpetuals::BPS_POWER
             && (self.trade_spread_short as u128) < Perpetuals::BPS_POWER
             && (self.swap_spread as u128) < Perpetuals::BPS_POWER
             && (self.max_utilization as u128) <= Perpetuals::BPS_POWER
             && self.max_position_locked_usd <= self.max_total_locked_usd
+            && self.min_holding_period >= 0
+            && self.lp_fee_decay_period >= 0
+            && self.risk_danger_health <= self.risk_warning_health
+            && self.max_power <= Self::MAX_POWER
+            && (self.convexity_vol as u128) <= Perpetuals::BPS_POWER
+    }
+
+    // returns the highest position power allowed in the market
+    pub fn get_max_power(&self) -> u8 {
+        if self.max_power == 0 {
+            Self::MAX_POWER
+        } else {
+            self.max_power
+        }
+    }
+
+    // checks a position power against the market max power
+    pub fn check_power(&self, power: u8) -> Result<()> {
+        require!(
+            (1..=Self::MAX_POWER).contains(&power),
+            PerpetualsError::InvalidPositionState
+        );
+        require!(
+            power <= self.get_max_power(),
+            PerpetualsError::MaxPowerExceeded
+        );
+        Ok(())
+    }
+}
+
+impl ExchangeRateParams {
+    // SPL stake pool program id
+    pub const SPL_STAKE_POOL_PROGRAM_ID: Pubkey =
+        Pubkey::from_str_const("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+    // StakePool::total_lamports offset, pool_token_supply follows it
+    const SPL_STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+
+    pub fn validate(&self) -> bool {
+        self.rate_type == ExchangeRateType::None || self.rate_account != Pubkey::default()
+    }
+
+    // reads the exchange rate from the rate account, in RATE_DECIMALS
+    pub fn get_rate(&self, rate_account: &AccountInfo, curtime: i64) -> Result<u64> {
+        require_keys_eq!(
+            rate_account.key(),
+            self.rate_account,
+            PerpetualsError::InvalidExchangeRate
+        );
+        let data = rate_account.try_borrow_data()?;
+        let rate = match self.rate_type {
+            ExchangeRateType::Custom => {
+                require!(
+                    rate_account.owner == &crate::ID && data.len() >= CustomOracle::LEN,
+                    PerpetualsError::InvalidExchangeRate
+                );
+                let oracle = CustomOracle::try_deserialize(&mut &data[..])?;
+                require!(
+                    self.max_rate_age_sec == 0
+                        || math::checked_sub(curtime, oracle.publish_time)?
+                            <= self.max_rate_age_sec as i64,
+                    PerpetualsError::StaleExchangeRate
+                );
+                OraclePrice::new(oracle.price, oracle.expo)
+                    .scale_to_exponent(-(Perpetuals::RATE_DECIMALS as i32))?
+                    .price
+            }
+            ExchangeRateType::SplStakePool => {
+                let offset = Self::SPL_STAKE_POOL_TOTAL_LAMPORTS_OFFSET;
+                require!(
+                    rate_account.owner == &Self::SPL_STAKE_POOL_PROGRAM_ID
+                        && data.len() >= offset + 16,
+                    PerpetualsError::InvalidExchangeRate
+                );
+                let total_lamports = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+                let pool_token_supply =
+                    u64::from_le_bytes(data[offset + 8..offset + 16].try_into().unwrap());
+                require!(pool_token_supply > 0, PerpetualsError::InvalidExchangeRate);
+                math::checked_as_u64(math::checked_div(
+                    math::checked_mul(total_lamports as u128, Perpetuals::RATE_POWER)?,
+                    pool_token_supply as u128,
+                )?)?
+            }
+            ExchangeRateType::None => return err!(PerpetualsError::InvalidExchangeRate),
+        };
+        require!(rate > 0, PerpetualsError::InvalidExchangeRate);
+        Ok(rate)
+    }
+}
+
+impl VolatilityParams {
+    pub fn validate(&self) -> bool {
+        self.vol_type == VolatilityType::None
+            || (self.vol_account != Pubkey::default()
+                && self.max_vol_age_sec > 0
+                && self.ref_vol > 0
+                && self.min_vol <= self.max_vol
+                && (self.vol_type != VolatilityType::OracleConf || self.conf_mult > 0))
+    }
+
+    // reads the volatility from the vol account, in BPS_DECIMALS
+    pub fn get_vol(&self, vol_account: &AccountInfo, curtime: i64) -> Result<u64> {
+        require_keys_eq!(
+            vol_account.key(),
+            self.vol_account,
+            PerpetualsError::InvalidVolatility
+        );
+        let data = vol_account.try_borrow_data()?;
+        require!(
+            vol_account.owner == &crate::ID && data.len() >= CustomOracle::LEN,
+            PerpetualsError::InvalidVolatility
+        );
+        let oracle = CustomOracle::try_deserialize(&mut &data[..])?;
+        require!(
+            math::checked_sub(curtime, oracle.publish_time)? <= self.max_vol_age_sec as i64,
+            PerpetualsError::StaleVolatility
+        );
+        match self.vol_type {
+            // conf and ema share the oracle exponent
+            VolatilityType::OracleConf => {
+                require!(oracle.ema > 0, PerpetualsError::InvalidVolatility);
+                math::checked_as_u64(math::checked_div(
+                    math::checked_mul(oracle.conf as u128, self.conf_mult as u128)?,
+                    oracle.ema as u128,
+                )?)
+            }
+            VolatilityType::Custom => Ok(OraclePrice::new(oracle.price, oracle.expo)
+                .scale_to_exponent(-(Perpetuals::BPS_DECIMALS as i32))?
+                .price),
+            VolatilityType::None => err!(PerpetualsError::InvalidVolatility),
+        }
     }
 }
 
@@ -252,8 +1109,88 @@ impl BorrowRateParams {
     }
 }
 
+impl RateHistory {
+    pub const NUM_SNAPSHOTS: usize = 24;
+    pub const SNAPSHOT_INTERVAL_SEC: i64 = 3600;
+
+    /// Returns the most recent snapshot, if any
+    pub fn get_last(&self) -> Option<&RateSnapshot> {
+        let idx = (self.next_index as usize + Self::NUM_SNAPSHOTS - 1) % Self::NUM_SNAPSHOTS;
+        let last = &self.snapshots[idx];
+        (last.time != 0).then_some(last)
+    }
+
+    /// Records a snapshot unless one was already taken in the same hour
+    pub fn record(&mut self, curtime: i64, borrow_rate_state: &BorrowRateState) {
+        if curtime <= 0 {
+            return;
+        }
+        if let Some(last) = self.get_last() {
+            if curtime.div_euclid(Self::SNAPSHOT_INTERVAL_SEC)
+                <= last.time.div_euclid(Self::SNAPSHOT_INTERVAL_SEC)
+            {
+                return;
+            }
+        }
+        self.snapshots[self.next_index as usize] = RateSnapshot {
+            time: curtime,
+            borrow_rate: borrow_rate_state.current_rate,
+            cumulative_interest: borrow_rate_state.cumulative_interest,
+        };
+        self.next_index = ((self.next_index as usize + 1) % Self::NUM_SNAPSHOTS) as u8;
+    }
+
+    /// Returns the latest snapshot taken at or before the given time, if still retained
+    pub fn get_snapshot_at(&self, time: i64) -> Option<&RateSnapshot> {
+        self.snapshots
+            .iter()
+            .filter(|snapshot| snapshot.time != 0 && snapshot.time <= time)
+            .max_by_key(|snapshot| snapshot.time)
+    }
+
+    /// Interest accrued between two retained snapshots (RATE_DECIMALS)
+    ///
+    /// Returns None if the window is not covered by the history or spans an interest
+    /// index rebase.
+    pub fn get_interest_between(&self, from_time: i64, to_time: i64) -> Option<u128> {
+        let from = self.get_snapshot_at(from_time)?;
+        let to = self.get_snapshot_at(to_time)?;
+        to.cumulative_interest.checked_sub(from.cumulative_interest)
+    }
+}
+
+impl ClaimQueue {
+    /// Queues a claim and returns its id
+    pub fn push(&mut self, amount: u64) -> Result<u64> {
+        let claim_id = self.next_claim_id;
+        self.next_claim_id = math::checked_add(self.next_claim_id, 1)?;
+        self.pending_amount = math::checked_add(self.pending_amount, amount)?;
+        Ok(claim_id)
+    }
+
+    /// Removes the claim at the head of the queue
+    pub fn pop(&mut self, claim_id: u64, amount: u64) -> Result<()> {
+        require!(
+            claim_id == self.next_execute_id && claim_id < self.next_claim_id,
+            PerpetualsError::ClaimNotAtQueueHead
+        );
+        self.next_execute_id = math::checked_add(self.next_execute_id, 1)?;
+        self.pending_amount = math::checked_sub(self.pending_amount, amount)?;
+        Ok(())
+    }
+}
+
 impl Custody {
     pub const LEN: usize = 8 + std::mem::size_of::<Custody>();
+    pub const MAX_ENTRY_FEE_TIERS: usize = 4;
+    // settlement prices must be published within this many seconds after expiry
+    pub const SETTLEMENT_PRICE_WINDOW_SEC: i64 = 60;
+    // cumulative interest index value that triggers a rebase, interest of 100 times the
+    // borrowed amount (RATE_DECIMALS). It is reached in about ten years at a 1000% APR,
+    // keeps products of the index with u64 amounts far below u128::MAX, and no position
+    // survives owing a full epoch of interest, so charging positions more than one epoch
+    // behind a full epoch as a lower bound doesn't undercharge a live position
+    pub const INTEREST_REBASE_THRESHOLD: u128 = 100 * Perpetuals::RATE_POWER;
 
     pub fn validate(&self) -> bool {
         (!self.is_virtual || !self.is_stable)
@@ -262,7 +1199,398 @@ impl Custody {
             && self.oracle.validate()
             && self.pricing.validate()
             && self.fees.validate()
+            && self.validate_entry_fee_tiers()
             && self.borrow_rate.validate()
+            && self.expiry_time >= 0
+            && self.exchange_rate.validate()
+            && self.volatility.validate()
+    }
+
+    // checks the params against the program-wide ParamBounds
+    pub fn validate_bounds(&self) -> bool {
+        let pricing = &self.pricing;
+        let fees = &self.fees;
+        let max_fee = [
+            fees.swap_in,
+            fees.swap_out,
+            fees.stable_swap_in,
+            fees.stable_swap_out,
+            fees.add_liquidity,
+            fees.remove_liquidity,
+            fees.open_position,
+            fees.close_position,
+            fees.liquidation,
+            fees.fee_max,
+            fees.fee_optimal,
+            fees.early_close,
+            fees.roll_position,
+            fees.early_remove_liquidity,
+        ]
+        .into_iter()
+        .chain(self.entry_fee_tiers.iter().map(|tier| tier.fee))
+        .max()
+        .unwrap_or(0);
+
+        pricing.trade_spread_long <= ParamBounds::MAX_SPREAD
+            && pricing.trade_spread_short <= ParamBounds::MAX_SPREAD
+            && pricing.swap_spread <= ParamBounds::MAX_SPREAD
+            && pricing.max_initial_leverage <= ParamBounds::MAX_LEVERAGE
+            && pricing.max_leverage <= ParamBounds::MAX_LEVERAGE
+            && pricing.maintenance_leverage <= ParamBounds::MAX_LEVERAGE
+            && max_fee <= ParamBounds::MAX_FEE
+            && fees.ratio_mult <= ParamBounds::MAX_FEE_MULT
+            && fees.utilization_mult <= ParamBounds::MAX_FEE_MULT
+            && (self.volatility.vol_type == VolatilityType::None
+                || (self.volatility.max_vol as u128) * Perpetuals::BPS_POWER
+                    <= (ParamBounds::MAX_VOL_MULT as u128) * (self.volatility.ref_vol as u128))
+    }
+
+    // returns the failed config checks, both the ones enforced by validate and the
+    // extended ones, empty if the config is sound
+    pub fn get_config_violations(&self) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+        let mut check = |ok: bool, violation: ConfigViolation| {
+            if !ok {
+                violations.push(violation);
+            }
+        };
+
+        check(!self.is_virtual || !self.is_stable, ConfigViolation::VirtualStable);
+        check(self.oracle.validate(), ConfigViolation::OracleParams);
+        check(self.pricing.validate(), ConfigViolation::PricingParams);
+        check(self.fees.validate(), ConfigViolation::Fees);
+        check(self.validate_entry_fee_tiers(), ConfigViolation::EntryFeeTiers);
+        check(self.borrow_rate.validate(), ConfigViolation::BorrowRate);
+        check(self.expiry_time >= 0, ConfigViolation::ExpiryTime);
+        check(self.exchange_rate.validate(), ConfigViolation::ExchangeRate);
+        check(self.volatility.validate(), ConfigViolation::Volatility);
+        check(self.validate_bounds(), ConfigViolation::ParamBounds);
+
+        check(
+            self.fees.stable_swap_in <= self.fees.swap_in
+                && self.fees.stable_swap_out <= self.fees.swap_out,
+            ConfigViolation::StableSwapFees,
+        );
+        // maintenance margin in BPS is BPS_POWER / leverage
+        let liquidation_leverage =
+            std::cmp::max(self.pricing.max_leverage, self.pricing.maintenance_leverage) as u128;
+        check(
+            liquidation_leverage == 0
+                || (self.fees.liquidation as u128) * liquidation_leverage
+                    < Perpetuals::BPS_POWER * Perpetuals::BPS_POWER,
+            ConfigViolation::LiquidationFee,
+        );
+        if self.oracle.oracle_type != OracleType::None {
+            check(self.oracle.max_price_error > 0, ConfigViolation::OraclePriceError);
+            check(self.oracle.max_price_age_sec > 0, ConfigViolation::OraclePriceAge);
+        }
+
+        violations
+    }
+
+    // used tiers come first with increasing thresholds, unused tiers are empty
+    fn validate_entry_fee_tiers(&self) -> bool {
+        let mut min_size_usd = 0;
+        let mut unused = false;
+        for tier in &self.entry_fee_tiers {
+            if tier.min_size_usd == 0 {
+                if tier.fee != 0 {
+                    return false;
+                }
+                unused = true;
+            } else if unused
+                || tier.min_size_usd <= min_size_usd
+                || tier.fee as u128 > Perpetuals::BPS_POWER
+            {
+                return false;
+            } else {
+                min_size_usd = tier.min_size_usd;
+            }
+        }
+        true
+    }
+
+    pub fn is_expired(&self, curtime: i64) -> bool {
+        self.expiry_time > 0 && curtime >= self.expiry_time
+    }
+
+    // no open position and no collateral held for positions of other custodies
+    pub fn has_no_positions(&self) -> bool {
+        self.long_positions.open_positions == 0
+            && self.short_positions.open_positions == 0
+            && self.assets.collateral == 0
+    }
+
+    // settles a close-only market once its last position is gone, returns true if the
+    // lifecycle changed
+    pub fn update_lifecycle(&mut self) -> bool {
+        if self.lifecycle == MarketLifecycle::CloseOnly && self.has_no_positions() {
+            msg!("Market settled");
+            self.lifecycle = MarketLifecycle::Settled;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Starts a new stats epoch, counters since the epoch start are reset to zero
+    pub fn start_stats_epoch(&mut self, curtime: i64) {
+        self.stats_epoch = StatsEpoch {
+            start_time: curtime,
+            collected_fees: self.collected_fees,
+            volume_stats: self.volume_stats,
+        };
+    }
+
+    /// Returns fees collected since the stats epoch started
+    pub fn get_epoch_collected_fees(&self) -> Result<FeesStats> {
+        self.collected_fees
+            .checked_sub(&self.stats_epoch.collected_fees)
+    }
+
+    /// Returns volume since the stats epoch started
+    pub fn get_epoch_volume_stats(&self) -> Result<VolumeStats> {
+        self.volume_stats.checked_sub(&self.stats_epoch.volume_stats)
+    }
+
+    // no owned assets and, if unrealized pnl counts towards AUM, no open positions
+    pub fn is_empty_for_aum(&self) -> bool {
+        self.assets.owned == 0
+            && (!self.pricing.use_unrealized_pnl_in_aum
+                || (self.long_positions.open_positions == 0
+                    && self.short_positions.open_positions == 0))
+    }
+
+    // pnl reserve available to pay trader profits, never more than the owned assets
+    pub fn get_pnl_reserve(&self) -> u64 {
+        std::cmp::min(self.pnl_reserve, self.assets.owned)
+    }
+
+    pub fn add_pnl_reserve(&mut self, amount: u64) -> Result<()> {
+        self.pnl_reserve = std::cmp::min(
+            math::checked_add(self.get_pnl_reserve(), amount)?,
+            self.assets.owned,
+        );
+        Ok(())
+    }
+
+    // pays a realized trader profit out of the owned assets, drawing on the pnl reserve
+    // first, returns the part paid out of LP principal
+    pub fn pay_profit(&mut self, amount: u64) -> Result<u64> {
+        let reserve_amount = std::cmp::min(self.get_pnl_reserve(), amount);
+        self.pnl_reserve = math::checked_sub(self.get_pnl_reserve(), reserve_amount)?;
+        self.assets.owned = math::checked_sub(self.assets.owned, amount)?;
+        math::checked_sub(amount, reserve_amount)
+    }
+
+    // once the reserve is used up, trader profits are paid out of LP principal
+    pub fn is_pnl_reserve_depleted(&self) -> bool {
+        self.get_pnl_reserve() == 0
+    }
+
+    pub fn is_lp_fee_decay_enabled(&self) -> bool {
+        self.pricing.lp_fee_decay_period > 0 && self.fees.early_remove_liquidity > 0
+    }
+
+    // spread applied when opening a position on the given side, in BPS
+    pub fn get_entry_spread(&self, side: Side, curtime: i64) -> Result<u64> {
+        let spread = if side == Side::Long {
+            self.pricing.trade_spread_long
+        } else {
+            self.pricing.trade_spread_short
+        };
+        self.get_dynamic_spread(spread, curtime)
+    }
+
+    // spread applied when closing a position on the given side, in BPS
+    pub fn get_exit_spread(&self, side: Side, curtime: i64) -> Result<u64> {
+        let spread = if side == Side::Long {
+            self.pricing.trade_spread_short
+        } else {
+            self.pricing.trade_spread_long
+        };
+        self.get_dynamic_spread(spread, curtime)
+    }
+
+    // spread applied to swaps out of the custody token, in BPS
+    pub fn get_swap_spread(&self, curtime: i64) -> Result<u64> {
+        self.get_dynamic_spread(self.pricing.swap_spread, curtime)
+    }
+
+    // spreads scale with volatility up to ParamBounds::MAX_SPREAD, a static spread
+    // above it is kept
+    fn get_dynamic_spread(&self, spread: u64, curtime: i64) -> Result<u64> {
+        Ok(std::cmp::min(
+            self.scale_by_volatility(spread, curtime)?,
+            std::cmp::max(spread, ParamBounds::MAX_SPREAD),
+        ))
+    }
+
+    // clamped feed volatility in BPS, None without a feed or once the cached value is
+    // older than max_vol_age_sec
+    pub fn get_volatility(&self, curtime: i64) -> Option<u64> {
+        let params = &self.volatility;
+        let state = &self.volatility_state;
+        if params.vol_type == VolatilityType::None
+            || state.last_update == 0
+            || curtime.saturating_sub(state.last_update) > params.max_vol_age_sec as i64
+        {
+            return None;
+        }
+        Some(std::cmp::max(
+            std::cmp::min(state.vol, params.max_vol),
+            params.min_vol,
+        ))
+    }
+
+    // multiplier of the dynamic pricing params in BPS, vol / ref_vol capped at
+    // ParamBounds::MAX_VOL_MULT. Falls back to the static params (BPS_POWER) without a
+    // fresh volatility
+    pub fn get_volatility_mult(&self, curtime: i64) -> Result<u64> {
+        let Some(vol) = self.get_volatility(curtime) else {
+            return Ok(Perpetuals::BPS_POWER as u64);
+        };
+        let mult = math::checked_as_u64(math::checked_div(
+            math::checked_mul(vol as u128, Perpetuals::BPS_POWER)?,
+            self.volatility.ref_vol as u128,
+        )?)?;
+        Ok(std::cmp::min(mult, ParamBounds::MAX_VOL_MULT))
+    }
+
+    // scales a dynamic pricing param by the volatility multiplier
+    fn scale_by_volatility(&self, value: u64, curtime: i64) -> Result<u64> {
+        let mult = self.get_volatility_mult(curtime)?;
+        if mult as u128 == Perpetuals::BPS_POWER {
+            return Ok(value);
+        }
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(value as u128, mult as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)
+    }
+
+    // registers a permissionless oracle update, returns false if it is throttled by the
+    // minimum publish time interval or the per slot cap
+    pub fn register_oracle_update(
+        &mut self,
+        last_publish_time: i64,
+        publish_time: i64,
+        slot: u64,
+    ) -> bool {
+        if publish_time.saturating_sub(last_publish_time)
+            < self.oracle.min_update_interval_sec as i64
+        {
+            return false;
+        }
+        if slot != self.oracle_update_slot {
+            self.oracle_update_slot = slot;
+            self.oracle_slot_updates = 0;
+        }
+        if self.oracle.max_updates_per_slot > 0
+            && self.oracle_slot_updates >= self.oracle.max_updates_per_slot
+        {
+            return false;
+        }
+        self.oracle_slot_updates = self.oracle_slot_updates.saturating_add(1);
+        true
+    }
+
+    // reads the custody oracle price, failures are logged with the custody mint so
+    // clients can tell which custody a typed oracle error refers to
+    pub fn get_oracle_price(
+        &self,
+        oracle_account: &AccountInfo,
+        curtime: i64,
+        use_ema: bool,
+        operation: OracleOperation,
+    ) -> Result<OraclePrice> {
+        OraclePrice::new_from_oracle(oracle_account, &self.oracle, curtime, use_ema, operation)
+            .inspect_err(|_| self.log_oracle_error())
+    }
+
+    pub fn get_oracle_publish_time(&self, oracle_account: &AccountInfo) -> Result<i64> {
+        OraclePrice::get_publish_time(oracle_account, &self.oracle)
+            .inspect_err(|_| self.log_oracle_error())
+    }
+
+    // settlement price of an expired market, scaled to PRICE_DECIMALS. The oracle price
+    // must be published at or after expiry and within SETTLEMENT_PRICE_WINDOW_SEC of it,
+    // so a permissionless crank can't snapshot a price from before expiry or pick a
+    // later one once the window has passed
+    pub fn get_settlement_price(&self, oracle_account: &AccountInfo, curtime: i64) -> Result<u64> {
+        require!(self.is_expired(curtime), PerpetualsError::CustodyNotSettled);
+        require!(
+            self.settlement_price == 0,
+            PerpetualsError::InstructionNotAllowed
+        );
+
+        let publish_time = self.get_oracle_publish_time(oracle_account)?;
+        require!(
+            publish_time >= self.expiry_time
+                && publish_time
+                    <= math::checked_add(self.expiry_time, Self::SETTLEMENT_PRICE_WINDOW_SEC)?,
+            PerpetualsError::SettlementPriceOutsideWindow
+        );
+        let token_price =
+            self.get_oracle_price(oracle_account, publish_time, false, OracleOperation::Close)?;
+        let settlement_price = token_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price;
+        require_gt!(settlement_price, 0, PerpetualsError::InvalidOraclePrice);
+
+        Ok(settlement_price)
+    }
+
+    pub fn get_close_operation(
+        &self,
+        oracle_account: &AccountInfo,
+        curtime: i64,
+    ) -> Result<OracleOperation> {
+        self.oracle
+            .get_close_operation(oracle_account, curtime)
+            .inspect_err(|_| self.log_oracle_error())
+    }
+
+    fn log_oracle_error(&self) {
+        msg!("Error: Oracle price unavailable for custody mint {}", self.mint);
+    }
+
+    // values the custody token at the oracle price of the underlying times the cached
+    // exchange rate, positions and borrow accounting keep using the underlying price
+    pub fn get_fair_price(&self, price: &OraclePrice, curtime: i64) -> Result<OraclePrice> {
+        if self.exchange_rate.rate_type == ExchangeRateType::None {
+            return Ok(*price);
+        }
+        let state = &self.exchange_rate_state;
+        require!(
+            state.rate > 0
+                && (self.exchange_rate.max_rate_age_sec == 0
+                    || math::checked_sub(curtime, state.last_update)?
+                        <= self.exchange_rate.max_rate_age_sec as i64),
+            PerpetualsError::StaleExchangeRate
+        );
+        Ok(OraclePrice {
+            price: math::checked_as_u64(math::checked_div(
+                math::checked_mul(price.price as u128, state.rate as u128)?,
+                Perpetuals::RATE_POWER,
+            )?)?,
+            exponent: price.exponent,
+        })
+    }
+
+    pub fn get_risk_tier(&self, health_factor: u64, liquidatable: bool) -> RiskTier {
+        if liquidatable || health_factor <= self.pricing.risk_danger_health {
+            RiskTier::Danger
+        } else if health_factor <= self.pricing.risk_warning_health {
+            RiskTier::Warning
+        } else {
+            RiskTier::Safe
+        }
+    }
+
+    pub fn is_within_holding_period(&self, open_time: i64, curtime: i64) -> bool {
+        self.pricing.min_holding_period > 0
+            && curtime < open_time.saturating_add(self.pricing.min_holding_period)
     }
 
     pub fn lock_funds(&mut self, amount: u64) -> Result<()> {
@@ -285,34 +1613,117 @@ impl Custody {
             );
         }
 
-        if self.assets.owned < self.assets.locked {
+        // funds reserved for queued claims can't be locked
+        if self.assets.owned
+            < math::checked_add(self.assets.locked, self.claim_queue.pending_amount)?
+        {
             Err(PerpetualsError::CustodyAmountLimit.into())
         } else {
             Ok(())
         }
     }
 
-    pub fn unlock_funds(&mut self, amount: u64) -> Result<()> {
+    // free liquidity not reserved for queued claims, owned - locked - pending claims
+    pub fn get_available_amount(&self) -> Result<u64> {
+        Ok(math::checked_sub(self.assets.owned, self.assets.locked)?
+            .saturating_sub(self.claim_queue.pending_amount))
+    }
+
+    // queues a claim behind the claims already waiting, its tokens stay in owned assets
+    // until it is executed
+    pub fn queue_claim(&mut self, pending_claim: &mut PendingClaim) -> Result<()> {
+        pending_claim.claim_id = self.claim_queue.push(pending_claim.get_total_amount())?;
+        Ok(())
+    }
+
+    // removes the claim at the head of the queue from owned assets, the head can use all
+    // free liquidity (owned - locked). The caller pays out pending_claim.amount
+    pub fn execute_claim(&mut self, pending_claim: &PendingClaim) -> Result<()> {
+        let total_amount = pending_claim.get_total_amount();
+        let mut claim_queue = self.claim_queue;
+        claim_queue.pop(pending_claim.claim_id, total_amount)?;
+        require!(
+            math::checked_sub(self.assets.owned, self.assets.locked)? >= total_amount,
+            PerpetualsError::CustodyAmountLimit
+        );
+        self.claim_queue = claim_queue;
+
+        self.assets.protocol_fees =
+            math::checked_add(self.assets.protocol_fees, pending_claim.protocol_fee)?;
+        self.assets.owned = math::checked_sub(self.assets.owned, total_amount)?;
+        Ok(())
+    }
+
+    // returns the amount actually unlocked, locked funds never go below zero
+    pub fn unlock_funds(&mut self, amount: u64) -> Result<u64> {
         require!(!self.is_virtual, PerpetualsError::InvalidCollateralCustody);
 
-        if amount > self.assets.locked {
-            self.assets.locked = 0;
+        let unlocked = std::cmp::min(amount, self.assets.locked);
+        self.assets.locked = math::checked_sub(self.assets.locked, unlocked)?;
+
+        Ok(unlocked)
+    }
+
+    pub fn get_locked_breakdown(&self) -> Result<LockedBreakdown> {
+        let utilization = if self.assets.owned > 0 {
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(self.assets.locked as u128, Perpetuals::BPS_POWER)?,
+                self.assets.owned as u128,
+            )?)?
         } else {
-            self.assets.locked = math::checked_sub(self.assets.locked, amount)?;
-        }
+            0
+        };
 
-        Ok(())
+        Ok(LockedBreakdown {
+            owned: self.assets.owned,
+            locked: self.assets.locked,
+            available: self
+                .assets
+                .owned
+                .saturating_sub(self.assets.locked)
+                .saturating_sub(self.claim_queue.pending_amount),
+            collateral: self.assets.collateral,
+            protocol_fees: self.assets.protocol_fees,
+            utilization,
+            max_utilization: self.pricing.max_utilization,
+        })
     }
 
-    pub fn get_locked_amount(&self, size: u64, side: Side) -> Result<u64> {
+    // funds locked for the position payoff: the size scaled by max_payoff_mult, plus a
+    // convexity reserve for power > 1 positions
+    pub fn get_locked_amount(&self, size: u64, side: Side, power: u8, curtime: i64) -> Result<u64> {
         let max_payoff_mult = if side == Side::Short {
             std::cmp::min(Perpetuals::BPS_POWER, self.pricing.max_payoff_mult as u128)
         } else {
             self.pricing.max_payoff_mult as u128
         };
-        math::checked_as_u64(math::checked_div(
+        let locked_amount = math::checked_div(
             math::checked_mul(size as u128, max_payoff_mult)?,
             Perpetuals::BPS_POWER,
+        )?;
+        let reserve = math::checked_div(
+            math::checked_mul(locked_amount, self.get_convexity_reserve(power, curtime)? as u128)?,
+            Perpetuals::BPS_POWER,
+        )?;
+        math::checked_as_u64(math::checked_add(locked_amount, reserve)?)
+    }
+
+    // convexity reserve of a power in BPS of the locked amount: the second order term
+    // of the power payoff for a move of convexity_vol, power * (power - 1) / 2 * vol^2.
+    // convexity_vol scales with the volatility feed, up to 100%. Zero for linear positions
+    pub fn get_convexity_reserve(&self, power: u8, curtime: i64) -> Result<u64> {
+        if power <= 1 || self.pricing.convexity_vol == 0 {
+            return Ok(0);
+        }
+        let vol = std::cmp::min(
+            self.scale_by_volatility(self.pricing.convexity_vol, curtime)? as u128,
+            Perpetuals::BPS_POWER,
+        );
+        let variance = math::checked_div(math::checked_mul(vol, vol)?, Perpetuals::BPS_POWER)?;
+        let power = power as u128;
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(variance, power * (power - 1))?,
+            2,
         )?)
     }
 
@@ -321,13 +1732,10 @@ impl Custody {
             return Ok(0);
         }
 
-        let cumulative_interest = self.get_cumulative_interest(curtime)?;
-
-        let position_interest = if cumulative_interest > position.cumulative_interest_snapshot {
-            math::checked_sub(cumulative_interest, position.cumulative_interest_snapshot)?
-        } else {
+        let position_interest = self.get_position_interest(position, curtime)?;
+        if position_interest == 0 {
             return Ok(0);
-        };
+        }
 
         math::checked_as_u64(math::checked_div(
             math::checked_mul(position_interest, position.borrow_size_usd as u128)?,
@@ -335,7 +1743,71 @@ impl Custody {
         )?)
     }
 
+    // interest index accrued since the position snapshot (RATE_DECIMALS), a position
+    // more than one epoch behind has accrued at least a full epoch of interest and is
+    // charged that lower bound
+    pub fn get_position_interest(&self, position: &Position, curtime: i64) -> Result<u128> {
+        let cumulative_interest = self.get_cumulative_interest(curtime)?;
+        match self.interest_epoch.id.checked_sub(position.interest_epoch) {
+            Some(0) => {
+                Ok(cumulative_interest.saturating_sub(position.cumulative_interest_snapshot))
+            }
+            Some(1) => math::checked_add(
+                self.interest_epoch
+                    .prev_end_interest
+                    .saturating_sub(position.cumulative_interest_snapshot),
+                cumulative_interest,
+            ),
+            Some(_) => {
+                math::checked_add(self.interest_epoch.prev_end_interest, cumulative_interest)
+            }
+            None => err!(PerpetualsError::InvalidPositionState),
+        }
+    }
+
+    // snapshots the interest index into a position that starts borrowing now
+    pub fn snapshot_interest(&self, position: &mut Position, curtime: i64) -> Result<()> {
+        position.cumulative_interest_snapshot = self.get_cumulative_interest(curtime)?;
+        position.interest_epoch = self.interest_epoch.id;
+        Ok(())
+    }
+
+    // starts a new interest epoch once the index reaches the rebase threshold, interest
+    // of the collective positions is settled into their stats first so their snapshots
+    // can restart from zero
+    pub fn rebase_interest_index(&mut self, curtime: i64) -> Result<bool> {
+        if self.borrow_rate_state.cumulative_interest < Self::INTEREST_REBASE_THRESHOLD {
+            return Ok(false);
+        }
+
+        for side in [Side::Long, Side::Short] {
+            let collective_position = self.get_collective_position(side)?;
+            let interest_usd = self.get_interest_amount_usd(&collective_position, curtime)?;
+            let stats = if side == Side::Long {
+                &mut self.long_positions
+            } else {
+                &mut self.short_positions
+            };
+            stats.cumulative_interest_usd =
+                math::checked_add(stats.cumulative_interest_usd, interest_usd)?;
+            stats.cumulative_interest_snapshot = 0;
+        }
+
+        self.interest_epoch = InterestEpoch {
+            id: math::checked_add(self.interest_epoch.id, 1)?,
+            prev_end_interest: self.borrow_rate_state.cumulative_interest,
+        };
+        self.borrow_rate_state.cumulative_interest = 0;
+        Ok(true)
+    }
+
     pub fn get_cumulative_interest(&self, curtime: i64) -> Result<u128> {
+        // no interest accrues after expiry
+        let curtime = if self.expiry_time > 0 {
+            std::cmp::min(curtime, self.expiry_time)
+        } else {
+            curtime
+        };
         if curtime > self.borrow_rate_state.last_update {
             let cumulative_interest = math::checked_ceil_div(
                 math::checked_mul(
@@ -363,6 +1835,7 @@ impl Custody {
             self.borrow_rate_state.current_rate = 0;
             self.borrow_rate_state.last_update =
                 std::cmp::max(curtime, self.borrow_rate_state.last_update);
+            self.rate_history.record(curtime, &self.borrow_rate_state);
             return Ok(());
         }
 
@@ -370,6 +1843,7 @@ impl Custody {
             // compute interest accumulated since previous update
             self.borrow_rate_state.cumulative_interest = self.get_cumulative_interest(curtime)?;
             self.borrow_rate_state.last_update = curtime;
+            self.rebase_interest_index(curtime)?;
         }
 
         // get current utilization
@@ -378,24 +1852,26 @@ impl Custody {
             self.assets.owned as u128,
         )?;
 
-        // compute and save new borrow rate
+        // compute and save new borrow rate, the slopes scale with volatility
+        let slope1 = self.scale_by_volatility(self.borrow_rate.slope1, curtime)? as u128;
+        let slope2 = self.scale_by_volatility(self.borrow_rate.slope2, curtime)? as u128;
         let hourly_rate = if current_utilization < (self.borrow_rate.optimal_utilization as u128)
             || (self.borrow_rate.optimal_utilization as u128) >= Perpetuals::RATE_POWER
         {
             math::checked_div(
-                math::checked_mul(current_utilization, self.borrow_rate.slope1 as u128)?,
+                math::checked_mul(current_utilization, slope1)?,
                 self.borrow_rate.optimal_utilization as u128,
             )?
         } else {
             math::checked_add(
-                self.borrow_rate.slope1 as u128,
+                slope1,
                 math::checked_div(
                     math::checked_mul(
                         math::checked_sub(
                             current_utilization,
                             self.borrow_rate.optimal_utilization as u128,
                         )?,
-                        self.borrow_rate.slope2 as u128,
+                        slope2,
                     )?,
                     Perpetuals::RATE_POWER - self.borrow_rate.optimal_utilization as u128,
                 )?,
@@ -407,6 +1883,7 @@ impl Custody {
         )?;
 
         self.borrow_rate_state.current_rate = hourly_rate;
+        self.rate_history.record(curtime, &self.borrow_rate_state);
 
         Ok(())
     }
@@ -432,6 +1909,7 @@ impl Custody {
                 borrow_size_usd: stats.borrow_size_usd,
                 unrealized_loss_usd: stats.cumulative_interest_usd,
                 cumulative_interest_snapshot: stats.cumulative_interest_snapshot,
+                interest_epoch: self.interest_epoch.id,
                 locked_amount: stats.locked_amount,
                 ..Position::default()
             })
@@ -488,16 +1966,22 @@ impl Custody {
 
         // check limits
         if self.pricing.max_position_locked_usd > 0 {
-            let locked_amount_usd =
-                token_price.get_asset_amount_usd(position.locked_amount, self.decimals)?;
+            let locked_amount_usd = token_price.get_asset_amount_usd(
+                position.locked_amount,
+                self.decimals,
+                RoundingDirection::Up,
+            )?;
             require!(
                 locked_amount_usd <= self.pricing.max_position_locked_usd,
                 PerpetualsError::PositionAmountLimit
             );
         }
         if self.pricing.max_total_locked_usd > 0 {
-            let locked_amount_usd =
-                token_price.get_asset_amount_usd(stats.locked_amount, self.decimals)?;
+            let locked_amount_usd = token_price.get_asset_amount_usd(
+                stats.locked_amount,
+                self.decimals,
+                RoundingDirection::Up,
+            )?;
             require!(
                 locked_amount_usd <= self.pricing.max_total_locked_usd,
                 PerpetualsError::CustodyAmountLimit
@@ -620,9 +2104,30 @@ impl DeprecatedCustody {
     pub const LEN: usize = 8 + std::mem::size_of::<DeprecatedCustody>();
 }
 
+impl CustodyV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<CustodyV0>();
+
+    /// Loads a custody stored in the deprecated layout or in the original layout, told
+    /// apart by the discriminator and the data length
+    pub fn load(data: &[u8]) -> Option<CustodyV0> {
+        let discriminator = data.get(..8)?;
+        if discriminator == DeprecatedCustody::DISCRIMINATOR
+            && data.len() == DeprecatedCustody::LEN
+        {
+            DeprecatedCustody::deserialize(&mut &data[8..])
+                .ok()
+                .map(CustodyV0::from)
+        } else if discriminator == Custody::DISCRIMINATOR && data.len() == CustodyV0::LEN {
+            CustodyV0::deserialize(&mut &data[8..]).ok()
+        } else {
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::*;
+    use {super::*, crate::state::pool::Pool};
 
     fn get_fixture() -> Custody {
         let assets = Assets {
@@ -740,4 +2245,763 @@ mod test {
         custody.update_borrow_rate(3600).unwrap();
         assert_eq!(custody.borrow_rate_state.current_rate, 199400);
     }
+
+    #[test]
+    fn test_cumulative_interest_after_expiry() {
+        let mut custody = get_fixture();
+        custody.expiry_time = 5400;
+        assert!(!custody.is_expired(5399));
+        assert!(custody.is_expired(5400));
+
+        custody.update_borrow_rate(3600).unwrap();
+        assert_eq!(custody.get_cumulative_interest(5400).unwrap(), 25000);
+        assert_eq!(custody.get_cumulative_interest(7200).unwrap(), 25000);
+
+        custody.update_borrow_rate(7200).unwrap();
+        assert_eq!(custody.borrow_rate_state.cumulative_interest, 25000);
+        assert_eq!(custody.get_cumulative_interest(10800).unwrap(), 25000);
+    }
+
+    #[test]
+    fn test_rate_history() {
+        let mut custody = get_fixture();
+        assert!(custody.rate_history.get_last().is_none());
+
+        custody.update_borrow_rate(3600).unwrap();
+        custody.update_borrow_rate(5400).unwrap();
+        custody.update_borrow_rate(7200).unwrap();
+        // one snapshot per hour
+        assert_eq!(custody.rate_history.next_index, 2);
+        assert_eq!(
+            custody.rate_history.get_last(),
+            Some(&RateSnapshot {
+                time: 7200,
+                borrow_rate: 50000,
+                cumulative_interest: 50000
+            })
+        );
+        assert_eq!(
+            custody.rate_history.get_snapshot_at(7199).unwrap().time,
+            3600
+        );
+        assert!(custody.rate_history.get_snapshot_at(3599).is_none());
+        assert_eq!(
+            custody.rate_history.get_interest_between(3600, 7200),
+            Some(50000)
+        );
+
+        // the oldest snapshots are overwritten once the buffer is full
+        for hour in 3..=(RateHistory::NUM_SNAPSHOTS as i64 + 1) {
+            custody.update_borrow_rate(hour * 3600).unwrap();
+        }
+        assert_eq!(custody.rate_history.next_index, 1);
+        assert!(custody.rate_history.get_snapshot_at(7199).is_none());
+        assert_eq!(
+            custody.rate_history.get_snapshot_at(7200).unwrap().time,
+            7200
+        );
+        assert_eq!(
+            custody.rate_history.get_interest_between(7200, 10800),
+            Some(50000)
+        );
+    }
+
+    #[test]
+    fn test_interest_rebase() {
+        // 1000% APR, the index reaches the rebase threshold after about ten years
+        let hourly_rate: u64 = 10 * Perpetuals::RATE_POWER as u64 / (24 * 365);
+        let mut custody = get_fixture();
+        custody.assets.locked = 0;
+        custody.borrow_rate.base_rate = hourly_rate;
+        custody.long_positions.open_positions = 1;
+        custody.long_positions.borrow_size_usd = 1_000_000;
+
+        let mut position = Position {
+            borrow_size_usd: 1_000_000,
+            ..Position::default()
+        };
+        let mut recent = position.clone();
+
+        // eleven years of hourly updates, position interest grows by exactly one hourly
+        // rate every hour, also across the rebase
+        let hours: i64 = 24 * 365 * 11;
+        let mut rebase_hour = 0;
+        for hour in 1..=hours {
+            let epoch = custody.interest_epoch.id;
+            custody.update_borrow_rate(hour * 3600).unwrap();
+            assert!(
+                custody.borrow_rate_state.cumulative_interest < Custody::INTEREST_REBASE_THRESHOLD
+            );
+            if custody.interest_epoch.id != epoch {
+                rebase_hour = hour;
+            }
+            if hour == 1000 {
+                custody
+                    .snapshot_interest(&mut position, hour * 3600)
+                    .unwrap();
+            }
+            if hour > 1000 {
+                assert_eq!(
+                    custody
+                        .get_position_interest(&position, hour * 3600)
+                        .unwrap(),
+                    (hour - 1000) as u128 * hourly_rate as u128
+                );
+            }
+            if hour == 90_000 {
+                custody.snapshot_interest(&mut recent, hour * 3600).unwrap();
+            }
+        }
+        let curtime = hours * 3600;
+        assert_eq!(custody.interest_epoch.id, 1);
+        assert_eq!(rebase_hour, 87_602);
+        assert_eq!(position.interest_epoch, 0);
+        assert_eq!(recent.interest_epoch, 1);
+
+        // interest in USD is continuous for positions from both epochs
+        assert_eq!(
+            custody.get_interest_amount_usd(&position, curtime).unwrap(),
+            ((hours - 1000) as u128 * hourly_rate as u128 * 1_000_000 / Perpetuals::RATE_POWER)
+                as u64
+        );
+        assert_eq!(
+            custody.get_position_interest(&recent, curtime).unwrap(),
+            (hours - 90_000) as u128 * hourly_rate as u128
+        );
+
+        // positions more than one epoch behind are charged at least a full epoch
+        let mut stale = position.clone();
+        stale.interest_epoch = 0;
+        custody.interest_epoch.id = 2;
+        assert!(
+            custody.get_position_interest(&stale, curtime).unwrap()
+                >= Custody::INTEREST_REBASE_THRESHOLD
+        );
+        custody.interest_epoch.id = 1;
+
+        // interest of the collective position was settled at the rebase
+        let collective_position = custody.get_collective_position(Side::Long).unwrap();
+        let total_interest_usd = custody.long_positions.cumulative_interest_usd
+            + custody
+                .get_interest_amount_usd(&collective_position, curtime)
+                .unwrap();
+        let expected_interest_usd = ((hours - 1) as u128 * hourly_rate as u128 * 1_000_000
+            / Perpetuals::RATE_POWER) as u64;
+        assert!(total_interest_usd <= expected_interest_usd);
+        assert!(expected_interest_usd - total_interest_usd <= 1);
+
+        // snapshots from a later epoch are invalid
+        recent.interest_epoch = 2;
+        assert!(custody.get_position_interest(&recent, curtime).is_err());
+    }
+
+    #[test]
+    fn test_claim_queue() {
+        let mut queue = ClaimQueue::default();
+        assert_eq!(queue.push(100).unwrap(), 0);
+        assert_eq!(queue.push(50).unwrap(), 1);
+        assert_eq!(queue.pending_amount, 150);
+
+        // claims execute in FIFO order
+        assert_eq!(
+            queue.pop(1, 50).unwrap_err(),
+            PerpetualsError::ClaimNotAtQueueHead.into()
+        );
+        queue.pop(0, 100).unwrap();
+        queue.pop(1, 50).unwrap();
+        assert_eq!(queue.pending_amount, 0);
+        assert_eq!(queue.next_execute_id, queue.next_claim_id);
+        assert!(queue.pop(2, 0).is_err());
+
+        // queued claims are reserved from free liquidity
+        let mut custody = get_fixture();
+        custody.claim_queue.push(300).unwrap();
+        assert_eq!(custody.get_available_amount().unwrap(), 200);
+        assert_eq!(
+            custody.lock_funds(201).unwrap_err(),
+            PerpetualsError::CustodyAmountLimit.into()
+        );
+        custody.assets.locked = 500;
+        custody.lock_funds(200).unwrap();
+        assert_eq!(custody.get_available_amount().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pending_claims() {
+        // a remove_liquidity withdrawal with its protocol fee, then a close_position payout
+        let mut custody = get_fixture();
+        let mut withdrawal = PendingClaim {
+            amount: 300,
+            protocol_fee: 20,
+            ..PendingClaim::default()
+        };
+        let mut payout = PendingClaim {
+            amount: 150,
+            ..PendingClaim::default()
+        };
+        custody.queue_claim(&mut withdrawal).unwrap();
+        custody.queue_claim(&mut payout).unwrap();
+        assert_eq!((withdrawal.claim_id, payout.claim_id), (0, 1));
+        assert_eq!(custody.claim_queue.pending_amount, 470);
+        assert_eq!(custody.assets.owned, 1000);
+
+        // later requests queue behind the claims even if owned - locked covers them
+        assert_eq!(custody.get_available_amount().unwrap(), 30);
+        let pool = Pool::default();
+        assert!(!pool.check_available_amount(31, &custody).unwrap());
+        assert!(pool.check_available_amount(30, &custody).unwrap());
+
+        // claims execute in FIFO order
+        assert_eq!(
+            custody.execute_claim(&payout),
+            Err(PerpetualsError::ClaimNotAtQueueHead.into())
+        );
+
+        // the head of the queue waits for free liquidity, owned - locked
+        custody.assets.locked = 681;
+        assert_eq!(
+            custody.execute_claim(&withdrawal),
+            Err(PerpetualsError::CustodyAmountLimit.into())
+        );
+        assert_eq!(custody.claim_queue.next_execute_id, 0);
+        custody.assets.locked = 680;
+        custody.execute_claim(&withdrawal).unwrap();
+        assert_eq!(custody.assets.owned, 680);
+        assert_eq!(custody.assets.protocol_fees, 20);
+        assert_eq!(custody.claim_queue.pending_amount, 150);
+
+        // a claim executes once
+        assert_eq!(
+            custody.execute_claim(&withdrawal),
+            Err(PerpetualsError::ClaimNotAtQueueHead.into())
+        );
+
+        custody.assets.locked = 530;
+        custody.execute_claim(&payout).unwrap();
+        assert_eq!(custody.assets.owned, 530);
+        assert_eq!(custody.assets.protocol_fees, 20);
+        assert_eq!(custody.claim_queue.pending_amount, 0);
+        assert_eq!(
+            custody.claim_queue.next_execute_id,
+            custody.claim_queue.next_claim_id
+        );
+    }
+
+    #[test]
+    fn test_stats_epoch() {
+        let mut custody = get_fixture();
+        custody.collected_fees.swap_usd = u64::MAX as u128;
+        custody.volume_stats.swap_usd = 1000;
+        custody.start_stats_epoch(3600);
+        assert_eq!(custody.get_epoch_collected_fees().unwrap(), FeesStats::default());
+
+        // lifetime counters don't wrap past u64
+        custody.collected_fees.swap_usd =
+            math::checked_add(custody.collected_fees.swap_usd, 10).unwrap();
+        custody.volume_stats.swap_usd = 1500;
+        assert_eq!(custody.collected_fees.swap_usd, u64::MAX as u128 + 10);
+        assert_eq!(custody.get_epoch_collected_fees().unwrap().swap_usd, 10);
+        assert_eq!(custody.get_epoch_volume_stats().unwrap().swap_usd, 500);
+        assert_eq!(custody.stats_epoch.start_time, 3600);
+    }
+
+    #[test]
+    fn test_protocol_share_override() {
+        let mut fees = Fees {
+            protocol_share: 1000,
+            liquidation_protocol_share: 10000,
+            ..Default::default()
+        };
+        assert_eq!(fees.get_protocol_share(FeeType::Swap), 1000);
+        assert_eq!(fees.get_protocol_share(FeeType::Liquidation), 10000);
+
+        let collected_fees = FeesStats {
+            swap_usd: 1000,
+            open_position_usd: 500,
+            close_position_usd: 500,
+            liquidation_usd: 1000,
+            ..Default::default()
+        };
+        // liquidation fees go entirely to the protocol
+        assert_eq!(fees.get_lp_fees_usd(&collected_fees).unwrap(), 1800);
+
+        fees.position_protocol_share = 5000;
+        assert_eq!(fees.get_lp_fees_usd(&collected_fees).unwrap(), 1400);
+        assert!(fees.validate());
+
+        fees.swap_protocol_share = 10001;
+        assert!(!fees.validate());
+    }
+
+    #[test]
+    fn test_oracle_heartbeat() {
+        let mut oracle = OracleParams {
+            max_price_age_sec: 60,
+            ..Default::default()
+        };
+        assert_eq!(oracle.get_heartbeat_limit(), 0);
+        assert!(!oracle.is_heartbeat_missed(0, 1_000_000));
+
+        oracle.heartbeat_mult = 5;
+        assert_eq!(oracle.get_heartbeat_limit(), 300);
+        assert!(!oracle.is_heartbeat_missed(1_000, 1_300));
+        assert!(oracle.is_heartbeat_missed(1_000, 1_301));
+    }
+
+    #[test]
+    fn test_close_grace_price_age() {
+        let mut oracle = OracleParams {
+            max_price_age_sec: 60,
+            ..Default::default()
+        };
+        assert_eq!(oracle.get_max_price_age(OracleOperation::CloseGrace), 60);
+
+        oracle.close_grace_mult = 3;
+        assert_eq!(oracle.get_max_price_age(OracleOperation::CloseGrace), 180);
+        oracle.max_price_age_close_sec = 100;
+        assert_eq!(oracle.get_max_price_age(OracleOperation::Close), 100);
+        assert_eq!(oracle.get_max_price_age(OracleOperation::CloseGrace), 300);
+        assert_eq!(oracle.get_max_price_age(OracleOperation::Open), 60);
+    }
+
+    #[test]
+    fn test_get_fair_price() {
+        let mut custody = get_fixture();
+        let price = OraclePrice::new(150_000, -3);
+        assert_eq!(custody.get_fair_price(&price, 100).unwrap(), price);
+
+        custody.exchange_rate = ExchangeRateParams {
+            rate_type: ExchangeRateType::SplStakePool,
+            rate_account: Pubkey::new_unique(),
+            max_rate_age_sec: 60,
+        };
+        assert!(custody.get_fair_price(&price, 100).is_err());
+
+        custody.exchange_rate_state = ExchangeRateState {
+            rate: 1_250_000_000,
+            last_update: 100,
+        };
+        assert_eq!(
+            custody.get_fair_price(&price, 160).unwrap(),
+            OraclePrice::new(187_500, -3)
+        );
+        assert!(custody.get_fair_price(&price, 161).is_err());
+    }
+
+    #[test]
+    fn test_pnl_reserve() {
+        let mut custody = get_fixture();
+        assert!(custody.is_pnl_reserve_depleted());
+
+        custody.add_pnl_reserve(300).unwrap();
+        assert_eq!(custody.get_pnl_reserve(), 300);
+
+        // profit covered by the reserve
+        assert_eq!(custody.pay_profit(200).unwrap(), 0);
+        assert_eq!(custody.get_pnl_reserve(), 100);
+        assert_eq!(custody.assets.owned, 800);
+
+        // profit exceeding the reserve is paid out of LP principal
+        assert_eq!(custody.pay_profit(250).unwrap(), 150);
+        assert!(custody.is_pnl_reserve_depleted());
+        assert_eq!(custody.assets.owned, 550);
+
+        // the reserve never exceeds owned assets
+        custody.add_pnl_reserve(1000).unwrap();
+        assert_eq!(custody.get_pnl_reserve(), 550);
+        assert!(custody.pay_profit(600).is_err());
+    }
+
+    #[test]
+    fn test_oracle_update_throttle() {
+        let mut custody = get_fixture();
+        assert!(custody.register_oracle_update(100, 101, 1));
+        assert!(custody.register_oracle_update(101, 102, 1));
+
+        custody.oracle.min_update_interval_sec = 5;
+        custody.oracle.max_updates_per_slot = 2;
+        assert!(!custody.register_oracle_update(100, 104, 2));
+        assert!(custody.register_oracle_update(100, 105, 2));
+        assert!(custody.register_oracle_update(105, 110, 2));
+        assert!(!custody.register_oracle_update(110, 115, 2));
+        assert_eq!(custody.oracle_slot_updates, 2);
+
+        // the cap resets in a new slot
+        assert!(custody.register_oracle_update(110, 115, 3));
+        assert_eq!(custody.oracle_update_slot, 3);
+        assert_eq!(custody.oracle_slot_updates, 1);
+    }
+
+    #[test]
+    fn test_entry_fee_tiers_validation() {
+        let mut custody = get_fixture();
+        assert!(custody.validate_entry_fee_tiers());
+
+        custody.entry_fee_tiers[0] = EntryFeeTier {
+            min_size_usd: 10_000,
+            fee: 10,
+        };
+        custody.entry_fee_tiers[1] = EntryFeeTier {
+            min_size_usd: 100_000,
+            fee: 20,
+        };
+        assert!(custody.validate_entry_fee_tiers());
+
+        // thresholds must increase
+        custody.entry_fee_tiers[1].min_size_usd = 10_000;
+        assert!(!custody.validate_entry_fee_tiers());
+        custody.entry_fee_tiers[1].min_size_usd = 100_000;
+
+        // unused tiers come last and charge nothing
+        custody.entry_fee_tiers[2].fee = 5;
+        assert!(!custody.validate_entry_fee_tiers());
+        custody.entry_fee_tiers[2].fee = 0;
+        custody.entry_fee_tiers[3] = custody.entry_fee_tiers[1];
+        custody.entry_fee_tiers[3].min_size_usd = 200_000;
+        assert!(!custody.validate_entry_fee_tiers());
+    }
+
+    #[test]
+    fn test_market_lifecycle() {
+        use MarketLifecycle::*;
+
+        assert!(Active.allows_open() && Active.allows_close() && Active.allows_lp_exit());
+        assert!(!CloseOnly.allows_open() && CloseOnly.allows_close());
+        assert!(!Settled.allows_open() && !Settled.allows_close() && Settled.allows_lp_exit());
+        assert!(!Retired.allows_open() && !Retired.allows_lp_exit());
+
+        assert!(Active.can_transition_to(CloseOnly));
+        assert!(CloseOnly.can_transition_to(Active));
+        assert!(CloseOnly.can_transition_to(Settled));
+        assert!(Settled.can_transition_to(Retired));
+        assert!(!Active.can_transition_to(Settled));
+        assert!(!Settled.can_transition_to(Active));
+        assert!(!Retired.can_transition_to(Active));
+
+        let mut custody = Custody::default();
+        custody.long_positions.open_positions = 1;
+        assert!(!custody.update_lifecycle());
+        custody.lifecycle = CloseOnly;
+        assert!(!custody.update_lifecycle());
+        custody.long_positions.open_positions = 0;
+        assert!(custody.update_lifecycle());
+        assert_eq!(custody.lifecycle, Settled);
+        assert!(!custody.update_lifecycle());
+    }
+
+    #[test]
+    fn test_settlement_price() {
+        let oracle_key = Pubkey::new_unique();
+        let mut custody = get_fixture();
+        custody.expiry_time = 1_000;
+        custody.oracle = OracleParams {
+            oracle_account: oracle_key,
+            oracle_type: OracleType::Custom,
+            max_price_error: 100,
+            max_price_age_sec: 10,
+            ..OracleParams::default()
+        };
+        let get_oracle_account = |publish_time: i64| -> AccountInfo<'static> {
+            let mut oracle = CustomOracle::default();
+            oracle.set(2_500, -2, 0, publish_time, 0).unwrap();
+            let mut data = vec![];
+            oracle.try_serialize(&mut data).unwrap();
+            AccountInfo::new(
+                Box::leak(Box::new(oracle_key)),
+                false,
+                false,
+                Box::leak(Box::new(1_000_000)),
+                Box::leak(data.into_boxed_slice()),
+                Box::leak(Box::new(crate::ID)),
+                false,
+                0,
+            )
+        };
+
+        // the market must have expired
+        assert_eq!(
+            custody.get_settlement_price(&get_oracle_account(1_000), 999),
+            Err(PerpetualsError::CustodyNotSettled.into())
+        );
+
+        // prices published before expiry or after the window are rejected, whenever the
+        // crank runs
+        for (publish_time, curtime) in [(999, 1_000), (990, 1_005), (1_061, 1_061)] {
+            assert_eq!(
+                custody.get_settlement_price(&get_oracle_account(publish_time), curtime),
+                Err(PerpetualsError::SettlementPriceOutsideWindow.into())
+            );
+        }
+
+        // prices published from expiry to the end of the window are accepted, staleness
+        // is checked as of the publish time so a late crank still snapshots them
+        for (publish_time, curtime) in [(1_000, 1_000), (1_060, 1_060), (1_030, 5_000)] {
+            assert_eq!(
+                custody
+                    .get_settlement_price(&get_oracle_account(publish_time), curtime)
+                    .unwrap(),
+                25_000_000
+            );
+        }
+
+        // the settlement price is snapshotted once
+        custody.settlement_price = 25_000_000;
+        assert_eq!(
+            custody.get_settlement_price(&get_oracle_account(1_000), 1_000),
+            Err(PerpetualsError::InstructionNotAllowed.into())
+        );
+    }
+
+    #[test]
+    fn test_locked_breakdown() {
+        let mut custody = get_fixture();
+        custody.assets.collateral = 200;
+        custody.assets.protocol_fees = 30;
+
+        let breakdown = custody.get_locked_breakdown().unwrap();
+        assert_eq!(breakdown.owned, 1000);
+        assert_eq!(breakdown.locked, 500);
+        assert_eq!(breakdown.available, 500);
+        assert_eq!(breakdown.collateral, 200);
+        assert_eq!(breakdown.protocol_fees, 30);
+        assert_eq!(breakdown.utilization, 5_000);
+
+        // unlocking more than locked is clamped
+        assert_eq!(custody.unlock_funds(300).unwrap(), 300);
+        assert_eq!(custody.unlock_funds(300).unwrap(), 200);
+        assert_eq!(custody.assets.locked, 0);
+        assert_eq!(custody.get_locked_breakdown().unwrap().utilization, 0);
+    }
+
+    #[test]
+    fn test_config_violations() {
+        let mut custody = get_fixture();
+        custody.token_account = Pubkey::new_unique();
+        custody.mint = Pubkey::new_unique();
+        custody.pricing.min_initial_leverage = 10_000;
+        custody.pricing.max_initial_leverage = 100_000;
+        custody.pricing.max_leverage = 100_000;
+        assert!(custody.validate());
+        assert!(custody.get_config_violations().is_empty());
+
+        // enforced checks match validate
+        custody.fees.fee_optimal = custody.fees.fee_max + 1;
+        assert!(!custody.validate());
+        assert_eq!(custody.get_config_violations(), vec![ConfigViolation::Fees]);
+        custody.fees.fee_optimal = 0;
+
+        // extended checks don't fail validate
+        custody.fees.stable_swap_in = custody.fees.swap_in + 1;
+        custody.fees.liquidation = 10_000 * 10_000 / custody.pricing.max_leverage;
+        assert!(custody.validate());
+        assert_eq!(
+            custody.get_config_violations(),
+            vec![ConfigViolation::StableSwapFees, ConfigViolation::LiquidationFee]
+        );
+    }
+
+    #[test]
+    fn test_max_power() {
+        let mut pricing = PricingParams::default();
+        assert_eq!(pricing.get_max_power(), PricingParams::MAX_POWER);
+        assert!(pricing.check_power(5).is_ok());
+        assert!(pricing.check_power(0).is_err());
+        assert!(pricing.check_power(6).is_err());
+
+        pricing.max_power = 2;
+        assert!(pricing.check_power(2).is_ok());
+        assert_eq!(
+            pricing.check_power(3),
+            Err(PerpetualsError::MaxPowerExceeded.into())
+        );
+
+        pricing.max_power = PricingParams::MAX_POWER + 1;
+        assert!(!pricing.validate());
+    }
+
+    #[test]
+    fn test_convexity_reserve() {
+        let mut custody = get_fixture();
+        custody.pricing.max_payoff_mult = 10_000;
+        let size = 1_000_000_000;
+
+        // no reserve until a volatility is set
+        assert_eq!(custody.get_locked_amount(size, Side::Long, 3, 0).unwrap(), size);
+
+        // 10% vol: 1% variance, x3 for power 3, x10 for power 5
+        custody.pricing.convexity_vol = 1_000;
+        assert_eq!(custody.get_convexity_reserve(1, 0).unwrap(), 0);
+        assert_eq!(custody.get_convexity_reserve(3, 0).unwrap(), 300);
+        assert_eq!(custody.get_convexity_reserve(5, 0).unwrap(), 1_000);
+
+        let power_1_locked = custody.get_locked_amount(size, Side::Long, 1, 0).unwrap();
+        let power_3_locked = custody.get_locked_amount(size, Side::Long, 3, 0).unwrap();
+        assert_eq!(power_1_locked, size);
+        assert_eq!(power_3_locked, size * 103 / 100);
+        assert_eq!(
+            custody.get_locked_amount(size, Side::Short, 3, 0).unwrap(),
+            power_3_locked
+        );
+
+        // the reserve grows with the volatility
+        custody.pricing.convexity_vol = 2_000;
+        assert_eq!(
+            custody.get_locked_amount(size, Side::Long, 3, 0).unwrap(),
+            size * 112 / 100
+        );
+    }
+
+    #[test]
+    fn test_volatility() {
+        let mut custody = get_fixture();
+        custody.pricing.trade_spread_long = 100;
+        custody.pricing.trade_spread_short = 200;
+        custody.pricing.swap_spread = 50;
+        custody.pricing.convexity_vol = 1_000;
+        custody.volatility = VolatilityParams {
+            vol_type: VolatilityType::Custom,
+            vol_account: Pubkey::new_unique(),
+            max_vol_age_sec: 60,
+            conf_mult: 0,
+            ref_vol: 5_000,
+            min_vol: 2_500,
+            max_vol: 15_000,
+        };
+        assert!(custody.volatility.validate());
+        assert!(custody.validate_bounds());
+
+        // static params until a volatility is cached
+        let curtime = 3600;
+        assert_eq!(custody.get_volatility(curtime), None);
+        assert_eq!(custody.get_entry_spread(Side::Long, curtime).unwrap(), 100);
+
+        // twice the reference volatility doubles the dynamic params
+        custody.volatility_state = VolatilityState {
+            vol: 10_000,
+            last_update: curtime,
+        };
+        assert_eq!(custody.get_volatility_mult(curtime).unwrap(), 20_000);
+        assert_eq!(custody.get_entry_spread(Side::Long, curtime).unwrap(), 200);
+        assert_eq!(custody.get_exit_spread(Side::Long, curtime).unwrap(), 400);
+        assert_eq!(custody.get_swap_spread(curtime).unwrap(), 100);
+        assert_eq!(custody.get_convexity_reserve(3, curtime).unwrap(), 1_200);
+        custody.update_borrow_rate(curtime).unwrap();
+        assert_eq!(custody.borrow_rate_state.current_rate, 100_000);
+
+        // the feed volatility is clamped, scaled spreads are capped
+        custody.volatility_state.vol = 100_000;
+        assert_eq!(custody.get_volatility(curtime), Some(15_000));
+        assert_eq!(custody.get_exit_spread(Side::Long, curtime).unwrap(), 600);
+        custody.pricing.trade_spread_long = 500;
+        assert_eq!(
+            custody.get_exit_spread(Side::Short, curtime).unwrap(),
+            ParamBounds::MAX_SPREAD
+        );
+        custody.volatility_state.vol = 0;
+        assert_eq!(custody.get_volatility_mult(curtime).unwrap(), 5_000);
+        assert_eq!(custody.get_swap_spread(curtime).unwrap(), 25);
+
+        // stale volatility falls back to the static params
+        assert_eq!(custody.get_volatility(curtime + 61), None);
+        assert_eq!(
+            custody.get_volatility_mult(curtime + 61).unwrap(),
+            Perpetuals::BPS_POWER as u64
+        );
+        assert_eq!(custody.get_swap_spread(curtime + 61).unwrap(), 50);
+
+        // max_vol / ref_vol must stay within ParamBounds::MAX_VOL_MULT
+        custody.volatility.max_vol = 30_000;
+        assert!(!custody.validate_bounds());
+        custody.volatility.ref_vol = 0;
+        assert!(!custody.volatility.validate());
+    }
+
+    #[test]
+    fn test_param_bounds() {
+        let mut custody = get_fixture();
+        custody.pricing.trade_spread_long = ParamBounds::MAX_SPREAD;
+        custody.pricing.max_leverage = ParamBounds::MAX_LEVERAGE;
+        custody.fees.open_position = ParamBounds::MAX_FEE;
+        custody.fees.utilization_mult = ParamBounds::MAX_FEE_MULT;
+        assert!(custody.validate_bounds());
+
+        custody.pricing.trade_spread_long += 1;
+        assert!(!custody.validate_bounds());
+        assert!(custody
+            .get_config_violations()
+            .contains(&ConfigViolation::ParamBounds));
+        custody.pricing.trade_spread_long = 0;
+
+        custody.pricing.maintenance_leverage = ParamBounds::MAX_LEVERAGE + 1;
+        assert!(!custody.validate_bounds());
+        custody.pricing.maintenance_leverage = 0;
+
+        custody.entry_fee_tiers[0] = EntryFeeTier {
+            min_size_usd: 1,
+            fee: ParamBounds::MAX_FEE + 1,
+        };
+        assert!(!custody.validate_bounds());
+        custody.entry_fee_tiers[0] = EntryFeeTier::default();
+
+        custody.fees.ratio_mult = ParamBounds::MAX_FEE_MULT + 1;
+        assert!(!custody.validate_bounds());
+    }
+
+    #[test]
+    fn test_load_legacy() {
+        let assets = AssetsV0 {
+            owned: 1000,
+            locked: 500,
+            ..AssetsV0::default()
+        };
+        let collected_fees = DeprecatedFeesStats {
+            swap_usd: 10,
+            ..DeprecatedFeesStats::default()
+        };
+
+        let deprecated = DeprecatedCustody {
+            decimals: 5,
+            assets,
+            collected_fees,
+            bump: 254,
+            token_account_bump: 253,
+            ..DeprecatedCustody::default()
+        };
+        let mut data = Vec::new();
+        deprecated.try_serialize(&mut data).unwrap();
+        data.resize(DeprecatedCustody::LEN, 0);
+        let custody = Custody::from(CustodyV0::load(&data).unwrap());
+        assert_eq!(custody.decimals, 5);
+        assert!(!custody.is_virtual);
+        assert_eq!(custody.assets.owned, 1000);
+        assert_eq!(custody.assets.locked, 500);
+        assert_eq!(custody.collected_fees.swap_usd, 10);
+        assert_eq!(custody.lifecycle, MarketLifecycle::Active);
+        assert_eq!((custody.bump, custody.token_account_bump), (254, 253));
+
+        let original = CustodyV0 {
+            decimals: 5,
+            is_virtual: true,
+            assets,
+            collected_fees,
+            bump: 254,
+            token_account_bump: 253,
+            ..CustodyV0::default()
+        };
+        let mut data = Custody::DISCRIMINATOR.to_vec();
+        original.serialize(&mut data).unwrap();
+        data.resize(CustodyV0::LEN, 0);
+        assert_eq!(CustodyV0::load(&data), Some(original.clone()));
+        let custody = Custody::from(original);
+        assert!(custody.is_virtual);
+        assert_eq!(custody.assets.owned, 1000);
+        assert_eq!(custody.collected_fees.swap_usd, 10);
+
+        // current custodies are not loaded as legacy ones
+        assert_ne!(CustodyV0::LEN, Custody::LEN);
+        let mut data = Vec::new();
+        custody.try_serialize(&mut data).unwrap();
+        data.resize(Custody::LEN, 0);
+        assert_eq!(CustodyV0::load(&data), None);
+    }
 }
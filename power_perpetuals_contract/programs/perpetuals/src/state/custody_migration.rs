@@ -0,0 +1,267 @@
+//! Custody mint migration state
+//!
+//! Tracks a scheduled migration of a custody to a new token mint (e.g. after a token
+//! re-denomination). The custody is paused while the migration is pending and the
+//! migration can only be executed once the timelock has passed.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            oracle::{OracleParams, OracleType},
+            perpetuals::{Perpetuals, Permissions},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Custody migration account
+///
+/// PDA derived from the custody being migrated.
+#[account]
+#[derive(Default, Debug)]
+pub struct CustodyMigration {
+    /// Custody being migrated
+    pub custody: Pubkey,
+    /// Mint the custody will be migrated to
+    pub new_mint: Pubkey,
+    /// New token amount per old token amount, after decimals adjustment (RATE_DECIMALS)
+    pub conversion_rate: u64,
+    /// Earliest time the migration can be executed
+    pub execute_time: i64,
+    /// Custody permissions before the custody was paused, restored after migration
+    pub permissions: Permissions,
+    /// Custody CPI permission, restored after migration
+    pub allow_cpi: bool,
+
+    /// Bump seed for the custody migration PDA
+    pub bump: u8,
+}
+
+impl CustodyMigration {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<CustodyMigration>();
+    /// Minimum delay between scheduling and executing a migration
+    pub const MIN_DELAY_SEC: i64 = 86400;
+
+    /// Convert a token amount of the old mint to the new mint
+    ///
+    /// # Arguments
+    /// * `amount` - Amount in old mint decimals
+    /// * `old_decimals` - Decimals of the old mint
+    /// * `new_decimals` - Decimals of the new mint
+    ///
+    /// # Returns
+    /// Amount in new mint decimals
+    pub fn convert_amount(&self, amount: u64, old_decimals: u8, new_decimals: u8) -> Result<u64> {
+        let amount = math::checked_div(
+            math::checked_mul(amount as u128, self.conversion_rate as u128)?,
+            Perpetuals::RATE_POWER,
+        )?;
+        let amount = if new_decimals >= old_decimals {
+            math::checked_mul(
+                amount,
+                math::checked_pow(10u128, (new_decimals - old_decimals) as usize)?,
+            )?
+        } else {
+            math::checked_div(
+                amount,
+                math::checked_pow(10u128, (old_decimals - new_decimals) as usize)?,
+            )?
+        };
+        math::checked_as_u64(amount)
+    }
+
+    /// Build the custody re-created for the new mint
+    ///
+    /// Configuration and stats are carried over, balances are rescaled to the new mint,
+    /// the custody permissions are restored and the oracle config is replaced, as oracles
+    /// price the old token. Custom oracle accounts are derived from the custody mint, so
+    /// a custom oracle must be the one of the new mint.
+    ///
+    /// # Arguments
+    /// * `custody` - Custody being migrated
+    /// * `new_custody_account` - Account of the migrated custody, must not exist yet
+    /// * `new_token_account` - Token account of the migrated custody
+    /// * `new_decimals` - Decimals of the new mint
+    /// * `oracle` - Oracle config of the migrated custody
+    ///
+    /// # Returns
+    /// The migrated custody, bumps are left to the caller
+    pub fn get_migrated_custody(
+        &self,
+        custody: &Custody,
+        new_custody_account: &AccountInfo,
+        new_token_account: Pubkey,
+        new_decimals: u8,
+        oracle: OracleParams,
+    ) -> Result<Custody> {
+        require!(
+            Perpetuals::is_empty_account(new_custody_account)?,
+            PerpetualsError::InvalidCustodyState
+        );
+        if oracle.oracle_type == OracleType::Custom {
+            let (oracle_account, _) = Pubkey::find_program_address(
+                &[
+                    b"oracle_account",
+                    custody.pool.as_ref(),
+                    self.new_mint.as_ref(),
+                ],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                oracle.oracle_account,
+                oracle_account,
+                PerpetualsError::InvalidOracleAccount
+            );
+        }
+
+        let mut new_custody = Custody::clone(custody);
+        new_custody.mint = self.new_mint;
+        new_custody.token_account = new_token_account;
+        new_custody.decimals = new_decimals;
+        new_custody.oracle = oracle;
+        new_custody.permissions = self.permissions;
+        new_custody.allow_cpi = self.allow_cpi;
+        new_custody.assets.owned =
+            self.convert_amount(custody.assets.owned, custody.decimals, new_decimals)?;
+        new_custody.assets.protocol_fees =
+            self.convert_amount(custody.assets.protocol_fees, custody.decimals, new_decimals)?;
+        if !new_custody.validate() {
+            return err!(PerpetualsError::InvalidCustodyConfig);
+        }
+
+        Ok(new_custody)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_account_info(lamports: u64, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
+            false,
+            true,
+            Box::leak(Box::new(lamports)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    fn get_fixture() -> (CustodyMigration, Custody) {
+        let custody_migration = CustodyMigration {
+            new_mint: Pubkey::new_unique(),
+            // 1 new token per 10 old tokens
+            conversion_rate: Perpetuals::RATE_POWER as u64 / 10,
+            permissions: Permissions {
+                allow_swap: true,
+                ..Permissions::default()
+            },
+            allow_cpi: true,
+            ..CustodyMigration::default()
+        };
+        let mut custody = Custody {
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            token_account: Pubkey::new_unique(),
+            decimals: 6,
+            ..Custody::default()
+        };
+        custody.pricing.min_initial_leverage = Perpetuals::BPS_POWER as u64;
+        custody.pricing.max_initial_leverage = Perpetuals::BPS_POWER as u64;
+        custody.pricing.max_leverage = Perpetuals::BPS_POWER as u64;
+        custody.borrow_rate.optimal_utilization = 1;
+        custody.assets.owned = 10_000_000;
+        custody.assets.protocol_fees = 1_000_000;
+        (custody_migration, custody)
+    }
+
+    #[test]
+    fn test_get_migrated_custody() {
+        let (custody_migration, custody) = get_fixture();
+        let new_token_account = Pubkey::new_unique();
+        let empty_account = get_account_info(0, vec![]);
+
+        let new_custody = custody_migration
+            .get_migrated_custody(
+                &custody,
+                &empty_account,
+                new_token_account,
+                9,
+                OracleParams::default(),
+            )
+            .unwrap();
+        assert_eq!(new_custody.mint, custody_migration.new_mint);
+        assert_eq!(new_custody.token_account, new_token_account);
+        assert_eq!(new_custody.decimals, 9);
+        assert_eq!(new_custody.assets.owned, 1_000_000_000);
+        assert_eq!(new_custody.assets.protocol_fees, 100_000_000);
+        assert_eq!(new_custody.permissions, custody_migration.permissions);
+        assert!(new_custody.allow_cpi);
+
+        // the migrated custody account already exists
+        let initialized_account = get_account_info(1_000_000, vec![0; Custody::LEN]);
+        assert_eq!(
+            custody_migration
+                .get_migrated_custody(
+                    &custody,
+                    &initialized_account,
+                    new_token_account,
+                    9,
+                    OracleParams::default(),
+                )
+                .unwrap_err(),
+            PerpetualsError::InvalidCustodyState.into()
+        );
+
+        // custom oracles of the old mint can't be carried over
+        let (old_oracle_account, _) = Pubkey::find_program_address(
+            &[b"oracle_account", custody.pool.as_ref(), custody.mint.as_ref()],
+            &crate::ID,
+        );
+        let mut oracle = OracleParams {
+            oracle_account: old_oracle_account,
+            oracle_type: OracleType::Custom,
+            max_price_error: 100,
+            max_price_age_sec: 60,
+            ..OracleParams::default()
+        };
+        assert_eq!(
+            custody_migration
+                .get_migrated_custody(&custody, &empty_account, new_token_account, 9, oracle)
+                .unwrap_err(),
+            PerpetualsError::InvalidOracleAccount.into()
+        );
+        (oracle.oracle_account, _) = Pubkey::find_program_address(
+            &[
+                b"oracle_account",
+                custody.pool.as_ref(),
+                custody_migration.new_mint.as_ref(),
+            ],
+            &crate::ID,
+        );
+        assert_eq!(
+            custody_migration
+                .get_migrated_custody(&custody, &empty_account, new_token_account, 9, oracle)
+                .unwrap()
+                .oracle,
+            oracle
+        );
+
+        // invalid oracle config
+        oracle.oracle_type = OracleType::Pyth;
+        oracle.oracle_account = Pubkey::default();
+        assert_eq!(
+            custody_migration
+                .get_migrated_custody(&custody, &empty_account, new_token_account, 9, oracle)
+                .unwrap_err(),
+            PerpetualsError::InvalidCustodyConfig.into()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Force settlement state
+//!
+//! Emergency settlement of a single position whose custody oracle is permanently
+//! broken. Admins schedule the settlement price, and once the timelock has passed the
+//! position can be settled at it, provided the price is confirmed by two independent
+//! reference prices.
+
+use {
+    crate::{
+        math,
+        state::{
+            oracle::{OracleParams, OracleType},
+            perpetuals::Perpetuals,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Force settlement account
+///
+/// PDA derived from the position being settled.
+#[account]
+#[derive(Default, Debug)]
+pub struct ForceSettlement {
+    /// Position to settle
+    pub position: Pubkey,
+    /// Settlement price of the position token, scaled to PRICE_DECIMALS
+    pub price: u64,
+    /// Earliest time the settlement can be executed
+    pub execute_time: i64,
+
+    /// Bump seed for the force settlement PDA
+    pub bump: u8,
+}
+
+impl ForceSettlement {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<ForceSettlement>();
+    /// Minimum delay between scheduling and executing a settlement
+    pub const MIN_DELAY_SEC: i64 = 86400;
+    /// Maximum deviation of the settlement price from each reference price (BPS)
+    pub const MAX_REFERENCE_DEVIATION_BPS: u64 = 200;
+
+    /// Checks whether the settlement price is within the allowed deviation of a
+    /// reference price
+    ///
+    /// # Arguments
+    /// * `reference_price` - Reference price, scaled to PRICE_DECIMALS
+    pub fn is_within_reference(&self, reference_price: u64) -> Result<bool> {
+        if reference_price == 0 {
+            return Ok(false);
+        }
+        let deviation = math::checked_div(
+            math::checked_mul(
+                self.price.abs_diff(reference_price) as u128,
+                Perpetuals::BPS_POWER,
+            )?,
+            reference_price as u128,
+        )?;
+        Ok(deviation <= Self::MAX_REFERENCE_DEVIATION_BPS as u128)
+    }
+
+    /// Checks whether two reference oracles are independent price sources
+    ///
+    /// Custom oracle prices are set by their authority, so they can't confirm an admin
+    /// price. The references must differ by oracle type or by provider, the program
+    /// owning the oracle account.
+    ///
+    /// # Arguments
+    /// * `oracle_a` - Oracle config of the first reference custody
+    /// * `provider_a` - Owner of the first reference oracle account
+    /// * `oracle_b` - Oracle config of the second reference custody
+    /// * `provider_b` - Owner of the second reference oracle account
+    pub fn are_independent_references(
+        oracle_a: &OracleParams,
+        provider_a: &Pubkey,
+        oracle_b: &OracleParams,
+        provider_b: &Pubkey,
+    ) -> bool {
+        let is_external = |oracle: &OracleParams| {
+            !matches!(oracle.oracle_type, OracleType::None | OracleType::Custom)
+        };
+        is_external(oracle_a)
+            && is_external(oracle_b)
+            && (oracle_a.oracle_type != oracle_b.oracle_type || provider_a != provider_b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reference_deviation() {
+        let force_settlement = ForceSettlement {
+            price: 100_000_000,
+            ..Default::default()
+        };
+        assert!(force_settlement.is_within_reference(100_000_000).unwrap());
+        assert!(force_settlement.is_within_reference(98_100_000).unwrap());
+        assert!(force_settlement.is_within_reference(101_900_000).unwrap());
+        assert!(!force_settlement.is_within_reference(97_000_000).unwrap());
+        assert!(!force_settlement.is_within_reference(103_000_000).unwrap());
+        assert!(!force_settlement.is_within_reference(0).unwrap());
+    }
+
+    #[test]
+    fn test_independent_references() {
+        let pyth = OracleParams {
+            oracle_type: OracleType::Pyth,
+            ..Default::default()
+        };
+        let custom = OracleParams {
+            oracle_type: OracleType::Custom,
+            ..Default::default()
+        };
+        let provider_a = Pubkey::new_from_array([1; 32]);
+        let provider_b = Pubkey::new_from_array([2; 32]);
+
+        assert!(ForceSettlement::are_independent_references(
+            &pyth,
+            &provider_a,
+            &pyth,
+            &provider_b
+        ));
+        // same type and provider
+        assert!(!ForceSettlement::are_independent_references(
+            &pyth,
+            &provider_a,
+            &pyth,
+            &provider_a
+        ));
+        // custom oracles are set by their authority
+        assert!(!ForceSettlement::are_independent_references(
+            &custom,
+            &provider_a,
+            &pyth,
+            &provider_b
+        ));
+        assert!(!ForceSettlement::are_independent_references(
+            &pyth,
+            &provider_a,
+            &custom,
+            &provider_b
+        ));
+        assert!(!ForceSettlement::are_independent_references(
+            &OracleParams::default(),
+            &provider_a,
+            &pyth,
+            &provider_b
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! Market listing state
+//!
+//! Pools can accept permissionless market listings. A lister posts a bond and proposes
+//! a virtual custody whose parameters are taken from an admin-set template, with the
+//! risk limits chosen by the lister inside the template bounds. The custody activates
+//! once the timelock has passed without a multisig veto. A veto slashes the bond, which
+//! keeps spam listings costly.
+//!
+//! Listing configs embed the custody pricing params, so their layout follows the pricing
+//! layout. Configs stored in a legacy layout are rejected until set_listing_config
+//! rewrites them in the current one.
+
+use {
+    crate::state::{
+        custody::{BorrowRateParams, Fees, PricingParams},
+        oracle::{OracleParams, OracleType},
+        perpetuals::Permissions,
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Custody configuration listed markets start from
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ListingTemplate {
+    /// Oracle settings, the lister picks the oracle account
+    pub oracle: OracleParams,
+    /// Pricing parameters, max_leverage and max_total_locked_usd are upper bounds
+    pub pricing: PricingParams,
+    pub permissions: Permissions,
+    pub allow_cpi: bool,
+    pub fees: Fees,
+    pub borrow_rate: BorrowRateParams,
+}
+
+/// Custody parameters chosen by the lister
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ListingParams {
+    /// Pyth price account of the listed token
+    pub oracle_account: Pubkey,
+    /// Maximum leverage, between the template max initial and max leverage (BPS)
+    pub max_leverage: u64,
+    /// Maximum total locked funds in USD, up to the template limit
+    pub max_total_locked_usd: u64,
+}
+
+/// Listing status of a proposal
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum ListingStatus {
+    #[default]
+    Pending,
+    Activated,
+    Vetoed,
+}
+
+/// Listing configuration account of a pool
+///
+/// PDA derived from the pool.
+#[account]
+#[derive(Default, Debug)]
+pub struct ListingConfig {
+    /// Pool the listings are added to
+    pub pool: Pubkey,
+    /// Mint of the listing bond
+    pub bond_mint: Pubkey,
+    /// Bond posted per listing (in bond token decimals)
+    pub bond_amount: u64,
+    /// Token account slashed bonds are paid to
+    pub slash_account: Pubkey,
+    /// Time a listing waits for a veto before it can be activated
+    pub timelock_sec: i64,
+    /// Custody configuration of listed markets
+    pub template: ListingTemplate,
+    /// Account layout version, see ListingConfig::VERSION
+    pub version: u8,
+
+    /// Bump seed for the listing config PDA
+    pub bump: u8,
+}
+
+/// Listing proposal account
+///
+/// PDA derived from the pool and the listed token mint.
+#[account]
+#[derive(Default, Debug)]
+pub struct ListingProposal {
+    /// Pool the custody is listed in
+    pub pool: Pubkey,
+    /// Mint of the listed token
+    pub mint: Pubkey,
+    /// Account that posted the bond, receives it back on activation
+    pub lister: Pubkey,
+    /// Custody parameters chosen by the lister
+    pub params: ListingParams,
+    /// Bond held in the bond vault
+    pub bond_amount: u64,
+    /// Time the listing was proposed
+    pub proposal_time: i64,
+    /// Earliest time the listing can be activated
+    pub activation_time: i64,
+    pub status: ListingStatus,
+
+    /// Bump seed for the listing proposal PDA
+    pub bump: u8,
+    /// Bump seed for the bond vault PDA
+    pub bond_vault_bump: u8,
+}
+
+impl ListingConfig {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<ListingConfig>();
+    /// Minimum veto window of a listing
+    pub const MIN_TIMELOCK_SEC: i64 = 86400;
+    /// Current account layout version, 0 = template pricing without the max power
+    /// or without the convexity volatility (no version field, read back as 0)
+    pub const VERSION: u8 = 1;
+
+    pub fn validate(&self) -> bool {
+        self.version == Self::VERSION
+            && self.bond_mint != Pubkey::default()
+            && self.bond_amount > 0
+            && self.slash_account != Pubkey::default()
+            && self.timelock_sec >= Self::MIN_TIMELOCK_SEC
+            && self.template.pricing.validate()
+            && self.template.fees.validate()
+    }
+
+    /// Checks that lister parameters are within the template bounds
+    pub fn validate_params(&self, params: &ListingParams) -> bool {
+        let pricing = &self.template.pricing;
+        params.oracle_account != Pubkey::default()
+            && params.max_leverage >= pricing.max_initial_leverage
+            && params.max_leverage <= pricing.max_leverage
+            && params.max_total_locked_usd >= pricing.max_position_locked_usd
+            && params.max_total_locked_usd <= pricing.max_total_locked_usd
+    }
+
+    /// Returns the oracle of a listed custody
+    ///
+    /// Listed markets are priced by Pyth only, custom oracle prices are set by their
+    /// authority and can't be trusted from a permissionless lister.
+    pub fn get_oracle(&self, params: &ListingParams) -> OracleParams {
+        OracleParams {
+            oracle_account: params.oracle_account,
+            oracle_type: OracleType::Pyth,
+            oracle_authority: Pubkey::default(),
+            ..self.template.oracle
+        }
+    }
+
+    /// Returns the pricing parameters of a listed custody
+    pub fn get_pricing(&self, params: &ListingParams) -> PricingParams {
+        PricingParams {
+            max_leverage: params.max_leverage,
+            max_total_locked_usd: params.max_total_locked_usd,
+            ..self.template.pricing
+        }
+    }
+}
+
+impl ListingProposal {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<ListingProposal>();
+
+    /// Whether the listing can be activated
+    pub fn can_activate(&self, curtime: i64) -> bool {
+        self.status == ListingStatus::Pending && curtime >= self.activation_time
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::state::{custody::LiquidationPriceMode, perpetuals::Perpetuals},
+    };
+
+    // template pricing without the max power
+    #[derive(AnchorSerialize, Default)]
+    struct PricingParamsV2 {
+        use_ema: bool,
+        use_unrealized_pnl_in_aum: bool,
+        trade_spread_long: u64,
+        trade_spread_short: u64,
+        swap_spread: u64,
+        min_initial_leverage: u64,
+        max_initial_leverage: u64,
+        max_leverage: u64,
+        maintenance_leverage: u64,
+        max_payoff_mult: u64,
+        max_utilization: u64,
+        max_position_locked_usd: u64,
+        max_total_locked_usd: u64,
+        min_holding_period: i64,
+        reject_early_close: bool,
+        liquidation_price_mode: LiquidationPriceMode,
+        lp_fee_decay_period: i64,
+        risk_warning_health: u64,
+        risk_danger_health: u64,
+    }
+
+    // template pricing without the convexity volatility
+    #[derive(AnchorSerialize, Default)]
+    struct PricingParamsV3 {
+        pricing: PricingParamsV2,
+        max_power: u8,
+    }
+
+    // serializes a config without the version field, with the template pricing of a
+    // legacy layout
+    fn serialize_legacy(config: &ListingConfig, pricing: &impl AnchorSerialize) -> Vec<u8> {
+        let mut data = ListingConfig::DISCRIMINATOR.to_vec();
+        config.pool.serialize(&mut data).unwrap();
+        config.bond_mint.serialize(&mut data).unwrap();
+        config.bond_amount.serialize(&mut data).unwrap();
+        config.slash_account.serialize(&mut data).unwrap();
+        config.timelock_sec.serialize(&mut data).unwrap();
+        let template = &config.template;
+        template.oracle.serialize(&mut data).unwrap();
+        pricing.serialize(&mut data).unwrap();
+        template.permissions.serialize(&mut data).unwrap();
+        template.allow_cpi.serialize(&mut data).unwrap();
+        template.fees.serialize(&mut data).unwrap();
+        template.borrow_rate.serialize(&mut data).unwrap();
+        config.bump.serialize(&mut data).unwrap();
+        data.resize(ListingConfig::LEN, 0);
+        data
+    }
+
+    #[test]
+    fn test_listing_template() {
+        let mut config = ListingConfig {
+            bond_mint: Pubkey::new_from_array([1; 32]),
+            bond_amount: 1_000,
+            slash_account: Pubkey::new_from_array([2; 32]),
+            timelock_sec: ListingConfig::MIN_TIMELOCK_SEC,
+            version: ListingConfig::VERSION,
+            ..Default::default()
+        };
+        config.template.pricing = PricingParams {
+            min_initial_leverage: Perpetuals::BPS_POWER as u64,
+            max_initial_leverage: 50_000,
+            max_leverage: 100_000,
+            max_position_locked_usd: 1_000,
+            max_total_locked_usd: 10_000,
+            ..Default::default()
+        };
+        assert!(config.validate());
+        config.timelock_sec -= 1;
+        assert!(!config.validate());
+
+        let mut params = ListingParams {
+            oracle_account: Pubkey::new_from_array([3; 32]),
+            max_leverage: 50_000,
+            max_total_locked_usd: 5_000,
+        };
+        assert!(config.validate_params(&params));
+        let pricing = config.get_pricing(&params);
+        assert_eq!(pricing.max_leverage, 50_000);
+        assert_eq!(pricing.max_total_locked_usd, 5_000);
+        assert!(pricing.validate());
+        assert_eq!(config.get_oracle(&params).oracle_type, OracleType::Pyth);
+
+        params.max_leverage = 100_001;
+        assert!(!config.validate_params(&params));
+        params.max_leverage = 49_999;
+        assert!(!config.validate_params(&params));
+        params.max_leverage = 100_000;
+        params.max_total_locked_usd = 10_001;
+        assert!(!config.validate_params(&params));
+
+        let mut proposal = ListingProposal {
+            activation_time: 100,
+            ..Default::default()
+        };
+        assert!(!proposal.can_activate(99));
+        assert!(proposal.can_activate(100));
+        proposal.status = ListingStatus::Vetoed;
+        assert!(!proposal.can_activate(100));
+    }
+
+    #[test]
+    fn test_legacy_listing_config() {
+        let config = ListingConfig {
+            pool: Pubkey::new_from_array([1; 32]),
+            bond_mint: Pubkey::new_from_array([2; 32]),
+            bond_amount: 1_000,
+            slash_account: Pubkey::new_from_array([3; 32]),
+            timelock_sec: ListingConfig::MIN_TIMELOCK_SEC,
+            version: ListingConfig::VERSION,
+            bump: 255,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        data.resize(ListingConfig::LEN, 0);
+        assert_eq!(
+            ListingConfig::try_deserialize(&mut &data[..]).unwrap().version,
+            ListingConfig::VERSION
+        );
+
+        // template pricing without the max power
+        let data = serialize_legacy(&config, &PricingParamsV2::default());
+        assert!(ListingConfig::try_deserialize(&mut &data[..])
+            .is_ok_and(|legacy| legacy.version != ListingConfig::VERSION));
+
+        // template pricing without the convexity volatility
+        let data = serialize_legacy(&config, &PricingParamsV3::default());
+        assert!(ListingConfig::try_deserialize(&mut &data[..])
+            .is_ok_and(|legacy| legacy.version != ListingConfig::VERSION));
+    }
+}
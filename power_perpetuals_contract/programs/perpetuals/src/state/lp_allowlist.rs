@@ -0,0 +1,76 @@
+//! Liquidity provider allowlist state
+//!
+//! Permissioned pools restrict add_liquidity and remove_liquidity, backstop deposits and
+//! withdrawals, and LP index deposits to allowlisted owners.
+//! The allowlist is managed by the multisig or by a designated compliance authority, so
+//! institutional pools can run alongside public ones in the same deployment.
+
+use {crate::error::PerpetualsError, anchor_lang::prelude::*};
+
+/// Liquidity provider allowlist account
+///
+/// PDA derived from the pool. Enforced only while `pool.lp_allowlist_enabled` is set.
+#[account]
+#[derive(Default, Debug)]
+pub struct LpAllowlist {
+    /// Pool the allowlist applies to
+    pub pool: Pubkey,
+    /// Authority allowed to update the allowlist besides the multisig
+    pub compliance_authority: Pubkey,
+    /// Owners allowed to add and remove liquidity
+    pub owners: Vec<Pubkey>,
+
+    /// Bump seed for the allowlist PDA
+    pub bump: u8,
+}
+
+impl LpAllowlist {
+    /// Maximum number of allowlisted owners
+    pub const MAX_OWNERS: usize = 128;
+    /// Account size in bytes (8 byte discriminator + data + owners)
+    pub const LEN: usize = 8
+        + std::mem::size_of::<LpAllowlist>()
+        + LpAllowlist::MAX_OWNERS * std::mem::size_of::<Pubkey>();
+
+    /// Check whether an owner is allowlisted
+    pub fn is_allowed(&self, owner: &Pubkey) -> bool {
+        self.owners.contains(owner)
+    }
+
+    /// Add an owner to the allowlist (no-op if already present)
+    ///
+    /// # Returns
+    /// Error if the allowlist is full
+    pub fn add_owner(&mut self, owner: Pubkey) -> Result<()> {
+        if self.is_allowed(&owner) {
+            return Ok(());
+        }
+        if self.owners.len() >= LpAllowlist::MAX_OWNERS {
+            return err!(PerpetualsError::LpAllowlistFull);
+        }
+        self.owners.push(owner);
+        Ok(())
+    }
+
+    /// Check an owner against the allowlist account of a permissioned pool
+    ///
+    /// For instructions handling LP tokens of several pools, which get the allowlists as
+    /// remaining accounts.
+    pub fn check_allowlist_account<'info>(
+        allowlist_info: &'info AccountInfo<'info>,
+        pool: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<()> {
+        let lp_allowlist = Account::<LpAllowlist>::try_from(allowlist_info)?;
+        require!(
+            lp_allowlist.pool == *pool && lp_allowlist.is_allowed(owner),
+            PerpetualsError::LpNotAllowlisted
+        );
+        Ok(())
+    }
+
+    /// Remove an owner from the allowlist (no-op if not present)
+    pub fn remove_owner(&mut self, owner: &Pubkey) {
+        self.owners.retain(|x| x != owner);
+    }
+}
@@ -0,0 +1,385 @@
+//! LP index state
+//!
+//! An LP index is a meta-LP token backed by a basket of LP tokens of several pools of
+//! this program. The LP tokens are held in per-component vaults, and the index token
+//! is priced from the pool AUMs, so passive LPs get diversified exposure across
+//! markets with a single mint. The AUMs are computed from the pool custodies and oracles
+//! when minting, the AUM cached in a pool is only refreshed by liquidity operations and
+//! the crank.
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Pool LP token held by an LP index
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct LpIndexComponent {
+    /// Pool whose LP tokens are held
+    pub pool: Pubkey,
+    /// Vault holding the pool LP tokens
+    pub vault: Pubkey,
+    /// Target share of the index value in BPS
+    pub weight: u64,
+    /// Bump seed for the vault PDA
+    pub vault_bump: u8,
+}
+
+/// LP index account
+///
+/// PDA derived from the index name.
+#[account]
+#[derive(Default, Debug)]
+pub struct LpIndex {
+    /// Index name
+    pub name: String,
+    /// Pool LP tokens held by the index
+    pub components: Vec<LpIndexComponent>,
+    /// Maximum deviation of a deposit component share from its weight in BPS
+    pub max_weight_deviation: u64,
+    /// Timestamp when the index was created
+    pub inception_time: i64,
+
+    /// Bump seed for the LP index PDA
+    pub bump: u8,
+    /// Bump seed for the index token mint PDA
+    pub index_token_bump: u8,
+}
+
+impl LpIndex {
+    /// Maximum number of pools in an index
+    pub const MAX_COMPONENTS: usize = 8;
+    /// Account size in bytes (8 byte discriminator + data + name + components)
+    pub const LEN: usize = 8
+        + std::mem::size_of::<LpIndex>()
+        + 64
+        + LpIndex::MAX_COMPONENTS * std::mem::size_of::<LpIndexComponent>();
+
+    /// Checks the index configuration (weights may not add up to 100% yet)
+    pub fn validate(&self) -> bool {
+        let mut total_weight: u128 = 0;
+        for (i, component) in self.components.iter().enumerate() {
+            if self.components[..i]
+                .iter()
+                .any(|c| c.pool == component.pool)
+            {
+                return false;
+            }
+            total_weight += component.weight as u128;
+        }
+
+        !self.name.is_empty()
+            && self.name.len() <= 64
+            && self.components.len() <= Self::MAX_COMPONENTS
+            && total_weight <= Perpetuals::BPS_POWER
+            && self.max_weight_deviation as u128 <= Perpetuals::BPS_POWER
+    }
+
+    /// Whether component weights add up to 100%, index tokens can only be minted then
+    pub fn is_balanced(&self) -> bool {
+        !self.components.is_empty()
+            && self
+                .components
+                .iter()
+                .map(|c| c.weight as u128)
+                .sum::<u128>()
+                == Perpetuals::BPS_POWER
+    }
+
+    /// Returns the index of the component holding LP tokens of the given pool
+    pub fn get_component_index(&self, pool: &Pubkey) -> Option<usize> {
+        self.components.iter().position(|c| c.pool == *pool)
+    }
+
+    /// Returns the USD value of pool LP tokens
+    ///
+    /// # Arguments
+    /// * `lp_amount` - Amount of pool LP tokens
+    /// * `aum_usd` - Pool assets under management in USD (scaled to USD_DECIMALS)
+    /// * `lp_supply` - Pool LP token supply
+    pub fn get_lp_value_usd(lp_amount: u64, aum_usd: u128, lp_supply: u64) -> Result<u64> {
+        let lp_price = Pool::get_lp_price(math::checked_as_u64(aum_usd)?, lp_supply)?;
+        math::checked_decimal_mul(
+            lp_amount,
+            -(Perpetuals::LP_DECIMALS as i32),
+            lp_price,
+            -(Perpetuals::USD_DECIMALS as i32),
+            -(Perpetuals::USD_DECIMALS as i32),
+        )
+    }
+
+    /// Returns the LP AUM of a component pool in USD, computed from its custodies and
+    /// oracles rather than read from the pool
+    ///
+    /// # Arguments
+    /// * `pool` - Component pool
+    /// * `accounts` - Pool custodies followed by their oracles, in pool order
+    /// * `aum_calc_mode` - Which price to use (Min/Max/Last/EMA)
+    /// * `curtime` - Current timestamp
+    pub fn get_component_aum_usd<'a>(
+        pool: &Pool,
+        accounts: &'a [AccountInfo<'a>],
+        aum_calc_mode: AumCalcMode,
+        curtime: i64,
+    ) -> Result<u128> {
+        require_eq!(
+            accounts.len(),
+            pool.custodies.len() * 2,
+            PerpetualsError::InvalidLpIndexState
+        );
+        let aum_usd = pool.get_assets_under_management_usd(aum_calc_mode, accounts, curtime)?;
+        Ok(pool.backstop.get_senior_aum_usd(aum_usd))
+    }
+
+    /// Returns the amount of index tokens minted for a deposit, rounded down
+    ///
+    /// The first deposit mints one index token per USD.
+    ///
+    /// # Arguments
+    /// * `deposit_usd` - Value of the deposited LP tokens in USD
+    /// * `index_value_usd` - Value of the LP tokens held by the index before the deposit
+    /// * `index_supply` - Index token supply before the deposit
+    pub fn get_mint_amount(
+        deposit_usd: u64,
+        index_value_usd: u64,
+        index_supply: u64,
+    ) -> Result<u64> {
+        if index_supply == 0 {
+            return Ok(deposit_usd);
+        }
+        require!(index_value_usd > 0, PerpetualsError::InvalidLpIndexState);
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(deposit_usd as u128, index_supply as u128)?,
+            index_value_usd as u128,
+        )?)
+    }
+
+    /// Checks that every deposited component share is within the allowed deviation
+    /// of its weight
+    ///
+    /// # Arguments
+    /// * `values_usd` - USD value of the deposited LP tokens, in component order
+    pub fn check_deposit_weights(&self, values_usd: &[u64]) -> Result<()> {
+        require_eq!(
+            values_usd.len(),
+            self.components.len(),
+            PerpetualsError::InvalidLpIndexState
+        );
+        let total_usd = values_usd.iter().map(|v| *v as u128).sum::<u128>();
+        require!(total_usd > 0, PerpetualsError::InvalidLpIndexState);
+
+        for (component, value_usd) in self.components.iter().zip(values_usd) {
+            let share = math::checked_div(
+                math::checked_mul(*value_usd as u128, Perpetuals::BPS_POWER)?,
+                total_usd,
+            )?;
+            require!(
+                share.abs_diff(component.weight as u128) <= self.max_weight_deviation as u128,
+                PerpetualsError::LpIndexWeightMismatch
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the amount of LP tokens paid out of a vault on redemption, rounded down
+    ///
+    /// # Arguments
+    /// * `vault_balance` - LP tokens held in the component vault
+    /// * `index_amount` - Index tokens being redeemed
+    /// * `index_supply` - Index token supply before the redemption
+    pub fn get_redeem_amount(
+        vault_balance: u64,
+        index_amount: u64,
+        index_supply: u64,
+    ) -> Result<u64> {
+        require!(
+            index_amount <= index_supply && index_supply > 0,
+            PerpetualsError::InvalidLpIndexState
+        );
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(vault_balance as u128, index_amount as u128)?,
+            index_supply as u128,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::state::{
+            custody::{Custody, PricingParams},
+            oracle::{CustomOracle, OracleParams, OracleType},
+        },
+    };
+
+    fn get_fixture() -> LpIndex {
+        LpIndex {
+            name: "index".to_string(),
+            components: vec![
+                LpIndexComponent {
+                    pool: Pubkey::new_from_array([1; 32]),
+                    weight: 6_000,
+                    ..Default::default()
+                },
+                LpIndexComponent {
+                    pool: Pubkey::new_from_array([2; 32]),
+                    weight: 4_000,
+                    ..Default::default()
+                },
+            ],
+            max_weight_deviation: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lp_index_config() {
+        let mut index = get_fixture();
+        assert!(index.validate());
+        assert!(index.is_balanced());
+        assert_eq!(
+            index.get_component_index(&Pubkey::new_from_array([2; 32])),
+            Some(1)
+        );
+
+        index.components[1].weight = 3_000;
+        assert!(index.validate());
+        assert!(!index.is_balanced());
+
+        index.components[1].weight = 5_000;
+        assert!(!index.validate());
+
+        index.components[1].weight = 4_000;
+        index.components[1].pool = index.components[0].pool;
+        assert!(!index.validate());
+    }
+
+    #[test]
+    fn test_lp_index_pricing() {
+        // 2 LP tokens of a pool with $300 AUM and 3 LP tokens in supply
+        assert_eq!(
+            LpIndex::get_lp_value_usd(2_000_000, 300_000_000, 3_000_000).unwrap(),
+            200_000_000
+        );
+        assert_eq!(LpIndex::get_lp_value_usd(2_000_000, 0, 0).unwrap(), 0);
+
+        assert_eq!(LpIndex::get_mint_amount(1_000, 0, 0).unwrap(), 1_000);
+        assert_eq!(LpIndex::get_mint_amount(1_000, 3_000, 2_000).unwrap(), 666);
+        assert!(LpIndex::get_mint_amount(1_000, 0, 2_000).is_err());
+
+        assert_eq!(LpIndex::get_redeem_amount(1_000, 1, 3).unwrap(), 333);
+        assert_eq!(LpIndex::get_redeem_amount(1_000, 3, 3).unwrap(), 1_000);
+        assert!(LpIndex::get_redeem_amount(1_000, 4, 3).is_err());
+
+        let index = get_fixture();
+        assert!(index.check_deposit_weights(&[600, 400]).is_ok());
+        assert!(index.check_deposit_weights(&[610, 390]).is_ok());
+        assert!(index.check_deposit_weights(&[620, 380]).is_err());
+        assert!(index.check_deposit_weights(&[600]).is_err());
+        assert!(index.check_deposit_weights(&[0, 0]).is_err());
+    }
+
+    fn get_account_info<T: AccountSerialize>(
+        key: Pubkey,
+        owner: Pubkey,
+        account: &T,
+    ) -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            false,
+            Box::leak(Box::new(1_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_component_aum() {
+        let custody_key = Pubkey::new_unique();
+        let oracle_key = Pubkey::new_unique();
+        let mut custody = Custody {
+            decimals: 9,
+            oracle: OracleParams {
+                oracle_account: oracle_key,
+                oracle_type: OracleType::Custom,
+                max_price_error: 10_000,
+                max_price_age_sec: 60,
+                ..OracleParams::default()
+            },
+            pricing: PricingParams {
+                use_ema: true,
+                ..PricingParams::default()
+            },
+            ..Custody::default()
+        };
+        // 10 tokens at $25 spot and $24 EMA
+        custody.assets.owned = 10_000_000_000;
+        let oracle = CustomOracle {
+            price: 25_000,
+            expo: -3,
+            ema: 24_000,
+            ..CustomOracle::default()
+        };
+        // the AUM cached in the pool is stale
+        let mut pool = Pool {
+            custodies: vec![custody_key],
+            aum_usd: 1_000_000,
+            ..Pool::default()
+        };
+        let get_accounts = |custody: &Custody| -> &'static [AccountInfo<'static>] {
+            Box::leak(Box::new([
+                get_account_info(custody_key, crate::ID, custody),
+                get_account_info(oracle_key, Pubkey::default(), &oracle),
+            ]))
+        };
+        let accounts = get_accounts(&custody);
+
+        assert_eq!(
+            LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Min, 0).unwrap(),
+            240_000_000
+        );
+        assert_eq!(
+            LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Max, 0).unwrap(),
+            250_000_000
+        );
+
+        // the AUM follows the custody balance, not the cached value
+        custody.assets.owned = 20_000_000_000;
+        assert_eq!(
+            LpIndex::get_component_aum_usd(&pool, get_accounts(&custody), AumCalcMode::Max, 0)
+                .unwrap(),
+            500_000_000
+        );
+
+        // the backstop tranche is not part of the LP AUM
+        pool.backstop.value_usd = 100_000_000;
+        assert_eq!(
+            LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Max, 0).unwrap(),
+            150_000_000
+        );
+
+        // stale oracle prices are rejected
+        assert!(LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Max, 60).is_ok());
+        assert!(LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Max, 61).is_err());
+
+        // the pool custodies and their oracles must all be passed
+        assert!(
+            LpIndex::get_component_aum_usd(&pool, &accounts[..1], AumCalcMode::Max, 0).is_err()
+        );
+        pool.custodies = vec![Pubkey::new_unique()];
+        assert!(LpIndex::get_component_aum_usd(&pool, accounts, AumCalcMode::Max, 0).is_err());
+    }
+}
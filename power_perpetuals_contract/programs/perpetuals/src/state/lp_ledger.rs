@@ -0,0 +1,174 @@
+//! Liquidity provider deposit ledger state
+//!
+//! Records the time of each add_liquidity deposit of an owner, so remove_liquidity can
+//! charge a higher fee on young deposits. This discourages just-in-time liquidity that
+//! is added right before a fee event and removed right after.
+
+use {
+    crate::{math, state::perpetuals::Perpetuals},
+    anchor_lang::prelude::*,
+};
+
+/// LP tokens minted by a single deposit
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct LpTranche {
+    /// Amount of LP tokens still tracked (in LP token decimals)
+    pub amount: u64,
+    /// Unix timestamp of the deposit
+    pub deposit_time: i64,
+}
+
+/// Liquidity provider deposit ledger account
+///
+/// PDA derived from the pool and the owner. Tranches are ordered from oldest to youngest.
+#[account]
+#[derive(Default, Debug)]
+pub struct LpLedger {
+    /// Pool the ledger applies to
+    pub pool: Pubkey,
+    /// Owner of the deposits
+    pub owner: Pubkey,
+    /// Deposit tranches, oldest first
+    pub tranches: Vec<LpTranche>,
+
+    /// Bump seed for the ledger PDA
+    pub bump: u8,
+}
+
+impl LpLedger {
+    /// Maximum number of tracked tranches
+    pub const MAX_TRANCHES: usize = 16;
+    /// Account size in bytes (8 byte discriminator + data + tranches)
+    pub const LEN: usize = 8
+        + std::mem::size_of::<LpLedger>()
+        + LpLedger::MAX_TRANCHES * std::mem::size_of::<LpTranche>();
+
+    /// Record a new deposit
+    ///
+    /// Once the ledger is full the deposit is merged into the youngest tranche, which
+    /// restarts its age (never lowers the fee).
+    pub fn add_tranche(&mut self, amount: u64, curtime: i64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let full = self.tranches.len() >= Self::MAX_TRANCHES;
+        match self.tranches.last_mut() {
+            Some(last) if last.deposit_time == curtime || full => {
+                last.amount = math::checked_add(last.amount, amount)?;
+                last.deposit_time = curtime;
+            }
+            _ => self.tranches.push(LpTranche {
+                amount,
+                deposit_time: curtime,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Calculate the extra remove liquidity fee rate for a withdrawal
+    ///
+    /// LP tokens are taken from the youngest tranches first. Each tranche pays
+    /// `max_fee * (decay_period - age) / decay_period`; LP tokens not tracked by the
+    /// ledger (deposited before it existed or received by transfer) have no known age and
+    /// pay the full max_fee, otherwise fresh deposits could skip the fee by moving to
+    /// another owner.
+    ///
+    /// # Arguments
+    /// * `lp_amount` - LP tokens being redeemed
+    /// * `max_fee` - Extra fee of a brand new deposit (in BPS)
+    /// * `decay_period` - Age after which no extra fee is charged (seconds)
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// Extra fee rate averaged over the redeemed LP tokens (in BPS)
+    pub fn get_early_remove_fee(
+        &self,
+        lp_amount: u64,
+        max_fee: u64,
+        decay_period: i64,
+        curtime: i64,
+    ) -> Result<u64> {
+        if lp_amount == 0 || max_fee == 0 || decay_period <= 0 {
+            return Ok(0);
+        }
+        let mut remaining = lp_amount;
+        let mut weighted_fee: u128 = 0;
+        for tranche in self.tranches.iter().rev() {
+            if remaining == 0 {
+                break;
+            }
+            let amount = std::cmp::min(remaining, tranche.amount);
+            remaining -= amount;
+
+            let age = curtime.saturating_sub(tranche.deposit_time).max(0);
+            if age >= decay_period {
+                continue;
+            }
+            let fee = math::checked_div(
+                math::checked_mul(max_fee as u128, (decay_period - age) as u128)?,
+                decay_period as u128,
+            )?;
+            weighted_fee = math::checked_add(weighted_fee, math::checked_mul(fee, amount as u128)?)?;
+        }
+        weighted_fee = math::checked_add(
+            weighted_fee,
+            math::checked_mul(max_fee as u128, remaining as u128)?,
+        )?;
+        let fee = math::checked_as_u64(math::checked_div(weighted_fee, lp_amount as u128)?)?;
+        Ok(std::cmp::min(fee, Perpetuals::BPS_POWER as u64))
+    }
+
+    /// Remove redeemed LP tokens from the ledger, youngest tranches first
+    pub fn remove(&mut self, lp_amount: u64) {
+        let mut remaining = lp_amount;
+        while remaining > 0 {
+            let Some(last) = self.tranches.last_mut() else {
+                break;
+            };
+            if last.amount > remaining {
+                last.amount -= remaining;
+                break;
+            }
+            remaining -= last.amount;
+            self.tranches.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_early_remove_fee() {
+        let mut ledger = LpLedger::default();
+        ledger.add_tranche(1000, 0).unwrap();
+        ledger.add_tranche(1000, 50).unwrap();
+        assert_eq!(2, ledger.tranches.len());
+
+        // youngest tranche first: 1000 at age 50 of 100 -> half of 200 BPS
+        assert_eq!(100, ledger.get_early_remove_fee(1000, 200, 100, 100).unwrap());
+        // both tranches: (1000 * 100 + 1000 * 0) / 2000
+        assert_eq!(50, ledger.get_early_remove_fee(2000, 200, 100, 100).unwrap());
+        // untracked LP tokens pay the max fee: (1000 * 100 + 1000 * 0 + 2000 * 200) / 4000
+        assert_eq!(125, ledger.get_early_remove_fee(4000, 200, 100, 100).unwrap());
+        assert_eq!(
+            200,
+            LpLedger::default().get_early_remove_fee(1000, 200, 100, 100).unwrap()
+        );
+        // fully aged
+        assert_eq!(0, ledger.get_early_remove_fee(1000, 200, 100, 150).unwrap());
+
+        ledger.remove(1500);
+        assert_eq!(vec![LpTranche { amount: 500, deposit_time: 0 }], ledger.tranches);
+
+        // full ledger merges into the youngest tranche
+        for i in 1..=LpLedger::MAX_TRANCHES as i64 {
+            ledger.add_tranche(1, i).unwrap();
+        }
+        assert_eq!(LpLedger::MAX_TRANCHES, ledger.tranches.len());
+        let last = ledger.tranches.last().unwrap();
+        assert_eq!(2, last.amount);
+        assert_eq!(LpLedger::MAX_TRANCHES as i64, last.deposit_time);
+    }
+}
@@ -0,0 +1,140 @@
+//! Market maker state
+//!
+//! Market makers are registered per pool by the multisig. They trade at reduced spreads
+//! and receive a rebate on swap fees until their discounted volume cap is reached, without
+//! changing the retail pricing parameters of the custodies.
+
+use {
+    crate::{
+        math,
+        state::{custody::Custody, perpetuals::Perpetuals},
+    },
+    anchor_lang::prelude::*,
+};
+
+/// Market maker account
+///
+/// PDA derived from the pool and market maker owner.
+#[account]
+#[derive(Default, Debug)]
+pub struct MarketMaker {
+    /// Wallet address of the market maker
+    pub owner: Pubkey,
+    /// Pool the market maker is registered with
+    pub pool: Pubkey,
+    /// Share of trade and swap spreads waived (in BPS, 10,000 = zero spread)
+    pub spread_discount: u64,
+    /// Share of swap fees rebated to the market maker (in BPS)
+    pub swap_rebate: u64,
+    /// Volume that can be traded at discounted pricing (USD_DECIMALS), 0 for unlimited
+    pub max_volume_usd: u64,
+    /// Volume traded at discounted pricing so far (USD_DECIMALS)
+    pub volume_usd: u64,
+
+    /// Bump seed for the market maker PDA
+    pub bump: u8,
+}
+
+impl MarketMaker {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<MarketMaker>();
+
+    /// Validate market maker parameters
+    ///
+    /// # Returns
+    /// true if discount and rebate are within 0-100%
+    pub fn validate(&self) -> bool {
+        (self.spread_discount as u128) <= Perpetuals::BPS_POWER
+            && (self.swap_rebate as u128) <= Perpetuals::BPS_POWER
+    }
+
+    /// Check whether the given volume still fits under the discounted volume cap
+    ///
+    /// # Arguments
+    /// * `volume_usd` - Volume of the trade in USD
+    ///
+    /// # Returns
+    /// true if the trade is eligible for market maker pricing
+    pub fn has_capacity(&self, volume_usd: u64) -> Result<bool> {
+        Ok(self.max_volume_usd == 0
+            || math::checked_add(self.volume_usd, volume_usd)? <= self.max_volume_usd)
+    }
+
+    /// Record volume traded at discounted pricing
+    pub fn add_volume(&mut self, volume_usd: u64) -> Result<()> {
+        self.volume_usd = math::checked_add(self.volume_usd, volume_usd)?;
+        Ok(())
+    }
+
+    /// Build a copy of the custody with market maker spreads applied
+    ///
+    /// # Arguments
+    /// * `custody` - Custody to copy pricing from
+    ///
+    /// # Returns
+    /// Custody with trade and swap spreads reduced by spread_discount
+    pub fn get_custody(&self, custody: &Custody) -> Result<Custody> {
+        let mut mm_custody = custody.clone();
+        mm_custody.pricing.trade_spread_long =
+            self.get_spread(custody.pricing.trade_spread_long)?;
+        mm_custody.pricing.trade_spread_short =
+            self.get_spread(custody.pricing.trade_spread_short)?;
+        mm_custody.pricing.swap_spread = self.get_spread(custody.pricing.swap_spread)?;
+        Ok(mm_custody)
+    }
+
+    /// Calculate swap fee after market maker rebate
+    ///
+    /// # Arguments
+    /// * `fee` - Retail fee amount in tokens
+    ///
+    /// # Returns
+    /// Fee amount after rebate
+    pub fn get_rebated_fee(&self, fee: u64) -> Result<u64> {
+        math::checked_sub(
+            fee,
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(fee as u128, self.swap_rebate as u128)?,
+                Perpetuals::BPS_POWER,
+            )?)?,
+        )
+    }
+
+    fn get_spread(&self, spread: u64) -> Result<u64> {
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(
+                spread as u128,
+                math::checked_sub(Perpetuals::BPS_POWER, self.spread_discount as u128)?,
+            )?,
+            Perpetuals::BPS_POWER,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_volume_cap() {
+        let mut market_maker = MarketMaker {
+            max_volume_usd: 1_000,
+            ..Default::default()
+        };
+        assert!(market_maker.has_capacity(1_000).unwrap());
+        assert!(!market_maker.has_capacity(1_001).unwrap());
+
+        // the checked volume is the recorded one
+        market_maker.add_volume(600).unwrap();
+        assert_eq!(market_maker.volume_usd, 600);
+        assert!(market_maker.has_capacity(400).unwrap());
+        assert!(!market_maker.has_capacity(401).unwrap());
+        market_maker.add_volume(400).unwrap();
+        assert!(market_maker.has_capacity(0).unwrap());
+        assert!(!market_maker.has_capacity(1).unwrap());
+
+        // unlimited volume
+        market_maker.max_volume_usd = 0;
+        assert!(market_maker.has_capacity(u64::MAX).unwrap());
+    }
+}
@@ -1,7 +1,323 @@
+pub mod crank_state;
+pub mod backstop;
+pub mod compliance_freeze;
 pub mod custody;
+pub mod custody_migration;
+pub mod force_settlement;
+pub mod listing;
+pub mod lp_allowlist;
+pub mod lp_index;
+pub mod lp_ledger;
+pub mod market_maker;
 pub mod multisig;
 pub mod oracle;
+pub mod order_commitment;
+pub mod owner_positions;
+pub mod pending_claim;
 pub mod perpetuals;
 pub mod pool;
+pub mod pool_migration;
 pub mod position;
+pub mod position_hook;
+pub mod position_summary;
+pub mod risk_oracle;
+pub mod staged_params;
+pub mod trader_activity;
 
+
+#[cfg(test)]
+mod test {
+    //! Account layout regression tests
+    //!
+    //! Client SDKs deserialize accounts by hand using these offsets, so any change
+    //! here must be intentional and shipped together with an SDK update.
+
+    use {
+        super::{
+            custody::{Custody, ExchangeRateType, FeesMode, MarketLifecycle, VolatilityType},
+            multisig::Multisig,
+            oracle::CustomOracle,
+            perpetuals::{Permissions, Perpetuals, PerpetualsV0},
+            pool::{Pool, TokenRatios},
+            position::{Position, RiskTier, Side},
+            position_summary::PositionSummary,
+        },
+        anchor_lang::prelude::*,
+        std::mem::{offset_of, size_of},
+    };
+
+    const KEY: Pubkey = Pubkey::new_from_array([1; 32]);
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Returns the serialized offset of the field modified by `update`
+    fn get_offset<T: AccountSerialize + Clone>(account: &T, update: impl FnOnce(&mut T)) -> usize {
+        let mut updated = account.clone();
+        update(&mut updated);
+        let data = serialize(account);
+        let updated_data = serialize(&updated);
+        assert_eq!(data.len(), updated_data.len());
+        data.iter()
+            .zip(updated_data.iter())
+            .position(|(a, b)| a != b)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_discriminators() {
+        // sha256("account:<Name>")[..8]
+        assert_eq!(Perpetuals::DISCRIMINATOR, [28, 167, 98, 191, 104, 82, 108, 196]);
+        assert_eq!(Pool::DISCRIMINATOR, [241, 154, 109, 4, 17, 177, 109, 188]);
+        assert_eq!(Custody::DISCRIMINATOR, [1, 184, 48, 81, 93, 131, 63, 145]);
+        assert_eq!(Position::DISCRIMINATOR, [170, 188, 143, 228, 122, 64, 247, 208]);
+        assert_eq!(Multisig::DISCRIMINATOR, [224, 116, 121, 186, 68, 161, 79, 236]);
+        assert_eq!(CustomOracle::DISCRIMINATOR, [227, 170, 164, 218, 127, 16, 35, 223]);
+
+        let data = serialize(&Position::default());
+        assert_eq!(&data[..8], Position::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn test_perpetuals_layout() {
+        let perpetuals = Perpetuals::default();
+        let data = serialize(&perpetuals);
+        assert_eq!(77, data.len());
+        assert!(data.len() <= Perpetuals::LEN);
+
+        assert_eq!(8, get_offset(&perpetuals, |x| x.permissions.allow_swap = true));
+        // pools: u32 length prefix at 16
+        assert_eq!(20, get_offset(&perpetuals, |x| x.transfer_authority_bump = 1));
+        assert_eq!(21, get_offset(&perpetuals, |x| x.perpetuals_bump = 1));
+        assert_eq!(22, get_offset(&perpetuals, |x| x.inception_time = 1));
+        assert_eq!(30, get_offset(&perpetuals, |x| x.risk_oracle.feed = KEY));
+        assert_eq!(62, get_offset(&perpetuals, |x| x.risk_oracle.data_offset = 1));
+        assert_eq!(64, get_offset(&perpetuals, |x| x.risk_oracle.incident_mask = 1));
+        assert_eq!(72, get_offset(&perpetuals, |x| x.risk_oracle.max_age_sec = 1));
+        assert_eq!(76, get_offset(&perpetuals, |x| x.allow_cpi = true));
+    }
+
+    #[test]
+    fn test_legacy_perpetuals_layout() {
+        // The original layout is sized for exactly its pools
+        let pools = vec![KEY, KEY];
+        let v0 = PerpetualsV0 {
+            permissions: Permissions {
+                allow_swap: true,
+                ..Permissions::default()
+            },
+            pools: pools.clone(),
+            transfer_authority_bump: 254,
+            perpetuals_bump: 253,
+            ..PerpetualsV0::default()
+        };
+        let mut data = Perpetuals::DISCRIMINATOR.to_vec();
+        v0.serialize(&mut data).unwrap();
+        data.resize(PerpetualsV0::LEN + pools.len() * 32, 0);
+
+        let upgraded = Perpetuals::from(PerpetualsV0::load(&data).unwrap());
+        assert!(upgraded.permissions.allow_swap);
+        assert!(!upgraded.allow_cpi);
+        assert_eq!(upgraded.pools, pools);
+        assert_eq!(upgraded.perpetuals_bump, 253);
+
+        // Legacy accounts are realloced on upgrade, the current layout fits the old pools
+        let mut data = serialize(&upgraded);
+        assert!(data.len() <= Perpetuals::LEN + pools.len() * 32);
+
+        // Current accounts are not loaded as legacy ones
+        data.resize(Perpetuals::LEN + pools.len() * 32, 0);
+        assert!(PerpetualsV0::load(&data).is_none());
+    }
+
+    #[test]
+    fn test_pool_layout() {
+        // name: 4 + 4, custodies: 4 + 32, ratios: 4 + 24
+        let pool = Pool {
+            name: "pool".to_string(),
+            custodies: vec![KEY],
+            ratios: vec![TokenRatios::default()],
+            ..Pool::default()
+        };
+        let data = serialize(&pool);
+        assert_eq!(338, data.len());
+        assert_eq!(&data[8..12], &4u32.to_le_bytes());
+        assert_eq!(&data[16..20], &1u32.to_le_bytes());
+        assert_eq!(&data[20..52], KEY.as_ref());
+        assert_eq!(&data[52..56], &1u32.to_le_bytes());
+
+        assert_eq!(80, get_offset(&pool, |x| x.aum_usd = 1));
+        assert_eq!(96, get_offset(&pool, |x| x.bump = 1));
+        assert_eq!(97, get_offset(&pool, |x| x.lp_token_bump = 1));
+        assert_eq!(98, get_offset(&pool, |x| x.inception_time = 1));
+        assert_eq!(106, get_offset(&pool, |x| x.lp_allowlist_enabled = true));
+        assert_eq!(107, get_offset(&pool, |x| x.max_positions_per_owner = 1));
+        assert_eq!(111, get_offset(&pool, |x| x.fee_custody = KEY));
+        assert_eq!(143, get_offset(&pool, |x| x.event_seq = 1));
+        assert_eq!(151, get_offset(&pool, |x| x.performance_fee_bps = 1));
+        assert_eq!(159, get_offset(&pool, |x| x.performance_fee_account = KEY));
+        assert_eq!(191, get_offset(&pool, |x| x.performance_epoch_sec = 1));
+        assert_eq!(199, get_offset(&pool, |x| x.performance_epoch_start = 1));
+        assert_eq!(207, get_offset(&pool, |x| x.lp_price_hwm = 1));
+        assert_eq!(215, get_offset(&pool, |x| x.liquidation_auction_slots = 1));
+        assert_eq!(223, get_offset(&pool, |x| x.liquidation_auction_start_share = 1));
+        assert_eq!(231, get_offset(&pool, |x| x.max_trades_per_slot = 1));
+        assert_eq!(233, get_offset(&pool, |x| x.position_hook.program = KEY));
+        assert_eq!(265, get_offset(&pool, |x| x.position_hook.events = 1));
+        assert_eq!(266, get_offset(&pool, |x| x.position_hook.required = true));
+        assert_eq!(267, get_offset(&pool, |x| x.position_hook.compute_budget = 1));
+        assert_eq!(271, get_offset(&pool, |x| x.imbalance_fee_bps = 1));
+        assert_eq!(279, get_offset(&pool, |x| x.backstop.value_usd = 1));
+        assert_eq!(287, get_offset(&pool, |x| x.backstop.fee_boost_bps = 1));
+        assert_eq!(295, get_offset(&pool, |x| x.backstop.absorbed_loss_usd = 1));
+        assert_eq!(303, get_offset(&pool, |x| x.backstop.token_bump = 1));
+        assert_eq!(304, get_offset(&pool, |x| x.compliance_freeze_enabled = true));
+        assert_eq!(305, get_offset(&pool, |x| x.max_lp_supply = 1));
+        assert_eq!(313, get_offset(&pool, |x| x.collateral_withdrawal_limit.min_size_usd = 1));
+        assert_eq!(321, get_offset(&pool, |x| x.collateral_withdrawal_limit.max_withdrawal_bps = 1));
+        assert_eq!(329, get_offset(&pool, |x| x.collateral_withdrawal_limit.window_sec = 1));
+        assert_eq!(337, get_offset(&pool, |x| x.allow_cpi = true));
+    }
+
+    #[test]
+    fn test_custody_layout() {
+        let custody = Custody::default();
+        let data = serialize(&custody);
+        assert_eq!(2304, data.len());
+        assert!(data.len() <= Custody::LEN);
+
+        assert_eq!(8, get_offset(&custody, |x| x.pool = KEY));
+        assert_eq!(40, get_offset(&custody, |x| x.mint = KEY));
+        assert_eq!(72, get_offset(&custody, |x| x.token_account = KEY));
+        assert_eq!(104, get_offset(&custody, |x| x.decimals = 1));
+        assert_eq!(105, get_offset(&custody, |x| x.is_stable = true));
+        assert_eq!(106, get_offset(&custody, |x| x.is_virtual = true));
+        assert_eq!(107, get_offset(&custody, |x| x.oracle.oracle_account = KEY));
+        assert_eq!(216, get_offset(&custody, |x| x.oracle.min_update_interval_sec = 1));
+        assert_eq!(220, get_offset(&custody, |x| x.oracle.max_updates_per_slot = 1));
+        assert_eq!(221, get_offset(&custody, |x| x.pricing.use_ema = true));
+        assert_eq!(271, get_offset(&custody, |x| x.pricing.maintenance_leverage = 1));
+        assert_eq!(345, get_offset(&custody, |x| x.pricing.max_power = 1));
+        assert_eq!(346, get_offset(&custody, |x| x.pricing.convexity_vol = 1));
+        assert_eq!(354, get_offset(&custody, |x| x.permissions.allow_swap = true));
+        assert_eq!(362, get_offset(&custody, |x| x.fees.mode = FeesMode::Optimal));
+        assert_eq!(531, get_offset(&custody, |x| x.borrow_rate.base_rate = 1));
+        assert_eq!(563, get_offset(&custody, |x| x.expiry_time = 1));
+        assert_eq!(571, get_offset(&custody, |x| x.exchange_rate.rate_type = ExchangeRateType::Custom));
+        assert_eq!(608, get_offset(&custody, |x| x.assets.collateral = 1));
+        assert_eq!(648, get_offset(&custody, |x| x.collected_fees.swap_usd = 1));
+        assert_eq!(744, get_offset(&custody, |x| x.volume_stats.swap_usd = 1));
+        assert_eq!(840, get_offset(&custody, |x| x.trade_stats.profit_usd = 1));
+        assert_eq!(872, get_offset(&custody, |x| x.long_positions.open_positions = 1));
+        assert_eq!(968, get_offset(&custody, |x| x.short_positions.open_positions = 1));
+        assert_eq!(1064, get_offset(&custody, |x| x.borrow_rate_state.current_rate = 1));
+        assert_eq!(1096, get_offset(&custody, |x| x.settlement_price = 1));
+        assert_eq!(1104, get_offset(&custody, |x| x.exchange_rate_state.rate = 1));
+        assert_eq!(1120, get_offset(&custody, |x| x.rate_history.snapshots[0].time = 1));
+        assert_eq!(1888, get_offset(&custody, |x| x.rate_history.next_index = 1));
+        assert_eq!(1889, get_offset(&custody, |x| x.claim_queue.next_claim_id = 1));
+        assert_eq!(1913, get_offset(&custody, |x| x.oracle_safe_mode = true));
+        assert_eq!(1914, get_offset(&custody, |x| x.stats_epoch.start_time = 1));
+        assert_eq!(2114, get_offset(&custody, |x| x.pnl_reserve = 1));
+        assert_eq!(2122, get_offset(&custody, |x| x.oracle_update_slot = 1));
+        assert_eq!(2130, get_offset(&custody, |x| x.oracle_slot_updates = 1));
+        assert_eq!(2131, get_offset(&custody, |x| x.entry_fee_tiers[0].min_size_usd = 1));
+        assert_eq!(2139, get_offset(&custody, |x| x.entry_fee_tiers[0].fee = 1));
+        assert_eq!(2195, get_offset(&custody, |x| x.lifecycle = MarketLifecycle::Retired));
+        assert_eq!(2196, get_offset(&custody, |x| x.interest_epoch.id = 1));
+        assert_eq!(2200, get_offset(&custody, |x| x.interest_epoch.prev_end_interest = 1));
+        assert_eq!(2216, get_offset(&custody, |x| x.volatility.vol_type = VolatilityType::Custom));
+        assert_eq!(2217, get_offset(&custody, |x| x.volatility.vol_account = KEY));
+        assert_eq!(2261, get_offset(&custody, |x| x.volatility.ref_vol = 1));
+        assert_eq!(2285, get_offset(&custody, |x| x.volatility_state.vol = 1));
+        assert_eq!(2293, get_offset(&custody, |x| x.volatility_state.last_update = 1));
+        assert_eq!(2301, get_offset(&custody, |x| x.allow_cpi = true));
+        assert_eq!(2302, get_offset(&custody, |x| x.bump = 1));
+        assert_eq!(2303, get_offset(&custody, |x| x.token_account_bump = 1));
+    }
+
+    #[test]
+    fn test_position_layout() {
+        let position = Position::default();
+        let data = serialize(&position);
+        assert_eq!(304, data.len());
+        assert!(data.len() <= Position::LEN);
+
+        assert_eq!(8, get_offset(&position, |x| x.owner = KEY));
+        assert_eq!(40, get_offset(&position, |x| x.pool = KEY));
+        assert_eq!(72, get_offset(&position, |x| x.custody = KEY));
+        assert_eq!(104, get_offset(&position, |x| x.collateral_custody = KEY));
+        assert_eq!(136, get_offset(&position, |x| x.open_time = 1));
+        assert_eq!(144, get_offset(&position, |x| x.update_time = 1));
+        assert_eq!(152, get_offset(&position, |x| x.side = Side::Long));
+        assert_eq!(153, get_offset(&position, |x| x.power = 1));
+        assert_eq!(154, get_offset(&position, |x| x.price = 1));
+        assert_eq!(162, get_offset(&position, |x| x.size_usd = 1));
+        assert_eq!(170, get_offset(&position, |x| x.borrow_size_usd = 1));
+        assert_eq!(178, get_offset(&position, |x| x.collateral_usd = 1));
+        assert_eq!(186, get_offset(&position, |x| x.unrealized_profit_usd = 1));
+        assert_eq!(194, get_offset(&position, |x| x.unrealized_loss_usd = 1));
+        assert_eq!(202, get_offset(&position, |x| x.cumulative_interest_snapshot = 1));
+        assert_eq!(218, get_offset(&position, |x| x.locked_amount = 1));
+        assert_eq!(226, get_offset(&position, |x| x.collateral_amount = 1));
+        assert_eq!(234, get_offset(&position, |x| x.risk_tier = RiskTier::Danger));
+        assert_eq!(235, get_offset(&position, |x| x.liquidation_auction_slot = 1));
+        assert_eq!(243, get_offset(&position, |x| x.interest_epoch = 1));
+        assert_eq!(247, get_offset(&position, |x| x.payout_account = KEY));
+        assert_eq!(279, get_offset(&position, |x| x.withdrawal_window_start = 1));
+        assert_eq!(287, get_offset(&position, |x| x.withdrawal_window_collateral_usd = 1));
+        assert_eq!(295, get_offset(&position, |x| x.withdrawn_collateral_usd = 1));
+        assert_eq!(303, get_offset(&position, |x| x.bump = 1));
+    }
+
+    #[test]
+    fn test_position_summary_layout() {
+        let summary = PositionSummary::default();
+        let data = serialize(&summary);
+        assert_eq!(30, data.len());
+        assert!(data.len() <= PositionSummary::LEN);
+
+        assert_eq!(8, get_offset(&summary, |x| x.owner_hash = 1));
+        assert_eq!(16, get_offset(&summary, |x| x.update_time = 1));
+        assert_eq!(24, get_offset(&summary, |x| x.custody_index = 1));
+        assert_eq!(25, get_offset(&summary, |x| x.side = Side::Long));
+        assert_eq!(26, get_offset(&summary, |x| x.power = 1));
+        assert_eq!(27, get_offset(&summary, |x| x.risk_tier = RiskTier::Danger));
+        assert_eq!(28, get_offset(&summary, |x| x.size_bucket = 1));
+        assert_eq!(29, get_offset(&summary, |x| x.bump = 1));
+    }
+
+    #[test]
+    fn test_custom_oracle_layout() {
+        let oracle = CustomOracle::default();
+        let data = serialize(&oracle);
+        assert_eq!(44, data.len());
+        assert!(data.len() <= CustomOracle::LEN);
+
+        assert_eq!(8, get_offset(&oracle, |x| x.price = 1));
+        assert_eq!(16, get_offset(&oracle, |x| x.expo = 1));
+        assert_eq!(20, get_offset(&oracle, |x| x.conf = 1));
+        assert_eq!(28, get_offset(&oracle, |x| x.ema = 1));
+        assert_eq!(36, get_offset(&oracle, |x| x.publish_time = 1));
+    }
+
+    #[test]
+    fn test_multisig_layout() {
+        // zero-copy account: offsets below are relative to the end of the discriminator
+        assert_eq!(213, size_of::<Multisig>());
+        assert_eq!(8 + 213, Multisig::LEN);
+
+        assert_eq!(0, offset_of!(Multisig, num_signers));
+        assert_eq!(1, offset_of!(Multisig, num_signed));
+        assert_eq!(2, offset_of!(Multisig, min_signatures));
+        assert_eq!(3, offset_of!(Multisig, instruction_accounts_len));
+        assert_eq!(4, offset_of!(Multisig, instruction_data_len));
+        assert_eq!(6, offset_of!(Multisig, instruction_hash));
+        assert_eq!(14, offset_of!(Multisig, signers));
+        assert_eq!(206, offset_of!(Multisig, signed));
+        assert_eq!(212, offset_of!(Multisig, bump));
+    }
+}
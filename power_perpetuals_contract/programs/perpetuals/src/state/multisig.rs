@@ -5,7 +5,6 @@
 
 use {
     crate::{error::PerpetualsError, math},
-    ahash::AHasher,
     anchor_lang::prelude::*,
     std::hash::Hasher,
 };
@@ -69,6 +68,84 @@ pub enum AdminInstruction {
     SetTestTime,
     /// Upgrade custody account
     UpgradeCustody,
+    /// Set custody expiry time (power futures)
+    SetCustodyExpiry,
+    /// Register or update a market maker
+    SetMarketMaker,
+    /// Remove a market maker
+    RemoveMarketMaker,
+    /// Schedule custody migration to a new mint
+    ScheduleCustodyMigration,
+    /// Execute custody migration to a new mint
+    MigrateCustodyMint,
+    /// Configure pool liquidity provider allowlist
+    SetLpAllowlist,
+    /// Configure pool crank schedule and bounty
+    SetCrankConfig,
+    /// Reconcile custody bookkeeping with its token account balance
+    ReconcileCustody,
+    /// Sweep excess SOL into the fee vault and withdraw from it
+    SweepSol,
+    /// Configure custody liquid staking exchange rate source
+    SetCustodyExchangeRate,
+    /// Configure pool limit of open positions per owner
+    SetPositionLimit,
+    /// Configure pool custody trade fees can be paid in
+    SetFeeCustody,
+    /// Freeze a pool and start migrating it to a new pool
+    ExportPoolState,
+    /// Import a custody of a migrated pool into the target pool
+    ImportPoolState,
+    /// Import positions of a migrated pool into the target pool
+    ImportPoolPositions,
+    /// Complete a pool migration and mint the migrated LP supply
+    FinalizePoolMigration,
+    /// Start a new custody volume and fee stats epoch
+    StartStatsEpoch,
+    /// Schedule the emergency settlement of a position at an admin supplied price
+    ScheduleForceSettlement,
+    /// Execute a scheduled emergency settlement of a position
+    ForceSettlePosition,
+    /// Configure pool performance fee
+    SetPerformanceFee,
+    /// Configure pool liquidation reward auction
+    SetLiquidationAuction,
+    /// Change custody market lifecycle
+    SetCustodyLifecycle,
+    /// Set pool trade rate limit
+    SetTradeRateLimit,
+    /// Create an LP index
+    AddLpIndex,
+    /// Add or reweight an LP index component
+    SetLpIndexComponent,
+    /// Set pool position hook
+    SetPositionHook,
+    /// Set protocol risk oracle
+    SetRiskOracle,
+    /// Set pool open interest imbalance fee
+    SetImbalanceFee,
+    /// Set pool backstop tranche
+    SetBackstopTranche,
+    /// Set pool market listing config
+    SetListingConfig,
+    /// Veto a pending market listing
+    VetoListing,
+    /// Add a new custody with params staged in a scratch account
+    AddCustodyFromStaged,
+    /// Configure custody compliance freeze list
+    SetComplianceFreeze,
+    /// Configure pool LP supply cap
+    SetLpSupplyCap,
+    /// Configure pool collateral withdrawal limit
+    SetCollateralWithdrawalLimit,
+    /// Configure custody volatility feed
+    SetCustodyVolatility,
+    /// Upgrade perpetuals account layout
+    UpgradePerpetuals,
+    /// Upgrade pool account layout
+    UpgradePool,
+    /// Allow or disallow CPI invocation of pool trading instructions
+    SetPoolCpi,
 }
 
 impl Multisig {
@@ -254,9 +331,12 @@ impl Multisig {
             //multisig.pack(*multisig_account.try_borrow_mut_data()?)?;
 
             math::checked_sub(self.min_signatures, 1)
+        } else if self.num_signed >= self.min_signatures {
+            // replay of an executed instruction, including by one of its signers
+            err!(PerpetualsError::MultisigAlreadyExecuted)
         } else if self.signed[signer_idx] == 1 {
             err!(PerpetualsError::MultisigAlreadySigned)
-        } else if self.num_signed < self.min_signatures {
+        } else {
             // count the signature in
             self.num_signed = math::checked_add(self.num_signed, 1)?;
             self.signed[signer_idx] = 1;
@@ -266,21 +346,20 @@ impl Multisig {
             } else {
                 math::checked_sub(self.min_signatures, self.num_signed)
             }
-        } else {
-            err!(PerpetualsError::MultisigAlreadyExecuted)
         }
     }
 
     /// Remove a signature from the multisig
     /// 
     /// Allows an admin to revoke their signature before execution.
-    /// Useful if instruction parameters need to change.
+    /// Useful if instruction parameters need to change. Signatures of an executed
+    /// instruction can't be revoked, otherwise re-signing would execute it again.
     /// 
     /// # Arguments
     /// * `signer_account` - Account info of the signer removing their signature
     /// 
     /// # Returns
-    /// Error if signer is not authorized or not found
+    /// Error if signer is not authorized or not found, or instruction was executed
     pub fn unsign_multisig(&mut self, signer_account: &AccountInfo) -> Result<()> {
         // return early if not a signer
         if !signer_account.is_signer {
@@ -304,6 +383,11 @@ impl Multisig {
             return Ok(());
         }
 
+        // executed instructions are final
+        if self.num_signed >= self.min_signatures {
+            return err!(PerpetualsError::MultisigAlreadyExecuted);
+        }
+
         // remove signature
         self.num_signed = math::checked_sub(self.num_signed, 1)?;
         self.signed[signer_idx] = 0;
@@ -337,4 +421,241 @@ impl Multisig {
     pub fn is_signer(&self, key: &Pubkey) -> Result<bool> {
         Ok(self.get_signer_index(key).is_ok())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_account_info(key: Pubkey, is_signer: bool) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            is_signer,
+            false,
+            Box::leak(Box::new(0)),
+            Box::leak(Box::new([])),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    fn get_signers(num_signers: usize) -> Vec<AccountInfo<'static>> {
+        (0..num_signers)
+            .map(|idx| get_account_info(Pubkey::new_from_array([idx as u8 + 1; 32]), true))
+            .collect()
+    }
+
+    fn get_multisig(
+        num_signers: usize,
+        min_signatures: u8,
+    ) -> (Multisig, Vec<AccountInfo<'static>>) {
+        let signers = get_signers(num_signers);
+        let mut multisig = Multisig::default();
+        multisig.set_signers(&signers, min_signatures).unwrap();
+        (multisig, signers)
+    }
+
+    fn get_accounts() -> Vec<AccountInfo<'static>> {
+        vec![
+            get_account_info(Pubkey::new_from_array([100; 32]), false),
+            get_account_info(Pubkey::new_from_array([101; 32]), false),
+        ]
+    }
+
+    #[test]
+    fn test_set_signers() {
+        let mut multisig = Multisig::default();
+        let signers = get_signers(Multisig::MAX_SIGNERS + 1);
+
+        assert!(multisig
+            .set_signers(
+                &signers[..Multisig::MAX_SIGNERS],
+                Multisig::MAX_SIGNERS as u8
+            )
+            .is_ok());
+        assert_eq!(multisig.num_signers, Multisig::MAX_SIGNERS as u8);
+        assert!(multisig.set_signers(&signers, 1).is_err());
+        assert!(multisig.set_signers(&signers[..2], 3).is_err());
+        assert!(multisig.set_signers(&signers[..2], 0).is_err());
+        assert!(multisig.set_signers(&[], 1).is_err());
+        let duplicates = [signers[0].clone(), signers[0].clone()];
+        assert!(multisig.set_signers(&duplicates, 1).is_err());
+    }
+
+    #[test]
+    fn test_sign_max_signers_threshold() {
+        let (mut multisig, signers) =
+            get_multisig(Multisig::MAX_SIGNERS, Multisig::MAX_SIGNERS as u8);
+        let accounts = get_accounts();
+
+        for (idx, signer) in signers.iter().enumerate() {
+            assert_eq!(
+                multisig.sign_multisig(signer, &accounts, &[1]).unwrap(),
+                (Multisig::MAX_SIGNERS - idx - 1) as u8
+            );
+        }
+        assert_eq!(multisig.num_signed, Multisig::MAX_SIGNERS as u8);
+
+        let outsider = get_account_info(Pubkey::new_unique(), true);
+        assert_eq!(
+            multisig
+                .sign_multisig(&outsider, &accounts, &[1])
+                .unwrap_err(),
+            PerpetualsError::MultisigAccountNotAuthorized.into()
+        );
+        let unsigned = get_account_info(*signers[0].key, false);
+        assert_eq!(
+            multisig
+                .sign_multisig(&unsigned, &accounts, &[1])
+                .unwrap_err(),
+            ProgramError::MissingRequiredSignature.into()
+        );
+    }
+
+    #[test]
+    fn test_sign_changed_instruction_resets() {
+        let (mut multisig, signers) = get_multisig(3, 2);
+        let accounts = get_accounts();
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            1
+        );
+
+        // changed params
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[1], &accounts, &[2])
+                .unwrap(),
+            1
+        );
+        assert_eq!(multisig.num_signed, 1);
+        assert_eq!(multisig.signed[0], 0);
+
+        // changed accounts
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts[..1], &[2])
+                .unwrap(),
+            1
+        );
+        assert_eq!(multisig.num_signed, 1);
+        assert_eq!(multisig.signed[1], 0);
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[1], &accounts[..1], &[2])
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_unsign_and_resign() {
+        let (mut multisig, signers) = get_multisig(3, 2);
+        let accounts = get_accounts();
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap_err(),
+            PerpetualsError::MultisigAlreadySigned.into()
+        );
+
+        multisig.unsign_multisig(&signers[0]).unwrap();
+        assert_eq!(multisig.num_signed, 0);
+        assert_eq!(multisig.signed[0], 0);
+        // unsigning twice or without a signature is a no-op
+        multisig.unsign_multisig(&signers[0]).unwrap();
+        multisig.unsign_multisig(&signers[1]).unwrap();
+        assert_eq!(multisig.num_signed, 0);
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[1], &accounts, &[1])
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_replay_executed_instruction() {
+        let (mut multisig, signers) = get_multisig(3, 2);
+        let accounts = get_accounts();
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[1], &accounts, &[1])
+                .unwrap(),
+            0
+        );
+
+        for signer in &signers {
+            assert_eq!(
+                multisig.sign_multisig(signer, &accounts, &[1]).unwrap_err(),
+                PerpetualsError::MultisigAlreadyExecuted.into()
+            );
+        }
+
+        // revoking a signature must not re-arm the executed instruction
+        assert_eq!(
+            multisig.unsign_multisig(&signers[0]).unwrap_err(),
+            PerpetualsError::MultisigAlreadyExecuted.into()
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[2], &accounts, &[1])
+                .unwrap_err(),
+            PerpetualsError::MultisigAlreadyExecuted.into()
+        );
+
+        // a new instruction starts a new round
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[2], &accounts, &[2])
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_single_signer() {
+        let (mut multisig, signers) = get_multisig(1, 1);
+        let accounts = get_accounts();
+
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], &accounts, &[1])
+                .unwrap(),
+            0
+        );
+        multisig.unsign_multisig(&signers[0]).unwrap();
+    }
+}
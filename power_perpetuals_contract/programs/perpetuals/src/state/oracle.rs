@@ -4,7 +4,12 @@
 //! and provides utilities for price normalization, conversion, and validation.
 
 use {
-    crate::{error::PerpetualsError, math, state::perpetuals::Perpetuals},
+    crate::{
+        conversions,
+        error::PerpetualsError,
+        math::{self, RoundingDirection},
+        state::perpetuals::Perpetuals,
+    },
     anchor_lang::prelude::*,
     core::cmp::Ordering,
 };
@@ -17,9 +22,10 @@ const ORACLE_PRICE_SCALE: u64 = 1_000_000_000;
 const ORACLE_MAX_PRICE: u64 = (1 << 28) - 1;
 
 /// Supported oracle types for price feeds
-#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub enum OracleType {
     /// No oracle configured
+    #[default]
     None,
     /// Custom oracle implementation
     Custom,
@@ -27,12 +33,6 @@ pub enum OracleType {
     Pyth,
 }
 
-impl Default for OracleType {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 /// Oracle price representation with mantissa and exponent
 /// 
 /// Price = price * 10^exponent
@@ -58,6 +58,79 @@ pub struct OracleParams {
     pub max_price_error: u64,
     /// Maximum age of price data in seconds before considered stale
     pub max_price_age_sec: u32,
+    /// Half-life of the on-chain EMA of custom oracles in seconds (0 = EMA follows spot price)
+    pub ema_half_life_sec: u32,
+    /// Maximum price age for closing positions (0 = use max_price_age_sec)
+    pub max_price_age_close_sec: u32,
+    /// Maximum price age for liquidations (0 = use max_price_age_sec)
+    pub max_price_age_liquidate_sec: u32,
+    /// Maximum price age for adding and removing liquidity (0 = use max_price_age_sec)
+    pub max_price_age_liquidity_sec: u32,
+    /// Oracle updates older than max_price_age_sec times this multiple put the custody
+    /// in safe mode (0 = heartbeat not enforced)
+    pub heartbeat_mult: u32,
+    /// Prices older than the close max price age but within this multiple of it can still
+    /// be used to close positions (0 or 1 = no grace)
+    pub close_grace_mult: u32,
+    /// Extra spread applied to exit prices of closes using the grace (BPS)
+    pub close_grace_spread: u64,
+    /// Minimum publish time interval between accepted permissionless updates in seconds
+    /// (0 = no minimum)
+    pub min_update_interval_sec: u32,
+    /// Maximum number of permissionless updates accepted per slot (0 = unlimited)
+    pub max_updates_per_slot: u8,
+}
+
+/// Oracle configuration of the original custody layout
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct OracleParamsV0 {
+    /// Public key of the oracle account
+    pub oracle_account: Pubkey,
+    /// Type of oracle (Pyth, Custom, etc.)
+    pub oracle_type: OracleType,
+    /// The oracle_authority pubkey is allowed to sign permissionless off-chain price updates.
+    pub oracle_authority: Pubkey,
+    /// Maximum acceptable price error in basis points (BPS)
+    pub max_price_error: u64,
+    /// Maximum age of price data in seconds before considered stale
+    pub max_price_age_sec: u32,
+}
+
+impl From<OracleParamsV0> for OracleParams {
+    fn from(oracle: OracleParamsV0) -> Self {
+        OracleParams {
+            oracle_account: oracle.oracle_account,
+            oracle_type: oracle.oracle_type,
+            oracle_authority: oracle.oracle_authority,
+            max_price_error: oracle.max_price_error,
+            max_price_age_sec: oracle.max_price_age_sec,
+            ema_half_life_sec: 0,
+            max_price_age_close_sec: 0,
+            max_price_age_liquidate_sec: 0,
+            max_price_age_liquidity_sec: 0,
+            heartbeat_mult: 0,
+            close_grace_mult: 0,
+            close_grace_spread: 0,
+            min_update_interval_sec: 0,
+            max_updates_per_slot: 0,
+        }
+    }
+}
+
+/// Operation a price is read for, selects the applicable max price age
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum OracleOperation {
+    /// Opening or modifying positions, swaps
+    #[default]
+    Open,
+    /// Closing and settling positions
+    Close,
+    /// Liquidating positions
+    Liquidate,
+    /// Adding and removing liquidity
+    Liquidity,
+    /// Closing positions with a price past the close max age, within the close grace
+    CloseGrace,
 }
 
 /// Custom oracle account structure for storing price data on-chain
@@ -70,7 +143,7 @@ pub struct CustomOracle {
     pub expo: i32,
     /// Price confidence interval (uncertainty)
     pub conf: u64,
-    /// Exponential moving average (EMA) price
+    /// Exponential moving average (EMA) price, computed on-chain on each update
     pub ema: u64,
     /// Unix timestamp when price was last published
     pub publish_time: i64,
@@ -79,14 +152,167 @@ pub struct CustomOracle {
 impl CustomOracle {
     /// Account size in bytes (8 byte discriminator + data)
     pub const LEN: usize = 8 + std::mem::size_of::<CustomOracle>();
+    /// Serialized size in bytes read by price fetches (8 byte discriminator + fields)
+    pub const DATA_LEN: usize = 44;
 
     /// Update all oracle price fields
-    pub fn set(&mut self, price: u64, expo: i32, conf: u64, ema: u64, publish_time: i64) {
+    ///
+    /// The EMA is not taken from the price publisher. It is updated on-chain from the
+    /// new price, weighting the previous EMA by 2^(-elapsed_time / ema_half_life_sec).
+    ///
+    /// # Arguments
+    /// * `price` - New price mantissa
+    /// * `expo` - Price exponent
+    /// * `conf` - Price confidence interval
+    /// * `publish_time` - Unix timestamp when price was published
+    /// * `ema_half_life_sec` - EMA half-life in seconds (0 = EMA follows spot price)
+    pub fn set(
+        &mut self,
+        price: u64,
+        expo: i32,
+        conf: u64,
+        publish_time: i64,
+        ema_half_life_sec: u32,
+    ) -> Result<()> {
+        self.ema = if self.ema == 0 || self.expo != expo {
+            price
+        } else {
+            self.get_ema(
+                price,
+                publish_time.saturating_sub(self.publish_time),
+                ema_half_life_sec,
+            )?
+        };
         self.price = price;
         self.expo = expo;
         self.conf = conf;
-        self.ema = ema;
         self.publish_time = publish_time;
+        Ok(())
+    }
+
+    /// Calculate the deviation of a new price from the current price
+    ///
+    /// # Arguments
+    /// * `price` - New price mantissa
+    /// * `expo` - New price exponent
+    ///
+    /// # Returns
+    /// Absolute deviation in BPS, 0 if there is no current price with the same exponent
+    pub fn get_deviation(&self, price: u64, expo: i32) -> Result<u64> {
+        if self.price == 0 || self.expo != expo {
+            return Ok(0);
+        }
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(self.price.abs_diff(price) as u128, Perpetuals::BPS_POWER)?,
+            self.price as u128,
+        )?)
+    }
+
+    /// Calculate the EMA after a new price is published
+    ///
+    /// The decay 2^(-elapsed_time / half_life) is exact for whole half-lives and
+    /// interpolated linearly in between.
+    ///
+    /// # Arguments
+    /// * `price` - New price mantissa (same exponent as the current EMA)
+    /// * `elapsed_time` - Time since the previous update (non-positive keeps the EMA)
+    /// * `ema_half_life_sec` - EMA half-life in seconds (0 = EMA follows spot price)
+    pub fn get_ema(&self, price: u64, elapsed_time: i64, ema_half_life_sec: u32) -> Result<u64> {
+        if ema_half_life_sec == 0 {
+            return Ok(price);
+        }
+        if elapsed_time <= 0 {
+            return Ok(self.ema);
+        }
+        let half_life = ema_half_life_sec as u128;
+        let elapsed_time = elapsed_time as u128;
+        let halvings = math::checked_div(elapsed_time, half_life)?;
+        if halvings >= 64 {
+            return Ok(price);
+        }
+        // weight of the previous EMA, in RATE_DECIMALS
+        let remainder_weight = math::checked_sub(
+            Perpetuals::RATE_POWER,
+            math::checked_div(
+                math::checked_mul(elapsed_time % half_life, Perpetuals::RATE_POWER)?,
+                math::checked_mul(half_life, 2)?,
+            )?,
+        )?;
+        let ema_weight = remainder_weight >> halvings;
+
+        math::checked_as_u64(math::checked_div(
+            math::checked_add(
+                math::checked_mul(self.ema as u128, ema_weight)?,
+                math::checked_mul(
+                    price as u128,
+                    math::checked_sub(Perpetuals::RATE_POWER, ema_weight)?,
+                )?,
+            )?,
+            Perpetuals::RATE_POWER,
+        )?)
+    }
+
+    /// Validate the Ed25519Program instruction verifying an oracle authority signature
+    ///
+    /// The Ed25519 instruction data follows Solana's specification:
+    /// - data[0] = number of signatures, data[1] = padding
+    /// - data[2..16] = signature offsets (signature, public key and message offsets
+    ///   and the indexes of the instructions holding them)
+    ///
+    /// The signature must be the only one, and all offsets must point into the
+    /// Ed25519 instruction itself, either through u16::MAX or its own index. The
+    /// public key and message compared are read at the offsets the program verified.
+    ///
+    /// # Arguments
+    /// * `signature_ix` - Ed25519 signature verification instruction from transaction
+    /// * `signature_ix_index` - Index of the signature instruction in the transaction
+    /// * `expected_pubkey` - Expected oracle authority pubkey
+    /// * `expected_message` - Serialized instruction parameters (must match signed message)
+    ///
+    /// # Returns
+    /// `Result<()>` - Success if signature is valid, or error
+    pub fn validate_ed25519_signature_instruction(
+        signature_ix: &anchor_lang::solana_program::instruction::Instruction,
+        signature_ix_index: u16,
+        expected_pubkey: &Pubkey,
+        expected_message: &[u8],
+    ) -> Result<()> {
+        require_keys_eq!(
+            signature_ix.program_id,
+            solana_program::ed25519_program::ID,
+            PerpetualsError::PermissionlessOracleMissingSignature
+        );
+
+        let data = &signature_ix.data;
+        require!(
+            signature_ix.accounts.is_empty() && data.len() >= 16 && data[0] == 1,
+            PerpetualsError::PermissionlessOracleMalformedEd25519Data
+        );
+        let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let signature_offset = read_u16(2) as usize;
+        let public_key_offset = read_u16(6) as usize;
+        let message_offset = read_u16(10) as usize;
+        let message_size = read_u16(12) as usize;
+        let instruction_indexes = [read_u16(4), read_u16(8), read_u16(14)];
+        require!(
+            instruction_indexes
+                .iter()
+                .all(|index| *index == u16::MAX || *index == signature_ix_index)
+                && signature_offset + 64 <= data.len()
+                && public_key_offset + 32 <= data.len()
+                && message_offset + message_size <= data.len(),
+            PerpetualsError::PermissionlessOracleMalformedEd25519Data
+        );
+
+        require!(
+            data[public_key_offset..public_key_offset + 32] == expected_pubkey.to_bytes(),
+            PerpetualsError::PermissionlessOracleSignerMismatch
+        );
+        require!(
+            data[message_offset..message_offset + message_size] == *expected_message,
+            PerpetualsError::PermissionlessOracleMessageMismatch
+        );
+        Ok(())
     }
 }
 
@@ -134,6 +360,7 @@ impl OraclePrice {
     /// * `oracle_params` - Oracle configuration parameters
     /// * `current_time` - Current Unix timestamp
     /// * `use_ema` - Whether to use EMA (exponential moving average) price instead of spot price
+    /// * `operation` - Operation the price is used for, selects the max price age
     /// 
     /// # Returns
     /// OraclePrice if successful, error otherwise
@@ -142,13 +369,11 @@ impl OraclePrice {
         oracle_params: &OracleParams,
         current_time: i64,
         use_ema: bool,
+        operation: OracleOperation,
     ) -> Result<Self> {
         match oracle_params.oracle_type {
             OracleType::Custom => {
-                require!(
-                    !Perpetuals::is_empty_account(oracle_account)?,
-                    PerpetualsError::InvalidOracleAccount
-                );
+                Self::validate_custom_oracle_account(oracle_account, oracle_params)?;
                 let data = oracle_account.try_borrow_data()?;
                 // Manually parse CustomOracle fields (skip 8-byte discriminator)
                 let price = u64::from_le_bytes(data[8..16].try_into().unwrap());
@@ -157,7 +382,7 @@ impl OraclePrice {
                 let ema = u64::from_le_bytes(data[28..36].try_into().unwrap());
                 let publish_time = i64::from_le_bytes(data[36..44].try_into().unwrap());
                 let last_update_age_sec = math::checked_sub(current_time, publish_time)?;
-                if last_update_age_sec > oracle_params.max_price_age_sec as i64 {
+                if last_update_age_sec > oracle_params.get_max_price_age(operation) as i64 {
                     msg!("Error: Custom oracle price is stale");
                     return err!(PerpetualsError::StaleOraclePrice);
                 }
@@ -177,36 +402,89 @@ impl OraclePrice {
                 })
             },
             OracleType::Pyth => {
+                require_keys_eq!(
+                    oracle_account.key(),
+                    oracle_params.oracle_account,
+                    PerpetualsError::InvalidOracleAccount
+                );
                 require!(
                     !Perpetuals::is_empty_account(oracle_account)?,
                     PerpetualsError::InvalidOracleAccount
                 );
                 // Temporary: Return error until Pyth SDK is properly configured
-                return err!(PerpetualsError::UnsupportedOracle);
+                err!(PerpetualsError::UnsupportedOracle)
             },
             _ => err!(PerpetualsError::UnsupportedOracle),
         }
     }
 
+    /// Validate a custom oracle account is the configured one and holds price data
+    ///
+    /// # Arguments
+    /// * `oracle_account` - Account info of the oracle
+    /// * `oracle_params` - Oracle configuration parameters
+    fn validate_custom_oracle_account(
+        oracle_account: &AccountInfo,
+        oracle_params: &OracleParams,
+    ) -> Result<()> {
+        if oracle_account.key() != oracle_params.oracle_account {
+            msg!("Error: Oracle account doesn't match the custody oracle");
+            return err!(PerpetualsError::InvalidOracleAccount);
+        }
+        if Perpetuals::is_empty_account(oracle_account)?
+            || oracle_account.try_data_len()? < CustomOracle::DATA_LEN
+        {
+            msg!("Error: Custom oracle account holds no price data");
+            return err!(PerpetualsError::InvalidOracleAccount);
+        }
+        Ok(())
+    }
+
+    /// Read the publish time of the last oracle update
+    ///
+    /// # Arguments
+    /// * `oracle_account` - Account info of the oracle
+    /// * `oracle_params` - Oracle configuration parameters
+    ///
+    /// # Returns
+    /// Unix timestamp of the last published price
+    pub fn get_publish_time(oracle_account: &AccountInfo, oracle_params: &OracleParams) -> Result<i64> {
+        match oracle_params.oracle_type {
+            OracleType::Custom => {
+                Self::validate_custom_oracle_account(oracle_account, oracle_params)?;
+                let data = oracle_account.try_borrow_data()?;
+                Ok(i64::from_le_bytes(data[36..44].try_into().unwrap()))
+            }
+            _ => err!(PerpetualsError::UnsupportedOracle),
+        }
+    }
+
     /// Converts token amount to USD value using oracle price
     /// 
     /// # Arguments
     /// * `token_amount` - Amount of tokens
     /// * `token_decimals` - Number of decimals for the token
+    /// * `rounding` - Down for USD credited to users, Up for USD debited from them
     /// 
     /// # Returns
     /// USD value with Perpetuals::USD_DECIMALS decimals
-    pub fn get_asset_amount_usd(&self, token_amount: u64, token_decimals: u8) -> Result<u64> {
-        if token_amount == 0 || self.price == 0 {
-            return Ok(0);
+    pub fn get_asset_amount_usd(
+        &self,
+        token_amount: u64,
+        token_decimals: u8,
+        rounding: RoundingDirection,
+    ) -> Result<u64> {
+        match rounding {
+            RoundingDirection::Down => {
+                conversions::token_to_usd(token_amount, token_decimals, self.price, self.exponent)
+            }
+            RoundingDirection::Up => conversions::token_to_usd_ceil(
+                token_amount,
+                token_decimals,
+                self.price,
+                self.exponent,
+            ),
         }
-        math::checked_decimal_mul(
-            token_amount,
-            -(token_decimals as i32),
-            self.price,
-            self.exponent,
-            -(Perpetuals::USD_DECIMALS as i32),
-        )
     }
 
     /// Converts USD amount to token amount using oracle price
@@ -214,20 +492,30 @@ impl OraclePrice {
     /// # Arguments
     /// * `asset_amount_usd` - USD amount with Perpetuals::USD_DECIMALS decimals
     /// * `token_decimals` - Number of decimals for the token
+    /// * `rounding` - Down for tokens paid out, Up for tokens collected from users
     /// 
     /// # Returns
     /// Token amount
-    pub fn get_token_amount(&self, asset_amount_usd: u64, token_decimals: u8) -> Result<u64> {
-        if asset_amount_usd == 0 || self.price == 0 {
-            return Ok(0);
+    pub fn get_token_amount(
+        &self,
+        asset_amount_usd: u64,
+        token_decimals: u8,
+        rounding: RoundingDirection,
+    ) -> Result<u64> {
+        match rounding {
+            RoundingDirection::Down => conversions::usd_to_token(
+                asset_amount_usd,
+                token_decimals,
+                self.price,
+                self.exponent,
+            ),
+            RoundingDirection::Up => conversions::usd_to_token_ceil(
+                asset_amount_usd,
+                token_decimals,
+                self.price,
+                self.exponent,
+            ),
         }
-        math::checked_decimal_div(
-            asset_amount_usd,
-            -(Perpetuals::USD_DECIMALS as i32),
-            self.price,
-            self.exponent,
-            -(token_decimals as i32),
-        )
     }
 
     /// Normalizes price mantissa to be less than ORACLE_MAX_PRICE
@@ -291,21 +579,10 @@ impl OraclePrice {
     /// # Returns
     /// OraclePrice with same value but different exponent
     pub fn scale_to_exponent(&self, target_exponent: i32) -> Result<OraclePrice> {
-        if target_exponent == self.exponent {
-            return Ok(*self);
-        }
-        let delta = math::checked_sub(target_exponent, self.exponent)?;
-        if delta > 0 {
-            Ok(OraclePrice {
-                price: math::checked_div(self.price, math::checked_pow(10, delta as usize)?)?,
-                exponent: target_exponent,
-            })
-        } else {
-            Ok(OraclePrice {
-                price: math::checked_mul(self.price, math::checked_pow(10, (-delta) as usize)?)?,
-                exponent: target_exponent,
-            })
-        }
+        Ok(OraclePrice {
+            price: conversions::scale_price(self.price, self.exponent, target_exponent)?,
+            exponent: target_exponent,
+        })
     }
 
     /// Convert OraclePrice to f64 floating point representation
@@ -437,7 +714,7 @@ impl OraclePrice {
         //     .map_err(|_| PerpetualsError::UnsupportedOracleAccount)?;
         
         // Temporary: Return error until Pyth SDK is properly configured
-        return err!(PerpetualsError::UnsupportedOracle);
+        err!(PerpetualsError::UnsupportedOracle)
         
         // TODO: Uncomment when Pyth SDK is added:
         /*
@@ -475,4 +752,330 @@ impl OraclePrice {
         })
         */
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::instructions::{
+            SetCustomOraclePricePermissionlessParams,
+            SetCustomOraclePricesPermissionlessBatchParams,
+        },
+        anchor_lang::solana_program::instruction::Instruction,
+        solana_program::ed25519_program,
+    };
+
+    // Ed25519 instruction with the public key, signature and message stored after the
+    // offsets, in the layout built by the Ed25519 program client
+    fn get_ed25519_instruction(pubkey: &Pubkey, message: &[u8]) -> Instruction {
+        let mut data = vec![1, 0];
+        for offset in [48, u16::MAX, 16, u16::MAX, 112, message.len() as u16, u16::MAX] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(pubkey.as_ref());
+        data.extend_from_slice(&[7; 64]);
+        data.extend_from_slice(message);
+        Instruction::new_with_bytes(ed25519_program::ID, &data, vec![])
+    }
+
+    fn set_offset(ix: &mut Instruction, position: usize, value: u16) {
+        ix.data[position..position + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_validate_ed25519_signature_instruction() {
+        let authority = Pubkey::new_unique();
+        let params = SetCustomOraclePricePermissionlessParams {
+            custody_account: Pubkey::new_unique(),
+            price: 1000,
+            expo: -3,
+            conf: 1,
+            ema: 0,
+            publish_time: 100,
+        };
+        let message = params.try_to_vec().unwrap();
+        let validate = |ix: &Instruction, message: &[u8]| {
+            CustomOracle::validate_ed25519_signature_instruction(ix, 0, &authority, message)
+        };
+
+        let ix = get_ed25519_instruction(&authority, &message);
+        assert!(validate(&ix, &message).is_ok());
+
+        // offsets may also point to the signature instruction by its index
+        let mut indexed = ix.clone();
+        for position in [4, 8, 14] {
+            set_offset(&mut indexed, position, 0);
+        }
+        assert!(validate(&indexed, &message).is_ok());
+
+        // batch updates sign the whole batch in one message
+        let batch = SetCustomOraclePricesPermissionlessBatchParams {
+            prices: vec![params, params],
+        };
+        let batch_message = batch.try_to_vec().unwrap();
+        let batch_ix = get_ed25519_instruction(&authority, &batch_message);
+        assert!(validate(&batch_ix, &batch_message).is_ok());
+        assert_eq!(
+            validate(&batch_ix, &message),
+            Err(PerpetualsError::PermissionlessOracleMessageMismatch.into())
+        );
+
+        // not the Ed25519 program
+        let mut forged = ix.clone();
+        forged.program_id = anchor_lang::solana_program::system_program::ID;
+        assert_eq!(
+            validate(&forged, &message),
+            Err(PerpetualsError::PermissionlessOracleMissingSignature.into())
+        );
+
+        // public key, signature or message taken from another instruction
+        for position in [4, 8, 14] {
+            let mut forged = ix.clone();
+            set_offset(&mut forged, position, 1);
+            assert_eq!(
+                validate(&forged, &message),
+                Err(PerpetualsError::PermissionlessOracleMalformedEd25519Data.into())
+            );
+        }
+
+        // more than one signature
+        let mut forged = ix.clone();
+        forged.data[0] = 2;
+        assert!(validate(&forged, &message).is_err());
+
+        // verified public key stored elsewhere than the authority bytes
+        let attacker = Pubkey::new_unique();
+        let mut forged = get_ed25519_instruction(&authority, &message);
+        forged.data.extend_from_slice(attacker.as_ref());
+        let attacker_offset = forged.data.len() as u16 - 32;
+        set_offset(&mut forged, 6, attacker_offset);
+        assert_eq!(
+            validate(&forged, &message),
+            Err(PerpetualsError::PermissionlessOracleSignerMismatch.into())
+        );
+
+        // verified message stored elsewhere than the compared bytes
+        let mut other = params;
+        other.price = 1;
+        let mut forged = get_ed25519_instruction(&authority, &message);
+        let other_offset = forged.data.len() as u16;
+        forged.data.extend_from_slice(&other.try_to_vec().unwrap());
+        set_offset(&mut forged, 10, other_offset);
+        assert_eq!(
+            validate(&forged, &message),
+            Err(PerpetualsError::PermissionlessOracleMessageMismatch.into())
+        );
+
+        // offsets out of bounds
+        let mut forged = ix.clone();
+        set_offset(&mut forged, 12, message.len() as u16 + 1);
+        assert!(validate(&forged, &message).is_err());
+        let mut forged = ix;
+        forged.data.truncate(15);
+        assert!(validate(&forged, &message).is_err());
+    }
+
+    #[test]
+    fn test_custom_oracle_ema() {
+        let mut oracle = CustomOracle::default();
+
+        // first update initializes the EMA to the spot price
+        oracle.set(1000, -3, 0, 100, 60).unwrap();
+        assert_eq!(1000, oracle.ema);
+
+        // one half-life moves the EMA halfway to the new price
+        oracle.set(2000, -3, 0, 160, 60).unwrap();
+        assert_eq!(1500, oracle.ema);
+
+        // half a half-life is interpolated between full and half weight
+        oracle.set(500, -3, 0, 190, 60).unwrap();
+        assert_eq!(1250, oracle.ema);
+
+        // stale or duplicate updates don't move the EMA
+        oracle.set(100, -3, 0, 190, 60).unwrap();
+        assert_eq!(1250, oracle.ema);
+
+        // zero half-life tracks the spot price
+        oracle.set(700, -3, 0, 200, 0).unwrap();
+        assert_eq!(700, oracle.ema);
+
+        // exponent change resets the EMA
+        oracle.set(7000, -4, 0, 201, 60).unwrap();
+        assert_eq!(7000, oracle.ema);
+    }
+
+    #[test]
+    fn test_rounding_dust() {
+        // xorshift, deterministic so failures reproduce
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = |max: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % max + 1
+        };
+
+        // pool tokens and USD owed to users, per token: (decimals, price)
+        let mut pool_tokens = [0u64; 3];
+        let mut user_usd = [0u64; 3];
+        let prices = [
+            (6, OraclePrice::new(1_000_003, -6)),
+            (9, OraclePrice::new(13_712_345_678, -8)),
+            (8, OraclePrice::new(6_543_217_891_234, -8)),
+        ];
+        for _ in 0..10_000 {
+            let id = next(3) as usize - 1;
+            let (decimals, price) = &prices[id];
+            match next(3) {
+                // deposit tokens, user is credited USD
+                1 => {
+                    let amount = next(1_000_000_000);
+                    pool_tokens[id] += amount;
+                    user_usd[id] += price
+                        .get_asset_amount_usd(amount, *decimals, RoundingDirection::Down)
+                        .unwrap();
+                }
+                // withdraw USD, user is paid tokens
+                2 => {
+                    let amount_usd = std::cmp::min(next(1_000_000_000), user_usd[id]);
+                    user_usd[id] -= amount_usd;
+                    pool_tokens[id] -= price
+                        .get_token_amount(amount_usd, *decimals, RoundingDirection::Down)
+                        .unwrap();
+                }
+                // fee in USD, user is charged tokens
+                _ => {
+                    let fee_usd = next(10_000);
+                    let fee = price
+                        .get_token_amount(fee_usd, *decimals, RoundingDirection::Up)
+                        .unwrap();
+                    assert!(
+                        price
+                            .get_asset_amount_usd(fee, *decimals, RoundingDirection::Up)
+                            .unwrap()
+                            >= fee_usd
+                    );
+                    pool_tokens[id] += fee;
+                }
+            }
+            // the pool always holds enough tokens to pay out every user
+            let owed = price
+                .get_token_amount(user_usd[id], *decimals, RoundingDirection::Down)
+                .unwrap();
+            assert!(pool_tokens[id] >= owed);
+        }
+    }
+
+    fn get_oracle_account_info(key: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            false,
+            Box::leak(Box::new(1_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(crate::ID)),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_custom_oracle_deviation() {
+        let mut oracle = CustomOracle::default();
+        assert_eq!(oracle.get_deviation(1_000, -3).unwrap(), 0);
+
+        oracle.set(1_000, -3, 0, 100, 0).unwrap();
+        assert_eq!(oracle.get_deviation(1_000, -3).unwrap(), 0);
+        assert_eq!(oracle.get_deviation(1_050, -3).unwrap(), 500);
+        assert_eq!(oracle.get_deviation(800, -3).unwrap(), 2_000);
+        assert_eq!(oracle.get_deviation(3_000, -3).unwrap(), 20_000);
+
+        // prices with another exponent are not compared
+        assert_eq!(oracle.get_deviation(10_000, -4).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_oracle_errors() {
+        let key = Pubkey::new_unique();
+        let params = OracleParams {
+            oracle_account: key,
+            oracle_type: OracleType::Custom,
+            max_price_error: 100,
+            max_price_age_sec: 60,
+            ..OracleParams::default()
+        };
+        let oracle = CustomOracle {
+            price: 1000,
+            expo: -3,
+            conf: 5,
+            ema: 1000,
+            publish_time: 100,
+        };
+        let get_price = |account: &AccountInfo, params: &OracleParams, oracle_time: i64| {
+            OraclePrice::new_from_oracle(account, params, oracle_time, false, OracleOperation::Open)
+        };
+        let serialize = |oracle: &CustomOracle| {
+            let mut data = Vec::new();
+            oracle.try_serialize(&mut data).unwrap();
+            data
+        };
+        let account = get_oracle_account_info(key, serialize(&oracle));
+        assert_eq!(1000, get_price(&account, &params, 160).unwrap().price);
+
+        // stale price
+        assert_eq!(
+            Error::from(PerpetualsError::StaleOraclePrice),
+            get_price(&account, &params, 161).unwrap_err()
+        );
+
+        // out of bounds confidence and zero price
+        let wide_conf = get_oracle_account_info(
+            key,
+            serialize(&CustomOracle {
+                conf: 11,
+                ..oracle
+            }),
+        );
+        assert_eq!(
+            Error::from(PerpetualsError::InvalidOraclePrice),
+            get_price(&wide_conf, &params, 100).unwrap_err()
+        );
+        let zero_price = get_oracle_account_info(
+            key,
+            serialize(&CustomOracle {
+                price: 0,
+                ..oracle
+            }),
+        );
+        assert_eq!(
+            Error::from(PerpetualsError::InvalidOraclePrice),
+            get_price(&zero_price, &params, 100).unwrap_err()
+        );
+
+        // wrong, empty or truncated account
+        let wrong_key = get_oracle_account_info(Pubkey::new_unique(), serialize(&oracle));
+        let empty = get_oracle_account_info(key, Vec::new());
+        let truncated = get_oracle_account_info(key, serialize(&oracle)[..40].to_vec());
+        for account in [&wrong_key, &empty, &truncated] {
+            assert_eq!(
+                Error::from(PerpetualsError::InvalidOracleAccount),
+                get_price(account, &params, 100).unwrap_err()
+            );
+            assert_eq!(
+                Error::from(PerpetualsError::InvalidOracleAccount),
+                OraclePrice::get_publish_time(account, &params).unwrap_err()
+            );
+        }
+
+        // unsupported oracle type
+        let none_params = OracleParams {
+            oracle_type: OracleType::None,
+            ..params
+        };
+        assert_eq!(
+            Error::from(PerpetualsError::UnsupportedOracle),
+            get_price(&account, &none_params, 100).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Order commitment state
+//!
+//! Commit-reveal flow for opening large positions. The owner first commits to a hash
+//! of the order together with a SOL deposit, and reveals the order in a later slot,
+//! when it executes at the then-current price. Until the reveal, observers can't see
+//! the market, side or size of the order and trade ahead of it.
+
+use {crate::math, anchor_lang::prelude::*, solana_program::hash::hashv};
+
+/// Order commitment account
+///
+/// PDA derived from the owner and the pool, so an owner has at most one outstanding
+/// commitment per pool.
+#[account]
+#[derive(Default, Debug)]
+pub struct OrderCommitment {
+    /// Owner of the committed order
+    pub owner: Pubkey,
+    /// Pool the order will be opened in
+    pub pool: Pubkey,
+    /// Hash of the order parameters, custodies and salt
+    pub hash: [u8; 32],
+    /// SOL deposit (lamports above rent), refunded on reveal, forfeited on cancel
+    pub deposit: u64,
+    /// Slot the commitment was made in
+    pub commit_slot: u64,
+
+    /// Bump seed for the order commitment PDA
+    pub bump: u8,
+}
+
+impl OrderCommitment {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<OrderCommitment>();
+    /// Number of slots after the commit slot the order can be revealed in
+    pub const REVEAL_WINDOW_SLOTS: u64 = 150;
+    /// Minimum SOL deposit in lamports
+    pub const MIN_DEPOSIT: u64 = 10_000_000;
+
+    /// Returns the commitment hash of an order
+    ///
+    /// # Arguments
+    /// * `params_data` - Serialized open position parameters
+    /// * `custody` - Custody of the position token
+    /// * `collateral_custody` - Custody of the collateral token
+    /// * `salt` - Random salt chosen by the owner
+    pub fn get_hash(
+        params_data: &[u8],
+        custody: &Pubkey,
+        collateral_custody: &Pubkey,
+        salt: &[u8; 32],
+    ) -> [u8; 32] {
+        hashv(&[
+            params_data,
+            custody.as_ref(),
+            collateral_custody.as_ref(),
+            salt,
+        ])
+        .to_bytes()
+    }
+
+    /// Checks whether the order can be revealed in the given slot
+    ///
+    /// The reveal must happen in a later slot than the commitment, within the window.
+    pub fn can_reveal(&self, slot: u64) -> Result<bool> {
+        Ok(slot > self.commit_slot
+            && slot <= math::checked_add(self.commit_slot, Self::REVEAL_WINDOW_SLOTS)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reveal_window() {
+        let commitment = OrderCommitment {
+            commit_slot: 100,
+            ..Default::default()
+        };
+        assert!(!commitment.can_reveal(100).unwrap());
+        assert!(commitment.can_reveal(101).unwrap());
+        assert!(commitment
+            .can_reveal(100 + OrderCommitment::REVEAL_WINDOW_SLOTS)
+            .unwrap());
+        assert!(!commitment
+            .can_reveal(101 + OrderCommitment::REVEAL_WINDOW_SLOTS)
+            .unwrap());
+
+        let custody = Pubkey::new_unique();
+        let hash = OrderCommitment::get_hash(&[1, 2, 3], &custody, &custody, &[0; 32]);
+        assert_ne!(
+            hash,
+            OrderCommitment::get_hash(&[1, 2, 3], &custody, &custody, &[1; 32])
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Owner position counter state
+//!
+//! Counts the open positions of an owner in a pool, so the pool can cap how many
+//! positions a single owner keeps open. Many tiny positions from one owner make
+//! liquidation keepers do more work for little value.
+
+use {
+    crate::{error::PerpetualsError, math},
+    anchor_lang::prelude::*,
+};
+
+/// Owner position counter account
+///
+/// PDA derived from the pool and the owner. Only positions opened while the counter
+/// was passed to open_position are counted.
+#[account]
+#[derive(Default, Debug)]
+pub struct OwnerPositions {
+    /// Pool the counter applies to
+    pub pool: Pubkey,
+    /// Owner of the positions
+    pub owner: Pubkey,
+    /// Number of tracked open positions
+    pub open_positions: u32,
+
+    /// Bump seed for the counter PDA
+    pub bump: u8,
+}
+
+impl OwnerPositions {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<OwnerPositions>();
+
+    /// Count a new position, failing if the owner is at the pool cap
+    ///
+    /// # Arguments
+    /// * `max_positions` - Pool cap on open positions per owner (0 = unlimited)
+    pub fn add_position(&mut self, max_positions: u32) -> Result<()> {
+        require!(
+            max_positions == 0 || self.open_positions < max_positions,
+            PerpetualsError::MaxPositionsPerOwner
+        );
+        self.open_positions = math::checked_add(self.open_positions, 1)?;
+        Ok(())
+    }
+
+    /// Stop counting a closed position
+    ///
+    /// Saturates at zero, positions opened without the counter are not tracked.
+    pub fn remove_position(&mut self) {
+        self.open_positions = self.open_positions.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_position_cap() {
+        let mut owner_positions = OwnerPositions::default();
+        owner_positions.add_position(2).unwrap();
+        owner_positions.add_position(2).unwrap();
+        assert!(owner_positions.add_position(2).is_err());
+        assert_eq!(owner_positions.open_positions, 2);
+
+        owner_positions.remove_position();
+        owner_positions.add_position(2).unwrap();
+
+        // uncapped pool
+        owner_positions.add_position(0).unwrap();
+        assert_eq!(owner_positions.open_positions, 3);
+
+        owner_positions.open_positions = 0;
+        owner_positions.remove_position();
+        assert_eq!(owner_positions.open_positions, 0);
+    }
+}
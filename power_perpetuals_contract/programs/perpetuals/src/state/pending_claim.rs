@@ -0,0 +1,60 @@
+//! Pending claim state
+//!
+//! Records a withdrawal that couldn't be paid because the custody had no free
+//! liquidity (owned - locked). Claims of a custody are executed in FIFO order by
+//! anyone once liquidity frees up, so users don't have to retry manually. A claim whose
+//! receiving account can't be paid is paid to the owner ATA instead, so it can't block
+//! the queue.
+
+use {
+    anchor_lang::prelude::*, anchor_spl::associated_token::get_associated_token_address,
+};
+
+/// Pending claim account
+///
+/// PDA derived from the custody and the claim id assigned by the custody queue.
+#[account]
+#[derive(Default, Debug)]
+pub struct PendingClaim {
+    /// Custody the tokens are owed from
+    pub custody: Pubkey,
+    /// Owner of the claim, receives the account rent back on execution
+    pub owner: Pubkey,
+    /// Token account the claimed tokens are paid to
+    pub receiving_account: Pubkey,
+    /// Position in the custody claim queue
+    pub claim_id: u64,
+    /// Tokens paid to the receiving account
+    pub amount: u64,
+    /// Tokens moved to protocol fees on execution
+    pub protocol_fee: u64,
+    /// Time the claim was queued
+    pub request_time: i64,
+
+    /// Bump seed for the pending claim PDA
+    pub bump: u8,
+}
+
+impl PendingClaim {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<PendingClaim>();
+
+    /// Total tokens the claim takes out of the custody owned assets
+    pub fn get_total_amount(&self) -> u64 {
+        self.amount.saturating_add(self.protocol_fee)
+    }
+
+    /// Check if an account can receive the claimed tokens
+    ///
+    /// # Arguments
+    /// * `account` - Token account to check
+    /// * `mint` - Custody token mint
+    ///
+    /// # Returns
+    /// true if the account is the receiving account of the claim or the associated token
+    /// account of the owner
+    pub fn is_receiving_account(&self, account: &Pubkey, mint: &Pubkey) -> bool {
+        *account == self.receiving_account
+            || *account == get_associated_token_address(&self.owner, mint)
+    }
+}
@@ -4,8 +4,24 @@
 //! for token transfers, account management, and permission controls.
 
 use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            crank_state::CrankState,
+            custody::{Custody, FeesStats, VolumeStats},
+            oracle::OracleType,
+            order_commitment::OrderCommitment,
+            pool::Pool,
+            position::RiskTier,
+            risk_oracle::RiskOracle,
+        },
+    },
     anchor_lang::prelude::*,
-    anchor_spl::token::{Burn, MintTo, Transfer},
+    anchor_spl::{
+        associated_token::spl_associated_token_account,
+        token::{spl_token, Token, TokenAccount},
+    },
+    solana_program::program::{invoke, invoke_signed},
 };
 
 /// Price and associated fee structure
@@ -35,6 +51,21 @@ pub struct NewPositionPricesAndFee {
     pub liquidation_price: u64,
     /// Fee charged for opening the position
     pub fee: u64,
+    /// Borrow size used for leverage checks
+    pub borrow_size_usd: u64,
+}
+
+/// Minimum collateral to open a position at a target leverage
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct RequiredCollateral {
+    /// Collateral amount in collateral tokens
+    pub collateral: u64,
+    /// Collateral value in USD at the min collateral price
+    pub collateral_usd: u64,
+    /// Entry fee charged on top of the collateral
+    pub fee: u64,
+    /// Position leverage in BPS with this collateral
+    pub leverage: u64,
 }
 
 /// Swap result with input/output amounts and fees
@@ -48,6 +79,23 @@ pub struct SwapAmountAndFees {
     pub fee_out: u64,
 }
 
+/// Cross-pool swap result with the amounts and fees of both legs
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CrossPoolSwapAmountAndFees {
+    /// Output amount after both swaps
+    pub amount_out: u64,
+    /// Stable token amount moved between the pools
+    pub stable_amount: u64,
+    /// Fee on input token
+    pub fee_in: u64,
+    /// Fees on the stable token (out of the first pool and into the second one)
+    pub stable_fee: u64,
+    /// Fee on output token
+    pub fee_out: u64,
+    /// Value of all fees in USD
+    pub fee_usd: u64,
+}
+
 /// Profit and loss calculation result
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct ProfitAndLoss {
@@ -57,6 +105,267 @@ pub struct ProfitAndLoss {
     pub loss: u64,
 }
 
+/// Profit cap of a position, set by the funds locked for its payoff
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct MaxPayoff {
+    /// Collateral tokens locked for the position payoff
+    pub locked_amount: u64,
+    /// Maximum profit the position can realize in USD
+    pub max_profit_usd: u64,
+    /// Current profit in USD before the cap
+    pub profit_usd: u64,
+    /// True if the cap reduces the current profit
+    pub capped: bool,
+}
+
+/// Quote for closing a position at current prices
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ClosePositionQuote {
+    /// Position account the quote applies to
+    pub position: Pubkey,
+    /// Exit price (scaled to PRICE_DECIMALS)
+    pub exit_price: u64,
+    /// Exit fee including the anti-scalp fee (in collateral token decimals)
+    pub fee: u64,
+    /// Collateral returned to the owner (in collateral token decimals)
+    pub amount_out: u64,
+    /// Net profit in USD
+    pub profit_usd: u64,
+    /// Net loss in USD
+    pub loss_usd: u64,
+}
+
+/// Projected outcome of liquidating a position at current prices
+///
+/// Token amounts are in collateral token decimals.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct LiquidationPreview {
+    /// Position account the preview applies to
+    pub position: Pubkey,
+    /// True if the position can be liquidated now
+    pub liquidatable: bool,
+    /// Collateral returned to the owner after the keeper reward
+    pub user_amount: u64,
+    /// Reward paid to the liquidator
+    pub reward: u64,
+    /// Liquidation fee charged on the position
+    pub fee: u64,
+    /// Protocol share of the liquidation fee
+    pub protocol_fee: u64,
+    /// Net profit of the position in USD
+    pub profit_usd: u64,
+    /// Net loss of the position in USD
+    pub loss_usd: u64,
+    /// Collateral kept by the pool (position collateral above the amount paid out)
+    pub pool_profit: u64,
+    /// Pool funds paid out above the position collateral
+    pub pool_loss: u64,
+}
+
+/// Volume and fee counters of a custody
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyStats {
+    /// Lifetime fees collected in USD
+    pub collected_fees: FeesStats,
+    /// Lifetime volume in USD
+    pub volume_stats: VolumeStats,
+    /// Time the current stats epoch started
+    pub epoch_start_time: i64,
+    /// Fees collected since the stats epoch started
+    pub epoch_collected_fees: FeesStats,
+    /// Volume since the stats epoch started
+    pub epoch_volume_stats: VolumeStats,
+    /// Highest position power allowed in the market
+    pub max_power: u8,
+}
+
+/// Interest accrued by a position since its last snapshot
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PositionInterest {
+    /// Interest accrued since the snapshot in USD
+    pub interest_usd: u64,
+    /// Cumulative interest of the collateral custody at the position's last snapshot
+    pub cumulative_interest_snapshot: u128,
+    /// Current cumulative interest of the collateral custody
+    pub cumulative_interest: u128,
+    /// Current hourly borrow rate of the collateral custody, scaled to RATE_DECIMALS
+    pub borrow_rate: u64,
+}
+
+/// Oracle freshness of a custody
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct OracleHealth {
+    /// Publish time of the last oracle update
+    pub last_update: i64,
+    /// Seconds since the last oracle update
+    pub age_sec: u64,
+    /// Maximum price age accepted by trading instructions
+    pub max_price_age_sec: u32,
+    /// Oracle update age that triggers safe mode (0 = heartbeat not enforced)
+    pub heartbeat_limit_sec: u64,
+    /// True if the price is older than max_price_age_sec
+    pub stale: bool,
+    /// True if the oracle missed its heartbeat
+    pub heartbeat_missed: bool,
+    /// Current safe mode flag of the custody
+    pub safe_mode: bool,
+}
+
+/// Risk assessment of a position at current prices
+///
+/// Leverages and the health factor are in BPS.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PositionRisk {
+    /// Leverage using the aggregate spot/EMA price selection
+    pub leverage: u64,
+    /// Maintenance leverage adjusted for position power (liquidation threshold)
+    pub max_leverage: u64,
+    /// Maintenance leverage over leverage (below BPS_POWER the position is liquidatable)
+    pub health_factor: u64,
+    /// Risk tier under the custody thresholds
+    pub risk_tier: RiskTier,
+}
+
+/// Token ratio impact of a prospective liquidity operation or swap leg
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct TokenRatioImpact {
+    /// Current token ratio (in BPS)
+    pub current_ratio: u64,
+    /// Token ratio after the operation (in BPS)
+    pub new_ratio: u64,
+    /// Target token ratio (in BPS)
+    pub target: u64,
+    /// Minimum token ratio (in BPS)
+    pub min: u64,
+    /// Maximum token ratio (in BPS)
+    pub max: u64,
+    /// Whether the operation passes the token ratio check
+    pub allowed: bool,
+}
+
+/// Trailing fee APR of a pool and its custodies
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PoolApr {
+    /// Fee APR of each custody, in pool custody order (in BPS)
+    pub custody_aprs: Vec<u64>,
+    /// Fee APR of the pool (in BPS)
+    pub pool_apr: u64,
+}
+
+/// Split of the custody token balance between pool liquidity, trader payoffs and the protocol
+///
+/// Amounts are in custody tokens.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct LockedBreakdown {
+    /// Liquidity owned by the pool
+    pub owned: u64,
+    /// Part of the owned liquidity locked for position payoffs
+    pub locked: u64,
+    /// Part of the owned liquidity free to back new positions and withdrawals
+    pub available: u64,
+    /// Trader collateral held by the custody
+    pub collateral: u64,
+    /// Fees reserved for the protocol
+    pub protocol_fees: u64,
+    /// Locked share of the owned liquidity (in BPS)
+    pub utilization: u64,
+    /// Utilization above which new positions are rejected (in BPS, 0 = no limit)
+    pub max_utilization: u64,
+}
+
+/// Key parameters of a pool custody for the pool overview
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyOverview {
+    /// Custody account
+    pub custody: Pubkey,
+    /// Custody token mint
+    pub mint: Pubkey,
+    /// Custody token decimals
+    pub decimals: u8,
+    /// Whether the token is a stablecoin
+    pub is_stable: bool,
+    /// Whether the custody is virtual (no token balance)
+    pub is_virtual: bool,
+    /// Oracle type of the custody
+    pub oracle_type: OracleType,
+    /// Current share of the pool AUM (in BPS)
+    pub current_ratio: u64,
+    /// Target share of the pool AUM (in BPS)
+    pub target_ratio: u64,
+    /// Locked share of the owned liquidity (in BPS)
+    pub utilization: u64,
+    /// Long open interest in USD
+    pub oi_long_usd: u64,
+    /// Short open interest in USD
+    pub oi_short_usd: u64,
+}
+
+/// Page of pool custody overviews
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PoolCustodies {
+    /// Total number of custodies in the pool
+    pub total: u8,
+    /// Custodies of the requested page, in pool order
+    pub custodies: Vec<CustodyOverview>,
+}
+
+/// LP supply cap of a pool
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct LpSupplyCapacity {
+    /// Maximum LP token supply (0 = uncapped)
+    pub max_lp_supply: u64,
+    /// LP token supply
+    pub lp_supply: u64,
+    /// LP tokens that can still be minted (u64::MAX if uncapped)
+    pub remaining_lp_supply: u64,
+}
+
+/// Fee and access configuration of a registered pool
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PoolConfigOverview {
+    /// Pool account
+    pub pool: Pubkey,
+    /// Pool name
+    pub name: String,
+    /// Number of custodies in the pool
+    pub custodies: u8,
+    /// Custody trade fees can be paid in (default = fees are charged in kind)
+    pub fee_custody: Pubkey,
+    /// Protocol share of LP token price gains above the high-water mark (BPS)
+    pub performance_fee_bps: u64,
+    /// LP token account performance fees are minted to
+    pub performance_fee_account: Pubkey,
+    /// Share of pool gains paid to the backstop tranche on top of its pro-rata share (BPS)
+    pub backstop_fee_boost_bps: u64,
+    /// Whether liquidity operations are restricted to the LP allowlist
+    pub lp_allowlist_enabled: bool,
+    /// Whether opens and increases are checked against custody compliance freeze lists
+    pub compliance_freeze_enabled: bool,
+    /// Whether trading instructions of the pool can be invoked through CPI
+    pub allow_cpi: bool,
+    /// Maximum LP token supply (0 = uncapped)
+    pub max_lp_supply: u64,
+}
+
+/// Program-level configuration with a page of registered pools
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct ProtocolConfig {
+    /// Protocol-wide permission flags
+    pub permissions: Permissions,
+    /// Allow user-facing trading instructions to be invoked through CPI
+    pub allow_cpi: bool,
+    /// Circuit-breaker feed halting risk-increasing operations
+    pub risk_oracle: RiskOracle,
+    /// Number of admin signatures required to execute admin instructions
+    pub min_signatures: u8,
+    /// Admin signers of the multisig
+    pub signers: Vec<Pubkey>,
+    /// Total number of registered pools
+    pub total_pools: u16,
+    /// Pools of the requested page, in registration order
+    pub pools: Vec<PoolConfigOverview>,
+}
+
 /// Permission flags controlling which operations are allowed
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Permissions {
@@ -96,6 +405,53 @@ pub struct Perpetuals {
     pub perpetuals_bump: u8,
     /// Time of inception, also used as current wall clock time for testing
     pub inception_time: i64,
+    /// Circuit-breaker feed halting risk-increasing operations
+    pub risk_oracle: RiskOracle,
+    /// Allow user-facing trading instructions to be invoked through CPI
+    pub allow_cpi: bool,
+}
+
+/// Original perpetuals account layout, upgraded by upgrade_perpetuals.
+/// Shares the Perpetuals account discriminator.
+#[derive(Clone, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PerpetualsV0 {
+    pub permissions: Permissions,
+    pub pools: Vec<Pubkey>,
+    pub transfer_authority_bump: u8,
+    pub perpetuals_bump: u8,
+    pub inception_time: i64,
+}
+
+impl PerpetualsV0 {
+    /// Account size in bytes without the pool list (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<PerpetualsV0>();
+
+    /// Load perpetuals stored in the original layout
+    ///
+    /// The account is sized for exactly its pools, which tells it apart from the
+    /// current layout.
+    pub fn load(data: &[u8]) -> Option<PerpetualsV0> {
+        if data.get(..8)? != Perpetuals::DISCRIMINATOR {
+            return None;
+        }
+        let perpetuals = PerpetualsV0::deserialize(&mut &data[8..]).ok()?;
+        let len = PerpetualsV0::LEN + perpetuals.pools.len() * std::mem::size_of::<Pubkey>();
+        (data.len() == len).then_some(perpetuals)
+    }
+}
+
+impl From<PerpetualsV0> for Perpetuals {
+    fn from(perpetuals: PerpetualsV0) -> Self {
+        Self {
+            permissions: perpetuals.permissions,
+            pools: perpetuals.pools,
+            transfer_authority_bump: perpetuals.transfer_authority_bump,
+            perpetuals_bump: perpetuals.perpetuals_bump,
+            inception_time: perpetuals.inception_time,
+            risk_oracle: RiskOracle::default(),
+            allow_cpi: false,
+        }
+    }
 }
 
 impl anchor_lang::Id for Perpetuals {
@@ -147,6 +503,42 @@ impl Perpetuals {
         }
     }
 
+    /// Check whether the current instruction is invoked through CPI
+    ///
+    /// # Returns
+    /// true if the instruction is not a top-level transaction instruction
+    pub fn is_cpi() -> bool {
+        anchor_lang::solana_program::instruction::get_stack_height()
+            > anchor_lang::solana_program::instruction::TRANSACTION_LEVEL_STACK_HEIGHT
+    }
+
+    /// Check whether CPI invocation of user-facing trading instructions is allowed
+    ///
+    /// Top-level invocations are always allowed. CPI invocations require global, pool
+    /// and custody permissions to protect users from malicious wrapper programs.
+    ///
+    /// # Arguments
+    /// * `pool` - Pool being traded
+    /// * `custody` - Custody being traded
+    ///
+    /// # Returns
+    /// true if the current invocation is allowed
+    pub fn check_cpi_allowed(&self, pool: &Pool, custody: &Custody) -> bool {
+        !Self::is_cpi() || self.is_cpi_allowed(pool, custody)
+    }
+
+    /// Check whether a CPI invocation would be allowed for a pool and custody
+    ///
+    /// # Arguments
+    /// * `pool` - Pool being traded
+    /// * `custody` - Custody being traded
+    ///
+    /// # Returns
+    /// true if the global, pool and custody permissions all allow CPI
+    pub fn is_cpi_allowed(&self, pool: &Pool, custody: &Custody) -> bool {
+        self.allow_cpi && pool.allow_cpi && custody.allow_cpi
+    }
+
     /// Validate that the program upgrade authority matches expected authority
     /// 
     /// # Arguments
@@ -183,6 +575,9 @@ impl Perpetuals {
     }
 
     /// Transfer tokens using the program's transfer authority PDA
+    ///
+    /// Token CPIs are invoked through solana_program, unlike the anchor_lang re-export its
+    /// invoke_signed can be stubbed, so handlers can run in host tests.
     /// 
     /// # Arguments
     /// * `from` - Source token account
@@ -201,17 +596,15 @@ impl Perpetuals {
         let authority_seeds: &[&[&[u8]]] =
             &[&[b"transfer_authority", &[self.transfer_authority_bump]]];
 
-        let context = CpiContext::new(
-            token_program,
-            Transfer {
-                from,
-                to,
-                authority,
-            },
-        )
-        .with_signer(authority_seeds);
-
-        anchor_spl::token::transfer(context, amount)
+        let instruction = spl_token::instruction::transfer(
+            token_program.key,
+            from.key,
+            to.key,
+            authority.key,
+            &[],
+            amount,
+        )?;
+        invoke_signed(&instruction, &[from, to, authority], authority_seeds).map_err(Into::into)
     }
 
     /// Transfer tokens from a user account (user signs the transaction)
@@ -230,15 +623,15 @@ impl Perpetuals {
         token_program: AccountInfo<'info>,
         amount: u64,
     ) -> Result<()> {
-        let context = CpiContext::new(
-            token_program,
-            Transfer {
-                from,
-                to,
-                authority,
-            },
-        );
-        anchor_spl::token::transfer(context, amount)
+        let instruction = spl_token::instruction::transfer(
+            token_program.key,
+            from.key,
+            to.key,
+            authority.key,
+            &[],
+            amount,
+        )?;
+        invoke_signed(&instruction, &[from, to, authority], &[]).map_err(Into::into)
     }
 
     /// Mint tokens using the program's transfer authority PDA
@@ -260,17 +653,15 @@ impl Perpetuals {
         let authority_seeds: &[&[&[u8]]] =
             &[&[b"transfer_authority", &[self.transfer_authority_bump]]];
 
-        let context = CpiContext::new(
-            token_program,
-            MintTo {
-                mint,
-                to,
-                authority,
-            },
-        )
-        .with_signer(authority_seeds);
-
-        anchor_spl::token::mint_to(context, amount)
+        let instruction = spl_token::instruction::mint_to(
+            token_program.key,
+            mint.key,
+            to.key,
+            authority.key,
+            &[],
+            amount,
+        )?;
+        invoke_signed(&instruction, &[mint, to, authority], authority_seeds).map_err(Into::into)
     }
 
     /// Burn tokens from an account
@@ -289,16 +680,15 @@ impl Perpetuals {
         token_program: AccountInfo<'info>,
         amount: u64,
     ) -> Result<()> {
-        let context = CpiContext::new(
-            token_program,
-            Burn {
-                mint,
-                from,
-                authority,
-            },
-        );
-
-        anchor_spl::token::burn(context, amount)
+        let instruction = spl_token::instruction::burn(
+            token_program.key,
+            from.key,
+            mint.key,
+            authority.key,
+            &[],
+            amount,
+        )?;
+        invoke_signed(&instruction, &[from, mint, authority], &[]).map_err(Into::into)
     }
 
     /// Check if an account is empty (no data or zero lamports)
@@ -312,6 +702,69 @@ impl Perpetuals {
         Ok(account_info.try_data_is_empty()? || account_info.try_lamports()? == 0)
     }
 
+    /// Load a settlement receiving account, creating the owner ATA if it doesn't exist
+    ///
+    /// Settlements pay out to the owner ATA, creating it on the fly (paid by the signer)
+    /// keeps them from failing on a closed or never opened token account. The creation
+    /// accounts are optional, without them a missing receiving account is an error.
+    /// Mint and owner of the returned account must be checked by the caller.
+    ///
+    /// # Arguments
+    /// * `payer` - Account paying the token account rent (must be signer)
+    /// * `receiving_account` - Receiving token account, the owner ATA if created
+    /// * `owner` - Owner of the receiving account
+    /// * `mint` - Mint of the receiving account
+    /// * `system_program` - System program account
+    /// * `token_program` - Token program account
+    /// * `associated_token_program` - Associated token program account
+    ///
+    /// # Returns
+    /// The receiving token account
+    pub fn load_or_create_receiving_account<'info>(
+        payer: AccountInfo<'info>,
+        receiving_account: AccountInfo<'info>,
+        owner: Option<AccountInfo<'info>>,
+        mint: Option<AccountInfo<'info>>,
+        system_program: Option<AccountInfo<'info>>,
+        token_program: AccountInfo<'info>,
+        associated_token_program: Option<AccountInfo<'info>>,
+    ) -> Result<TokenAccount> {
+        if Perpetuals::is_empty_account(&receiving_account)? {
+            let (Some(owner), Some(mint), Some(system_program), Some(associated_token_program)) =
+                (owner, mint, system_program, associated_token_program)
+            else {
+                return err!(PerpetualsError::InvalidReceivingAccount);
+            };
+            msg!("Create receiving account");
+            let instruction =
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    payer.key,
+                    owner.key,
+                    mint.key,
+                    token_program.key,
+                );
+            invoke(
+                &instruction,
+                &[
+                    payer,
+                    receiving_account.clone(),
+                    owner,
+                    mint,
+                    system_program,
+                    token_program,
+                    associated_token_program,
+                ],
+            )?;
+        }
+
+        require_keys_eq!(
+            *receiving_account.owner,
+            Token::id(),
+            PerpetualsError::InvalidReceivingAccount
+        );
+        TokenAccount::try_deserialize(&mut &receiving_account.try_borrow_data()?[..])
+    }
+
     /// Close a token account and transfer remaining lamports to receiver
     /// 
     /// # Arguments
@@ -361,6 +814,38 @@ impl Perpetuals {
         Ok(())
     }
 
+    /// Get the lamports sweep_sol can move out of a program-owned account
+    ///
+    /// Accounts keep their rent-exempt minimum. The excluded accounts (SOL fee vault,
+    /// multisig), crank state accounts (keeper bounty budget) and order commitments
+    /// (commit-reveal deposits) can't be swept.
+    ///
+    /// # Arguments
+    /// * `account` - Account to sweep, must be writable
+    /// * `excluded` - Keys of accounts that can't be swept
+    /// * `rent` - Rent sysvar
+    ///
+    /// # Returns
+    /// Lamports above the rent-exempt minimum, or error if the account can't be swept
+    pub fn get_sweepable_lamports(
+        account: &AccountInfo,
+        excluded: &[Pubkey],
+        rent: &Rent,
+    ) -> Result<u64> {
+        let data = account.try_borrow_data()?;
+        if account.owner != &crate::ID
+            || !account.is_writable
+            || excluded.contains(account.key)
+            || data.starts_with(CrankState::DISCRIMINATOR)
+            || data.starts_with(OrderCommitment::DISCRIMINATOR)
+        {
+            return Err(anchor_lang::error::ErrorCode::ConstraintRaw.into());
+        }
+        Ok(account
+            .try_lamports()?
+            .saturating_sub(rent.minimum_balance(data.len())))
+    }
+
     /// Transfer SOL using system program CPI
     /// 
     /// # Arguments
@@ -410,8 +895,216 @@ impl Perpetuals {
             lamports_diff,
         )?;
 
+        #[allow(deprecated)]
         target_account
             .realloc(new_len, zero_init)
             .map_err(|_| ProgramError::InvalidRealloc.into())
     }
-}
\ No newline at end of file
+
+    /// Create a program-owned PDA account
+    ///
+    /// # Arguments
+    /// * `funding_account` - Account paying rent for the new account (must be signer)
+    /// * `target_account` - PDA to create
+    /// * `system_program` - System program account
+    /// * `space` - Account size in bytes
+    /// * `seeds` - Seeds for signing the PDA
+    pub fn create_account<'a>(
+        funding_account: AccountInfo<'a>,
+        target_account: AccountInfo<'a>,
+        system_program: AccountInfo<'a>,
+        space: usize,
+        seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = anchor_lang::system_program::CreateAccount {
+            from: funding_account,
+            to: target_account,
+        };
+        let cpi_context = anchor_lang::context::CpiContext::new(system_program, cpi_accounts);
+
+        anchor_lang::system_program::create_account(
+            cpi_context.with_signer(seeds),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )
+    }
+
+    /// Shrink a program-owned account to a new size
+    ///
+    /// Lamports above the rent-exempt minimum for the new size are refunded to the receiver.
+    ///
+    /// # Arguments
+    /// * `target_account` - Program-owned account to shrink
+    /// * `receiver` - Account receiving the freed rent
+    /// * `new_len` - New account size in bytes (must not exceed the current size)
+    pub fn shrink<'a>(
+        target_account: AccountInfo<'a>,
+        receiver: AccountInfo<'a>,
+        new_len: usize,
+    ) -> Result<()> {
+        if new_len > target_account.data_len() {
+            return Err(ProgramError::InvalidRealloc.into());
+        }
+
+        #[allow(deprecated)]
+        target_account
+            .realloc(new_len, false)
+            .map_err(|_| ProgramError::InvalidRealloc)?;
+
+        let new_minimum_balance = Rent::get()?.minimum_balance(new_len);
+        let refund = target_account
+            .try_lamports()?
+            .saturating_sub(new_minimum_balance);
+
+        Perpetuals::transfer_sol_from_owned(target_account, receiver, refund)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*, crate::state::pending_claim::PendingClaim,
+        anchor_lang::solana_program::program_pack::Pack,
+        anchor_spl::{associated_token, token::spl_token},
+    };
+
+    fn get_account_info<T: AccountSerialize>(
+        owner: Pubkey,
+        is_writable: bool,
+        lamports: u64,
+        account: &T,
+    ) -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
+            false,
+            is_writable,
+            Box::leak(Box::new(lamports)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_get_sweepable_lamports() {
+        let rent = Rent::default();
+        let excess = 1_000;
+
+        // lamports above rent are swept
+        let position = crate::state::position::Position::default();
+        let min_balance = rent.minimum_balance(8 + position.try_to_vec().unwrap().len());
+        let account = get_account_info(crate::ID, true, min_balance + excess, &position);
+        assert_eq!(
+            Perpetuals::get_sweepable_lamports(&account, &[], &rent),
+            Ok(excess)
+        );
+        let account = get_account_info(crate::ID, true, min_balance - 1, &position);
+        assert_eq!(
+            Perpetuals::get_sweepable_lamports(&account, &[], &rent),
+            Ok(0)
+        );
+
+        // not program-owned or read-only
+        let account = get_account_info(Pubkey::new_unique(), true, min_balance + excess, &position);
+        assert!(Perpetuals::get_sweepable_lamports(&account, &[], &rent).is_err());
+        let account = get_account_info(crate::ID, false, min_balance + excess, &position);
+        assert!(Perpetuals::get_sweepable_lamports(&account, &[], &rent).is_err());
+
+        // SOL fee vault and multisig
+        let account = get_account_info(crate::ID, true, min_balance + excess, &position);
+        let vault = Pubkey::new_unique();
+        assert!(
+            Perpetuals::get_sweepable_lamports(&account, &[vault, *account.key], &rent).is_err()
+        );
+        assert!(Perpetuals::get_sweepable_lamports(&account, &[vault], &rent).is_ok());
+
+        // keeper bounty budget
+        let account = get_account_info(crate::ID, true, u64::MAX / 2, &CrankState::default());
+        assert!(Perpetuals::get_sweepable_lamports(&account, &[], &rent).is_err());
+
+        // commit-reveal deposit
+        let commitment = OrderCommitment {
+            deposit: excess,
+            ..OrderCommitment::default()
+        };
+        let account = get_account_info(crate::ID, true, u64::MAX / 2, &commitment);
+        assert!(Perpetuals::get_sweepable_lamports(&account, &[], &rent).is_err());
+    }
+
+    #[test]
+    fn test_claim_receiving_account() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let owner_ata = associated_token::get_associated_token_address(&owner, &mint);
+        let claim = PendingClaim {
+            owner,
+            receiving_account: Pubkey::new_unique(),
+            ..PendingClaim::default()
+        };
+
+        // the claim is paid to its receiving account, or to the owner ATA as a fallback
+        assert!(claim.is_receiving_account(&claim.receiving_account, &mint));
+        assert!(claim.is_receiving_account(&owner_ata, &mint));
+        assert!(!claim.is_receiving_account(
+            &associated_token::get_associated_token_address(&Pubkey::new_unique(), &mint),
+            &mint
+        ));
+        assert!(!claim.is_receiving_account(
+            &associated_token::get_associated_token_address(&owner, &Pubkey::new_unique()),
+            &mint
+        ));
+
+        let get_token_account = |key: Pubkey, lamports: u64, owner: Pubkey, data: Vec<u8>| {
+            AccountInfo::new(
+                Box::leak(Box::new(key)),
+                false,
+                true,
+                Box::leak(Box::new(lamports)),
+                Box::leak(data.into_boxed_slice()),
+                Box::leak(Box::new(owner)),
+                false,
+                0,
+            )
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        }
+        .pack_into_slice(&mut data);
+        let token_program = get_token_account(Token::id(), 1, Pubkey::default(), vec![]);
+        let load = |receiving_account: AccountInfo<'static>| {
+            Perpetuals::load_or_create_receiving_account(
+                token_program.clone(),
+                receiving_account,
+                None,
+                None,
+                None,
+                token_program.clone(),
+                None,
+            )
+        };
+
+        // an existing owner ATA is loaded
+        let account = load(get_token_account(owner_ata, 1, Token::id(), data.clone())).unwrap();
+        assert_eq!((account.mint, account.owner), (mint, owner));
+
+        // a closed account can't be paid without the accounts to create the owner ATA
+        assert_eq!(
+            load(get_token_account(owner_ata, 0, Pubkey::default(), vec![])).unwrap_err(),
+            PerpetualsError::InvalidReceivingAccount.into()
+        );
+
+        // accounts not owned by the token program are rejected
+        assert_eq!(
+            load(get_token_account(owner_ata, 1, crate::ID, data)).unwrap_err(),
+            PerpetualsError::InvalidReceivingAccount.into()
+        );
+    }
+}
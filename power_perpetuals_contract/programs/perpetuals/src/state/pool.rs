@@ -5,16 +5,20 @@
 
 use {
     crate::{
+        conversions,
         error::PerpetualsError,
-        math,
+        math::{self, RoundingDirection},
         state::{
-            custody::{Custody, FeesMode},
-            oracle::OraclePrice,
+            backstop::BackstopTranche,
+            custody::{Custody, EntryFeeTier, FeeType, FeesMode, LiquidationPriceMode},
+            oracle::{OracleOperation, OraclePrice},
             perpetuals::Perpetuals,
-            position::{Position, Side},
+            position::{Position, RiskTier, Side},
+            position_hook::{PositionHook, PositionHookPayload},
         },
     },
     anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
     std::cmp::Ordering,
 };
 
@@ -33,6 +37,37 @@ pub enum AumCalcMode {
     EMA,
 }
 
+/// Pool flow moving custody balances, decides whether token ratio bounds apply
+///
+/// User-initiated swaps and liquidity operations are held to the bounds. Flows that
+/// settle positions on behalf of the protocol are exempt, they have to go through in
+/// stressed markets, when ratios are the most likely to be out of range.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RatioFlow {
+    /// Swapping tokens
+    Swap,
+    /// Adding liquidity
+    AddLiquidity,
+    /// Removing liquidity
+    RemoveLiquidity,
+    /// Liquidating positions
+    Liquidation,
+    /// Settling positions of expired power futures markets
+    ExpirySettlement,
+    /// Settling positions at an admin supplied price
+    ForceSettlement,
+}
+
+impl RatioFlow {
+    /// Whether the flow may move a custody outside its token ratio bounds
+    pub fn is_ratio_exempt(&self) -> bool {
+        matches!(
+            self,
+            RatioFlow::Liquidation | RatioFlow::ExpirySettlement | RatioFlow::ForceSettlement
+        )
+    }
+}
+
 /// Token ratio constraints for pool rebalancing
 /// 
 /// All ratios are in basis points (BPS), where 10,000 BPS = 100%
@@ -46,6 +81,75 @@ pub struct TokenRatios {
     pub max: u64,
 }
 
+/// Rate limit on collateral removal from large positions
+///
+/// Keeps large positions from stripping their collateral right before an adverse
+/// oracle update.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CollateralWithdrawalLimit {
+    /// Position size from which collateral removal is limited (USD, scaled to USD_DECIMALS)
+    pub min_size_usd: u64,
+    /// Share of the position collateral removable per window (BPS, 0 = disabled)
+    pub max_withdrawal_bps: u64,
+    /// Window duration in seconds
+    pub window_sec: i64,
+}
+
+impl CollateralWithdrawalLimit {
+    /// Validate the limit configuration
+    pub fn validate(&self) -> bool {
+        self.max_withdrawal_bps == 0
+            || (self.max_withdrawal_bps as u128 <= Perpetuals::BPS_POWER && self.window_sec > 0)
+    }
+}
+
+/// Result of checking whether a position can be liquidated
+///
+/// All leverages are in BPS.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct LiquidationCheck {
+    /// Leverage using the aggregate spot/EMA price selection
+    pub leverage: u64,
+    /// Leverage using spot prices only
+    pub spot_leverage: u64,
+    /// Leverage using EMA prices only
+    pub ema_leverage: u64,
+    /// Maintenance leverage adjusted for position power (liquidation threshold)
+    pub max_leverage: u64,
+    /// Whether the position can be liquidated under the custody liquidation price mode
+    pub liquidatable: bool,
+    /// Maintenance leverage over the leverage deciding liquidation (BPS_POWER = liquidation
+    /// threshold)
+    pub health_factor: u64,
+    /// Risk tier of the position under the custody thresholds
+    pub risk_tier: RiskTier,
+}
+
+/// Settled amounts of a position being closed, or settled and re-entered
+///
+/// Token amounts are in collateral tokens, USD values scaled to USD_DECIMALS.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct PositionSettlement {
+    /// Collateral returned to the owner, or carried over to the re-entered position
+    pub amount: u64,
+    /// Fee charged in kind out of the collateral
+    pub fee_amount: u64,
+    /// Total fee in USD, charged in kind or in the pool fee token
+    pub fee_usd: u64,
+    /// Fee tracked in the collected close position fees
+    pub close_fee_usd: u64,
+    /// Fee tracked in the collected open position fees
+    pub open_fee_usd: u64,
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+    /// Borrow interest paid by the position, feeds the pnl reserve
+    pub interest_amount: u64,
+    /// Whether the collateral stays in the pool for a re-entered position
+    pub reentered: bool,
+    /// Whether the payout waits in the claim queue
+    pub queued: bool,
+}
+
 /// Pool account - manages a multi-token liquidity pool
 /// 
 /// The pool tracks multiple token custodies, their target ratios,
@@ -68,6 +172,112 @@ pub struct Pool {
     pub lp_token_bump: u8,
     /// Pool creation timestamp
     pub inception_time: i64,
+    /// Whether liquidity operations are restricted to the LP allowlist
+    pub lp_allowlist_enabled: bool,
+    /// Maximum number of open positions per owner (0 = unlimited)
+    pub max_positions_per_owner: u32,
+    /// Custody trade fees can be paid in instead of the collateral token
+    /// (default = fees are always charged in kind)
+    pub fee_custody: Pubkey,
+    /// Sequence number of the last emitted pool event
+    pub event_seq: u64,
+    /// Protocol share of LP token price gains above the high-water mark (BPS, 0 = disabled)
+    pub performance_fee_bps: u64,
+    /// LP token account performance fees are minted to
+    pub performance_fee_account: Pubkey,
+    /// Minimum duration of a performance fee epoch in seconds
+    pub performance_epoch_sec: i64,
+    /// Start time of the current performance fee epoch
+    pub performance_epoch_start: i64,
+    /// Highest LP token price recorded at an epoch rollover, net of performance fees
+    /// (scaled to USD_DECIMALS, 0 = not recorded yet)
+    pub lp_price_hwm: u64,
+    /// Number of slots the liquidation reward ramps up over (0 = fixed reward)
+    pub liquidation_auction_slots: u64,
+    /// Share of the full liquidation reward claimable when the auction starts (BPS)
+    pub liquidation_auction_start_share: u64,
+    /// Maximum number of position opens and closes per owner and slot (0 = unlimited)
+    pub max_trades_per_slot: u16,
+    /// External program notified of position lifecycle events
+    pub position_hook: PositionHook,
+    /// Open position fee surcharge at a fully one-sided book, charged on opens towards
+    /// the heavier side and refunded on opens against it (BPS, 0 = disabled)
+    pub imbalance_fee_bps: u64,
+    /// Junior liquidity tranche absorbing trader profits before pool LPs
+    pub backstop: BackstopTranche,
+    /// Whether opens and increases are checked against the custody compliance freeze lists
+    pub compliance_freeze_enabled: bool,
+    /// Maximum LP token supply (0 = uncapped)
+    pub max_lp_supply: u64,
+    /// Rate limit on collateral removal from large positions
+    pub collateral_withdrawal_limit: CollateralWithdrawalLimit,
+    /// Allow user-facing trading instructions of the pool to be invoked through CPI,
+    /// on top of the global and custody permissions
+    pub allow_cpi: bool,
+}
+
+/// Original pool account layout, upgraded by upgrade_pool.
+/// Shares the Pool account discriminator.
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PoolV0 {
+    pub name: String,
+    pub custodies: Vec<Pubkey>,
+    pub ratios: Vec<TokenRatios>,
+    pub aum_usd: u128,
+    pub bump: u8,
+    pub lp_token_bump: u8,
+    pub inception_time: i64,
+}
+
+impl PoolV0 {
+    /// Account size in bytes without the custodies (8 byte discriminator + 64 byte
+    /// string + data)
+    pub const LEN: usize = 8 + 64 + std::mem::size_of::<PoolV0>();
+
+    /// Load a pool stored in the original layout
+    ///
+    /// The account is sized for exactly its custodies, which tells it apart from the
+    /// current layout.
+    pub fn load(data: &[u8]) -> Option<PoolV0> {
+        if data.get(..8)? != Pool::DISCRIMINATOR {
+            return None;
+        }
+        let pool = PoolV0::deserialize(&mut &data[8..]).ok()?;
+        let len = PoolV0::LEN
+            + pool.custodies.len() * std::mem::size_of::<Pubkey>()
+            + pool.ratios.len() * std::mem::size_of::<TokenRatios>();
+        (data.len() == len).then_some(pool)
+    }
+}
+
+impl From<PoolV0> for Pool {
+    fn from(pool: PoolV0) -> Self {
+        // fields added since the original layout start disabled
+        Pool {
+            name: pool.name,
+            custodies: pool.custodies,
+            ratios: pool.ratios,
+            aum_usd: pool.aum_usd,
+            bump: pool.bump,
+            lp_token_bump: pool.lp_token_bump,
+            inception_time: pool.inception_time,
+            ..Pool::default()
+        }
+    }
+}
+
+/// Accounts used to charge trade fees in the pool fee token
+///
+/// Passed to open_position and close_position as remaining accounts:
+///   - fee custody (mutable)
+///   - fee custody oracle account
+///   - fee custody token account (mutable)
+///   - user's fee token account (mutable, owned by the position owner)
+pub struct FeeTokenAccounts<'info> {
+    pub fee_custody: Account<'info, Custody>,
+    pub fee_custody_oracle_account: AccountInfo<'info>,
+    pub fee_custody_token_account: AccountInfo<'info>,
+    pub funding_account: AccountInfo<'info>,
 }
 
 impl TokenRatios {
@@ -88,6 +298,89 @@ impl TokenRatios {
     }
 }
 
+impl<'info> FeeTokenAccounts<'info> {
+    /// Charge a trade fee in the fee token
+    ///
+    /// The fee is converted at the lower of the fee token spot and EMA prices and
+    /// transferred from the user's fee token account. The protocol share goes to
+    /// the fee custody protocol fees, the rest to the fee custody owned assets.
+    ///
+    /// # Arguments
+    /// * `perpetuals` - Perpetuals account (for token transfers)
+    /// * `owner` - Position owner (transfer authority of the funding account)
+    /// * `token_program` - Token program account
+    /// * `fee_amount_usd` - Fee to charge in USD
+    /// * `protocol_share` - Protocol share of the fee in BPS
+    /// * `operation` - Operation the fee is charged for (Open or Close)
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// Fee amount charged in fee token decimals
+    #[allow(clippy::too_many_arguments)]
+    pub fn charge_fee(
+        &mut self,
+        perpetuals: &Perpetuals,
+        owner: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        fee_amount_usd: u64,
+        protocol_share: u64,
+        operation: OracleOperation,
+        curtime: i64,
+    ) -> Result<u64> {
+        let fee_custody = &mut self.fee_custody;
+        let fee_token_price = fee_custody.get_oracle_price(
+            &self.fee_custody_oracle_account,
+            curtime,
+            false,
+            operation,
+        )?;
+        let fee_token_ema_price = fee_custody.get_oracle_price(
+            &self.fee_custody_oracle_account,
+            curtime,
+            fee_custody.pricing.use_ema,
+            operation,
+        )?;
+        let min_price = fee_token_price.get_min_price(&fee_token_ema_price, fee_custody.is_stable)?;
+
+        let fee_amount = min_price.get_token_amount(
+            fee_amount_usd,
+            fee_custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        msg!("Collected fee in fee token: {}", fee_amount);
+        perpetuals.transfer_tokens_from_user(
+            self.funding_account.clone(),
+            self.fee_custody_token_account.clone(),
+            owner,
+            token_program,
+            fee_amount,
+        )?;
+
+        if operation == OracleOperation::Open {
+            fee_custody.collected_fees.open_position_usd = math::checked_add(
+                fee_custody.collected_fees.open_position_usd,
+                fee_amount_usd as u128,
+            )?;
+        } else {
+            fee_custody.collected_fees.close_position_usd = math::checked_add(
+                fee_custody.collected_fees.close_position_usd,
+                fee_amount_usd as u128,
+            )?;
+        }
+
+        let protocol_fee = Pool::get_fee_amount(protocol_share, fee_amount)?;
+        fee_custody.assets.protocol_fees =
+            math::checked_add(fee_custody.assets.protocol_fees, protocol_fee)?;
+        fee_custody.assets.owned = math::checked_add(
+            fee_custody.assets.owned,
+            math::checked_sub(fee_amount, protocol_fee)?,
+        )?;
+        fee_custody.exit(&crate::ID)?;
+
+        Ok(fee_amount)
+    }
+}
+
 /// Token Pool implementation
 /// 
 /// All returned prices are scaled to PRICE_DECIMALS.
@@ -96,6 +389,247 @@ impl Pool {
     /// Account size in bytes (8 byte discriminator + 64 byte string + data)
     pub const LEN: usize = 8 + 64 + std::mem::size_of::<Pool>();
 
+    /// Load the accounts used to charge trade fees in the pool fee token
+    ///
+    /// Fees are charged in kind (returns None) if the pool has no fee custody, the
+    /// accounts are not provided, or the fee custody is one of the trade custodies.
+    ///
+    /// # Arguments
+    /// * `remaining_accounts` - Remaining accounts of the instruction
+    /// * `owner` - Position owner, must own the fee token account
+    /// * `custody` - Custody account key of the position token
+    /// * `collateral_custody` - Custody account key of the collateral token
+    ///
+    /// # Returns
+    /// Validated fee token accounts, if fees are paid in the fee token
+    pub fn load_fee_token_accounts<'info>(
+        &self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        owner: &Pubkey,
+        custody: &Pubkey,
+        collateral_custody: &Pubkey,
+    ) -> Result<Option<FeeTokenAccounts<'info>>> {
+        if self.fee_custody == Pubkey::default()
+            || remaining_accounts.len() < 4
+            || self.fee_custody == *custody
+            || self.fee_custody == *collateral_custody
+        {
+            return Ok(None);
+        }
+
+        let fee_custody_info = &remaining_accounts[0];
+        require_keys_eq!(fee_custody_info.key(), self.fee_custody);
+        require!(
+            fee_custody_info.is_writable,
+            PerpetualsError::InvalidCustodyState
+        );
+        let fee_custody = Account::<Custody>::try_from(fee_custody_info)?;
+
+        let fee_custody_oracle_account = &remaining_accounts[1];
+        require_keys_eq!(
+            fee_custody_oracle_account.key(),
+            fee_custody.oracle.oracle_account
+        );
+
+        let fee_custody_token_account = &remaining_accounts[2];
+        require_keys_eq!(fee_custody_token_account.key(), fee_custody.token_account);
+
+        let funding_account = &remaining_accounts[3];
+        let funding_token_account = Account::<TokenAccount>::try_from(funding_account)?;
+        require_keys_eq!(funding_token_account.owner, *owner);
+        require_keys_eq!(funding_token_account.mint, fee_custody.mint);
+
+        Ok(Some(FeeTokenAccounts {
+            fee_custody,
+            fee_custody_oracle_account: fee_custody_oracle_account.clone(),
+            fee_custody_token_account: fee_custody_token_account.clone(),
+            funding_account: funding_account.clone(),
+        }))
+    }
+
+    /// Advance the pool event sequence
+    ///
+    /// # Returns
+    /// Sequence number to embed in the next emitted event
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        self.event_seq = math::checked_add(self.event_seq, 1)?;
+        Ok(self.event_seq)
+    }
+
+    /// Maximum performance fee (BPS)
+    pub const MAX_PERFORMANCE_FEE_BPS: u64 = 5_000;
+    /// Maximum open interest imbalance fee (BPS)
+    pub const MAX_IMBALANCE_FEE_BPS: u64 = 1_000;
+    /// Liquidation auctions expire after this many auction durations, so an auction
+    /// started before the position recovered doesn't pay the full reward right away
+    pub const LIQUIDATION_AUCTION_EXPIRY_MULT: u64 = 2;
+
+    /// Compute the LP token price
+    ///
+    /// # Arguments
+    /// * `aum_usd` - Pool assets under management in USD (scaled to USD_DECIMALS)
+    /// * `lp_supply` - LP token supply
+    ///
+    /// # Returns
+    /// LP token price in USD (scaled to USD_DECIMALS), or 0 if the supply is zero
+    pub fn get_lp_price(aum_usd: u64, lp_supply: u64) -> Result<u64> {
+        if lp_supply == 0 {
+            return Ok(0);
+        }
+        math::checked_decimal_div(
+            aum_usd,
+            -(Perpetuals::USD_DECIMALS as i32),
+            lp_supply,
+            -(Perpetuals::LP_DECIMALS as i32),
+            -(Perpetuals::USD_DECIMALS as i32),
+        )
+    }
+
+    /// Compute the performance fee due at an epoch rollover
+    ///
+    /// The fee is a share of the LP token price gain above the high-water mark over
+    /// the whole supply. It is paid by minting LP tokens, the amount is chosen so the
+    /// minted tokens are worth the fee after the dilution they cause.
+    ///
+    /// # Arguments
+    /// * `aum_usd` - Pool assets under management in USD (scaled to USD_DECIMALS)
+    /// * `lp_supply` - LP token supply before the fee is minted
+    ///
+    /// # Returns
+    /// Tuple of (fee in USD, LP tokens to mint), zero if no fee is due
+    pub fn get_performance_fee(&self, aum_usd: u64, lp_supply: u64) -> Result<(u64, u64)> {
+        let lp_price = Self::get_lp_price(aum_usd, lp_supply)?;
+        if self.performance_fee_bps == 0 || self.lp_price_hwm == 0 || lp_price <= self.lp_price_hwm
+        {
+            return Ok((0, 0));
+        }
+
+        let gain_usd = math::checked_decimal_mul(
+            math::checked_sub(lp_price, self.lp_price_hwm)?,
+            -(Perpetuals::USD_DECIMALS as i32),
+            lp_supply,
+            -(Perpetuals::LP_DECIMALS as i32),
+            -(Perpetuals::USD_DECIMALS as i32),
+        )?;
+        let fee_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(gain_usd as u128, self.performance_fee_bps as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        if fee_usd == 0 {
+            return Ok((0, 0));
+        }
+
+        // fee_lp / (lp_supply + fee_lp) = fee_usd / aum_usd
+        let fee_lp = math::checked_as_u64(math::checked_div(
+            math::checked_mul(fee_usd as u128, lp_supply as u128)?,
+            math::checked_sub(aum_usd, fee_usd)? as u128,
+        )?)?;
+
+        Ok((fee_usd, fee_lp))
+    }
+
+    /// Roll over the performance fee epoch once it has elapsed
+    ///
+    /// Computes the performance fee, moves the high-water mark to the LP token price net
+    /// of the fee and starts a new epoch. The caller mints the fee LP tokens.
+    ///
+    /// # Arguments
+    /// * `aum_usd` - Pool assets under management in USD (scaled to USD_DECIMALS)
+    /// * `lp_supply` - LP token supply before the fee is minted
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// Tuple of (LP token price, fee in USD, LP tokens to mint)
+    pub fn roll_performance_epoch(
+        &mut self,
+        aum_usd: u64,
+        lp_supply: u64,
+        curtime: i64,
+    ) -> Result<(u64, u64, u64)> {
+        require!(
+            self.performance_fee_bps > 0,
+            PerpetualsError::InstructionNotAllowed
+        );
+        require!(
+            curtime >= math::checked_add(self.performance_epoch_start, self.performance_epoch_sec)?,
+            PerpetualsError::PerformanceEpochNotElapsed
+        );
+
+        let lp_price = Self::get_lp_price(aum_usd, lp_supply)?;
+        let (fee_usd, fee_lp_amount) = self.get_performance_fee(aum_usd, lp_supply)?;
+        let net_lp_price =
+            Self::get_lp_price(aum_usd, math::checked_add(lp_supply, fee_lp_amount)?)?;
+        self.lp_price_hwm = std::cmp::max(self.lp_price_hwm, net_lp_price);
+        self.performance_epoch_start = curtime;
+
+        Ok((lp_price, fee_usd, fee_lp_amount))
+    }
+
+    /// Compute the LP tokens minted for a deposit
+    ///
+    /// Deposits are priced at the current LP token price. Without LP supply there is
+    /// no price: once every LP token is burned the pool can still hold residual AUM
+    /// (rounding dust, open position collateral), so the next deposit is priced like
+    /// the first one (1 LP token per USD) and the depositor is credited the residual.
+    /// The same applies while the senior AUM is zero.
+    ///
+    /// # Arguments
+    /// * `token_amount_usd` - Deposit value in USD after fees
+    /// * `pool_amount_usd` - Senior pool AUM in USD before the deposit
+    /// * `lp_supply` - LP token supply before the mint
+    ///
+    /// # Returns
+    /// LP tokens to mint
+    pub fn get_lp_amount(
+        token_amount_usd: u64,
+        pool_amount_usd: u128,
+        lp_supply: u64,
+    ) -> Result<u64> {
+        if lp_supply == 0 || pool_amount_usd == 0 {
+            return Ok(token_amount_usd);
+        }
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(token_amount_usd as u128, lp_supply as u128)?,
+            pool_amount_usd,
+        )?)
+    }
+
+    /// Number of LP tokens that can still be minted under the supply cap
+    ///
+    /// # Arguments
+    /// * `lp_supply` - LP token supply
+    ///
+    /// # Returns
+    /// Remaining LP token capacity, u64::MAX if the supply is uncapped
+    pub fn get_lp_supply_capacity(&self, lp_supply: u64) -> u64 {
+        if self.max_lp_supply == 0 {
+            u64::MAX
+        } else {
+            self.max_lp_supply.saturating_sub(lp_supply)
+        }
+    }
+
+    /// Check that minting LP tokens keeps the supply within the cap
+    ///
+    /// # Arguments
+    /// * `lp_supply` - LP token supply before the mint
+    /// * `lp_amount` - LP tokens to mint
+    pub fn check_lp_supply_cap(&self, lp_supply: u64, lp_amount: u64) -> Result<()> {
+        require!(
+            lp_amount <= self.get_lp_supply_capacity(lp_supply),
+            PerpetualsError::LpSupplyCapExceeded
+        );
+        Ok(())
+    }
+
+    /// Exact account size in bytes needed to store the current pool data
+    ///
+    /// # Returns
+    /// 8 byte discriminator + serialized pool size
+    pub fn get_size(&self) -> Result<usize> {
+        Ok(8 + borsh::to_vec(self)?.len())
+    }
+
     /// Validate pool configuration
     /// 
     /// Checks:
@@ -119,7 +653,7 @@ impl Pool {
             && self
                 .ratios
                 .iter()
-                .map(|&x| (x.target as u128))
+                .map(|&x| x.target as u128)
                 .sum::<u128>()
                 != Perpetuals::BPS_POWER
         {
@@ -133,7 +667,11 @@ impl Pool {
             }
         }
 
-        !self.name.is_empty() && self.name.len() <= 64 && self.custodies.len() == self.ratios.len()
+        !self.name.is_empty()
+            && self.name.len() <= 64
+            && self.custodies.len() == self.ratios.len()
+            && self.position_hook.validate()
+            && self.backstop.validate()
     }
 
     /// Get the token ID (index) for a given custody address
@@ -159,6 +697,7 @@ impl Pool {
     /// * `token_ema_price` - EMA price from oracle
     /// * `side` - Position side (Long or Short)
     /// * `custody` - Custody account for the token
+    /// * `curtime` - Current timestamp, the spread scales with the custody volatility
     /// 
     /// # Returns
     /// Entry price scaled to PRICE_DECIMALS
@@ -168,16 +707,13 @@ impl Pool {
         token_ema_price: &OraclePrice,
         side: Side,
         custody: &Custody,
+        curtime: i64,
     ) -> Result<u64> {
         let price = self.get_price(
             token_price,
             token_ema_price,
             side,
-            if side == Side::Long {
-                custody.pricing.trade_spread_long
-            } else {
-                custody.pricing.trade_spread_short
-            },
+            custody.get_entry_spread(side, curtime)?,
         )?;
         require_gt!(price.price, 0, PerpetualsError::MaxPriceSlippage);
 
@@ -195,9 +731,14 @@ impl Pool {
     /// - entry_fee = custody.fees.open_position * utilization_fee * size
     /// - utilization_fee = 1 + custody.fees.utilization_mult * (new_utilization - optimal_utilization) / (1 - optimal_utilization)
     /// 
+    /// Size tiers add their fee on top, charged on the part of the size above each
+    /// tier threshold, so the fee doesn't jump at a tier boundary.
+    /// 
     /// # Arguments
     /// * `base_fee` - Base fee rate (in BPS)
+    /// * `fee_tiers` - Size dependent fee tiers of the position custody
     /// * `size` - Position size in tokens
+    /// * `size_usd` - Position size in USD
     /// * `locked_amount` - Amount that will be locked for this position
     /// * `collateral_custody` - Custody account for collateral token
     /// 
@@ -206,7 +747,9 @@ impl Pool {
     pub fn get_entry_fee(
         &self,
         base_fee: u64,
+        fee_tiers: &[EntryFeeTier],
         size: u64,
+        size_usd: u64,
         locked_amount: u64,
         collateral_custody: &Custody,
     ) -> Result<u64> {
@@ -252,9 +795,113 @@ impl Pool {
             )?)?;
         }
 
+        for tier in fee_tiers {
+            if tier.min_size_usd == 0 || size_usd <= tier.min_size_usd {
+                continue;
+            }
+            // part of the size above the tier threshold, in tokens
+            let tier_size = math::checked_as_u64(math::checked_div(
+                math::checked_mul(
+                    size as u128,
+                    math::checked_sub(size_usd, tier.min_size_usd)? as u128,
+                )?,
+                size_usd as u128,
+            )?)?;
+            size_fee = math::checked_add(size_fee, Self::get_fee_amount(tier.fee, tier_size)?)?;
+        }
+
         Ok(size_fee)
     }
 
+    /// Run realized pool gains and losses through the backstop tranche waterfall
+    ///
+    /// # Arguments
+    /// * `gain_usd` - Pool share of realized fees and trader losses in USD
+    /// * `loss_usd` - Realized trader profits paid by the pool in USD
+    pub fn record_backstop_pnl(&mut self, gain_usd: u64, loss_usd: u64) -> Result<()> {
+        self.backstop.record_pnl(gain_usd, loss_usd, self.aum_usd)
+    }
+
+    /// Get the open interest skew towards a side, averaged over a trade
+    ///
+    /// The skew is (side OI - opposite OI) / total OI, positive when the side is the
+    /// heavier one. It is averaged before and after the trade so splitting an order
+    /// doesn't change its fee.
+    ///
+    /// # Arguments
+    /// * `custody` - Custody account of the position token
+    /// * `side` - Side of the trade
+    /// * `size_usd` - Trade size in USD
+    ///
+    /// # Returns
+    /// Skew in BPS, between -BPS_POWER and BPS_POWER
+    pub fn get_oi_skew(custody: &Custody, side: Side, size_usd: u64) -> Result<i128> {
+        let (side_oi, opposite_oi) = match side {
+            Side::Long => (
+                custody.trade_stats.oi_long_usd,
+                custody.trade_stats.oi_short_usd,
+            ),
+            Side::Short => (
+                custody.trade_stats.oi_short_usd,
+                custody.trade_stats.oi_long_usd,
+            ),
+            Side::None => return Ok(0),
+        };
+        let get_skew = |side_oi: u64| -> Result<i128> {
+            let total_oi = math::checked_add(side_oi as i128, opposite_oi as i128)?;
+            if total_oi == 0 {
+                return Ok(0);
+            }
+            math::checked_div(
+                math::checked_mul(
+                    math::checked_sub(side_oi as i128, opposite_oi as i128)?,
+                    Perpetuals::BPS_POWER as i128,
+                )?,
+                total_oi,
+            )
+        };
+        let skew_before = get_skew(side_oi)?;
+        let skew_after = get_skew(math::checked_add(side_oi, size_usd)?)?;
+        math::checked_div(math::checked_add(skew_before, skew_after)?, 2)
+    }
+
+    /// Apply the open interest imbalance surcharge or discount to an entry fee
+    ///
+    /// Opens towards the heavier side pay imbalance_fee_bps scaled by the skew on
+    /// top of the fee, opens against it get the same amount off, down to zero.
+    ///
+    /// # Arguments
+    /// * `fee` - Entry fee in tokens
+    /// * `size` - Position size in tokens
+    /// * `size_usd` - Position size in USD
+    /// * `side` - Side of the position
+    /// * `custody` - Custody account of the position token
+    ///
+    /// # Returns
+    /// Entry fee with the imbalance adjustment in tokens
+    pub fn apply_imbalance_fee(
+        &self,
+        fee: u64,
+        size: u64,
+        size_usd: u64,
+        side: Side,
+        custody: &Custody,
+    ) -> Result<u64> {
+        if self.imbalance_fee_bps == 0 {
+            return Ok(fee);
+        }
+        let skew = Self::get_oi_skew(custody, side, size_usd)?;
+        let rate = math::checked_as_u64(math::checked_div(
+            math::checked_mul(self.imbalance_fee_bps as u128, skew.unsigned_abs())?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        if skew > 0 {
+            math::checked_add(fee, Self::get_fee_amount(rate, size)?)
+        } else {
+            Ok(fee.saturating_sub(conversions::apply_bps(size, rate)?))
+        }
+    }
+
     /// Calculate exit price for closing a position
     /// 
     /// Uses the minimum price (spot or EMA) for the opposite side,
@@ -265,6 +912,7 @@ impl Pool {
     /// * `token_ema_price` - EMA price from oracle
     /// * `side` - Position side being closed (Long or Short)
     /// * `custody` - Custody account for the token
+    /// * `curtime` - Current timestamp, the spread scales with the custody volatility
     /// 
     /// # Returns
     /// Exit price scaled to PRICE_DECIMALS
@@ -274,6 +922,7 @@ impl Pool {
         token_ema_price: &OraclePrice,
         side: Side,
         custody: &Custody,
+        curtime: i64,
     ) -> Result<u64> {
         let price = self.get_price(
             token_price,
@@ -283,11 +932,7 @@ impl Pool {
             } else {
                 Side::Long
             },
-            if side == Side::Long {
-                custody.pricing.trade_spread_short
-            } else {
-                custody.pricing.trade_spread_long
-            },
+            custody.get_exit_spread(side, curtime)?,
         )?;
 
         Ok(price
@@ -307,6 +952,20 @@ impl Pool {
         Self::get_fee_amount(custody.fees.close_position, size)
     }
 
+    /// Calculate anti-scalp fee for closing a position within the minimum holding period
+    ///
+    /// Charged on top of the exit fee.
+    ///
+    /// # Arguments
+    /// * `size` - Position size in tokens
+    /// * `custody` - Custody account for the position token
+    ///
+    /// # Returns
+    /// Early close fee amount in tokens
+    pub fn get_early_close_fee(&self, size: u64, custody: &Custody) -> Result<u64> {
+        Self::get_fee_amount(custody.fees.early_close, size)
+    }
+
     /// Calculate close amount and PnL for closing a position
     /// 
     /// Returns the amount of collateral to return, fees, profit, and loss.
@@ -362,8 +1021,11 @@ impl Pool {
         } else {
             collateral_token_ema_price
         };
-        let close_amount = max_collateral_price
-            .get_token_amount(available_amount_usd, collateral_custody.decimals)?;
+        let close_amount = max_collateral_price.get_token_amount(
+            available_amount_usd,
+            collateral_custody.decimals,
+            RoundingDirection::Down,
+        )?;
         let max_amount = math::checked_add(
             position.locked_amount.saturating_sub(fee_amount),
             position.collateral_amount,
@@ -377,6 +1039,258 @@ impl Pool {
         ))
     }
 
+    /// Calculate the settlement of a position rolled into a new position
+    ///
+    /// The position is settled like a close, charging the roll fee instead of the close
+    /// fee, plus the early close fee within the minimum holding period. Expired positions
+    /// can't be rolled, they are settled with settle_expired_position.
+    ///
+    /// # Arguments
+    /// * `position` - Position being rolled
+    /// * `token_price` - Current spot price for the position token
+    /// * `token_ema_price` - EMA price for the position token
+    /// * `custody` - Custody account for the position token
+    /// * `collateral_token_price` - Current spot price for collateral token
+    /// * `collateral_token_ema_price` - EMA price for collateral token
+    /// * `collateral_custody` - Custody account for collateral token
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// Tuple of (settled_amount, fee_amount, roll_fee_usd, early_close_fee_usd, profit_usd,
+    /// loss_usd), fee_amount is the total fee in collateral tokens
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_roll_amount(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<(u64, u64, u64, u64, u64, u64)> {
+        require!(
+            !custody.is_expired(curtime),
+            PerpetualsError::CustodyExpired
+        );
+
+        // Settle the position, charging the roll fee in place of the close fee
+        let mut roll_custody = custody.clone();
+        roll_custody.fees.close_position = custody.fees.roll_position;
+        let (mut settled_amount, fee_amount, profit_usd, loss_usd) = self.get_close_amount(
+            position,
+            token_price,
+            token_ema_price,
+            &roll_custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+
+        // Convert fee to collateral token
+        let roll_fee_usd = token_ema_price.get_asset_amount_usd(
+            fee_amount,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
+        let mut fee_amount = collateral_token_ema_price.get_token_amount(
+            roll_fee_usd,
+            collateral_custody.decimals,
+            RoundingDirection::Up,
+        )?;
+
+        // Charge anti-scalp fee on positions rolled within the minimum holding period
+        let mut early_close_fee_usd = 0;
+        if custody.is_within_holding_period(position.open_time, curtime) {
+            let size = token_ema_price.get_token_amount(
+                position.size_usd,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?;
+            early_close_fee_usd = token_ema_price.get_asset_amount_usd(
+                self.get_early_close_fee(size, custody)?,
+                custody.decimals,
+                RoundingDirection::Up,
+            )?;
+            let early_close_fee = std::cmp::min(
+                collateral_token_ema_price.get_token_amount(
+                    early_close_fee_usd,
+                    collateral_custody.decimals,
+                    RoundingDirection::Up,
+                )?,
+                settled_amount,
+            );
+            msg!("Early close fee: {}", early_close_fee);
+            settled_amount = math::checked_sub(settled_amount, early_close_fee)?;
+            fee_amount = math::checked_add(fee_amount, early_close_fee)?;
+        }
+        require!(settled_amount > 0, PerpetualsError::InsufficientAmountReturned);
+
+        Ok((
+            settled_amount,
+            fee_amount,
+            roll_fee_usd,
+            early_close_fee_usd,
+            profit_usd,
+            loss_usd,
+        ))
+    }
+
+    /// Settle a position in the custody accounts and notify the pool position hook
+    ///
+    /// Moves the settled PnL between collateral and pool-owned assets, collects the
+    /// protocol fee, runs the PnL through the backstop, updates the trade statistics and
+    /// removes the position from custody tracking. Shared by closes, settlements of
+    /// expired and force-settled positions, rolls and power changes. Funds locked for the
+    /// position must be unlocked and the payout transferred or queued beforehand.
+    ///
+    /// # Arguments
+    /// * `position` - Settled position, as it was before any re-entry
+    /// * `custody` - Custody account for the position token
+    /// * `collateral_custody` - Custody account for collateral token
+    /// * `settlement` - Settled amounts
+    /// * `hook_program` - Position hook program, if passed by the caller
+    /// * `hook_authority` - Hook authority PDA, if passed by the caller
+    /// * `hook_authority_bump` - Bump seed of the hook authority PDA
+    /// * `hook_payload` - Close event reported to the hook, its time is the current time
+    #[allow(clippy::too_many_arguments)]
+    pub fn settle_position<'info>(
+        &mut self,
+        position: &Position,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+        settlement: &PositionSettlement,
+        hook_program: Option<&AccountInfo<'info>>,
+        hook_authority: Option<&AccountInfo<'info>>,
+        hook_authority_bump: Option<u8>,
+        hook_payload: &PositionHookPayload,
+    ) -> Result<()> {
+        let curtime = hook_payload.time;
+
+        // Track collected fees
+        collateral_custody.collected_fees.close_position_usd = math::checked_add(
+            collateral_custody.collected_fees.close_position_usd,
+            settlement.close_fee_usd as u128,
+        )?;
+        collateral_custody.collected_fees.open_position_usd = math::checked_add(
+            collateral_custody.collected_fees.open_position_usd,
+            settlement.open_fee_usd as u128,
+        )?;
+
+        // Adjust owned assets based on PnL, profits are paid out of the pnl reserve first
+        if settlement.amount > position.collateral_amount {
+            let amount_lost = settlement.amount.saturating_sub(position.collateral_amount);
+            // Paid out profits are checked with the transfer, carried over ones here
+            if settlement.reentered {
+                require!(
+                    self.check_available_amount(amount_lost, collateral_custody)?,
+                    PerpetualsError::CustodyAmountLimit
+                );
+            }
+            let principal_amount = collateral_custody.pay_profit(amount_lost)?;
+            msg!("Profit paid out of LP principal: {}", principal_amount);
+        } else {
+            let amount_gained = position.collateral_amount.saturating_sub(settlement.amount);
+            collateral_custody.assets.owned =
+                math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+        }
+        collateral_custody.add_pnl_reserve(settlement.interest_amount)?;
+
+        // Remove the position collateral, a re-entered position keeps the settled amount
+        collateral_custody.assets.collateral = math::checked_sub(
+            collateral_custody.assets.collateral,
+            position.collateral_amount,
+        )?;
+        if settlement.reentered {
+            collateral_custody.assets.collateral =
+                math::checked_add(collateral_custody.assets.collateral, settlement.amount)?;
+        }
+
+        // Keep a queued payout in owned assets until the claim is executed
+        if settlement.queued {
+            collateral_custody.assets.owned =
+                math::checked_add(collateral_custody.assets.owned, settlement.amount)?;
+        }
+
+        // Pay protocol_fee from custody if possible, otherwise no protocol_fee
+        let protocol_share = custody.fees.get_protocol_share(FeeType::Position);
+        let protocol_fee = Self::get_fee_amount(protocol_share, settlement.fee_amount)?;
+        if self.check_available_amount(protocol_fee, collateral_custody)? {
+            collateral_custody.assets.protocol_fees =
+                math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
+            collateral_custody.assets.owned =
+                math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
+        }
+
+        // Run the realized trader PnL through the backstop waterfall, trader losses
+        // include the fees
+        let protocol_fee_usd = Self::get_fee_amount(protocol_share, settlement.fee_usd)?;
+        self.record_backstop_pnl(
+            settlement.loss_usd.saturating_sub(protocol_fee_usd),
+            settlement.profit_usd,
+        )?;
+
+        // Update trade statistics, long positions of non-virtual custodies are tracked
+        // in the collateral custody
+        let collateral_tracked = position.side == Side::Long && !custody.is_virtual;
+        let stats_custody = if collateral_tracked {
+            &mut *collateral_custody
+        } else {
+            &mut *custody
+        };
+        stats_custody.volume_stats.close_position_usd = math::checked_add(
+            stats_custody.volume_stats.close_position_usd,
+            position.size_usd as u128,
+        )?;
+        if position.side == Side::Long {
+            stats_custody.trade_stats.oi_long_usd = stats_custody
+                .trade_stats
+                .oi_long_usd
+                .saturating_sub(position.size_usd);
+        } else {
+            stats_custody.trade_stats.oi_short_usd = stats_custody
+                .trade_stats
+                .oi_short_usd
+                .saturating_sub(position.size_usd);
+        }
+        stats_custody.trade_stats.profit_usd = stats_custody
+            .trade_stats
+            .profit_usd
+            .wrapping_add(settlement.profit_usd);
+        stats_custody.trade_stats.loss_usd = stats_custody
+            .trade_stats
+            .loss_usd
+            .wrapping_add(settlement.loss_usd);
+
+        // Remove position from custody tracking
+        if collateral_tracked {
+            collateral_custody.remove_position(position, curtime, None)?;
+            collateral_custody.update_borrow_rate(curtime)?;
+            // Sync custody account data
+            *custody = collateral_custody.clone();
+        } else {
+            custody.remove_position(position, curtime, Some(collateral_custody))?;
+            collateral_custody.update_borrow_rate(curtime)?;
+        }
+
+        // A market winding down is settled once its last position is closed
+        custody.update_lifecycle();
+        collateral_custody.update_lifecycle();
+
+        // Notify the pool position hook
+        self.position_hook.invoke(
+            hook_program,
+            hook_authority,
+            hook_authority_bump,
+            hook_payload,
+        )?;
+
+        Ok(())
+    }
+
     /// Calculate swap price between two tokens
     /// 
     /// Uses minimum input price and maximum output price, then applies swap spread.
@@ -388,6 +1302,7 @@ impl Pool {
     /// * `token_out_price` - Spot price for output token
     /// * `token_out_ema_price` - EMA price for output token
     /// * `custody_in` - Custody account for input token
+    /// * `curtime` - Current timestamp, the spread scales with the custody volatility
     /// 
     /// # Returns
     /// Swap price as OraclePrice (output tokens per input token)
@@ -398,6 +1313,7 @@ impl Pool {
         token_out_price: &OraclePrice,
         token_out_ema_price: &OraclePrice,
         custody_in: &Custody,
+        curtime: i64,
     ) -> Result<OraclePrice> {
         let min_price = if token_in_price < token_in_ema_price {
             token_in_price
@@ -417,7 +1333,7 @@ impl Pool {
             &pair_price,
             &pair_price,
             Side::Short,
-            custody_in.pricing.swap_spread,
+            custody_in.get_swap_spread(curtime)?,
         )
     }
 
@@ -431,6 +1347,7 @@ impl Pool {
     /// * `custody_in` - Custody account for input token
     /// * `custody_out` - Custody account for output token
     /// * `amount_in` - Input amount in input token's native decimals
+    /// * `curtime` - Current timestamp, the spread scales with the custody volatility
     /// 
     /// # Returns
     /// Output amount in output token's native decimals
@@ -444,6 +1361,7 @@ impl Pool {
         custody_in: &Custody,
         custody_out: &Custody,
         amount_in: u64,
+        curtime: i64,
     ) -> Result<u64> {
         let swap_price = self.get_swap_price(
             token_in_price,
@@ -451,6 +1369,7 @@ impl Pool {
             token_out_price,
             token_out_ema_price,
             custody_in,
+            curtime,
         )?;
 
         math::checked_decimal_mul(
@@ -587,12 +1506,76 @@ impl Pool {
         Self::get_fee_amount(custody.fees.liquidation, size)
     }
 
+    /// Calculate the liquidation reward rate of a position
+    ///
+    /// The reward starts at a share of the full rate when the liquidation auction of the
+    /// position starts and ramps up linearly slot by slot, until it reaches the full rate
+    /// after the auction duration. A liquidation without a running auction pays the start
+    /// rate.
+    ///
+    /// # Arguments
+    /// * `position` - Position being liquidated
+    /// * `custody` - Custody account for the position token
+    /// * `slot` - Current slot
+    ///
+    /// # Returns
+    /// Liquidation reward rate (in BPS)
+    pub fn get_liquidation_reward_rate(
+        &self,
+        position: &Position,
+        custody: &Custody,
+        slot: u64,
+    ) -> Result<u64> {
+        let full_rate = custody.fees.liquidation;
+        if self.liquidation_auction_slots == 0 {
+            return Ok(full_rate);
+        }
+
+        let start_rate = math::checked_as_u64(math::checked_div(
+            math::checked_mul(full_rate as u128, self.liquidation_auction_start_share as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        let elapsed_slots = if self.is_liquidation_auction_running(position, slot) {
+            slot.saturating_sub(position.liquidation_auction_slot)
+        } else {
+            0
+        };
+        if elapsed_slots >= self.liquidation_auction_slots {
+            return Ok(full_rate);
+        }
+
+        math::checked_add(
+            start_rate,
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(
+                    math::checked_sub(full_rate, start_rate)? as u128,
+                    elapsed_slots as u128,
+                )?,
+                self.liquidation_auction_slots as u128,
+            )?)?,
+        )
+    }
+
+    /// Check whether the liquidation auction of a position is running
+    ///
+    /// An auction runs from its start slot until it expires after
+    /// LIQUIDATION_AUCTION_EXPIRY_MULT auction durations.
+    pub fn is_liquidation_auction_running(&self, position: &Position, slot: u64) -> bool {
+        position.liquidation_auction_slot != 0
+            && slot.saturating_sub(position.liquidation_auction_slot)
+                < self
+                    .liquidation_auction_slots
+                    .saturating_mul(Self::LIQUIDATION_AUCTION_EXPIRY_MULT)
+    }
+
     /// Check if a liquidity operation maintains valid token ratio
     /// 
     /// Allows operations that improve ratio even if they temporarily go outside bounds,
-    /// as long as the new ratio is better than current ratio.
+    /// as long as the new ratio is better than current ratio. Flows exempt from the
+    /// ratio bounds always pass, see `RatioFlow`.
     /// 
     /// # Arguments
+    /// * `flow` - Pool flow the check is made for
     /// * `token_id` - Token ID being modified
     /// * `amount_add` - Amount being added (0 if removing)
     /// * `amount_remove` - Amount being removed (0 if adding)
@@ -603,12 +1586,17 @@ impl Pool {
     /// true if ratio constraints are satisfied
     pub fn check_token_ratio(
         &self,
+        flow: RatioFlow,
         token_id: usize,
         amount_add: u64,
         amount_remove: u64,
         custody: &Custody,
         token_price: &OraclePrice,
     ) -> Result<bool> {
+        if flow.is_ratio_exempt() {
+            return Ok(true);
+        }
+
         let new_ratio = self.get_new_ratio(amount_add, amount_remove, custody, token_price)?;
 
         if new_ratio < self.ratios[token_id].min {
@@ -631,10 +1619,12 @@ impl Pool {
     /// # Returns
     /// true if amount is available
     pub fn check_available_amount(&self, amount: u64, custody: &Custody) -> Result<bool> {
+        // queued claims are served first
         let available_amount = math::checked_sub(
             math::checked_add(custody.assets.owned, custody.assets.collateral)?,
             custody.assets.locked,
-        )?;
+        )?
+        .saturating_sub(custody.claim_queue.pending_amount);
         Ok(available_amount >= amount)
     }
 
@@ -727,6 +1717,45 @@ impl Pool {
         curtime: i64,
         initial: bool,
     ) -> Result<bool> {
+        Ok(self
+            .get_leverage_error(
+                position,
+                token_price,
+                token_ema_price,
+                custody,
+                collateral_token_price,
+                collateral_token_ema_price,
+                collateral_custody,
+                curtime,
+                initial,
+            )?
+            .is_none())
+    }
+
+    /// Find which leverage constraint a position violates, if any
+    ///
+    /// The computed leverage and the violated limit are logged in BPS so clients can
+    /// derive how much collateral needs to be added or removed.
+    ///
+    /// # Arguments
+    /// Same as `check_leverage`
+    ///
+    /// # Returns
+    /// `MinInitialLeverage`, `MaxInitialLeverage` or `MaxLeverage` error for the first
+    /// violated constraint, or None if leverage is within allowed limits
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_leverage_error(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+        initial: bool,
+    ) -> Result<Option<PerpetualsError>> {
         let current_leverage = self.get_leverage(
             position,
             token_price,
@@ -737,15 +1766,145 @@ impl Pool {
             collateral_custody,
             curtime,
         )?;
+        let (power_max_initial_leverage, power_max_leverage) =
+            Self::get_power_leverage_limits(position.power, custody);
+
+        if current_leverage > power_max_leverage {
+            msg!(
+                "Leverage {} BPS exceeds max_leverage {} BPS",
+                current_leverage,
+                power_max_leverage
+            );
+            return Ok(Some(PerpetualsError::MaxLeverage));
+        }
+        if initial {
+            if current_leverage < custody.pricing.min_initial_leverage {
+                msg!(
+                    "Leverage {} BPS is below min_initial_leverage {} BPS",
+                    current_leverage,
+                    custody.pricing.min_initial_leverage
+                );
+                return Ok(Some(PerpetualsError::MinInitialLeverage));
+            }
+            if current_leverage > power_max_initial_leverage {
+                msg!(
+                    "Leverage {} BPS exceeds max_initial_leverage {} BPS",
+                    current_leverage,
+                    power_max_initial_leverage
+                );
+                return Ok(Some(PerpetualsError::MaxInitialLeverage));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Check whether a position can be liquidated
+    ///
+    /// Positions are liquidatable above the maintenance leverage, which can be set above
+    /// max leverage so a position opened or left at max leverage is not liquidated
+    /// right away. With `LiquidationPriceMode::SpotAndEma` both spot and EMA prices must
+    /// breach maintenance leverage, so a spot price gap that the EMA does not confirm cannot be used to
+    /// liquidate a position that would be healthy again within the same slot.
+    /// The health factor and risk tier are derived from the same leverage leg.
+    ///
+    /// # Arguments
+    /// * `position` - Position to check
+    /// * `token_price` - Current spot price for position token
+    /// * `token_ema_price` - EMA price for position token
+    /// * `custody` - Custody account for position token
+    /// * `collateral_token_price` - Current spot price for collateral
+    /// * `collateral_token_ema_price` - EMA price for collateral
+    /// * `collateral_custody` - Custody account for collateral
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// `LiquidationCheck` with leverages for each price leg and the decision
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_liquidation_check(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<LiquidationCheck> {
+        let leverage = self.get_leverage(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?;
+        let spot_leverage = self.get_leverage(
+            position,
+            token_price,
+            token_price,
+            custody,
+            collateral_token_price,
+            collateral_token_price,
+            collateral_custody,
+            curtime,
+        )?;
+        let ema_leverage = self.get_leverage(
+            position,
+            token_ema_price,
+            token_ema_price,
+            custody,
+            collateral_token_ema_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?;
+        let max_leverage = Self::get_power_maintenance_leverage(position.power, custody)?;
+
+        // the leverage that has to breach maintenance leverage for the position to be
+        // liquidatable
+        let check_leverage = match custody.pricing.liquidation_price_mode {
+            LiquidationPriceMode::Aggregate => leverage,
+            LiquidationPriceMode::SpotAndEma => std::cmp::min(spot_leverage, ema_leverage),
+        };
+        let liquidatable = check_leverage > max_leverage;
+
+        let health_factor = if check_leverage > 0 {
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(max_leverage as u128, Perpetuals::BPS_POWER)?,
+                check_leverage as u128,
+            )?)?
+        } else {
+            u64::MAX
+        };
+
+        Ok(LiquidationCheck {
+            leverage,
+            spot_leverage,
+            ema_leverage,
+            max_leverage,
+            liquidatable,
+            health_factor,
+            risk_tier: custody.get_risk_tier(health_factor, liquidatable),
+        })
+    }
 
-        // Apply power-based leverage limits
-        // Higher power = more volatile = lower max leverage
-        // power=1: standard leverage limits
-        // power=2: max 20x initial, max 40x leverage
-        // power=3: max 10x initial, max 20x leverage
-        // power=4: max 5x initial, max 10x leverage
-        // power=5: max 3x initial, max 6x leverage
-        let power_max_initial_leverage = match position.power {
+    /// Get initial and maintenance leverage limits adjusted for position power
+    ///
+    /// Higher power = more volatile = lower max leverage:
+    /// power=1: standard leverage limits
+    /// power=2: max 20x initial, max 40x leverage
+    /// power=3: max 10x initial, max 20x leverage
+    /// power=4: max 5x initial, max 10x leverage
+    /// power=5: max 3x initial, max 6x leverage
+    ///
+    /// # Returns
+    /// `(max_initial_leverage, max_leverage)` in BPS
+    pub fn get_power_leverage_limits(power: u8, custody: &Custody) -> (u64, u64) {
+        let power_max_initial_leverage = match power {
             1 => custody.pricing.max_initial_leverage,
             2 => std::cmp::min(custody.pricing.max_initial_leverage, 20_0000), // 20x in BPS
             3 => std::cmp::min(custody.pricing.max_initial_leverage, 10_0000), // 10x in BPS
@@ -754,7 +1913,7 @@ impl Pool {
             _ => custody.pricing.max_initial_leverage,
         };
 
-        let power_max_leverage = match position.power {
+        let power_max_leverage = match power {
             1 => custody.pricing.max_leverage,
             2 => std::cmp::min(custody.pricing.max_leverage, 40_0000), // 40x in BPS
             3 => std::cmp::min(custody.pricing.max_leverage, 20_0000), // 20x in BPS
@@ -763,10 +1922,29 @@ impl Pool {
             _ => custody.pricing.max_leverage,
         };
 
-        Ok(current_leverage <= power_max_leverage
-            && (!initial
-                || (current_leverage >= custody.pricing.min_initial_leverage
-                    && current_leverage <= power_max_initial_leverage)))
+        (power_max_initial_leverage, power_max_leverage)
+    }
+
+    /// Get the maintenance leverage adjusted for position power
+    ///
+    /// The power-adjusted max leverage is scaled by maintenance_leverage / max_leverage,
+    /// so every power keeps the same relative buffer. Without a maintenance leverage
+    /// this is the power-adjusted max leverage.
+    ///
+    /// # Returns
+    /// Leverage in BPS above which the position can be liquidated
+    pub fn get_power_maintenance_leverage(power: u8, custody: &Custody) -> Result<u64> {
+        let (_, power_max_leverage) = Self::get_power_leverage_limits(power, custody);
+        if custody.pricing.maintenance_leverage == 0 || custody.pricing.max_leverage == 0 {
+            return Ok(power_max_leverage);
+        }
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(
+                power_max_leverage as u128,
+                custody.pricing.maintenance_leverage as u128,
+            )?,
+            custody.pricing.max_leverage as u128,
+        )?)
     }
 
     /// Calculate liquidation price for a position
@@ -776,6 +1954,10 @@ impl Pool {
     /// 
     /// Formula:
     /// liq_price = pos_price ± (margin - size/max_leverage - exit_fee - interest) * pos_price / size
+    ///
+    /// max_leverage is the maintenance leverage (see get_power_maintenance_leverage).
+    /// For power positions it is the power-adjusted limit and the price move
+    /// follows the power payoff (see get_power_liquidation_price).
     /// 
     /// # Arguments
     /// * `position` - Position to calculate liquidation price for
@@ -785,7 +1967,7 @@ impl Pool {
     /// * `curtime` - Current timestamp
     /// 
     /// # Returns
-    /// Liquidation price scaled to PRICE_DECIMALS (0 if already liquidated)
+    /// Liquidation price scaled to PRICE_DECIMALS (0 if no price triggers liquidation)
     pub fn get_liquidation_price(
         &self,
         position: &Position,
@@ -799,25 +1981,37 @@ impl Pool {
             return Ok(0);
         }
 
-        let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
+        let size = token_ema_price.get_token_amount(
+            position.size_usd,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
         let exit_fee_tokens = self.get_exit_fee(size, custody)?;
-        let exit_fee_usd =
-            token_ema_price.get_asset_amount_usd(exit_fee_tokens, custody.decimals)?;
+        let exit_fee_usd = token_ema_price.get_asset_amount_usd(
+            exit_fee_tokens,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
         let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
         let unrealized_loss_usd = math::checked_add(
             math::checked_add(exit_fee_usd, interest_usd)?,
             position.unrealized_loss_usd,
         )?;
 
+        let max_leverage = Self::get_power_maintenance_leverage(position.power, custody)?;
         let max_loss_usd = math::checked_as_u64(math::checked_div(
             math::checked_mul(position.size_usd as u128, Perpetuals::BPS_POWER)?,
-            custody.pricing.max_leverage as u128,
+            max_leverage as u128,
         )?)?;
         let max_loss_usd = math::checked_add(max_loss_usd, unrealized_loss_usd)?;
 
         let margin_usd =
             math::checked_add(position.collateral_usd, position.unrealized_profit_usd)?;
 
+        if position.power > 1 {
+            return Self::get_power_liquidation_price(position, margin_usd, max_loss_usd);
+        }
+
         let max_price_diff = if max_loss_usd >= margin_usd {
             math::checked_sub(max_loss_usd, margin_usd)?
         } else {
@@ -860,6 +2054,41 @@ impl Pool {
         }
     }
 
+    /// Calculate liquidation price for a power position
+    ///
+    /// Solves size * (1 - ratio^power) = margin - max_loss for the price, where
+    /// ratio = price / pos_price for longs and pos_price / price for shorts.
+    ///
+    /// # Arguments
+    /// * `position` - Position to calculate liquidation price for
+    /// * `margin_usd` - Position margin including unrealized profit
+    /// * `max_loss_usd` - Loss at which the position is liquidated
+    ///
+    /// # Returns
+    /// Liquidation price scaled to PRICE_DECIMALS (0 if no price triggers liquidation)
+    fn get_power_liquidation_price(
+        position: &Position,
+        margin_usd: u64,
+        max_loss_usd: u64,
+    ) -> Result<u64> {
+        let loss_ratio = math::checked_float_div(
+            math::checked_as_f64(margin_usd)? - math::checked_as_f64(max_loss_usd)?,
+            math::checked_as_f64(position.size_usd)?,
+        )?;
+        let ratio_powered = 1.0 - loss_ratio;
+        if ratio_powered <= 0.0 {
+            return Ok(0);
+        }
+
+        let ratio = math::checked_powf(ratio_powered, 1.0 / position.power as f64)?;
+        let entry_price = math::checked_as_f64(position.price)?;
+        if position.side == Side::Long {
+            math::checked_as_u64(math::checked_float_mul(entry_price, ratio)?)
+        } else {
+            math::checked_as_u64(math::checked_float_div(entry_price, ratio)?)
+        }
+    }
+
     /// Calculate profit and loss for a position in USD
     /// 
     /// Accounts for:
@@ -881,7 +2110,7 @@ impl Pool {
     /// * `liquidation` - Whether this is a liquidation (affects fee)
     /// 
     /// # Returns
-    /// Tuple of (profit_usd, loss_usd, fee_amount)
+    /// Tuple of (profit_usd, loss_usd, fee_amount), profit capped by get_max_profit_usd
     #[allow(clippy::too_many_arguments)]
     pub fn get_pnl_usd(
         &self,
@@ -894,15 +2123,58 @@ impl Pool {
         collateral_custody: &Custody,
         curtime: i64,
         liquidation: bool,
+    ) -> Result<(u64, u64, u64)> {
+        let (profit_usd, loss_usd, fee_amount) = self.get_uncapped_pnl_usd(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_custody,
+            curtime,
+            liquidation,
+        )?;
+        if profit_usd == 0 {
+            return Ok((0, loss_usd, fee_amount));
+        }
+        let max_profit_usd = Self::get_max_profit_usd(
+            position,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?;
+        Ok((std::cmp::min(profit_usd, max_profit_usd), loss_usd, fee_amount))
+    }
+
+    /// Calculate profit and loss for a position in USD before the max payoff cap
+    ///
+    /// Same as get_pnl_usd, but the profit isn't capped at the locked funds.
+    ///
+    /// # Returns
+    /// Tuple of (profit_usd, loss_usd, fee_amount)
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_uncapped_pnl_usd(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_custody: &Custody,
+        curtime: i64,
+        liquidation: bool,
     ) -> Result<(u64, u64, u64)> {
         if position.size_usd == 0 || position.price == 0 {
             return Ok((0, 0, 0));
         }
 
         let exit_price =
-            self.get_exit_price(token_price, token_ema_price, position.side, custody)?;
+            self.get_exit_price(token_price, token_ema_price, position.side, custody, curtime)?;
 
-        let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
+        let size = token_ema_price.get_token_amount(
+            position.size_usd,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
 
         let exit_fee = if liquidation {
             self.get_liquidation_fee(size, custody)?
@@ -910,7 +2182,11 @@ impl Pool {
             self.get_exit_fee(size, custody)?
         };
 
-        let exit_fee_usd = token_ema_price.get_asset_amount_usd(exit_fee, custody.decimals)?;
+        let exit_fee_usd = token_ema_price.get_asset_amount_usd(
+            exit_fee,
+            custody.decimals,
+            RoundingDirection::Up,
+        )?;
         let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
         let unrealized_loss_usd = math::checked_add(
             math::checked_add(exit_fee_usd, interest_usd)?,
@@ -943,33 +2219,12 @@ impl Pool {
         };
 
         if price_profit_usd > 0 {
-            let potential_profit_usd = price_profit_usd;
-
             let potential_profit_usd =
-                math::checked_add(potential_profit_usd, position.unrealized_profit_usd)?;
+                math::checked_add(price_profit_usd, position.unrealized_profit_usd)?;
 
             if potential_profit_usd >= unrealized_loss_usd {
-                let cur_profit_usd = math::checked_sub(potential_profit_usd, unrealized_loss_usd)?;
-                let min_collateral_price = if collateral_custody.is_virtual {
-                    // if collateral_custody is virtual it means this function is called from get_assets_under_management_usd()
-                    // (to calculate unrealized pnl of all open positions) and actual collateral custody is a stablecoin.
-                    // we need to use 1USD reference price for such positions
-                    OraclePrice {
-                        price: 10u64.pow(Perpetuals::USD_DECIMALS as u32),
-                        exponent: -(Perpetuals::USD_DECIMALS as i32),
-                    }
-                } else {
-                    collateral_token_price
-                        .get_min_price(collateral_token_ema_price, collateral_custody.is_stable)?
-                };
-                let max_profit_usd = if curtime <= position.open_time {
-                    0
-                } else {
-                    min_collateral_price
-                        .get_asset_amount_usd(position.locked_amount, collateral_custody.decimals)?
-                };
                 Ok((
-                    std::cmp::min(max_profit_usd, cur_profit_usd),
+                    math::checked_sub(potential_profit_usd, unrealized_loss_usd)?,
                     0u64,
                     exit_fee,
                 ))
@@ -981,9 +2236,7 @@ impl Pool {
                 ))
             }
         } else {
-            let potential_loss_usd = price_loss_usd;
-
-            let potential_loss_usd = math::checked_add(potential_loss_usd, unrealized_loss_usd)?;
+            let potential_loss_usd = math::checked_add(price_loss_usd, unrealized_loss_usd)?;
 
             if potential_loss_usd >= position.unrealized_profit_usd {
                 Ok((
@@ -992,25 +2245,8 @@ impl Pool {
                     exit_fee,
                 ))
             } else {
-                let cur_profit_usd =
-                    math::checked_sub(position.unrealized_profit_usd, potential_loss_usd)?;
-                let min_collateral_price = if collateral_custody.is_virtual {
-                    OraclePrice {
-                        price: 10u64.pow(Perpetuals::USD_DECIMALS as u32),
-                        exponent: -(Perpetuals::USD_DECIMALS as i32),
-                    }
-                } else {
-                    collateral_token_price
-                        .get_min_price(collateral_token_ema_price, collateral_custody.is_stable)?
-                };
-                let max_profit_usd = if curtime <= position.open_time {
-                    0
-                } else {
-                    min_collateral_price
-                        .get_asset_amount_usd(position.locked_amount, collateral_custody.decimals)?
-                };
                 Ok((
-                    std::cmp::min(max_profit_usd, cur_profit_usd),
+                    math::checked_sub(position.unrealized_profit_usd, potential_loss_usd)?,
                     0u64,
                     exit_fee,
                 ))
@@ -1018,6 +2254,51 @@ impl Pool {
         }
     }
 
+    /// Calculate the maximum profit a position can realize in USD
+    ///
+    /// Profits are paid out of the funds locked for the position (locked_amount, sized
+    /// by pricing.max_payoff_mult at open), so profit is capped at the locked funds
+    /// valued at the min collateral price. Nothing can be realized in the opening
+    /// second.
+    ///
+    /// # Arguments
+    /// * `position` - Position to calculate the cap for
+    /// * `collateral_token_price` - Current spot price for collateral
+    /// * `collateral_token_ema_price` - EMA price for collateral
+    /// * `collateral_custody` - Custody account for collateral
+    /// * `curtime` - Current timestamp
+    ///
+    /// # Returns
+    /// Maximum profit in USD (scaled to USD_DECIMALS)
+    pub fn get_max_profit_usd(
+        position: &Position,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<u64> {
+        if curtime <= position.open_time {
+            return Ok(0);
+        }
+        let min_collateral_price = if collateral_custody.is_virtual {
+            // if collateral_custody is virtual it means this function is called from get_assets_under_management_usd()
+            // (to calculate unrealized pnl of all open positions) and actual collateral custody is a stablecoin.
+            // we need to use 1USD reference price for such positions
+            OraclePrice {
+                price: 10u64.pow(Perpetuals::USD_DECIMALS as u32),
+                exponent: -(Perpetuals::USD_DECIMALS as i32),
+            }
+        } else {
+            collateral_token_price
+                .get_min_price(collateral_token_ema_price, collateral_custody.is_stable)?
+        };
+        min_collateral_price.get_asset_amount_usd(
+            position.locked_amount,
+            collateral_custody.decimals,
+            RoundingDirection::Down,
+        )
+    }
+
     /// Calculate total Assets Under Management (AUM) in USD
     /// 
     /// Sums up all token values in the pool, optionally including unrealized PnL.
@@ -1034,101 +2315,59 @@ impl Pool {
         aum_calc_mode: AumCalcMode,
         accounts: &'a [AccountInfo<'a>],
         curtime: i64,
+    ) -> Result<u128> {
+        self.get_assets_under_management_usd_with_custody(aum_calc_mode, accounts, &[], curtime)
+    }
+
+    /// Calculate total AUM in USD, valuing the given custodies from their in-memory state
+    /// 
+    /// Instructions that modify custodies and then refresh pool AUM pass the updated
+    /// custodies here instead of writing them back to the accounts first, so the AUM can't
+    /// be computed from stale account data.
+    /// 
+    /// # Arguments
+    /// * `aum_calc_mode` - Which price to use (Min/Max/Last/EMA)
+    /// * `accounts` - Account infos array: [custody0, custody1, ..., oracle0, oracle1, ...]
+    /// * `updated_custodies` - Keys and in-memory states of custodies to use instead of
+    ///   their accounts
+    /// * `curtime` - Current timestamp
+    /// 
+    /// # Returns
+    /// Total AUM in USD (scaled to USD_DECIMALS)
+    pub fn get_assets_under_management_usd_with_custody<'a>(
+        &self,
+        aum_calc_mode: AumCalcMode,
+        accounts: &'a [AccountInfo<'a>],
+        updated_custodies: &[(Pubkey, &Custody)],
+        curtime: i64,
     ) -> Result<u128> {
         let mut pool_amount_usd: u128 = 0;
-        for (idx, &custody) in self.custodies.iter().enumerate() {
+        for (idx, &custody_key) in self.custodies.iter().enumerate() {
             let oracle_idx = idx + self.custodies.len();
             if oracle_idx >= accounts.len() {
                 return Err(PerpetualsError::UnsupportedOracle.into());
             }
 
-            require_keys_eq!(accounts[idx].key(), custody);
-            let custody = Account::<Custody>::try_from(&accounts[idx])?;
-
-            require_keys_eq!(accounts[oracle_idx].key(), custody.oracle.oracle_account);
-
-            let token_price = OraclePrice::new_from_oracle(
-                &accounts[oracle_idx],
-                &custody.oracle,
-                curtime,
-                false,
-            )?;
-
-            let token_ema_price = OraclePrice::new_from_oracle(
-                &accounts[oracle_idx],
-                &custody.oracle,
-                curtime,
-                custody.pricing.use_ema,
-            )?;
-
-            let aum_token_price = match aum_calc_mode {
-                AumCalcMode::Last => token_price,
-                AumCalcMode::EMA => token_ema_price,
-                AumCalcMode::Min => {
-                    if token_price < token_ema_price {
-                        token_price
-                    } else {
-                        token_ema_price
-                    }
-                }
-                AumCalcMode::Max => {
-                    if token_price > token_ema_price {
-                        token_price
-                    } else {
-                        token_ema_price
-                    }
-                }
-            };
-
-            let token_amount_usd =
-                aum_token_price.get_asset_amount_usd(custody.assets.owned, custody.decimals)?;
-
-            pool_amount_usd = math::checked_add(pool_amount_usd, token_amount_usd as u128)?;
-
-            if custody.pricing.use_unrealized_pnl_in_aum {
-                if custody.is_stable {
-                    // compute accumulated interest
-                    let collective_position = custody.get_collective_position(Side::Long)?;
-                    let interest_usd =
-                        custody.get_interest_amount_usd(&collective_position, curtime)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
-
-                    let collective_position = custody.get_collective_position(Side::Short)?;
-                    let interest_usd =
-                        custody.get_interest_amount_usd(&collective_position, curtime)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
-                } else {
-                    // compute aggregate unrealized pnl
-                    let (long_profit, long_loss, _) = self.get_pnl_usd(
-                        &custody.get_collective_position(Side::Long)?,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        curtime,
-                        false,
-                    )?;
-                    let (short_profit, short_loss, _) = self.get_pnl_usd(
-                        &custody.get_collective_position(Side::Short)?,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        &token_price,
-                        &token_ema_price,
+            require_keys_eq!(accounts[idx].key(), custody_key);
+            pool_amount_usd = match updated_custodies.iter().find(|(key, _)| *key == custody_key) {
+                Some((_, custody)) => self.add_custody_amount_usd(
+                    pool_amount_usd,
+                    custody,
+                    &accounts[oracle_idx],
+                    aum_calc_mode,
+                    curtime,
+                )?,
+                _ => {
+                    let custody = Account::<Custody>::try_from(&accounts[idx])?;
+                    self.add_custody_amount_usd(
+                        pool_amount_usd,
                         &custody,
+                        &accounts[oracle_idx],
+                        aum_calc_mode,
                         curtime,
-                        false,
-                    )?;
-
-                    // adjust pool amount by collective profit/loss
-                    pool_amount_usd = math::checked_add(pool_amount_usd, long_loss as u128)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, short_loss as u128)?;
-                    pool_amount_usd = pool_amount_usd.saturating_sub(long_profit as u128);
-                    pool_amount_usd = pool_amount_usd.saturating_sub(short_profit as u128);
+                    )?
                 }
-            }
+            };
         }
 
         Ok(pool_amount_usd)
@@ -1145,16 +2384,138 @@ impl Pool {
     /// # Returns
     /// Fee amount (0 if fee or amount is 0)
     pub fn get_fee_amount(fee: u64, amount: u64) -> Result<u64> {
-        if fee == 0 || amount == 0 {
-            return Ok(0);
+        conversions::apply_bps_ceil(amount, fee)
+    }
+
+    // ========== Private Helper Functions ==========
+
+    /// Add the value of a single custody in USD to an AUM accumulator
+    ///
+    /// Unrealized profits are subtracted from the running pool total (saturating),
+    /// as traders' profits are paid out of any pool asset.
+    fn add_custody_amount_usd(
+        &self,
+        pool_amount_usd: u128,
+        custody: &Custody,
+        oracle_account: &AccountInfo,
+        aum_calc_mode: AumCalcMode,
+        curtime: i64,
+    ) -> Result<u128> {
+        require_keys_eq!(
+            oracle_account.key(),
+            custody.oracle.oracle_account,
+            PerpetualsError::InvalidOracleAccount
+        );
+
+        // custodies without assets or open interest add nothing, skip the oracle reads
+        if custody.is_empty_for_aum() {
+            return Ok(pool_amount_usd);
+        }
+
+        let mut pool_amount_usd = pool_amount_usd;
+
+        let token_price = custody.get_oracle_price(
+            oracle_account,
+            curtime,
+            false,
+            OracleOperation::Liquidity,
+        )?;
+
+        let token_ema_price = custody.get_oracle_price(
+            oracle_account,
+            curtime,
+            custody.pricing.use_ema,
+            OracleOperation::Liquidity,
+        )?;
+
+        // Value liquid staking tokens at their fair value in underlying terms
+        let token_price = custody.get_fair_price(&token_price, curtime)?;
+        let token_ema_price = custody.get_fair_price(&token_ema_price, curtime)?;
+
+        let aum_token_price = match aum_calc_mode {
+            AumCalcMode::Last => token_price,
+            AumCalcMode::EMA => token_ema_price,
+            AumCalcMode::Min => {
+                if token_price < token_ema_price {
+                    token_price
+                } else {
+                    token_ema_price
+                }
+            }
+            AumCalcMode::Max => {
+                if token_price > token_ema_price {
+                    token_price
+                } else {
+                    token_ema_price
+                }
+            }
+        };
+
+        // tokens of queued claims are owed to their claimants, the AUM rounds the same
+        // way as the price mode (up for deposits, down for withdrawals)
+        let rounding = if aum_calc_mode == AumCalcMode::Max {
+            RoundingDirection::Up
+        } else {
+            RoundingDirection::Down
+        };
+        let token_amount_usd = aum_token_price.get_asset_amount_usd(
+            custody
+                .assets
+                .owned
+                .saturating_sub(custody.claim_queue.pending_amount),
+            custody.decimals,
+            rounding,
+        )?;
+
+        pool_amount_usd = math::checked_add(pool_amount_usd, token_amount_usd as u128)?;
+
+        if custody.pricing.use_unrealized_pnl_in_aum {
+            if custody.is_stable {
+                // compute accumulated interest
+                let collective_position = custody.get_collective_position(Side::Long)?;
+                let interest_usd =
+                    custody.get_interest_amount_usd(&collective_position, curtime)?;
+                pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
+
+                let collective_position = custody.get_collective_position(Side::Short)?;
+                let interest_usd =
+                    custody.get_interest_amount_usd(&collective_position, curtime)?;
+                pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
+            } else {
+                // compute aggregate unrealized pnl
+                let (long_profit, long_loss, _) = self.get_pnl_usd(
+                    &custody.get_collective_position(Side::Long)?,
+                    &token_price,
+                    &token_ema_price,
+                    custody,
+                    &token_price,
+                    &token_ema_price,
+                    custody,
+                    curtime,
+                    false,
+                )?;
+                let (short_profit, short_loss, _) = self.get_pnl_usd(
+                    &custody.get_collective_position(Side::Short)?,
+                    &token_price,
+                    &token_ema_price,
+                    custody,
+                    &token_price,
+                    &token_ema_price,
+                    custody,
+                    curtime,
+                    false,
+                )?;
+
+                // adjust pool amount by collective profit/loss
+                pool_amount_usd = math::checked_add(pool_amount_usd, long_loss as u128)?;
+                pool_amount_usd = math::checked_add(pool_amount_usd, short_loss as u128)?;
+                pool_amount_usd = pool_amount_usd.saturating_sub(long_profit as u128);
+                pool_amount_usd = pool_amount_usd.saturating_sub(short_profit as u128);
+            }
         }
-        math::checked_as_u64(math::checked_ceil_div(
-            math::checked_mul(amount as u128, fee as u128)?,
-            Perpetuals::BPS_POWER,
-        )?)
-    }
 
-    // ========== Private Helper Functions ==========
+        Ok(pool_amount_usd)
+    }
     
     /// Get current token ratio in the pool
     /// 
@@ -1164,13 +2525,17 @@ impl Pool {
     /// 
     /// # Returns
     /// Current ratio in BPS (0 if AUM is 0 or token is virtual)
-    fn get_current_ratio(&self, custody: &Custody, token_price: &OraclePrice) -> Result<u64> {
+    pub fn get_current_ratio(&self, custody: &Custody, token_price: &OraclePrice) -> Result<u64> {
         if self.aum_usd == 0 || custody.is_virtual {
             return Ok(0);
         }
         let ratio = math::checked_as_u64(math::checked_div(
             math::checked_mul(
-                token_price.get_asset_amount_usd(custody.assets.owned, custody.decimals)? as u128,
+                token_price.get_asset_amount_usd(
+                    custody.assets.owned,
+                    custody.decimals,
+                    RoundingDirection::Down,
+                )? as u128,
                 Perpetuals::BPS_POWER,
             )?,
             self.aum_usd,
@@ -1188,7 +2553,7 @@ impl Pool {
     /// 
     /// # Returns
     /// New ratio in BPS (0 if pool would be empty or token is virtual)
-    fn get_new_ratio(
+    pub fn get_new_ratio(
         &self,
         amount_add: u64,
         amount_remove: u64,
@@ -1202,23 +2567,34 @@ impl Pool {
             return Err(PerpetualsError::InvalidPositionState.into());
         } else if amount_add == 0 && amount_remove == 0 {
             (
-                token_price.get_asset_amount_usd(custody.assets.owned, custody.decimals)? as u128,
+                token_price.get_asset_amount_usd(
+                    custody.assets.owned,
+                    custody.decimals,
+                    RoundingDirection::Down,
+                )? as u128,
                 self.aum_usd,
             )
         } else if amount_add > 0 {
-            let added_aum_usd =
-                token_price.get_asset_amount_usd(amount_add, custody.decimals)? as u128;
+            let added_aum_usd = token_price.get_asset_amount_usd(
+                amount_add,
+                custody.decimals,
+                RoundingDirection::Down,
+            )? as u128;
 
             (
                 token_price.get_asset_amount_usd(
                     math::checked_add(custody.assets.owned, amount_add)?,
                     custody.decimals,
+                    RoundingDirection::Down,
                 )? as u128,
                 math::checked_add(self.aum_usd, added_aum_usd)?,
             )
         } else {
-            let removed_aum_usd =
-                token_price.get_asset_amount_usd(amount_remove, custody.decimals)? as u128;
+            let removed_aum_usd = token_price.get_asset_amount_usd(
+                amount_remove,
+                custody.decimals,
+                RoundingDirection::Down,
+            )? as u128;
 
             if removed_aum_usd >= self.aum_usd || amount_remove >= custody.assets.owned {
                 (0, 0)
@@ -1227,6 +2603,7 @@ impl Pool {
                     token_price.get_asset_amount_usd(
                         math::checked_sub(custody.assets.owned, amount_remove)?,
                         custody.decimals,
+                        RoundingDirection::Down,
                     )? as u128,
                     math::checked_sub(self.aum_usd, removed_aum_usd)?,
                 )
@@ -1469,17 +2846,60 @@ impl Pool {
         custody: &Custody,
         token_price: &OraclePrice,
     ) -> Result<u64> {
-        // Fee calculations must temporarily be in i64 because of negative slope.
-        let fee_max: i64 = custody.fees.fee_max as i64;
-        let fee_optimal: i64 = custody.fees.fee_optimal as i64;
+        let post_lp_ratio =
+            self.get_new_ratio(amount_add, amount_remove, custody, token_price)?;
+        let lp_fee = Self::get_optimal_lp_fee(
+            custody.fees.fee_max,
+            custody.fees.fee_optimal,
+            &self.ratios[token_id],
+            post_lp_ratio,
+            amount_add != 0,
+        )?;
+
+        // The lp fee goes negative below the target when fee_max > 2 * fee_optimal,
+        // the discount can cancel the base fee but never turns into a rebate.
+        let fee = std::cmp::max(math::checked_add(lp_fee, base_fee as i64)?, 0);
+
+        Self::get_fee_amount(
+            math::checked_as_u64(fee)?,
+            std::cmp::max(amount_add, amount_remove),
+        )
+    }
+
+    /// Calculate the optimal mode LP fee at the post-trade token ratio
+    ///
+    /// The fee is piecewise linear in the ratio and equals fee_optimal at the target.
+    /// Additions follow the increasing line reaching fee_max at the max ratio, removals
+    /// follow the decreasing line reaching fee_max at the min ratio. On the other side of
+    /// the target the same slope keeps going, giving a discount for rebalancing trades.
+    ///
+    /// # Arguments
+    /// * `fee_max` - Fee at the edge of the allowed range (BPS)
+    /// * `fee_optimal` - Fee at the target ratio (BPS)
+    /// * `ratios` - Token ratio constraints
+    /// * `post_lp_ratio` - Token ratio after the trade (BPS)
+    /// * `is_add` - True for additions to the pool, false for removals
+    ///
+    /// # Returns
+    /// LP fee in BPS, negative if the discount exceeds fee_optimal
+    fn get_optimal_lp_fee(
+        fee_max: u64,
+        fee_optimal: u64,
+        ratios: &TokenRatios,
+        post_lp_ratio: u64,
+        is_add: bool,
+    ) -> Result<i64> {
+        // Enforced by Fees::validate, an inverted config would flip both slopes
+        require!(fee_max >= fee_optimal, PerpetualsError::InvalidCustodyConfig);
 
-        let target_ratio: i64 = self.ratios[token_id].target as i64;
-        let min_ratio: i64 = self.ratios[token_id].min as i64;
-        let max_ratio: i64 = self.ratios[token_id].max as i64;
-        let post_lp_ratio: i64 =
-            self.get_new_ratio(amount_add, amount_remove, custody, token_price)? as i64;
+        // Fee calculations must temporarily be in i64 because of negative slope.
+        let fee_max: i64 = fee_max as i64;
+        let fee_optimal: i64 = fee_optimal as i64;
 
-        let base_fee: i64 = base_fee as i64;
+        let target_ratio: i64 = ratios.target as i64;
+        let min_ratio: i64 = ratios.min as i64;
+        let max_ratio: i64 = ratios.max as i64;
+        let post_lp_ratio: i64 = post_lp_ratio as i64;
 
         let slope_denominator: i64 = if post_lp_ratio > target_ratio {
             math::checked_sub(max_ratio, target_ratio)?
@@ -1487,7 +2907,7 @@ impl Pool {
             math::checked_sub(target_ratio, min_ratio)?
         };
 
-        let slope_numerator: i64 = if amount_add != 0 {
+        let slope_numerator: i64 = if is_add {
             if post_lp_ratio > max_ratio {
                 return err!(PerpetualsError::TokenRatioOutOfRange);
             }
@@ -1499,6 +2919,12 @@ impl Pool {
             fee_optimal - fee_max
         };
 
+        // Target on the edge of the range (min == target or max == target), the line on
+        // that side is degenerate and the fee stays flat.
+        if slope_denominator == 0 {
+            return Ok(fee_optimal);
+        }
+
         // Delay applying slope_denominator until the very end to avoid losing precision.
         // b = fee_optimal - target_ratio * slope
         // lp_fee = slope * post_lp_ratio + b
@@ -1506,14 +2932,9 @@ impl Pool {
             math::checked_mul(fee_optimal, slope_denominator)?,
             math::checked_mul(target_ratio, slope_numerator)?,
         )?;
-        let lp_fee: i64 = math::checked_div(
+        math::checked_div(
             math::checked_add(math::checked_mul(slope_numerator, post_lp_ratio)?, b)?,
             slope_denominator,
-        )?;
-
-        Self::get_fee_amount(
-            math::checked_as_u64(math::checked_add(lp_fee, base_fee)?)?,
-            std::cmp::max(amount_add, amount_remove),
         )
     }
 }
@@ -1523,9 +2944,10 @@ mod test {
     use {
         super::*,
         crate::state::{
-            custody::{BorrowRateParams, Fees, PricingParams},
-            oracle::{OracleParams, OracleType},
+            custody::{Fees, LiquidationPriceMode, PricingParams},
+            oracle::{CustomOracle, OracleParams, OracleType},
             perpetuals::Permissions,
+            position_hook::PositionHookEvent,
         },
     };
 
@@ -1542,6 +2964,15 @@ mod test {
             oracle_authority: Pubkey::default(),
             max_price_error: 100,
             max_price_age_sec: 1,
+            ema_half_life_sec: 0,
+            max_price_age_close_sec: 0,
+            max_price_age_liquidate_sec: 0,
+            max_price_age_liquidity_sec: 0,
+            heartbeat_mult: 0,
+            close_grace_mult: 0,
+            close_grace_spread: 0,
+            min_update_interval_sec: 0,
+            max_updates_per_slot: 0,
         };
 
         let pricing = PricingParams {
@@ -1553,10 +2984,19 @@ mod test {
             min_initial_leverage: 10_000,
             max_initial_leverage: 100_000,
             max_leverage: 100_000,
+            maintenance_leverage: 0,
             max_payoff_mult: 10_000,
             max_utilization: 0,
             max_position_locked_usd: 0,
             max_total_locked_usd: 0,
+            min_holding_period: 0,
+            reject_early_close: false,
+            liquidation_price_mode: LiquidationPriceMode::Aggregate,
+            lp_fee_decay_period: 0,
+            risk_warning_health: 0,
+            risk_danger_health: 0,
+            max_power: 0,
+            convexity_vol: 0,
         };
 
         let permissions = Permissions {
@@ -1586,6 +3026,13 @@ mod test {
             protocol_share: 25,
             fee_max: 0,
             fee_optimal: 0,
+            early_close: 0,
+            roll_position: 0,
+            early_remove_liquidity: 0,
+            swap_protocol_share: 0,
+            liquidity_protocol_share: 0,
+            position_protocol_share: 0,
+            liquidation_protocol_share: 0,
         };
 
         let custody = Custody {
@@ -1643,4 +3090,1092 @@ mod test {
         )
         .unwrap()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_legacy_pool() {
+        let legacy = PoolV0 {
+            name: "pool".to_string(),
+            custodies: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            ratios: vec![TokenRatios::default(); 2],
+            aum_usd: 1_000,
+            bump: 254,
+            lp_token_bump: 253,
+            inception_time: 100,
+        };
+        let mut data = Pool::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(PoolV0::LEN + 2 * (32 + std::mem::size_of::<TokenRatios>()), 0);
+        assert_eq!(PoolV0::load(&data), Some(legacy.clone()));
+
+        let pool = Pool::from(legacy.clone());
+        assert_eq!(pool.name, legacy.name);
+        assert_eq!(pool.custodies, legacy.custodies);
+        assert_eq!(pool.ratios, legacy.ratios);
+        assert_eq!(pool.aum_usd, 1_000);
+        assert_eq!((pool.bump, pool.lp_token_bump), (254, 253));
+        assert_eq!(pool.inception_time, 100);
+        assert_eq!(pool.performance_fee_bps, 0);
+        assert!(!pool.lp_allowlist_enabled);
+
+        // current pools are not loaded as legacy ones
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+        data.resize(Pool::LEN + 2 * (32 + std::mem::size_of::<TokenRatios>()), 0);
+        assert_eq!(PoolV0::load(&data), None);
+    }
+
+    #[test]
+    fn test_get_entry_price() {
+        let (pool, custody, _position, token_price, token_ema_price) = get_fixture();
+
+        assert_eq!(
+            scale_f64(25_553.0, Perpetuals::PRICE_DECIMALS),
+            pool.get_entry_price(&token_price, &token_ema_price, Side::Long, &custody, 0)
+                .unwrap()
+        );
+        assert_eq!(
+            scale_f64(24_750.0, Perpetuals::PRICE_DECIMALS),
+            pool.get_entry_price(&token_price, &token_ema_price, Side::Short, &custody, 0)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_entry_fee_tiers() {
+        let (pool, mut custody, ..) = get_fixture();
+        custody.entry_fee_tiers[0] = EntryFeeTier {
+            min_size_usd: scale(10_000, Perpetuals::USD_DECIMALS),
+            fee: 10,
+        };
+        custody.entry_fee_tiers[1] = EntryFeeTier {
+            min_size_usd: scale(100_000, Perpetuals::USD_DECIMALS),
+            fee: 20,
+        };
+        // no utilization adjustment, a token is worth 1 USD
+        let collateral_custody = Custody::default();
+        let get_fee = |size_usd: u64, fee_tiers: &[EntryFeeTier]| {
+            pool.get_entry_fee(10, fee_tiers, size_usd, size_usd, 0, &collateral_custody)
+                .unwrap()
+        };
+
+        // flat fee without tiers
+        assert_eq!(get_fee(scale(200_000, Perpetuals::USD_DECIMALS), &[]), 200_000_000);
+
+        // tiers apply above their threshold only
+        let tiers = &custody.entry_fee_tiers;
+        assert_eq!(get_fee(scale(10_000, Perpetuals::USD_DECIMALS), tiers), 10_000_000);
+        assert_eq!(get_fee(scale(10_001, Perpetuals::USD_DECIMALS), tiers), 10_002_000);
+        assert_eq!(get_fee(scale(100_000, Perpetuals::USD_DECIMALS), tiers), 190_000_000);
+        assert_eq!(get_fee(scale(100_001, Perpetuals::USD_DECIMALS), tiers), 190_004_000);
+        assert_eq!(get_fee(scale(200_000, Perpetuals::USD_DECIMALS), tiers), 590_000_000);
+    }
+
+    #[test]
+    fn test_imbalance_fee() {
+        let (mut pool, mut custody, ..) = get_fixture();
+        let size = 100_000;
+        assert_eq!(
+            pool.apply_imbalance_fee(1_000, size, 100, Side::Long, &custody).unwrap(),
+            1_000
+        );
+
+        // an open into an empty book moves the skew from 0 to 100%
+        pool.imbalance_fee_bps = 100;
+        assert_eq!(Pool::get_oi_skew(&custody, Side::Long, 100).unwrap(), 5_000);
+        assert_eq!(
+            pool.apply_imbalance_fee(1_000, size, 100, Side::Long, &custody).unwrap(),
+            1_500
+        );
+
+        // longs are the heavier side: skew 50% -> 60% for longs, -50% -> -20% for shorts
+        custody.trade_stats.oi_long_usd = 300;
+        custody.trade_stats.oi_short_usd = 100;
+        assert_eq!(Pool::get_oi_skew(&custody, Side::Long, 100).unwrap(), 5_500);
+        assert_eq!(Pool::get_oi_skew(&custody, Side::Short, 100).unwrap(), -3_500);
+        assert_eq!(
+            pool.apply_imbalance_fee(1_000, size, 100, Side::Long, &custody).unwrap(),
+            1_550
+        );
+        assert_eq!(
+            pool.apply_imbalance_fee(1_000, size, 100, Side::Short, &custody).unwrap(),
+            650
+        );
+
+        // the discount doesn't turn the fee into a rebate
+        assert_eq!(
+            pool.apply_imbalance_fee(100, size, 100, Side::Short, &custody).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_liquidation_reward_rate() {
+        let (mut pool, mut custody, mut position, ..) = get_fixture();
+        custody.fees.liquidation = 100;
+
+        // fixed reward without an auction
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 50).unwrap(), 100);
+
+        pool.liquidation_auction_slots = 10;
+        pool.liquidation_auction_start_share = 2_000;
+
+        // start rate if the auction wasn't started
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 50).unwrap(), 20);
+
+        // ramps up slot by slot to the full rate
+        position.liquidation_auction_slot = 40;
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 40).unwrap(), 20);
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 41).unwrap(), 28);
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 45).unwrap(), 60);
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 50).unwrap(), 100);
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 59).unwrap(), 100);
+
+        // expired auctions pay the start rate again
+        assert!(pool.is_liquidation_auction_running(&position, 59));
+        assert!(!pool.is_liquidation_auction_running(&position, 60));
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 60).unwrap(), 20);
+        assert_eq!(pool.get_liquidation_reward_rate(&position, &custody, 500).unwrap(), 20);
+    }
+
+    #[test]
+    fn test_get_liquidation_price_power() {
+        let (pool, mut custody, mut position, _, _) = get_fixture();
+        custody.pricing.trade_spread_long = 0;
+        custody.pricing.trade_spread_short = 0;
+        let token_ema_price = OraclePrice {
+            price: 25_000_000,
+            exponent: -3,
+        };
+
+        // x4 position with x10 max leverage is liquidated after a 15% loss on size
+        position.power = 1;
+        assert_eq!(
+            pool.get_liquidation_price(&position, &token_ema_price, &custody, &custody, 0)
+                .unwrap(),
+            scale(21_250, Perpetuals::PRICE_DECIMALS)
+        );
+
+        // power 2 loses 15% of size at sqrt(0.85) of the entry price
+        position.power = 2;
+        let liquidation_price = pool
+            .get_liquidation_price(&position, &token_ema_price, &custody, &custody, 0)
+            .unwrap();
+        assert_eq!(liquidation_price / 1_000_000, 23_048);
+
+        for (price, liquidatable) in [
+            (liquidation_price - 1_000_000, true),
+            (liquidation_price + 1_000_000, false),
+        ] {
+            let price = OraclePrice {
+                price,
+                exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+            };
+            assert_eq!(
+                pool.get_liquidation_check(
+                    &position, &price, &price, &custody, &price, &price, &custody, 0
+                )
+                .unwrap()
+                .liquidatable,
+                liquidatable
+            );
+        }
+
+        position.side = Side::Short;
+        let liquidation_price = pool
+            .get_liquidation_price(&position, &token_ema_price, &custody, &custody, 0)
+            .unwrap();
+        assert_eq!(liquidation_price / 1_000_000, 27_116);
+    }
+
+    #[test]
+    fn test_max_payoff_cap() {
+        let (pool, mut custody, mut position, _, _) = get_fixture();
+        custody.pricing.trade_spread_long = 0;
+        custody.pricing.trade_spread_short = 0;
+        custody.fees.close_position = 0;
+        position.power = 2;
+        position.open_time = 0;
+
+        // locked funds are the position size scaled by max_payoff_mult, shorts at most x1
+        let size = scale(4, 9);
+        assert_eq!(custody.get_locked_amount(size, Side::Long, 1, 0).unwrap(), size);
+        custody.pricing.max_payoff_mult = 20_000;
+        assert_eq!(custody.get_locked_amount(size, Side::Long, 1, 0).unwrap(), 2 * size);
+        assert_eq!(custody.get_locked_amount(size, Side::Short, 1, 0).unwrap(), size);
+        custody.pricing.max_payoff_mult = 10_000;
+
+        // power 2 long at x3 the entry price earns x8 the size, capped at the 4 locked
+        // tokens worth $75k each
+        let price = OraclePrice {
+            price: 75_000_000,
+            exponent: -3,
+        };
+        let (uncapped_profit_usd, loss_usd, _) = pool
+            .get_uncapped_pnl_usd(&position, &price, &price, &custody, &custody, 1, false)
+            .unwrap();
+        assert_eq!(uncapped_profit_usd, scale(800_000, Perpetuals::USD_DECIMALS));
+        assert_eq!(loss_usd, 0);
+        let max_profit_usd =
+            Pool::get_max_profit_usd(&position, &price, &price, &custody, 1).unwrap();
+        assert_eq!(max_profit_usd, scale(300_000, Perpetuals::USD_DECIMALS));
+        let (profit_usd, _, _) = pool
+            .get_pnl_usd(&position, &price, &price, &custody, &price, &price, &custody, 1, false)
+            .unwrap();
+        assert_eq!(profit_usd, max_profit_usd);
+
+        // below the cap the profit is paid in full
+        let price = OraclePrice {
+            price: 30_000_000,
+            exponent: -3,
+        };
+        let (uncapped_profit_usd, _, _) = pool
+            .get_uncapped_pnl_usd(&position, &price, &price, &custody, &custody, 1, false)
+            .unwrap();
+        let (profit_usd, _, _) = pool
+            .get_pnl_usd(&position, &price, &price, &custody, &price, &price, &custody, 1, false)
+            .unwrap();
+        assert_eq!(profit_usd, uncapped_profit_usd);
+        assert_eq!(profit_usd, scale(44_000, Perpetuals::USD_DECIMALS));
+
+        // nothing can be realized in the opening second
+        let (profit_usd, _, _) = pool
+            .get_pnl_usd(&position, &price, &price, &custody, &price, &price, &custody, 0, false)
+            .unwrap();
+        assert_eq!(profit_usd, 0);
+    }
+
+    #[test]
+    fn test_get_roll_amount() {
+        let (pool, mut custody, mut position, token_price, _) = get_fixture();
+        custody.pricing.trade_spread_long = 0;
+        custody.pricing.trade_spread_short = 0;
+        custody.fees.close_position = 1_000;
+        custody.fees.roll_position = 50;
+        position.power = 1;
+        position.open_time = 0;
+        let get_roll_amount =
+            |position: &Position, price: &OraclePrice, custody: &Custody, curtime| {
+                pool.get_roll_amount(position, price, price, custody, price, price, custody, curtime)
+            };
+
+        // the roll fee is charged instead of the close fee
+        let (settled_amount, fee_amount, roll_fee_usd, early_close_fee_usd, _, _) =
+            get_roll_amount(&position, &token_price, &custody, 100).unwrap();
+        let mut close_custody = custody.clone();
+        close_custody.fees.close_position = custody.fees.roll_position;
+        let (close_amount, close_fee, _, _) = pool
+            .get_close_amount(
+                &position,
+                &token_price,
+                &token_price,
+                &close_custody,
+                &token_price,
+                &token_price,
+                &close_custody,
+                100,
+                false,
+            )
+            .unwrap();
+        assert_eq!(settled_amount, close_amount);
+        assert_eq!(fee_amount, close_fee);
+        assert!(fee_amount > 0);
+        assert_eq!(
+            roll_fee_usd,
+            token_price
+                .get_asset_amount_usd(fee_amount, custody.decimals, RoundingDirection::Up)
+                .unwrap()
+        );
+        assert_eq!(early_close_fee_usd, 0);
+
+        // profits are settled on top of the collateral, losses out of it
+        let price = OraclePrice {
+            price: 30_000_000,
+            exponent: -3,
+        };
+        let (settled_amount, _, _, _, profit_usd, loss_usd) =
+            get_roll_amount(&position, &price, &custody, 100).unwrap();
+        assert!(profit_usd > 0 && loss_usd == 0);
+        assert!(settled_amount > position.collateral_amount);
+        let price = OraclePrice {
+            price: 22_000_000,
+            exponent: -3,
+        };
+        let (settled_amount, _, _, _, profit_usd, loss_usd) =
+            get_roll_amount(&position, &price, &custody, 100).unwrap();
+        assert!(profit_usd == 0 && loss_usd > 0);
+        assert!(settled_amount < position.collateral_amount);
+
+        // a loss exceeding the collateral leaves nothing to roll
+        let price = OraclePrice {
+            price: 18_000_000,
+            exponent: -3,
+        };
+        assert!(get_roll_amount(&position, &price, &custody, 100).is_err());
+
+        // rolls within the holding period pay the early close fee
+        custody.fees.early_close = 100;
+        let (settled_amount, fee_amount, roll_fee_usd, _, _, _) =
+            get_roll_amount(&position, &token_price, &custody, 59).unwrap();
+        custody.pricing.min_holding_period = 60;
+        let (early_amount, early_fee, early_roll_fee_usd, early_close_fee_usd, _, _) =
+            get_roll_amount(&position, &token_price, &custody, 59).unwrap();
+        assert_eq!(early_roll_fee_usd, roll_fee_usd);
+        assert_eq!(early_close_fee_usd, scale(1_000, Perpetuals::USD_DECIMALS));
+        let early_close_fee = token_price
+            .get_token_amount(early_close_fee_usd, custody.decimals, RoundingDirection::Up)
+            .unwrap();
+        assert_eq!(early_fee, fee_amount + early_close_fee);
+        assert_eq!(early_amount + early_close_fee, settled_amount);
+        let (_, _, _, early_close_fee_usd, _, _) =
+            get_roll_amount(&position, &token_price, &custody, 60).unwrap();
+        assert_eq!(early_close_fee_usd, 0);
+
+        // expired positions can't be rolled
+        custody.expiry_time = 100;
+        assert!(get_roll_amount(&position, &token_price, &custody, 99).is_ok());
+        assert_eq!(
+            get_roll_amount(&position, &token_price, &custody, 100),
+            Err(PerpetualsError::CustodyExpired.into())
+        );
+    }
+
+    #[test]
+    fn test_check_leverage_power() {
+        let (pool, custody, mut position, token_price, token_ema_price) = get_fixture();
+
+        // x4 initial leverage is within limits for power 1 to 4
+        for power in 1..=4 {
+            position.power = power;
+            assert!(pool
+                .check_leverage(
+                    &position,
+                    &token_price,
+                    &token_ema_price,
+                    &custody,
+                    &token_price,
+                    &token_ema_price,
+                    &custody,
+                    0,
+                    true
+                )
+                .unwrap());
+        }
+
+        // power 5 caps initial leverage at x3 but maintenance leverage at x6
+        position.power = 5;
+        assert!(!pool
+            .check_leverage(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                0,
+                true
+            )
+            .unwrap());
+        assert!(pool
+            .check_leverage(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                0,
+                false
+            )
+            .unwrap());
+        assert!(matches!(
+            pool.get_leverage_error(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                0,
+                true
+            )
+            .unwrap(),
+            Some(PerpetualsError::MaxInitialLeverage)
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_leverage() {
+        let (pool, mut custody, mut position, _, _) = get_fixture();
+        custody.pricing.trade_spread_long = 0;
+        custody.pricing.trade_spread_short = 0;
+        custody.pricing.maintenance_leverage = 200_000;
+        position.power = 1;
+        assert!(custody.pricing.validate());
+        assert_eq!(Pool::get_power_maintenance_leverage(1, &custody).unwrap(), 200_000);
+        assert_eq!(Pool::get_power_maintenance_leverage(5, &custody).unwrap(), 120_000);
+
+        // x4 position with x20 maintenance leverage is liquidated after a 20% loss on size
+        let token_ema_price = OraclePrice {
+            price: 25_000_000,
+            exponent: -3,
+        };
+        assert_eq!(
+            pool.get_liquidation_price(&position, &token_ema_price, &custody, &custody, 0)
+                .unwrap(),
+            scale(20_000, Perpetuals::PRICE_DECIMALS)
+        );
+
+        // past max leverage the position can't remove collateral but isn't liquidatable
+        let price = OraclePrice {
+            price: scale(21_000, Perpetuals::PRICE_DECIMALS),
+            exponent: -(Perpetuals::PRICE_DECIMALS as i32),
+        };
+        assert!(!pool
+            .check_leverage(&position, &price, &price, &custody, &price, &price, &custody, 0, false)
+            .unwrap());
+        assert!(!pool
+            .get_liquidation_check(&position, &price, &price, &custody, &price, &price, &custody, 0)
+            .unwrap()
+            .liquidatable);
+
+        custody.pricing.maintenance_leverage = 50_000;
+        assert!(!custody.pricing.validate());
+    }
+
+    #[test]
+    fn test_pool_size() {
+        let (mut pool, _custody, _position, _token_price, _token_ema_price) = get_fixture();
+        pool.ratios.clear();
+        let empty_size = pool.get_size().unwrap();
+
+        // add_custody capacity always fits the exact pool size
+        for i in 0..30 {
+            pool.custodies.push(Pubkey::new_unique());
+            pool.ratios.push(TokenRatios::default());
+            let capacity = Pool::LEN
+                + (i + 1) * std::mem::size_of::<Pubkey>()
+                + (i + 1) * std::mem::size_of::<TokenRatios>();
+            assert!(pool.get_size().unwrap() <= capacity);
+        }
+
+        // every removal shrinks by exactly one custody and ratio
+        let entry_size = 32 + borsh::to_vec(&TokenRatios::default()).unwrap().len();
+        while !pool.custodies.is_empty() {
+            let size = pool.get_size().unwrap();
+            pool.custodies.remove(0);
+            pool.ratios.remove(0);
+            assert_eq!(size - entry_size, pool.get_size().unwrap());
+        }
+        assert_eq!(empty_size, pool.get_size().unwrap());
+    }
+
+    fn get_account_info<T: AccountSerialize>(
+        key: Pubkey,
+        owner: Pubkey,
+        account: &T,
+    ) -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            false,
+            Box::leak(Box::new(1_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_token_ratio_exemptions() {
+        let (mut pool, mut custody, _position, token_price, _token_ema_price) = get_fixture();
+        pool.aum_usd = scale(250_000, Perpetuals::USD_DECIMALS) as u128;
+        custody.assets.owned = scale(5, 9);
+
+        // within bounds
+        for flow in [RatioFlow::AddLiquidity, RatioFlow::RemoveLiquidity, RatioFlow::Swap] {
+            assert!(pool
+                .check_token_ratio(flow, 0, 0, scale(1, 9), &custody, &token_price)
+                .unwrap());
+        }
+
+        // user flows can't push the custody below its min ratio
+        let amount_remove = scale(45, 8);
+        assert!(pool.get_new_ratio(0, amount_remove, &custody, &token_price).unwrap() < 1_000);
+        for flow in [RatioFlow::RemoveLiquidity, RatioFlow::Swap] {
+            assert!(!flow.is_ratio_exempt());
+            assert!(!pool
+                .check_token_ratio(flow, 0, 0, amount_remove, &custody, &token_price)
+                .unwrap());
+        }
+
+        // settlement flows are exempt
+        for flow in [
+            RatioFlow::Liquidation,
+            RatioFlow::ExpirySettlement,
+            RatioFlow::ForceSettlement,
+        ] {
+            assert!(flow.is_ratio_exempt());
+            assert!(pool
+                .check_token_ratio(flow, 0, 0, amount_remove, &custody, &token_price)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_aum_with_updated_custody() {
+        let (mut pool, mut custody, _position, _token_price, _token_ema_price) = get_fixture();
+        let custody_key = Pubkey::new_unique();
+        let oracle_key = Pubkey::new_unique();
+        let custody_key_b = Pubkey::new_unique();
+        let oracle_key_b = Pubkey::new_unique();
+        pool.custodies = vec![custody_key, custody_key_b];
+        custody.oracle.oracle_account = oracle_key;
+        custody.assets.owned = scale(10, 9);
+        let mut custody_b = custody.clone();
+        custody_b.oracle.oracle_account = oracle_key_b;
+
+        let oracle = CustomOracle {
+            price: 25_000_000,
+            expo: -3,
+            ema: 25_000_000,
+            ..CustomOracle::default()
+        };
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+            get_account_info(custody_key, crate::ID, &custody),
+            get_account_info(custody_key_b, crate::ID, &custody_b),
+            get_account_info(oracle_key, Pubkey::default(), &oracle),
+            get_account_info(oracle_key_b, Pubkey::default(), &oracle),
+        ]));
+
+        let stored_aum = scale(500_000, Perpetuals::USD_DECIMALS) as u128;
+        assert_eq!(
+            stored_aum,
+            pool.get_assets_under_management_usd(AumCalcMode::EMA, accounts, 0)
+                .unwrap()
+        );
+
+        // the in-memory custody balance is used instead of the stale account data
+        custody.assets.owned = scale(20, 9);
+        assert_eq!(
+            stored_aum * 3 / 2,
+            pool.get_assets_under_management_usd_with_custody(
+                AumCalcMode::EMA,
+                accounts,
+                &[(custody_key, &custody)],
+                0
+            )
+            .unwrap()
+        );
+
+        // both custodies of a swap are used in memory
+        custody_b.assets.owned = 0;
+        assert_eq!(
+            stored_aum,
+            pool.get_assets_under_management_usd_with_custody(
+                AumCalcMode::EMA,
+                accounts,
+                &[(custody_key, &custody), (custody_key_b, &custody_b)],
+                0
+            )
+            .unwrap()
+        );
+
+        // an unrelated custody doesn't replace any account
+        assert_eq!(
+            stored_aum,
+            pool.get_assets_under_management_usd_with_custody(
+                AumCalcMode::EMA,
+                accounts,
+                &[(Pubkey::new_unique(), &custody)],
+                0
+            )
+            .unwrap()
+        );
+
+        // mismatched oracle accounts are rejected for in-memory custodies too
+        custody.oracle.oracle_account = Pubkey::new_unique();
+        assert!(pool
+            .get_assets_under_management_usd_with_custody(
+                AumCalcMode::EMA,
+                accounts,
+                &[(custody_key, &custody)],
+                0
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_aum_skips_empty_custodies() {
+        let (mut pool, mut custody, _position, _token_price, _token_ema_price) = get_fixture();
+        let custody_key = Pubkey::new_unique();
+        let oracle_key = Pubkey::new_unique();
+        let empty_custody_key = Pubkey::new_unique();
+        let empty_oracle_key = Pubkey::new_unique();
+        pool.custodies = vec![custody_key, empty_custody_key];
+        custody.oracle.oracle_account = oracle_key;
+        custody.assets.owned = scale(10, 9);
+
+        // freshly added custody without balances, its oracle was never published to
+        let mut empty_custody = custody.clone();
+        empty_custody.oracle.oracle_account = empty_oracle_key;
+        empty_custody.assets.owned = 0;
+        assert!(empty_custody.is_empty_for_aum());
+
+        let oracle = CustomOracle {
+            price: 25_000_000,
+            expo: -3,
+            ema: 25_000_000,
+            ..CustomOracle::default()
+        };
+        let get_accounts = |empty_custody: &Custody| -> &'static [AccountInfo<'static>] {
+            Box::leak(Box::new([
+                get_account_info(custody_key, crate::ID, &custody),
+                get_account_info(empty_custody_key, crate::ID, empty_custody),
+                get_account_info(oracle_key, Pubkey::default(), &oracle),
+                AccountInfo::new(
+                    Box::leak(Box::new(empty_oracle_key)),
+                    false,
+                    false,
+                    Box::leak(Box::new(0)),
+                    Box::leak(Vec::new().into_boxed_slice()),
+                    Box::leak(Box::new(Pubkey::default())),
+                    false,
+                    0,
+                ),
+            ]))
+        };
+
+        // the empty custody costs no oracle reads, so its missing price doesn't matter
+        assert_eq!(
+            scale(250_000, Perpetuals::USD_DECIMALS) as u128,
+            pool.get_assets_under_management_usd(
+                AumCalcMode::EMA,
+                get_accounts(&empty_custody),
+                0
+            )
+            .unwrap()
+        );
+
+        // open positions are valued when unrealized pnl counts towards AUM
+        empty_custody.pricing.use_unrealized_pnl_in_aum = true;
+        empty_custody.long_positions.open_positions = 1;
+        assert!(!empty_custody.is_empty_for_aum());
+        assert!(pool
+            .get_assets_under_management_usd(AumCalcMode::EMA, get_accounts(&empty_custody), 0)
+            .is_err());
+    }
+
+    // host-side bench of AUM passes over a sparse pool, one funded custody out of eight
+    // against the same pool with all custodies funded, every skipped custody saves two
+    // oracle reads and two pnl aggregations. Custodies are passed in memory so the
+    // timings printed with `--nocapture` leave out the custody account deserialization.
+    #[test]
+    fn bench_aum_sparse_pool() {
+        const CUSTODIES: usize = 8;
+        const PASSES: u32 = 1_000;
+
+        let (mut pool, custody, _position, _token_price, _token_ema_price) = get_fixture();
+        let keys: Vec<(Pubkey, Pubkey)> = (0..CUSTODIES)
+            .map(|_| (Pubkey::new_unique(), Pubkey::new_unique()))
+            .collect();
+        pool.custodies = keys.iter().map(|(custody_key, _)| *custody_key).collect();
+
+        let oracle = CustomOracle {
+            price: 25_000_000,
+            expo: -3,
+            ema: 25_000_000,
+            ..CustomOracle::default()
+        };
+        let custodies: Vec<Custody> = keys
+            .iter()
+            .map(|(_, oracle_key)| {
+                let mut custody = custody.clone();
+                custody.oracle.oracle_account = *oracle_key;
+                custody
+            })
+            .collect();
+        let mut accounts = Vec::with_capacity(CUSTODIES * 2);
+        for ((custody_key, _), custody) in keys.iter().zip(custodies.iter()) {
+            accounts.push(get_account_info(*custody_key, crate::ID, custody));
+        }
+        for (_, oracle_key) in keys.iter() {
+            accounts.push(get_account_info(*oracle_key, Pubkey::default(), &oracle));
+        }
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(accounts.into_boxed_slice());
+
+        let bench = |funded: usize| {
+            let custodies: Vec<Custody> = custodies
+                .iter()
+                .enumerate()
+                .map(|(idx, custody)| {
+                    let mut custody = custody.clone();
+                    custody.assets.owned = if idx < funded { scale(10, 9) } else { 0 };
+                    custody
+                })
+                .collect();
+            let updated_custodies: Vec<(Pubkey, &Custody)> = keys
+                .iter()
+                .map(|(custody_key, _)| *custody_key)
+                .zip(custodies.iter())
+                .collect();
+
+            let start = std::time::Instant::now();
+            let mut aum_usd = 0;
+            for _ in 0..PASSES {
+                aum_usd = pool
+                    .get_assets_under_management_usd_with_custody(
+                        AumCalcMode::EMA,
+                        accounts,
+                        &updated_custodies,
+                        0,
+                    )
+                    .unwrap();
+            }
+            (aum_usd, start.elapsed() / PASSES)
+        };
+
+        let (sparse_aum_usd, sparse_time) = bench(1);
+        let (dense_aum_usd, dense_time) = bench(CUSTODIES);
+        assert_eq!(
+            sparse_aum_usd,
+            scale(250_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        assert_eq!(dense_aum_usd, sparse_aum_usd * CUSTODIES as u128);
+        println!(
+            "AUM pass over {} custodies: 1 funded {:?} (2 oracle reads), {} funded {:?} ({} oracle reads)",
+            CUSTODIES,
+            sparse_time,
+            CUSTODIES,
+            dense_time,
+            CUSTODIES * 2
+        );
+    }
+
+    #[test]
+    fn test_performance_fee() {
+        let mut pool = Pool {
+            performance_fee_bps: 2_000,
+            ..Default::default()
+        };
+        let aum_usd = scale(1_200, Perpetuals::USD_DECIMALS);
+        let lp_supply = scale(1_000, Perpetuals::LP_DECIMALS);
+        assert_eq!(Pool::get_lp_price(aum_usd, 0).unwrap(), 0);
+        assert_eq!(Pool::get_lp_price(aum_usd, lp_supply).unwrap(), 1_200_000);
+
+        // no fee until the first high-water mark is recorded
+        assert_eq!(pool.get_performance_fee(aum_usd, lp_supply).unwrap(), (0, 0));
+
+        // no fee at or below the high-water mark
+        pool.lp_price_hwm = 1_200_000;
+        assert_eq!(pool.get_performance_fee(aum_usd, lp_supply).unwrap(), (0, 0));
+
+        // 20% of a 200 USD gain, minted tokens are worth the fee after dilution
+        pool.lp_price_hwm = 1_000_000;
+        let (fee_usd, fee_lp) = pool.get_performance_fee(aum_usd, lp_supply).unwrap();
+        assert_eq!(fee_usd, scale(40, Perpetuals::USD_DECIMALS));
+        assert_eq!(fee_lp, 34_482_758);
+        assert_eq!(
+            Pool::get_lp_price(aum_usd, lp_supply + fee_lp).unwrap(),
+            1_160_000
+        );
+
+        // disabled fee
+        pool.performance_fee_bps = 0;
+        assert_eq!(pool.get_performance_fee(aum_usd, lp_supply).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_roll_performance_epoch() {
+        let mut pool = Pool {
+            performance_fee_bps: 2_000,
+            performance_epoch_sec: 100,
+            performance_epoch_start: 1_000,
+            lp_price_hwm: 1_000_000,
+            ..Default::default()
+        };
+        let aum_usd = scale(1_200, Perpetuals::USD_DECIMALS);
+        let lp_supply = scale(1_000, Perpetuals::LP_DECIMALS);
+
+        // the epoch can't be rolled before it has elapsed
+        assert_eq!(
+            pool.roll_performance_epoch(aum_usd, lp_supply, 1_099),
+            Err(PerpetualsError::PerformanceEpochNotElapsed.into())
+        );
+
+        // the fee is taken and the high-water mark moves to the price net of the fee
+        assert_eq!(
+            pool.roll_performance_epoch(aum_usd, lp_supply, 1_100).unwrap(),
+            (1_200_000, scale(40, Perpetuals::USD_DECIMALS), 34_482_758)
+        );
+        assert_eq!(pool.lp_price_hwm, 1_160_000);
+        assert_eq!(pool.performance_epoch_start, 1_100);
+
+        // below the high-water mark no fee is due and the mark stays
+        let aum_usd = scale(1_100, Perpetuals::USD_DECIMALS);
+        assert_eq!(
+            pool.roll_performance_epoch(aum_usd, lp_supply, 1_200).unwrap(),
+            (1_100_000, 0, 0)
+        );
+        assert_eq!(pool.lp_price_hwm, 1_160_000);
+        assert_eq!(pool.performance_epoch_start, 1_200);
+
+        // disabled fee
+        pool.performance_fee_bps = 0;
+        assert!(pool.roll_performance_epoch(aum_usd, lp_supply, 1_300).is_err());
+    }
+
+    #[test]
+    fn test_lp_supply_cap() {
+        let mut pool = Pool::default();
+        assert_eq!(pool.get_lp_supply_capacity(1_000), u64::MAX);
+        assert!(pool.check_lp_supply_cap(1_000, u64::MAX).is_ok());
+
+        pool.max_lp_supply = 1_000;
+        assert_eq!(pool.get_lp_supply_capacity(400), 600);
+        assert!(pool.check_lp_supply_cap(400, 600).is_ok());
+        assert!(pool.check_lp_supply_cap(400, 601).is_err());
+
+        // a cap lowered below the supply blocks new mints
+        assert_eq!(pool.get_lp_supply_capacity(1_200), 0);
+        assert!(pool.check_lp_supply_cap(1_200, 1).is_err());
+    }
+
+    #[test]
+    fn test_lp_amount_drain_and_refill() {
+        let deposit_usd = scale(1_000, Perpetuals::USD_DECIMALS);
+
+        // first deposit, 1 LP token per USD
+        let lp_supply = Pool::get_lp_amount(deposit_usd, 0, 0).unwrap();
+        assert_eq!(lp_supply, scale(1_000, Perpetuals::LP_DECIMALS));
+
+        // subsequent deposits are priced at the LP token price
+        let aum_usd = scale(2_000, Perpetuals::USD_DECIMALS) as u128;
+        assert_eq!(
+            Pool::get_lp_amount(deposit_usd, aum_usd, lp_supply).unwrap(),
+            lp_supply / 2
+        );
+
+        // full withdrawal leaves residual AUM without supply, the next deposit is
+        // priced like the first one and isn't minted zero LP tokens
+        let residual_usd = 1_234u128;
+        let lp_amount = Pool::get_lp_amount(deposit_usd, residual_usd, 0).unwrap();
+        assert_eq!(lp_amount, lp_supply);
+
+        // the refilling depositor owns the whole pool including the residual
+        let lp_price = Pool::get_lp_price(deposit_usd + residual_usd as u64, lp_amount).unwrap();
+        assert!(lp_price >= scale(1, Perpetuals::USD_DECIMALS));
+
+        // supply left with no senior AUM also restarts at 1 LP token per USD
+        assert_eq!(
+            Pool::get_lp_amount(deposit_usd, 0, lp_supply).unwrap(),
+            deposit_usd
+        );
+    }
+
+    #[test]
+    fn test_optimal_lp_fee() {
+        let ratios = TokenRatios {
+            target: 5_000,
+            min: 1_000,
+            max: 9_000,
+        };
+        let fee = |ratio, is_add| Pool::get_optimal_lp_fee(250, 10, &ratios, ratio, is_add);
+
+        // fee_optimal at the target from both sides, fee_max at the edges
+        assert_eq!(fee(5_000, true).unwrap(), 10);
+        assert_eq!(fee(5_000, false).unwrap(), 10);
+        assert_eq!(fee(9_000, true).unwrap(), 250);
+        assert_eq!(fee(1_000, false).unwrap(), 250);
+        assert_eq!(fee(1_000, true).unwrap(), -230);
+        assert_eq!(fee(9_000, false).unwrap(), -230);
+
+        // out of range in the trade direction
+        assert!(fee(9_001, true).is_err());
+        assert!(fee(999, false).is_err());
+
+        // continuity at the target and monotonicity on each side
+        for is_add in [true, false] {
+            let step = |ratio: u64| {
+                let delta = fee(ratio + 1, is_add).unwrap() - fee(ratio, is_add).unwrap();
+                if is_add {
+                    assert!((0..=1).contains(&delta), "{} {}", ratio, delta);
+                } else {
+                    assert!((-1..=0).contains(&delta), "{} {}", ratio, delta);
+                }
+            };
+            (ratios.min..ratios.max).for_each(step);
+        }
+
+        // target on the edge of the range
+        let edge = TokenRatios {
+            target: 9_000,
+            min: 1_000,
+            max: 9_000,
+        };
+        assert_eq!(Pool::get_optimal_lp_fee(250, 10, &edge, 9_000, true).unwrap(), 10);
+        let edge = TokenRatios {
+            target: 1_000,
+            min: 1_000,
+            max: 9_000,
+        };
+        assert_eq!(Pool::get_optimal_lp_fee(250, 10, &edge, 500, true).unwrap(), 10);
+
+        // fee_max below fee_optimal is rejected by validation and at runtime
+        assert!(Pool::get_optimal_lp_fee(10, 250, &ratios, 5_000, true).is_err());
+        let fees = Fees {
+            fee_max: 10,
+            fee_optimal: 250,
+            ..Fees::default()
+        };
+        assert!(!fees.validate());
+        assert!(Fees {
+            fee_max: 250,
+            fee_optimal: 10,
+            ..Fees::default()
+        }
+        .validate());
+    }
+
+    #[test]
+    fn test_settle_position() {
+        let (pool, custody, position, token_price, _) = get_fixture();
+        let curtime = 100;
+        let short_position = Position {
+            side: Side::Short,
+            ..position
+        };
+        let mut stable_custody = custody.clone();
+        stable_custody.is_stable = true;
+        stable_custody.assets.owned = scale(100, 9);
+        stable_custody.assets.collateral = position.collateral_amount;
+        let hook_payload = PositionHookPayload {
+            event: PositionHookEvent::Close,
+            time: curtime,
+            ..PositionHookPayload::default()
+        };
+        // settles the short position opened on the custody against the stable custody
+        let settle = |stable_custody: &Custody, settlement: &PositionSettlement| {
+            let (mut pool, mut custody, mut collateral_custody) =
+                (pool.clone(), custody.clone(), stable_custody.clone());
+            custody.add_position(
+                &short_position,
+                &token_price,
+                0,
+                Some(&mut collateral_custody),
+            )?;
+            custody.trade_stats.oi_short_usd = short_position.size_usd;
+            pool.settle_position(
+                &short_position,
+                &mut custody,
+                &mut collateral_custody,
+                settlement,
+                None,
+                None,
+                None,
+                &hook_payload,
+            )?;
+            Ok::<_, Error>((custody, collateral_custody))
+        };
+        let protocol_fee = Pool::get_fee_amount(25, scale(1, 7)).unwrap();
+
+        // closed at a loss, the lost collateral goes to the pool
+        let settlement = PositionSettlement {
+            amount: scale(8, 8),
+            fee_amount: scale(1, 7),
+            fee_usd: scale(250, Perpetuals::USD_DECIMALS),
+            close_fee_usd: scale(250, Perpetuals::USD_DECIMALS),
+            loss_usd: scale(5_000, Perpetuals::USD_DECIMALS),
+            interest_amount: scale(1, 6),
+            ..PositionSettlement::default()
+        };
+        let (custody_after, collateral_after) = settle(&stable_custody, &settlement).unwrap();
+        assert_eq!(
+            collateral_after.assets.owned,
+            scale(100, 9) + scale(2, 8) - protocol_fee
+        );
+        assert_eq!(collateral_after.assets.collateral, 0);
+        assert_eq!(collateral_after.assets.protocol_fees, protocol_fee);
+        assert_eq!(collateral_after.pnl_reserve, scale(1, 6));
+        assert_eq!(
+            collateral_after.collected_fees.close_position_usd,
+            settlement.close_fee_usd as u128
+        );
+        assert_eq!(custody_after.trade_stats.oi_short_usd, 0);
+        assert_eq!(custody_after.trade_stats.loss_usd, settlement.loss_usd);
+        assert_eq!(
+            custody_after.volume_stats.close_position_usd,
+            position.size_usd as u128
+        );
+        assert_eq!(custody_after.short_positions.open_positions, 0);
+
+        // a queued payout stays in owned assets until the claim is executed
+        let queued = PositionSettlement {
+            queued: true,
+            ..settlement
+        };
+        let (_, collateral_after) = settle(&stable_custody, &queued).unwrap();
+        assert_eq!(
+            collateral_after.assets.owned,
+            scale(100, 9) + position.collateral_amount - protocol_fee
+        );
+
+        // re-entered at a profit, the profit is added to the carried over collateral
+        let reentered = PositionSettlement {
+            amount: scale(12, 8),
+            fee_amount: scale(1, 7),
+            fee_usd: scale(250, Perpetuals::USD_DECIMALS),
+            open_fee_usd: scale(250, Perpetuals::USD_DECIMALS),
+            profit_usd: scale(5_000, Perpetuals::USD_DECIMALS),
+            reentered: true,
+            ..PositionSettlement::default()
+        };
+        let (custody_after, collateral_after) = settle(&stable_custody, &reentered).unwrap();
+        assert_eq!(
+            collateral_after.assets.owned,
+            scale(100, 9) - scale(2, 8) - protocol_fee
+        );
+        assert_eq!(collateral_after.assets.collateral, scale(12, 8));
+        assert_eq!(
+            collateral_after.collected_fees.open_position_usd,
+            reentered.open_fee_usd as u128
+        );
+        assert_eq!(custody_after.trade_stats.profit_usd, reentered.profit_usd);
+
+        // carried over profits must be available in the pool
+        let mut locked_custody = stable_custody.clone();
+        locked_custody.assets.locked = scale(100, 9) + position.collateral_amount - 1;
+        assert_eq!(
+            settle(&locked_custody, &reentered).map(|_| ()),
+            Err(PerpetualsError::CustodyAmountLimit.into())
+        );
+
+        // long positions of non-virtual custodies are tracked in the collateral custody
+        let mut pool = pool.clone();
+        let mut custody = stable_custody.clone();
+        custody
+            .add_position(&position, &token_price, 0, None)
+            .unwrap();
+        custody.trade_stats.oi_long_usd = position.size_usd;
+        let mut collateral_custody = custody.clone();
+        pool.settle_position(
+            &position,
+            &mut custody,
+            &mut collateral_custody,
+            &settlement,
+            None,
+            None,
+            None,
+            &hook_payload,
+        )
+        .unwrap();
+        assert_eq!(collateral_custody.trade_stats.oi_long_usd, 0);
+        assert_eq!(collateral_custody.long_positions.open_positions, 0);
+        assert_eq!(custody, collateral_custody);
+    }
+}
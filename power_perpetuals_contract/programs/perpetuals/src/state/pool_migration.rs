@@ -0,0 +1,109 @@
+//! Pool migration state
+//!
+//! Tracks the move of a pool's custodies, positions and LP token supply to a new pool
+//! PDA (e.g. after a pool rename). Custodies and positions are derived from the pool
+//! key, so they are re-created under the target pool in chunks over several
+//! transactions, and this account keeps the cursor and the integrity counters.
+
+use {
+    crate::{error::PerpetualsError, state::perpetuals::Permissions},
+    anchor_lang::prelude::*,
+};
+
+/// Source custody and the target custody it was imported into
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyMapping {
+    /// Custody of the source pool
+    pub source: Pubkey,
+    /// Custody of the target pool (default until imported)
+    pub target: Pubkey,
+    /// Custody permissions before the source pool was frozen
+    pub permissions: Permissions,
+    /// Custody CPI permission before the source pool was frozen
+    pub allow_cpi: bool,
+}
+
+/// Pool migration account
+///
+/// PDA derived from the source pool.
+#[account]
+#[derive(Default, Debug)]
+pub struct PoolMigration {
+    /// Pool being migrated (frozen while the migration is in progress)
+    pub source_pool: Pubkey,
+    /// Pool the state is imported into
+    pub target_pool: Pubkey,
+    /// Source custodies in pool order
+    pub custodies: Vec<CustodyMapping>,
+    /// Open positions of the source pool when it was exported
+    pub positions_total: u64,
+    /// Positions re-created under the target pool so far
+    pub positions_migrated: u64,
+    /// LP token supply of the source pool when it was exported
+    pub lp_supply: u64,
+    /// Whether the LP supply was minted to the migration LP vault
+    pub finalized: bool,
+
+    /// Bump seed for the pool migration PDA
+    pub bump: u8,
+}
+
+impl PoolMigration {
+    /// Maximum number of custodies of a migrated pool
+    pub const MAX_CUSTODIES: usize = 16;
+    /// Account size in bytes (8 byte discriminator + data + custodies)
+    pub const LEN: usize = 8
+        + std::mem::size_of::<PoolMigration>()
+        + PoolMigration::MAX_CUSTODIES * std::mem::size_of::<CustodyMapping>();
+
+    /// Returns the custody mapping of a source custody
+    pub fn get_mapping_mut(&mut self, source: &Pubkey) -> Result<&mut CustodyMapping> {
+        self.custodies
+            .iter_mut()
+            .find(|x| x.source == *source)
+            .ok_or_else(|| PerpetualsError::UnsupportedToken.into())
+    }
+
+    /// Returns the target custody a source custody was imported into
+    pub fn get_target_custody(&self, source: &Pubkey) -> Result<Pubkey> {
+        match self.custodies.iter().find(|x| x.source == *source) {
+            Some(mapping) if mapping.target != Pubkey::default() => Ok(mapping.target),
+            _ => err!(PerpetualsError::InvalidCustodyState),
+        }
+    }
+
+    /// Checks whether all custodies and positions were imported
+    pub fn is_imported(&self) -> bool {
+        self.positions_migrated == self.positions_total
+            && self.custodies.iter().all(|x| x.target != Pubkey::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_custody_mapping() {
+        let source = Pubkey::new_unique();
+        let mut migration = PoolMigration {
+            custodies: vec![CustodyMapping {
+                source,
+                ..Default::default()
+            }],
+            positions_total: 1,
+            ..Default::default()
+        };
+        assert!(migration.get_target_custody(&source).is_err());
+        assert!(migration.get_mapping_mut(&Pubkey::new_unique()).is_err());
+        assert!(!migration.is_imported());
+
+        let target = Pubkey::new_unique();
+        migration.get_mapping_mut(&source).unwrap().target = target;
+        assert_eq!(migration.get_target_custody(&source).unwrap(), target);
+        assert!(!migration.is_imported());
+
+        migration.positions_migrated = 1;
+        assert!(migration.is_imported());
+    }
+}
@@ -4,14 +4,20 @@
 //! for tracking user positions in power perpetuals.
 
 use {
-    crate::{math, state::perpetuals::Perpetuals},
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{perpetuals::Perpetuals, pool::CollateralWithdrawalLimit},
+    },
     anchor_lang::prelude::*,
+    anchor_spl::associated_token::get_associated_token_address,
 };
 
 /// Position side (direction of the trade)
-#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub enum Side {
     /// No position (closed or not opened)
+    #[default]
     None,
     /// Long position (betting price will go up)
     Long,
@@ -19,16 +25,26 @@ pub enum Side {
     Short,
 }
 
-impl Default for Side {
-    fn default() -> Self {
-        Self::None
-    }
+/// Position risk label derived from its health factor
+///
+/// Thresholds are configured per custody in `PricingParams`, a liquidatable
+/// position is always `Danger`.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum RiskTier {
+    /// Health factor above the warning threshold
+    #[default]
+    Safe,
+    /// Health factor at or below the warning threshold
+    Warning,
+    /// Health factor at or below the danger threshold, or liquidatable
+    Danger,
 }
 
 /// Collateral change operation type
-#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub enum CollateralChange {
     /// No collateral change
+    #[default]
     None,
     /// Adding collateral to position
     Add,
@@ -36,9 +52,22 @@ pub enum CollateralChange {
     Remove,
 }
 
-impl Default for CollateralChange {
-    fn default() -> Self {
-        Self::None
+/// Collateral amount of add_collateral and remove_collateral
+///
+/// Either side of the conversion can be given, the other one is derived from the oracle
+/// price with rounding in favor of the pool.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum CollateralAmount {
+    /// Amount in collateral token native units
+    Tokens(u64),
+    /// Amount in USD (Perpetuals::USD_DECIMALS decimals)
+    Usd(u64),
+}
+
+impl CollateralAmount {
+    /// Returns true if the amount is zero
+    pub fn is_zero(&self) -> bool {
+        matches!(self, CollateralAmount::Tokens(0) | CollateralAmount::Usd(0))
     }
 }
 
@@ -89,11 +118,96 @@ pub struct Position {
     pub locked_amount: u64,
     /// Amount of collateral tokens (in collateral token decimals)
     pub collateral_amount: u64,
+    /// Risk tier as of the last instruction that modified the position
+    pub risk_tier: RiskTier,
+    /// Slot the liquidation reward auction started at (0 = not started)
+    pub liquidation_auction_slot: u64,
+    /// Interest epoch of the collateral custody the snapshot was taken in
+    pub interest_epoch: u32,
+    /// Token account the remainder is paid to when a third party closes the position
+    /// (default = associated token account of the owner)
+    pub payout_account: Pubkey,
+    /// Start time of the current collateral withdrawal window
+    pub withdrawal_window_start: i64,
+    /// Collateral value in USD at the start of the withdrawal window
+    pub withdrawal_window_collateral_usd: u64,
+    /// Collateral value in USD removed in the withdrawal window
+    pub withdrawn_collateral_usd: u64,
 
     /// Bump seed for the position PDA
     pub bump: u8,
 }
 
+/// Original position account layout, upgraded by upgrade_position.
+/// Shares the Position account discriminator.
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PositionV0 {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub open_time: i64,
+    pub update_time: i64,
+    pub side: Side,
+    pub power: u8,
+    pub price: u64,
+    pub size_usd: u64,
+    pub borrow_size_usd: u64,
+    pub collateral_usd: u64,
+    pub unrealized_profit_usd: u64,
+    pub unrealized_loss_usd: u64,
+    pub cumulative_interest_snapshot: u128,
+    pub locked_amount: u64,
+    pub collateral_amount: u64,
+    pub bump: u8,
+}
+
+impl PositionV0 {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<PositionV0>();
+
+    /// Load a position stored in the original layout, told apart by the data length
+    pub fn load(data: &[u8]) -> Option<PositionV0> {
+        if data.get(..8)? != Position::DISCRIMINATOR || data.len() != PositionV0::LEN {
+            return None;
+        }
+        PositionV0::deserialize(&mut &data[8..]).ok()
+    }
+}
+
+impl From<PositionV0> for Position {
+    fn from(position: PositionV0) -> Self {
+        Position {
+            owner: position.owner,
+            pool: position.pool,
+            custody: position.custody,
+            collateral_custody: position.collateral_custody,
+            open_time: position.open_time,
+            update_time: position.update_time,
+            side: position.side,
+            power: position.power,
+            price: position.price,
+            size_usd: position.size_usd,
+            borrow_size_usd: position.borrow_size_usd,
+            collateral_usd: position.collateral_usd,
+            unrealized_profit_usd: position.unrealized_profit_usd,
+            unrealized_loss_usd: position.unrealized_loss_usd,
+            cumulative_interest_snapshot: position.cumulative_interest_snapshot,
+            locked_amount: position.locked_amount,
+            collateral_amount: position.collateral_amount,
+            risk_tier: RiskTier::default(),
+            liquidation_auction_slot: 0,
+            // the snapshot was taken before the interest index was ever rebased
+            interest_epoch: 0,
+            payout_account: Pubkey::default(),
+            withdrawal_window_start: 0,
+            withdrawal_window_collateral_usd: 0,
+            withdrawn_collateral_usd: 0,
+            bump: position.bump,
+        }
+    }
+}
+
 impl Position {
     /// Account size in bytes (8 byte discriminator + data)
     pub const LEN: usize = 8 + std::mem::size_of::<Position>();
@@ -113,4 +227,319 @@ impl Position {
             self.collateral_usd as u128,
         )?)
     }
+
+    /// Check that a token account is where the owner wants the position remainder paid
+    ///
+    /// # Arguments
+    /// * `account` - Token account supplied to receive the remainder
+    /// * `mint` - Collateral token mint
+    ///
+    /// # Returns
+    /// true if the account is the registered payout account, or the associated token
+    /// account of the owner if none is registered
+    pub fn is_payout_account(&self, account: &Pubkey, mint: &Pubkey) -> bool {
+        if self.payout_account == Pubkey::default() {
+            *account == get_associated_token_address(&self.owner, mint)
+        } else {
+            *account == self.payout_account
+        }
+    }
+
+    /// Entry price after increasing the position size at a new price
+    ///
+    /// Power payoffs of the same side and power add up: size / entry^power for longs
+    /// and size * entry^power for shorts. The new entry price keeps that sum, so the
+    /// increased position has the PnL of its two tranches at any exit price. With
+    /// power 1 this is the size-weighted harmonic mean (longs) or arithmetic mean
+    /// (shorts) of the entry prices.
+    ///
+    /// # Arguments
+    /// * `price` - Entry price of the added size, scaled to PRICE_DECIMALS
+    /// * `added_size_usd` - Added size in USD
+    ///
+    /// # Returns
+    /// Entry price of the increased position, scaled to PRICE_DECIMALS
+    pub fn get_increased_entry_price(&self, price: u64, added_size_usd: u64) -> Result<u64> {
+        if self.size_usd == 0 || self.price == 0 {
+            return Ok(price);
+        }
+        require!(
+            price > 0 && self.power >= 1 && self.power <= 5,
+            PerpetualsError::InvalidPositionState
+        );
+        // extra precision for the power and root
+        let scale = math::checked_pow(10u128, 12)?;
+
+        // existing size payoff in units of the added size payoff: (price / entry)^power
+        // for longs, (entry / price)^power for shorts
+        let (numerator, denominator) = if self.side == Side::Long {
+            (price, self.price)
+        } else {
+            (self.price, price)
+        };
+        let ratio = math::checked_div(
+            math::checked_mul(numerator as u128, scale)?,
+            denominator as u128,
+        )?;
+        let Some(ratio_powered) = math::fixed_pow(ratio, self.power, scale) else {
+            return err!(PerpetualsError::MathOverflow);
+        };
+
+        // size-weighted payoff of both tranches, back to a price ratio
+        let size_usd = math::checked_add(self.size_usd, added_size_usd)?;
+        let weighted = math::checked_div(
+            math::checked_add(
+                math::checked_mul(self.size_usd as u128, ratio_powered)?,
+                math::checked_mul(added_size_usd as u128, scale)?,
+            )?,
+            size_usd as u128,
+        )?;
+        let root = math::checked_fixed_root(weighted, self.power, scale)?;
+
+        if self.side == Side::Long {
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(price as u128, scale)?,
+                root,
+            )?)
+        } else {
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(price as u128, root)?,
+                scale,
+            )?)
+        }
+    }
+
+    /// Record a collateral removal against the pool withdrawal limit
+    ///
+    /// Positions at or above the limit size can remove at most max_withdrawal_bps of
+    /// the collateral they held when the window started. The window restarts on the
+    /// first removal after it elapsed. Must be called before collateral_usd is reduced.
+    ///
+    /// # Arguments
+    /// * `limit` - Pool collateral withdrawal limit
+    /// * `collateral_usd` - Collateral value being removed in USD
+    /// * `curtime` - Current timestamp
+    pub fn record_collateral_withdrawal(
+        &mut self,
+        limit: &CollateralWithdrawalLimit,
+        collateral_usd: u64,
+        curtime: i64,
+    ) -> Result<()> {
+        if limit.max_withdrawal_bps == 0 || self.size_usd < limit.min_size_usd {
+            return Ok(());
+        }
+        if self.withdrawal_window_start == 0
+            || curtime >= math::checked_add(self.withdrawal_window_start, limit.window_sec)?
+        {
+            self.withdrawal_window_start = curtime;
+            self.withdrawal_window_collateral_usd = self.collateral_usd;
+            self.withdrawn_collateral_usd = 0;
+        }
+        self.withdrawn_collateral_usd =
+            math::checked_add(self.withdrawn_collateral_usd, collateral_usd)?;
+
+        let max_withdrawal_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(
+                self.withdrawal_window_collateral_usd as u128,
+                limit.max_withdrawal_bps as u128,
+            )?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        require!(
+            self.withdrawn_collateral_usd <= max_withdrawal_usd,
+            PerpetualsError::CollateralWithdrawalLimitExceeded
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_legacy_position() {
+        let legacy = PositionV0 {
+            owner: Pubkey::new_unique(),
+            side: Side::Short,
+            power: 2,
+            size_usd: 1_000,
+            cumulative_interest_snapshot: 500,
+            collateral_amount: 10,
+            bump: 254,
+            ..PositionV0::default()
+        };
+        let mut data = Position::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(PositionV0::LEN, 0);
+        assert_eq!(PositionV0::load(&data), Some(legacy.clone()));
+
+        let position = Position::from(legacy.clone());
+        assert_eq!(position.owner, legacy.owner);
+        assert_eq!(position.side, Side::Short);
+        assert_eq!(position.power, 2);
+        assert_eq!(position.size_usd, 1_000);
+        assert_eq!(position.cumulative_interest_snapshot, 500);
+        assert_eq!(position.collateral_amount, 10);
+        assert_eq!(position.interest_epoch, 0);
+        assert_eq!(position.payout_account, Pubkey::default());
+        assert_eq!(position.bump, 254);
+
+        // current positions are not loaded as legacy ones
+        assert_ne!(PositionV0::LEN, Position::LEN);
+        let mut data = Vec::new();
+        position.try_serialize(&mut data).unwrap();
+        data.resize(Position::LEN, 0);
+        assert_eq!(PositionV0::load(&data), None);
+    }
+
+    #[test]
+    fn test_payout_account() {
+        let mint = Pubkey::new_unique();
+        let mut position = Position {
+            owner: Pubkey::new_unique(),
+            ..Position::default()
+        };
+        let ata = get_associated_token_address(&position.owner, &mint);
+        let other = Pubkey::new_unique();
+
+        // defaults to the associated token account of the owner
+        assert!(position.is_payout_account(&ata, &mint));
+        assert!(!position.is_payout_account(&other, &mint));
+
+        position.payout_account = other;
+        assert!(position.is_payout_account(&other, &mint));
+        assert!(!position.is_payout_account(&ata, &mint));
+    }
+
+    fn get_pnl(position: &Position, exit_price: u64) -> i128 {
+        let (exit_price, entry_price) = if position.side == Side::Long {
+            (exit_price, position.price)
+        } else {
+            (position.price, exit_price)
+        };
+        let (profit, loss) = math::calc_power_perps_pnl(
+            exit_price,
+            entry_price,
+            position.size_usd,
+            position.power,
+            Perpetuals::PRICE_DECIMALS,
+            Perpetuals::USD_DECIMALS,
+        )
+        .unwrap();
+        profit as i128 - loss as i128
+    }
+
+    #[test]
+    fn test_increased_entry_price() {
+        let size_usd = 10_000_000_000;
+        let added_size_usd = 5_000_000_000;
+        for side in [Side::Long, Side::Short] {
+            for power in 1..=5 {
+                for price in [80_000_000, 125_000_000] {
+                    let position = Position {
+                        side,
+                        power,
+                        price: 100_000_000,
+                        size_usd,
+                        ..Position::default()
+                    };
+                    let added = Position {
+                        price,
+                        size_usd: added_size_usd,
+                        ..position
+                    };
+                    let increased = Position {
+                        price: position
+                            .get_increased_entry_price(price, added_size_usd)
+                            .unwrap(),
+                        size_usd: size_usd + added_size_usd,
+                        ..position
+                    };
+
+                    // PnL of the increased position matches its tranches
+                    for exit_price in [price, 90_000_000, 110_000_000, price * 3 / 2] {
+                        let expected = get_pnl(&position, exit_price) + get_pnl(&added, exit_price);
+                        let pnl = get_pnl(&increased, exit_price);
+                        assert!(
+                            (expected - pnl).abs()
+                                <= ((size_usd + added_size_usd) / 10_000) as i128,
+                            "{:?} power {} price {} exit {}: {} != {}",
+                            side,
+                            power,
+                            price,
+                            exit_price,
+                            expected,
+                            pnl
+                        );
+                    }
+                }
+            }
+        }
+
+        // power 1 longs average entry prices harmonically
+        let position = Position {
+            side: Side::Long,
+            power: 1,
+            price: 100_000_000,
+            size_usd: 1_000_000,
+            ..Position::default()
+        };
+        assert_eq!(
+            position
+                .get_increased_entry_price(50_000_000, 1_000_000)
+                .unwrap(),
+            66_666_666
+        );
+        assert_eq!(
+            Position::default()
+                .get_increased_entry_price(50_000_000, 1_000_000)
+                .unwrap(),
+            50_000_000
+        );
+    }
+
+    #[test]
+    fn test_collateral_withdrawal_limit() {
+        let limit = CollateralWithdrawalLimit {
+            min_size_usd: 100_000,
+            max_withdrawal_bps: 2_500,
+            window_sec: 3_600,
+        };
+        let mut position = Position {
+            size_usd: 100_000,
+            collateral_usd: 10_000,
+            ..Position::default()
+        };
+
+        // up to 25% of the collateral at the window start per window
+        position
+            .record_collateral_withdrawal(&limit, 1_500, 1_000)
+            .unwrap();
+        position.collateral_usd -= 1_500;
+        position
+            .record_collateral_withdrawal(&limit, 1_000, 2_000)
+            .unwrap();
+        position.collateral_usd -= 1_000;
+        assert!(position
+            .record_collateral_withdrawal(&limit, 1, 4_599)
+            .is_err());
+
+        // a new window starts once the previous one elapsed
+        position
+            .record_collateral_withdrawal(&limit, 1_875, 4_600)
+            .unwrap();
+        assert_eq!(position.withdrawal_window_collateral_usd, 7_500);
+
+        // smaller positions and disabled limits aren't restricted
+        position.size_usd = 99_999;
+        assert!(position
+            .record_collateral_withdrawal(&limit, 7_000, 4_601)
+            .is_ok());
+        position.size_usd = 100_000;
+        let disabled = CollateralWithdrawalLimit::default();
+        assert!(position
+            .record_collateral_withdrawal(&disabled, 7_000, 4_601)
+            .is_ok());
+    }
 }
\ No newline at end of file
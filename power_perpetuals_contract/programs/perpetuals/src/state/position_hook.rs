@@ -0,0 +1,214 @@
+//! Position hook state
+//!
+//! A pool can notify an allow-listed external program (points programs, copy trading,
+//! notification protocols) after positions are opened, closed or liquidated. Rolls and
+//! power changes are reported as a close of the old position and an open of the new
+//! one, settlements of expired or force-settled positions as closes. The hook is CPI-invoked with a compact
+//! payload and signed by the hook authority PDA, which owns nothing, so the hook program
+//! can verify the caller without gaining authority over any pool account.
+//!
+//! A failing CPI aborts the whole transaction, so failures are isolated by keeping the
+//! hook optional: unless the pool marks it required, the hook only runs when the caller
+//! passes the hook program and enough compute is left, and a failing hook is skipped by
+//! resending the transaction without it.
+
+use {
+    crate::{error::PerpetualsError, state::position::Side},
+    anchor_lang::{
+        prelude::*,
+        solana_program::{
+            instruction::{AccountMeta, Instruction},
+            program::invoke_signed,
+        },
+    },
+};
+
+/// Position lifecycle event reported to the hook
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum PositionHookEvent {
+    #[default]
+    Open,
+    Close,
+    Liquidate,
+}
+
+/// Position hook configuration of a pool
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PositionHook {
+    /// Program invoked after position lifecycle events (default = no hook)
+    pub program: Pubkey,
+    /// Bitmask of the events the hook is invoked for, see PositionHook::EVENT_*
+    pub events: u8,
+    /// Whether the operation fails if the hook can't be invoked
+    pub required: bool,
+    /// Compute units that must be left to invoke the hook (0 = no minimum)
+    pub compute_budget: u32,
+}
+
+/// Payload passed to the hook program
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PositionHookPayload {
+    pub event: PositionHookEvent,
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub custody: Pubkey,
+    pub side: Side,
+    /// Position size in USD (scaled to USD_DECIMALS)
+    pub size_usd: u64,
+    /// Entry price on open, exit or settlement price on close and liquidation
+    /// (PRICE_DECIMALS)
+    pub price: u64,
+    pub time: i64,
+}
+
+impl PositionHook {
+    pub const EVENT_OPEN: u8 = 1;
+    pub const EVENT_CLOSE: u8 = 1 << 1;
+    pub const EVENT_LIQUIDATE: u8 = 1 << 2;
+    pub const EVENT_ALL: u8 = Self::EVENT_OPEN | Self::EVENT_CLOSE | Self::EVENT_LIQUIDATE;
+    /// Instruction discriminator of the hook, sha256("global:on_position_event")[..8],
+    /// so Anchor programs can implement it as `on_position_event(payload)`
+    pub const INSTRUCTION_DISCRIMINATOR: [u8; 8] = [120, 221, 195, 93, 12, 4, 120, 156];
+
+    pub fn validate(&self) -> bool {
+        self.events & !Self::EVENT_ALL == 0
+            && (self.program != Pubkey::default() || (self.events == 0 && !self.required))
+    }
+
+    /// Whether the hook is invoked for the event
+    pub fn is_enabled(&self, event: PositionHookEvent) -> bool {
+        let flag = match event {
+            PositionHookEvent::Open => Self::EVENT_OPEN,
+            PositionHookEvent::Close => Self::EVENT_CLOSE,
+            PositionHookEvent::Liquidate => Self::EVENT_LIQUIDATE,
+        };
+        self.program != Pubkey::default() && self.events & flag != 0
+    }
+
+    /// Returns the compute units left in the transaction (unlimited off-chain)
+    pub fn get_remaining_compute_units() -> u64 {
+        #[cfg(target_os = "solana")]
+        {
+            solana_program::compute_units::sol_remaining_compute_units()
+        }
+
+        #[cfg(not(target_os = "solana"))]
+        {
+            u64::MAX
+        }
+    }
+
+    /// Returns the hook instruction data for a payload
+    pub fn get_instruction_data(payload: &PositionHookPayload) -> Result<Vec<u8>> {
+        let mut data = Self::INSTRUCTION_DISCRIMINATOR.to_vec();
+        payload.serialize(&mut data)?;
+        Ok(data)
+    }
+
+    /// Invokes the hook program for a position event
+    ///
+    /// # Arguments
+    /// * `hook_program` - Hook program account, if passed by the caller
+    /// * `hook_authority` - Hook authority PDA, if passed by the caller
+    /// * `hook_authority_bump` - Bump seed of the hook authority PDA
+    /// * `payload` - Event payload
+    ///
+    /// # Returns
+    /// Whether the hook was invoked
+    pub fn invoke<'info>(
+        &self,
+        hook_program: Option<&AccountInfo<'info>>,
+        hook_authority: Option<&AccountInfo<'info>>,
+        hook_authority_bump: Option<u8>,
+        payload: &PositionHookPayload,
+    ) -> Result<bool> {
+        if !self.is_enabled(payload.event) {
+            return Ok(false);
+        }
+
+        let (Some(hook_program), Some(hook_authority), Some(bump)) =
+            (hook_program, hook_authority, hook_authority_bump)
+        else {
+            require!(!self.required, PerpetualsError::PositionHookRequired);
+            return Ok(false);
+        };
+        require_keys_eq!(
+            hook_program.key(),
+            self.program,
+            PerpetualsError::InvalidPositionHook
+        );
+        require!(
+            hook_program.executable,
+            PerpetualsError::InvalidPositionHook
+        );
+
+        if Self::get_remaining_compute_units() < self.compute_budget as u64 {
+            require!(!self.required, PerpetualsError::PositionHookRequired);
+            msg!("Position hook skipped, not enough compute left");
+            return Ok(false);
+        }
+
+        let instruction = Instruction {
+            program_id: self.program,
+            accounts: vec![AccountMeta::new_readonly(hook_authority.key(), true)],
+            data: Self::get_instruction_data(payload)?,
+        };
+        let authority_seeds: &[&[&[u8]]] = &[&[b"hook_authority", &[bump]]];
+        invoke_signed(
+            &instruction,
+            &[hook_authority.clone(), hook_program.clone()],
+            authority_seeds,
+        )?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, solana_program::hash::hashv};
+
+    #[test]
+    fn test_position_hook() {
+        assert_eq!(
+            PositionHook::INSTRUCTION_DISCRIMINATOR,
+            hashv(&[b"global:on_position_event"]).to_bytes()[..8]
+        );
+
+        let mut hook = PositionHook::default();
+        assert!(hook.validate());
+        assert!(!hook.is_enabled(PositionHookEvent::Open));
+
+        hook.events = PositionHook::EVENT_OPEN | PositionHook::EVENT_LIQUIDATE;
+        assert!(!hook.validate());
+
+        hook.program = Pubkey::new_from_array([1; 32]);
+        assert!(hook.validate());
+        assert!(hook.is_enabled(PositionHookEvent::Open));
+        assert!(!hook.is_enabled(PositionHookEvent::Close));
+        assert!(hook.is_enabled(PositionHookEvent::Liquidate));
+
+        hook.events = 1 << 3;
+        assert!(!hook.validate());
+
+        // disabled hooks are skipped, required ones fail without the hook accounts
+        hook.events = PositionHook::EVENT_CLOSE;
+        let payload = PositionHookPayload {
+            event: PositionHookEvent::Open,
+            ..Default::default()
+        };
+        hook.required = true;
+        assert!(!hook.invoke(None, None, None, &payload).unwrap());
+        let payload = PositionHookPayload {
+            event: PositionHookEvent::Close,
+            ..payload
+        };
+        assert!(hook.invoke(None, None, None, &payload).is_err());
+        hook.required = false;
+        assert!(!hook.invoke(None, None, None, &payload).unwrap());
+
+        let data = PositionHook::get_instruction_data(&payload).unwrap();
+        assert_eq!(data.len(), 8 + 1 + 4 * 32 + 1 + 8 + 8 + 8);
+        assert_eq!(data[..8], PositionHook::INSTRUCTION_DISCRIMINATOR);
+    }
+}
@@ -0,0 +1,130 @@
+//! Position summary state
+//!
+//! Compact read-only mirror of a position for analytics. Scanning full Position
+//! accounts with getProgramAccounts is expensive at scale, the summary keeps only the
+//! fields indexers filter on and is updated by every instruction that mutates the
+//! position, if it was passed.
+
+use {
+    crate::{
+        math,
+        state::{
+            perpetuals::Perpetuals,
+            position::{Position, RiskTier, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::hash::hashv,
+};
+
+/// Position summary account
+///
+/// PDA derived from the position. Only positions whose summary was passed to
+/// open_position are mirrored, a closed position leaves a summary with side `None`.
+#[account]
+#[derive(Default, Debug)]
+pub struct PositionSummary {
+    /// First 8 bytes of the sha256 hash of the owner address (little endian)
+    pub owner_hash: u64,
+    /// Timestamp of the last position update
+    pub update_time: i64,
+    /// Index of the position custody in the pool custodies
+    pub custody_index: u8,
+    /// Position side (None once the position is closed)
+    pub side: Side,
+    /// Power multiplier of the position
+    pub power: u8,
+    /// Health bucket of the position
+    pub risk_tier: RiskTier,
+    /// Size bucket, bit length of the position size in whole USD (0 if under 1 USD)
+    pub size_bucket: u8,
+
+    /// Bump seed for the position summary PDA
+    pub bump: u8,
+}
+
+impl PositionSummary {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<PositionSummary>();
+
+    /// Returns the owner hash stored in summaries of the given owner
+    pub fn get_owner_hash(owner: &Pubkey) -> u64 {
+        let hash = hashv(&[owner.as_ref()]).to_bytes();
+        u64::from_le_bytes(hash[..8].try_into().unwrap())
+    }
+
+    /// Returns the size bucket of a position size
+    ///
+    /// # Arguments
+    /// * `size_usd` - Position size in USD (scaled to USD_DECIMALS)
+    pub fn get_size_bucket(size_usd: u64) -> Result<u8> {
+        let size = math::checked_div(size_usd, 10u64.pow(Perpetuals::USD_DECIMALS as u32))?;
+        Ok((u64::BITS - size.leading_zeros()) as u8)
+    }
+
+    /// Mirrors the current state of a position
+    ///
+    /// # Arguments
+    /// * `position` - Position after the mutation
+    /// * `custody_index` - Index of the position custody in the pool custodies
+    /// * `curtime` - Current timestamp
+    pub fn update(
+        &mut self,
+        position: &Position,
+        custody_index: usize,
+        curtime: i64,
+    ) -> Result<()> {
+        self.owner_hash = Self::get_owner_hash(&position.owner);
+        self.update_time = curtime;
+        self.custody_index = math::checked_as_u8(custody_index)?;
+        self.side = position.side;
+        self.power = position.power;
+        self.risk_tier = position.risk_tier;
+        self.size_bucket = Self::get_size_bucket(position.size_usd)?;
+        Ok(())
+    }
+
+    /// Marks the mirrored position as closed
+    pub fn clear(&mut self, curtime: i64) {
+        self.update_time = curtime;
+        self.side = Side::None;
+        self.risk_tier = RiskTier::Safe;
+        self.size_bucket = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_position_summary() {
+        assert_eq!(PositionSummary::get_size_bucket(999_999).unwrap(), 0);
+        assert_eq!(PositionSummary::get_size_bucket(1_000_000).unwrap(), 1);
+        assert_eq!(PositionSummary::get_size_bucket(3_999_999).unwrap(), 2);
+        assert_eq!(PositionSummary::get_size_bucket(1_000_000_000_000).unwrap(), 20);
+
+        let position = Position {
+            owner: Pubkey::new_from_array([1; 32]),
+            side: Side::Short,
+            power: 2,
+            risk_tier: RiskTier::Warning,
+            size_usd: 5_000_000_000,
+            ..Default::default()
+        };
+        let mut summary = PositionSummary::default();
+        summary.update(&position, 3, 100).unwrap();
+        assert_eq!(summary.owner_hash, PositionSummary::get_owner_hash(&position.owner));
+        assert_ne!(summary.owner_hash, 0);
+        assert_eq!(summary.custody_index, 3);
+        assert_eq!(summary.side, Side::Short);
+        assert_eq!(summary.power, 2);
+        assert_eq!(summary.risk_tier, RiskTier::Warning);
+        assert_eq!(summary.size_bucket, 13);
+
+        summary.clear(200);
+        assert_eq!(summary.side, Side::None);
+        assert_eq!(summary.size_bucket, 0);
+        assert_eq!(summary.update_time, 200);
+    }
+}
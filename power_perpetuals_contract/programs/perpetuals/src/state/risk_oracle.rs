@@ -0,0 +1,134 @@
+//! Risk oracle state
+//!
+//! The protocol can follow an external circuit-breaker feed that flags incidents such
+//! as a stablecoin depeg or a halted chain. While the feed reports an incident, or stops
+//! updating, risk-increasing operations (opening positions, swaps) are refused and
+//! risk-reducing ones keep working, without waiting for a multisig round.
+//!
+//! The feed account may be owned by any program. It must hold, at the configured data
+//! offset, the incident flags (u64, little endian) followed by the publish time (i64,
+//! little endian).
+
+use {crate::error::PerpetualsError, anchor_lang::prelude::*};
+
+/// Risk oracle configuration of the protocol
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct RiskOracle {
+    /// Circuit-breaker feed account (default = no risk oracle)
+    pub feed: Pubkey,
+    /// Offset of the incident flags in the feed account data
+    pub data_offset: u16,
+    /// Incident flags of the feed that halt risk-increasing operations
+    pub incident_mask: u64,
+    /// Maximum age of the feed before it is treated as an incident
+    pub max_age_sec: u32,
+}
+
+impl RiskOracle {
+    /// Size of the feed data read at the data offset (incident flags + publish time)
+    pub const DATA_LEN: usize = 16;
+
+    pub fn validate(&self) -> bool {
+        !self.is_enabled() || (self.incident_mask != 0 && self.max_age_sec > 0)
+    }
+
+    /// Whether the protocol follows a risk oracle
+    pub fn is_enabled(&self) -> bool {
+        self.feed != Pubkey::default()
+    }
+
+    /// Whether the feed data reports an incident or is stale
+    ///
+    /// # Arguments
+    /// * `data` - Feed account data
+    /// * `curtime` - Current time
+    pub fn is_incident(&self, data: &[u8], curtime: i64) -> Result<bool> {
+        let offset = self.data_offset as usize;
+        let Some(feed) = data.get(offset..offset + Self::DATA_LEN) else {
+            return err!(PerpetualsError::InvalidRiskOracleAccount);
+        };
+        let flags = u64::from_le_bytes(feed[..8].try_into().unwrap());
+        let publish_time = i64::from_le_bytes(feed[8..].try_into().unwrap());
+
+        if flags & self.incident_mask != 0 {
+            msg!("Risk oracle reports an incident: {}", flags);
+            return Ok(true);
+        }
+        if curtime.saturating_sub(publish_time) > self.max_age_sec as i64 {
+            msg!("Risk oracle is stale, last update: {}", publish_time);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Checks that risk-increasing operations are allowed by the risk oracle
+    ///
+    /// # Arguments
+    /// * `feed_account` - Feed account, required if the risk oracle is enabled
+    /// * `curtime` - Current time
+    pub fn check_risk_increase(
+        &self,
+        feed_account: Option<&AccountInfo>,
+        curtime: i64,
+    ) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let Some(feed_account) = feed_account else {
+            return err!(PerpetualsError::InvalidRiskOracleAccount);
+        };
+        require_keys_eq!(
+            feed_account.key(),
+            self.feed,
+            PerpetualsError::InvalidRiskOracleAccount
+        );
+        require!(
+            !self.is_incident(&feed_account.try_borrow_data()?, curtime)?,
+            PerpetualsError::RiskOracleIncident
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_feed_data(offset: usize, flags: u64, publish_time: i64) -> Vec<u8> {
+        let mut data = vec![0; offset];
+        data.extend_from_slice(&flags.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_risk_oracle() {
+        let mut oracle = RiskOracle::default();
+        assert!(oracle.validate());
+        assert!(oracle.check_risk_increase(None, 0).is_ok());
+
+        oracle.feed = Pubkey::new_from_array([1; 32]);
+        assert!(!oracle.validate());
+        assert!(oracle.check_risk_increase(None, 0).is_err());
+
+        oracle.data_offset = 8;
+        oracle.incident_mask = 0b101;
+        oracle.max_age_sec = 60;
+        assert!(oracle.validate());
+
+        // only masked flags halt trading
+        let data = get_feed_data(8, 0, 1_000);
+        assert!(!oracle.is_incident(&data, 1_060).unwrap());
+        let data = get_feed_data(8, 0b010, 1_000);
+        assert!(!oracle.is_incident(&data, 1_000).unwrap());
+        let data = get_feed_data(8, 0b100, 1_000);
+        assert!(oracle.is_incident(&data, 1_000).unwrap());
+
+        // a stale feed counts as an incident
+        let data = get_feed_data(8, 0, 1_000);
+        assert!(oracle.is_incident(&data, 1_061).unwrap());
+
+        // feed data too short
+        assert!(oracle.is_incident(&data[..23], 1_000).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Staged instruction parameters
+//!
+//! Admin payloads such as AddCustodyParams of a pool with many custodies can exceed the
+//! transaction size limit. Admins write the serialized params into a scratch account
+//! over several stage_params transactions, and the *_from_staged instruction reads them
+//! from the account instead of the instruction data. The account is zero-copy, chunks
+//! are copied as raw bytes and only deserialized once by the executing instruction.
+
+use {crate::error::PerpetualsError, anchor_lang::prelude::*};
+
+/// Scratch account holding serialized instruction params
+///
+/// PDA derived from the pool the params apply to.
+#[repr(C, packed)]
+#[account(zero_copy)]
+pub struct StagedParams {
+    /// Pool the params apply to
+    pub pool: Pubkey,
+    /// Number of bytes staged so far
+    pub data_len: u32,
+    /// Bump seed for the staged params PDA
+    pub bump: u8,
+    /// Serialized params, only the first data_len bytes are valid
+    /// (split in pages, bytemuck only implements Pod for arrays up to 4096 bytes)
+    pub data: [[u8; 4096]; 2], // StagedParams::MAX_DATA_LEN
+}
+
+impl StagedParams {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<StagedParams>();
+    /// Maximum size of the staged params
+    pub const MAX_DATA_LEN: usize = 2 * 4096;
+
+    /// Writes a chunk of params
+    ///
+    /// Chunks must be written in order, a chunk at offset 0 restarts staging.
+    ///
+    /// # Arguments
+    /// * `offset` - Offset of the chunk in the serialized params
+    /// * `chunk` - Chunk bytes
+    pub fn write(&mut self, offset: u32, chunk: &[u8]) -> Result<()> {
+        if offset == 0 {
+            self.data_len = 0;
+        }
+        require_eq!(offset, self.data_len, PerpetualsError::InvalidStagedParams);
+        let start = offset as usize;
+        let end = start + chunk.len();
+        require!(
+            end <= Self::MAX_DATA_LEN,
+            PerpetualsError::InvalidStagedParams
+        );
+        self.data.as_flattened_mut()[start..end].copy_from_slice(chunk);
+        self.data_len = end as u32;
+        Ok(())
+    }
+
+    /// Deserializes the staged params, fails unless they were fully staged
+    pub fn get_params<T: AnchorDeserialize>(&self) -> Result<T> {
+        let data_len = self.data_len as usize;
+        T::try_from_slice(&self.data.as_flattened()[..data_len])
+            .map_err(|_| PerpetualsError::InvalidStagedParams.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_staged_params() {
+        let params: Vec<u64> = (0..100).collect();
+        let mut data = vec![];
+        params.serialize(&mut data).unwrap();
+
+        let mut staged: StagedParams = bytemuck::Zeroable::zeroed();
+        let (first, rest) = data.split_at(300);
+        staged.write(0, first).unwrap();
+        assert!(staged.get_params::<Vec<u64>>().is_err());
+
+        // chunks must be written in order
+        assert!(staged.write(301, rest).is_err());
+        staged.write(300, rest).unwrap();
+        assert_eq!(staged.get_params::<Vec<u64>>().unwrap(), params);
+
+        // staging restarts at offset 0
+        staged.write(0, &data[..4]).unwrap();
+        let data_len = staged.data_len;
+        assert_eq!(data_len, 4);
+        assert!(staged
+            .write(4, &[0; StagedParams::MAX_DATA_LEN - 3])
+            .is_err());
+    }
+}
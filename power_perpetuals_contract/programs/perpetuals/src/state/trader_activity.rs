@@ -0,0 +1,77 @@
+//! Trader activity state
+//!
+//! Counts the position opens and closes of an owner in the current slot, so the pool
+//! can cap how many a single signer makes per slot. Opening and closing within the
+//! same slot with flash-loaned funds is the typical way to farm oracle latency.
+
+use {
+    crate::{error::PerpetualsError, math},
+    anchor_lang::prelude::*,
+};
+
+/// Trader activity counter account
+///
+/// PDA derived from the pool and the owner. The counter resets lazily on the first
+/// trade in a new slot.
+#[account]
+#[derive(Default, Debug)]
+pub struct TraderActivity {
+    /// Pool the counter applies to
+    pub pool: Pubkey,
+    /// Owner of the positions
+    pub owner: Pubkey,
+    /// Slot of the last counted trade
+    pub slot: u64,
+    /// Number of opens and closes in that slot
+    pub trades: u16,
+
+    /// Bump seed for the counter PDA
+    pub bump: u8,
+}
+
+impl TraderActivity {
+    /// Account size in bytes (8 byte discriminator + data)
+    pub const LEN: usize = 8 + std::mem::size_of::<TraderActivity>();
+
+    /// Count a position open or close, failing if the owner is at the pool cap
+    ///
+    /// # Arguments
+    /// * `slot` - Current slot
+    /// * `max_trades_per_slot` - Pool cap on trades per owner and slot (0 = unlimited)
+    pub fn record_trade(&mut self, slot: u64, max_trades_per_slot: u16) -> Result<()> {
+        if self.slot != slot {
+            self.slot = slot;
+            self.trades = 0;
+        }
+        require!(
+            max_trades_per_slot == 0 || self.trades < max_trades_per_slot,
+            PerpetualsError::MaxTradesPerSlot
+        );
+        self.trades = math::checked_add(self.trades, 1)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_trade_rate_limit() {
+        let mut activity = TraderActivity::default();
+        activity.record_trade(10, 2).unwrap();
+        activity.record_trade(10, 2).unwrap();
+        assert!(activity.record_trade(10, 2).is_err());
+        assert_eq!(activity.trades, 2);
+
+        // lazy reset in a new slot
+        activity.record_trade(11, 2).unwrap();
+        assert_eq!(activity.slot, 11);
+        assert_eq!(activity.trades, 1);
+
+        // uncapped pool
+        activity.record_trade(11, 0).unwrap();
+        activity.record_trade(11, 0).unwrap();
+        assert_eq!(activity.trades, 3);
+    }
+}
@@ -0,0 +1,76 @@
+//! Off-chain usage of the crate: account layouts, PDAs and pricing math
+//! available to keepers and quoting services depending on the `client` feature.
+
+use {
+    anchor_lang::{prelude::*, AccountDeserialize, AccountSerialize},
+    perpetuals::{
+        conversions,
+        math::RoundingDirection,
+        pda,
+        state::{
+            oracle::OraclePrice,
+            owner_positions::OwnerPositions,
+            position::{Position, Side},
+        },
+    },
+};
+
+#[test]
+fn test_pda_derivation() {
+    let (pool, _) = pda::find_pool_address("test pool");
+    assert_eq!(pool, pda::find_pool_address("test pool").0);
+    assert_ne!(pool, pda::find_pool_address("other pool").0);
+
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (custody, _) = pda::find_custody_address(&pool, &mint);
+    assert_ne!(custody, pda::find_custody_token_account_address(&pool, &mint).0);
+
+    let (long, _) = pda::find_position_address(&owner, &pool, &custody, Side::Long);
+    let (short, _) = pda::find_position_address(&owner, &pool, &custody, Side::Short);
+    assert_ne!(long, short);
+    assert!(!long.is_on_curve());
+
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"owner_positions", pool.as_ref(), owner.as_ref()],
+        &perpetuals::ID,
+    );
+    assert_eq!(
+        pda::find_owner_positions_address(&pool, &owner),
+        (expected, bump)
+    );
+}
+
+#[test]
+fn test_account_round_trip() {
+    let position = Position {
+        owner: Pubkey::new_unique(),
+        side: Side::Short,
+        power: 2,
+        size_usd: 1_000_000_000,
+        ..Default::default()
+    };
+    let mut data = Vec::with_capacity(Position::LEN);
+    position.try_serialize(&mut data).unwrap();
+    assert!(data.len() <= Position::LEN);
+
+    let decoded = Position::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(decoded.owner, position.owner);
+    assert_eq!(decoded.side, Side::Short);
+    assert_eq!(decoded.size_usd, position.size_usd);
+
+    // discriminators keep account types apart
+    assert!(OwnerPositions::try_deserialize(&mut data.as_slice()).is_err());
+}
+
+#[test]
+fn test_pricing_math() {
+    let price = OraclePrice::new(15_000, -2);
+    assert_eq!(
+        price
+            .get_asset_amount_usd(1_000_000_000, 9, RoundingDirection::Down)
+            .unwrap(),
+        conversions::token_to_usd(1_000_000_000, 9, 15_000, -2).unwrap()
+    );
+    assert_eq!(conversions::apply_bps_ceil(1_000_000, 30).unwrap(), 3_000);
+}
@@ -0,0 +1,281 @@
+//! close_position: the remaining collateral is paid to the owner ATA, which is created
+//! when it doesn't exist yet and the accounts to create it are provided.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::{
+        associated_token::{self, get_associated_token_address},
+        token::{spl_token, TokenAccount},
+    },
+    common::{
+        get_account_info, get_custody, get_mint_account, get_oracle_account, get_perpetuals,
+        get_pool, get_program_account, get_signer_account, get_token_account,
+        get_unchecked_account, read_account, set_time, take_created_accounts, take_token_cpis,
+        TokenCpi,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::close_position::{
+            self, ClosePosition, ClosePositionBumps, ClosePositionParams,
+        },
+        pda,
+        state::{
+            custody::Custody,
+            position::{Position, Side},
+        },
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+struct Fixture {
+    accounts: &'static [AccountInfo<'static>],
+    owner: Pubkey,
+    usdc_mint: Pubkey,
+    collateral_custody_token_account: Pubkey,
+}
+
+/// Builds a $1,000 short SOL position opened at $100 with 100 USDC of collateral. The
+/// remaining collateral is paid to `receiving_account`, the mint and associated token
+/// program to create it are only provided with `create_accounts`.
+fn get_fixture(
+    owner: Pubkey,
+    usdc_mint: Pubkey,
+    receiving_account: AccountInfo<'static>,
+    create_accounts: bool,
+) -> Fixture {
+    let sol_mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (pool_key, _) = pda::find_pool_address("pool");
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &sol_mint);
+    let (collateral_custody_key, _) = pda::find_custody_address(&pool_key, &usdc_mint);
+    let (position_key, position_bump) =
+        pda::find_position_address(&owner, &pool_key, &custody_key, Side::Short);
+
+    let pool = get_pool("pool", vec![custody_key, collateral_custody_key]);
+    let mut custody = get_custody(pool_key, sol_mint, 9, 1_000_000_000_000);
+    custody.short_positions.open_positions = 1;
+    let mut collateral_custody = get_custody(pool_key, usdc_mint, 6, 100_000_000_000);
+    collateral_custody.is_stable = true;
+    collateral_custody.assets.collateral = 100_000_000;
+    collateral_custody.assets.locked = 1_000_000_000;
+    let position = Position {
+        owner,
+        pool: pool_key,
+        custody: custody_key,
+        collateral_custody: collateral_custody_key,
+        open_time: CURTIME - 3_600,
+        update_time: CURTIME - 3_600,
+        side: Side::Short,
+        power: 1,
+        price: 100_000_000,
+        size_usd: 1_000_000_000,
+        collateral_usd: 100_000_000,
+        locked_amount: 1_000_000_000,
+        collateral_amount: 100_000_000,
+        bump: position_bump,
+        ..Default::default()
+    };
+    let optional_account = |account: AccountInfo<'static>| {
+        if create_accounts {
+            account
+        } else {
+            get_unchecked_account(perpetuals::ID, true)
+        }
+    };
+
+    let accounts = vec![
+        get_signer_account(owner),
+        receiving_account,
+        get_unchecked_account(transfer_authority_key, false),
+        get_program_account(perpetuals_key, &get_perpetuals()),
+        get_program_account(pool_key, &pool),
+        get_program_account(position_key, &position),
+        get_program_account(custody_key, &custody),
+        get_oracle_account(custody.oracle.oracle_account, 100_000_000, -6, CURTIME),
+        get_program_account(collateral_custody_key, &collateral_custody),
+        get_oracle_account(
+            collateral_custody.oracle.oracle_account,
+            1_000_000,
+            -6,
+            CURTIME,
+        ),
+        get_token_account(
+            collateral_custody.token_account,
+            usdc_mint,
+            transfer_authority_key,
+            100_100_000_000,
+        ),
+        get_unchecked_account(spl_token::ID, true),
+        get_unchecked_account(System::id(), true),
+        // market_maker, owner_positions, trader_activity, position_summary, pending_claim,
+        // hook_program and hook_authority are not provided
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        optional_account(get_mint_account(usdc_mint, Pubkey::new_unique(), 0, 6)),
+        optional_account(get_unchecked_account(associated_token::ID, true)),
+    ];
+
+    Fixture {
+        accounts: Box::leak(accounts.into_boxed_slice()),
+        owner,
+        usdc_mint,
+        collateral_custody_token_account: collateral_custody.token_account,
+    }
+}
+
+fn close_position(fixture: &Fixture) -> Result<()> {
+    let params = ClosePositionParams { price: 100_000_000 };
+    let mut infos = fixture.accounts;
+    let mut bumps = ClosePositionBumps::default();
+    let mut accounts = ClosePosition::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    close_position::close_position(
+        Context::new(&perpetuals::ID, &mut accounts, &[], bumps),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)
+}
+
+fn get_empty_account(key: Pubkey) -> AccountInfo<'static> {
+    get_account_info(key, Pubkey::default(), false, true, false, vec![])
+}
+
+#[test]
+fn test_close_position_creates_receiving_account() {
+    set_time(CURTIME);
+    let owner = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let owner_ata = get_associated_token_address(&owner, &usdc_mint);
+    let fixture = get_fixture(owner, usdc_mint, get_empty_account(owner_ata), true);
+
+    // the price didn't move, the whole collateral is paid to the new owner ATA
+    close_position(&fixture).unwrap();
+
+    assert_eq!(take_created_accounts(), vec![owner_ata]);
+    assert_eq!(
+        take_token_cpis(),
+        vec![TokenCpi::Transfer {
+            from: fixture.collateral_custody_token_account,
+            to: owner_ata,
+            amount: 100_000_000,
+        }]
+    );
+    let receiving_account: TokenAccount = read_account(&fixture.accounts[1]);
+    assert_eq!(
+        (receiving_account.owner, receiving_account.mint),
+        (fixture.owner, fixture.usdc_mint)
+    );
+
+    let collateral_custody: Custody = read_account(&fixture.accounts[8]);
+    assert_eq!(collateral_custody.assets.collateral, 0);
+    assert_eq!(collateral_custody.assets.locked, 0);
+    assert_eq!(collateral_custody.assets.owned, 100_000_000_000);
+}
+
+#[test]
+fn test_close_position_existing_receiving_account() {
+    set_time(CURTIME);
+    let owner = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+
+    // an existing account is paid without the accounts to create it, and doesn't have
+    // to be the owner ATA
+    let receiving_account = Pubkey::new_unique();
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_token_account(receiving_account, usdc_mint, owner, 0),
+        false,
+    );
+    close_position(&fixture).unwrap();
+
+    assert!(take_created_accounts().is_empty());
+    assert_eq!(
+        take_token_cpis(),
+        vec![TokenCpi::Transfer {
+            from: fixture.collateral_custody_token_account,
+            to: receiving_account,
+            amount: 100_000_000,
+        }]
+    );
+}
+
+#[test]
+fn test_close_position_receiving_account_errors() {
+    set_time(CURTIME);
+    let owner = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let owner_ata = get_associated_token_address(&owner, &usdc_mint);
+
+    // a missing account can't be created without the mint and associated token program
+    let fixture = get_fixture(owner, usdc_mint, get_empty_account(owner_ata), false);
+    assert_eq!(
+        close_position(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+
+    // only the owner ATA can be created, the instruction misses any other account
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_empty_account(Pubkey::new_unique()),
+        true,
+    );
+    assert_eq!(
+        close_position(&fixture),
+        Err(ProgramError::NotEnoughAccountKeys.into())
+    );
+    take_created_accounts();
+
+    // existing accounts must hold the collateral mint for the owner
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_token_account(owner_ata, usdc_mint, Pubkey::new_unique(), 0),
+        true,
+    );
+    assert_eq!(
+        close_position(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_token_account(owner_ata, Pubkey::new_unique(), owner, 0),
+        true,
+    );
+    assert_eq!(
+        close_position(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+
+    // accounts not owned by the token program are rejected
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_account_info(owner_ata, perpetuals::ID, false, true, false, vec![0; 165]),
+        true,
+    );
+    assert_eq!(
+        close_position(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+
+    assert!(take_created_accounts().is_empty());
+    assert!(take_token_cpis().is_empty());
+}
@@ -0,0 +1,186 @@
+//! Account validation of the collateral instructions: the user token account must hold
+//! the mint of the position's collateral custody.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::token::spl_token,
+    common::{get_program_account, get_signer_account, get_token_account, get_unchecked_account},
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::{
+            add_collateral::{AddCollateral, AddCollateralBumps, AddCollateralParams},
+            remove_collateral::{RemoveCollateral, RemoveCollateralBumps, RemoveCollateralParams},
+        },
+        pda,
+        state::{
+            custody::Custody,
+            oracle::OracleParams,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{CollateralAmount, Position, Side},
+        },
+    },
+    std::collections::BTreeSet,
+};
+
+struct Fixture {
+    owner: Pubkey,
+    custody_mint: Pubkey,
+    collateral_mint: Pubkey,
+}
+
+/// Builds the accounts of a short position on the custody mint, collateralized in the
+/// collateral mint, with the user token account holding `user_mint`.
+fn get_accounts(fixture: &Fixture, user_mint: Pubkey) -> &'static [AccountInfo<'static>] {
+    let (perpetuals_key, perpetuals_bump) = pda::find_perpetuals_address();
+    let (transfer_authority_key, transfer_authority_bump) = pda::find_transfer_authority_address();
+    let (pool_key, pool_bump) = pda::find_pool_address("pool");
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &fixture.custody_mint);
+    let (collateral_custody_key, _) =
+        pda::find_custody_address(&pool_key, &fixture.collateral_mint);
+    let (token_account_key, token_account_bump) =
+        pda::find_custody_token_account_address(&pool_key, &fixture.collateral_mint);
+    let (position_key, position_bump) =
+        pda::find_position_address(&fixture.owner, &pool_key, &custody_key, Side::Short);
+    let custody_oracle = Pubkey::new_unique();
+    let collateral_oracle = Pubkey::new_unique();
+
+    let perpetuals = Perpetuals {
+        perpetuals_bump,
+        transfer_authority_bump,
+        ..Default::default()
+    };
+    let pool = Pool {
+        name: "pool".to_string(),
+        custodies: vec![custody_key, collateral_custody_key],
+        bump: pool_bump,
+        ..Default::default()
+    };
+    let position = Position {
+        owner: fixture.owner,
+        pool: pool_key,
+        custody: custody_key,
+        collateral_custody: collateral_custody_key,
+        side: Side::Short,
+        bump: position_bump,
+        ..Default::default()
+    };
+    let custody = Custody {
+        pool: pool_key,
+        mint: fixture.custody_mint,
+        oracle: OracleParams {
+            oracle_account: custody_oracle,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let collateral_custody = Custody {
+        pool: pool_key,
+        mint: fixture.collateral_mint,
+        token_account: token_account_key,
+        oracle: OracleParams {
+            oracle_account: collateral_oracle,
+            ..Default::default()
+        },
+        token_account_bump,
+        ..Default::default()
+    };
+
+    let accounts = vec![
+        get_signer_account(fixture.owner),
+        get_token_account(
+            Pubkey::new_unique(),
+            user_mint,
+            fixture.owner,
+            1_000_000_000,
+        ),
+        get_unchecked_account(transfer_authority_key, false),
+        get_program_account(perpetuals_key, &perpetuals),
+        get_program_account(pool_key, &pool),
+        get_program_account(position_key, &position),
+        get_program_account(custody_key, &custody),
+        get_unchecked_account(custody_oracle, false),
+        get_program_account(collateral_custody_key, &collateral_custody),
+        get_unchecked_account(collateral_oracle, false),
+        get_token_account(
+            token_account_key,
+            fixture.collateral_mint,
+            transfer_authority_key,
+            1_000_000_000,
+        ),
+        get_unchecked_account(spl_token::ID, true),
+        // position_summary is not provided
+        get_unchecked_account(perpetuals::ID, true),
+    ];
+    Box::leak(accounts.into_boxed_slice())
+}
+
+fn get_fixture() -> Fixture {
+    Fixture {
+        owner: Pubkey::new_unique(),
+        custody_mint: Pubkey::new_unique(),
+        collateral_mint: Pubkey::new_unique(),
+    }
+}
+
+fn try_add_collateral(accounts: &'static [AccountInfo<'static>]) -> Result<()> {
+    let mut accounts = accounts;
+    let params = AddCollateralParams {
+        collateral: CollateralAmount::Tokens(1_000_000),
+    };
+    AddCollateral::try_accounts(
+        &perpetuals::ID,
+        &mut accounts,
+        &params.try_to_vec().unwrap(),
+        &mut AddCollateralBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .map(|_| ())
+}
+
+fn try_remove_collateral(accounts: &'static [AccountInfo<'static>]) -> Result<()> {
+    let mut accounts = accounts;
+    let params = RemoveCollateralParams {
+        collateral: CollateralAmount::Usd(1_000_000),
+    };
+    RemoveCollateral::try_accounts(
+        &perpetuals::ID,
+        &mut accounts,
+        &params.try_to_vec().unwrap(),
+        &mut RemoveCollateralBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn test_add_collateral_mint() {
+    let fixture = get_fixture();
+
+    assert_eq!(
+        try_add_collateral(get_accounts(&fixture, fixture.custody_mint)),
+        Err(PerpetualsError::InvalidCollateralMint.into())
+    );
+    assert_eq!(
+        try_add_collateral(get_accounts(&fixture, Pubkey::new_unique())),
+        Err(PerpetualsError::InvalidCollateralMint.into())
+    );
+    assert!(try_add_collateral(get_accounts(&fixture, fixture.collateral_mint)).is_ok());
+}
+
+#[test]
+fn test_remove_collateral_mint() {
+    let fixture = get_fixture();
+
+    assert_eq!(
+        try_remove_collateral(get_accounts(&fixture, fixture.custody_mint)),
+        Err(PerpetualsError::InvalidCollateralMint.into())
+    );
+    assert_eq!(
+        try_remove_collateral(get_accounts(&fixture, Pubkey::new_unique())),
+        Err(PerpetualsError::InvalidCollateralMint.into())
+    );
+    assert!(try_remove_collateral(get_accounts(&fixture, fixture.collateral_mint)).is_ok());
+}
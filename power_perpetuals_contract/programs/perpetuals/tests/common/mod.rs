@@ -0,0 +1,367 @@
+//! Shared fixtures of the integration tests. Instruction handlers run on the host with
+//! the clock and CPI syscalls stubbed: the clock returns the time set by the test and
+//! CPIs are recorded instead of executed, so tests can check the transferred amounts.
+//! Associated token accounts are initialized by the stubs, so handlers can load them.
+
+#![allow(dead_code)]
+
+use {
+    anchor_lang::{
+        prelude::*,
+        solana_program::{instruction::Instruction, program_pack::Pack},
+        AccountSerialize, Discriminator,
+    },
+    anchor_spl::{
+        associated_token,
+        token::spl_token::{self, instruction::TokenInstruction},
+    },
+    perpetuals::{
+        pda,
+        state::{
+            custody::{Assets, Custody, Fees, FeesMode},
+            multisig::Multisig,
+            oracle::{CustomOracle, OracleParams, OracleType},
+            perpetuals::{Permissions, Perpetuals},
+            pool::{Pool, TokenRatios},
+        },
+    },
+    solana_program::{
+        entrypoint::ProgramResult,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+    },
+    std::{
+        cell::{Cell, RefCell},
+        sync::Once,
+    },
+};
+
+thread_local! {
+    static TIME: Cell<i64> = const { Cell::new(0) };
+    static INSTRUCTIONS: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+}
+
+static STUBS: Once = Once::new();
+
+struct TestStubs;
+
+impl SyscallStubs for TestStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: TIME.get(),
+            ..Default::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INSTRUCTIONS.with_borrow_mut(|instructions| instructions.push(instruction.clone()));
+        if instruction.program_id == associated_token::ID {
+            create_associated_token_account(instruction, account_infos)?;
+        }
+        Ok(())
+    }
+}
+
+/// Initializes an empty associated token account like the associated token program
+fn create_associated_token_account(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+) -> ProgramResult {
+    let [_, account, wallet, mint, ..] = &instruction.accounts[..] else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let account_info = account_infos
+        .iter()
+        .find(|account_info| *account_info.key == account.pubkey)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !account_info.data_is_empty() {
+        return Ok(());
+    }
+
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: mint.pubkey,
+        owner: wallet.pubkey,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    *account_info.try_borrow_mut_data()? = Box::leak(data.into_boxed_slice());
+    account_info.assign(&spl_token::ID);
+    Ok(())
+}
+
+/// Token program CPIs recorded by the stubs
+#[derive(Debug, PartialEq)]
+pub enum TokenCpi {
+    Transfer {
+        from: Pubkey,
+        to: Pubkey,
+        amount: u64,
+    },
+    MintTo {
+        account: Pubkey,
+        amount: u64,
+    },
+    Burn {
+        account: Pubkey,
+        amount: u64,
+    },
+}
+
+/// Installs the syscall stubs and sets the clock of the current test thread.
+pub fn set_time(time: i64) {
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(TestStubs));
+    });
+    TIME.set(time);
+}
+
+/// Returns and clears the CPIs recorded on the current test thread.
+pub fn take_instructions() -> Vec<Instruction> {
+    INSTRUCTIONS.take()
+}
+
+/// Returns and clears the associated token accounts created on the current test thread.
+pub fn take_created_accounts() -> Vec<Pubkey> {
+    INSTRUCTIONS.with_borrow_mut(|instructions| {
+        let (created, rest) = instructions
+            .drain(..)
+            .partition::<Vec<_>, _>(|instruction| instruction.program_id == associated_token::ID);
+        *instructions = rest;
+        created
+            .iter()
+            .map(|instruction| instruction.accounts[1].pubkey)
+            .collect()
+    })
+}
+
+/// Returns and clears the token program CPIs recorded on the current test thread.
+pub fn take_token_cpis() -> Vec<TokenCpi> {
+    take_instructions()
+        .iter()
+        .filter(|instruction| instruction.program_id == spl_token::ID)
+        .map(|instruction| {
+            let keys: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect();
+            match TokenInstruction::unpack(&instruction.data).unwrap() {
+                TokenInstruction::Transfer { amount } => TokenCpi::Transfer {
+                    from: keys[0],
+                    to: keys[1],
+                    amount,
+                },
+                TokenInstruction::MintTo { amount } => TokenCpi::MintTo {
+                    account: keys[1],
+                    amount,
+                },
+                TokenInstruction::Burn { amount } => TokenCpi::Burn {
+                    account: keys[0],
+                    amount,
+                },
+                other => panic!("unexpected token instruction {:?}", other),
+            }
+        })
+        .collect()
+}
+
+pub fn get_account_info(
+    key: Pubkey,
+    owner: Pubkey,
+    is_signer: bool,
+    is_writable: bool,
+    executable: bool,
+    data: Vec<u8>,
+) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        is_writable,
+        Box::leak(Box::new(1_000_000_000)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(owner)),
+        executable,
+        0,
+    )
+}
+
+pub fn get_signer_account(key: Pubkey) -> AccountInfo<'static> {
+    get_account_info(key, Pubkey::default(), true, true, false, vec![])
+}
+
+pub fn get_unchecked_account(key: Pubkey, executable: bool) -> AccountInfo<'static> {
+    get_account_info(key, Pubkey::default(), false, false, executable, vec![])
+}
+
+pub fn get_program_account<T: AccountSerialize>(key: Pubkey, account: &T) -> AccountInfo<'static> {
+    let mut data = vec![];
+    account.try_serialize(&mut data).unwrap();
+    get_account_info(key, perpetuals::ID, false, true, false, data)
+}
+
+pub fn get_token_account(
+    key: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> AccountInfo<'static> {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    get_account_info(key, spl_token::ID, false, true, false, data)
+}
+
+pub fn get_mint_account(
+    key: Pubkey,
+    authority: Pubkey,
+    supply: u64,
+    decimals: u8,
+) -> AccountInfo<'static> {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: Some(authority).into(),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: None.into(),
+    }
+    .pack_into_slice(&mut data);
+    get_account_info(key, spl_token::ID, false, true, false, data)
+}
+
+/// Multisig of the given admins, `min_signatures` of them have to sign
+pub fn get_multisig_account(signers: &[Pubkey], min_signatures: u8) -> AccountInfo<'static> {
+    let (key, bump) = pda::find_multisig_address();
+    let mut multisig = Multisig {
+        num_signers: signers.len() as u8,
+        min_signatures,
+        bump,
+        ..Default::default()
+    };
+    multisig.signers[..signers.len()].copy_from_slice(signers);
+    let mut data = Multisig::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&multisig));
+    get_account_info(key, perpetuals::ID, false, true, false, data)
+}
+
+/// Custom oracle with the same spot and EMA price, published at `publish_time`
+pub fn get_oracle_account(
+    key: Pubkey,
+    price: u64,
+    expo: i32,
+    publish_time: i64,
+) -> AccountInfo<'static> {
+    let oracle = CustomOracle {
+        price,
+        expo,
+        conf: 0,
+        ema: price,
+        publish_time,
+    };
+    let mut data = vec![];
+    oracle.try_serialize(&mut data).unwrap();
+    get_account_info(key, perpetuals::ID, false, false, false, data)
+}
+
+pub fn get_permissions() -> Permissions {
+    Permissions {
+        allow_swap: true,
+        allow_add_liquidity: true,
+        allow_remove_liquidity: true,
+        allow_open_position: true,
+        allow_close_position: true,
+        allow_pnl_withdrawal: true,
+        allow_collateral_withdrawal: true,
+        allow_size_change: true,
+    }
+}
+
+pub fn get_perpetuals() -> Perpetuals {
+    let (_, perpetuals_bump) = pda::find_perpetuals_address();
+    let (_, transfer_authority_bump) = pda::find_transfer_authority_address();
+    Perpetuals {
+        permissions: get_permissions(),
+        perpetuals_bump,
+        transfer_authority_bump,
+        ..Default::default()
+    }
+}
+
+/// Custody priced by a custom oracle, with fixed 1% swap and remove liquidity fees of
+/// which the protocol keeps 25%
+pub fn get_custody(pool: Pubkey, mint: Pubkey, decimals: u8, owned: u64) -> Custody {
+    let (_, bump) = pda::find_custody_address(&pool, &mint);
+    let (token_account, token_account_bump) = pda::find_custody_token_account_address(&pool, &mint);
+    Custody {
+        pool,
+        mint,
+        token_account,
+        decimals,
+        oracle: OracleParams {
+            oracle_account: Pubkey::new_unique(),
+            oracle_type: OracleType::Custom,
+            max_price_error: 100,
+            max_price_age_sec: 60,
+            ..Default::default()
+        },
+        permissions: get_permissions(),
+        fees: Fees {
+            mode: FeesMode::Fixed,
+            swap_in: 100,
+            swap_out: 100,
+            remove_liquidity: 100,
+            protocol_share: 2_500,
+            ..Default::default()
+        },
+        assets: Assets {
+            owned,
+            ..Default::default()
+        },
+        bump,
+        token_account_bump,
+        ..Default::default()
+    }
+}
+
+/// Pool of the given custodies, none of them is constrained by its token ratio
+pub fn get_pool(name: &str, custodies: Vec<Pubkey>) -> Pool {
+    let (pool, bump) = pda::find_pool_address(name);
+    let (_, lp_token_bump) = pda::find_lp_token_mint_address(&pool);
+    let ratios = TokenRatios {
+        target: 5_000,
+        min: 0,
+        max: 10_000,
+    };
+    Pool {
+        name: name.to_string(),
+        ratios: vec![ratios; custodies.len()],
+        custodies,
+        bump,
+        lp_token_bump,
+        ..Default::default()
+    }
+}
+
+/// Deserializes a program account, e.g. after the handler accounts were exited
+pub fn read_account<T: AccountDeserialize>(account: &AccountInfo) -> T {
+    T::try_deserialize(&mut &account.data.borrow()[..]).unwrap()
+}
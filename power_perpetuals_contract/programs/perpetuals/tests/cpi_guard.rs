@@ -0,0 +1,52 @@
+//! CPI guard of the trading instructions. The stack height syscall is stubbed for the
+//! whole test binary, so this file holds a single test.
+
+use {
+    perpetuals::state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    solana_program::program_stubs::{set_syscall_stubs, SyscallStubs},
+};
+
+struct CpiStubs;
+
+impl SyscallStubs for CpiStubs {
+    fn sol_get_stack_height(&self) -> u64 {
+        2
+    }
+}
+
+#[test]
+fn test_cpi_through_disallowed_pool() {
+    let mut perpetuals = Perpetuals {
+        allow_cpi: true,
+        ..Default::default()
+    };
+    let mut pool = Pool {
+        allow_cpi: true,
+        ..Default::default()
+    };
+    let mut custody = Custody {
+        allow_cpi: true,
+        ..Default::default()
+    };
+
+    // top-level invocations are always allowed
+    pool.allow_cpi = false;
+    assert!(!Perpetuals::is_cpi());
+    assert!(perpetuals.check_cpi_allowed(&pool, &custody));
+
+    set_syscall_stubs(Box::new(CpiStubs));
+    assert!(Perpetuals::is_cpi());
+
+    // a CPI through a disallowed pool is refused even if the custody allows it
+    assert!(!perpetuals.check_cpi_allowed(&pool, &custody));
+
+    pool.allow_cpi = true;
+    assert!(perpetuals.check_cpi_allowed(&pool, &custody));
+
+    custody.allow_cpi = false;
+    assert!(!perpetuals.check_cpi_allowed(&pool, &custody));
+
+    custody.allow_cpi = true;
+    perpetuals.allow_cpi = false;
+    assert!(!perpetuals.check_cpi_allowed(&pool, &custody));
+}
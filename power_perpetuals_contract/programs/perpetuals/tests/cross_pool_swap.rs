@@ -0,0 +1,220 @@
+//! cross_pool_swap: the input is deposited in the first pool, the stable tokens of the
+//! first leg move to the second pool, and the user receives the output of the second leg.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::token::spl_token,
+    common::{
+        get_custody, get_oracle_account, get_perpetuals, get_pool, get_program_account,
+        get_signer_account, get_token_account, get_unchecked_account, read_account, set_time,
+        take_token_cpis, TokenCpi,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::cross_pool_swap::{
+            self, CrossPoolSwap, CrossPoolSwapBumps, CrossPoolSwapParams,
+        },
+        pda,
+        state::{custody::Custody, perpetuals::CrossPoolSwapAmountAndFees},
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+struct Fixture {
+    accounts: &'static [AccountInfo<'static>],
+    funding_account: Pubkey,
+    receiving_account: Pubkey,
+    receiving_custody_token_account: Pubkey,
+    receiving_stable_custody_token_account: Pubkey,
+    dispensing_stable_custody_token_account: Pubkey,
+    dispensing_custody_token_account: Pubkey,
+}
+
+/// Returns the custody, oracle and custody token accounts of a custody.
+fn get_custody_accounts(custody: &Custody, price: u64) -> Vec<AccountInfo<'static>> {
+    let (custody_key, _) = pda::find_custody_address(&custody.pool, &custody.mint);
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    vec![
+        get_program_account(custody_key, custody),
+        get_oracle_account(custody.oracle.oracle_account, price, -6, CURTIME),
+        get_token_account(
+            custody.token_account,
+            custody.mint,
+            transfer_authority_key,
+            custody.assets.owned,
+        ),
+    ]
+}
+
+/// Builds a pool of SOL at $100 and USDC, and a pool of USDC and ETH at $2,000. Each
+/// custody holds $100,000. The owner swaps SOL for ETH, routed through USDC unless the
+/// stable custodies aren't marked stable.
+fn get_fixture(is_stable: bool) -> Fixture {
+    let owner = Pubkey::new_unique();
+    let sol_mint = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let eth_mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (receiving_pool_key, _) = pda::find_pool_address("pool_a");
+    let (dispensing_pool_key, _) = pda::find_pool_address("pool_b");
+
+    let receiving_custody = get_custody(receiving_pool_key, sol_mint, 9, 1_000_000_000_000);
+    let mut receiving_stable_custody =
+        get_custody(receiving_pool_key, usdc_mint, 6, 100_000_000_000);
+    let mut dispensing_stable_custody =
+        get_custody(dispensing_pool_key, usdc_mint, 6, 100_000_000_000);
+    let dispensing_custody = get_custody(dispensing_pool_key, eth_mint, 8, 5_000_000_000);
+    receiving_stable_custody.is_stable = is_stable;
+    dispensing_stable_custody.is_stable = is_stable;
+
+    let receiving_pool = get_pool(
+        "pool_a",
+        vec![
+            pda::find_custody_address(&receiving_pool_key, &sol_mint).0,
+            pda::find_custody_address(&receiving_pool_key, &usdc_mint).0,
+        ],
+    );
+    let dispensing_pool = get_pool(
+        "pool_b",
+        vec![
+            pda::find_custody_address(&dispensing_pool_key, &usdc_mint).0,
+            pda::find_custody_address(&dispensing_pool_key, &eth_mint).0,
+        ],
+    );
+    let funding_account = Pubkey::new_unique();
+    let receiving_account = Pubkey::new_unique();
+
+    let mut accounts = vec![
+        get_signer_account(owner),
+        get_token_account(funding_account, sol_mint, owner, 10_000_000_000),
+        get_token_account(receiving_account, eth_mint, owner, 0),
+        get_unchecked_account(transfer_authority_key, false),
+        get_program_account(perpetuals_key, &get_perpetuals()),
+        get_program_account(receiving_pool_key, &receiving_pool),
+    ];
+    accounts.extend(get_custody_accounts(&receiving_custody, 100_000_000));
+    accounts.extend(get_custody_accounts(&receiving_stable_custody, 1_000_000));
+    accounts.push(get_program_account(dispensing_pool_key, &dispensing_pool));
+    accounts.extend(get_custody_accounts(&dispensing_stable_custody, 1_000_000));
+    accounts.extend(get_custody_accounts(&dispensing_custody, 2_000_000_000));
+    accounts.extend([
+        get_unchecked_account(spl_token::ID, true),
+        // risk_oracle_account is not provided
+        get_unchecked_account(perpetuals::ID, true),
+    ]);
+
+    Fixture {
+        accounts: Box::leak(accounts.into_boxed_slice()),
+        funding_account,
+        receiving_account,
+        receiving_custody_token_account: receiving_custody.token_account,
+        receiving_stable_custody_token_account: receiving_stable_custody.token_account,
+        dispensing_stable_custody_token_account: dispensing_stable_custody.token_account,
+        dispensing_custody_token_account: dispensing_custody.token_account,
+    }
+}
+
+fn cross_pool_swap(
+    fixture: &Fixture,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Result<CrossPoolSwapAmountAndFees> {
+    let params = CrossPoolSwapParams {
+        amount_in,
+        min_amount_out,
+    };
+    let mut infos = fixture.accounts;
+    let mut bumps = CrossPoolSwapBumps::default();
+    let mut accounts = CrossPoolSwap::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    let result = cross_pool_swap::cross_pool_swap(
+        Context::new(&perpetuals::ID, &mut accounts, &[], bumps),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)?;
+    Ok(result)
+}
+
+#[test]
+fn test_cross_pool_swap() {
+    set_time(CURTIME);
+    let fixture = get_fixture(true);
+
+    // 10 SOL are swapped into 1,000 USDC minus the 10 USDC swap out fee, and 990 USDC
+    // into 0.495 ETH minus the 0.00495 ETH swap out fee. The fees are worth $20 in the
+    // first leg and $19.80 in the second
+    assert_eq!(
+        cross_pool_swap(&fixture, 10_000_000_000, 49_005_000).unwrap(),
+        CrossPoolSwapAmountAndFees {
+            amount_out: 49_005_000,
+            stable_amount: 990_000_000,
+            fee_in: 100_000_000,
+            stable_fee: 19_900_000,
+            fee_out: 495_000,
+            fee_usd: 39_800_000,
+        }
+    );
+    assert_eq!(
+        take_token_cpis(),
+        vec![
+            TokenCpi::Transfer {
+                from: fixture.funding_account,
+                to: fixture.receiving_custody_token_account,
+                amount: 10_000_000_000,
+            },
+            TokenCpi::Transfer {
+                from: fixture.receiving_stable_custody_token_account,
+                to: fixture.dispensing_stable_custody_token_account,
+                amount: 990_000_000,
+            },
+            TokenCpi::Transfer {
+                from: fixture.dispensing_custody_token_account,
+                to: fixture.receiving_account,
+                amount: 49_005_000,
+            },
+        ]
+    );
+
+    // the protocol share of the stable fees stays in the custody that charged it
+    let receiving_stable_custody: Custody = read_account(&fixture.accounts[9]);
+    assert_eq!(receiving_stable_custody.assets.protocol_fees, 2_500_000);
+    assert_eq!(receiving_stable_custody.assets.owned, 99_007_500_000);
+    let dispensing_stable_custody: Custody = read_account(&fixture.accounts[13]);
+    assert_eq!(dispensing_stable_custody.assets.protocol_fees, 2_475_000);
+    assert_eq!(dispensing_stable_custody.assets.owned, 100_987_525_000);
+}
+
+#[test]
+fn test_cross_pool_swap_errors() {
+    set_time(CURTIME);
+
+    let fixture = get_fixture(true);
+    assert_eq!(
+        cross_pool_swap(&fixture, 0, 0),
+        Err(ErrorCode::ConstraintRaw.into())
+    );
+    // slippage protection applies to the output of the second leg
+    assert_eq!(
+        cross_pool_swap(&fixture, 10_000_000_000, 49_005_001),
+        Err(PerpetualsError::InsufficientAmountReturned.into())
+    );
+
+    // both pools must route through a stable custody
+    let fixture = get_fixture(false);
+    assert_eq!(
+        cross_pool_swap(&fixture, 10_000_000_000, 0),
+        Err(PerpetualsError::InvalidCustodyState.into())
+    );
+
+    assert!(take_token_cpis().is_empty());
+}
@@ -0,0 +1,161 @@
+//! donate: the donated tokens are transferred into the custody, cover its bad debt
+//! first and the rest is credited to the owned assets.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::token::spl_token,
+    common::{
+        get_custody, get_perpetuals, get_pool, get_program_account, get_signer_account,
+        get_token_account, get_unchecked_account, read_account, set_time, take_token_cpis,
+        TokenCpi,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::donate::{self, Donate, DonateBumps, DonateParams},
+        pda,
+        state::custody::Custody,
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+struct Fixture {
+    accounts: &'static [AccountInfo<'static>],
+    funding_account: Pubkey,
+    custody_token_account: Pubkey,
+}
+
+/// Builds a custody owning 1,000 tokens with 50 tokens of bad debt. The donor funds the
+/// donation from a token account of `funding_mint`, owned by `funding_owner` unless it
+/// is the donor.
+fn get_fixture(
+    funding_mint: Option<Pubkey>,
+    funding_owner: Option<Pubkey>,
+    is_virtual: bool,
+) -> Fixture {
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (pool_key, _) = pda::find_pool_address("pool");
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &mint);
+
+    let mut custody = get_custody(pool_key, mint, 6, 1_000_000_000);
+    custody.assets.bad_debt = 50_000_000;
+    custody.is_virtual = is_virtual;
+    let funding_account = Pubkey::new_unique();
+
+    let accounts = vec![
+        get_signer_account(owner),
+        get_token_account(
+            funding_account,
+            funding_mint.unwrap_or(mint),
+            funding_owner.unwrap_or(owner),
+            100_000_000,
+        ),
+        get_program_account(perpetuals_key, &get_perpetuals()),
+        get_program_account(pool_key, &get_pool("pool", vec![custody_key])),
+        get_program_account(custody_key, &custody),
+        get_token_account(
+            custody.token_account,
+            mint,
+            transfer_authority_key,
+            950_000_000,
+        ),
+        get_unchecked_account(spl_token::ID, true),
+    ];
+
+    Fixture {
+        accounts: Box::leak(accounts.into_boxed_slice()),
+        funding_account,
+        custody_token_account: custody.token_account,
+    }
+}
+
+fn donate(fixture: &Fixture, amount: u64) -> Result<()> {
+    let params = DonateParams { amount };
+    let mut infos = fixture.accounts;
+    let mut bumps = DonateBumps::default();
+    let mut accounts = Donate::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    donate::donate(
+        Context::new(&perpetuals::ID, &mut accounts, &[], bumps),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)
+}
+
+#[test]
+fn test_donate() {
+    set_time(CURTIME);
+    let fixture = get_fixture(None, None, false);
+
+    // 30 tokens only cover part of the bad debt
+    donate(&fixture, 30_000_000).unwrap();
+    assert_eq!(
+        take_token_cpis(),
+        vec![TokenCpi::Transfer {
+            from: fixture.funding_account,
+            to: fixture.custody_token_account,
+            amount: 30_000_000,
+        }]
+    );
+    let custody: Custody = read_account(&fixture.accounts[4]);
+    assert_eq!(custody.assets.bad_debt, 20_000_000);
+    assert_eq!(custody.assets.owned, 1_000_000_000);
+
+    // 50 more tokens cover the remaining 20 tokens of bad debt, the rest is owned
+    donate(&fixture, 50_000_000).unwrap();
+    assert_eq!(
+        take_token_cpis(),
+        vec![TokenCpi::Transfer {
+            from: fixture.funding_account,
+            to: fixture.custody_token_account,
+            amount: 50_000_000,
+        }]
+    );
+    let custody: Custody = read_account(&fixture.accounts[4]);
+    assert_eq!(custody.assets.bad_debt, 0);
+    assert_eq!(custody.assets.owned, 1_030_000_000);
+    assert_eq!(custody.assets.protocol_fees, 0);
+}
+
+#[test]
+fn test_donate_errors() {
+    set_time(CURTIME);
+
+    let fixture = get_fixture(None, None, false);
+    assert_eq!(donate(&fixture, 0), Err(ErrorCode::ConstraintRaw.into()));
+
+    // virtual custodies don't hold tokens
+    let fixture = get_fixture(None, None, true);
+    assert_eq!(
+        donate(&fixture, 30_000_000),
+        Err(PerpetualsError::InstructionNotAllowed.into())
+    );
+
+    // the funding account must hold the custody mint for the donor
+    let fixture = get_fixture(Some(Pubkey::new_unique()), None, false);
+    assert_eq!(
+        donate(&fixture, 30_000_000),
+        Err(ErrorCode::ConstraintRaw.into())
+    );
+    let fixture = get_fixture(None, Some(Pubkey::new_unique()), false);
+    assert_eq!(
+        donate(&fixture, 30_000_000),
+        Err(ErrorCode::ConstraintHasOne.into())
+    );
+
+    assert!(take_token_cpis().is_empty());
+    let custody: Custody = read_account(&fixture.accounts[4]);
+    assert_eq!(custody.assets.bad_debt, 50_000_000);
+    assert_eq!(custody.assets.owned, 1_000_000_000);
+}
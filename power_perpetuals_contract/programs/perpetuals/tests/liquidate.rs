@@ -0,0 +1,241 @@
+//! liquidate: the remaining collateral is paid to the owner ATA, which the liquidator
+//! creates when it doesn't exist yet, and the reward to the liquidator.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::{
+        associated_token::{self, get_associated_token_address},
+        token::{spl_token, TokenAccount},
+    },
+    common::{
+        get_account_info, get_custody, get_mint_account, get_oracle_account, get_perpetuals,
+        get_pool, get_program_account, get_signer_account, get_token_account,
+        get_unchecked_account, read_account, set_time, take_created_accounts, take_token_cpis,
+        TokenCpi,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::liquidate::{self, Liquidate, LiquidateBumps, LiquidateParams},
+        pda,
+        state::{
+            custody::Custody,
+            position::{Position, Side},
+        },
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+struct Fixture {
+    accounts: &'static [AccountInfo<'static>],
+    rewards_receiving_account: Pubkey,
+    collateral_custody_token_account: Pubkey,
+}
+
+/// Builds a $1,000 short SOL position opened at $100 with 100 USDC of collateral, SOL
+/// is priced at $105. The custody liquidates above 10x leverage with a 1% fee. The
+/// remaining collateral is paid to `receiving_account`, the accounts to create it are
+/// only provided with `create_accounts`.
+fn get_fixture(
+    owner: Pubkey,
+    usdc_mint: Pubkey,
+    receiving_account: AccountInfo<'static>,
+    create_accounts: bool,
+) -> Fixture {
+    let liquidator = Pubkey::new_unique();
+    let sol_mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (pool_key, _) = pda::find_pool_address("pool");
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &sol_mint);
+    let (collateral_custody_key, _) = pda::find_custody_address(&pool_key, &usdc_mint);
+    let (position_key, position_bump) =
+        pda::find_position_address(&owner, &pool_key, &custody_key, Side::Short);
+
+    let pool = get_pool("pool", vec![custody_key, collateral_custody_key]);
+    let mut custody = get_custody(pool_key, sol_mint, 9, 1_000_000_000_000);
+    custody.pricing.max_leverage = 100_000;
+    custody.fees.liquidation = 100;
+    custody.short_positions.open_positions = 1;
+    let mut collateral_custody = get_custody(pool_key, usdc_mint, 6, 100_000_000_000);
+    collateral_custody.is_stable = true;
+    collateral_custody.assets.collateral = 100_000_000;
+    collateral_custody.assets.locked = 1_000_000_000;
+    let position = Position {
+        owner,
+        pool: pool_key,
+        custody: custody_key,
+        collateral_custody: collateral_custody_key,
+        open_time: CURTIME - 3_600,
+        update_time: CURTIME - 3_600,
+        side: Side::Short,
+        power: 1,
+        price: 100_000_000,
+        size_usd: 1_000_000_000,
+        collateral_usd: 100_000_000,
+        locked_amount: 1_000_000_000,
+        collateral_amount: 100_000_000,
+        bump: position_bump,
+        ..Default::default()
+    };
+    let optional_account = |account: AccountInfo<'static>| {
+        if create_accounts {
+            account
+        } else {
+            get_unchecked_account(perpetuals::ID, true)
+        }
+    };
+    let rewards_receiving_account = Pubkey::new_unique();
+
+    let accounts = vec![
+        get_signer_account(liquidator),
+        receiving_account,
+        get_token_account(rewards_receiving_account, usdc_mint, liquidator, 0),
+        get_unchecked_account(transfer_authority_key, false),
+        get_program_account(perpetuals_key, &get_perpetuals()),
+        get_program_account(pool_key, &pool),
+        get_program_account(position_key, &position),
+        get_program_account(custody_key, &custody),
+        get_oracle_account(custody.oracle.oracle_account, 105_000_000, -6, CURTIME),
+        get_program_account(collateral_custody_key, &collateral_custody),
+        get_oracle_account(
+            collateral_custody.oracle.oracle_account,
+            1_000_000,
+            -6,
+            CURTIME,
+        ),
+        get_token_account(
+            collateral_custody.token_account,
+            usdc_mint,
+            transfer_authority_key,
+            100_100_000_000,
+        ),
+        get_unchecked_account(spl_token::ID, true),
+        // owner_positions, position_summary, hook_program and hook_authority are not
+        // provided
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+        optional_account(get_unchecked_account(owner, false)),
+        optional_account(get_mint_account(usdc_mint, Pubkey::new_unique(), 0, 6)),
+        optional_account(get_unchecked_account(System::id(), true)),
+        optional_account(get_unchecked_account(associated_token::ID, true)),
+    ];
+
+    Fixture {
+        accounts: Box::leak(accounts.into_boxed_slice()),
+        rewards_receiving_account,
+        collateral_custody_token_account: collateral_custody.token_account,
+    }
+}
+
+fn liquidate(fixture: &Fixture) -> Result<()> {
+    let params = LiquidateParams {};
+    let mut infos = fixture.accounts;
+    let mut bumps = LiquidateBumps::default();
+    let mut accounts = Liquidate::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    liquidate::liquidate(
+        Context::new(&perpetuals::ID, &mut accounts, &[], bumps),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)
+}
+
+fn get_empty_account(key: Pubkey) -> AccountInfo<'static> {
+    get_account_info(key, Pubkey::default(), false, true, false, vec![])
+}
+
+#[test]
+fn test_liquidate_creates_receiving_account() {
+    set_time(CURTIME);
+    let owner = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let owner_ata = get_associated_token_address(&owner, &usdc_mint);
+    let fixture = get_fixture(owner, usdc_mint, get_empty_account(owner_ata), true);
+
+    // the short lost $47.62 and pays the $10 liquidation fee, the liquidator receives
+    // 1% of the remaining 42.38 USDC
+    liquidate(&fixture).unwrap();
+
+    assert_eq!(take_created_accounts(), vec![owner_ata]);
+    assert_eq!(
+        take_token_cpis(),
+        vec![
+            TokenCpi::Transfer {
+                from: fixture.collateral_custody_token_account,
+                to: owner_ata,
+                amount: 41_956_199,
+            },
+            TokenCpi::Transfer {
+                from: fixture.collateral_custody_token_account,
+                to: fixture.rewards_receiving_account,
+                amount: 423_800,
+            },
+        ]
+    );
+    let receiving_account: TokenAccount = read_account(&fixture.accounts[1]);
+    assert_eq!(
+        (receiving_account.owner, receiving_account.mint),
+        (owner, usdc_mint)
+    );
+
+    let collateral_custody: Custody = read_account(&fixture.accounts[9]);
+    assert_eq!(collateral_custody.assets.collateral, 0);
+    assert_eq!(collateral_custody.assets.locked, 0);
+    // the pool keeps the 57.62 USDC lost by the position but the protocol share of the
+    // fee
+    assert_eq!(collateral_custody.assets.protocol_fees, 2_500_001);
+    assert_eq!(collateral_custody.assets.owned, 100_055_120_000);
+}
+
+#[test]
+fn test_liquidate_receiving_account_errors() {
+    set_time(CURTIME);
+    let owner = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let owner_ata = get_associated_token_address(&owner, &usdc_mint);
+
+    // a missing owner ATA can't be created without the owner, mint and programs
+    let fixture = get_fixture(owner, usdc_mint, get_empty_account(owner_ata), false);
+    assert_eq!(
+        liquidate(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+
+    // the remainder can only be paid to the owner ATA
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_token_account(Pubkey::new_unique(), usdc_mint, owner, 0),
+        true,
+    );
+    assert_eq!(
+        liquidate(&fixture),
+        Err(PerpetualsError::InvalidPayoutAccount.into())
+    );
+
+    // accounts not owned by the token program are rejected
+    let fixture = get_fixture(
+        owner,
+        usdc_mint,
+        get_account_info(owner_ata, perpetuals::ID, false, true, false, vec![0; 165]),
+        true,
+    );
+    assert_eq!(
+        liquidate(&fixture),
+        Err(PerpetualsError::InvalidReceivingAccount.into())
+    );
+
+    assert!(take_created_accounts().is_empty());
+    assert!(take_token_cpis().is_empty());
+}
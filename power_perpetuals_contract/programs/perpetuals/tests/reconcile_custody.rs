@@ -0,0 +1,174 @@
+//! reconcile_custody: the custody bookkeeping is reconciled with the custody token
+//! account balance once the admins signed, without moving tokens.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    common::{
+        get_custody, get_multisig_account, get_perpetuals, get_pool, get_program_account,
+        get_signer_account, get_token_account, read_account, set_time, take_instructions,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::reconcile_custody::{
+            self, ReconcileCustody, ReconcileCustodyBumps, ReconcileCustodyParams,
+        },
+        pda,
+        state::custody::Custody,
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+/// Builds a custody owning 1,000 tokens, holding 100 collateral tokens and 10 protocol
+/// fee tokens, with 5 tokens of bad debt. Its token account holds `balance` tokens.
+/// Returns the accounts signed by the first of the multisig `admins`.
+fn get_accounts(
+    admins: &[Pubkey],
+    min_signatures: u8,
+    balance: u64,
+    is_virtual: bool,
+) -> &'static [AccountInfo<'static>] {
+    let mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (pool_key, _) = pda::find_pool_address("pool");
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &mint);
+
+    let mut custody = get_custody(pool_key, mint, 6, 1_000_000_000);
+    custody.assets.collateral = 100_000_000;
+    custody.assets.protocol_fees = 10_000_000;
+    custody.assets.bad_debt = 5_000_000;
+    custody.is_virtual = is_virtual;
+
+    let accounts = vec![
+        get_signer_account(admins[0]),
+        get_multisig_account(admins, min_signatures),
+        get_program_account(perpetuals_key, &get_perpetuals()),
+        get_program_account(pool_key, &get_pool("pool", vec![custody_key])),
+        get_program_account(custody_key, &custody),
+        get_token_account(custody.token_account, mint, transfer_authority_key, balance),
+    ];
+    Box::leak(accounts.into_boxed_slice())
+}
+
+/// Returns the accounts signed by `admin` instead
+fn sign_with(
+    accounts: &'static [AccountInfo<'static>],
+    admin: Pubkey,
+) -> &'static [AccountInfo<'static>] {
+    let mut accounts = accounts.to_vec();
+    accounts[0] = get_signer_account(admin);
+    Box::leak(accounts.into_boxed_slice())
+}
+
+fn reconcile_custody(
+    accounts: &'static [AccountInfo<'static>],
+    surplus_to_owned: bool,
+) -> Result<u8> {
+    let params = ReconcileCustodyParams { surplus_to_owned };
+    let mut infos = accounts;
+    let mut bumps = ReconcileCustodyBumps::default();
+    let mut accounts = ReconcileCustody::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    let signatures_left = reconcile_custody::reconcile_custody(
+        Context::new(&perpetuals::ID, &mut accounts, &[], bumps),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)?;
+    Ok(signatures_left)
+}
+
+#[test]
+fn test_reconcile_custody_surplus() {
+    set_time(CURTIME);
+    let admins = [Pubkey::new_unique()];
+
+    // 50 tokens more than booked go to protocol fees and the bad debt is cleared
+    let accounts = get_accounts(&admins, 1, 1_160_000_000, false);
+    assert_eq!(reconcile_custody(accounts, false), Ok(0));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.owned, 1_000_000_000);
+    assert_eq!(custody.assets.collateral, 100_000_000);
+    assert_eq!(custody.assets.protocol_fees, 60_000_000);
+    assert_eq!(custody.assets.bad_debt, 0);
+
+    // or to the liquidity providers
+    let accounts = get_accounts(&admins, 1, 1_160_000_000, false);
+    assert_eq!(reconcile_custody(accounts, true), Ok(0));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.owned, 1_050_000_000);
+    assert_eq!(custody.assets.protocol_fees, 10_000_000);
+    assert_eq!(custody.assets.bad_debt, 0);
+
+    assert!(take_instructions().is_empty());
+}
+
+#[test]
+fn test_reconcile_custody_deficit() {
+    set_time(CURTIME);
+    let admins = [Pubkey::new_unique()];
+
+    // 10 tokens missing from the token account are recorded as bad debt, the bookkeeping
+    // is left as is
+    let accounts = get_accounts(&admins, 1, 1_100_000_000, false);
+    assert_eq!(reconcile_custody(accounts, true), Ok(0));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.owned, 1_000_000_000);
+    assert_eq!(custody.assets.collateral, 100_000_000);
+    assert_eq!(custody.assets.protocol_fees, 10_000_000);
+    assert_eq!(custody.assets.bad_debt, 10_000_000);
+
+    // a balanced custody has no bad debt
+    let accounts = get_accounts(&admins, 1, 1_110_000_000, false);
+    assert_eq!(reconcile_custody(accounts, false), Ok(0));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.protocol_fees, 10_000_000);
+    assert_eq!(custody.assets.bad_debt, 0);
+}
+
+#[test]
+fn test_reconcile_custody_errors() {
+    set_time(CURTIME);
+    let admins = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    // virtual custodies don't hold tokens
+    let accounts = get_accounts(&admins, 1, 1_160_000_000, true);
+    assert_eq!(
+        reconcile_custody(accounts, false),
+        Err(PerpetualsError::InstructionNotAllowed.into())
+    );
+
+    // only admins of the multisig can sign
+    let accounts = get_accounts(&admins, 1, 1_160_000_000, false);
+    assert_eq!(
+        reconcile_custody(sign_with(accounts, Pubkey::new_unique()), false),
+        Err(PerpetualsError::MultisigAccountNotAuthorized.into())
+    );
+
+    // the custody is reconciled once enough admins signed
+    let accounts = get_accounts(&admins, 2, 1_160_000_000, false);
+    assert_eq!(reconcile_custody(accounts, false), Ok(1));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.protocol_fees, 10_000_000);
+    assert_eq!(custody.assets.bad_debt, 5_000_000);
+
+    let second_accounts = sign_with(accounts, admins[1]);
+    assert_eq!(reconcile_custody(second_accounts, false), Ok(0));
+    let custody: Custody = read_account(&accounts[4]);
+    assert_eq!(custody.assets.protocol_fees, 60_000_000);
+    assert_eq!(custody.assets.bad_debt, 0);
+
+    // and can't be replayed
+    assert_eq!(
+        reconcile_custody(second_accounts, false),
+        Err(PerpetualsError::MultisigAlreadyExecuted.into())
+    );
+}
@@ -0,0 +1,216 @@
+//! remove_liquidity_and_swap: the withdrawn custody keeps the swap input and only loses
+//! the protocol share of the fees, the dispensing custody pays the swap output, and the
+//! pool AUM is refreshed from the updated custodies.
+
+mod common;
+
+use {
+    anchor_lang::{prelude::*, Accounts},
+    anchor_spl::token::spl_token,
+    common::{
+        get_custody, get_mint_account, get_oracle_account, get_perpetuals, get_pool,
+        get_program_account, get_signer_account, get_token_account, get_unchecked_account,
+        read_account, set_time, take_token_cpis, TokenCpi,
+    },
+    perpetuals::{
+        error::PerpetualsError,
+        instructions::remove_liquidity_and_swap::{
+            self, RemoveLiquidityAndSwap, RemoveLiquidityAndSwapBumps, RemoveLiquidityAndSwapParams,
+        },
+        pda,
+        state::{
+            custody::Custody,
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    std::collections::BTreeSet,
+};
+
+const CURTIME: i64 = 1_700_000_000;
+
+struct Fixture {
+    accounts: &'static [AccountInfo<'static>],
+    remaining_accounts: &'static [AccountInfo<'static>],
+    receiving_account: Pubkey,
+    lp_token_account: Pubkey,
+    dispensing_custody_token_account: Pubkey,
+}
+
+/// Builds a pool of 1,000 SOL at $100 and 100,000 USDC at $1, with 200,000 LP tokens
+/// worth $1 each. The owner withdraws SOL and receives USDC.
+fn get_fixture(dispensing_locked: u64) -> Fixture {
+    let owner = Pubkey::new_unique();
+    let sol_mint = Pubkey::new_unique();
+    let usdc_mint = Pubkey::new_unique();
+    let (perpetuals_key, _) = pda::find_perpetuals_address();
+    let (transfer_authority_key, _) = pda::find_transfer_authority_address();
+    let (pool_key, _) = pda::find_pool_address("pool");
+    let (lp_token_mint_key, _) = pda::find_lp_token_mint_address(&pool_key);
+    let (custody_key, _) = pda::find_custody_address(&pool_key, &sol_mint);
+    let (dispensing_custody_key, _) = pda::find_custody_address(&pool_key, &usdc_mint);
+
+    let perpetuals = get_perpetuals();
+    let pool = get_pool("pool", vec![custody_key, dispensing_custody_key]);
+    let custody = get_custody(pool_key, sol_mint, 9, 1_000_000_000_000);
+    let mut dispensing_custody = get_custody(pool_key, usdc_mint, 6, 100_000_000_000);
+    dispensing_custody.is_stable = true;
+    dispensing_custody.assets.locked = dispensing_locked;
+
+    let custody_account = get_program_account(custody_key, &custody);
+    let custody_oracle_account =
+        get_oracle_account(custody.oracle.oracle_account, 100_000_000, -6, CURTIME);
+    let dispensing_custody_account =
+        get_program_account(dispensing_custody_key, &dispensing_custody);
+    let dispensing_custody_oracle_account = get_oracle_account(
+        dispensing_custody.oracle.oracle_account,
+        1_000_000,
+        -6,
+        CURTIME,
+    );
+    let receiving_account = Pubkey::new_unique();
+    let lp_token_account = Pubkey::new_unique();
+
+    let accounts = vec![
+        get_signer_account(owner),
+        get_token_account(receiving_account, usdc_mint, owner, 0),
+        get_token_account(lp_token_account, lp_token_mint_key, owner, 10_000_000_000),
+        get_unchecked_account(transfer_authority_key, false),
+        get_program_account(perpetuals_key, &perpetuals),
+        get_program_account(pool_key, &pool),
+        custody_account.clone(),
+        custody_oracle_account.clone(),
+        dispensing_custody_account.clone(),
+        dispensing_custody_oracle_account.clone(),
+        get_token_account(
+            dispensing_custody.token_account,
+            usdc_mint,
+            transfer_authority_key,
+            100_000_000_000,
+        ),
+        get_mint_account(
+            lp_token_mint_key,
+            transfer_authority_key,
+            200_000_000_000,
+            Perpetuals::LP_DECIMALS,
+        ),
+        get_unchecked_account(System::id(), true),
+        get_unchecked_account(spl_token::ID, true),
+        // lp_allowlist and lp_ledger are not provided
+        get_unchecked_account(perpetuals::ID, true),
+        get_unchecked_account(perpetuals::ID, true),
+    ];
+    let remaining_accounts = vec![
+        custody_account,
+        dispensing_custody_account,
+        custody_oracle_account,
+        dispensing_custody_oracle_account,
+    ];
+
+    Fixture {
+        accounts: Box::leak(accounts.into_boxed_slice()),
+        remaining_accounts: Box::leak(remaining_accounts.into_boxed_slice()),
+        receiving_account,
+        lp_token_account,
+        dispensing_custody_token_account: dispensing_custody.token_account,
+    }
+}
+
+fn remove_liquidity_and_swap(fixture: &Fixture, min_amount_out: u64) -> Result<()> {
+    let params = RemoveLiquidityAndSwapParams {
+        lp_amount_in: 10_000_000_000,
+        min_amount_out,
+        max_fee_bps: 0,
+    };
+    let mut infos = fixture.accounts;
+    let mut bumps = RemoveLiquidityAndSwapBumps::default();
+    let mut accounts = RemoveLiquidityAndSwap::try_accounts(
+        &perpetuals::ID,
+        &mut infos,
+        &params.try_to_vec().unwrap(),
+        &mut bumps,
+        &mut BTreeSet::new(),
+    )?;
+    remove_liquidity_and_swap::remove_liquidity_and_swap(
+        Context::new(
+            &perpetuals::ID,
+            &mut accounts,
+            fixture.remaining_accounts,
+            bumps,
+        ),
+        &params,
+    )?;
+    accounts.exit(&perpetuals::ID)
+}
+
+#[test]
+fn test_remove_liquidity_and_swap() {
+    set_time(CURTIME);
+    let fixture = get_fixture(0);
+
+    // 10,000 LP tokens redeem 100 SOL, the 1 SOL remove fee is kept. The remaining 99 SOL
+    // are swapped into 9,900 USDC minus the 99 USDC swap out fee
+    remove_liquidity_and_swap(&fixture, 9_801_000_000).unwrap();
+
+    assert_eq!(
+        take_token_cpis(),
+        vec![
+            TokenCpi::Transfer {
+                from: fixture.dispensing_custody_token_account,
+                to: fixture.receiving_account,
+                amount: 9_801_000_000,
+            },
+            TokenCpi::Burn {
+                account: fixture.lp_token_account,
+                amount: 10_000_000_000,
+            },
+        ]
+    );
+
+    // the withdrawn custody only loses the protocol share of the remove and swap in
+    // fees: 25% of 1 SOL and 0.99 SOL
+    let custody: Custody = read_account(&fixture.accounts[6]);
+    assert_eq!(custody.assets.protocol_fees, 497_500_000);
+    assert_eq!(custody.assets.owned, 999_502_500_000);
+
+    // the dispensing custody pays the swap output and the protocol share of the swap out
+    // fee
+    let dispensing_custody: Custody = read_account(&fixture.accounts[8]);
+    assert_eq!(dispensing_custody.assets.protocol_fees, 24_750_000);
+    assert_eq!(dispensing_custody.assets.owned, 90_174_250_000);
+
+    // 999.5025 SOL at $100 and 90,174.25 USDC
+    let pool: Pool = read_account(&fixture.accounts[5]);
+    assert_eq!(pool.aum_usd, 190_124_500_000);
+    assert_eq!(
+        pool.aum_usd,
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, fixture.remaining_accounts, CURTIME)
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_remove_liquidity_and_swap_limits() {
+    set_time(CURTIME);
+
+    // slippage protection applies to the final amount
+    let fixture = get_fixture(0);
+    assert_eq!(
+        remove_liquidity_and_swap(&fixture, 9_801_000_001),
+        Err(PerpetualsError::InsufficientAmountReturned.into())
+    );
+
+    // the swap output and its protocol fee must be available in the dispensing custody
+    let fixture = get_fixture(100_000_000_000 - 9_825_749_999);
+    assert_eq!(
+        remove_liquidity_and_swap(&fixture, 0),
+        Err(PerpetualsError::CustodyAmountLimit.into())
+    );
+    assert!(take_token_cpis().is_empty());
+
+    let fixture = get_fixture(100_000_000_000 - 9_825_750_000);
+    assert!(remove_liquidity_and_swap(&fixture, 0).is_ok());
+
+    let custody: Custody = read_account(&fixture.accounts[8]);
+    assert_eq!(custody.assets.owned, custody.assets.locked);
+}